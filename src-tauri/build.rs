@@ -1,3 +1,69 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Written to `OUT_DIR` and compiled into the binary by `resource_integrity`,
+/// which checks the bundled api tree against it.
+const MANIFEST: &str = "resource-manifest.json";
+const SIDECAR_FILES: [&str; 2] = ["sidecar/local-api-server.mjs", "sidecar/package.json"];
+
+fn hash_file(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    Some(Sha256::digest(&bytes).iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Every bundled file under `api/`, keyed by its path from the resource root.
+fn hash_tree(dir: &Path, prefix: &str, files: &mut BTreeMap<String, String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if path.is_dir() {
+            if name != "node_modules" {
+                hash_tree(&path, &format!("{prefix}{name}/"), files);
+            }
+        } else if !name.contains(".test.") {
+            if let Some(hash) = hash_file(&path) {
+                files.insert(format!("{prefix}{name}"), hash);
+            }
+        }
+    }
+}
+
+fn write_resource_manifest() {
+    let mut files = BTreeMap::new();
+    for file in SIDECAR_FILES {
+        println!("cargo:rerun-if-changed={file}");
+        if let Some(hash) = hash_file(Path::new(file)) {
+            files.insert(file.to_string(), hash);
+        }
+    }
+    println!("cargo:rerun-if-changed=../api");
+    hash_tree(Path::new("../api"), "api/", &mut files);
+    let manifest = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "files": files,
+    });
+    let Some(out_dir) = env::var_os("OUT_DIR").map(PathBuf::from) else {
+        println!("cargo:warning=OUT_DIR is not set; the resource manifest was not written");
+        return;
+    };
+    let path = out_dir.join(MANIFEST);
+    let text = manifest.to_string();
+    // Rewritten only on change, so the crate isn't rebuilt for nothing.
+    if fs::read_to_string(&path).ok().as_deref() != Some(text.as_str()) {
+        if let Err(err) = fs::write(&path, text) {
+            println!("cargo:warning=Could not write {}: {err}", path.display());
+        }
+    }
+}
+
 fn main() {
+    write_resource_manifest();
     tauri_build::build()
 }
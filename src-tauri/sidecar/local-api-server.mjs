@@ -9,6 +9,7 @@ import { brotliCompress, gzipSync } from 'node:zlib';
 import path from 'node:path';
 import { pathToFileURL } from 'node:url';
 import crypto from 'node:crypto';
+import net from 'node:net';
 
 const brotliCompressAsync = promisify(brotliCompress);
 
@@ -19,6 +20,17 @@ const brotliCompressAsync = promisify(brotliCompress);
 // fetch() calls in dynamically-loaded handler modules (api/*.js) use IPv4.
 const _originalFetch = globalThis.fetch;
 
+// The desktop app's UA (`LOCAL_API_USER_AGENT`), sent upstream by requests
+// that don't set their own. Handlers that need a browser UA keep theirs.
+let appUserAgent = '';
+
+/** `headers` plus the app UA, unless a User-Agent is already set. */
+export function withAppUserAgent(headers) {
+  if (!appUserAgent) return headers;
+  if (Object.keys(headers).some((name) => name.toLowerCase() === 'user-agent')) return headers;
+  return { ...headers, 'User-Agent': appUserAgent };
+}
+
 function normalizeRequestBody(body) {
   if (body == null) return null;
   if (typeof body === 'string' || Buffer.isBuffer(body) || body instanceof Uint8Array) return body;
@@ -60,11 +72,60 @@ function isTransientVerificationError(error) {
   return /timed out|timeout|network|fetch failed|failed to fetch|socket hang up/i.test(error.message);
 }
 
+// Upstream hosts per data source (ids as in DATA_SOURCE_KEYS), matched on
+// the host or a parent domain. Calls to them are counted since start and
+// polled by the desktop shell on /api/local-api-usage for daily quotas.
+const PROVIDER_HOSTS = {
+  'acleddata.com': 'acled',
+  'api.abuseipdb.com': 'abuseipdb',
+  'firms.modaps.eosdis.nasa.gov': 'nasa-firms',
+  'api.stlouisfed.org': 'fred',
+  'api.eia.gov': 'eia',
+  'finnhub.io': 'finnhub',
+  'otx.alienvault.com': 'otx',
+  'urlhaus-api.abuse.ch': 'urlhaus',
+  'api.cloudflare.com': 'cloudflare-radar',
+  'api.groq.com': 'groq',
+  'openrouter.ai': 'openrouter',
+  'customer-api.wingbits.com': 'wingbits',
+  'ucdpapi.pcr.uu.se': 'ucdp',
+  'worldmonitor.app': 'worldmonitor',
+  'wto.org': 'wto',
+  'api.aviationstack.com': 'aviationstack',
+  'dataservices.icao.int': 'icao',
+  'api.portcast.io': 'portcast',
+  'gateway.api.globalfishingwatch.org': 'global-fishing-watch',
+  'api.waqi.info': 'waqi',
+  'data-api.globalforestwatch.org': 'global-forest-watch',
+  'liveuamap.com': 'liveuamap',
+  'api.whale-alert.io': 'whale-alert',
+  'api.github.com': 'github',
+  'opensky-network.org': 'opensky',
+};
+const apiUsage = { since: Date.now(), calls: new Map() };
+
+export function providerOf(hostname) {
+  for (let host = hostname.toLowerCase(); host.includes('.'); host = host.slice(host.indexOf('.') + 1)) {
+    if (PROVIDER_HOSTS[host]) return PROVIDER_HOSTS[host];
+  }
+  return null;
+}
+
+function countUpstreamCall(hostname) {
+  const provider = providerOf(hostname);
+  if (provider) apiUsage.calls.set(provider, (apiUsage.calls.get(provider) || 0) + 1);
+}
+
+export function getApiUsage() {
+  return { since: apiUsage.since, calls: Object.fromEntries(apiUsage.calls) };
+}
+
 globalThis.fetch = async function ipv4Fetch(input, init) {
   const isRequest = input && typeof input === 'object' && 'url' in input;
   let url;
   try { url = new URL(typeof input === 'string' ? input : input.url); } catch { return _originalFetch(input, init); }
   if (url.protocol !== 'https:' && url.protocol !== 'http:') return _originalFetch(input, init);
+  countUpstreamCall(url.hostname);
   const mod = url.protocol === 'https:' ? https : http;
   const method = init?.method || (isRequest ? input.method : 'GET');
   const body = await resolveRequestBody(input, init, method, isRequest);
@@ -76,7 +137,7 @@ globalThis.fetch = async function ipv4Fetch(input, init) {
     Object.assign(headers, h);
   }
   return new Promise((resolve, reject) => {
-    const req = mod.request({ hostname: url.hostname, port: url.port || (url.protocol === 'https:' ? 443 : 80), path: url.pathname + url.search, method, headers, family: 4 }, (res) => {
+    const req = mod.request({ hostname: url.hostname, port: url.port || (url.protocol === 'https:' ? 443 : 80), path: url.pathname + url.search, method, headers: withAppUserAgent(headers), family: 4 }, (res) => {
       const chunks = [];
       res.on('data', (c) => chunks.push(c));
       res.on('end', () => {
@@ -117,6 +178,43 @@ const ALLOWED_ENV_KEYS = new Set([
   'AIRFRAMES_API_KEY', 'GITHUB_TOKEN',
 ]);
 
+// Vault keys per data source; mirrors DATA_SOURCES in src/data_sources.rs.
+// A disabled source's keys are withheld from process.env so its handlers
+// behave as if unconfigured and stop polling upstream.
+const DATA_SOURCE_KEYS = {
+  'groq': ['GROQ_API_KEY'],
+  'openrouter': ['OPENROUTER_API_KEY'],
+  'fred': ['FRED_API_KEY'],
+  'eia': ['EIA_API_KEY'],
+  'cloudflare-radar': ['CLOUDFLARE_API_TOKEN'],
+  'acled': ['ACLED_ACCESS_TOKEN'],
+  'urlhaus': ['URLHAUS_AUTH_KEY'],
+  'otx': ['OTX_API_KEY'],
+  'abuseipdb': ['ABUSEIPDB_API_KEY'],
+  'wingbits': ['WINGBITS_API_KEY'],
+  'ws-relay': ['WS_RELAY_URL', 'VITE_WS_RELAY_URL'],
+  'opensky': ['VITE_OPENSKY_RELAY_URL', 'OPENSKY_CLIENT_ID', 'OPENSKY_CLIENT_SECRET'],
+  'aisstream': ['AISSTREAM_API_KEY'],
+  'finnhub': ['FINNHUB_API_KEY'],
+  'nasa-firms': ['NASA_FIRMS_API_KEY'],
+  'ucdp': ['UCDP_ACCESS_TOKEN'],
+  'ollama': ['OLLAMA_API_URL', 'OLLAMA_MODEL'],
+  'worldmonitor': ['WORLDMONITOR_API_KEY'],
+  'wto': ['WTO_API_KEY'],
+  'aviationstack': ['AVIATIONSTACK_API'],
+  'icao': ['ICAO_API_KEY'],
+  'portcast': ['PORTCAST_API_KEY'],
+  'global-fishing-watch': ['GLOBAL_FISHING_WATCH_API_KEY'],
+  'electricity-maps': ['ELECTRICITY_MAPS_API_KEY'],
+  'sentinel-hub': ['SENTINEL_HUB_CLIENT_ID', 'SENTINEL_HUB_CLIENT_SECRET'],
+  'waqi': ['WAQI_API_TOKEN'],
+  'global-forest-watch': ['GLOBAL_FOREST_WATCH_API_KEY'],
+  'liveuamap': ['LIVEUAMAP_API_KEY'],
+  'whale-alert': ['WHALE_ALERT_API_KEY'],
+  'airframes': ['AIRFRAMES_API_KEY'],
+  'github': ['GITHUB_TOKEN'],
+};
+
 const CHROME_UA = 'Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36';
 
 // ── RSS allowed domains (mirrors api/rss-proxy.js) ──────────────────────
@@ -222,6 +320,69 @@ function isPrivateIP(ip) {
   return false;
 }
 
+// ── Trusted local hosts ──────────────────────────────────────────────────
+// LAN services the user has vouched for (`trustedLocalHosts` in the desktop
+// prefs) as host:port patterns: 192.168.1.20:11434, 192.168.1.*:8080,
+// [fd12::7]:3004, nas.local:*. The desktop shell validates and normalizes
+// them; here they let isSafeUrl through and let relay and Ollama URLs on
+// the LAN be verified. Anything else on the LAN stays blocked.
+
+const LAN_NAME_SUFFIXES = ['.local', '.lan', '.home.arpa', '.internal'];
+const LAN_URL_KEYS = new Set(['WS_RELAY_URL', 'VITE_WS_RELAY_URL', 'VITE_OPENSKY_RELAY_URL', 'OLLAMA_API_URL']);
+let trustedLocalHosts = [];
+
+function bareHost(hostname) {
+  return String(hostname).replace(/^\[|\]$/g, '').replace(/\.$/, '').toLowerCase();
+}
+
+function urlPort(parsed) {
+  if (parsed.port) return Number(parsed.port);
+  return parsed.protocol === 'https:' || parsed.protocol === 'wss:' ? 443 : 80;
+}
+
+function parseTrustedHost(pattern) {
+  const match = /^(\[[^\]]+\]|[^:\s]+):(\d+|\*)$/.exec(String(pattern).trim());
+  if (!match) return null;
+  return { host: bareHost(match[1]), port: match[2] === '*' ? null : Number(match[2]) };
+}
+
+export function getTrustedLocalHosts() {
+  return trustedLocalHosts.map(({ host, port }) => `${host.includes(':') ? `[${host}]` : host}:${port ?? '*'}`);
+}
+
+/** Replace the trusted list; malformed entries are dropped. Returns the new list. */
+export function setTrustedLocalHosts(patterns) {
+  trustedLocalHosts = patterns.map(parseTrustedHost).filter(Boolean);
+  return getTrustedLocalHosts();
+}
+
+export function isTrustedLocalHost(hostname, port) {
+  const host = bareHost(hostname);
+  return trustedLocalHosts.some((trusted) => {
+    if (trusted.port !== null && trusted.port !== Number(port)) return false;
+    if (!trusted.host.endsWith('.*')) return trusted.host === host;
+    const prefix = trusted.host.slice(0, -1);
+    return net.isIPv4(host) && host.startsWith(prefix);
+  });
+}
+
+/** Private, link-local or unique-local address, or a LAN-only name; loopback is not. */
+function isLanHost(hostname) {
+  const host = bareHost(hostname);
+  if (host === 'localhost' || host.endsWith('.localhost') || host === '::1' || /^(::ffff:)?127\./.test(host)) return false;
+  if (net.isIP(host)) return isPrivateIP(host);
+  return !host.includes('.') || LAN_NAME_SUFFIXES.some((suffix) => host.endsWith(suffix));
+}
+
+export function needsTrust(urlString) {
+  try {
+    const parsed = new URL(urlString);
+    return isLanHost(parsed.hostname) && !isTrustedLocalHost(parsed.hostname, urlPort(parsed));
+  } catch {
+    return false;
+  }
+}
+
 async function isSafeUrl(urlString) {
   let parsed;
   try {
@@ -242,6 +403,11 @@ async function isSafeUrl(urlString) {
 
   const hostname = parsed.hostname;
 
+  // The user vouched for this one; there is nothing to pin it to.
+  if (isTrustedLocalHost(hostname, urlPort(parsed))) {
+    return { safe: true, resolvedAddresses: [] };
+  }
+
   // Quick-reject obvious private hostnames before DNS resolution
   if (hostname === 'localhost' || hostname === '[::1]') {
     return { safe: false, reason: 'Requests to localhost are not allowed' };
@@ -474,6 +640,40 @@ const failedImports = new Set();
 const fallbackCounts = new Map();
 const cloudPreferred = new Set();
 
+const disabledSources = new Set();
+// Values of withheld keys, restored when their source is enabled again.
+const withheldEnv = new Map();
+
+function isWithheldKey(key) {
+  return Array.from(disabledSources).some((id) => DATA_SOURCE_KEYS[id].includes(key));
+}
+
+export function getDisabledSources() {
+  return Array.from(disabledSources);
+}
+
+/** Replace the disabled set; unknown ids are ignored. Returns the new set. */
+export function setDisabledSources(ids) {
+  const next = new Set(ids.filter((id) => Object.hasOwn(DATA_SOURCE_KEYS, id)));
+  for (const [id, keys] of Object.entries(DATA_SOURCE_KEYS)) {
+    for (const key of keys) {
+      if (next.has(id) && process.env[key] !== undefined) {
+        withheldEnv.set(key, process.env[key]);
+        delete process.env[key];
+      } else if (!next.has(id) && withheldEnv.has(key)) {
+        process.env[key] = withheldEnv.get(key);
+        withheldEnv.delete(key);
+      }
+    }
+  }
+  disabledSources.clear();
+  for (const id of next) disabledSources.add(id);
+  moduleCache.clear();
+  failedImports.clear();
+  cloudPreferred.clear();
+  return getDisabledSources();
+}
+
 const TRAFFIC_LOG_MAX = 200;
 const trafficLog = [];
 let verboseMode = false;
@@ -486,12 +686,59 @@ let _verboseStatePath = null;
 // IS configured, the bundled api/ais-snapshot.js handler (which proxies
 // to the external relay server) takes precedence instead.
 let _aisRelay = null;
+// Set by the desktop shell during the user's quiet hours: long-lived
+// upstream connections are dropped until they end.
+let quietHours = false;
+// Set while the user has paused all polling: upstream connections close and
+// data routes answer 503 until it is resumed.
+let pollingPaused = false;
+// Above 1 on a metered connection the user chose to spare: clients are told
+// to stretch their polling intervals by this factor (x-poll-multiplier) and
+// the speculative slow bootstrap tier is skipped.
+let pollMultiplier = 1;
+
+function parsePollMultiplier(value) {
+  const multiplier = Number(value);
+  return Number.isInteger(multiplier) && multiplier >= 1 ? multiplier : 1;
+}
+// Local clock minus server time in ms, measured by the desktop shell; null
+// until known. Logged next to upstream auth failures, which a wrong clock
+// causes more often than a wrong key.
+let clockSkewMs = null;
+
+// LOCAL_API_MODE of the second process the desktop shell can run for the
+// forensics fusion and anomaly stages, so they don't stall the API process.
+const FORENSICS_WORKER_MODE = 'forensics-worker';
+const FORENSICS_WORKER_PREFIX = '/api/internal/forensics/';
+
+function hasForensicsWorkerSecret(req) {
+  const configured = process.env.FORENSICS_WORKER_SHARED_SECRET?.trim() || '';
+  if (!configured) return false;
+  const expected = Buffer.from(configured);
+  const actual = Buffer.from(String(req.headers['x-forensics-worker-secret'] || '').trim());
+  return actual.length === expected.length && crypto.timingSafeEqual(actual, expected);
+}
+
+function isLoopbackWorkerUrl(value) {
+  try {
+    const url = new URL(value);
+    return url.protocol === 'http:' && ['127.0.0.1', 'localhost', '[::1]'].includes(url.hostname);
+  } catch {
+    return false;
+  }
+}
+
+function parseClockSkew(value) {
+  if (value === null || value === undefined || value === '') return null;
+  const skew = Number(value);
+  return Number.isFinite(skew) ? Math.round(skew) : null;
+}
 
 async function maybeStartAisRelay(logger = console) {
   const apiKey = process.env.AISSTREAM_API_KEY;
   const relayUrl = process.env.WS_RELAY_URL;
   // Only use the embedded relay when no external relay URL is configured.
-  if (apiKey && !relayUrl) {
+  if (apiKey && !relayUrl && !quietHours && !pollingPaused) {
     if (!_aisRelay) {
       try {
         const { createAisRelay } = await import('./ais-relay.mjs');
@@ -502,13 +749,70 @@ async function maybeStartAisRelay(logger = console) {
         logger.warn('[local-api] could not start embedded AIS relay:', err.message);
       }
     }
-  } else if (_aisRelay && (!apiKey || relayUrl)) {
+  } else if (_aisRelay && (!apiKey || relayUrl || quietHours || pollingPaused)) {
     _aisRelay.stop();
     _aisRelay = null;
     logger.log('[local-api] embedded AIS relay stopped');
   }
 }
 
+// Work that a quit must not cut short. The desktop shell polls
+// /api/local-busy before exiting and offers to wait while any job is open.
+const busyJobs = new Map();
+let nextBusyJobId = 1;
+
+export function beginBusyJob(label) {
+  const id = nextBusyJobId++;
+  busyJobs.set(id, { label, startedAt: Date.now() });
+  return () => { busyJobs.delete(id); };
+}
+
+export function getBusyJobs() {
+  const now = Date.now();
+  return Array.from(busyJobs.values(), ({ label, startedAt }) => ({ label, elapsedMs: now - startedAt }));
+}
+
+async function trackBusy(label, task) {
+  const end = beginBusyJob(label);
+  try {
+    return await task();
+  } finally {
+    end();
+  }
+}
+
+// Outcome of the latest requests per handler, so the desktop shell can tell
+// a quiet panel from a broken feed. Polled on /api/local-source-health.
+const sourceHealth = new Map();
+
+function sourceOf(pathname) {
+  if (!pathname.startsWith('/api/') || pathname.startsWith('/api/local-')) return null;
+  if (pathname === '/api/service-status') return null;
+  return pathname.slice('/api/'.length).split('/')[0] || null;
+}
+
+/** Record one answer; client errors other than auth and rate limits don't count. */
+export function recordSourceOutcome(pathname, status, error = '') {
+  const source = sourceOf(pathname);
+  if (!source) return;
+  const failed = status >= 500 || status === 401 || status === 403 || status === 429;
+  if (!failed && status >= 400) return;
+  const entry = sourceHealth.get(source) || { source, lastSuccess: null, lastError: null, lastErrorAt: null, consecutiveFailures: 0 };
+  if (failed) {
+    entry.lastError = error || `HTTP ${status}`;
+    entry.lastErrorAt = Date.now();
+    entry.consecutiveFailures += 1;
+  } else {
+    entry.lastSuccess = Date.now();
+    entry.consecutiveFailures = 0;
+  }
+  sourceHealth.set(source, entry);
+}
+
+export function getSourceHealth() {
+  return Array.from(sourceHealth.values(), (entry) => ({ ...entry }));
+}
+
 function loadVerboseState(dataDir) {
   _verboseStatePath = path.join(dataDir, 'verbose-mode.json');
   try {
@@ -564,6 +868,7 @@ async function importHandler(modulePath) {
 
 function resolveConfig(options = {}) {
   const port = Number(options.port ?? process.env.LOCAL_API_PORT ?? 46123);
+  const host = String(options.host ?? process.env.LOCAL_API_HOST ?? '127.0.0.1');
   const remoteBase = String(options.remoteBase ?? process.env.LOCAL_API_REMOTE_BASE ?? 'https://worldmonitor.app').replace(/\/$/, '');
   const resourceDir = String(options.resourceDir ?? process.env.LOCAL_API_RESOURCE_DIR ?? process.cwd());
   const apiDir = options.apiDir
@@ -579,6 +884,7 @@ function resolveConfig(options = {}) {
 
   return {
     port,
+    host,
     remoteBase,
     resourceDir,
     dataDir,
@@ -667,7 +973,7 @@ async function fetchWithTimeout(url, options = {}, timeoutMs = 12000) {
         port: u.port || 443,
         path: u.pathname + u.search,
         method: options.method || 'GET',
-        headers: options.headers || {},
+        headers: withAppUserAgent(options.headers || {}),
         family: 4,
       };
       // Pin to a pre-resolved IP to prevent TOCTOU DNS rebinding.
@@ -759,6 +1065,10 @@ async function validateSecretAgainstProvider(key, rawValue, context = {}) {
   const fail = (message) => ({ valid: false, message });
   const ok = (message) => ({ valid: true, message });
 
+  if (LAN_URL_KEYS.has(key) && needsTrust(value)) {
+    return fail('This host is on the local network; add it to trusted local hosts first');
+  }
+
   try {
     switch (key) {
     case 'GROQ_API_KEY': {
@@ -1200,11 +1510,28 @@ async function dispatch(requestUrl, req, routes, context) {
     return handleLocalServiceStatus(context);
   }
 
+  // The forensics worker answers only the API process, which holds the
+  // shared secret rather than the session token, and serves nothing else.
+  if (context.mode === FORENSICS_WORKER_MODE) {
+    if (!requestUrl.pathname.startsWith(FORENSICS_WORKER_PREFIX)) {
+      return json({ error: 'Not served by the forensics worker', endpoint: requestUrl.pathname }, 404);
+    }
+    if (!hasForensicsWorkerSecret(req)) {
+      context.logger.warn(`[local-api] forensics worker refused ${requestUrl.pathname}: bad or missing secret`);
+      return json({ error: 'Unauthorized' }, 401);
+    }
+    return invokeRoute(requestUrl, req, routes, context);
+  }
+
   // HLS proxy — exempt from auth because <video src="..."> cannot carry
   // custom headers.  Proxies HLS manifests and segments from allowlisted CDN
   // hosts, adding the required Referer header that browsers cannot set.
   // Desktop-only (sidecar); web uses YouTube fallback.
   if (requestUrl.pathname === '/api/hls-proxy') {
+    // Served ahead of the data-route pause check below, so check it here.
+    if (pollingPaused) {
+      return new Response('Polling is paused', { status: 503, headers: { 'content-type': 'text/plain', 'x-polling-paused': '1', ...makeCorsHeaders(req) } });
+    }
     const ALLOWED_HLS_HOSTS = new Set(['cdn-ca2-na.lncnetworks.host']);
     const upstreamRaw = requestUrl.searchParams.get('url');
     if (!upstreamRaw) return new Response('Missing url param', { status: 400, headers: { 'content-type': 'text/plain', ...makeCorsHeaders(req) } });
@@ -1301,8 +1628,143 @@ async function dispatch(requestUrl, req, routes, context) {
       remoteBase: context.remoteBase,
       cloudFallback: context.cloudFallback,
       routes: routes.length,
+      busy: busyJobs.size > 0,
+      disabledSources: getDisabledSources(),
+      quietHours,
+      pollingPaused,
+      pollMultiplier,
+      clockSkewMs,
+      forensicsWorker: process.env.FORENSICS_WORKER_URL ? 'remote' : 'local',
     });
   }
+  if (requestUrl.pathname === '/api/local-busy') {
+    const jobs = getBusyJobs();
+    return json({ busy: jobs.length > 0, jobs });
+  }
+  if (requestUrl.pathname === '/api/local-source-health') {
+    return json({ sources: getSourceHealth() });
+  }
+  if (requestUrl.pathname === '/api/local-api-usage') {
+    return json(getApiUsage());
+  }
+  if (requestUrl.pathname === '/api/local-disabled-sources') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let sources;
+      try { ({ sources } = JSON.parse(body?.toString() || '{}')); } catch { /* bad JSON */ }
+      if (!Array.isArray(sources) || !sources.every((id) => typeof id === 'string')) {
+        return json({ error: 'expected { sources: string[] }' }, 400);
+      }
+      const disabled = setDisabledSources(sources);
+      context.logger.log(`[local-api] disabled sources: ${disabled.join(',') || '(none)'}`);
+      maybeStartAisRelay(context.logger).catch(() => {});
+      return json({ disabledSources: disabled });
+    }
+    return json({ disabledSources: getDisabledSources() });
+  }
+  if (requestUrl.pathname === '/api/local-trusted-hosts') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let hosts;
+      try { ({ hosts } = JSON.parse(body?.toString() || '{}')); } catch { /* bad JSON */ }
+      if (!Array.isArray(hosts) || !hosts.every((host) => typeof host === 'string')) {
+        return json({ error: 'expected { hosts: string[] }' }, 400);
+      }
+      const trusted = setTrustedLocalHosts(hosts);
+      context.logger.log(`[local-api] trusted local hosts: ${trusted.join(',') || '(none)'}`);
+      return json({ trustedHosts: trusted });
+    }
+    return json({ trustedHosts: getTrustedLocalHosts() });
+  }
+  if (requestUrl.pathname === '/api/local-quiet-hours') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let quiet;
+      try { ({ quiet } = JSON.parse(body?.toString() || '{}')); } catch { /* bad JSON */ }
+      if (typeof quiet !== 'boolean') {
+        return json({ error: 'expected { quiet: boolean }' }, 400);
+      }
+      if (quiet !== quietHours) {
+        quietHours = quiet;
+        context.logger.log(`[local-api] quiet hours ${quietHours ? 'started' : 'ended'}`);
+        await maybeStartAisRelay(context.logger);
+      }
+    }
+    return json({ quietHours });
+  }
+  if (requestUrl.pathname === '/api/local-polling') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let paused;
+      try { ({ paused } = JSON.parse(body?.toString() || '{}')); } catch { /* bad JSON */ }
+      if (typeof paused !== 'boolean') {
+        return json({ error: 'expected { paused: boolean }' }, 400);
+      }
+      if (paused !== pollingPaused) {
+        pollingPaused = paused;
+        context.logger.log(`[local-api] polling ${pollingPaused ? 'paused' : 'resumed'}`);
+        await maybeStartAisRelay(context.logger);
+      }
+    }
+    return json({ pollingPaused });
+  }
+  if (requestUrl.pathname === '/api/local-network-profile') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let profile;
+      try { profile = JSON.parse(body?.toString() || '{}'); } catch { /* bad JSON */ }
+      const multiplier = profile?.pollMultiplier;
+      if (typeof profile?.metered !== 'boolean' || !Number.isInteger(multiplier) || multiplier < 1) {
+        return json({ error: 'expected { metered: boolean, pollMultiplier: integer >= 1 }' }, 400);
+      }
+      if (multiplier !== pollMultiplier) {
+        pollMultiplier = multiplier;
+        context.logger.log(`[local-api] poll interval multiplier now ${pollMultiplier}`);
+      }
+    }
+    return json({ pollMultiplier });
+  }
+  if (requestUrl.pathname === '/api/local-forensics-worker') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let update;
+      try { update = JSON.parse(body?.toString() || '{}'); } catch { /* bad JSON */ }
+      const { url, secret } = update ?? {};
+      const valid = url === null
+        || (typeof url === 'string' && isLoopbackWorkerUrl(url) && typeof secret === 'string' && secret.length > 0);
+      if (!valid) {
+        return json({ error: 'expected { url: loopback http URL, secret: string } or { url: null }' }, 400);
+      }
+      if (url === null) {
+        delete process.env.FORENSICS_WORKER_URL;
+        context.logger.log('[local-api] forensics worker gone; running forensics in-process');
+      } else {
+        process.env.FORENSICS_WORKER_URL = url;
+        process.env.FORENSICS_WORKER_SHARED_SECRET = secret;
+        context.logger.log(`[local-api] forensics worker at ${url}`);
+      }
+    }
+    return json({ forensicsWorker: process.env.FORENSICS_WORKER_URL ? 'remote' : 'local' });
+  }
+  if (pollingPaused && !requestUrl.pathname.startsWith('/api/local-') && requestUrl.pathname !== '/api/register-interest') {
+    return json({ error: 'Polling is paused', code: 'polling_paused' }, 503, { 'x-polling-paused': '1' });
+  }
+  if (pollMultiplier > 1 && requestUrl.pathname === '/api/bootstrap' && requestUrl.searchParams.get('tier') === 'slow') {
+    // Prefetch for panels that may never be looked at; they load on demand.
+    return json({ data: {}, skipped: 'metered' });
+  }
+  if (requestUrl.pathname === '/api/local-clock-skew') {
+    if (req.method === 'POST') {
+      const body = await readBody(req);
+      let skewMs;
+      try { ({ skewMs } = JSON.parse(body?.toString() || '{}')); } catch { /* bad JSON */ }
+      if (skewMs !== null && !Number.isFinite(skewMs)) {
+        return json({ error: 'expected { skewMs: number | null }' }, 400);
+      }
+      clockSkewMs = parseClockSkew(skewMs);
+    }
+    return json({ clockSkewMs });
+  }
   if (requestUrl.pathname === '/api/local-traffic-log') {
     if (req.method === 'DELETE') {
       trafficLog.length = 0;
@@ -1345,16 +1807,18 @@ async function dispatch(requestUrl, req, routes, context) {
       if (!email || typeof email !== 'string' || !/^[^\s@]+@[^\s@]+\.[^\s@]+$/.test(email)) {
         return json({ error: 'Invalid email address' }, 400);
       }
-      const response = await fetchWithTimeout(`${convexUrl}/api/mutation`, {
-        method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
-        body: JSON.stringify({
-          path: 'registerInterest:register',
-          args: { email, source: parsed.source || 'desktop', appVersion: parsed.appVersion || 'unknown' },
-          format: 'json',
-        }),
-      }, 15000);
-      const responseBody = await response.text();
+      const responseBody = await trackBusy('register-interest', async () => {
+        const response = await fetchWithTimeout(`${convexUrl}/api/mutation`, {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json' },
+          body: JSON.stringify({
+            path: 'registerInterest:register',
+            args: { email, source: parsed.source || 'desktop', appVersion: parsed.appVersion || 'unknown' },
+            format: 'json',
+          }),
+        }, 15000);
+        return response.text();
+      });
       let result;
       try { result = JSON.parse(responseBody); } catch { result = { status: 'registered' }; }
       if (result.status === 'error') {
@@ -1423,6 +1887,13 @@ async function dispatch(requestUrl, req, routes, context) {
         try {
           const { key, value } = JSON.parse(body.toString());
           if (typeof key === 'string' && key.length > 0 && ALLOWED_ENV_KEYS.has(key)) {
+            if (isWithheldKey(key)) {
+              // Held until the source is enabled again.
+              if (value == null || value === '') withheldEnv.delete(key);
+              else withheldEnv.set(key, String(value));
+              context.logger.log(`[local-api] env held for disabled source: ${key}`);
+              return json({ ok: true, key });
+            }
             if (value == null || value === '') {
               delete process.env[key];
               context.logger.log(`[local-api] env unset: ${key}`);
@@ -1484,6 +1955,10 @@ async function dispatch(requestUrl, req, routes, context) {
     return json(snapshot);
   }
 
+  return invokeRoute(requestUrl, req, routes, context);
+}
+
+async function invokeRoute(requestUrl, req, routes, context) {
   const modulePath = pickModule(requestUrl.pathname, routes);
   if (!modulePath || !existsSync(modulePath)) {
     if (context.cloudFallback) {
@@ -1544,6 +2019,15 @@ async function dispatch(requestUrl, req, routes, context) {
 export async function createLocalApiServer(options = {}) {
   const context = resolveConfig(options);
   loadVerboseState(context.dataDir);
+  const disabledFromEnv = String(options.disabledSources ?? process.env.LOCAL_API_DISABLED_SOURCES ?? '');
+  setDisabledSources(disabledFromEnv.split(',').map((id) => id.trim()).filter(Boolean));
+  const trustedFromEnv = String(options.trustedHosts ?? process.env.LOCAL_API_TRUSTED_HOSTS ?? '');
+  setTrustedLocalHosts(trustedFromEnv.split(',').map((host) => host.trim()).filter(Boolean));
+  quietHours = String(options.quietHours ?? process.env.LOCAL_API_QUIET_HOURS ?? '') === '1';
+  pollingPaused = String(options.pollingPaused ?? process.env.LOCAL_API_POLLING_PAUSED ?? '') === '1';
+  pollMultiplier = parsePollMultiplier(options.pollMultiplier ?? process.env.LOCAL_API_POLL_MULTIPLIER ?? 1);
+  appUserAgent = String(options.userAgent ?? process.env.LOCAL_API_USER_AGENT ?? '');
+  clockSkewMs = parseClockSkew(options.clockSkewMs ?? process.env.LOCAL_API_CLOCK_SKEW_MS);
   const routes = await buildRouteTable(context.apiDir);
 
   const server = createServer(async (req, res) => {
@@ -1559,6 +2043,16 @@ export async function createLocalApiServer(options = {}) {
     const skipRecord = req.method === 'OPTIONS'
       || requestUrl.pathname === '/api/local-traffic-log'
       || requestUrl.pathname === '/api/local-debug-toggle'
+      || requestUrl.pathname === '/api/local-busy'
+      || requestUrl.pathname === '/api/local-disabled-sources'
+      || requestUrl.pathname === '/api/local-trusted-hosts'
+      || requestUrl.pathname === '/api/local-source-health'
+      || requestUrl.pathname === '/api/local-api-usage'
+      || requestUrl.pathname === '/api/local-quiet-hours'
+      || requestUrl.pathname === '/api/local-polling'
+      || requestUrl.pathname === '/api/local-network-profile'
+      || requestUrl.pathname === '/api/local-forensics-worker'
+      || requestUrl.pathname === '/api/local-clock-skew'
       || requestUrl.pathname === '/api/local-env-update'
       || requestUrl.pathname === '/api/local-validate-secret';
 
@@ -1580,6 +2074,14 @@ export async function createLocalApiServer(options = {}) {
           durationMs,
         });
       }
+      if (req.method !== 'OPTIONS' && !response.headers.has('x-polling-paused')) {
+        recordSourceOutcome(requestUrl.pathname, response.status);
+      }
+      const upstreamAuthFailure = (response.status === 401 || response.status === 403)
+        && !requestUrl.pathname.startsWith('/api/local-');
+      if (upstreamAuthFailure && clockSkewMs !== null) {
+        context.logger.warn(`[local-api] ${requestUrl.pathname} answered ${response.status}; local clock skew is ${(clockSkewMs / 1000).toFixed(1)}s`);
+      }
 
       const acceptEncoding = req.headers['accept-encoding'] || '';
       body = await maybeCompressResponseBody(body, headers, acceptEncoding);
@@ -1587,6 +2089,10 @@ export async function createLocalApiServer(options = {}) {
       if (headers['content-encoding']) {
         delete headers['content-length'];
       }
+      if (pollMultiplier > 1 && !requestUrl.pathname.startsWith('/api/local-')) {
+        headers['x-poll-multiplier'] = String(pollMultiplier);
+        headers['access-control-expose-headers'] = 'x-poll-multiplier';
+      }
 
       res.writeHead(response.status, headers);
       res.end(body);
@@ -1604,6 +2110,7 @@ export async function createLocalApiServer(options = {}) {
           error: error.message,
         });
       }
+      if (req.method !== 'OPTIONS') recordSourceOutcome(requestUrl.pathname, 500, error.message);
 
       res.writeHead(500, { 'content-type': 'application/json', ...makeCorsHeaders(req) });
       res.end(JSON.stringify({ error: 'Internal server error' }));
@@ -1620,7 +2127,7 @@ export async function createLocalApiServer(options = {}) {
         const onError = (error) => { server.off('listening', onListening); reject(error); };
         server.once('listening', onListening);
         server.once('error', onError);
-        server.listen(port, '127.0.0.1');
+        server.listen(port, context.host);
       });
 
       try {
@@ -1640,12 +2147,14 @@ export async function createLocalApiServer(options = {}) {
 
       const portFile = process.env.LOCAL_API_PORT_FILE;
       if (portFile) {
-        try { writeFileSync(portFile, String(boundPort)); } catch {}
+        // The bound address goes on a second line so the shell can check it is loopback.
+        const boundHost = typeof address === 'object' && address?.address ? address.address : context.host;
+        try { writeFileSync(portFile, `${boundPort}\n${boundHost}`); } catch {}
       }
 
-      context.logger.log(`[local-api] listening on http://127.0.0.1:${boundPort} (apiDir=${context.apiDir}, routes=${routes.length}, cloudFallback=${context.cloudFallback})`);
+      context.logger.log(`[local-api] listening on http://${context.host}:${boundPort} (apiDir=${context.apiDir}, routes=${routes.length}, cloudFallback=${context.cloudFallback})`);
       // Start embedded AIS relay if AISSTREAM_API_KEY is already set in env.
-      await maybeStartAisRelay(context.logger);
+      if (context.mode !== FORENSICS_WORKER_MODE) await maybeStartAisRelay(context.logger);
       return { port: boundPort };
     },
     async close() {
@@ -0,0 +1,367 @@
+//! Daily upstream call counts per data source, against optional quotas.
+//! The sidecar counts outgoing requests by provider (matched on the host)
+//! since it started and serves them on `/api/local-api-usage`; the shell
+//! polls that every minute, adds what is new to the counters for the local
+//! calendar day and keeps the last [`HISTORY_DAYS`] days in the persistent
+//! cache. Quotas are the `apiQuotas` pref, `{ source: callsPerDay }`; a
+//! source crossing 80% or 100% of its quota fires `api-quota-warning`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::data_sources;
+use crate::logging::{log_event, now_ms};
+use crate::maintenance::TaskSpec;
+use crate::{get_from_local_api, PersistentCache, RuntimePrefs};
+
+pub(crate) const PREF_API_QUOTAS: &str = "apiQuotas";
+const CACHE_KEY: &str = "api-usage";
+const QUOTA_EVENT: &str = "api-quota-warning";
+const HISTORY_DAYS: u64 = 7;
+const WARN_PERCENT: u64 = 80;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Counters as the sidecar reports them: calls per source since `since`
+/// (unix milliseconds), when that sidecar started.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub(crate) struct SidecarCounts {
+    since: u64,
+    calls: BTreeMap<String, u64>,
+}
+
+/// Calls per local day (`YYYY-MM-DD`) and source, plus the last sidecar
+/// report, to count only what came after it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Ledger {
+    days: BTreeMap<String, BTreeMap<String, u64>>,
+    last_report: Option<SidecarCounts>,
+}
+
+/// `api-quota-warning` payload.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct QuotaWarning {
+    provider: String,
+    used: u64,
+    quota: u64,
+    /// 80 or 100.
+    percent: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct DayCount {
+    day: String,
+    calls: u64,
+}
+
+/// `get_api_usage_stats` entry.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct ProviderUsage {
+    provider: String,
+    today: u64,
+    quota: Option<u64>,
+    /// Oldest first, ending with today; days without calls count 0.
+    history: Vec<DayCount>,
+}
+
+/// Managed state.
+#[derive(Default)]
+pub(crate) struct ApiUsageState {
+    ledger: Mutex<Ledger>,
+}
+
+fn local_offset(unix_secs: i64) -> i64 {
+    chrono::DateTime::from_timestamp(unix_secs, 0)
+        .map(|utc| i64::from(chrono::Local.offset_from_utc_datetime(&utc.naive_utc()).local_minus_utc()))
+        .unwrap_or(0)
+}
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    let map = value
+        .as_object()
+        .ok_or_else(|| format!("Runtime pref {PREF_API_QUOTAS} must be an object"))?;
+    for (id, quota) in map {
+        if !data_sources::is_known(id) {
+            return Err(format!("Unknown data source: {id}"));
+        }
+        if quota.as_u64().unwrap_or(0) == 0 {
+            return Err(format!("Runtime pref {PREF_API_QUOTAS}.{id} must be a positive integer"));
+        }
+    }
+    Ok(())
+}
+
+fn quotas(app: &AppHandle) -> BTreeMap<String, u64> {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_API_QUOTAS))
+        .and_then(|value| value.as_object().cloned())
+        .map(|map| {
+            map.into_iter()
+                .filter_map(|(id, quota)| Some((id, quota.as_u64()?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The calendar day at `unix_ms`, with `offset` giving the local offset from
+/// UTC in seconds at a unix time in seconds.
+pub(crate) fn local_day(unix_ms: u64, offset: impl Fn(i64) -> i64) -> NaiveDate {
+    let secs = (unix_ms / 1000) as i64;
+    chrono::DateTime::from_timestamp(secs + offset(secs), 0)
+        .map(|shifted| shifted.date_naive())
+        .unwrap_or_default()
+}
+
+/// Calls in `report` that `last` hadn't seen. A different `since` is a new
+/// sidecar, whose counters started over.
+fn new_calls(last: Option<&SidecarCounts>, report: &SidecarCounts) -> BTreeMap<String, u64> {
+    let seen = last.filter(|last| last.since == report.since);
+    report
+        .calls
+        .iter()
+        .filter_map(|(provider, &count)| {
+            let before = seen.and_then(|last| last.calls.get(provider)).copied().unwrap_or(0);
+            let new = count.saturating_sub(before);
+            (new > 0).then(|| (provider.clone(), new))
+        })
+        .collect()
+}
+
+/// The highest of 80% and 100% of `quota` that going from `before` to
+/// `after` calls crossed.
+fn crossed(before: u64, after: u64, quota: u64) -> Option<u64> {
+    [100, WARN_PERCENT].into_iter().find(|percent| {
+        let line = (quota * percent).div_ceil(100);
+        before < line && after >= line
+    })
+}
+
+impl Ledger {
+    /// Count `report`'s new calls on `day`, forget days that fell out of the
+    /// history, and return the quota thresholds crossed.
+    pub(crate) fn record(&mut self, day: NaiveDate, report: SidecarCounts, quotas: &BTreeMap<String, u64>) -> Vec<QuotaWarning> {
+        let new = new_calls(self.last_report.as_ref(), &report);
+        self.last_report = Some(report);
+        let first_kept = (day - chrono::Days::new(HISTORY_DAYS - 1)).to_string();
+        self.days.retain(|kept, _| *kept >= first_kept);
+        let today = self.days.entry(day.to_string()).or_default();
+        let mut warnings = Vec::new();
+        for (provider, calls) in new {
+            let used = today.entry(provider.clone()).or_default();
+            let before = *used;
+            *used += calls;
+            if let Some(&quota) = quotas.get(&provider) {
+                if let Some(percent) = crossed(before, *used, quota) {
+                    warnings.push(QuotaWarning {
+                        provider,
+                        used: *used,
+                        quota,
+                        percent,
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Every source with calls in the history or a quota, by id.
+    pub(crate) fn usage(&self, today: NaiveDate, quotas: &BTreeMap<String, u64>) -> Vec<ProviderUsage> {
+        let days: Vec<String> = (0..HISTORY_DAYS)
+            .rev()
+            .map(|back| (today - chrono::Days::new(back)).to_string())
+            .collect();
+        let providers: BTreeSet<&String> = days
+            .iter()
+            .filter_map(|day| self.days.get(day))
+            .flat_map(|calls| calls.keys())
+            .chain(quotas.keys())
+            .collect();
+        providers
+            .into_iter()
+            .map(|provider| {
+                let history: Vec<DayCount> = days
+                    .iter()
+                    .map(|day| DayCount {
+                        day: day.clone(),
+                        calls: self.days.get(day).and_then(|calls| calls.get(provider)).copied().unwrap_or(0),
+                    })
+                    .collect();
+                ProviderUsage {
+                    provider: provider.clone(),
+                    today: history.last().map_or(0, |day| day.calls),
+                    quota: quotas.get(provider).copied(),
+                    history,
+                }
+            })
+            .collect()
+    }
+}
+
+async fn query(app: &AppHandle) -> Option<SidecarCounts> {
+    get_from_local_api(app, "/api/local-api-usage", REQUEST_TIMEOUT).await
+}
+
+/// Poll the sidecar once, count the new calls and warn on quotas crossed.
+pub(crate) async fn refresh(app: &AppHandle) {
+    let Some(report) = query(app).await else {
+        return;
+    };
+    let state = app.state::<ApiUsageState>();
+    let (snapshot, warnings) = {
+        let mut ledger = state.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        let warnings = ledger.record(local_day(now_ms(), local_offset), report, &quotas(app));
+        (ledger.clone(), warnings)
+    };
+    if let (Some(cache), Ok(value)) = (app.try_state::<PersistentCache>(), serde_json::to_value(&snapshot)) {
+        // Written out with the rest of the cache on exit.
+        cache.insert(CACHE_KEY.to_string(), value);
+    }
+    for warning in warnings {
+        log_event(
+            app,
+            "WARN",
+            "api_quota_warning",
+            &[
+                ("provider", &warning.provider),
+                ("used", &warning.used.to_string()),
+                ("quota", &warning.quota.to_string()),
+                ("percent", &warning.percent.to_string()),
+            ],
+        );
+        let _ = app.emit(QUOTA_EVENT, warning);
+    }
+}
+
+pub(crate) fn report(app: &AppHandle) -> Vec<ProviderUsage> {
+    let Some(state) = app.try_state::<ApiUsageState>() else {
+        return Vec::new();
+    };
+    let ledger = state.ledger.lock().unwrap_or_else(|e| e.into_inner());
+    ledger.usage(local_day(now_ms(), local_offset), &quotas(app))
+}
+
+/// Restore the counters from the persistent cache.
+pub(crate) fn restore(app: &AppHandle) {
+    let Some(saved) = app
+        .try_state::<PersistentCache>()
+        .and_then(|cache| cache.get(CACHE_KEY))
+        .and_then(|value: Value| serde_json::from_value::<Ledger>(value).ok())
+    else {
+        return;
+    };
+    *app.state::<ApiUsageState>().ledger.lock().unwrap_or_else(|e| e.into_inner()) = saved;
+}
+
+/// Poll the sidecar every minute, after [`restore`] at startup.
+pub(crate) const MAINTENANCE: TaskSpec = TaskSpec::sidecar_poll("api_usage", |app| {
+    Box::pin(async move {
+        refresh(&app).await;
+        Ok(())
+    })
+});
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    use super::{crossed, local_day, validate_pref, Ledger, SidecarCounts};
+
+    fn counts(since: u64, calls: &[(&str, u64)]) -> SidecarCounts {
+        SidecarCounts {
+            since,
+            calls: calls.iter().map(|(id, n)| (id.to_string(), *n)).collect(),
+        }
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    #[test]
+    fn counts_only_new_calls_across_a_sidecar_restart() {
+        let mut ledger = Ledger::default();
+        let none = BTreeMap::new();
+        ledger.record(day(10), counts(1, &[("fred", 5), ("eia", 2)]), &none);
+        ledger.record(day(10), counts(1, &[("fred", 8), ("eia", 2)]), &none);
+        // Restarted sidecar: its counters start from zero.
+        ledger.record(day(10), counts(2, &[("fred", 3)]), &none);
+        let usage = ledger.usage(day(10), &none);
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[0].provider.as_str(), usage[0].today), ("eia", 2));
+        assert_eq!((usage[1].provider.as_str(), usage[1].today), ("fred", 11));
+    }
+
+    #[test]
+    fn days_roll_over_at_local_midnight() {
+        // 2026-03-10 22:30 UTC is already the 11th two hours east.
+        let at = 1_773_181_800_000;
+        assert_eq!(local_day(at, |_| 0), day(10));
+        assert_eq!(local_day(at, |_| 2 * 3600), day(11));
+        assert_eq!(local_day(at, |_| -5 * 3600), day(10));
+
+        let mut ledger = Ledger::default();
+        let none = BTreeMap::new();
+        ledger.record(local_day(at, |_| 0), counts(1, &[("fred", 4)]), &none);
+        ledger.record(local_day(at + 3 * 3600 * 1000, |_| 0), counts(1, &[("fred", 6)]), &none);
+        let fred = &ledger.usage(day(11), &none)[0];
+        assert_eq!(fred.today, 2);
+        assert_eq!(fred.history.len(), 7);
+        assert_eq!(fred.history[0].day, "2026-03-05");
+        assert_eq!(fred.history[5].calls, 4);
+
+        // A week on, the 10th has been dropped.
+        ledger.record(day(17), counts(1, &[("fred", 6)]), &none);
+        assert_eq!(ledger.days.keys().collect::<Vec<_>>(), ["2026-03-11", "2026-03-17"]);
+    }
+
+    #[test]
+    fn warns_once_at_eighty_and_at_a_hundred_percent() {
+        assert_eq!(crossed(0, 79, 100), None);
+        assert_eq!(crossed(79, 80, 100), Some(80));
+        assert_eq!(crossed(80, 99, 100), None);
+        assert_eq!(crossed(99, 100, 100), Some(100));
+        assert_eq!(crossed(10, 150, 100), Some(100));
+        assert_eq!(crossed(0, 3, 3), Some(100));
+
+        let quotas = BTreeMap::from([("fred".to_string(), 10)]);
+        let mut ledger = Ledger::default();
+        let warnings = ledger.record(day(10), counts(1, &[("fred", 8), ("eia", 50)]), &quotas);
+        assert_eq!(serde_json::to_value(&warnings).unwrap(), json!([{ "provider": "fred", "used": 8, "quota": 10, "percent": 80 }]));
+        assert!(ledger.record(day(10), counts(1, &[("fred", 9)]), &quotas).is_empty());
+        // A new day starts from zero.
+        assert!(ledger.record(day(11), counts(1, &[("fred", 10)]), &quotas).is_empty());
+        let usage = ledger.usage(day(11), &quotas);
+        assert_eq!(usage.iter().find(|usage| usage.provider == "fred").unwrap().quota, Some(10));
+    }
+
+    #[test]
+    fn ledger_survives_a_cache_round_trip() {
+        let mut ledger = Ledger::default();
+        ledger.record(day(10), counts(7, &[("fred", 3)]), &BTreeMap::new());
+        let stored = serde_json::to_value(&ledger).unwrap();
+        assert_eq!(stored["days"]["2026-03-10"]["fred"], 3);
+        let mut restored: Ledger = serde_json::from_value(stored).unwrap();
+        assert_eq!(restored, ledger);
+        // The same sidecar reporting again adds only its new calls.
+        restored.record(day(10), counts(7, &[("fred", 4)]), &BTreeMap::new());
+        assert_eq!(restored.usage(day(10), &BTreeMap::new())[0].today, 4);
+    }
+
+    #[test]
+    fn quotas_pref_is_positive_calls_per_known_source() {
+        assert!(validate_pref(&json!({ "fred": 120, "finnhub": 60 })).is_ok());
+        assert!(validate_pref(&json!({ "fred": 0 })).is_err());
+        assert!(validate_pref(&json!({ "fred": "120" })).is_err());
+        assert!(validate_pref(&json!({ "nope": 5 })).is_err());
+        assert!(validate_pref(&json!([])).is_err());
+    }
+}
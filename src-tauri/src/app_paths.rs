@@ -0,0 +1,99 @@
+//! Resolved on-disk locations for support and the doctor tab. Nothing here
+//! creates or moves anything: a path that does not exist yet is reported as
+//! missing, and `writable` says whether it could be written or created.
+//! `degradedStorage` is set when startup fell back to a session directory.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::data_dir;
+use crate::{local_api_paths, PERSISTENT_CACHE_FILE, RUNTIME_PREFS_FILE};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct PathStatus {
+    path: String,
+    exists: bool,
+    writable: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppPaths {
+    data_dir: Option<PathStatus>,
+    log_dir: Option<PathStatus>,
+    cache_file: Option<PathStatus>,
+    prefs_file: Option<PathStatus>,
+    sidecar_script: PathStatus,
+    resource_root: PathStatus,
+    degraded_storage: Option<data_dir::DegradedStorage>,
+}
+
+pub(crate) fn app_paths(app: &AppHandle) -> AppPaths {
+    let data_dir = data_dir::resolve_data_dir(app).ok();
+    let cache_dir = data_dir::resolve_cache_dir(app).ok();
+    let log_dir = data_dir::resolve_log_dir(app).ok();
+    let sidecar = local_api_paths(app);
+    AppPaths {
+        cache_file: cache_dir.as_ref().map(|dir| path_status(&dir.join(PERSISTENT_CACHE_FILE))),
+        prefs_file: data_dir.as_ref().map(|dir| path_status(&dir.join(RUNTIME_PREFS_FILE))),
+        data_dir: data_dir.as_deref().map(path_status),
+        log_dir: log_dir.as_deref().map(path_status),
+        sidecar_script: path_status(&sidecar.script),
+        resource_root: path_status(&sidecar.resource_root),
+        degraded_storage: data_dir::degraded().cloned(),
+    }
+}
+
+pub(crate) fn path_status(path: &Path) -> PathStatus {
+    PathStatus {
+        path: path.display().to_string(),
+        exists: path.exists(),
+        writable: is_writable(path),
+    }
+}
+
+/// Whether `path` can be written, or, when it doesn't exist yet, whether its
+/// nearest existing ancestor would let it be created. No probe file is
+/// written.
+fn is_writable(path: &Path) -> bool {
+    let existing: Option<PathBuf> = path.ancestors().find(|candidate| candidate.exists()).map(Path::to_path_buf);
+    existing.is_some_and(|existing| permits_write(&existing))
+}
+
+#[cfg(unix)]
+fn permits_write(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `c_path` is a valid NUL-terminated string.
+    unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn permits_write(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::path_status;
+
+    #[test]
+    fn reports_existing_and_missing_paths_without_creating_them() {
+        let dir = std::env::temp_dir().join(format!("wm-app-paths-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = path_status(&dir);
+        assert!(existing.exists);
+        assert!(existing.writable);
+
+        let missing = dir.join("not-yet").join("persistent-cache.json");
+        let status = path_status(&missing);
+        assert!(!status.exists);
+        assert!(status.writable);
+        assert!(!missing.parent().unwrap().exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
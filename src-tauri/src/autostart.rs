@@ -0,0 +1,422 @@
+//! Launch at login. The OS registration is the source of truth: a LaunchAgent
+//! plist on macOS, a `Run` registry value on Windows, and an XDG autostart
+//! `.desktop` entry elsewhere. Users can remove any of these outside the app,
+//! so reads check the OS and correct the `autostart` pref mirror to match.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::{store_runtime_pref, RuntimePrefs};
+
+/// Mirror of the OS registration; only written by this module.
+pub(crate) const PREF_AUTOSTART: &str = "autostart";
+/// Whether the registration passes `--start-minimized`.
+pub(crate) const PREF_AUTOSTART_MINIMIZED: &str = "autostartMinimized";
+const START_MINIMIZED_ARG: &str = "--start-minimized";
+
+/// Whether the app is registered to start at login, per the OS.
+pub(crate) fn autostart_enabled(app: &AppHandle) -> Result<bool, DesktopError> {
+    let enabled = read_registration(app)?.is_some_and(|contents| registration_active(&contents));
+    sync_pref(app, enabled);
+    Ok(enabled)
+}
+
+/// Register or unregister. `start_minimized` defaults to the previous choice.
+pub(crate) fn set_autostart_enabled(
+    app: &AppHandle,
+    enabled: bool,
+    start_minimized: Option<bool>,
+) -> Result<(), DesktopError> {
+    let start_minimized = start_minimized
+        .unwrap_or_else(|| app.state::<RuntimePrefs>().get_bool(PREF_AUTOSTART_MINIMIZED, false));
+    if enabled {
+        write_registration(app, &expected_registration(app, start_minimized)?)?;
+    } else {
+        remove_registration(app)?;
+    }
+    store_runtime_pref(app, PREF_AUTOSTART, Value::Bool(enabled))?;
+    store_runtime_pref(app, PREF_AUTOSTART_MINIMIZED, Value::Bool(start_minimized))?;
+    log_event(
+        app,
+        "INFO",
+        "autostart_updated",
+        &[
+            ("enabled", &enabled.to_string()),
+            ("start_minimized", &start_minimized.to_string()),
+        ],
+    );
+    Ok(())
+}
+
+/// Point an existing registration at the current executable. AppImages and
+/// portable builds can move between runs, leaving a login item that launches
+/// nothing. Called once from setup.
+pub(crate) fn refresh_registration(app: &AppHandle) {
+    let existing = match read_registration(app) {
+        Ok(Some(contents)) if registration_active(&contents) => contents,
+        Ok(_) => {
+            sync_pref(app, false);
+            return;
+        }
+        Err(err) => {
+            log_event(app, "WARN", "autostart_check_failed", &[("error", &err.to_string())]);
+            return;
+        }
+    };
+    sync_pref(app, true);
+    let start_minimized = app.state::<RuntimePrefs>().get_bool(PREF_AUTOSTART_MINIMIZED, false);
+    let result = expected_registration(app, start_minimized).and_then(|expected| {
+        if expected == existing {
+            return Ok(false);
+        }
+        write_registration(app, &expected).map(|()| true)
+    });
+    match result {
+        Ok(true) => log_event(app, "INFO", "autostart_registration_refreshed", &[]),
+        Ok(false) => {}
+        Err(err) => log_event(app, "WARN", "autostart_refresh_failed", &[("error", &err.to_string())]),
+    }
+}
+
+fn sync_pref(app: &AppHandle, enabled: bool) {
+    if app.state::<RuntimePrefs>().get_bool(PREF_AUTOSTART, false) == enabled {
+        return;
+    }
+    if let Err(err) = store_runtime_pref(app, PREF_AUTOSTART, Value::Bool(enabled)) {
+        log_event(app, "WARN", "autostart_pref_sync_failed", &[("error", &err.to_string())]);
+    }
+}
+
+/// The file the login item should launch. AppImages run from a fresh
+/// `/tmp/.mount_*` directory each time; `$APPIMAGE` is the stable path.
+fn launch_executable() -> Result<PathBuf, DesktopError> {
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE").filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe()
+        .map_err(|e| DesktopError::Internal(format!("Failed to resolve the app executable: {e}")))
+}
+
+fn launch_args(start_minimized: bool) -> Vec<&'static str> {
+    if start_minimized {
+        vec![START_MINIMIZED_ARG]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg_attr(windows, allow(unused_variables))]
+fn expected_registration(app: &AppHandle, start_minimized: bool) -> Result<String, DesktopError> {
+    let exe = launch_executable()?;
+    let args = launch_args(start_minimized);
+    #[cfg(target_os = "macos")]
+    let contents = launch_agent_plist(&app.config().identifier, &exe, &args);
+    #[cfg(windows)]
+    let contents = windows_command_line(&exe, &args);
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let contents = desktop_entry(&app.package_info().name, &exe, &args);
+    Ok(contents)
+}
+
+#[cfg(not(windows))]
+fn registration_path(app: &AppHandle) -> Result<PathBuf, DesktopError> {
+    let identifier = &app.config().identifier;
+    #[cfg(target_os = "macos")]
+    let path = dirs::home_dir().map(|home| home.join("Library/LaunchAgents").join(format!("{identifier}.plist")));
+    #[cfg(not(target_os = "macos"))]
+    let path = dirs::config_dir().map(|config| config.join("autostart").join(format!("{identifier}.desktop")));
+    path.ok_or_else(|| DesktopError::Internal("Failed to resolve the autostart directory".to_string()))
+}
+
+#[cfg(not(windows))]
+fn read_registration(app: &AppHandle) -> Result<Option<String>, DesktopError> {
+    let path = registration_path(app)?;
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(DesktopError::io("Failed to read autostart entry", &path, err)),
+    }
+}
+
+#[cfg(not(windows))]
+fn write_registration(app: &AppHandle, contents: &str) -> Result<(), DesktopError> {
+    let path = registration_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| DesktopError::io("Failed to create", dir, e))?;
+    }
+    std::fs::write(&path, contents).map_err(|e| DesktopError::io("Failed to write autostart entry", &path, e))
+}
+
+#[cfg(not(windows))]
+fn remove_registration(app: &AppHandle) -> Result<(), DesktopError> {
+    let path = registration_path(app)?;
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(DesktopError::io("Failed to remove autostart entry", &path, err))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn registration_active(_contents: &str) -> bool {
+    true
+}
+
+/// GNOME's "Startup Applications" disables entries in place rather than
+/// deleting them.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn registration_active(contents: &str) -> bool {
+    !contents.lines().filter_map(|line| line.split_once('=')).any(|(key, value)| {
+        matches!(
+            (key.trim(), value.trim()),
+            ("Hidden", "true") | ("X-GNOME-Autostart-enabled", "false")
+        )
+    })
+}
+
+#[cfg(windows)]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+#[cfg(windows)]
+fn reg(args: &[&str]) -> Result<std::process::Output, DesktopError> {
+    use std::os::windows::process::CommandExt;
+    std::process::Command::new("reg")
+        .args(args)
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .map_err(|e| DesktopError::Internal(format!("Failed to run reg.exe: {e}")))
+}
+
+#[cfg(windows)]
+fn read_registration(app: &AppHandle) -> Result<Option<String>, DesktopError> {
+    let name = &app.package_info().name;
+    let output = reg(&["query", RUN_KEY, "/v", name])?;
+    if !output.status.success() {
+        // reg.exe exits non-zero when the value doesn't exist.
+        return Ok(None);
+    }
+    Ok(parse_reg_query_value(&String::from_utf8_lossy(&output.stdout), name))
+}
+
+#[cfg(windows)]
+fn write_registration(app: &AppHandle, contents: &str) -> Result<(), DesktopError> {
+    let output = reg(&["add", RUN_KEY, "/v", &app.package_info().name, "/t", "REG_SZ", "/d", contents, "/f"])?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(DesktopError::Internal(format!(
+            "Failed to register autostart: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(windows)]
+fn remove_registration(app: &AppHandle) -> Result<(), DesktopError> {
+    if read_registration(app)?.is_none() {
+        return Ok(());
+    }
+    let output = reg(&["delete", RUN_KEY, "/v", &app.package_info().name, "/f"])?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(DesktopError::Internal(format!(
+            "Failed to remove autostart: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(windows)]
+fn registration_active(_contents: &str) -> bool {
+    true
+}
+
+/// Pull the data out of a `reg query` line: `    <name>    REG_SZ    <data>`.
+#[cfg(any(windows, test))]
+fn parse_reg_query_value(output: &str, name: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let rest = line.trim_start().strip_prefix(name)?;
+        let (_, data) = rest.split_once("REG_SZ")?;
+        Some(data.trim().to_string())
+    })
+}
+
+/// The executable is always quoted; the arguments are fixed flags.
+#[cfg(any(windows, test))]
+fn windows_command_line(exe: &Path, args: &[&str]) -> String {
+    let mut line = format!("\"{}\"", exe.display());
+    for arg in args {
+        line.push(' ');
+        line.push_str(arg);
+    }
+    line
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn launch_agent_plist(label: &str, exe: &Path, args: &[&str]) -> String {
+    let program_arguments: String = std::iter::once(exe.to_string_lossy())
+        .chain(args.iter().map(|arg| (*arg).into()))
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{program_arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>ProcessType</key>
+    <string>Interactive</string>
+</dict>
+</plist>
+"#,
+        label = xml_escape(label),
+    )
+}
+
+/// Quote one `Exec=` argument per the Desktop Entry spec. Backslashes need
+/// four characters: the string-level escape is undone before the quoting rule.
+#[cfg(any(not(any(target_os = "macos", windows)), test))]
+fn desktop_exec_arg(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c));
+    if !needs_quotes {
+        return arg;
+    }
+    let mut out = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '\\' => out.push_str("\\\\\\\\"),
+            '"' | '`' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(any(not(any(target_os = "macos", windows)), test))]
+fn desktop_entry(name: &str, exe: &Path, args: &[&str]) -> String {
+    let exec: Vec<String> = std::iter::once(exe.to_string_lossy())
+        .chain(args.iter().map(|arg| (*arg).into()))
+        .map(|arg| desktop_exec_arg(&arg))
+        .collect();
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name={name}\n\
+         Comment=Start {name} at login\n\
+         Exec={exec}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        exec = exec.join(" "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        desktop_entry, desktop_exec_arg, launch_agent_plist, launch_args, parse_reg_query_value,
+        windows_command_line,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn desktop_entry_quotes_exec_per_spec() {
+        let entry = desktop_entry(
+            "World Monitor",
+            Path::new("/home/me/Apps/World Monitor.AppImage"),
+            &launch_args(true),
+        );
+        assert_eq!(
+            entry,
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=World Monitor\n\
+             Comment=Start World Monitor at login\n\
+             Exec=\"/home/me/Apps/World Monitor.AppImage\" --start-minimized\n\
+             Terminal=false\n\
+             X-GNOME-Autostart-enabled=true\n"
+        );
+        assert_eq!(desktop_exec_arg("/opt/world-monitor/world-monitor"), "/opt/world-monitor/world-monitor");
+        assert_eq!(desktop_exec_arg("/tmp/100%"), "/tmp/100%%");
+        assert_eq!(desktop_exec_arg("/a/$HOME \"x\""), "\"/a/\\$HOME \\\"x\\\"\"");
+        assert_eq!(desktop_exec_arg("C:\\x y"), "\"C:\\\\\\\\x y\"");
+    }
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    #[test]
+    fn desktop_entry_disabled_in_place_is_inactive() {
+        use super::registration_active;
+        let entry = desktop_entry("World Monitor", Path::new("/usr/bin/world-monitor"), &[]);
+        assert!(registration_active(&entry));
+        assert!(!registration_active(&entry.replace("X-GNOME-Autostart-enabled=true", "X-GNOME-Autostart-enabled=false")));
+        assert!(!registration_active(&format!("{entry}Hidden = true\n")));
+    }
+
+    #[test]
+    fn launch_agent_plist_lists_program_arguments() {
+        let plist = launch_agent_plist(
+            "app.worldmonitor.desktop",
+            Path::new("/Applications/World Monitor & Co.app/Contents/MacOS/world-monitor"),
+            &launch_args(true),
+        );
+        assert!(plist.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE plist"));
+        assert!(plist.contains("<key>Label</key>\n    <string>app.worldmonitor.desktop</string>"));
+        assert!(plist.contains(
+            "    <array>\n        \
+             <string>/Applications/World Monitor &amp; Co.app/Contents/MacOS/world-monitor</string>\n        \
+             <string>--start-minimized</string>\n    \
+             </array>"
+        ));
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+
+        let plain = launch_agent_plist("app.worldmonitor.desktop", Path::new("/bin/wm"), &launch_args(false));
+        assert!(!plain.contains("--start-minimized"));
+    }
+
+    #[test]
+    fn windows_run_value_round_trips_through_reg_query() {
+        let command = windows_command_line(Path::new(r"C:\Users\me\World Monitor\world-monitor.exe"), &launch_args(true));
+        assert_eq!(command, r#""C:\Users\me\World Monitor\world-monitor.exe" --start-minimized"#);
+        let output = format!(
+            "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Run\r\n    World Monitor    REG_SZ    {command}\r\n\r\n"
+        );
+        assert_eq!(parse_reg_query_value(&output, "World Monitor"), Some(command));
+        assert_eq!(parse_reg_query_value(&output, "Other"), None);
+    }
+}
@@ -0,0 +1,479 @@
+//! Binary store for large cached assets (map tiles, model files, imagery)
+//! that would double in size as base64 in the JSON cache. Each blob is a
+//! file under `blobs/` in the data directory, named by a hash of its key,
+//! beside a small `.meta` file holding the key itself. `index.json` keeps
+//! size, content type and timestamps for every blob; if it is lost or
+//! corrupt it is rebuilt from the `.meta` files. The directory is capped
+//! with LRU eviction. Blobs cross IPC as raw bytes: `write_blob` takes the
+//! request body with the key in the `x-blob-key` header, and `read_blob`
+//! answers with an `ArrayBuffer`. Keys follow the JSON cache's rules.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::ipc::{InvokeBody, Request};
+use tauri::{AppHandle, Manager};
+
+use crate::cache_limits::{self, lru_victims, LruEntry};
+use crate::data_dir;
+use crate::disk_space;
+use crate::error::DesktopError;
+use crate::logging::{log_event, now_ms};
+
+pub(crate) const BLOB_DIR: &str = "blobs";
+const INDEX_FILE: &str = "index.json";
+const BLOB_EXT: &str = "blob";
+const META_EXT: &str = "meta";
+const MAX_STORE_BYTES: u64 = 512 * 1024 * 1024;
+const MAX_BLOB_BYTES: u64 = 128 * 1024 * 1024;
+const MAX_CONTENT_TYPE_LEN: usize = 255;
+pub(crate) const KEY_HEADER: &str = "x-blob-key";
+pub(crate) const CONTENT_TYPE_HEADER: &str = "x-blob-content-type";
+
+/// File stem for `key`: the blob and its `.meta` share it.
+fn file_stem(key: &str) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    digest.iter().take(16).map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    stem: String,
+    size: u64,
+    created_at: u64,
+    /// Last time the blob was read or written, for LRU eviction.
+    last_used: u64,
+    content_type: Option<String>,
+}
+
+impl LruEntry for Entry {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn last_used(&self) -> u64 {
+        self.last_used
+    }
+}
+
+/// What a `.meta` file holds: enough to rebuild the index entry.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    key: String,
+    created_at: u64,
+    content_type: Option<String>,
+}
+
+/// `list_blobs` / `write_blob` entry.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlobInfo {
+    key: String,
+    size: u64,
+    created_at: u64,
+    last_used: u64,
+    content_type: Option<String>,
+}
+
+/// Write `contents` beside `path` as `<name>.tmp` and move it into place.
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    fs::write(&staging, contents)?;
+    fs::rename(&staging, path)
+}
+
+/// The index and blob files in one directory.
+struct BlobStore {
+    dir: PathBuf,
+    max_bytes: u64,
+    entries: HashMap<String, Entry>,
+}
+
+impl BlobStore {
+    /// Load the index, rebuilding it from the `.meta` files when it is
+    /// missing or unreadable. The flag says a rebuild found blobs.
+    fn load(dir: PathBuf, max_bytes: u64) -> (Self, bool) {
+        let indexed = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok());
+        if let Some(entries) = indexed {
+            return (BlobStore { dir, max_bytes, entries }, false);
+        }
+        let mut store = BlobStore {
+            dir,
+            max_bytes,
+            entries: HashMap::new(),
+        };
+        store.rebuild();
+        let rebuilt = !store.entries.is_empty();
+        if rebuilt {
+            let _ = store.save_index();
+        }
+        (store, rebuilt)
+    }
+
+    fn path(&self, stem: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{stem}.{ext}"))
+    }
+
+    /// Index every blob that has a readable `.meta`, and delete the files
+    /// that can't be matched up.
+    fn rebuild(&mut self) {
+        self.entries.clear();
+        let Ok(listing) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut blobs = Vec::new();
+        let mut metas = Vec::new();
+        for path in listing.filter_map(Result::ok).map(|entry| entry.path()) {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(BLOB_EXT) => blobs.push(path),
+                Some(META_EXT) => metas.push(path),
+                Some("tmp") => {
+                    let _ = fs::remove_file(&path);
+                }
+                _ => {}
+            }
+        }
+        for meta_path in metas {
+            let Some(stem) = meta_path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+                continue;
+            };
+            let meta: Option<Meta> = fs::read_to_string(&meta_path)
+                .ok()
+                .and_then(|raw| serde_json::from_str(&raw).ok())
+                .filter(|meta: &Meta| file_stem(&meta.key) == stem);
+            let blob = fs::metadata(self.path(&stem, BLOB_EXT)).ok();
+            let (Some(meta), Some(blob)) = (meta, blob) else {
+                let _ = fs::remove_file(&meta_path);
+                continue;
+            };
+            let last_used = blob
+                .modified()
+                .ok()
+                .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+                .map_or(meta.created_at, |at| at.as_millis() as u64);
+            self.entries.insert(
+                meta.key,
+                Entry {
+                    stem,
+                    size: blob.len(),
+                    created_at: meta.created_at,
+                    last_used,
+                    content_type: meta.content_type,
+                },
+            );
+        }
+        for blob in blobs {
+            let indexed = blob
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| self.entries.values().any(|entry| entry.stem == stem));
+            if !indexed {
+                let _ = fs::remove_file(&blob);
+            }
+        }
+    }
+
+    fn save_index(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let serialized = serde_json::to_string(&self.entries).map_err(io::Error::other)?;
+        write_atomic(&self.dir.join(INDEX_FILE), serialized.as_bytes())
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                let _ = fs::remove_file(self.path(&entry.stem, BLOB_EXT));
+                let _ = fs::remove_file(self.path(&entry.stem, META_EXT));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Store `bytes` under `key`. Returns the new entry and how many blobs
+    /// were evicted to make room.
+    fn write(&mut self, key: &str, bytes: &[u8], content_type: Option<String>, now: u64) -> io::Result<(BlobInfo, usize)> {
+        let stem = file_stem(key);
+        let created_at = self.entries.get(key).map_or(now, |entry| entry.created_at);
+        fs::create_dir_all(&self.dir)?;
+        write_atomic(&self.path(&stem, BLOB_EXT), bytes)?;
+        let meta = Meta {
+            key: key.to_string(),
+            created_at,
+            content_type: content_type.clone(),
+        };
+        let meta = serde_json::to_vec(&meta).map_err(io::Error::other)?;
+        write_atomic(&self.path(&stem, META_EXT), &meta)?;
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                stem,
+                size: bytes.len() as u64,
+                created_at,
+                last_used: now,
+                content_type,
+            },
+        );
+        let victims = lru_victims(&self.entries, self.max_bytes, key);
+        for victim in &victims {
+            self.remove(victim);
+        }
+        self.save_index()?;
+        Ok((info(key, &self.entries[key]), victims.len()))
+    }
+
+    /// The blob's bytes, or `None` if there is none. A blob whose file is
+    /// gone or the wrong size is dropped.
+    fn read(&mut self, key: &str, now: u64) -> Option<Vec<u8>> {
+        let entry = self.entries.get(key)?;
+        let bytes = fs::read(self.path(&entry.stem, BLOB_EXT))
+            .ok()
+            .filter(|bytes| bytes.len() as u64 == entry.size);
+        match bytes {
+            Some(bytes) => {
+                if let Some(entry) = self.entries.get_mut(key) {
+                    entry.last_used = now;
+                }
+                let _ = self.save_index();
+                Some(bytes)
+            }
+            None => {
+                self.remove(key);
+                let _ = self.save_index();
+                None
+            }
+        }
+    }
+
+    fn list(&self) -> Vec<BlobInfo> {
+        let mut blobs: Vec<BlobInfo> = self.entries.iter().map(|(key, entry)| info(key, entry)).collect();
+        blobs.sort_by(|a, b| a.key.cmp(&b.key));
+        blobs
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+}
+
+fn info(key: &str, entry: &Entry) -> BlobInfo {
+    BlobInfo {
+        key: key.to_string(),
+        size: entry.size,
+        created_at: entry.created_at,
+        last_used: entry.last_used,
+        content_type: entry.content_type.clone(),
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct BlobStoreState {
+    store: Mutex<Option<BlobStore>>,
+    evictions: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlobStoreStats {
+    entries: usize,
+    bytes: u64,
+    max_bytes: u64,
+    /// Blobs evicted to stay under `max_bytes`, this session.
+    evictions: u64,
+}
+
+fn blob_dir(app: &AppHandle) -> Result<PathBuf, DesktopError> {
+    data_dir::resolve_data_dir(app)
+        .map(|dir| dir.join(BLOB_DIR))
+        .map_err(|e| DesktopError::Internal(format!("Failed to resolve data dir: {e}")))
+}
+
+/// Run `f` on the store for the current data directory, loading it first
+/// (or again, after a data-directory migration).
+fn with_store<T>(app: &AppHandle, f: impl FnOnce(&mut BlobStore) -> T) -> Result<T, DesktopError> {
+    let dir = blob_dir(app)?;
+    let state = app
+        .try_state::<BlobStoreState>()
+        .ok_or_else(|| DesktopError::Internal("Blob store unavailable".to_string()))?;
+    let mut store = state.store.lock().unwrap_or_else(|e| e.into_inner());
+    match &mut *store {
+        Some(loaded) if loaded.dir == dir => Ok(f(loaded)),
+        slot => {
+            let (loaded, rebuilt) = BlobStore::load(dir, MAX_STORE_BYTES);
+            if rebuilt {
+                log_event(app, "WARN", "blob_index_rebuilt", &[("blobs", &loaded.entries.len().to_string())]);
+            }
+            Ok(f(slot.insert(loaded)))
+        }
+    }
+}
+
+/// Key, content type and bytes from a `write_blob` request.
+pub(crate) fn parse_write(request: &Request<'_>) -> Result<(String, Option<String>, Vec<u8>), DesktopError> {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let key = header(KEY_HEADER)
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("write_blob needs the key in an {KEY_HEADER} header")))?;
+    let content_type = header(CONTENT_TYPE_HEADER).filter(|value| !value.is_empty());
+    if content_type.as_ref().is_some_and(|value| value.len() > MAX_CONTENT_TYPE_LEN) {
+        return Err(DesktopError::InvalidArgument(format!(
+            "Blob content type is longer than {MAX_CONTENT_TYPE_LEN} characters"
+        )));
+    }
+    let InvokeBody::Raw(bytes) = request.body() else {
+        return Err(DesktopError::InvalidArgument(
+            "write_blob takes the blob as a raw body, not JSON".to_string(),
+        ));
+    };
+    Ok((key, content_type, bytes.clone()))
+}
+
+pub(crate) fn write(app: &AppHandle, key: &str, bytes: Vec<u8>, content_type: Option<String>) -> Result<BlobInfo, DesktopError> {
+    cache_limits::check_key(key)?;
+    let size = bytes.len() as u64;
+    if size > MAX_BLOB_BYTES {
+        log_event(app, "WARN", "blob_write_refused", &[("key", key), ("bytes", &size.to_string())]);
+        return Err(DesktopError::CacheEntryTooLarge(format!(
+            "Blob {key} is {size} bytes; blobs are limited to {MAX_BLOB_BYTES} bytes"
+        )));
+    }
+    let dir = blob_dir(app)?;
+    disk_space::ensure_space(app, &dir, size)?;
+    let (info, evicted) = with_store(app, |store| store.write(key, &bytes, content_type, now_ms()))?
+        .map_err(|e| DesktopError::io("Failed to write blob to", &dir, e))?;
+    if evicted > 0 {
+        app.state::<BlobStoreState>().evictions.fetch_add(evicted as u64, Ordering::Relaxed);
+        log_event(app, "INFO", "blob_store_evicted", &[("blobs", &evicted.to_string())]);
+    }
+    Ok(info)
+}
+
+pub(crate) fn read(app: &AppHandle, key: &str) -> Result<Vec<u8>, DesktopError> {
+    cache_limits::check_key(key)?;
+    with_store(app, |store| store.read(key, now_ms()))?.ok_or_else(|| DesktopError::NotFound(format!("No blob stored under {key}")))
+}
+
+/// Whether there was a blob to delete.
+pub(crate) fn delete(app: &AppHandle, key: &str) -> Result<bool, DesktopError> {
+    cache_limits::check_key(key)?;
+    with_store(app, |store| {
+        let removed = store.remove(key);
+        if removed {
+            let _ = store.save_index();
+        }
+        removed
+    })
+}
+
+pub(crate) fn list(app: &AppHandle) -> Result<Vec<BlobInfo>, DesktopError> {
+    with_store(app, |store| store.list())
+}
+
+pub(crate) fn stats(app: &AppHandle) -> BlobStoreStats {
+    let (entries, bytes) = with_store(app, |store| (store.entries.len(), store.total_bytes())).unwrap_or_default();
+    BlobStoreStats {
+        entries,
+        bytes,
+        max_bytes: MAX_STORE_BYTES,
+        evictions: app
+            .try_state::<BlobStoreState>()
+            .map_or(0, |state| state.evictions.load(Ordering::Relaxed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{file_stem, BlobStore, INDEX_FILE};
+    use crate::test_support::temp_dir;
+
+    fn keys(store: &BlobStore) -> Vec<String> {
+        let mut keys: Vec<String> = store.entries.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn least_recently_used_blobs_are_evicted_to_fit() {
+        let dir = temp_dir("blobs-lru");
+        let (mut store, _) = BlobStore::load(dir.clone(), 250);
+        store.write("tiles/a", &[1; 100], None, 1).unwrap();
+        store.write("tiles/b", &[2; 100], None, 2).unwrap();
+        assert_eq!(store.read("tiles/a", 3), Some(vec![1; 100]), "a is now the newer");
+
+        let (_, evicted) = store.write("tiles/c", &[3; 100], Some("image/png".to_string()), 4).unwrap();
+        assert_eq!(evicted, 1);
+        assert_eq!(keys(&store), ["tiles/a", "tiles/c"]);
+        assert!(!dir.join(format!("{}.blob", file_stem("tiles/b"))).exists());
+        assert!(!dir.join(format!("{}.meta", file_stem("tiles/b"))).exists());
+
+        // A blob bigger than the cap still replaces everything else.
+        let (_, evicted) = store.write("model", &[4; 300], None, 5).unwrap();
+        assert_eq!(evicted, 2);
+        assert_eq!(keys(&store), ["model"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_index_is_rebuilt_from_meta_files() {
+        let dir = temp_dir("blobs-rebuild");
+        let (mut store, rebuilt) = BlobStore::load(dir.clone(), 1024);
+        assert!(!rebuilt, "nothing to rebuild in an empty directory");
+        store.write("imagery/1", b"first", Some("image/jpeg".to_string()), 10).unwrap();
+        store.write("imagery/2", b"second", None, 20).unwrap();
+        let before = store.list();
+
+        fs::write(dir.join(INDEX_FILE), "{not json").unwrap();
+        fs::write(dir.join("0123456789abcdef0123456789abcdef.blob"), b"orphan").unwrap();
+        fs::write(dir.join(format!("{}.meta", file_stem("lost"))), br#"{"key":"lost","createdAt":1}"#).unwrap();
+
+        let (mut store, rebuilt) = BlobStore::load(dir.clone(), 1024);
+        assert!(rebuilt);
+        assert_eq!(keys(&store), ["imagery/1", "imagery/2"]);
+        let after = store.list();
+        assert_eq!(after[0].content_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(
+            after.iter().map(|blob| (blob.size, blob.created_at)).collect::<Vec<_>>(),
+            before.iter().map(|blob| (blob.size, blob.created_at)).collect::<Vec<_>>()
+        );
+        assert_eq!(store.read("imagery/2", 30).as_deref(), Some(&b"second"[..]));
+        assert!(!dir.join("0123456789abcdef0123456789abcdef.blob").exists(), "orphan blob removed");
+        assert!(!dir.join(format!("{}.meta", file_stem("lost"))).exists(), "meta without a blob removed");
+
+        // The rebuilt index was saved.
+        let (store, rebuilt) = BlobStore::load(dir.clone(), 1024);
+        assert!(!rebuilt);
+        assert_eq!(store.entries.len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_blob_missing_on_disk_is_dropped_on_read() {
+        let dir = temp_dir("blobs-missing");
+        let (mut store, _) = BlobStore::load(dir.clone(), 1024);
+        store.write("k", b"bytes", None, 1).unwrap();
+        fs::remove_file(dir.join(format!("{}.blob", file_stem("k")))).unwrap();
+        assert_eq!(store.read("k", 2), None);
+        assert!(store.entries.is_empty());
+        assert!(!store.remove("k"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
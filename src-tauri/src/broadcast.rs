@@ -0,0 +1,218 @@
+//! Cross-window events: `broadcast_event(channel, payload)` re-emits a
+//! payload as `broadcast:<channel>` to the other app windows. Channels are
+//! registered in [`CHANNELS`]; each has its own budget so a render loop
+//! stuck re-broadcasting can't flood IPC. Each broadcast is emitted once,
+//! filtered to the recipient windows: a listener targeting its own window
+//! hears only what is meant for it, while one registered globally hears
+//! every broadcast once, its own included, so the payload names the
+//! `source` window.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, EventTarget, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::rate_limit::Limit;
+
+const EVENT_PREFIX: &str = "broadcast:";
+/// Largest serialized payload, in bytes.
+const MAX_PAYLOAD_BYTES: usize = 64 * 1024;
+
+/// Every channel the frontend may broadcast on, with its budget.
+pub(crate) const CHANNELS: [(&str, Limit); 6] = [
+    ("settings-updated", Limit { rate: 5.0, burst: 20.0 }),
+    ("layout-changed", Limit { rate: 5.0, burst: 20.0 }),
+    ("alert", Limit { rate: 2.0, burst: 10.0 }),
+    ("theme-changed", Limit { rate: 2.0, burst: 5.0 }),
+    ("watchlist-changed", Limit { rate: 5.0, burst: 20.0 }),
+    ("panel-state", Limit { rate: 10.0, burst: 30.0 }),
+];
+
+pub(crate) fn find_channel(name: &str) -> Result<(&'static str, Limit), DesktopError> {
+    CHANNELS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .copied()
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("Unknown broadcast channel: {name}")))
+}
+
+/// `list_broadcast_channels` entry.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ChannelInfo {
+    name: &'static str,
+    /// Broadcasts per second, sustained.
+    rate: f64,
+    /// Broadcasts allowed back to back.
+    burst: f64,
+}
+
+pub(crate) fn channels() -> Vec<ChannelInfo> {
+    CHANNELS
+        .iter()
+        .map(|(name, limit)| ChannelInfo {
+            name,
+            rate: limit.rate,
+            burst: limit.burst,
+        })
+        .collect()
+}
+
+fn check_payload_size(payload: &Value) -> Result<(), DesktopError> {
+    let size = serde_json::to_vec(payload)
+        .map_err(|e| DesktopError::Json(format!("Broadcast payload: {e}")))?
+        .len();
+    if size > MAX_PAYLOAD_BYTES {
+        return Err(DesktopError::InvalidArgument(format!(
+            "Broadcast payload is {size} bytes; the limit is {MAX_PAYLOAD_BYTES}"
+        )));
+    }
+    Ok(())
+}
+
+/// Token bucket; over-budget broadcasts are dropped, not queued.
+#[derive(Debug)]
+struct Budget {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Budget {
+    fn new(limit: Limit, now: Instant) -> Self {
+        Budget {
+            tokens: limit.burst,
+            updated: now,
+        }
+    }
+
+    fn try_take(&mut self, limit: Limit, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Managed state: the remaining budget per channel.
+#[derive(Default)]
+pub(crate) struct BroadcastState {
+    budgets: Mutex<HashMap<&'static str, Budget>>,
+}
+
+impl BroadcastState {
+    fn admit(&self, channel: &'static str, limit: Limit, now: Instant) -> bool {
+        self.budgets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(channel)
+            .or_insert_with(|| Budget::new(limit, now))
+            .try_take(limit, now)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct Broadcast {
+    channel: &'static str,
+    source: String,
+    payload: Value,
+}
+
+/// Emit `payload` on `channel` to every trusted window, skipping `source`
+/// unless `include_source`. Returns how many windows it went to.
+pub(crate) fn broadcast(
+    app: &AppHandle,
+    source: &str,
+    channel: &str,
+    payload: Value,
+    include_source: bool,
+) -> Result<usize, DesktopError> {
+    let (channel, limit) = find_channel(channel)?;
+    check_payload_size(&payload)?;
+    if !app.state::<BroadcastState>().admit(channel, limit, Instant::now()) {
+        log_event(app, "DEBUG", "broadcast_rate_limited", &[("channel", channel), ("source", source)]);
+        return Err(DesktopError::RateLimited(format!(
+            "Too many broadcasts on {channel}; limit is {}/s",
+            limit.rate
+        )));
+    }
+    let event = format!("{EVENT_PREFIX}{channel}");
+    let message = Broadcast {
+        channel,
+        source: source.to_string(),
+        payload,
+    };
+    let recipients: Vec<String> = app
+        .webview_windows()
+        .into_keys()
+        .filter(|label| (include_source || label != source) && crate::require_trusted_window(label).is_ok())
+        .collect();
+    let addressed = |target: &EventTarget| match target {
+        EventTarget::WebviewWindow { label } | EventTarget::Webview { label } | EventTarget::Window { label } => {
+            recipients.contains(label)
+        }
+        _ => false,
+    };
+    if let Err(err) = app.emit_filter(&event, message, addressed) {
+        log_event(app, "WARN", "broadcast_emit_failed", &[("channel", channel), ("error", &err.to_string())]);
+        return Err(DesktopError::Internal(format!("Broadcast on {channel} failed: {err}")));
+    }
+    let delivered = recipients.len();
+    Ok(delivered)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::{check_payload_size, find_channel, BroadcastState, CHANNELS, MAX_PAYLOAD_BYTES};
+
+    #[test]
+    fn only_registered_channels_are_accepted() {
+        assert_eq!(find_channel("settings-updated").unwrap().0, "settings-updated");
+        for bad in ["", "Settings-Updated", "settings-navigate", "broadcast:alert"] {
+            assert_eq!(find_channel(bad).unwrap_err().code(), "invalid_argument", "{bad}");
+        }
+        let mut names: Vec<&str> = CHANNELS.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), CHANNELS.len());
+    }
+
+    #[test]
+    fn payloads_over_the_cap_are_rejected() {
+        assert!(check_payload_size(&json!({"layout": ["map", "intel"]})).is_ok());
+        let big = json!("x".repeat(MAX_PAYLOAD_BYTES));
+        assert_eq!(check_payload_size(&big).unwrap_err().code(), "invalid_argument");
+    }
+
+    #[test]
+    fn each_channel_has_its_own_budget() {
+        let state = BroadcastState::default();
+        let (alert, limit) = find_channel("alert").unwrap();
+        let (layout, layout_limit) = find_channel("layout-changed").unwrap();
+        let start = Instant::now();
+
+        let admitted = (0..100).filter(|_| state.admit(alert, limit, start)).count();
+        assert_eq!(admitted, limit.burst as usize);
+        assert!(state.admit(layout, layout_limit, start), "another channel is unaffected");
+
+        // Refills at `rate` per second, never beyond `burst`.
+        let half_second = start + Duration::from_millis(500);
+        assert!(state.admit(alert, limit, half_second));
+        assert!(!state.admit(alert, limit, half_second));
+        let later = start + Duration::from_secs(3600);
+        let admitted = (0..100).filter(|_| state.admit(alert, limit, later)).count();
+        assert_eq!(admitted, limit.burst as usize);
+    }
+}
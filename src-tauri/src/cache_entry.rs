@@ -0,0 +1,146 @@
+//! The envelope persistent-cache values are stored in:
+//! `{"$envelope": 1, "value": …, "writtenAt": <unix ms>, "sourceVersion": …}`.
+//! `write_cache_entry` stamps `writtenAt` itself and takes the optional
+//! `sourceVersion` from the caller, so the frontend can tell entries from an
+//! older app build. Values written before the envelope existed are read as
+//! they are, with no write time; both kinds live side by side until the old
+//! ones are rewritten or pruned. `read_cache_entry` still answers with the
+//! value alone; `read_cache_entry_with_meta` adds the metadata.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::DesktopError;
+use crate::logging::now_ms;
+
+const MARKER: &str = "$envelope";
+const FORMAT: u64 = 1;
+const MAX_SOURCE_VERSION_LEN: usize = 64;
+
+pub(crate) fn validate_source_version(version: &str) -> Result<(), DesktopError> {
+    if version.is_empty() || version.len() > MAX_SOURCE_VERSION_LEN || !version.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(DesktopError::InvalidArgument(format!(
+            "Cache source version must be 1 to {MAX_SOURCE_VERSION_LEN} printable ASCII characters"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn wrap(value: Value, written_at: u64, source_version: Option<String>) -> Value {
+    let mut envelope = Map::new();
+    envelope.insert(MARKER.to_string(), Value::from(FORMAT));
+    envelope.insert("value".to_string(), value);
+    envelope.insert("writtenAt".to_string(), Value::from(written_at));
+    if let Some(version) = source_version {
+        envelope.insert("sourceVersion".to_string(), Value::from(version));
+    }
+    Value::Object(envelope)
+}
+
+/// [`wrap`] with the current time.
+pub(crate) fn stamp(value: Value, source_version: Option<String>) -> Value {
+    wrap(value, now_ms(), source_version)
+}
+
+fn envelope(stored: &Value) -> Option<&Map<String, Value>> {
+    stored
+        .as_object()
+        .filter(|object| object.get(MARKER).and_then(Value::as_u64) == Some(FORMAT))
+}
+
+/// The stored value, unwrapped when it is in an envelope.
+pub(crate) fn value(stored: &Value) -> &Value {
+    envelope(stored)
+        .and_then(|envelope| envelope.get("value"))
+        .unwrap_or(stored)
+}
+
+pub(crate) fn into_value(stored: Value) -> Value {
+    match stored {
+        Value::Object(mut object) if object.get(MARKER).and_then(Value::as_u64) == Some(FORMAT) => {
+            object.remove("value").unwrap_or(Value::Null)
+        }
+        legacy => legacy,
+    }
+}
+
+/// Unix milliseconds; `None` for a value from before the envelope.
+pub(crate) fn written_at(stored: &Value) -> Option<u64> {
+    envelope(stored)?.get("writtenAt")?.as_u64()
+}
+
+fn source_version(stored: &Value) -> Option<String> {
+    Some(envelope(stored)?.get("sourceVersion")?.as_str()?.to_string())
+}
+
+/// `read_cache_entry_with_meta` result.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EntryWithMeta {
+    value: Value,
+    /// Unix milliseconds; `null` when unknown.
+    written_at: Option<u64>,
+    age_secs: Option<u64>,
+    source_version: Option<String>,
+}
+
+pub(crate) fn with_meta(stored: Value, now: u64) -> EntryWithMeta {
+    let written_at = written_at(&stored);
+    let source_version = source_version(&stored);
+    EntryWithMeta {
+        value: into_value(stored),
+        written_at,
+        age_secs: written_at.map(|at| now.saturating_sub(at) / 1000),
+        source_version,
+    }
+}
+
+/// [`with_meta`] aged from now.
+pub(crate) fn read_with_meta(stored: Value) -> EntryWithMeta {
+    with_meta(stored, now_ms())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{into_value, validate_source_version, value, with_meta, wrap, written_at};
+
+    #[test]
+    fn envelope_round_trips_value_and_metadata() {
+        let stored = wrap(json!({ "items": [1, 2] }), 1_000_000, Some("2.6.1".to_string()));
+        assert_eq!(value(&stored), &json!({ "items": [1, 2] }));
+        assert_eq!(written_at(&stored), Some(1_000_000));
+        assert_eq!(
+            serde_json::to_value(with_meta(stored, 1_720_000)).unwrap(),
+            json!({ "value": { "items": [1, 2] }, "writtenAt": 1_000_000, "ageSecs": 720, "sourceVersion": "2.6.1" })
+        );
+    }
+
+    #[test]
+    fn legacy_bare_values_read_with_unknown_age() {
+        let legacy = json!({ "key": "feed:world", "updatedAt": 5, "data": [] });
+        assert_eq!(value(&legacy), &legacy);
+        assert_eq!(written_at(&legacy), None);
+        assert_eq!(into_value(legacy.clone()), legacy);
+        let meta = with_meta(legacy.clone(), 10_000);
+        assert_eq!(
+            serde_json::to_value(meta).unwrap(),
+            json!({ "value": legacy, "writtenAt": null, "ageSecs": null, "sourceVersion": null })
+        );
+
+        // Only the marker makes an envelope; lookalikes stay as they are.
+        let lookalike = json!({ "value": 1, "writtenAt": 2 });
+        assert_eq!(value(&lookalike), &lookalike);
+        assert_eq!(into_value(json!("plain")), json!("plain"));
+        assert_eq!(into_value(wrap(json!(null), 1, None)), json!(null));
+    }
+
+    #[test]
+    fn source_version_is_short_printable_ascii() {
+        assert!(validate_source_version("2.6.1+build.7").is_ok());
+        for bad in ["", "has space", "naïve", &"9".repeat(65)] {
+            assert_eq!(validate_source_version(bad).unwrap_err().code(), "invalid_argument", "{bad:?}");
+        }
+    }
+}
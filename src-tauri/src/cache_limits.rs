@@ -0,0 +1,239 @@
+//! Guardrails for `write_cache_entry`. Every write re-serializes the whole
+//! persistent cache, so one runaway entry makes every later write slow and
+//! can exhaust the webview on read. Keys must be non-empty, at most
+//! [`MAX_KEY_CHARS`] long and made of URL characters, with no `.` or `..`
+//! segment, so a one-file-per-key layout can store them as they are. An
+//! entry larger than `cacheMaxEntryMb` (20 MB by default) is refused with
+//! `cache_entry_too_large`, and a write that would take the whole cache
+//! past `cacheMaxTotalMb` (256 MB by default) with `cache_full`. Each
+//! refusal is logged with the key and size.
+//!
+//! [`lru_victims`] picks what the blob store and HTTP cache evict to stay
+//! under their own caps.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_CACHE_MAX_ENTRY_MB: &str = "cacheMaxEntryMb";
+pub(crate) const PREF_CACHE_MAX_TOTAL_MB: &str = "cacheMaxTotalMb";
+const DEFAULT_MAX_ENTRY_MB: u64 = 20;
+const DEFAULT_MAX_TOTAL_MB: u64 = 256;
+const MAX_LIMIT_MB: u64 = 4096;
+/// Response-cache keys embed the request URL, so this is roomier than a
+/// plain name needs.
+pub(crate) const MAX_KEY_CHARS: usize = 512;
+/// Besides ASCII letters and digits: what a URL may contain unescaped.
+const KEY_PUNCTUATION: &str = "-._~:/?#[]@!$&'()*+,;=%";
+
+fn validate_mb(pref: &str, value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(mb) if (1..=MAX_LIMIT_MB).contains(&mb) => Ok(()),
+        _ => Err(format!("Runtime pref {pref} must be an integer from 1 to {MAX_LIMIT_MB}")),
+    }
+}
+
+pub(crate) fn validate_max_entry_mb(value: &Value) -> Result<(), String> {
+    validate_mb(PREF_CACHE_MAX_ENTRY_MB, value)
+}
+
+pub(crate) fn validate_max_total_mb(value: &Value) -> Result<(), String> {
+    validate_mb(PREF_CACHE_MAX_TOTAL_MB, value)
+}
+
+pub(crate) fn check_key(key: &str) -> Result<(), DesktopError> {
+    let invalid = |reason: &str| Err(DesktopError::InvalidCacheKey(format!("Invalid cache key: {reason}")));
+    if key.is_empty() {
+        return invalid("it is empty");
+    }
+    if key.chars().count() > MAX_KEY_CHARS {
+        return invalid(&format!("longer than {MAX_KEY_CHARS} characters"));
+    }
+    if let Some(bad) = key
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !KEY_PUNCTUATION.contains(*c))
+    {
+        return invalid(&format!("{bad:?} is not allowed in {key}"));
+    }
+    if key.split('/').any(|segment| segment == "." || segment == "..") {
+        return invalid(&format!("{key} has a relative path segment"));
+    }
+    Ok(())
+}
+
+/// Size limits in bytes, from prefs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Limits {
+    pub(crate) max_entry_bytes: u64,
+    pub(crate) max_total_bytes: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_entry_bytes: DEFAULT_MAX_ENTRY_MB * 1024 * 1024,
+            max_total_bytes: DEFAULT_MAX_TOTAL_MB * 1024 * 1024,
+        }
+    }
+}
+
+impl Limits {
+    pub(crate) fn from_prefs(app: &AppHandle) -> Self {
+        let defaults = Limits::default();
+        let Some(prefs) = app.try_state::<RuntimePrefs>() else {
+            return defaults;
+        };
+        let mb = |pref| prefs.get(pref).and_then(|value| value.as_u64()).map(|mb| mb * 1024 * 1024);
+        Limits {
+            max_entry_bytes: mb(PREF_CACHE_MAX_ENTRY_MB).unwrap_or(defaults.max_entry_bytes),
+            max_total_bytes: mb(PREF_CACHE_MAX_TOTAL_MB).unwrap_or(defaults.max_total_bytes),
+        }
+    }
+
+    pub(crate) fn check_entry(&self, key: &str, bytes: u64) -> Result<(), DesktopError> {
+        if bytes <= self.max_entry_bytes {
+            return Ok(());
+        }
+        Err(DesktopError::CacheEntryTooLarge(format!(
+            "Cache entry {key} is {bytes} bytes; entries are limited to {} bytes ({PREF_CACHE_MAX_ENTRY_MB})",
+            self.max_entry_bytes
+        )))
+    }
+
+    /// `total_bytes` is the serialized cache with the write applied.
+    pub(crate) fn check_total(&self, key: &str, total_bytes: u64) -> Result<(), DesktopError> {
+        if total_bytes <= self.max_total_bytes {
+            return Ok(());
+        }
+        Err(DesktopError::CacheFull(format!(
+            "Writing {key} would grow the cache to {total_bytes} bytes, over its {} byte limit \
+             ({PREF_CACHE_MAX_TOTAL_MB}); delete entries with delete_cache_entry and retry",
+            self.max_total_bytes
+        )))
+    }
+}
+
+/// Log a write refused for its size.
+pub(crate) fn log_refusal(app: &AppHandle, key: &str, bytes: u64, err: &DesktopError) {
+    if matches!(err, DesktopError::CacheEntryTooLarge(_) | DesktopError::CacheFull(_)) {
+        log_event(
+            app,
+            "WARN",
+            "cache_write_refused",
+            &[("key", key), ("bytes", &bytes.to_string()), ("code", err.code())],
+        );
+    }
+}
+
+/// An entry a size-capped store can evict by last use.
+pub(crate) trait LruEntry {
+    fn size(&self) -> u64;
+    /// Last time the entry was read or written.
+    fn last_used(&self) -> u64;
+}
+
+/// Least recently used keys to drop so the rest fit in `max_bytes`. `keep`
+/// (the entry just written) is never chosen.
+pub(crate) fn lru_victims<E: LruEntry>(entries: &HashMap<String, E>, max_bytes: u64, keep: &str) -> Vec<String> {
+    let mut total: u64 = entries.values().map(LruEntry::size).sum();
+    let mut candidates: Vec<(&String, &E)> = entries.iter().filter(|(key, _)| key.as_str() != keep).collect();
+    candidates.sort_by(|(a_key, a), (b_key, b)| a.last_used().cmp(&b.last_used()).then_with(|| a_key.cmp(b_key)));
+    let mut victims = Vec::new();
+    for (key, entry) in candidates {
+        if total <= max_bytes {
+            break;
+        }
+        total -= entry.size();
+        victims.push(key.clone());
+    }
+    victims
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{check_key, lru_victims, validate_max_entry_mb, Limits, LruEntry, MAX_KEY_CHARS};
+
+    /// `(size, last_used)`.
+    struct Sized(u64, u64);
+
+    impl LruEntry for Sized {
+        fn size(&self) -> u64 {
+            self.0
+        }
+
+        fn last_used(&self) -> u64 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn keys_in_use_are_accepted() {
+        for key in [
+            "summary:world-brief",
+            "risk-scores:latest",
+            "feed:world/europe",
+            "api-response:/api/rss-proxy?url=https%3A%2F%2Fexample.com%2Ffeed.xml&limit=20",
+            "offline:0f3a",
+        ] {
+            assert!(check_key(key).is_ok(), "{key}");
+        }
+    }
+
+    #[test]
+    fn bad_keys_are_invalid_cache_key() {
+        let too_long = "k".repeat(MAX_KEY_CHARS + 1);
+        for key in ["", too_long.as_str(), "feed:a b", "feed\\x", "naïve", "line\nbreak", "../prefs", "feed/./x", ".."] {
+            let err = check_key(key).unwrap_err();
+            assert_eq!(err.code(), "invalid_cache_key", "{key:?}");
+        }
+        assert!(check_key(&"k".repeat(MAX_KEY_CHARS)).is_ok());
+    }
+
+    #[test]
+    fn entry_and_total_limits_have_their_own_codes() {
+        let limits = Limits {
+            max_entry_bytes: 100,
+            max_total_bytes: 1000,
+        };
+        assert!(limits.check_entry("k", 100).is_ok());
+        let err = limits.check_entry("k", 101).unwrap_err();
+        assert_eq!(err.code(), "cache_entry_too_large");
+        assert!(err.to_string().contains("101 bytes"));
+
+        assert!(limits.check_total("k", 1000).is_ok());
+        let err = limits.check_total("k", 1001).unwrap_err();
+        assert_eq!(err.code(), "cache_full");
+        assert!(err.to_string().contains("delete_cache_entry"));
+    }
+
+    #[test]
+    fn defaults_and_pref_validation() {
+        assert_eq!(Limits::default().max_entry_bytes, 20 * 1024 * 1024);
+        assert!(validate_max_entry_mb(&json!(64)).is_ok());
+        assert!(validate_max_entry_mb(&json!(0)).is_err());
+        assert!(validate_max_entry_mb(&json!("20")).is_err());
+    }
+
+    #[test]
+    fn eviction_drops_least_recently_used_until_under_budget() {
+        let entries = HashMap::from([
+            ("old".to_string(), Sized(40, 1)),
+            ("mid".to_string(), Sized(40, 2)),
+            ("new".to_string(), Sized(40, 3)),
+        ]);
+        assert!(lru_victims(&entries, 120, "new").is_empty());
+        assert_eq!(lru_victims(&entries, 100, "new"), vec!["old".to_string()]);
+        assert_eq!(lru_victims(&entries, 40, "new"), vec!["old".to_string(), "mid".to_string()]);
+        // The entry just written survives even if it is the oldest.
+        assert_eq!(lru_victims(&entries, 80, "old"), vec!["mid".to_string()]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Command-line flags, parsed at the top of `main` before anything else runs.
+//! Desktop environments and launchers sometimes append arguments of their own
+//! (macOS `-psn_*`, `.desktop` `%U` deep links), so unknown or malformed flags
+//! are collected as warnings instead of aborting the launch.
+
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::logging::LogLevel;
+use crate::profile;
+
+pub(crate) const USAGE: &str = "\
+Usage: world-monitor [OPTIONS] [worldmonitor://LINK]
+
+Options:
+  --settings           Open the settings window once the app is ready
+  --start-minimized    Start with the main window minimized (or in the tray)
+  --no-onboarding      Don't open settings automatically on a fresh install
+  --headless           Run only the local API: no windows, port and token on stdout
+  --listen ADDR        Address the headless local API binds (default 127.0.0.1)
+  --safe-mode          Force the conservative renderer settings for this launch
+  --port N             Preferred local API port for this session
+  --log-level LEVEL    Minimum desktop.log level: debug, info, warn, error
+  --profile NAME       Separate keychain vault, data and logs for this instance
+  --version            Print the version and exit
+  --help               Print this help and exit";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct CliOptions {
+    pub(crate) open_settings: bool,
+    pub(crate) start_minimized: bool,
+    pub(crate) no_onboarding: bool,
+    pub(crate) safe_mode: bool,
+    pub(crate) headless: bool,
+    /// Bind address for the sidecar; only honoured together with `--headless`.
+    pub(crate) listen: Option<IpAddr>,
+    pub(crate) port: Option<u16>,
+    pub(crate) log_level: Option<LogLevel>,
+    pub(crate) profile: Option<String>,
+    pub(crate) print_version: bool,
+    pub(crate) print_help: bool,
+    /// Ignored arguments, logged once desktop.log is available.
+    pub(crate) warnings: Vec<String>,
+}
+
+/// Parse `args` (without the program name). Accepts `--flag value` and
+/// `--flag=value`; positional arguments such as deep links are left alone.
+pub(crate) fn parse_cli_args<I>(args: I) -> CliOptions
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = CliOptions::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            continue;
+        }
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+        let takes_value = matches!(flag.as_str(), "--port" | "--listen" | "--log-level" | "--profile");
+        if !takes_value {
+            if inline_value.is_some() {
+                options.warnings.push(format!("{flag} does not take a value; ignoring {arg}"));
+                continue;
+            }
+            match flag.as_str() {
+                "--settings" => options.open_settings = true,
+                "--start-minimized" => options.start_minimized = true,
+                "--no-onboarding" => options.no_onboarding = true,
+                "--safe-mode" => options.safe_mode = true,
+                "--headless" => options.headless = true,
+                "--version" | "-V" => options.print_version = true,
+                "--help" | "-h" => options.print_help = true,
+                _ => options.warnings.push(format!("Unknown argument {arg}")),
+            }
+            continue;
+        }
+
+        let Some(value) = inline_value.or_else(|| args.next()) else {
+            options.warnings.push(format!("{flag} requires a value"));
+            continue;
+        };
+        match flag.as_str() {
+            "--port" => match value.parse::<u16>() {
+                Ok(port) if port > 0 => options.port = Some(port),
+                _ => options.warnings.push(format!("Invalid --port value {value:?}")),
+            },
+            "--listen" => match value.parse::<IpAddr>() {
+                Ok(addr) => options.listen = Some(addr),
+                Err(_) => options.warnings.push(format!("Invalid --listen address {value:?}")),
+            },
+            "--log-level" => match LogLevel::parse(&value) {
+                Some(level) => options.log_level = Some(level),
+                None => options.warnings.push(format!("Invalid --log-level value {value:?}")),
+            },
+            "--profile" => match profile::validate_name(&value) {
+                Ok(()) => options.profile = Some(value),
+                Err(err) => options.warnings.push(err),
+            },
+            _ => unreachable!("takes_value covers only the flags matched here"),
+        }
+    }
+    if !options.headless {
+        if let Some(addr) = options.listen.take() {
+            options.warnings.push(format!("--listen {addr} only applies with --headless"));
+        }
+    } else if options.open_settings {
+        options.open_settings = false;
+        options.warnings.push("--settings has no effect with --headless".to_string());
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cli_args, CliOptions};
+    use crate::logging::LogLevel;
+
+    fn parse(args: &[&str]) -> CliOptions {
+        parse_cli_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_combined_flags() {
+        let options = parse(&["--settings", "--port", "47000", "--log-level=debug", "--safe-mode"]);
+        assert!(options.open_settings);
+        assert!(options.safe_mode);
+        assert_eq!(options.port, Some(47000));
+        assert_eq!(options.log_level, Some(LogLevel::Debug));
+        assert!(options.warnings.is_empty());
+
+        let options = parse(&["--port=46200", "worldmonitor://panel/markets", "--version", "--start-minimized", "--no-onboarding"]);
+        assert_eq!(options.port, Some(46200));
+        assert!(options.start_minimized);
+        assert!(options.no_onboarding);
+        assert!(options.print_version);
+        assert!(options.warnings.is_empty());
+
+        let options = parse(&["--profile", "dev", "--profile=../prod"]);
+        assert_eq!(options.profile.as_deref(), Some("dev"));
+        assert!(options.warnings[0].contains("Invalid --profile"));
+    }
+
+    #[test]
+    fn malformed_and_unknown_arguments_warn_without_aborting() {
+        let options = parse(&["-psn_0_12345", "--port", "99999", "--log-level", "loud", "--settings=yes", "--port"]);
+        assert_eq!(options.port, None);
+        assert_eq!(options.log_level, None);
+        assert!(!options.open_settings);
+        assert_eq!(options.warnings.len(), 5);
+        assert!(options.warnings[0].contains("-psn_0_12345"));
+        assert!(options.warnings[4].contains("requires a value"));
+
+        // A flag value is consumed even if it looks like a flag.
+        let options = parse(&["--log-level", "--settings"]);
+        assert!(!options.open_settings);
+        assert_eq!(options.warnings.len(), 1);
+    }
+
+    #[test]
+    fn listen_requires_headless() {
+        let options = parse(&["--headless", "--listen", "0.0.0.0", "--port=47000"]);
+        assert!(options.headless);
+        assert_eq!(options.listen, Some("0.0.0.0".parse().unwrap()));
+        assert!(options.warnings.is_empty());
+
+        let options = parse(&["--listen=0.0.0.0"]);
+        assert_eq!(options.listen, None);
+        assert!(options.warnings[0].contains("only applies with --headless"));
+
+        let options = parse(&["--headless", "--listen", "lan", "--settings"]);
+        assert_eq!(options.listen, None);
+        assert!(!options.open_settings);
+        assert_eq!(options.warnings.len(), 2);
+    }
+}
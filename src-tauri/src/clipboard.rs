@@ -0,0 +1,150 @@
+//! Clipboard writes for the settings UI. Sensitive copies (API keys) are
+//! cleared again after a delay, but only if the clipboard still holds exactly
+//! what we put there, so something the user copied in the meantime survives.
+//!
+//! Access goes through tauri-plugin-clipboard-manager. On Windows, sensitive
+//! copies are kept out of clipboard history and cloud sync, which the plugin
+//! has no option for, so those are written with arboard (the plugin's own
+//! backend) directly. Copied text is never logged; only its length is.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+
+const DEFAULT_SENSITIVE_CLEAR_SECS: u32 = 30;
+const MAX_CLEAR_SECS: u32 = 3_600;
+const MAX_TEXT_BYTES: usize = 1024 * 1024;
+
+/// A scheduled clear. Each copy bumps the generation, so an older timer
+/// never clears a newer copy of the same text early.
+struct PendingClear {
+    generation: u64,
+    text: String,
+}
+
+#[derive(Default)]
+pub(crate) struct ClipboardState {
+    generation: Mutex<u64>,
+    pending: Mutex<Option<PendingClear>>,
+}
+
+/// Whether a scheduled clear may wipe the clipboard: only while it still
+/// holds exactly the text we copied.
+fn should_clear(current: Option<&str>, copied: &str) -> bool {
+    current.is_some_and(|current| current == copied)
+}
+
+#[cfg(windows)]
+fn write(app: &AppHandle, text: &str, sensitive: bool) -> Result<(), String> {
+    use arboard::SetExtWindows;
+
+    if !sensitive {
+        return app.clipboard().write_text(text).map_err(|e| e.to_string());
+    }
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    // Covers clipboard history and cloud upload as well as other monitors.
+    clipboard.set().exclude_from_monitoring().text(text).map_err(|e| e.to_string())
+}
+
+#[cfg(not(windows))]
+fn write(app: &AppHandle, text: &str, _sensitive: bool) -> Result<(), String> {
+    app.clipboard().write_text(text).map_err(|e| e.to_string())
+}
+
+pub(crate) fn copy(
+    app: &AppHandle,
+    text: String,
+    sensitive: bool,
+    clear_after_secs: Option<u32>,
+) -> Result<(), DesktopError> {
+    if text.len() > MAX_TEXT_BYTES {
+        return Err(DesktopError::InvalidArgument("Text is too large to copy".to_string()));
+    }
+    let clear_after = match clear_after_secs {
+        Some(0) => return Err(DesktopError::InvalidArgument("clear_after_secs must be positive".to_string())),
+        Some(secs) => Some(secs.min(MAX_CLEAR_SECS)),
+        None if sensitive => Some(DEFAULT_SENSITIVE_CLEAR_SECS),
+        None => None,
+    };
+    write(app, &text, sensitive).map_err(DesktopError::Internal)?;
+
+    let state = app.state::<ClipboardState>();
+    let generation = {
+        let mut generation = state.generation.lock().unwrap_or_else(|e| e.into_inner());
+        *generation += 1;
+        *generation
+    };
+    let length = text.chars().count().to_string();
+    *state.pending.lock().unwrap_or_else(|e| e.into_inner()) =
+        clear_after.map(|_| PendingClear { generation, text });
+    log_event(
+        app,
+        "INFO",
+        "clipboard_copied",
+        &[
+            ("chars", &length),
+            ("sensitive", if sensitive { "true" } else { "false" }),
+            ("clear_after_secs", &clear_after.map_or("never".to_string(), |secs| secs.to_string())),
+        ],
+    );
+    if let Some(secs) = clear_after {
+        let handle = app.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(u64::from(secs)));
+            clear_if_unchanged(&handle, generation);
+        });
+    }
+    Ok(())
+}
+
+fn clear_if_unchanged(app: &AppHandle, generation: u64) {
+    let state = app.state::<ClipboardState>();
+    let mut pending = state.pending.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(clear) = pending.take_if(|clear| clear.generation == generation) else {
+        return;
+    };
+    if !should_clear(app.clipboard().read_text().ok().as_deref(), &clear.text) {
+        log_event(app, "INFO", "clipboard_clear_skipped", &[("reason", "replaced")]);
+        return;
+    }
+    match app.clipboard().clear() {
+        Ok(()) => log_event(app, "INFO", "clipboard_cleared", &[("reason", "timer")]),
+        Err(err) => log_event(app, "WARN", "clipboard_clear_failed", &[("error", &err.to_string())]),
+    }
+}
+
+/// Empty the clipboard now and cancel any scheduled clear.
+pub(crate) fn clear(app: &AppHandle) -> Result<(), DesktopError> {
+    app.state::<ClipboardState>()
+        .pending
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    app.clipboard()
+        .clear()
+        .map_err(|e| DesktopError::Internal(format!("Failed to clear the clipboard: {e}")))?;
+    log_event(app, "INFO", "clipboard_cleared", &[("reason", "manual")]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::should_clear;
+
+    #[test]
+    fn clears_only_the_exact_value_we_copied() {
+        assert!(should_clear(Some("sk-test-123"), "sk-test-123"));
+        assert!(!should_clear(Some("something else"), "sk-test-123"));
+        assert!(!should_clear(Some("sk-test-123\n"), "sk-test-123"));
+        assert!(!should_clear(Some("sk-test-12"), "sk-test-123"));
+        assert!(!should_clear(Some(""), "sk-test-123"));
+        // Unreadable clipboard: leave it alone.
+        assert!(!should_clear(None, "sk-test-123"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Local clock versus the world. Signed upstream requests (and Convex) are
+//! rejected when the clock is minutes off, which users read as bad keys. A
+//! HEAD to the update host shortly after startup and every few hours
+//! compares its `Date` header with local time; the skew is kept here, sent
+//! to the sidecar for its auth-failure logs, and `clock-skew-warning` fires
+//! when it crosses `clockSkewWarnSecs` (60 s by default). A failed request
+//! leaves the skew unknown and never warns. The checks run as a
+//! [`maintenance`](crate::maintenance) task.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::extra_ca;
+use crate::logging::{log_event, now_iso8601};
+use crate::maintenance::TaskSpec;
+use crate::{post_to_local_api, update_manifest_url, user_agent, RuntimePrefs};
+
+pub(crate) const PREF_CLOCK_SKEW_WARN_SECS: &str = "clockSkewWarnSecs";
+pub(crate) const CLOCK_SKEW_ENV: &str = "LOCAL_API_CLOCK_SKEW_MS";
+const DEFAULT_WARN_SECS: u64 = 60;
+const WARNING_EVENT: &str = "clock-skew-warning";
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(30);
+const RECHECK_INTERVAL: Duration = Duration::from_secs(4 * 3600);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One measurement. Positive `skew_ms` means the local clock is ahead.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct ClockSkew {
+    pub(crate) skew_ms: i64,
+    /// Request round trip; the measurement is good to about half of it.
+    pub(crate) round_trip_ms: u64,
+    pub(crate) measured_at: String,
+}
+
+/// `get_clock_skew` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ClockSkewReport {
+    /// `None` until a measurement succeeds.
+    last: Option<ClockSkew>,
+    threshold_ms: u64,
+    pub(crate) exceeds_threshold: bool,
+}
+
+/// Managed state: the last good measurement.
+#[derive(Default)]
+pub(crate) struct ClockSkewState {
+    last: Mutex<Option<ClockSkew>>,
+    over_threshold: AtomicBool,
+}
+
+impl ClockSkewState {
+    pub(crate) fn last(&self) -> Option<ClockSkew> {
+        self.last.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+pub(crate) fn validate_warn_secs(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(secs) if secs > 0 => Ok(()),
+        _ => Err(format!("Runtime pref {PREF_CLOCK_SKEW_WARN_SECS} must be a positive integer")),
+    }
+}
+
+fn threshold_ms(app: &AppHandle) -> u64 {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_CLOCK_SKEW_WARN_SECS))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_WARN_SECS)
+        .saturating_mul(1000)
+}
+
+fn exceeds(skew_ms: i64, threshold_ms: u64) -> bool {
+    skew_ms.unsigned_abs() > threshold_ms
+}
+
+/// Edge-triggered: true only when a measurement first goes over, re-armed
+/// once one comes back under.
+fn crossed(over: &AtomicBool, skew_ms: i64, threshold_ms: u64) -> bool {
+    let now_over = exceeds(skew_ms, threshold_ms);
+    let was_over = over.swap(now_over, Ordering::Relaxed);
+    now_over && !was_over
+}
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Unix seconds from an RFC 9110 `Date` (IMF-fixdate,
+/// `Sun, 06 Nov 1994 08:49:37 GMT`); the obsolete forms aren't sent by
+/// anything we talk to.
+fn parse_http_date(value: &str) -> Option<i64> {
+    let rest = value.trim().split_once(", ")?.1;
+    let parts: Vec<&str> = rest.split(' ').collect();
+    let [day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let month = MONTHS.iter().position(|m| m == month)? as u32 + 1;
+    let date = chrono::NaiveDate::from_ymd_opt(year.parse().ok()?, month, day.parse().ok()?)?;
+    let mut hms = time.split(':').map(|n| n.parse::<u32>().ok());
+    let (Some(Some(h)), Some(Some(m)), Some(Some(s)), None) = (hms.next(), hms.next(), hms.next(), hms.next()) else {
+        return None;
+    };
+    Some(date.and_hms_opt(h, m, s)?.and_utc().timestamp())
+}
+
+/// Local minus server time. The server stamped its `Date` somewhere in the
+/// round trip, so it is compared with the local midpoint; the header is
+/// truncated to the second, so it is read as the middle of that second.
+fn skew_ms(sent_unix_ms: i64, round_trip: Duration, server_unix_secs: i64) -> i64 {
+    let local_mid = sent_unix_ms + (round_trip.as_millis() / 2) as i64;
+    local_mid - (server_unix_secs * 1000 + 500)
+}
+
+async fn measure(app: &AppHandle) -> Result<ClockSkew, String> {
+    let url = update_manifest_url();
+    let client = extra_ca::client_builder(app)
+        .map_err(|e| e.to_string())?
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(user_agent::effective(app))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let sent_unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_millis() as i64;
+    let started = Instant::now();
+    let response = client.head(&url).send().await.map_err(|e| e.to_string())?;
+    let round_trip = started.elapsed();
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| format!("{url} sent no Date header"))?;
+    let server = parse_http_date(date).ok_or_else(|| format!("Unparseable Date header: {date}"))?;
+    Ok(ClockSkew {
+        skew_ms: skew_ms(sent_unix_ms, round_trip, server),
+        round_trip_ms: round_trip.as_millis() as u64,
+        measured_at: now_iso8601(),
+    })
+}
+
+/// Measure now and record the result. `None` when the network failed; the
+/// previous measurement, if any, is kept.
+pub(crate) async fn refresh(app: &AppHandle) -> Option<ClockSkew> {
+    let skew = match measure(app).await {
+        Ok(skew) => skew,
+        Err(err) => {
+            log_event(app, "DEBUG", "clock_skew_unknown", &[("error", &err)]);
+            return None;
+        }
+    };
+    let state = app.state::<ClockSkewState>();
+    *state.last.lock().unwrap_or_else(|e| e.into_inner()) = Some(skew.clone());
+    let threshold = threshold_ms(app);
+    let level = if exceeds(skew.skew_ms, threshold) { "WARN" } else { "INFO" };
+    log_event(
+        app,
+        level,
+        "clock_skew_measured",
+        &[
+            ("skew_ms", &skew.skew_ms.to_string()),
+            ("round_trip_ms", &skew.round_trip_ms.to_string()),
+        ],
+    );
+    if crossed(&state.over_threshold, skew.skew_ms, threshold) {
+        let _ = app.emit(WARNING_EVENT, report(app));
+    }
+    let body = serde_json::json!({ "skewMs": skew.skew_ms });
+    if let Err(err) = post_to_local_api(app, "/api/local-clock-skew", body).await {
+        log_event(app, "DEBUG", "clock_skew_push_failed", &[("error", &err)]);
+    }
+    Some(skew)
+}
+
+pub(crate) fn report(app: &AppHandle) -> ClockSkewReport {
+    let last = app.try_state::<ClockSkewState>().and_then(|state| state.last());
+    let threshold_ms = threshold_ms(app);
+    ClockSkewReport {
+        exceeds_threshold: last.as_ref().is_some_and(|skew| exceeds(skew.skew_ms, threshold_ms)),
+        last,
+        threshold_ms,
+    }
+}
+
+/// For the sidecar's environment at launch, when a skew is known.
+pub(crate) fn env_value(app: &AppHandle) -> Option<String> {
+    app.try_state::<ClockSkewState>()?.last().map(|skew| skew.skew_ms.to_string())
+}
+
+/// Measure shortly after startup, then every few hours.
+pub(crate) const MAINTENANCE: TaskSpec = TaskSpec {
+    id: "clock_skew",
+    interval: RECHECK_INTERVAL,
+    jitter: Duration::from_secs(10 * 60),
+    first_delay: FIRST_CHECK_DELAY,
+    skip_while_paused: true,
+    run: |app| {
+        Box::pin(async move {
+            match refresh(&app).await {
+                Some(_) => Ok(()),
+                None => Err("Couldn't reach the update host to measure the clock".to_string()),
+            }
+        })
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::{crossed, parse_http_date, skew_ms, validate_warn_secs};
+
+    #[test]
+    fn parses_imf_fixdate() {
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse_http_date("Sat, 18 Oct 2026 00:00:00 GMT"), Some(1_792_281_600));
+        for bad in [
+            "",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            "Sun, 06 Nov 1994 08:49:37 PST",
+            "Sun, 31 Feb 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+        ] {
+            assert_eq!(parse_http_date(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn skew_compares_against_the_round_trip_midpoint() {
+        // Sent at 1000.000 s, 400 ms round trip, server said 1000 s: in sync.
+        assert_eq!(skew_ms(1_000_000, Duration::from_millis(400), 1000), -300);
+        // Local clock two minutes ahead.
+        assert_eq!(skew_ms(1_120_300, Duration::from_millis(400), 1000), 120_000);
+        assert_eq!(skew_ms(880_300, Duration::from_millis(400), 1000), -120_000);
+    }
+
+    #[test]
+    fn warns_once_per_excursion_either_way() {
+        let over = AtomicBool::new(false);
+        assert!(!crossed(&over, 5_000, 60_000));
+        assert!(crossed(&over, 90_000, 60_000));
+        assert!(!crossed(&over, 95_000, 60_000));
+        assert!(!crossed(&over, 1_000, 60_000));
+        assert!(crossed(&over, -61_000, 60_000));
+    }
+
+    #[test]
+    fn pref_is_a_positive_integer() {
+        assert!(validate_warn_secs(&json!(60)).is_ok());
+        assert!(validate_warn_secs(&json!(0)).is_err());
+        assert!(validate_warn_secs(&json!("60")).is_err());
+    }
+}
@@ -0,0 +1,552 @@
+//! A whole configuration in one file, for handing a team's setup to a new
+//! analyst: the user-settable runtime prefs, the data-source toggles, the
+//! named window layouts and, on request, the vault. The envelope carries
+//! [`BUNDLE_VERSION`] so an import refuses a layout it doesn't understand.
+//!
+//! A bundle with secrets is always sealed with a passphrase: PBKDF2-SHA256
+//! stretches it into an AES-256-GCM key, so a wrong passphrase or a modified
+//! file fails authentication before anything is used. The iteration count
+//! read from a file is bounded, since it decides how long an import spends
+//! in the KDF. Imports go through the same setters as the settings window,
+//! so a changed pref still restarts the sidecar or republishes the source
+//! list; the few prefs that decide what the app runs or trusts are only
+//! imported when the user confirms each one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::extra_ca::PREF_EXTRA_CA_CERTIFICATES;
+use crate::logging::{log_event, now_iso8601};
+use crate::sidecar_options::{PREF_SIDECAR_EXTRA_ENV, PREF_SIDECAR_NODE_ARGS};
+use crate::trusted_hosts::PREF_TRUSTED_LOCAL_HOSTS;
+use crate::window_layout::{self, Layout};
+use crate::{
+    apply_runtime_pref_change, data_sources, require_supported_secret_key, store_runtime_pref, update_vault,
+    validate_runtime_pref, RuntimePrefs, SecretsCache, SettingsSessionState, PREF_ALLOWED_URL_SCHEMES,
+};
+
+const BUNDLE_FORMAT: &str = "worldmonitor-config";
+pub(crate) const BUNDLE_VERSION: u32 = 1;
+const KDF: &str = "pbkdf2-sha256";
+const CIPHER: &str = "aes-256-gcm";
+const KDF_ITERATIONS: u32 = 600_000;
+/// What an imported bundle may ask for.
+const MIN_KDF_ITERATIONS: u32 = 10_000;
+const MAX_KDF_ITERATIONS: u32 = 2_000_000;
+/// Prefs that change what code the sidecar runs or what the app trusts. A
+/// shared bundle only sets these when the user confirms each one.
+const SENSITIVE_PREFS: [&str; 5] = [
+    PREF_SIDECAR_NODE_ARGS,
+    PREF_SIDECAR_EXTRA_ENV,
+    PREF_EXTRA_CA_CERTIFICATES,
+    PREF_ALLOWED_URL_SCHEMES,
+    PREF_TRUSTED_LOCAL_HOSTS,
+];
+
+/// What a bundle holds. Secrets only ever leave here sealed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConfigBundle {
+    #[serde(default)]
+    prefs: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_sources: Option<Value>,
+    #[serde(default)]
+    layouts: BTreeMap<String, Layout>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secrets: Option<BTreeMap<String, String>>,
+}
+
+/// The file: plain `config`, or the same JSON `sealed`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    exported_at: String,
+    #[serde(default)]
+    app_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<ConfigBundle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<Sealed>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Sealed {
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    /// Includes the GCM tag.
+    ciphertext: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MergeStrategy {
+    /// Bundle values win; anything the bundle doesn't mention is kept.
+    #[default]
+    Merge,
+    /// Each section in the bundle replaces the current one outright.
+    Replace,
+}
+
+/// `export_app_config` result.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ExportSummary {
+    path: String,
+    sections: Vec<&'static str>,
+    encrypted: bool,
+}
+
+/// One section of an import.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct SectionResult {
+    /// Not in the bundle, so nothing was touched.
+    skipped: bool,
+    applied: Vec<String>,
+    /// Keys reset or deleted under [`MergeStrategy::Replace`].
+    removed: Vec<String>,
+    /// Key and why it was refused.
+    rejected: Vec<(String, String)>,
+}
+
+/// `import_app_config` result.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct ImportReport {
+    prefs: SectionResult,
+    data_sources: SectionResult,
+    layouts: SectionResult,
+    secrets: SectionResult,
+}
+
+fn skipped() -> SectionResult {
+    SectionResult {
+        skipped: true,
+        ..Default::default()
+    }
+}
+
+fn invalid(message: impl Into<String>) -> DesktopError {
+    DesktopError::InvalidArgument(message.into())
+}
+
+fn cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    Aes256Gcm::new(&key.into())
+}
+
+fn seal(plaintext: &[u8], passphrase: &str, iterations: u32) -> Result<Sealed, DesktopError> {
+    let (mut salt, mut nonce) = ([0u8; 16], [0u8; 12]);
+    getrandom::getrandom(&mut salt)
+        .and_then(|()| getrandom::getrandom(&mut nonce))
+        .map_err(|e| DesktopError::Internal(format!("No randomness for encryption: {e}")))?;
+    let ciphertext = cipher(passphrase, &salt, iterations)
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|e| DesktopError::Internal(format!("Failed to encrypt configuration: {e}")))?;
+    Ok(Sealed {
+        kdf: KDF.to_string(),
+        iterations,
+        salt: BASE64.encode(salt),
+        cipher: CIPHER.to_string(),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn unseal(sealed: &Sealed, passphrase: &str) -> Result<Vec<u8>, DesktopError> {
+    if sealed.kdf != KDF || sealed.cipher != CIPHER {
+        return Err(invalid(format!("Unsupported bundle encryption: {} with {}", sealed.kdf, sealed.cipher)));
+    }
+    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&sealed.iterations) {
+        return Err(invalid(format!(
+            "Unsupported bundle encryption: {} iterations, expected {MIN_KDF_ITERATIONS} to {MAX_KDF_ITERATIONS}",
+            sealed.iterations
+        )));
+    }
+    let damaged = || invalid("The bundle's encrypted section is damaged");
+    let decode = |field: &str| BASE64.decode(field).map_err(|_| damaged());
+    let (salt, nonce, ciphertext) = (decode(&sealed.salt)?, decode(&sealed.nonce)?, decode(&sealed.ciphertext)?);
+    if nonce.len() != 12 {
+        return Err(damaged());
+    }
+    cipher(passphrase, &salt, sealed.iterations)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| invalid("Wrong passphrase, or the bundle was modified"))
+}
+
+fn usable_passphrase(passphrase: Option<&str>) -> Option<&str> {
+    passphrase.filter(|p| !p.is_empty())
+}
+
+/// Serialize `bundle`, sealed when a passphrase is given. Secrets without
+/// one are refused rather than written in the clear.
+fn encode(bundle: &ConfigBundle, passphrase: Option<&str>, app_version: &str, iterations: u32) -> Result<String, DesktopError> {
+    let passphrase = usable_passphrase(passphrase);
+    if bundle.secrets.is_some() && passphrase.is_none() {
+        return Err(invalid("Exporting secrets requires a passphrase"));
+    }
+    let mut envelope = Envelope {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: now_iso8601(),
+        app_version: app_version.to_string(),
+        config: None,
+        sealed: None,
+    };
+    match passphrase {
+        Some(passphrase) => {
+            let plaintext = serde_json::to_vec(bundle)
+                .map_err(|e| DesktopError::Json(format!("Failed to serialize configuration: {e}")))?;
+            envelope.sealed = Some(seal(&plaintext, passphrase, iterations)?);
+        }
+        None => envelope.config = Some(bundle.clone()),
+    }
+    serde_json::to_string_pretty(&envelope)
+        .map_err(|e| DesktopError::Json(format!("Failed to serialize configuration: {e}")))
+}
+
+fn decode(raw: &str, passphrase: Option<&str>) -> Result<ConfigBundle, DesktopError> {
+    let envelope: Envelope =
+        serde_json::from_str(raw).map_err(|e| invalid(format!("Not a configuration bundle: {e}")))?;
+    if envelope.format != BUNDLE_FORMAT {
+        return Err(invalid(format!("Not a configuration bundle: format is {:?}", envelope.format)));
+    }
+    if envelope.version != BUNDLE_VERSION {
+        return Err(invalid(format!(
+            "Configuration bundle version {} is not supported; this build reads version {BUNDLE_VERSION}",
+            envelope.version
+        )));
+    }
+    match (envelope.config, envelope.sealed) {
+        (Some(config), None) => {
+            if config.secrets.is_some() {
+                return Err(invalid("The bundle has unencrypted secrets; refusing to import it"));
+            }
+            Ok(config)
+        }
+        (None, Some(sealed)) => {
+            let passphrase = usable_passphrase(passphrase).ok_or_else(|| invalid("This bundle is encrypted; a passphrase is required"))?;
+            let plaintext = unseal(&sealed, passphrase)?;
+            serde_json::from_slice(&plaintext).map_err(|e| invalid(format!("The bundle's encrypted section is damaged: {e}")))
+        }
+        _ => Err(invalid("A configuration bundle holds exactly one of config or sealed")),
+    }
+}
+
+fn require_json_path(path: &str) -> Result<PathBuf, DesktopError> {
+    let path = PathBuf::from(path.trim());
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(path),
+        _ => Err(invalid(format!("Configuration path must end in .json: {}", path.display()))),
+    }
+}
+
+/// Prefs a user can set; the shell's own bookkeeping (window geometry and
+/// the like) fails validation and stays on this machine.
+fn exportable_prefs(app: &AppHandle) -> Map<String, Value> {
+    let prefs = app.state::<RuntimePrefs>();
+    let prefs = prefs.prefs.lock().unwrap_or_else(|e| e.into_inner());
+    prefs
+        .iter()
+        .filter(|(key, value)| key.as_str() != data_sources::PREF_DATA_SOURCES && validate_runtime_pref(key, value).is_ok())
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+pub(crate) fn export(app: &AppHandle, path: &str, include_secrets: bool, passphrase: Option<&str>) -> Result<ExportSummary, DesktopError> {
+    let path = require_json_path(path)?;
+    let secrets = include_secrets.then(|| {
+        let cache = app.state::<SecretsCache>();
+        let secrets = cache.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        secrets.iter().map(|(key, value)| (key.clone(), value.clone())).collect()
+    });
+    let bundle = ConfigBundle {
+        prefs: exportable_prefs(app),
+        data_sources: app.state::<RuntimePrefs>().get(data_sources::PREF_DATA_SOURCES),
+        layouts: window_layout::named_layouts(app),
+        secrets,
+    };
+    let encoded = encode(&bundle, passphrase, &app.package_info().version.to_string(), KDF_ITERATIONS)?;
+    fs::write(&path, encoded).map_err(|e| DesktopError::io("Failed to write configuration", &path, e))?;
+    let mut sections = vec!["prefs", "layouts"];
+    if bundle.data_sources.is_some() {
+        sections.push("data_sources");
+    }
+    if bundle.secrets.is_some() {
+        sections.push("secrets");
+    }
+    let encrypted = usable_passphrase(passphrase).is_some();
+    log_event(
+        app,
+        "INFO",
+        "app_config_exported",
+        &[("sections", &sections.join(",")), ("encrypted", if encrypted { "true" } else { "false" })],
+    );
+    Ok(ExportSummary {
+        path: path.display().to_string(),
+        sections,
+        encrypted,
+    })
+}
+
+/// Set one pref the way `set_runtime_pref` does, side effects included.
+fn apply_pref(app: &AppHandle, key: &str, value: Value) -> Result<(), String> {
+    validate_runtime_pref(key, &value)?;
+    store_runtime_pref(app, key, value).map_err(|e| e.to_string())?;
+    app.state::<SettingsSessionState>().record_pref(key);
+    apply_runtime_pref_change(app, key);
+    Ok(())
+}
+
+/// Why `key` can't be imported without the user's say-so, if it can't.
+fn unconfirmed(key: &str, confirmed: &[String]) -> Option<String> {
+    (SENSITIVE_PREFS.contains(&key) && !confirmed.iter().any(|c| c == key))
+        .then(|| "Security-sensitive; confirm this pref to import it".to_string())
+}
+
+fn import_prefs(app: &AppHandle, prefs: Map<String, Value>, strategy: MergeStrategy, confirmed: &[String]) -> SectionResult {
+    let mut result = SectionResult::default();
+    if strategy == MergeStrategy::Replace {
+        for key in exportable_prefs(app).into_iter().map(|(key, _)| key).filter(|key| !prefs.contains_key(key)) {
+            match apply_pref(app, &key, Value::Null) {
+                Ok(()) => result.removed.push(key),
+                Err(err) => result.rejected.push((key, err)),
+            }
+        }
+    }
+    for (key, value) in prefs {
+        if key == data_sources::PREF_DATA_SOURCES {
+            result.rejected.push((key, "Data sources are their own section".to_string()));
+            continue;
+        }
+        if let Some(reason) = unconfirmed(&key, confirmed) {
+            result.rejected.push((key, reason));
+            continue;
+        }
+        match apply_pref(app, &key, value) {
+            Ok(()) => result.applied.push(key),
+            Err(err) => result.rejected.push((key, err)),
+        }
+    }
+    result
+}
+
+fn import_data_sources(app: &AppHandle, toggles: Option<Value>, strategy: MergeStrategy) -> SectionResult {
+    let key = data_sources::PREF_DATA_SOURCES.to_string();
+    let value = match toggles {
+        Some(value) => value,
+        None if strategy == MergeStrategy::Replace => Value::Null,
+        None => return skipped(),
+    };
+    let mut result = SectionResult::default();
+    let reset = value.is_null();
+    match apply_pref(app, &key, value) {
+        Ok(()) if reset => result.removed.push(key),
+        Ok(()) => result.applied.push(key),
+        Err(err) => result.rejected.push((key, err)),
+    }
+    result
+}
+
+fn import_layouts(app: &AppHandle, layouts: BTreeMap<String, Layout>, strategy: MergeStrategy) -> SectionResult {
+    if layouts.is_empty() && strategy == MergeStrategy::Merge {
+        return skipped();
+    }
+    match window_layout::import_named(app, layouts, strategy == MergeStrategy::Replace) {
+        Ok(imported) => SectionResult {
+            applied: imported.written,
+            rejected: imported.refused,
+            ..Default::default()
+        },
+        Err(err) => SectionResult {
+            rejected: vec![("*".to_string(), err.to_string())],
+            ..Default::default()
+        },
+    }
+}
+
+fn import_secrets(app: &AppHandle, secrets: Option<BTreeMap<String, String>>, strategy: MergeStrategy) -> SectionResult {
+    let Some(secrets) = secrets else {
+        return skipped();
+    };
+    let mut result = SectionResult::default();
+    let session = app.state::<SettingsSessionState>();
+    if strategy == MergeStrategy::Replace {
+        let current: Vec<String> = {
+            let cache = app.state::<SecretsCache>();
+            let held = cache.secrets.lock().unwrap_or_else(|e| e.into_inner());
+            held.keys().filter(|key| !secrets.contains_key(*key)).cloned().collect()
+        };
+        for key in current {
+            match update_vault(app, key.clone(), None) {
+                Ok(()) => {
+                    session.record_secret(&key);
+                    result.removed.push(key);
+                }
+                Err(err) => result.rejected.push((key, err.to_string())),
+            }
+        }
+    }
+    for (key, value) in secrets {
+        let value = value.trim().to_string();
+        let outcome = require_supported_secret_key(&key)
+            .and_then(|()| update_vault(app, key.clone(), (!value.is_empty()).then_some(value)));
+        match outcome {
+            Ok(()) => {
+                session.record_secret(&key);
+                result.applied.push(key);
+            }
+            Err(err) => result.rejected.push((key, err.to_string())),
+        }
+    }
+    result
+}
+
+/// Read, check and apply a bundle. A bad file or passphrase fails as a
+/// whole; once it decodes, each section reports on its own. Sensitive prefs
+/// not in `confirmed` are reported as rejected, so the caller can ask.
+pub(crate) fn import(
+    app: &AppHandle,
+    path: &str,
+    passphrase: Option<&str>,
+    strategy: MergeStrategy,
+    confirmed: &[String],
+) -> Result<ImportReport, DesktopError> {
+    let path: &Path = &require_json_path(path)?;
+    let raw = fs::read_to_string(path).map_err(|e| DesktopError::io("Failed to read configuration", path, e))?;
+    let bundle = decode(&raw, passphrase)?;
+    let report = ImportReport {
+        prefs: import_prefs(app, bundle.prefs, strategy, confirmed),
+        data_sources: import_data_sources(app, bundle.data_sources, strategy),
+        layouts: import_layouts(app, bundle.layouts, strategy),
+        secrets: import_secrets(app, bundle.secrets, strategy),
+    };
+    let count = |section: &SectionResult| section.applied.len().to_string();
+    log_event(
+        app,
+        "INFO",
+        "app_config_imported",
+        &[
+            ("prefs", &count(&report.prefs)),
+            ("layouts", &count(&report.layouts)),
+            ("secrets", &count(&report.secrets)),
+        ],
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::{json, Map, Value};
+
+    use super::{decode, encode, unconfirmed, ConfigBundle, BUNDLE_VERSION, MAX_KDF_ITERATIONS};
+
+    /// The lowest count an import accepts, so tests stay fast.
+    const TEST_ITERATIONS: u32 = super::MIN_KDF_ITERATIONS;
+
+    fn bundle(with_secrets: bool) -> ConfigBundle {
+        let prefs: Map<String, Value> = json!({ "theme": "dark", "quietHours": { "start": "22:00", "end": "07:00" } })
+            .as_object()
+            .cloned()
+            .unwrap();
+        ConfigBundle {
+            prefs,
+            data_sources: Some(json!({ "acled": false })),
+            layouts: serde_json::from_value(json!({ "ops": { "windows": [] } })).unwrap(),
+            secrets: with_secrets.then(|| BTreeMap::from([("GROQ_API_KEY".to_string(), "gsk_test".to_string())])),
+        }
+    }
+
+    #[test]
+    fn plain_bundle_round_trips() {
+        let original = bundle(false);
+        let encoded = encode(&original, None, "2.5.23", TEST_ITERATIONS).unwrap();
+        assert_eq!(decode(&encoded, None).unwrap(), original);
+    }
+
+    #[test]
+    fn sealed_bundle_round_trips_and_hides_the_secrets() {
+        let original = bundle(true);
+        let encoded = encode(&original, Some("correct horse"), "2.5.23", TEST_ITERATIONS).unwrap();
+        assert!(!encoded.contains("gsk_test"));
+        assert!(!encoded.contains("GROQ_API_KEY"));
+        assert_eq!(decode(&encoded, Some("correct horse")).unwrap(), original);
+
+        let wrong = decode(&encoded, Some("battery staple")).unwrap_err();
+        assert_eq!(wrong.code(), "invalid_argument");
+        assert!(decode(&encoded, None).is_err(), "a sealed bundle needs the passphrase");
+    }
+
+    #[test]
+    fn secrets_are_never_written_or_read_in_the_clear() {
+        assert!(encode(&bundle(true), None, "2.5.23", TEST_ITERATIONS).is_err());
+        assert!(encode(&bundle(true), Some(""), "2.5.23", TEST_ITERATIONS).is_err());
+        let tampered = json!({
+            "format": "worldmonitor-config",
+            "version": BUNDLE_VERSION,
+            "config": { "secrets": { "GROQ_API_KEY": "gsk_test" } },
+        });
+        assert!(decode(&tampered.to_string(), None).is_err());
+    }
+
+    #[test]
+    fn other_versions_and_formats_are_refused() {
+        let mut envelope: Value = serde_json::from_str(&encode(&bundle(false), None, "2.5.23", TEST_ITERATIONS).unwrap()).unwrap();
+        envelope["version"] = json!(BUNDLE_VERSION + 1);
+        let err = decode(&envelope.to_string(), None).unwrap_err();
+        assert!(err.to_string().contains("version 2 is not supported"), "{err}");
+
+        envelope["version"] = json!(BUNDLE_VERSION);
+        envelope["format"] = json!("something-else");
+        assert!(decode(&envelope.to_string(), None).is_err());
+        assert!(decode("{}", None).is_err());
+    }
+
+    #[test]
+    fn a_modified_ciphertext_fails_authentication() {
+        let encoded = encode(&bundle(true), Some("pw"), "2.5.23", TEST_ITERATIONS).unwrap();
+        let mut envelope: Value = serde_json::from_str(&encoded).unwrap();
+        let ciphertext = envelope["sealed"]["ciphertext"].as_str().unwrap().to_string();
+        let flipped = if ciphertext.starts_with('A') { "B" } else { "A" };
+        envelope["sealed"]["ciphertext"] = json!(format!("{flipped}{}", &ciphertext[1..]));
+        let err = decode(&envelope.to_string(), Some("pw")).unwrap_err();
+        assert_eq!(err.to_string(), "Wrong passphrase, or the bundle was modified");
+    }
+
+    #[test]
+    fn iteration_counts_outside_the_bounds_are_refused_before_deriving() {
+        let encoded = encode(&bundle(true), Some("pw"), "2.5.23", TEST_ITERATIONS).unwrap();
+        let mut envelope: Value = serde_json::from_str(&encoded).unwrap();
+        for iterations in [0, TEST_ITERATIONS - 1, MAX_KDF_ITERATIONS + 1, u32::MAX] {
+            envelope["sealed"]["iterations"] = json!(iterations);
+            let err = decode(&envelope.to_string(), Some("pw")).unwrap_err();
+            assert!(err.to_string().contains("iterations"), "{iterations}: {err}");
+        }
+    }
+
+    #[test]
+    fn sensitive_prefs_need_their_own_confirmation() {
+        assert_eq!(unconfirmed("theme", &[]), None);
+        for key in ["sidecarNodeArgs", "sidecarExtraEnv", "extraCaCertificates", "allowedUrlSchemes", "trustedLocalHosts"] {
+            assert!(unconfirmed(key, &[]).is_some(), "{key}");
+            assert!(unconfirmed(key, &["theme".to_string()]).is_some(), "{key}");
+            assert_eq!(unconfirmed(key, &[key.to_string()]), None, "{key}");
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Keep windows out of screen captures and shares. Windows and macOS
+//! support it; elsewhere requests are remembered but reported as
+//! unsupported. Requests are kept per label for the session, so a settings
+//! or panel window that is closed and reopened comes back protected. The
+//! `protectSettingsWindow` pref protects settings regardless, and is read
+//! by the builder so key fields are never shown unprotected.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::panel_windows::panel_id_from_label;
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_PROTECT_SETTINGS_WINDOW: &str = "protectSettingsWindow";
+const SETTINGS_LABEL: &str = "settings";
+
+pub(crate) fn is_supported() -> bool {
+    cfg!(any(target_os = "windows", target_os = "macos"))
+}
+
+/// Managed state: the protection asked for, by window label.
+#[derive(Default)]
+pub(crate) struct ContentProtectionState {
+    requested: Mutex<BTreeMap<String, bool>>,
+}
+
+impl ContentProtectionState {
+    fn set(&self, label: &str, enabled: bool) {
+        self.requested
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(label.to_string(), enabled);
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, bool> {
+        self.requested.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// The pref wins for settings; a request can't unprotect it.
+pub(crate) fn effective(label: &str, requested: Option<bool>, protect_settings: bool) -> bool {
+    (label == SETTINGS_LABEL && protect_settings) || requested.unwrap_or(false)
+}
+
+fn protect_settings(app: &AppHandle) -> bool {
+    app.try_state::<RuntimePrefs>()
+        .is_some_and(|prefs| prefs.get_bool(PREF_PROTECT_SETTINGS_WINDOW, false))
+}
+
+/// Whether a window about to be built with `label` should start protected.
+pub(crate) fn for_new_window(app: &AppHandle, label: &str) -> bool {
+    let requested = app.state::<ContentProtectionState>().snapshot().get(label).copied();
+    effective(label, requested, protect_settings(app))
+}
+
+/// Labels that may be protected: open windows, plus the ones we recreate.
+fn is_known_label(app: &AppHandle, label: &str) -> bool {
+    label == SETTINGS_LABEL || panel_id_from_label(label).is_some() || app.get_webview_window(label).is_some()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct WindowProtection {
+    label: String,
+    /// Protection in effect once the window exists.
+    protected: bool,
+    open: bool,
+    /// `false` off Windows and macOS: the request is kept but does nothing.
+    supported: bool,
+}
+
+fn window_protection(app: &AppHandle, label: &str, requested: Option<bool>, protect_settings: bool) -> WindowProtection {
+    WindowProtection {
+        label: label.to_string(),
+        protected: effective(label, requested, protect_settings),
+        open: app.get_webview_window(label).is_some(),
+        supported: is_supported(),
+    }
+}
+
+/// Record the request for `label` and apply it if the window is open.
+pub(crate) fn set(app: &AppHandle, label: &str, enabled: bool) -> Result<WindowProtection, DesktopError> {
+    if !is_known_label(app, label) {
+        return Err(DesktopError::InvalidArgument(format!("Unknown window: {label}")));
+    }
+    app.state::<ContentProtectionState>().set(label, enabled);
+    apply(app, label)?;
+    let result = window_protection(app, label, Some(enabled), protect_settings(app));
+    log_event(
+        app,
+        "INFO",
+        "content_protection_changed",
+        &[
+            ("window", label),
+            ("protected", &result.protected.to_string()),
+            ("supported", &result.supported.to_string()),
+        ],
+    );
+    Ok(result)
+}
+
+/// Push the effective state to the open window for `label`, if any.
+pub(crate) fn apply(app: &AppHandle, label: &str) -> Result<(), DesktopError> {
+    if !is_supported() {
+        return Ok(());
+    }
+    let Some(window) = app.get_webview_window(label) else {
+        return Ok(());
+    };
+    window
+        .set_content_protected(for_new_window(app, label))
+        .map_err(|e| DesktopError::Internal(format!("Failed to set content protection on {label}: {e}")))
+}
+
+/// `get_content_protection_state` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ContentProtectionReport {
+    supported: bool,
+    protect_settings_window: bool,
+    /// Every open window plus any closed one with a recorded request.
+    windows: Vec<WindowProtection>,
+}
+
+pub(crate) fn report(app: &AppHandle) -> ContentProtectionReport {
+    let requested = app.state::<ContentProtectionState>().snapshot();
+    let protect_settings = protect_settings(app);
+    let mut labels: Vec<String> = app.webview_windows().into_keys().collect();
+    labels.extend(requested.keys().cloned());
+    labels.sort();
+    labels.dedup();
+    ContentProtectionReport {
+        supported: is_supported(),
+        protect_settings_window: protect_settings,
+        windows: labels
+            .iter()
+            .map(|label| window_protection(app, label, requested.get(label).copied(), protect_settings))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::effective;
+
+    #[test]
+    fn settings_pref_overrides_requests_for_settings_only() {
+        assert!(effective("settings", None, true));
+        assert!(effective("settings", Some(false), true));
+        assert!(!effective("settings", Some(false), false));
+        assert!(effective("settings", Some(true), false));
+        assert!(!effective("main", None, true));
+        assert!(effective("panel-map", Some(true), false));
+        assert!(!effective("panel-map", Some(false), true));
+    }
+}
@@ -0,0 +1,240 @@
+//! Panic handling. Release builds use the Windows GUI subsystem, so a panic's
+//! stderr output is invisible and the app "just closes". The hook installed
+//! here records every panic in desktop.log (or a temp-dir crash file before
+//! the app handle exists) and, for panics that would take down the main
+//! thread, shows a native dialog pointing at the log before aborting.
+
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::thread::{self, ThreadId};
+
+use tauri::ipc::Invoke;
+use tauri::AppHandle;
+
+use crate::logging::{log_panic, now_iso8601};
+use crate::{desktop_log_path, logs_dir_path};
+
+const LAST_CRASH_FILE: &str = "last-crash.txt";
+const TEMP_CRASH_LOG_FILE: &str = "world-monitor-crash.log";
+const TEMP_LAST_CRASH_FILE: &str = "world-monitor-last-crash.txt";
+
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+thread_local! {
+    /// Depth of [`catch_command_panics`] frames on this thread. Panics inside
+    /// a command are recovered there, so the hook must not abort for them.
+    static COMMAND_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Install the hook. Call first thing in `main`, on the main thread.
+pub(crate) fn install_panic_hook() {
+    let _ = MAIN_THREAD.set(thread::current().id());
+    panic::set_hook(Box::new(|info| {
+        let report = CrashReport::capture(info);
+        let log_path = record_crash(&report);
+        eprintln!("[tauri] {}", report.render());
+
+        let fatal = COMMAND_DEPTH.with(|depth| depth.get() == 0)
+            && MAIN_THREAD.get() == Some(&thread::current().id());
+        if fatal {
+            show_crash_dialog(log_path.as_deref());
+            std::process::abort();
+        }
+    }));
+}
+
+/// Make the app handle available to the hook once setup has run.
+pub(crate) fn attach_app_handle(app: &AppHandle) {
+    let _ = APP_HANDLE.set(app.clone());
+}
+
+/// Wrap the generated invoke handler so a panicking command rejects its own
+/// promise instead of unwinding into the event loop.
+pub(crate) fn catch_command_panics<F>(handler: F) -> impl Fn(Invoke) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke) -> bool + Send + Sync + 'static,
+{
+    move |invoke: Invoke| {
+        let resolver = invoke.resolver.clone();
+        let command = invoke.message.command().to_string();
+        COMMAND_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| handler(invoke)));
+        COMMAND_DEPTH.with(|depth| depth.set(depth.get() - 1));
+        result.unwrap_or_else(|_| {
+            resolver.reject(format!("Command {command} failed unexpectedly; see desktop.log"));
+            true
+        })
+    }
+}
+
+/// Timestamp of the most recent recorded crash, if any.
+pub(crate) fn last_crash_at(app: &AppHandle) -> Option<String> {
+    let app_marker = logs_dir_path(app).ok().map(|dir| dir.join(LAST_CRASH_FILE));
+    let temp_marker = Some(std::env::temp_dir().join(TEMP_LAST_CRASH_FILE));
+    latest_timestamp(
+        [app_marker, temp_marker]
+            .into_iter()
+            .flatten()
+            .filter_map(|path| fs::read_to_string(path).ok()),
+    )
+}
+
+/// ISO-8601 UTC timestamps sort lexically, so the max string is the latest.
+fn latest_timestamp<I: IntoIterator<Item = String>>(candidates: I) -> Option<String> {
+    candidates
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .max()
+}
+
+struct CrashReport {
+    timestamp: String,
+    thread: String,
+    location: String,
+    message: String,
+    backtrace: String,
+}
+
+impl CrashReport {
+    fn capture(info: &PanicHookInfo<'_>) -> Self {
+        CrashReport {
+            timestamp: now_iso8601(),
+            thread: thread::current().name().unwrap_or("<unnamed>").to_string(),
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+                .unwrap_or_else(|| "<unknown>".to_string()),
+            message: panic_message(info.payload()),
+            backtrace: Backtrace::force_capture().to_string(),
+        }
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "[{}] panic on thread '{}' at {}: {}\n{}",
+            self.timestamp, self.thread, self.location, self.message, self.backtrace
+        )
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+/// Write the report and crash marker; returns the log the report went to.
+fn record_crash(report: &CrashReport) -> Option<PathBuf> {
+    if let Some(app) = APP_HANDLE.get() {
+        let logged = log_panic(
+            app,
+            &[
+                ("thread", &report.thread),
+                ("location", &report.location),
+                ("message", &report.message),
+                ("backtrace", &report.backtrace),
+            ],
+        );
+        if logged {
+            if let Ok(dir) = logs_dir_path(app) {
+                let _ = fs::write(dir.join(LAST_CRASH_FILE), &report.timestamp);
+            }
+            return desktop_log_path(app).ok();
+        }
+    }
+
+    let temp = std::env::temp_dir();
+    let crash_log = temp.join(TEMP_CRASH_LOG_FILE);
+    let mut file = OpenOptions::new().create(true).append(true).open(&crash_log).ok()?;
+    writeln!(file, "{}", report.render()).ok()?;
+    let _ = fs::write(temp.join(TEMP_LAST_CRASH_FILE), &report.timestamp);
+    Some(crash_log)
+}
+
+fn crash_dialog_text(log_path: Option<&Path>) -> String {
+    match log_path {
+        Some(path) => format!(
+            "World Monitor hit an unexpected error and has to close.\n\nDetails were written to:\n{}",
+            path.display()
+        ),
+        None => "World Monitor hit an unexpected error and has to close.".to_string(),
+    }
+}
+
+/// Best effort: the hook may run before (or after) the event loop is usable,
+/// so use a separate process rather than a toolkit dialog.
+fn show_crash_dialog(log_path: Option<&Path>) {
+    let text = crash_dialog_text(log_path);
+
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            "display alert \"World Monitor\" message \"{}\" as critical",
+            text.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let _ = Command::new("osascript").args(["-e", &script]).status();
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let script = format!(
+            "Add-Type -AssemblyName PresentationFramework; [System.Windows.MessageBox]::Show('{}', 'World Monitor', 'OK', 'Error') | Out-Null",
+            text.replace('\'', "''")
+        );
+        let _ = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .status();
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let shown = Command::new("zenity")
+            .args(["--error", "--title=World Monitor", "--no-markup", &format!("--text={text}")])
+            .status()
+            .is_ok_and(|s| s.success());
+        if !shown {
+            let _ = Command::new("kdialog")
+                .args(["--title", "World Monitor", "--error", &text])
+                .status();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crash_dialog_text, latest_timestamp, panic_message};
+    use std::path::Path;
+
+    #[test]
+    fn picks_latest_crash_timestamp() {
+        let markers = vec![
+            "2026-03-01T10:00:00.000Z\n".to_string(),
+            String::new(),
+            "2026-03-02T09:00:00.000Z".to_string(),
+        ];
+        assert_eq!(latest_timestamp(markers).as_deref(), Some("2026-03-02T09:00:00.000Z"));
+        assert_eq!(latest_timestamp(Vec::new()), None);
+    }
+
+    #[test]
+    fn extracts_panic_messages_and_names_the_log() {
+        assert_eq!(panic_message(&"boom"), "boom");
+        assert_eq!(panic_message(&String::from("formatted boom")), "formatted boom");
+        assert_eq!(panic_message(&42u8), "<non-string panic payload>");
+        assert!(crash_dialog_text(Some(Path::new("/tmp/desktop.log"))).ends_with("/tmp/desktop.log"));
+    }
+}
@@ -0,0 +1,728 @@
+//! Movable data directory. By default the persistent cache, runtime prefs and
+//! the other stores live in the platform app data dir and logs in the app log dir. After
+//! `migrate_data_directory` they live under a user-chosen directory (logs in
+//! its `logs/` subdirectory), recorded in a pointer file that always stays in
+//! the default app data dir. In a Flatpak the cache starts out in the app
+//! cache dir instead, and joins the prefs once the directory is moved.
+//!
+//! A migration stops the sidecar, copies and verifies every file, and only
+//! then flips the pointer, so a failure at any step leaves the old directory
+//! in charge. The old files are left in place for the user to remove.
+//!
+//! A `--profile` launch starts from `profiles/NAME` under both defaults.
+//!
+//! [`prepare_storage`] creates and probes the directories once at startup.
+//! When one can't be resolved or written (locked-down profiles, a read-only
+//! home), that part moves to a session directory under the temp dir for the
+//! rest of the run: prefs, cache and logs keep working but are not kept.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::{blob_store, http_cache, keyring_migration, packaging, profile, restart, window_layout};
+use crate::{
+    start_local_api, stop_local_api, LocalApiState, PersistentCache, RuntimePrefs, PERSISTENT_CACHE_FILE,
+    RUNTIME_PREFS_FILE,
+};
+
+const POINTER_FILE: &str = "data-location.json";
+const LOGS_SUBDIR: &str = "logs";
+const WRITE_PROBE_FILE: &str = ".data-dir-write-probe";
+const PROGRESS_EVENT: &str = "data-migration-progress";
+const DEGRADED_EVENT: &str = "storage-degraded";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pointer {
+    data_dir: PathBuf,
+}
+
+/// The pointer target as last read or written; `None` until first needed.
+static OVERRIDE: Mutex<Option<Option<PathBuf>>> = Mutex::new(None);
+
+/// Set by [`prepare_storage`] when part of the storage fell back.
+static DEGRADED: OnceLock<DegradedStorage> = OnceLock::new();
+
+/// What [`prepare_storage`] had to replace, and why. Also the
+/// `storage-degraded` payload.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DegradedStorage {
+    /// The session directory standing in for the failed ones.
+    sandbox: PathBuf,
+    /// Why the data (or cache) dir is unusable; `None` when it is fine.
+    data_dir_error: Option<String>,
+    log_dir_error: Option<String>,
+}
+
+impl DegradedStorage {
+    /// `None` when both directories are usable.
+    fn new(sandbox: PathBuf, data_dir_error: Option<String>, log_dir_error: Option<String>) -> Option<Self> {
+        (data_dir_error.is_some() || log_dir_error.is_some()).then_some(DegradedStorage {
+            sandbox,
+            data_dir_error,
+            log_dir_error,
+        })
+    }
+
+    fn data_dir(&self) -> Option<PathBuf> {
+        self.data_dir_error.is_some().then(|| self.sandbox.join("data"))
+    }
+
+    fn log_dir(&self) -> Option<PathBuf> {
+        self.log_dir_error.is_some().then(|| self.sandbox.join(LOGS_SUBDIR))
+    }
+
+    pub(crate) fn data_dir_error(&self) -> Option<&str> {
+        self.data_dir_error.as_deref()
+    }
+
+    pub(crate) fn log_dir_error(&self) -> Option<&str> {
+        self.log_dir_error.as_deref()
+    }
+}
+
+/// The fallback in effect this session, if any.
+pub(crate) fn degraded() -> Option<&'static DegradedStorage> {
+    DEGRADED.get()
+}
+
+/// The override recorded in `default_dir`, if it names an existing absolute
+/// directory. Anything else (missing, malformed, unplugged drive) falls back
+/// to the defaults rather than failing startup.
+fn read_pointer(default_dir: &Path) -> Option<PathBuf> {
+    let raw = fs::read_to_string(default_dir.join(POINTER_FILE)).ok()?;
+    let pointer: Pointer = serde_json::from_str(&raw).ok()?;
+    (pointer.data_dir.is_absolute() && pointer.data_dir.is_dir()).then_some(pointer.data_dir)
+}
+
+/// Record `target` as the data dir, or remove the pointer for `None`. The
+/// file is replaced by rename so a crash never leaves half a pointer.
+fn write_pointer(default_dir: &Path, target: Option<&Path>) -> io::Result<()> {
+    let path = default_dir.join(POINTER_FILE);
+    let Some(target) = target else {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    };
+    fs::create_dir_all(default_dir)?;
+    let pointer = Pointer {
+        data_dir: target.to_path_buf(),
+    };
+    let serialized = serde_json::to_string_pretty(&pointer).map_err(io::Error::other)?;
+    let staging = default_dir.join(format!("{POINTER_FILE}.tmp"));
+    fs::write(&staging, serialized)?;
+    fs::rename(&staging, &path)
+}
+
+fn override_dir(default_dir: &Path) -> Option<PathBuf> {
+    let mut cached = OVERRIDE.lock().unwrap_or_else(|e| e.into_inner());
+    cached.get_or_insert_with(|| read_pointer(default_dir)).clone()
+}
+
+fn default_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| profile::scope_dir(&dir))
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))
+}
+
+fn default_log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_log_dir()
+        .map(|dir| profile::scope_dir(&dir))
+        .map_err(|e| format!("Failed to resolve app log dir: {e}"))
+}
+
+/// Where the persistent cache and runtime prefs live.
+pub(crate) fn resolve_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(dir) = degraded().and_then(DegradedStorage::data_dir) {
+        return Ok(dir);
+    }
+    let default_dir = default_data_dir(app)?;
+    Ok(override_dir(&default_dir).unwrap_or(default_dir))
+}
+
+/// Where the persistent cache lives: with the prefs, except in a Flatpak
+/// without a moved data directory, where it belongs in the XDG cache dir.
+pub(crate) fn resolve_cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(dir) = degraded().and_then(DegradedStorage::data_dir) {
+        return Ok(dir);
+    }
+    let default_dir = default_data_dir(app)?;
+    match override_dir(&default_dir) {
+        Some(dir) => Ok(dir),
+        None if packaging::is_flatpak() => app
+            .path()
+            .app_cache_dir()
+            .map(|dir| profile::scope_dir(&dir))
+            .map_err(|e| format!("Failed to resolve app cache dir: {e}")),
+        None => Ok(default_dir),
+    }
+}
+
+/// Move a cache left in the data dir by an earlier Flatpak build to
+/// [`resolve_cache_dir`], unless one is already there. Runs before the
+/// cache is first read.
+pub(crate) fn relocate_cache(app: &AppHandle) {
+    let (Ok(data), Ok(cache)) = (resolve_data_dir(app), resolve_cache_dir(app)) else {
+        return;
+    };
+    let (from, to) = (data.join(PERSISTENT_CACHE_FILE), cache.join(PERSISTENT_CACHE_FILE));
+    if from == to || !from.is_file() || to.exists() {
+        return;
+    }
+    // A rename can't cross filesystems; copy then remove instead.
+    let moved = fs::create_dir_all(&cache)
+        .and_then(|_| fs::rename(&from, &to).or_else(|_| fs::copy(&from, &to).and_then(|_| fs::remove_file(&from))));
+    match moved {
+        Ok(()) => log_event(app, "INFO", "cache_relocated", &[("to", &to.display().to_string())]),
+        Err(err) => log_event(app, "WARN", "cache_relocate_failed", &[("error", &err.to_string())]),
+    }
+}
+
+/// [`resolve_data_dir`] for code that runs before Tauri starts, using the
+/// same `<data dir>/<identifier>` layout Tauri uses for `app_data_dir`.
+pub(crate) fn resolve_data_dir_before_app(identifier: &str) -> Option<PathBuf> {
+    let default_dir = profile::scope_dir(&dirs::data_dir()?.join(identifier));
+    Some(override_dir(&default_dir).unwrap_or(default_dir))
+}
+
+/// Where desktop and sidecar logs live.
+pub(crate) fn resolve_log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    if let Some(dir) = degraded().and_then(DegradedStorage::log_dir) {
+        return Ok(dir);
+    }
+    match override_dir(&default_data_dir(app)?) {
+        Some(dir) => Ok(dir.join(LOGS_SUBDIR)),
+        None => default_log_dir(app),
+    }
+}
+
+/// Create `dir` if needed and check a file can be written in it.
+fn prepare_dir(dir: Result<PathBuf, String>) -> Result<(), String> {
+    let dir = dir?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+    let probe = dir.join(WRITE_PROBE_FILE);
+    fs::write(&probe, b"ok")
+        .and_then(|()| fs::remove_file(&probe))
+        .map_err(|e| format!("{} is not writable: {e}", dir.display()))
+}
+
+/// Check the data, cache and log dirs once, before the prefs, cache or log
+/// are first touched, creating them as needed. Whatever is unusable is
+/// replaced by a session directory for the rest of the run; the result is
+/// then reported by [`report_storage`] once logging is up.
+pub(crate) fn prepare_storage(app: &AppHandle) {
+    let data_dir_error = prepare_dir(resolve_data_dir(app))
+        .and_then(|()| prepare_dir(resolve_cache_dir(app)))
+        .err();
+    let log_dir_error = prepare_dir(resolve_log_dir(app)).err();
+    let sandbox = std::env::temp_dir().join(format!("{}-session-{}", app.config().identifier, std::process::id()));
+    let Some(degraded) = DegradedStorage::new(sandbox, data_dir_error, log_dir_error) else {
+        return;
+    };
+    // Nowhere better to go if the temp dir fails too; writes then fail
+    // one by one as they did before this check.
+    for dir in [degraded.data_dir(), degraded.log_dir()].into_iter().flatten() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = DEGRADED.set(degraded);
+}
+
+/// Log and announce a fallback made by [`prepare_storage`].
+pub(crate) fn report_storage(app: &AppHandle) {
+    let Some(degraded) = degraded() else {
+        return;
+    };
+    log_event(
+        app,
+        "WARN",
+        "storage_degraded",
+        &[
+            ("sandbox", &degraded.sandbox.display().to_string()),
+            ("data_dir_error", degraded.data_dir_error().unwrap_or("")),
+            ("log_dir_error", degraded.log_dir_error().unwrap_or("")),
+        ],
+    );
+    let _ = app.emit(DEGRADED_EVENT, degraded);
+}
+
+/// `path` with `.` and `..` folded away, resolving symlinks for the part
+/// that already exists, so containment checks can't be fooled.
+fn normalize(path: &Path) -> PathBuf {
+    let existing = path.ancestors().find(|candidate| candidate.exists());
+    let (base, rest) = match existing.and_then(|base| Some((base.canonicalize().ok()?, base))) {
+        Some((canonical, base)) => (canonical, path.strip_prefix(base).unwrap_or(Path::new(""))),
+        None => (PathBuf::new(), path),
+    };
+    let mut normalized = base;
+    for component in rest.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Check that `target` can become the data dir, creating it if needed.
+/// Returns the normalized path.
+fn validate_target(current: &Path, target: &Path) -> Result<PathBuf, String> {
+    if !target.is_absolute() {
+        return Err("Choose an absolute path for the data directory".to_string());
+    }
+    let target = normalize(target);
+    let current = normalize(current);
+    if target == current {
+        return Err("That is already the data directory".to_string());
+    }
+    if target.starts_with(&current) {
+        return Err("The new data directory can't be inside the current one".to_string());
+    }
+    fs::create_dir_all(&target).map_err(|e| format!("Cannot create {}: {e}", target.display()))?;
+    if !target.is_dir() {
+        return Err(format!("{} is not a directory", target.display()));
+    }
+    let probe = target.join(WRITE_PROBE_FILE);
+    fs::write(&probe, b"ok")
+        .and_then(|()| fs::remove_file(&probe))
+        .map_err(|e| format!("{} is not writable: {e}", target.display()))?;
+    Ok(target)
+}
+
+#[derive(Debug, PartialEq)]
+struct CopyItem {
+    from: PathBuf,
+    to: PathBuf,
+    bytes: u64,
+}
+
+/// Files directly under the data dir that move with it.
+const STORE_FILES: [&str; 5] = [
+    PERSISTENT_CACHE_FILE,
+    RUNTIME_PREFS_FILE,
+    window_layout::LAYOUT_FILE,
+    restart::HISTORY_FILE,
+    keyring_migration::PROGRESS_FILE,
+];
+
+/// Directories under the data dir that move with it, contents and all.
+const STORE_DIRS: [&str; 2] = [blob_store::BLOB_DIR, http_cache::CACHE_DIR];
+
+/// The files to carry over: every store in `old_data` (see [`STORE_FILES`]
+/// and [`STORE_DIRS`]), and every file directly inside `old_logs`.
+fn plan_copy(old_data: &Path, old_logs: &Path, new_data: &Path, new_logs: &Path) -> io::Result<Vec<CopyItem>> {
+    let mut items = Vec::new();
+    for name in STORE_FILES {
+        let from = old_data.join(name);
+        if let Ok(metadata) = fs::metadata(&from) {
+            items.push(CopyItem {
+                to: new_data.join(name),
+                from,
+                bytes: metadata.len(),
+            });
+        }
+    }
+    for name in STORE_DIRS {
+        let from = old_data.join(name);
+        if from.is_dir() {
+            plan_dir(&from, &new_data.join(name), &mut items)?;
+        }
+    }
+    if let Ok(entries) = fs::read_dir(old_logs) {
+        let mut logs = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                logs.push(CopyItem {
+                    from: entry.path(),
+                    to: new_logs.join(entry.file_name()),
+                    bytes: metadata.len(),
+                });
+            }
+        }
+        logs.sort_by(|a, b| a.from.cmp(&b.from));
+        items.extend(logs);
+    }
+    Ok(items)
+}
+
+/// Every file under `from`, recursively and in path order, mapped to the
+/// same place under `to`.
+fn plan_dir(from: &Path, to: &Path, items: &mut Vec<CopyItem>) -> io::Result<()> {
+    let mut entries = fs::read_dir(from)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let metadata = entry.metadata()?;
+        let target = to.join(entry.file_name());
+        if metadata.is_dir() {
+            plan_dir(&entry.path(), &target, items)?;
+        } else if metadata.is_file() {
+            items.push(CopyItem {
+                from: entry.path(),
+                to: target,
+                bytes: metadata.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    let (mut buf_a, mut buf_b) = (vec![0u8; 64 * 1024], vec![0u8; 64 * 1024]);
+    loop {
+        let read = a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buf_b[..read])?;
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MigrationProgress {
+    copied_files: usize,
+    total_files: usize,
+    copied_bytes: u64,
+    total_bytes: u64,
+    file: String,
+}
+
+/// Copy and verify every item. On failure the copies made so far are
+/// removed, so the target holds nothing half-migrated.
+fn copy_and_verify(items: &[CopyItem], mut progress: impl FnMut(&MigrationProgress)) -> Result<(), String> {
+    let total_bytes = items.iter().map(|item| item.bytes).sum();
+    let mut copied: Vec<&Path> = Vec::new();
+    let mut report = MigrationProgress {
+        copied_files: 0,
+        total_files: items.len(),
+        copied_bytes: 0,
+        total_bytes,
+        file: String::new(),
+    };
+    for item in items {
+        let result = (|| -> Result<(), String> {
+            if let Some(parent) = item.to.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {e}", parent.display()))?;
+            }
+            fs::copy(&item.from, &item.to).map_err(|e| format!("Failed to copy {}: {e}", item.from.display()))?;
+            copied.push(&item.to);
+            match same_contents(&item.from, &item.to) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(format!("Copy of {} does not match the original", item.from.display())),
+                Err(e) => Err(format!("Failed to verify {}: {e}", item.to.display())),
+            }
+        })();
+        if let Err(err) = result {
+            for path in copied {
+                let _ = fs::remove_file(path);
+            }
+            return Err(err);
+        }
+        report.copied_files += 1;
+        report.copied_bytes += item.bytes;
+        report.file = item.from.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        progress(&report);
+    }
+    Ok(())
+}
+
+/// Copy, verify, then point `default_dir` at `new_data` (`None` when moving
+/// back to the default location). The pointer is untouched on failure.
+fn migrate_files(
+    default_dir: &Path,
+    items: &[CopyItem],
+    new_data: Option<&Path>,
+    progress: impl FnMut(&MigrationProgress),
+) -> Result<(), String> {
+    copy_and_verify(items, progress)?;
+    write_pointer(default_dir, new_data).map_err(|e| format!("Failed to record the new data directory: {e}"))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MigrationResult {
+    data_dir: String,
+    log_dir: String,
+    files_copied: usize,
+    previous_data_dir: String,
+}
+
+/// Move the data directory to `new_path`. Passing the platform default app
+/// data dir moves it back there.
+pub(crate) fn migrate(app: &AppHandle, new_path: &str) -> Result<MigrationResult, DesktopError> {
+    if let Some(reason) = degraded().and_then(DegradedStorage::data_dir_error) {
+        // The pointer lives in the default dir, which is what failed.
+        return Err(DesktopError::Unsupported(format!(
+            "The data directory is unavailable this session, so it can't be moved: {reason}"
+        )));
+    }
+    let default_dir = default_data_dir(app).map_err(DesktopError::Internal)?;
+    let old_data = resolve_data_dir(app).map_err(DesktopError::Internal)?;
+    let old_logs = resolve_log_dir(app).map_err(DesktopError::Internal)?;
+    let target = validate_target(&old_data, Path::new(new_path.trim())).map_err(DesktopError::InvalidArgument)?;
+    let back_to_default = target == normalize(&default_dir);
+    let new_logs = if back_to_default {
+        default_log_dir(app).map_err(DesktopError::Internal)?
+    } else {
+        target.join(LOGS_SUBDIR)
+    };
+
+    let sidecar_was_running = app
+        .try_state::<LocalApiState>()
+        .is_some_and(|state| state.child.lock().unwrap_or_else(|e| e.into_inner()).is_some());
+    log_event(
+        app,
+        "INFO",
+        "data_migration_started",
+        &[("from", &old_data.display().to_string()), ("to", &target.display().to_string())],
+    );
+    stop_local_api(app);
+
+    let cache = app.state::<PersistentCache>();
+    let old_cache_path = old_data.join(PERSISTENT_CACHE_FILE);
+    let result = cache.flush(&old_cache_path).and_then(|_| {
+        // Hold off cache and pref writes until the pointer has flipped, so
+        // nothing lands in the old directory after its copy was taken. No
+        // logging in here: log_event reads the prefs.
+        let _cache_guard = cache.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let prefs = app.state::<RuntimePrefs>();
+        let _prefs_guard = prefs.prefs.lock().unwrap_or_else(|e| e.into_inner());
+        let items = plan_copy(&old_data, &old_logs, &target, &new_logs)
+            .map_err(|e| format!("Failed to list files in {}: {e}", old_data.display()))?;
+        fs::create_dir_all(&new_logs).map_err(|e| format!("Cannot create {}: {e}", new_logs.display()))?;
+        let progress = |report: &MigrationProgress| {
+            let _ = app.emit(PROGRESS_EVENT, report);
+        };
+        migrate_files(&default_dir, &items, (!back_to_default).then_some(target.as_path()), progress)?;
+        *OVERRIDE.lock().unwrap_or_else(|e| e.into_inner()) = Some((!back_to_default).then(|| target.clone()));
+        Ok(items.len())
+    });
+
+    let restart = |app: &AppHandle| {
+        if sidecar_was_running {
+            if let Err(err) = start_local_api(app) {
+                log_event(app, "ERROR", "data_migration_sidecar_restart_failed", &[("error", &err)]);
+            }
+        }
+    };
+    let files_copied = match result {
+        Ok(files_copied) => files_copied,
+        Err(err) => {
+            log_event(app, "ERROR", "data_migration_failed", &[("error", &err)]);
+            restart(app);
+            return Err(DesktopError::Internal(err));
+        }
+    };
+    log_event(
+        app,
+        "INFO",
+        "data_migration_finished",
+        &[("to", &target.display().to_string()), ("files", &files_copied.to_string())],
+    );
+    restart(app);
+    Ok(MigrationResult {
+        data_dir: target.display().to_string(),
+        log_dir: new_logs.display().to_string(),
+        files_copied,
+        previous_data_dir: old_data.display().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        migrate_files, plan_copy, prepare_dir, read_pointer, validate_target, write_pointer, DegradedStorage,
+        MigrationProgress, POINTER_FILE,
+    };
+    use crate::test_support::temp_dir;
+
+    fn seed_old_layout(root: &Path) -> (PathBuf, PathBuf) {
+        let old_data = root.join("default");
+        let old_logs = root.join("default-logs");
+        fs::create_dir_all(&old_data).unwrap();
+        fs::create_dir_all(&old_logs).unwrap();
+        fs::write(old_data.join("persistent-cache.json"), r#"{"k":"v"}"#).unwrap();
+        fs::write(old_data.join("runtime-prefs.json"), r#"{"closeToTray":true}"#).unwrap();
+        fs::write(old_logs.join("desktop.log"), "line one\nline two\n").unwrap();
+        fs::write(old_logs.join("local-api.log"), "sidecar\n").unwrap();
+        (old_data, old_logs)
+    }
+
+    #[test]
+    fn pointer_resolution_falls_back_on_anything_unusable() {
+        let root = temp_dir("data-dir-pointer");
+        let default_dir = root.join("default");
+        let target = root.join("elsewhere");
+        fs::create_dir_all(&target).unwrap();
+
+        assert_eq!(read_pointer(&default_dir), None);
+        write_pointer(&default_dir, Some(&target)).unwrap();
+        assert_eq!(read_pointer(&default_dir), Some(target.clone()));
+
+        // A target that has gone away (unplugged drive) is ignored.
+        fs::remove_dir_all(&target).unwrap();
+        assert_eq!(read_pointer(&default_dir), None);
+
+        fs::write(default_dir.join(POINTER_FILE), r#"{"dataDir":"relative/dir"}"#).unwrap();
+        assert_eq!(read_pointer(&default_dir), None);
+        fs::write(default_dir.join(POINTER_FILE), "not json").unwrap();
+        assert_eq!(read_pointer(&default_dir), None);
+
+        write_pointer(&default_dir, None).unwrap();
+        assert!(!default_dir.join(POINTER_FILE).exists());
+        // Removing an absent pointer is fine.
+        write_pointer(&default_dir, None).unwrap();
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn validates_the_target_directory() {
+        let root = temp_dir("data-dir-validate");
+        let current = root.join("current");
+        fs::create_dir_all(&current).unwrap();
+
+        assert!(validate_target(&current, Path::new("relative")).is_err());
+        assert!(validate_target(&current, &current).is_err());
+        assert!(validate_target(&current, &current.join("nested")).is_err());
+        assert!(validate_target(&current, &root.join("other").join("..").join("current").join("x")).is_err());
+
+        let file = root.join("a-file");
+        fs::write(&file, "x").unwrap();
+        assert!(validate_target(&current, &file).is_err());
+
+        let created = validate_target(&current, &root.join("new").join("data")).unwrap();
+        assert!(created.is_dir());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn copies_verifies_then_flips_the_pointer() {
+        let root = temp_dir("data-dir-migrate");
+        let (old_data, old_logs) = seed_old_layout(&root);
+        let new_data = root.join("new");
+        fs::create_dir_all(&new_data).unwrap();
+        let new_logs = new_data.join("logs");
+
+        let items = plan_copy(&old_data, &old_logs, &new_data, &new_logs).unwrap();
+        assert_eq!(items.len(), 4);
+        let mut reports: Vec<MigrationProgress> = Vec::new();
+        migrate_files(&old_data, &items, Some(&new_data), |report| reports.push(report.clone())).unwrap();
+
+        assert_eq!(read_pointer(&old_data), Some(new_data.clone()));
+        assert_eq!(fs::read_to_string(new_data.join("persistent-cache.json")).unwrap(), r#"{"k":"v"}"#);
+        assert_eq!(fs::read_to_string(new_logs.join("desktop.log")).unwrap(), "line one\nline two\n");
+        // The originals stay behind.
+        assert!(old_data.join("persistent-cache.json").exists());
+        let last = reports.last().unwrap();
+        assert_eq!((last.copied_files, last.total_files), (4, 4));
+        assert_eq!(last.copied_bytes, last.total_bytes);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn every_store_moves_with_the_data_directory() {
+        let root = temp_dir("data-dir-migrate-stores");
+        let (old_data, old_logs) = seed_old_layout(&root);
+        let blobs = old_data.join("blobs");
+        fs::create_dir_all(&blobs).unwrap();
+        fs::write(blobs.join("index.json"), r#"{"tile":{}}"#).unwrap();
+        fs::write(blobs.join("tile.blob"), [0u8, 1, 2, 3]).unwrap();
+        fs::write(blobs.join("tile.meta"), r#"{"key":"tile"}"#).unwrap();
+        fs::create_dir_all(old_data.join("http-cache").join("nested")).unwrap();
+        fs::write(old_data.join("http-cache").join("nested").join("entry"), "cached").unwrap();
+        fs::write(old_data.join("window-layout.json"), "{}").unwrap();
+        fs::write(old_data.join("restart-history.json"), "[]").unwrap();
+        fs::write(old_data.join("keychain-migration.json"), "{}").unwrap();
+        // Not a store: left behind.
+        fs::write(old_data.join(POINTER_FILE), "{}").unwrap();
+        let new_data = root.join("new");
+        fs::create_dir_all(&new_data).unwrap();
+        let new_logs = new_data.join("logs");
+
+        let items = plan_copy(&old_data, &old_logs, &new_data, &new_logs).unwrap();
+        assert_eq!(items.len(), 11);
+        migrate_files(&old_data, &items, Some(&new_data), |_| {}).unwrap();
+
+        assert_eq!(fs::read(new_data.join("blobs").join("tile.blob")).unwrap(), [0u8, 1, 2, 3]);
+        assert_eq!(fs::read_to_string(new_data.join("blobs").join("index.json")).unwrap(), r#"{"tile":{}}"#);
+        assert!(new_data.join("blobs").join("tile.meta").exists());
+        assert_eq!(fs::read_to_string(new_data.join("http-cache").join("nested").join("entry")).unwrap(), "cached");
+        for name in ["window-layout.json", "restart-history.json", "keychain-migration.json"] {
+            assert!(new_data.join(name).exists(), "{name} was not copied");
+        }
+        assert!(!new_data.join(POINTER_FILE).exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn a_failed_copy_leaves_the_old_directory_in_charge() {
+        let root = temp_dir("data-dir-migrate-fail");
+        let (old_data, old_logs) = seed_old_layout(&root);
+        let new_data = root.join("new");
+        fs::create_dir_all(&new_data).unwrap();
+        // A file where the logs directory should go makes the log copy fail
+        // after the cache and prefs have already been copied.
+        let new_logs = new_data.join("logs");
+        fs::write(&new_logs, "in the way").unwrap();
+
+        let items = plan_copy(&old_data, &old_logs, &new_data, &new_logs).unwrap();
+        let result = migrate_files(&old_data, &items, Some(&new_data), |_| {});
+        assert!(result.is_err());
+        assert_eq!(read_pointer(&old_data), None);
+        assert!(!new_data.join("persistent-cache.json").exists());
+        assert!(!new_data.join("runtime-prefs.json").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn unusable_directories_are_replaced_by_the_session_sandbox() {
+        let root = temp_dir("data-dir-prepare");
+        assert!(prepare_dir(Ok(root.join("fresh").join("data"))).is_ok());
+        assert!(root.join("fresh").join("data").is_dir());
+        // A file where the directory should be can't be created over.
+        fs::write(root.join("blocked"), "x").unwrap();
+        let reason = prepare_dir(Ok(root.join("blocked").join("data"))).unwrap_err();
+        assert!(reason.contains("Cannot create"), "{reason}");
+        let unresolved = prepare_dir(Err("Failed to resolve app data dir: no home".to_string())).unwrap_err();
+        assert_eq!(unresolved, "Failed to resolve app data dir: no home");
+
+        let sandbox = root.join("session");
+        assert_eq!(DegradedStorage::new(sandbox.clone(), None, None), None);
+        let data_only = DegradedStorage::new(sandbox.clone(), Some(unresolved), None).unwrap();
+        assert_eq!(data_only.data_dir(), Some(sandbox.join("data")));
+        assert_eq!(data_only.log_dir(), None);
+        let logs_only = DegradedStorage::new(sandbox.clone(), None, Some(reason)).unwrap();
+        assert_eq!(logs_only.data_dir(), None);
+        assert_eq!(logs_only.log_dir(), Some(sandbox.join("logs")));
+        assert_eq!(
+            serde_json::to_value(&data_only).unwrap()["dataDirError"],
+            "Failed to resolve app data dir: no home"
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,186 @@
+//! Per-source on/off switches, kept in the `dataSources` pref as
+//! `{ id: enabled }`. Sources are the providers behind the vault keys; a
+//! disabled source keeps its key in the vault, but the sidecar withholds it
+//! from the handlers so they stop polling. The list is passed at start as
+//! `LOCAL_API_DISABLED_SOURCES` and pushed to `/api/local-disabled-sources`
+//! when it changes while the sidecar runs. Unlisted ids default to enabled.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logging::log_event;
+use crate::{post_to_local_api, RuntimePrefs};
+
+pub(crate) const PREF_DATA_SOURCES: &str = "dataSources";
+pub(crate) const DISABLED_SOURCES_ENV: &str = "LOCAL_API_DISABLED_SOURCES";
+const CHANGED_EVENT: &str = "data-sources-changed";
+
+/// Source id and the vault keys it uses. Mirrors `SUPPORTED_SECRET_KEYS`
+/// and the sidecar's `DATA_SOURCE_KEYS`; every key belongs to one source.
+pub(crate) const DATA_SOURCES: [(&str, &[&str]); 31] = [
+    ("groq", &["GROQ_API_KEY"]),
+    ("openrouter", &["OPENROUTER_API_KEY"]),
+    ("fred", &["FRED_API_KEY"]),
+    ("eia", &["EIA_API_KEY"]),
+    ("cloudflare-radar", &["CLOUDFLARE_API_TOKEN"]),
+    ("acled", &["ACLED_ACCESS_TOKEN"]),
+    ("urlhaus", &["URLHAUS_AUTH_KEY"]),
+    ("otx", &["OTX_API_KEY"]),
+    ("abuseipdb", &["ABUSEIPDB_API_KEY"]),
+    ("wingbits", &["WINGBITS_API_KEY"]),
+    ("ws-relay", &["WS_RELAY_URL", "VITE_WS_RELAY_URL"]),
+    ("opensky", &["VITE_OPENSKY_RELAY_URL", "OPENSKY_CLIENT_ID", "OPENSKY_CLIENT_SECRET"]),
+    ("aisstream", &["AISSTREAM_API_KEY"]),
+    ("finnhub", &["FINNHUB_API_KEY"]),
+    ("nasa-firms", &["NASA_FIRMS_API_KEY"]),
+    ("ucdp", &["UCDP_ACCESS_TOKEN"]),
+    ("ollama", &["OLLAMA_API_URL", "OLLAMA_MODEL"]),
+    ("worldmonitor", &["WORLDMONITOR_API_KEY"]),
+    ("wto", &["WTO_API_KEY"]),
+    ("aviationstack", &["AVIATIONSTACK_API"]),
+    ("icao", &["ICAO_API_KEY"]),
+    ("portcast", &["PORTCAST_API_KEY"]),
+    ("global-fishing-watch", &["GLOBAL_FISHING_WATCH_API_KEY"]),
+    ("electricity-maps", &["ELECTRICITY_MAPS_API_KEY"]),
+    ("sentinel-hub", &["SENTINEL_HUB_CLIENT_ID", "SENTINEL_HUB_CLIENT_SECRET"]),
+    ("waqi", &["WAQI_API_TOKEN"]),
+    ("global-forest-watch", &["GLOBAL_FOREST_WATCH_API_KEY"]),
+    ("liveuamap", &["LIVEUAMAP_API_KEY"]),
+    ("whale-alert", &["WHALE_ALERT_API_KEY"]),
+    ("airframes", &["AIRFRAMES_API_KEY"]),
+    ("github", &["GITHUB_TOKEN"]),
+];
+
+pub(crate) fn is_known(id: &str) -> bool {
+    DATA_SOURCES.iter().any(|(known, _)| *known == id)
+}
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    let map = value
+        .as_object()
+        .ok_or_else(|| format!("Runtime pref {PREF_DATA_SOURCES} must be an object"))?;
+    for (id, enabled) in map {
+        if !is_known(id) {
+            return Err(format!("Unknown data source: {id}"));
+        }
+        if !enabled.is_boolean() {
+            return Err(format!("Runtime pref {PREF_DATA_SOURCES}.{id} must be a boolean"));
+        }
+    }
+    Ok(())
+}
+
+/// Every known source with its state, unknown ids in `stored` dropped.
+pub(crate) fn toggles(stored: Option<&Value>) -> BTreeMap<String, bool> {
+    let stored = stored.and_then(Value::as_object);
+    DATA_SOURCES
+        .iter()
+        .map(|(id, _)| {
+            let enabled = stored
+                .and_then(|map| map.get(*id))
+                .and_then(Value::as_bool)
+                .unwrap_or(true);
+            ((*id).to_string(), enabled)
+        })
+        .collect()
+}
+
+/// Disabled ids in `DATA_SOURCES` order.
+pub(crate) fn disabled(stored: Option<&Value>) -> Vec<&'static str> {
+    let toggles = toggles(stored);
+    DATA_SOURCES
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| toggles.get(*id) == Some(&false))
+        .collect()
+}
+
+/// Value for [`DISABLED_SOURCES_ENV`]; empty when everything is on.
+pub(crate) fn env_value(disabled: &[&str]) -> String {
+    disabled.join(",")
+}
+
+/// The stored map with `id` set, ready to persist.
+pub(crate) fn with_toggle(stored: Option<Value>, id: &str, enabled: bool) -> Value {
+    let mut map = stored
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default();
+    map.insert(id.to_string(), Value::Bool(enabled));
+    Value::Object(map)
+}
+
+pub(crate) fn stored(app: &AppHandle) -> Option<Value> {
+    app.try_state::<RuntimePrefs>().and_then(|prefs| prefs.get(PREF_DATA_SOURCES))
+}
+
+#[derive(Clone, Serialize)]
+struct DataSourcesChanged {
+    sources: BTreeMap<String, bool>,
+    disabled: Vec<&'static str>,
+}
+
+/// Tell the panels and, if it is running, the sidecar.
+pub(crate) fn publish(app: &AppHandle) {
+    let stored = stored(app);
+    let disabled = disabled(stored.as_ref());
+    let payload = DataSourcesChanged {
+        sources: toggles(stored.as_ref()),
+        disabled: disabled.clone(),
+    };
+    log_event(app, "INFO", "data_sources_changed", &[("disabled", &env_value(&disabled))]);
+    if let Err(err) = app.emit(CHANGED_EVENT, payload) {
+        log_event(app, "WARN", "data_sources_emit_failed", &[("error", &err.to_string())]);
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let body = serde_json::json!({ "sources": disabled });
+        if let Err(err) = post_to_local_api(&app, "/api/local-disabled-sources", body).await {
+            // The next start passes the list through the environment anyway.
+            log_event(&app, "WARN", "data_sources_push_failed", &[("error", &err)]);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{disabled, env_value, toggles, validate_pref, with_toggle, DATA_SOURCES};
+    use crate::SUPPORTED_SECRET_KEYS;
+
+    #[test]
+    fn sources_cover_each_secret_key_once() {
+        let mut keys: Vec<&str> = DATA_SOURCES.iter().flat_map(|(_, keys)| keys.iter().copied()).collect();
+        keys.sort();
+        let mut supported = SUPPORTED_SECRET_KEYS.to_vec();
+        supported.sort();
+        assert_eq!(keys, supported);
+    }
+
+    #[test]
+    fn pref_only_accepts_known_ids_and_booleans() {
+        assert!(validate_pref(&json!({"nasa-firms": false, "global-fishing-watch": true})).is_ok());
+        assert!(validate_pref(&json!({})).is_ok());
+        assert!(validate_pref(&json!({"NASA_FIRMS_API_KEY": false})).unwrap_err().contains("Unknown data source"));
+        assert!(validate_pref(&json!({"nasa-firms": "off"})).unwrap_err().contains("boolean"));
+        assert!(validate_pref(&json!(["nasa-firms"])).is_err());
+    }
+
+    #[test]
+    fn env_lists_disabled_sources_in_table_order() {
+        assert_eq!(env_value(&disabled(None)), "");
+        let stored = with_toggle(Some(json!({"global-fishing-watch": false})), "nasa-firms", false);
+        let stored = with_toggle(Some(stored), "fred", true);
+        assert_eq!(env_value(&disabled(Some(&stored))), "nasa-firms,global-fishing-watch");
+
+        // Hand-edited junk is ignored rather than disabling anything.
+        let junk = json!({"bogus": false, "acled": "no"});
+        assert_eq!(env_value(&disabled(Some(&junk))), "");
+        let all = toggles(Some(&junk));
+        assert_eq!(all.len(), DATA_SOURCES.len());
+        assert!(all.values().all(|enabled| *enabled));
+    }
+}
@@ -0,0 +1,235 @@
+//! Free-space guard for large writes. On a nearly full disk a cache flush
+//! would use up the last bytes and then fail halfway, leaving the sidecar to
+//! fail in stranger ways after it. Writes that would leave less than the
+//! `minFreeDiskMb` pref (500 MB by default; 0 turns the guard off) are
+//! refused with `disk_full`. The first refusal logs a WARN and emits
+//! `low-disk-space`; the event re-arms once space is back above the line.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_MIN_FREE_DISK_MB: &str = "minFreeDiskMb";
+const DEFAULT_MIN_FREE_DISK_MB: u64 = 500;
+const LOW_DISK_SPACE_EVENT: &str = "low-disk-space";
+
+pub(crate) fn validate_min_free_mb(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(_) => Ok(()),
+        None => Err(format!("Runtime pref {PREF_MIN_FREE_DISK_MB} must be a non-negative integer")),
+    }
+}
+
+fn threshold_bytes(app: &AppHandle) -> u64 {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_MIN_FREE_DISK_MB))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_MIN_FREE_DISK_MB)
+        .saturating_mul(1024 * 1024)
+}
+
+/// Whether writing `needed` bytes still leaves `threshold` free.
+fn has_room(free: u64, needed: u64, threshold: u64) -> bool {
+    threshold == 0 || free.saturating_sub(needed) >= threshold
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Verdict {
+    allowed: bool,
+    /// First refusal since space was last fine: tell the user.
+    notify: bool,
+}
+
+/// Managed state: whether the last check came up short.
+#[derive(Default)]
+pub(crate) struct DiskSpaceState {
+    low: AtomicBool,
+}
+
+impl DiskSpaceState {
+    fn evaluate(&self, free: u64, needed: u64, threshold: u64) -> Verdict {
+        let allowed = has_room(free, needed, threshold);
+        let was_low = self.low.swap(!allowed, Ordering::Relaxed);
+        Verdict {
+            allowed,
+            notify: !allowed && !was_low,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct LowDiskSpace {
+    path: String,
+    free_bytes: u64,
+    threshold_bytes: u64,
+}
+
+/// The nearest existing directory at or above `path`; the target file (and
+/// maybe its directory) doesn't exist yet.
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.is_dir()).map(Path::to_path_buf)
+}
+
+/// Free bytes available to this user on the volume holding `path`.
+pub(crate) fn free_bytes(path: &Path) -> io::Result<u64> {
+    let dir = existing_ancestor(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no existing ancestor"))?;
+    platform::free_bytes(&dir)
+}
+
+/// Free space on the data-dir volume, for resource usage and diagnostics.
+pub(crate) fn data_dir_free_bytes(app: &AppHandle) -> Option<u64> {
+    let dir = crate::data_dir::resolve_data_dir(app).ok()?;
+    free_bytes(&dir).ok()
+}
+
+/// Refuse a write of about `needed` bytes under `path` when it would leave
+/// less than the threshold free. Unknown free space lets the write through.
+pub(crate) fn ensure_space(app: &AppHandle, path: &Path, needed: u64) -> Result<(), DesktopError> {
+    let threshold = threshold_bytes(app);
+    if threshold == 0 {
+        return Ok(());
+    }
+    let Ok(free) = free_bytes(path) else {
+        return Ok(());
+    };
+    let verdict = match app.try_state::<DiskSpaceState>() {
+        Some(state) => state.evaluate(free, needed, threshold),
+        None => Verdict {
+            allowed: has_room(free, needed, threshold),
+            notify: false,
+        },
+    };
+    if verdict.allowed {
+        return Ok(());
+    }
+    let shown = path.display().to_string();
+    if verdict.notify {
+        log_event(
+            app,
+            "WARN",
+            "low_disk_space",
+            &[
+                ("path", &shown),
+                ("free_bytes", &free.to_string()),
+                ("threshold_bytes", &threshold.to_string()),
+            ],
+        );
+        let _ = app.emit(
+            LOW_DISK_SPACE_EVENT,
+            LowDiskSpace {
+                path: shown.clone(),
+                free_bytes: free,
+                threshold_bytes: threshold,
+            },
+        );
+    }
+    Err(DesktopError::DiskFull(format!(
+        "Not enough disk space to write {shown}: {} MB free, {} MB must stay free",
+        free / (1024 * 1024),
+        threshold / (1024 * 1024)
+    )))
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub(super) fn free_bytes(dir: &Path) -> io::Result<u64> {
+        let c_path = CString::new(dir.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: `c_path` is NUL-terminated and `stats` is a valid out-pointer.
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        #[allow(clippy::unnecessary_cast)]
+        Ok((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_to_caller: *mut u64,
+            total: *mut u64,
+            total_free: *mut u64,
+        ) -> i32;
+    }
+
+    pub(super) fn free_bytes(dir: &Path) -> io::Result<u64> {
+        let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut free_to_caller = 0u64;
+        // SAFETY: `wide` is NUL-terminated; the totals may be null.
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(wide.as_ptr(), &mut free_to_caller, std::ptr::null_mut(), std::ptr::null_mut())
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(free_to_caller)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{free_bytes, has_room, validate_min_free_mb, DiskSpaceState, Verdict};
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn the_write_must_leave_the_threshold_free() {
+        assert!(has_room(600 * MB, 50 * MB, 500 * MB));
+        assert!(has_room(550 * MB, 50 * MB, 500 * MB));
+        assert!(!has_room(549 * MB, 50 * MB, 500 * MB));
+        assert!(!has_room(10 * MB, 50 * MB, 500 * MB), "no underflow");
+        assert!(has_room(0, 50 * MB, 0), "0 disables the guard");
+    }
+
+    #[test]
+    fn low_space_is_reported_once_until_it_recovers() {
+        let state = DiskSpaceState::default();
+        let threshold = 500 * MB;
+        let refused = |notify| Verdict { allowed: false, notify };
+        let fine = Verdict { allowed: true, notify: false };
+
+        assert_eq!(state.evaluate(900 * MB, MB, threshold), fine);
+        assert_eq!(state.evaluate(400 * MB, MB, threshold), refused(true));
+        assert_eq!(state.evaluate(390 * MB, MB, threshold), refused(false));
+        assert_eq!(state.evaluate(380 * MB, MB, threshold), refused(false));
+        assert_eq!(state.evaluate(800 * MB, MB, threshold), fine);
+        assert_eq!(state.evaluate(300 * MB, MB, threshold), refused(true));
+    }
+
+    #[test]
+    fn free_space_is_read_from_the_nearest_existing_directory() {
+        let missing = std::env::temp_dir().join("wm-disk-space-test/not/yet/created.json");
+        assert!(free_bytes(&missing).is_ok());
+    }
+
+    #[test]
+    fn pref_is_a_non_negative_integer() {
+        assert!(validate_min_free_mb(&json!(0)).is_ok());
+        assert!(validate_min_free_mb(&json!(2048)).is_ok());
+        assert!(validate_min_free_mb(&json!(-1)).is_err());
+        assert!(validate_min_free_mb(&json!("500")).is_err());
+    }
+}
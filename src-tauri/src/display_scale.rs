@@ -0,0 +1,190 @@
+//! `forceScaleFactor`: a UI scale for Linux HiDPI setups where WebKitGTK and
+//! a fractional-scaling compositor disagree. GTK only takes an integer
+//! `GDK_SCALE`, so a fractional factor is split into that plus a text-only
+//! `GDK_DPI_SCALE`. Both are read at GTK init, so the pref is applied by the
+//! WebKit env policy before any window exists and changes need a restart.
+//! `get_display_info` reports what the monitors ask for so settings can
+//! suggest a value.
+
+use std::fs;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::{data_dir, RuntimePrefs, RUNTIME_PREFS_FILE};
+
+pub(crate) const PREF_FORCE_SCALE_FACTOR: &str = "forceScaleFactor";
+const MIN_SCALE: f64 = 0.5;
+const MAX_SCALE: f64 = 4.0;
+/// Suggestions are rounded to what scaling dialogs usually offer.
+const SUGGESTION_STEP: f64 = 0.25;
+
+/// `None` for `"auto"` or unset, which leaves GTK's own detection alone.
+pub(crate) fn parse_pref(value: &Value) -> Result<Option<f64>, String> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(s) if s == "auto" => Ok(None),
+        Value::Number(n) => match n.as_f64() {
+            Some(factor) if (MIN_SCALE..=MAX_SCALE).contains(&factor) => Ok(Some(factor)),
+            _ => Err(format!(
+                "Runtime pref {PREF_FORCE_SCALE_FACTOR} must be between {MIN_SCALE} and {MAX_SCALE}"
+            )),
+        },
+        _ => Err(format!("Runtime pref {PREF_FORCE_SCALE_FACTOR} must be a number or \"auto\"")),
+    }
+}
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    parse_pref(value).map(|_| ())
+}
+
+/// `4` decimals at most, without trailing zeros.
+fn format_factor(value: f64) -> String {
+    let formatted = format!("{value:.4}");
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `GDK_SCALE` (whole part, at least 1) and, when the rest isn't 1,
+/// `GDK_DPI_SCALE` for the remaining ratio.
+pub(crate) fn scale_env(factor: f64) -> Vec<(&'static str, String)> {
+    let whole = factor.floor().max(1.0);
+    let mut env = vec![("GDK_SCALE", format_factor(whole))];
+    let dpi = factor / whole;
+    if (dpi - 1.0).abs() > 1e-6 {
+        env.push(("GDK_DPI_SCALE", format_factor(dpi)));
+    }
+    env
+}
+
+/// The pref as saved on disk, read before Tauri (and `RuntimePrefs`) start.
+/// Anything unreadable or invalid counts as `"auto"`.
+pub(crate) fn read_launch_pref(identifier: &str) -> Option<f64> {
+    let path = data_dir::resolve_data_dir_before_app(identifier)?.join(RUNTIME_PREFS_FILE);
+    let prefs: Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    parse_pref(prefs.get(PREF_FORCE_SCALE_FACTOR)?).ok().flatten()
+}
+
+pub(crate) fn saved_pref(app: &AppHandle) -> Option<f64> {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_FORCE_SCALE_FACTOR))
+        .and_then(|value| parse_pref(&value).ok().flatten())
+}
+
+/// Managed state: the factor this launch applied. Always `None` off Linux.
+#[derive(Default)]
+pub(crate) struct DisplayScaleState {
+    pub(crate) applied: Option<f64>,
+}
+
+/// A factor for the densest monitor, or `None` when 1x already fits.
+pub(crate) fn suggest(scale_factors: &[f64]) -> Option<f64> {
+    let densest = scale_factors.iter().copied().fold(None, |max: Option<f64>, f| Some(max.map_or(f, |m| m.max(f))))?;
+    let rounded = ((densest / SUGGESTION_STEP).round() * SUGGESTION_STEP).clamp(MIN_SCALE, MAX_SCALE);
+    ((rounded - 1.0).abs() > f64::EPSILON).then_some(rounded)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct MonitorInfo {
+    name: Option<String>,
+    scale_factor: f64,
+    /// Physical pixels.
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    primary: bool,
+}
+
+/// `get_display_info` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct DisplayInfo {
+    monitors: Vec<MonitorInfo>,
+    /// `None` means `"auto"`.
+    force_scale_factor: Option<f64>,
+    applied_at_launch: Option<f64>,
+    suggested_scale_factor: Option<f64>,
+    /// The pref only has an effect on Linux.
+    supported: bool,
+}
+
+pub(crate) fn display_info(app: &AppHandle) -> DisplayInfo {
+    let primary = app.primary_monitor().ok().flatten().and_then(|m| m.name().cloned());
+    let monitors: Vec<MonitorInfo> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| MonitorInfo {
+            name: monitor.name().cloned(),
+            scale_factor: monitor.scale_factor(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            primary: primary.is_some() && monitor.name() == primary.as_ref(),
+        })
+        .collect();
+    let scales: Vec<f64> = monitors.iter().map(|m| m.scale_factor).collect();
+    DisplayInfo {
+        force_scale_factor: saved_pref(app),
+        applied_at_launch: app.state::<DisplayScaleState>().applied,
+        suggested_scale_factor: suggest(&scales),
+        monitors,
+        supported: cfg!(target_os = "linux"),
+    }
+}
+
+/// Returned by `set_force_scale_factor`.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ScaleFactorChange {
+    force_scale_factor: Option<f64>,
+    applied_at_launch: Option<f64>,
+    /// The saved value differs from what this launch applied.
+    restart_required: bool,
+}
+
+pub(crate) fn change_result(app: &AppHandle) -> ScaleFactorChange {
+    let saved = saved_pref(app);
+    let applied = app.state::<DisplayScaleState>().applied;
+    ScaleFactorChange {
+        force_scale_factor: saved,
+        applied_at_launch: applied,
+        restart_required: cfg!(target_os = "linux") && saved != applied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{parse_pref, scale_env, suggest};
+
+    #[test]
+    fn pref_accepts_auto_or_a_bounded_number() {
+        assert_eq!(parse_pref(&json!("auto")), Ok(None));
+        assert_eq!(parse_pref(&json!(null)), Ok(None));
+        assert_eq!(parse_pref(&json!(1.5)), Ok(Some(1.5)));
+        assert_eq!(parse_pref(&json!(2)), Ok(Some(2.0)));
+        for bad in [json!(0.25), json!(5), json!("1.5"), json!(true)] {
+            assert!(parse_pref(&bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn suggestion_follows_the_densest_monitor() {
+        assert_eq!(suggest(&[]), None);
+        assert_eq!(suggest(&[1.0, 1.0]), None);
+        assert_eq!(suggest(&[1.0, 1.6666]), Some(1.75));
+        assert_eq!(suggest(&[2.0]), Some(2.0));
+    }
+
+    #[test]
+    fn fractional_factors_split_into_gdk_scale_and_dpi() {
+        let env = |factor| scale_env(factor).into_iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>();
+        assert_eq!(env(2.0), ["GDK_SCALE=2"]);
+        assert_eq!(env(1.5), ["GDK_SCALE=1", "GDK_DPI_SCALE=1.5"]);
+        assert_eq!(env(2.5), ["GDK_SCALE=2", "GDK_DPI_SCALE=1.25"]);
+        assert_eq!(env(0.75), ["GDK_SCALE=1", "GDK_DPI_SCALE=0.75"]);
+        assert_eq!(env(2.2), ["GDK_SCALE=2", "GDK_DPI_SCALE=1.1"]);
+    }
+}
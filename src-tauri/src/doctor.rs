@@ -0,0 +1,357 @@
+//! Environment checks behind the settings "Doctor" tab. Each check is a plain
+//! blocking function in [`ENVIRONMENT_CHECKS`]; they run concurrently on their
+//! own threads and a check that overruns its timeout is reported as failed
+//! without holding up the rest.
+
+use std::fs;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::clock_skew;
+use crate::data_dir;
+use crate::diagnostics::node_version;
+use crate::extra_ca;
+use crate::logging::{log_event, now_iso8601};
+use crate::{
+    local_api_paths, logs_dir_path, preferred_local_api_port, resolve_node_binary, update_manifest_url,
+    LocalApiState, SecretsCache,
+};
+
+const MIN_NODE_MAJOR: u32 = 18;
+const WRITE_PROBE_FILE: &str = ".doctor-write-probe";
+const QUICK_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const NETWORK_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+type CheckOutcome = (CheckStatus, String);
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct CheckResult {
+    pub(crate) id: &'static str,
+    pub(crate) status: CheckStatus,
+    pub(crate) detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct EnvironmentReport {
+    /// Worst status across all checks.
+    pub(crate) overall: CheckStatus,
+    pub(crate) checked_at: String,
+    pub(crate) checks: Vec<CheckResult>,
+}
+
+/// One registry entry. `T` is the context handed to every check: the app
+/// handle in production, anything cloneable in tests.
+pub(crate) struct EnvironmentCheck<T> {
+    pub(crate) id: &'static str,
+    pub(crate) timeout: Duration,
+    pub(crate) run: fn(&T) -> CheckOutcome,
+}
+
+const ENVIRONMENT_CHECKS: &[EnvironmentCheck<AppHandle>] = &[
+    EnvironmentCheck { id: "node_binary", timeout: QUICK_CHECK_TIMEOUT, run: check_node_binary },
+    EnvironmentCheck { id: "sidecar_script", timeout: QUICK_CHECK_TIMEOUT, run: check_sidecar_script },
+    EnvironmentCheck { id: "local_api_port", timeout: QUICK_CHECK_TIMEOUT, run: check_local_api_port },
+    EnvironmentCheck { id: "keyring", timeout: QUICK_CHECK_TIMEOUT, run: check_keyring },
+    EnvironmentCheck { id: "app_data_dir", timeout: QUICK_CHECK_TIMEOUT, run: check_app_data_dir },
+    EnvironmentCheck { id: "app_log_dir", timeout: QUICK_CHECK_TIMEOUT, run: check_app_log_dir },
+    EnvironmentCheck { id: "https", timeout: NETWORK_CHECK_TIMEOUT, run: check_https },
+    EnvironmentCheck { id: "clock_skew", timeout: NETWORK_CHECK_TIMEOUT, run: check_clock_skew },
+];
+
+/// Run every check on its own thread and collect results in registry order.
+/// Each check's timeout is measured from the common start.
+pub(crate) fn run_checks<T: Clone + Send + 'static>(ctx: &T, checks: &[EnvironmentCheck<T>]) -> Vec<CheckResult> {
+    let started = Instant::now();
+    let pending: Vec<_> = checks
+        .iter()
+        .map(|check| {
+            let (tx, rx) = mpsc::channel();
+            let ctx = ctx.clone();
+            let run = check.run;
+            thread::spawn(move || {
+                let _ = tx.send(run(&ctx));
+            });
+            (check, rx)
+        })
+        .collect();
+
+    pending
+        .into_iter()
+        .map(|(check, rx)| {
+            let (status, detail) = match rx.recv_timeout(check.timeout.saturating_sub(started.elapsed())) {
+                Ok(outcome) => outcome,
+                Err(RecvTimeoutError::Timeout) => (
+                    CheckStatus::Fail,
+                    format!("Timed out after {}s", check.timeout.as_secs()),
+                ),
+                Err(RecvTimeoutError::Disconnected) => {
+                    (CheckStatus::Fail, "Check failed unexpectedly; see desktop.log".to_string())
+                }
+            };
+            CheckResult {
+                id: check.id,
+                status,
+                detail,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn overall_status(results: &[CheckResult]) -> CheckStatus {
+    results.iter().map(|r| r.status).max().unwrap_or(CheckStatus::Pass)
+}
+
+/// `("pass=5 warn=1 fail=1", "keyring,https")`: counts plus non-passing ids.
+pub(crate) fn summarize(results: &[CheckResult]) -> (String, String) {
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let counts = format!(
+        "pass={} warn={} fail={}",
+        count(CheckStatus::Pass),
+        count(CheckStatus::Warn),
+        count(CheckStatus::Fail)
+    );
+    let problems: Vec<&str> = results
+        .iter()
+        .filter(|r| r.status != CheckStatus::Pass)
+        .map(|r| r.id)
+        .collect();
+    (counts, problems.join(","))
+}
+
+pub(crate) fn run_environment_checks(app: &AppHandle) -> EnvironmentReport {
+    let checks = run_checks(app, ENVIRONMENT_CHECKS);
+    let overall = overall_status(&checks);
+    let (counts, problems) = summarize(&checks);
+    log_event(
+        app,
+        if overall == CheckStatus::Pass { "INFO" } else { "WARN" },
+        "environment_checks",
+        &[("overall", overall.as_str()), ("counts", &counts), ("problems", &problems)],
+    );
+    EnvironmentReport {
+        overall,
+        checked_at: now_iso8601(),
+        checks,
+    }
+}
+
+/// Major version from `node --version` output such as `v20.11.1`.
+pub(crate) fn parse_node_major(version: &str) -> Option<u32> {
+    version.trim().trim_start_matches('v').split('.').next()?.parse().ok()
+}
+
+fn check_node_binary(app: &AppHandle) -> CheckOutcome {
+    let Some(node) = resolve_node_binary(app) else {
+        return (
+            CheckStatus::Fail,
+            "Node.js executable not found. Install Node 18+ or set LOCAL_API_NODE_BIN".to_string(),
+        );
+    };
+    let Some(version) = node_version(&node) else {
+        return (CheckStatus::Fail, format!("{} --version failed", node.display()));
+    };
+    match parse_node_major(&version) {
+        Some(major) if major >= MIN_NODE_MAJOR => (CheckStatus::Pass, format!("{version} at {}", node.display())),
+        Some(_) => (
+            CheckStatus::Fail,
+            format!("{version} at {} is older than Node {MIN_NODE_MAJOR}", node.display()),
+        ),
+        None => (CheckStatus::Warn, format!("Unrecognized version {version:?} at {}", node.display())),
+    }
+}
+
+fn check_sidecar_script(app: &AppHandle) -> CheckOutcome {
+    let script = local_api_paths(app).script;
+    if script.is_file() {
+        (CheckStatus::Pass, script.display().to_string())
+    } else {
+        (CheckStatus::Fail, format!("Missing at {}", script.display()))
+    }
+}
+
+fn check_local_api_port(app: &AppHandle) -> CheckOutcome {
+    let state = app.state::<LocalApiState>();
+    let sidecar_running = state.child.lock().unwrap_or_else(|e| e.into_inner()).is_some();
+    let sidecar_port = *state.port.lock().unwrap_or_else(|e| e.into_inner());
+    if let (true, Some(port)) = (sidecar_running, sidecar_port) {
+        return (CheckStatus::Pass, format!("Port {port} is owned by the local API"));
+    }
+    let preferred = preferred_local_api_port(app);
+    match TcpListener::bind((Ipv4Addr::LOCALHOST, preferred)) {
+        Ok(_) => (CheckStatus::Pass, format!("Port {preferred} is available")),
+        Err(e) => (
+            CheckStatus::Warn,
+            format!("Port {preferred} is in use by another process ({e}); the local API will pick another"),
+        ),
+    }
+}
+
+fn check_keyring(app: &AppHandle) -> CheckOutcome {
+    match app.state::<SecretsCache>().store.probe() {
+        Ok(true) => (CheckStatus::Pass, "Write, read and delete succeeded".to_string()),
+        Ok(false) => (CheckStatus::Fail, "Keyring returned a different value than was written".to_string()),
+        Err(e) => (CheckStatus::Fail, format!("Keyring unavailable: {e}")),
+    }
+}
+
+fn check_dir_writable(dir: Result<std::path::PathBuf, String>) -> CheckOutcome {
+    let dir = match dir {
+        Ok(dir) => dir,
+        Err(e) => return (CheckStatus::Fail, e),
+    };
+    match probe_write(&dir) {
+        Ok(()) => (CheckStatus::Pass, dir.display().to_string()),
+        Err(e) => (CheckStatus::Fail, format!("{} is not writable: {e}", dir.display())),
+    }
+}
+
+fn probe_write(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(WRITE_PROBE_FILE);
+    fs::write(&probe, b"ok")?;
+    fs::remove_file(probe)
+}
+
+/// A directory replaced at startup fails with the reason, even though its
+/// stand-in is writable.
+fn check_app_data_dir(app: &AppHandle) -> CheckOutcome {
+    match data_dir::degraded().and_then(|degraded| degraded.data_dir_error()) {
+        Some(reason) => (CheckStatus::Fail, format!("Using a temporary session directory: {reason}")),
+        None => check_dir_writable(data_dir::resolve_data_dir(app)),
+    }
+}
+
+fn check_app_log_dir(app: &AppHandle) -> CheckOutcome {
+    match data_dir::degraded().and_then(|degraded| degraded.log_dir_error()) {
+        Some(reason) => (CheckStatus::Fail, format!("Using a temporary session directory: {reason}")),
+        None => check_dir_writable(logs_dir_path(app)),
+    }
+}
+
+/// Trusts the extra CA certificates, as every other outbound client does.
+fn check_https(app: &AppHandle) -> CheckOutcome {
+    let url = update_manifest_url();
+    let builder = match extra_ca::client_builder(app) {
+        Ok(builder) => builder,
+        Err(e) => return (CheckStatus::Fail, format!("Could not load the extra CA certificates: {e}")),
+    };
+    let response = tauri::async_runtime::block_on(async {
+        builder
+            .timeout(NETWORK_CHECK_TIMEOUT - Duration::from_secs(1))
+            .build()?
+            .head(&url)
+            .send()
+            .await
+    });
+    match response {
+        // Any HTTP response proves DNS, TCP and TLS work.
+        Ok(resp) => (CheckStatus::Pass, format!("{url} answered HTTP {}", resp.status().as_u16())),
+        Err(e) => (CheckStatus::Fail, format!("Could not reach {url}: {e}")),
+    }
+}
+
+/// Unreachable counts as a pass: the https check already reports that.
+fn check_clock_skew(app: &AppHandle) -> CheckOutcome {
+    let Some(skew) = tauri::async_runtime::block_on(clock_skew::refresh(app)) else {
+        return (CheckStatus::Pass, "Could not measure; skew unknown".to_string());
+    };
+    let direction = if skew.skew_ms >= 0 { "ahead" } else { "behind" };
+    let secs = skew.skew_ms.unsigned_abs() / 1000;
+    if clock_skew::report(app).exceeds_threshold {
+        (
+            CheckStatus::Warn,
+            format!("System clock is {secs}s {direction}; signed API requests may be rejected until it is corrected"),
+        )
+    } else {
+        (CheckStatus::Pass, format!("System clock is within {secs}s ({direction})"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        overall_status, parse_node_major, run_checks, summarize, CheckOutcome, CheckResult, CheckStatus,
+        EnvironmentCheck,
+    };
+    use std::time::Duration;
+
+    fn result(id: &'static str, status: CheckStatus) -> CheckResult {
+        CheckResult {
+            id,
+            status,
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn overall_is_the_worst_status() {
+        assert_eq!(overall_status(&[]), CheckStatus::Pass);
+        let results = [
+            result("a", CheckStatus::Pass),
+            result("b", CheckStatus::Warn),
+            result("c", CheckStatus::Pass),
+        ];
+        assert_eq!(overall_status(&results), CheckStatus::Warn);
+        let (counts, problems) = summarize(&results);
+        assert_eq!(counts, "pass=2 warn=1 fail=0");
+        assert_eq!(problems, "b");
+
+        let with_fail = [result("a", CheckStatus::Fail), result("b", CheckStatus::Warn)];
+        assert_eq!(overall_status(&with_fail), CheckStatus::Fail);
+        assert_eq!(summarize(&with_fail).1, "a,b");
+    }
+
+    fn passes(_: &()) -> CheckOutcome {
+        (CheckStatus::Pass, "ok".to_string())
+    }
+
+    fn hangs(_: &()) -> CheckOutcome {
+        std::thread::sleep(Duration::from_secs(5));
+        (CheckStatus::Pass, "late".to_string())
+    }
+
+    #[test]
+    fn hung_check_times_out_without_blocking_others() {
+        let checks = [
+            EnvironmentCheck::<()> { id: "slow", timeout: Duration::from_millis(50), run: hangs },
+            EnvironmentCheck::<()> { id: "fast", timeout: Duration::from_secs(2), run: passes },
+        ];
+        let started = std::time::Instant::now();
+        let results = run_checks(&(), &checks);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(results[0].id, "slow");
+        assert_eq!(results[0].status, CheckStatus::Fail);
+        assert_eq!(results[1], CheckResult { id: "fast", status: CheckStatus::Pass, detail: "ok".to_string() });
+    }
+
+    #[test]
+    fn parses_node_major_version() {
+        assert_eq!(parse_node_major("v20.11.1\n"), Some(20));
+        assert_eq!(parse_node_major("18.0.0"), Some(18));
+        assert_eq!(parse_node_major("nightly"), None);
+    }
+}
@@ -0,0 +1,320 @@
+//! Typed command errors. Serialized as `{ code, message, ...details }` so the
+//! frontend can branch on a stable `code` instead of matching English text;
+//! `message` stays human-readable for toasts and logs.
+
+use std::fmt;
+use std::path::Path;
+
+use serde::ser::{Serialize, Serializer};
+
+#[derive(Clone, Debug)]
+pub(crate) enum DesktopError {
+    /// The calling window may not use this command.
+    UntrustedWindow { label: String },
+    KeyringUnavailable(String),
+    UnsupportedSecretKey { key: String },
+    /// Waited for the local API to become ready; `state` is where it was.
+    SidecarTimeout { state: &'static str, message: String },
+    InvalidUrl(String),
+    InvalidArgument(String),
+    /// The OS refused a global shortcut, usually because another app holds it.
+    ShortcutUnavailable(String),
+    /// A native fetch waited too long for its host's rate limit.
+    RateLimited(String),
+    /// This platform's webview can't do it.
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    Unsupported(String),
+    /// A screen capture would include something protected.
+    CaptureBlocked(String),
+    /// The write would leave less free disk space than the guard allows.
+    DiskFull(String),
+    /// Polling is paused by the user; not a failure of the source.
+    PollingPaused(String),
+    /// A persistent-cache key that is empty, too long or not path-safe.
+    InvalidCacheKey(String),
+    /// One cache entry over the per-entry size limit.
+    CacheEntryTooLarge(String),
+    /// The write would take the persistent cache past its total size
+    /// limit; delete entries and retry.
+    CacheFull(String),
+    /// Nothing stored under the requested key.
+    NotFound(String),
+    /// A file path that didn't come from a recent `pick_save_path` or
+    /// `pick_open_path`; pick it again.
+    PathNotGranted(String),
+    /// The keychain vault no longer matches what was loaded; saving would
+    /// overwrite someone else's edit.
+    VaultChanged { local_keys: Vec<String>, keychain_keys: Vec<String> },
+    Io { path: Option<String>, message: String },
+    Http { status: Option<u16>, message: String },
+    Json(String),
+    Internal(String),
+}
+
+impl DesktopError {
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            DesktopError::UntrustedWindow { .. } => "untrusted_window",
+            DesktopError::KeyringUnavailable(_) => "keyring_unavailable",
+            DesktopError::UnsupportedSecretKey { .. } => "unsupported_secret_key",
+            DesktopError::SidecarTimeout { .. } => "sidecar_timeout",
+            DesktopError::InvalidUrl(_) => "invalid_url",
+            DesktopError::InvalidArgument(_) => "invalid_argument",
+            DesktopError::ShortcutUnavailable(_) => "shortcut_unavailable",
+            DesktopError::RateLimited(_) => "rate_limited",
+            DesktopError::Unsupported(_) => "unsupported",
+            DesktopError::CaptureBlocked(_) => "capture_blocked",
+            DesktopError::DiskFull(_) => "disk_full",
+            DesktopError::PollingPaused(_) => "polling_paused",
+            DesktopError::InvalidCacheKey(_) => "invalid_cache_key",
+            DesktopError::CacheEntryTooLarge(_) => "cache_entry_too_large",
+            DesktopError::CacheFull(_) => "cache_full",
+            DesktopError::NotFound(_) => "not_found",
+            DesktopError::PathNotGranted(_) => "path_not_granted",
+            DesktopError::VaultChanged { .. } => "vault_changed_externally",
+            DesktopError::Io { .. } => "io_error",
+            DesktopError::Http { .. } => "http_error",
+            DesktopError::Json(_) => "json_error",
+            DesktopError::Internal(_) => "internal",
+        }
+    }
+
+    /// An I/O failure on `path`; `action` reads like "Failed to write cache".
+    pub(crate) fn io(action: &str, path: &Path, err: impl fmt::Display) -> Self {
+        DesktopError::Io {
+            path: Some(path.display().to_string()),
+            message: format!("{action} {}: {err}", path.display()),
+        }
+    }
+
+    pub(crate) fn http(context: &str, err: reqwest::Error) -> Self {
+        DesktopError::Http {
+            status: err.status().map(|s| s.as_u16()),
+            message: format!("{context}: {err}"),
+        }
+    }
+}
+
+impl fmt::Display for DesktopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DesktopError::UntrustedWindow { label } => write!(f, "Command not allowed from window '{label}'"),
+            DesktopError::UnsupportedSecretKey { key } => write!(f, "Unsupported secret key: {key}"),
+            DesktopError::VaultChanged { .. } => {
+                f.write_str("The keychain vault was changed outside World Monitor; reload secrets before saving")
+            }
+            DesktopError::KeyringUnavailable(message)
+            | DesktopError::InvalidUrl(message)
+            | DesktopError::InvalidArgument(message)
+            | DesktopError::ShortcutUnavailable(message)
+            | DesktopError::RateLimited(message)
+            | DesktopError::Unsupported(message)
+            | DesktopError::CaptureBlocked(message)
+            | DesktopError::DiskFull(message)
+            | DesktopError::PollingPaused(message)
+            | DesktopError::InvalidCacheKey(message)
+            | DesktopError::CacheEntryTooLarge(message)
+            | DesktopError::CacheFull(message)
+            | DesktopError::NotFound(message)
+            | DesktopError::PathNotGranted(message)
+            | DesktopError::SidecarTimeout { message, .. }
+            | DesktopError::Io { message, .. }
+            | DesktopError::Http { message, .. }
+            | DesktopError::Json(message)
+            | DesktopError::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for DesktopError {}
+
+#[derive(serde::Serialize)]
+struct WireError<'a> {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<&'a str>,
+    #[serde(rename = "localKeys", skip_serializing_if = "Option::is_none")]
+    local_keys: Option<&'a [String]>,
+    #[serde(rename = "keychainKeys", skip_serializing_if = "Option::is_none")]
+    keychain_keys: Option<&'a [String]>,
+}
+
+impl Serialize for DesktopError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (path, status, key) = match self {
+            DesktopError::Io { path, .. } => (path.as_deref(), None, None),
+            DesktopError::Http { status, .. } => (None, *status, None),
+            DesktopError::UnsupportedSecretKey { key } => (None, None, Some(key.as_str())),
+            _ => (None, None, None),
+        };
+        let state = match self {
+            DesktopError::SidecarTimeout { state, .. } => Some(*state),
+            _ => None,
+        };
+        let (local_keys, keychain_keys) = match self {
+            DesktopError::VaultChanged { local_keys, keychain_keys } => {
+                (Some(local_keys.as_slice()), Some(keychain_keys.as_slice()))
+            }
+            _ => (None, None),
+        };
+        WireError {
+            code: self.code(),
+            message: self.to_string(),
+            path,
+            status,
+            key,
+            state,
+            local_keys,
+            keychain_keys,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<std::io::Error> for DesktopError {
+    fn from(err: std::io::Error) -> Self {
+        DesktopError::Io {
+            path: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<keyring::Error> for DesktopError {
+    fn from(err: keyring::Error) -> Self {
+        DesktopError::KeyringUnavailable(format!("Keyring error: {err}"))
+    }
+}
+
+impl From<reqwest::Error> for DesktopError {
+    fn from(err: reqwest::Error) -> Self {
+        DesktopError::http("HTTP request failed", err)
+    }
+}
+
+impl From<serde_json::Error> for DesktopError {
+    fn from(err: serde_json::Error) -> Self {
+        DesktopError::Json(err.to_string())
+    }
+}
+
+/// Helpers that still report `String` convert as `internal`.
+impl From<String> for DesktopError {
+    fn from(message: String) -> Self {
+        DesktopError::Internal(message)
+    }
+}
+
+/// Lets commands that still return `Result<_, String>` use `?` on helpers
+/// that were converted.
+impl From<DesktopError> for String {
+    fn from(err: DesktopError) -> Self {
+        err.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DesktopError;
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let unsupported = DesktopError::UnsupportedSecretKey { key: "NOPE".to_string() };
+        assert_eq!(
+            serde_json::to_value(&unsupported).unwrap(),
+            json!({ "code": "unsupported_secret_key", "message": "Unsupported secret key: NOPE", "key": "NOPE" })
+        );
+
+        let io = DesktopError::io("Failed to write cache", Path::new("/tmp/cache.json"), "disk full");
+        assert_eq!(
+            serde_json::to_value(&io).unwrap(),
+            json!({
+                "code": "io_error",
+                "message": "Failed to write cache /tmp/cache.json: disk full",
+                "path": "/tmp/cache.json",
+            })
+        );
+
+        let http = DesktopError::Http { status: Some(503), message: "Polymarket HTTP 503".to_string() };
+        assert_eq!(
+            serde_json::to_value(&http).unwrap(),
+            json!({ "code": "http_error", "message": "Polymarket HTTP 503", "status": 503 })
+        );
+
+        let untrusted = DesktopError::UntrustedWindow { label: "x".to_string() };
+        assert_eq!(
+            serde_json::to_value(&untrusted).unwrap(),
+            json!({ "code": "untrusted_window", "message": "Command not allowed from window 'x'" })
+        );
+
+        let timeout = DesktopError::SidecarTimeout {
+            state: "starting",
+            message: "The local API was not ready after 5000 ms (state: starting)".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&timeout).unwrap(),
+            json!({
+                "code": "sidecar_timeout",
+                "message": "The local API was not ready after 5000 ms (state: starting)",
+                "state": "starting",
+            })
+        );
+    }
+
+    #[test]
+    fn codes_are_stable() {
+        let codes: Vec<&str> = [
+            DesktopError::KeyringUnavailable(String::new()),
+            DesktopError::InvalidUrl(String::new()),
+            DesktopError::InvalidArgument(String::new()),
+            DesktopError::ShortcutUnavailable(String::new()),
+            DesktopError::RateLimited(String::new()),
+            DesktopError::Unsupported(String::new()),
+            DesktopError::CaptureBlocked(String::new()),
+            DesktopError::DiskFull(String::new()),
+            DesktopError::PollingPaused(String::new()),
+            DesktopError::InvalidCacheKey(String::new()),
+            DesktopError::CacheEntryTooLarge(String::new()),
+            DesktopError::CacheFull(String::new()),
+            DesktopError::NotFound(String::new()),
+            DesktopError::PathNotGranted(String::new()),
+            DesktopError::Json(String::new()),
+            DesktopError::from("boom".to_string()),
+        ]
+        .iter()
+        .map(DesktopError::code)
+        .collect();
+        assert_eq!(
+            codes,
+            [
+                "keyring_unavailable",
+                "invalid_url",
+                "invalid_argument",
+                "shortcut_unavailable",
+                "rate_limited",
+                "unsupported",
+                "capture_blocked",
+                "disk_full",
+                "polling_paused",
+                "invalid_cache_key",
+                "cache_entry_too_large",
+                "cache_full",
+                "not_found",
+                "path_not_granted",
+                "json_error",
+                "internal"
+            ]
+        );
+        let as_string: String = DesktopError::InvalidUrl("Invalid URL".to_string()).into();
+        assert_eq!(as_string, "Invalid URL");
+    }
+}
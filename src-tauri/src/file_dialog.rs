@@ -0,0 +1,459 @@
+//! Native open and save dialogs for commands that read or write a file the
+//! user chose. A webview never names a path on its own: `pick_save_path` and
+//! `pick_open_path` return the canonical path with a single-use grant, and
+//! the commands that touch the file redeem it through [`redeem`], so a
+//! compromised page can't point an export at `~/.ssh/config`. Grants expire
+//! after [`GRANT_TTL`]. The pickers are the platform's own dialogs, titled
+//! in the menu language, and nothing from the webview reaches a script or
+//! command line. They open in the directory last used for the same purpose,
+//! kept in the `fileDialogDirectories` pref, or else in Downloads.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use rfd::{AsyncFileDialog, FileHandle};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::i18n;
+use crate::logging::log_event;
+use crate::{store_runtime_pref, RuntimePrefs};
+
+/// Shell bookkeeping, like window geometry: not settable from a webview.
+const PREF_FILE_DIALOG_DIRECTORIES: &str = "fileDialogDirectories";
+pub(crate) const GRANT_TTL: Duration = Duration::from_secs(5 * 60);
+const MAX_GRANTS: usize = 32;
+const MAX_PURPOSES: usize = 32;
+const DEFAULT_PURPOSE: &str = "default";
+const MAX_FILTERS: usize = 8;
+const MAX_EXTENSIONS: usize = 16;
+
+/// One entry in a dialog's file type list.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub(crate) struct DialogFilter {
+    name: String,
+    extensions: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Access {
+    Save,
+    Open,
+}
+
+impl Access {
+    fn as_str(self) -> &'static str {
+        match self {
+            Access::Save => "saving",
+            Access::Open => "opening",
+        }
+    }
+}
+
+/// `pick_save_path` / `pick_open_path` result: pass both back to the
+/// command that uses the file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct PickedPath {
+    path: String,
+    grant: String,
+}
+
+struct Grant {
+    path: PathBuf,
+    access: Access,
+    issued: Instant,
+}
+
+/// Paths recently handed out by a dialog, by nonce.
+#[derive(Default)]
+pub(crate) struct GrantRegistry {
+    grants: HashMap<String, Grant>,
+}
+
+impl GrantRegistry {
+    fn prune(&mut self, now: Instant) {
+        self.grants
+            .retain(|_, grant| now.saturating_duration_since(grant.issued) < GRANT_TTL);
+    }
+
+    fn issue(&mut self, nonce: String, path: PathBuf, access: Access, now: Instant) {
+        self.prune(now);
+        if self.grants.len() >= MAX_GRANTS {
+            let oldest = self
+                .grants
+                .iter()
+                .min_by_key(|(_, grant)| grant.issued)
+                .map(|(nonce, _)| nonce.clone());
+            if let Some(oldest) = oldest {
+                self.grants.remove(&oldest);
+            }
+        }
+        self.grants.insert(nonce, Grant { path, access, issued: now });
+    }
+
+    /// Use up `nonce`, which must have been issued for `path` and `access`.
+    /// A mismatch burns the nonce too.
+    fn redeem(&mut self, nonce: &str, path: &Path, access: Access, now: Instant) -> Result<PathBuf, String> {
+        self.prune(now);
+        let grant = self
+            .grants
+            .remove(nonce)
+            .ok_or_else(|| "This file choice has expired or was already used; pick the file again".to_string())?;
+        if grant.access != access {
+            return Err(format!(
+                "{} was picked for {}, not {}",
+                grant.path.display(),
+                grant.access.as_str(),
+                access.as_str()
+            ));
+        }
+        if grant.path != path {
+            return Err(format!("{} is not the file that was picked", path.display()));
+        }
+        Ok(grant.path)
+    }
+}
+
+/// Managed state.
+#[derive(Default)]
+pub(crate) struct FileDialogState {
+    grants: Mutex<GrantRegistry>,
+}
+
+fn validate_filters(filters: &[DialogFilter]) -> Result<(), String> {
+    if filters.len() > MAX_FILTERS {
+        return Err(format!("At most {MAX_FILTERS} file filters are allowed"));
+    }
+    for filter in filters {
+        let name_ok = !filter.name.trim().is_empty()
+            && filter.name.chars().count() <= 64
+            && !filter.name.chars().any(|c| c.is_control() || c == '|');
+        if !name_ok {
+            return Err(format!("Invalid file filter name: {:?}", filter.name));
+        }
+        if filter.extensions.is_empty() || filter.extensions.len() > MAX_EXTENSIONS {
+            return Err(format!("File filter {} needs 1 to {MAX_EXTENSIONS} extensions", filter.name));
+        }
+        for extension in &filter.extensions {
+            if extension.is_empty() || extension.len() > 10 || !extension.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return Err(format!("Invalid file extension: {extension:?}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_default_name(name: &str) -> Result<(), String> {
+    let trimmed = name.trim();
+    let ok = !trimmed.is_empty()
+        && trimmed.len() <= 255
+        && trimmed != "."
+        && trimmed != ".."
+        && !trimmed.chars().any(|c| c.is_control() || c == '/' || c == '\\');
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("Invalid file name: {name:?}"))
+    }
+}
+
+fn validate_purpose(purpose: Option<&str>) -> Result<&str, String> {
+    let purpose = purpose.unwrap_or(DEFAULT_PURPOSE);
+    let ok = !purpose.is_empty()
+        && purpose.len() <= 32
+        && purpose.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+    if ok {
+        Ok(purpose)
+    } else {
+        Err(format!("Invalid dialog purpose: {purpose:?}"))
+    }
+}
+
+fn title_id(access: Access) -> &'static str {
+    match access {
+        Access::Save => "dialog.save",
+        Access::Open => "dialog.open",
+    }
+}
+
+struct DialogRequest<'a> {
+    access: Access,
+    start_dir: &'a Path,
+    default_name: Option<&'a str>,
+    filters: &'a [DialogFilter],
+    multiple: bool,
+}
+
+/// Show the dialog and wait; an empty list means the user cancelled. The
+/// dialog is created on the main thread, which macOS requires, and awaited
+/// on a helper thread so the event loop keeps running.
+fn show(app: &AppHandle, request: &DialogRequest) -> Result<Vec<PathBuf>, String> {
+    let mut dialog = AsyncFileDialog::new()
+        .set_title(i18n::text(i18n::language(app), title_id(request.access)))
+        .set_directory(request.start_dir)
+        .set_can_create_directories(true);
+    if let Some(name) = request.default_name {
+        dialog = dialog.set_file_name(name);
+    }
+    for filter in request.filters {
+        let extensions: Vec<&str> = filter.extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter(&filter.name, &extensions);
+    }
+    let (access, multiple) = (request.access, request.multiple);
+    let (tx, rx) = mpsc::sync_channel(1);
+    app.run_on_main_thread(move || {
+        let pending: Pin<Box<dyn Future<Output = Vec<FileHandle>> + Send>> = match (access, multiple) {
+            (Access::Save, _) => Box::pin(async move { dialog.save_file().await.into_iter().collect() }),
+            (Access::Open, true) => Box::pin(async move { dialog.pick_files().await.unwrap_or_default() }),
+            (Access::Open, false) => Box::pin(async move { dialog.pick_file().await.into_iter().collect() }),
+        };
+        std::thread::spawn(move || {
+            let _ = tx.send(tauri::async_runtime::block_on(pending));
+        });
+    })
+    .map_err(|e| format!("Failed to show the file dialog: {e}"))?;
+    let picked = rx.recv().map_err(|_| "The file dialog closed without an answer".to_string())?;
+    Ok(picked.iter().map(|handle| handle.path().to_path_buf()).collect())
+}
+
+fn remembered_dirs(app: &AppHandle) -> serde_json::Map<String, Value> {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_FILE_DIALOG_DIRECTORIES))
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default()
+}
+
+fn start_dir(app: &AppHandle, purpose: &str) -> PathBuf {
+    remembered_dirs(app)
+        .get(purpose)
+        .and_then(Value::as_str)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .or_else(|| app.path().download_dir().ok())
+        .or_else(|| app.path().home_dir().ok())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+fn remember_dir(app: &AppHandle, purpose: &str, dir: &Path) {
+    let mut dirs = remembered_dirs(app);
+    if !dirs.contains_key(purpose) && dirs.len() >= MAX_PURPOSES {
+        return;
+    }
+    dirs.insert(purpose.to_string(), Value::String(dir.display().to_string()));
+    if let Err(err) = store_runtime_pref(app, PREF_FILE_DIALOG_DIRECTORIES, Value::Object(dirs)) {
+        log_event(app, "WARN", "file_dialog_dir_save_failed", &[("error", &err.to_string())]);
+    }
+}
+
+/// The chosen file in its canonical directory; the file itself may not
+/// exist yet.
+fn canonical_save_path(path: &Path) -> Result<PathBuf, String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("Not a file path: {}", path.display()))?;
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .ok_or_else(|| format!("Not an absolute path: {}", path.display()))?;
+    let dir = parent
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve {}: {e}", parent.display()))?;
+    let full = dir.join(name);
+    if full.is_dir() {
+        return Err(format!("{} is a directory", full.display()));
+    }
+    Ok(full)
+}
+
+fn canonical_open_path(path: &Path) -> Result<PathBuf, String> {
+    let full = path
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve {}: {e}", path.display()))?;
+    if !full.is_file() {
+        return Err(format!("{} is not a file", full.display()));
+    }
+    Ok(full)
+}
+
+fn grant(app: &AppHandle, path: PathBuf, access: Access) -> PickedPath {
+    let nonce = crate::generate_local_token();
+    let picked = PickedPath {
+        path: path.display().to_string(),
+        grant: nonce.clone(),
+    };
+    app.state::<FileDialogState>()
+        .grants
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .issue(nonce, path, access, Instant::now());
+    picked
+}
+
+/// Ask where to save `default_name`; `None` when the user cancels.
+pub(crate) fn pick_save(
+    app: &AppHandle,
+    default_name: &str,
+    filters: &[DialogFilter],
+    purpose: Option<&str>,
+) -> Result<Option<PickedPath>, DesktopError> {
+    validate_default_name(default_name).map_err(DesktopError::InvalidArgument)?;
+    validate_filters(filters).map_err(DesktopError::InvalidArgument)?;
+    let purpose = validate_purpose(purpose).map_err(DesktopError::InvalidArgument)?;
+    let start = start_dir(app, purpose);
+    let request = DialogRequest {
+        access: Access::Save,
+        start_dir: &start,
+        default_name: Some(default_name.trim()),
+        filters,
+        multiple: false,
+    };
+    let Some(chosen) = show(app, &request)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let path = canonical_save_path(&chosen).map_err(DesktopError::InvalidArgument)?;
+    if let Some(dir) = path.parent() {
+        remember_dir(app, purpose, dir);
+    }
+    Ok(Some(grant(app, path, Access::Save)))
+}
+
+/// Ask for one or, with `multiple`, several files to open; empty when the
+/// user cancels.
+pub(crate) fn pick_open(
+    app: &AppHandle,
+    filters: &[DialogFilter],
+    multiple: bool,
+    purpose: Option<&str>,
+) -> Result<Vec<PickedPath>, DesktopError> {
+    validate_filters(filters).map_err(DesktopError::InvalidArgument)?;
+    let purpose = validate_purpose(purpose).map_err(DesktopError::InvalidArgument)?;
+    let start = start_dir(app, purpose);
+    let request = DialogRequest {
+        access: Access::Open,
+        start_dir: &start,
+        default_name: None,
+        filters,
+        multiple,
+    };
+    let paths = show(app, &request)?
+        .iter()
+        .map(|path| canonical_open_path(path))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(DesktopError::InvalidArgument)?;
+    if let Some(dir) = paths.first().and_then(|path| path.parent()) {
+        remember_dir(app, purpose, dir);
+    }
+    Ok(paths.into_iter().map(|path| grant(app, path, Access::Open)).collect())
+}
+
+/// For commands handed a path by a webview: the path, if `grant` was issued
+/// for it by a recent dialog.
+pub(crate) fn redeem(app: &AppHandle, grant: &str, path: &str, access: Access) -> Result<PathBuf, DesktopError> {
+    app.state::<FileDialogState>()
+        .grants
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .redeem(grant, Path::new(path), access, Instant::now())
+        .map_err(DesktopError::PathNotGranted)
+}
+
+/// [`redeem`] for commands whose path is optional and defaults to somewhere
+/// the shell picks.
+pub(crate) fn redeem_optional(
+    app: &AppHandle,
+    grant: Option<&str>,
+    path: Option<&str>,
+    access: Access,
+) -> Result<Option<PathBuf>, DesktopError> {
+    match (path.filter(|path| !path.trim().is_empty()), grant) {
+        (None, _) => Ok(None),
+        (Some(path), Some(grant)) => redeem(app, grant, path, access).map(Some),
+        (Some(path), None) => Err(DesktopError::PathNotGranted(format!(
+            "{path} was not picked in a file dialog; use pick_save_path"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    use super::{
+        validate_default_name, validate_filters, validate_purpose, Access, DialogFilter, GrantRegistry, GRANT_TTL,
+        MAX_GRANTS,
+    };
+
+    fn json_filter() -> Vec<DialogFilter> {
+        vec![DialogFilter {
+            name: "Configuration".to_string(),
+            extensions: vec!["json".to_string()],
+        }]
+    }
+
+    #[test]
+    fn a_grant_is_single_use_and_expires() {
+        let t0 = Instant::now();
+        let path = Path::new("/home/u/Downloads/config.json");
+        let mut grants = GrantRegistry::default();
+        grants.issue("n1".into(), path.to_path_buf(), Access::Save, t0);
+        assert_eq!(grants.redeem("n1", path, Access::Save, t0 + Duration::from_secs(1)), Ok(path.to_path_buf()));
+        assert!(grants.redeem("n1", path, Access::Save, t0 + Duration::from_secs(2)).is_err(), "used up");
+
+        grants.issue("n2".into(), path.to_path_buf(), Access::Save, t0);
+        assert!(grants.redeem("n2", path, Access::Save, t0 + GRANT_TTL).is_err(), "expired");
+        assert!(grants.redeem("unknown", path, Access::Save, t0).is_err());
+    }
+
+    #[test]
+    fn a_grant_only_covers_its_own_path_and_access() {
+        let t0 = Instant::now();
+        let picked = Path::new("/home/u/Downloads/config.json");
+        let mut grants = GrantRegistry::default();
+        grants.issue("n1".into(), picked.to_path_buf(), Access::Save, t0);
+        assert!(grants.redeem("n1", Path::new("/home/u/.ssh/config"), Access::Save, t0).is_err());
+        assert!(grants.redeem("n1", picked, Access::Save, t0).is_err(), "a mismatch burns the grant");
+
+        grants.issue("n2".into(), picked.to_path_buf(), Access::Open, t0);
+        assert!(grants.redeem("n2", picked, Access::Save, t0).is_err(), "opened, not saved");
+    }
+
+    #[test]
+    fn the_oldest_grant_makes_room() {
+        let t0 = Instant::now();
+        let mut grants = GrantRegistry::default();
+        for n in 0..=MAX_GRANTS as u64 {
+            grants.issue(format!("n{n}"), PathBuf::from(format!("/tmp/{n}")), Access::Open, t0 + Duration::from_millis(n));
+        }
+        assert_eq!(grants.grants.len(), MAX_GRANTS);
+        assert!(grants.redeem("n0", Path::new("/tmp/0"), Access::Open, t0).is_err());
+        assert!(grants.redeem("n1", Path::new("/tmp/1"), Access::Open, t0).is_ok());
+    }
+
+    #[test]
+    fn arguments_from_the_webview_are_checked() {
+        assert!(validate_filters(&json_filter()).is_ok());
+        let bad = |name: &str, extension: &str| {
+            validate_filters(&[DialogFilter {
+                name: name.to_string(),
+                extensions: vec![extension.to_string()],
+            }])
+        };
+        assert!(bad("Config | *.*", "json").is_err());
+        assert!(bad("Config", "*").is_err());
+        assert!(bad("Config", "json;rm").is_err());
+        assert!(validate_default_name("world-monitor-config.json").is_ok());
+        for name in ["", "..", "../x.json", "a\\b.json", "a\nb"] {
+            assert!(validate_default_name(name).is_err(), "{name:?}");
+        }
+        assert_eq!(validate_purpose(None), Ok("default"));
+        assert_eq!(validate_purpose(Some("secrets-backup")), Ok("secrets-backup"));
+        assert!(validate_purpose(Some("../prefs")).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! Find in page for long news and report panels. Edit > Find emits
+//! `open-find-bar` to the focused window, which renders its own input and
+//! calls `find_in_page`; the webview does the highlighting. Repeating the
+//! same query advances through the matches. WebKitGTK's find controller and
+//! WebView2's find API report a match count; WKWebView only says whether
+//! anything matched. Where the webview can't search at all (WebView2 runtimes
+//! before the find API) the result is `supported: false` and the frontend
+//! falls back to its JS finder.
+//!
+//! A window's session ends on `stop_find`, navigation, reload or close.
+
+use std::collections::BTreeMap;
+#[cfg(target_os = "linux")]
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use std::sync::mpsc;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::error::DesktopError;
+
+pub(crate) const MENU_EDIT_FIND_ID: &str = "edit.find";
+const OPEN_FIND_BAR_EVENT: &str = "open-find-bar";
+const MAX_QUERY_CHARS: usize = 500;
+/// WebKit stops counting here; the UI shows "1000+".
+#[cfg(target_os = "linux")]
+const MAX_MATCHES: u32 = 1000;
+/// How long to wait for the webview to report the matches.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct FindOptions {
+    pub(crate) forward: bool,
+    pub(crate) match_case: bool,
+}
+
+impl Default for FindOptions {
+    fn default() -> Self {
+        FindOptions {
+            forward: true,
+            match_case: false,
+        }
+    }
+}
+
+/// `find_in_page` result.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct FindResult {
+    /// False when this platform's webview can't search; use the JS finder.
+    supported: bool,
+    /// None when the webview didn't report in time, or (WKWebView) found
+    /// matches without counting them.
+    match_count: Option<u32>,
+    /// 1-based position of the highlighted match.
+    active_match: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct FindSession {
+    query: String,
+    match_case: bool,
+    match_count: Option<u32>,
+    active_match: Option<u32>,
+}
+
+/// What a call does given the window's current session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Start,
+    Next,
+    Previous,
+}
+
+fn step_for(session: Option<&FindSession>, query: &str, options: FindOptions) -> Step {
+    match session {
+        Some(session) if session.query == query && session.match_case == options.match_case => {
+            if options.forward {
+                Step::Next
+            } else {
+                Step::Previous
+            }
+        }
+        _ => Step::Start,
+    }
+}
+
+/// The match highlighted after `step`, wrapping at either end.
+fn active_after(step: Step, previous: Option<u32>, count: u32, forward: bool) -> Option<u32> {
+    if count == 0 {
+        return None;
+    }
+    Some(match (step, previous) {
+        (Step::Start, _) | (_, None) => {
+            if forward {
+                1
+            } else {
+                count
+            }
+        }
+        (Step::Next, Some(index)) => index % count + 1,
+        (Step::Previous, Some(index)) => (index + count - 2) % count + 1,
+    })
+}
+
+fn validate_query(query: &str) -> Result<(), DesktopError> {
+    if query.is_empty() {
+        return Err(DesktopError::InvalidArgument("Find query is empty".to_string()));
+    }
+    if query.chars().count() > MAX_QUERY_CHARS {
+        return Err(DesktopError::InvalidArgument(format!(
+            "Find query is longer than {MAX_QUERY_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Managed state: the find session per window.
+#[derive(Default)]
+pub(crate) struct FindState {
+    sessions: Mutex<BTreeMap<String, FindSession>>,
+    /// Waiting `find_in_page` calls, answered from WebKit's signals.
+    #[cfg(target_os = "linux")]
+    pending: Mutex<BTreeMap<String, mpsc::Sender<u32>>>,
+    /// Windows whose find controller has our signal handlers.
+    #[cfg(target_os = "linux")]
+    hooked: Mutex<BTreeSet<String>>,
+}
+
+impl FindState {
+    fn session(&self, label: &str) -> Option<FindSession> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner()).get(label).cloned()
+    }
+
+    fn store(&self, label: &str, session: Option<FindSession>) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        match session {
+            Some(session) => sessions.insert(label.to_string(), session),
+            None => sessions.remove(label),
+        };
+    }
+}
+
+/// The page changed under the session (navigation or reload); the next
+/// call starts over.
+pub(crate) fn clear_session(app: &AppHandle, label: &str) {
+    if let Some(state) = app.try_state::<FindState>() {
+        state.store(label, None);
+    }
+}
+
+/// Drop everything kept for a closed window.
+pub(crate) fn forget_window(app: &AppHandle, label: &str) {
+    clear_session(app, label);
+    #[cfg(target_os = "linux")]
+    if let Some(state) = app.try_state::<FindState>() {
+        state.pending.lock().unwrap_or_else(|e| e.into_inner()).remove(label);
+        state.hooked.lock().unwrap_or_else(|e| e.into_inner()).remove(label);
+    }
+}
+
+/// Edit > Find: ask the focused app window to show its find bar.
+pub(crate) fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+    if id != MENU_EDIT_FIND_ID {
+        return false;
+    }
+    let target = app
+        .webview_windows()
+        .into_values()
+        .filter(|window| crate::require_trusted_window(window.label()).is_ok())
+        .find(|window| window.is_focused().unwrap_or(false))
+        .or_else(|| app.get_webview_window("main"));
+    if let Some(window) = target {
+        let _ = window.emit_to(window.label(), OPEN_FIND_BAR_EVENT, ());
+    }
+    true
+}
+
+fn window(app: &AppHandle, label: &str) -> Result<WebviewWindow, DesktopError> {
+    crate::require_trusted_window(label)?;
+    app.get_webview_window(label)
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("No open window: {label}")))
+}
+
+/// Search `label` for `query`, or move to the next or previous match when
+/// the query is unchanged. Blocks until the webview answers.
+pub(crate) fn find(app: &AppHandle, label: &str, query: &str, options: FindOptions) -> Result<FindResult, DesktopError> {
+    validate_query(query)?;
+    let window = window(app, label)?;
+    let state = app.state::<FindState>();
+    let session = state.session(label);
+    let step = step_for(session.as_ref(), query, options);
+    let Some(reported) = platform_find(app, &window, step, query, options)? else {
+        return Ok(FindResult::default());
+    };
+    // Stepping doesn't always re-report the count; keep the one we have.
+    let match_count = reported.or_else(|| session.as_ref().and_then(|s| s.match_count));
+    let previous = session.as_ref().filter(|_| step != Step::Start).and_then(|s| s.active_match);
+    let active_match = match_count.and_then(|count| active_after(step, previous, count, options.forward));
+    state.store(
+        label,
+        Some(FindSession {
+            query: query.to_string(),
+            match_case: options.match_case,
+            match_count,
+            active_match,
+        }),
+    );
+    Ok(FindResult {
+        supported: true,
+        match_count,
+        active_match,
+    })
+}
+
+/// Clear the highlights in `label` and end its session.
+pub(crate) fn stop(app: &AppHandle, label: &str) -> Result<(), DesktopError> {
+    let window = window(app, label)?;
+    clear_session(app, label);
+    platform_stop(&window)
+}
+
+/// `Ok(None)` when the platform can't search; otherwise the match count
+/// the webview reported, if it did in time.
+#[cfg(target_os = "linux")]
+fn platform_find(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    step: Step,
+    query: &str,
+    options: FindOptions,
+) -> Result<Option<Option<u32>>, DesktopError> {
+    let label = window.label().to_string();
+    let state = app.state::<FindState>();
+    let (tx, rx) = mpsc::channel();
+    state.pending.lock().unwrap_or_else(|e| e.into_inner()).insert(label.clone(), tx);
+    let hook = state.hooked.lock().unwrap_or_else(|e| e.into_inner()).insert(label.clone());
+
+    let mut flags = webkit2gtk::FindOptions::WRAP_AROUND;
+    if !options.match_case {
+        flags |= webkit2gtk::FindOptions::CASE_INSENSITIVE;
+    }
+    if !options.forward {
+        flags |= webkit2gtk::FindOptions::BACKWARDS;
+    }
+    let query = query.to_string();
+    let handle = app.clone();
+    window
+        .with_webview(move |webview| {
+            use webkit2gtk::{FindControllerExt, WebViewExt};
+            let Some(controller) = webview.inner().find_controller() else {
+                return;
+            };
+            if hook {
+                let found = (handle.clone(), label.clone());
+                controller.connect_found_text(move |_, count| reply(&found.0, &found.1, count));
+                let failed = (handle.clone(), label.clone());
+                controller.connect_failed_to_find_text(move |_| reply(&failed.0, &failed.1, 0));
+            }
+            match step {
+                Step::Start => controller.search(&query, flags.bits(), MAX_MATCHES),
+                Step::Next => controller.search_next(),
+                Step::Previous => controller.search_previous(),
+            }
+        })
+        .map_err(|e| DesktopError::Internal(format!("Find in page: {e}")))?;
+
+    let count = rx.recv_timeout(REPLY_TIMEOUT).ok();
+    Ok(Some(count))
+}
+
+/// Hand WebKit's answer to the `find_in_page` call waiting on `label`.
+#[cfg(target_os = "linux")]
+fn reply(app: &AppHandle, label: &str, count: u32) {
+    let sender = app
+        .try_state::<FindState>()
+        .and_then(|state| state.pending.lock().unwrap_or_else(|e| e.into_inner()).remove(label));
+    if let Some(sender) = sender {
+        let _ = sender.send(count);
+    }
+}
+
+/// WKWebView answers with found or not found; a hit has no count.
+#[cfg(target_os = "macos")]
+fn platform_find(
+    _app: &AppHandle,
+    window: &WebviewWindow,
+    step: Step,
+    query: &str,
+    options: FindOptions,
+) -> Result<Option<Option<u32>>, DesktopError> {
+    let (tx, rx) = mpsc::channel::<bool>();
+    let query = query.to_string();
+    // Start searches from the selection, like Next; WebKit wraps either way.
+    let backwards = match step {
+        Step::Start => !options.forward,
+        Step::Next => false,
+        Step::Previous => true,
+    };
+    let queued = window.with_webview(move |webview| unsafe {
+        use block2::RcBlock;
+        use objc2::MainThreadMarker;
+        use objc2_foundation::NSString;
+        use objc2_web_kit::{WKFindConfiguration, WKFindResult, WKWebView};
+
+        // with_webview runs this on the main thread.
+        let Some(main_thread) = MainThreadMarker::new() else {
+            return;
+        };
+        let view: &WKWebView = &*webview.inner().cast();
+        let configuration = WKFindConfiguration::new(main_thread);
+        configuration.setBackwards(backwards);
+        configuration.setCaseSensitive(options.match_case);
+        configuration.setWraps(true);
+        let completion = RcBlock::new(move |result: std::ptr::NonNull<WKFindResult>| {
+            let _ = tx.send(result.as_ref().matchFound());
+        });
+        view.findString_withConfiguration_completionHandler(
+            &NSString::from_str(&query),
+            Some(&configuration),
+            &completion,
+        );
+    });
+    queued.map_err(|e| DesktopError::Internal(format!("Find in page: {e}")))?;
+
+    let count = match rx.recv_timeout(REPLY_TIMEOUT) {
+        Ok(false) => Some(0),
+        Ok(true) | Err(_) => None,
+    };
+    Ok(Some(count))
+}
+
+/// WebView2's find session; `Ok(None)` on runtimes without it.
+#[cfg(target_os = "windows")]
+fn platform_find(
+    _app: &AppHandle,
+    window: &WebviewWindow,
+    step: Step,
+    query: &str,
+    options: FindOptions,
+) -> Result<Option<Option<u32>>, DesktopError> {
+    // Dropped unsent when the runtime has no find API.
+    let (tx, rx) = mpsc::channel::<Option<u32>>();
+    let term: Vec<u16> = query.encode_utf16().chain(std::iter::once(0)).collect();
+    let queued = window.with_webview(move |webview| unsafe {
+        use webview2_com::FindStartCompletedHandler;
+        use webview2_com::Microsoft::Web::WebView2::Win32::{
+            ICoreWebView2Environment15, ICoreWebView2Find, ICoreWebView2_2, ICoreWebView2_28,
+        };
+        use windows_core::{Interface, PCWSTR};
+
+        fn match_count(find: &ICoreWebView2Find) -> Option<u32> {
+            let mut count = 0;
+            unsafe { find.MatchCount(&mut count) }.ok()?;
+            u32::try_from(count).ok()
+        }
+
+        let Ok(core) = webview.controller().CoreWebView2() else {
+            return;
+        };
+        let Ok(find) = core.cast::<ICoreWebView2_28>().and_then(|core| core.Find()) else {
+            return;
+        };
+        match step {
+            Step::Start => {
+                let Ok(find_options) = core
+                    .cast::<ICoreWebView2_2>()
+                    .and_then(|core| core.Environment())
+                    .and_then(|environment| environment.cast::<ICoreWebView2Environment15>())
+                    .and_then(|environment| environment.CreateFindOptions())
+                else {
+                    return;
+                };
+                let configured = find_options
+                    .SetFindTerm(PCWSTR::from_raw(term.as_ptr()))
+                    .and_then(|()| find_options.SetIsCaseSensitive(options.match_case))
+                    .and_then(|()| find_options.SetShouldHighlightAllMatches(true));
+                if configured.is_err() {
+                    return;
+                }
+                let started = find.clone();
+                let handler = FindStartCompletedHandler::create(Box::new(move |result| {
+                    // Start lands on the first match; backwards begins at the last.
+                    if result.is_ok() && !options.forward {
+                        let _ = started.FindPrevious();
+                    }
+                    let _ = tx.send(result.ok().and_then(|()| match_count(&started)));
+                    Ok(())
+                }));
+                let _ = find.Start(&find_options, &handler);
+            }
+            Step::Next => {
+                if find.FindNext().is_ok() {
+                    let _ = tx.send(match_count(&find));
+                }
+            }
+            Step::Previous => {
+                if find.FindPrevious().is_ok() {
+                    let _ = tx.send(match_count(&find));
+                }
+            }
+        }
+    });
+    queued.map_err(|e| DesktopError::Internal(format!("Find in page: {e}")))?;
+
+    match rx.recv_timeout(REPLY_TIMEOUT) {
+        Ok(count) => Ok(Some(count)),
+        Err(mpsc::RecvTimeoutError::Timeout) => Ok(Some(None)),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Ok(None),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_find(
+    _app: &AppHandle,
+    _window: &WebviewWindow,
+    _step: Step,
+    _query: &str,
+    _options: FindOptions,
+) -> Result<Option<Option<u32>>, DesktopError> {
+    Ok(None)
+}
+
+#[cfg(target_os = "linux")]
+fn platform_stop(window: &WebviewWindow) -> Result<(), DesktopError> {
+    window
+        .with_webview(|webview| {
+            use webkit2gtk::{FindControllerExt, WebViewExt};
+            if let Some(controller) = webview.inner().find_controller() {
+                controller.search_finish();
+            }
+        })
+        .map_err(|e| DesktopError::Internal(format!("Stop find: {e}")))
+}
+
+/// WKWebView has no find session to end; its highlight is the selection.
+#[cfg(target_os = "macos")]
+fn platform_stop(window: &WebviewWindow) -> Result<(), DesktopError> {
+    window
+        .eval("window.getSelection()?.removeAllRanges()")
+        .map_err(|e| DesktopError::Internal(format!("Stop find: {e}")))
+}
+
+#[cfg(target_os = "windows")]
+fn platform_stop(window: &WebviewWindow) -> Result<(), DesktopError> {
+    window
+        .with_webview(|webview| unsafe {
+            use webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2_28;
+            use windows_core::Interface;
+            let find = webview
+                .controller()
+                .CoreWebView2()
+                .and_then(|core| core.cast::<ICoreWebView2_28>())
+                .and_then(|core| core.Find());
+            if let Ok(find) = find {
+                let _ = find.Stop();
+            }
+        })
+        .map_err(|e| DesktopError::Internal(format!("Stop find: {e}")))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_stop(_window: &WebviewWindow) -> Result<(), DesktopError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{active_after, step_for, validate_query, FindOptions, FindSession, Step};
+
+    fn session(query: &str, match_case: bool) -> FindSession {
+        FindSession {
+            query: query.to_string(),
+            match_case,
+            match_count: Some(3),
+            active_match: Some(1),
+        }
+    }
+
+    #[test]
+    fn repeating_a_query_advances_instead_of_restarting() {
+        let forward = FindOptions::default();
+        let backward = FindOptions { forward: false, ..forward };
+        assert_eq!(step_for(None, "iran", forward), Step::Start);
+        assert_eq!(step_for(Some(&session("iran", false)), "iran", forward), Step::Next);
+        assert_eq!(step_for(Some(&session("iran", false)), "iran", backward), Step::Previous);
+        assert_eq!(step_for(Some(&session("iran", false)), "Iran", forward), Step::Start);
+        let match_case = FindOptions { match_case: true, ..forward };
+        assert_eq!(step_for(Some(&session("iran", false)), "iran", match_case), Step::Start);
+    }
+
+    #[test]
+    fn active_match_wraps_at_both_ends() {
+        assert_eq!(active_after(Step::Start, None, 3, true), Some(1));
+        assert_eq!(active_after(Step::Start, Some(2), 3, false), Some(3));
+        assert_eq!(active_after(Step::Next, Some(2), 3, true), Some(3));
+        assert_eq!(active_after(Step::Next, Some(3), 3, true), Some(1));
+        assert_eq!(active_after(Step::Previous, Some(1), 3, false), Some(3));
+        assert_eq!(active_after(Step::Previous, Some(3), 3, false), Some(2));
+        assert_eq!(active_after(Step::Next, Some(1), 0, true), None);
+    }
+
+    #[test]
+    fn queries_must_be_non_empty_and_bounded() {
+        assert!(validate_query("strait of hormuz").is_ok());
+        assert!(validate_query("").is_err());
+        assert!(validate_query(&"x".repeat(501)).is_err());
+    }
+}
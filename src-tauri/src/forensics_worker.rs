@@ -0,0 +1,531 @@
+//! Optional second sidecar process for forensics. The orchestrator sends its
+//! fusion and anomaly stages to `FORENSICS_WORKER_URL` when that is set and
+//! otherwise runs them inside the API process, where a long job stalls every
+//! data route and a crash takes all feeds down with it. With the
+//! `separateForensicsWorker` pref the shell starts the same script a second
+//! time in `forensics-worker` mode, on a port of its own and logging to
+//! `forensics-worker.log`, and points the API process at it. The worker
+//! serves only the internal forensics routes, to callers holding the shared
+//! secret. It is stopped with the API, watched and restarted with the same
+//! backoff and breaker, and while it is down forensics runs in-process again.
+
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::MutexGuard;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::logging::{self, log_event};
+use crate::sidecar_history::{ExitDecision, LifecycleKind, SidecarHistory};
+use crate::{post_to_local_api, LocalApiState, RuntimePrefs};
+
+pub(crate) const PREF_SEPARATE_FORENSICS_WORKER: &str = "separateForensicsWorker";
+pub(crate) const URL_ENV: &str = "FORENSICS_WORKER_URL";
+pub(crate) const SECRET_ENV: &str = "FORENSICS_WORKER_SHARED_SECRET";
+const WORKER_MODE: &str = "forensics-worker";
+const LOG_FILE: &str = "forensics-worker.log";
+const PORT_FILE: &str = "forensics-worker.port";
+const PORT_WAIT_MS: u64 = 5000;
+const ENDPOINT_PATH: &str = "/api/local-forensics-worker";
+
+/// How a watched process ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Exit {
+    code: Option<i32>,
+    signal: Option<i32>,
+}
+
+/// The part of a child process the bookkeeping needs; `Child` outside tests.
+pub(crate) trait WorkerProcess {
+    fn id(&self) -> u32;
+    /// `Some` once the process has exited.
+    fn try_exit(&mut self) -> Option<Exit>;
+}
+
+impl WorkerProcess for Child {
+    fn id(&self) -> u32 {
+        Child::id(self)
+    }
+
+    fn try_exit(&mut self) -> Option<Exit> {
+        let status = self.try_wait().ok().flatten()?;
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+        Some(Exit {
+            code: status.code(),
+            signal,
+        })
+    }
+}
+
+/// What a watcher found when it looked at the worker it was started for.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Poll {
+    Running,
+    /// Stopped or replaced through the shell; the watch ends quietly.
+    Gone,
+    Exited(Exit),
+}
+
+/// The worker's slot in [`LocalApiState`].
+pub(crate) struct WorkerSlot<C> {
+    child: Option<C>,
+    port: Option<u16>,
+    started_at: Option<String>,
+    /// Kept for the session, so an API process started with it still
+    /// matches a restarted worker.
+    secret: Option<String>,
+    history: SidecarHistory,
+    gave_up: bool,
+}
+
+impl<C> Default for WorkerSlot<C> {
+    fn default() -> Self {
+        WorkerSlot {
+            child: None,
+            port: None,
+            started_at: None,
+            secret: None,
+            history: SidecarHistory::default(),
+            gave_up: false,
+        }
+    }
+}
+
+/// `forensics_worker` in `get_local_api_status`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct WorkerStatus {
+    enabled: bool,
+    running: bool,
+    pid: Option<u32>,
+    port: Option<u16>,
+    started_at: Option<String>,
+    /// Crashed too often; forensics runs in the API process until the pref
+    /// is switched off and on again.
+    gave_up: bool,
+}
+
+impl<C: WorkerProcess> WorkerSlot<C> {
+    fn secret(&mut self, generate: impl FnOnce() -> String) -> String {
+        self.secret.get_or_insert_with(generate).clone()
+    }
+
+    /// Spawn unless a worker is already running. Returns the new pid, or
+    /// `None` when there was nothing to do.
+    fn start(&mut self, at: String, spawn: impl FnOnce() -> Result<C, String>) -> Result<Option<u32>, String> {
+        if self.child.is_some() {
+            return Ok(None);
+        }
+        let child = spawn()?;
+        let pid = child.id();
+        self.child = Some(child);
+        self.port = None;
+        self.started_at = Some(at.clone());
+        self.history.record(at, LifecycleKind::Started { pid });
+        Ok(Some(pid))
+    }
+
+    /// Record the port `pid` reported; ignored if that worker is gone.
+    fn confirm_port(&mut self, pid: u32, port: u16) -> bool {
+        if self.child.as_ref().map(WorkerProcess::id) != Some(pid) {
+            return false;
+        }
+        self.port = Some(port);
+        true
+    }
+
+    fn poll(&mut self, pid: u32, at: String) -> Poll {
+        let Some(child) = self.child.as_mut().filter(|child| child.id() == pid) else {
+            return Poll::Gone;
+        };
+        let Some(exit) = child.try_exit() else {
+            return Poll::Running;
+        };
+        self.child = None;
+        self.port = None;
+        self.started_at = None;
+        self.history.record(at, LifecycleKind::Exited { code: exit.code, signal: exit.signal });
+        Poll::Exited(exit)
+    }
+
+    /// Count an exit nobody asked for and decide whether to restart.
+    fn on_crash(&mut self, at: String, now: Instant) -> ExitDecision {
+        let decision = self.history.on_crash(now);
+        let kind = match decision {
+            ExitDecision::Restart { attempt, delay } => LifecycleKind::RestartScheduled {
+                attempt,
+                delay_ms: delay.as_millis() as u64,
+            },
+            ExitDecision::GiveUp { crashes } => {
+                self.gave_up = true;
+                LifecycleKind::GaveUp { crashes }
+            }
+        };
+        self.history.record(at, kind);
+        decision
+    }
+
+    fn reset_breaker(&mut self, at: String) {
+        if self.gave_up {
+            self.gave_up = false;
+            self.history.reset(at);
+        }
+    }
+
+    /// Hand over the child for stopping.
+    fn take(&mut self) -> Option<C> {
+        self.port = None;
+        self.started_at = None;
+        self.child.take()
+    }
+
+    fn url(&self) -> Option<String> {
+        self.port.map(|port| format!("http://127.0.0.1:{port}/api"))
+    }
+
+    fn endpoint(&self) -> Option<WorkerEndpoint> {
+        Some(WorkerEndpoint {
+            url: self.url()?,
+            secret: self.secret.clone()?,
+        })
+    }
+
+    fn status(&self, enabled: bool) -> WorkerStatus {
+        WorkerStatus {
+            enabled,
+            running: self.child.is_some(),
+            pid: self.child.as_ref().map(WorkerProcess::id),
+            port: self.port,
+            started_at: self.started_at.clone(),
+            gave_up: self.gave_up,
+        }
+    }
+}
+
+/// What the API process needs to reach the worker.
+#[derive(Clone, Debug)]
+pub(crate) struct WorkerEndpoint {
+    pub(crate) url: String,
+    pub(crate) secret: String,
+}
+
+fn lock(state: &LocalApiState) -> MutexGuard<'_, WorkerSlot<Child>> {
+    state.forensics.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn enabled(app: &AppHandle) -> bool {
+    app.try_state::<RuntimePrefs>()
+        .is_some_and(|prefs| prefs.get_bool(PREF_SEPARATE_FORENSICS_WORKER, false))
+}
+
+fn api_running(app: &AppHandle) -> bool {
+    app.state::<LocalApiState>()
+        .child
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some()
+}
+
+pub(crate) fn status(app: &AppHandle) -> WorkerStatus {
+    app.try_state::<LocalApiState>()
+        .map(|state| lock(&state).status(enabled(app)))
+        .unwrap_or_default()
+}
+
+/// The running worker's endpoint, without starting one.
+pub(crate) fn current_endpoint(app: &AppHandle) -> Option<WorkerEndpoint> {
+    let state = app.try_state::<LocalApiState>()?;
+    let endpoint = lock(&state).endpoint();
+    endpoint
+}
+
+/// Called by `start_local_api` before it launches the API process. `None`
+/// leaves forensics in-process: the pref is off, or the worker didn't come up.
+pub(crate) fn start_if_enabled(app: &AppHandle) -> Option<WorkerEndpoint> {
+    if !enabled(app) {
+        return None;
+    }
+    match start(app) {
+        Ok(endpoint) => Some(endpoint),
+        Err(err) => {
+            log_event(app, "ERROR", "forensics_worker_start_failed", &[("error", &err)]);
+            None
+        }
+    }
+}
+
+fn start(app: &AppHandle) -> Result<WorkerEndpoint, String> {
+    let state = app.state::<LocalApiState>();
+    let logs_dir = crate::logs_dir_path(app)?;
+    let port_file = logs_dir.join(PORT_FILE);
+    let (pid, secret) = {
+        let mut slot = lock(&state);
+        let secret = slot.secret(crate::generate_local_token);
+        let spawned = slot.start(logging::now_iso8601(), || spawn(app, &logs_dir, &port_file, &secret))?;
+        match spawned {
+            Some(pid) => (pid, secret),
+            None => {
+                let url = slot.url().ok_or_else(|| "The forensics worker is still starting".to_string())?;
+                return Ok(WorkerEndpoint { url, secret });
+            }
+        }
+    };
+    log_event(app, "INFO", "forensics_worker_started", &[("pid", &pid.to_string())]);
+    let watcher = app.clone();
+    std::thread::spawn(move || watch(watcher, pid));
+
+    let Some((port, _)) = crate::read_port_file(&port_file, PORT_WAIT_MS) else {
+        stop(app);
+        return Err("The forensics worker did not report a port".to_string());
+    };
+    if !lock(&state).confirm_port(pid, port) {
+        return Err("The forensics worker exited before it was ready".to_string());
+    }
+    log_event(app, "INFO", "forensics_worker_port_confirmed", &[("port", &port.to_string())]);
+    let url = lock(&state).url().unwrap_or_default();
+    Ok(WorkerEndpoint { url, secret })
+}
+
+fn spawn(app: &AppHandle, logs_dir: &Path, port_file: &Path, secret: &str) -> Result<Child, String> {
+    let paths = crate::local_api_paths(app);
+    let node_binary = crate::resolve_node_binary(app).ok_or_else(|| "Node.js executable not found".to_string())?;
+    let _ = fs::remove_file(port_file);
+    let log_path = logs_dir.join(LOG_FILE);
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| format!("Failed to open forensics worker log {}: {e}", log_path.display()))?;
+    let log_file_err = log_file
+        .try_clone()
+        .map_err(|e| format!("Failed to clone forensics worker log handle: {e}"))?;
+
+    let mut cmd = crate::node_binary::command(&node_binary);
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::creation_flags(&mut cmd, 0x08000000);
+    cmd.args(crate::sidecar_options::node_args(app))
+        .arg(crate::sanitize_path_for_node(&paths.script))
+        .env("LOCAL_API_PORT", "0")
+        .env("LOCAL_API_PORT_FILE", port_file)
+        .env("LOCAL_API_HOST", "127.0.0.1")
+        .env("LOCAL_API_RESOURCE_DIR", crate::sanitize_path_for_node(&paths.resource_root))
+        .env("LOCAL_API_DATA_DIR", crate::sanitize_path_for_node(logs_dir))
+        .env("LOCAL_API_MODE", WORKER_MODE)
+        .env(SECRET_ENV, secret)
+        .stdout(Stdio::from(log_file))
+        .stderr(Stdio::from(log_file_err));
+    if let Some(parent) = paths.script.parent() {
+        cmd.current_dir(parent);
+    }
+    cmd.spawn().map_err(|e| format!("Failed to launch forensics worker: {e}"))
+}
+
+/// Stop the worker, if one is running. Part of `stop_local_api`.
+pub(crate) fn stop(app: &AppHandle) {
+    let Some(state) = app.try_state::<LocalApiState>() else {
+        return;
+    };
+    let child = lock(&state).take();
+    if let Some(mut child) = child {
+        crate::graceful_kill(&mut child);
+        log_event(app, "INFO", "forensics_worker_stopped", &[]);
+    }
+    if let Ok(logs_dir) = crate::logs_dir_path(app) {
+        let _ = fs::remove_file(logs_dir.join(PORT_FILE));
+    }
+}
+
+/// Point a running API process at the worker, or back at in-process
+/// forensics with `None`.
+fn publish(app: &AppHandle, endpoint: Option<&WorkerEndpoint>) {
+    let body: Value = match endpoint {
+        Some(endpoint) => json!({ "url": endpoint.url, "secret": endpoint.secret }),
+        None => json!({ "url": null }),
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = post_to_local_api(&app, ENDPOINT_PATH, body).await {
+            log_event(&app, "WARN", "forensics_worker_publish_failed", &[("error", &err)]);
+        }
+    });
+}
+
+/// Start or stop the worker after the pref changed. A stopped API process
+/// is left alone; it picks the pref up when it starts.
+pub(crate) fn apply(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if !enabled(&app) {
+            stop(&app);
+            publish(&app, None);
+            return;
+        }
+        if !api_running(&app) {
+            return;
+        }
+        lock(&app.state::<LocalApiState>()).reset_breaker(logging::now_iso8601());
+        match start(&app) {
+            Ok(endpoint) => publish(&app, Some(&endpoint)),
+            Err(err) => log_event(&app, "ERROR", "forensics_worker_start_failed", &[("error", &err)]),
+        }
+    });
+}
+
+/// Poll the worker started as `pid` until it exits, then restart it with
+/// backoff until the breaker trips; see `watch_local_api`.
+fn watch(app: AppHandle, pid: u32) {
+    let state = app.state::<LocalApiState>();
+    let exit = loop {
+        std::thread::sleep(Duration::from_secs(1));
+        match lock(&state).poll(pid, logging::now_iso8601()) {
+            Poll::Running => continue,
+            Poll::Gone => return,
+            Poll::Exited(exit) => break exit,
+        }
+    };
+    log_event(
+        &app,
+        "WARN",
+        "forensics_worker_exited",
+        &[
+            ("pid", &pid.to_string()),
+            ("code", &exit.code.map_or_else(|| "none".to_string(), |c| c.to_string())),
+            ("signal", &exit.signal.map_or_else(|| "none".to_string(), |s| s.to_string())),
+        ],
+    );
+    publish(&app, None);
+    let decision = lock(&state).on_crash(logging::now_iso8601(), Instant::now());
+    match decision {
+        ExitDecision::Restart { attempt, delay } => {
+            log_event(
+                &app,
+                "INFO",
+                "forensics_worker_restart_scheduled",
+                &[("attempt", &attempt.to_string()), ("delay_ms", &delay.as_millis().to_string())],
+            );
+            std::thread::sleep(delay);
+            if !enabled(&app) || !api_running(&app) {
+                return;
+            }
+            match start(&app) {
+                Ok(endpoint) => publish(&app, Some(&endpoint)),
+                Err(err) => log_event(&app, "ERROR", "forensics_worker_restart_failed", &[("error", &err)]),
+            }
+        }
+        ExitDecision::GiveUp { crashes } => {
+            log_event(&app, "ERROR", "forensics_worker_breaker_tripped", &[("crashes", &crashes.to_string())]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use super::{Exit, Poll, WorkerProcess, WorkerSlot};
+    use crate::sidecar_history::{ExitDecision, MAX_CRASHES};
+
+    /// A spawned worker whose exit the test decides.
+    struct FakeChild {
+        pid: u32,
+        exit: Rc<Cell<Option<Exit>>>,
+    }
+
+    impl WorkerProcess for FakeChild {
+        fn id(&self) -> u32 {
+            self.pid
+        }
+
+        fn try_exit(&mut self) -> Option<Exit> {
+            self.exit.get()
+        }
+    }
+
+    fn fake(pid: u32) -> (FakeChild, Rc<Cell<Option<Exit>>>) {
+        let exit = Rc::new(Cell::new(None));
+        (FakeChild { pid, exit: Rc::clone(&exit) }, exit)
+    }
+
+    const CRASH: Exit = Exit {
+        code: Some(1),
+        signal: None,
+    };
+
+    #[test]
+    fn spawns_once_and_keeps_one_secret_for_the_session() {
+        let mut slot = WorkerSlot::default();
+        let secret = slot.secret(|| "s3cret".to_string());
+        assert_eq!(slot.start("t0".into(), || Ok(fake(10).0)), Ok(Some(10)));
+        assert_eq!(
+            slot.start("t1".into(), || panic!("a running worker is not spawned again")),
+            Ok(None)
+        );
+        assert!(slot.url().is_none(), "no port until the worker reports one");
+        assert!(slot.confirm_port(10, 50123));
+        assert_eq!(slot.url().as_deref(), Some("http://127.0.0.1:50123/api"));
+
+        let status = slot.status(true);
+        assert!(status.running && status.enabled);
+        assert_eq!((status.pid, status.port), (Some(10), Some(50123)));
+
+        drop(slot.take());
+        assert_eq!(slot.status(true).port, None);
+        assert_eq!(slot.start("t2".into(), || Ok(fake(11).0)), Ok(Some(11)));
+        assert_eq!(slot.secret(|| "other".to_string()), secret);
+    }
+
+    #[test]
+    fn a_failed_spawn_leaves_the_slot_empty() {
+        let mut slot: WorkerSlot<FakeChild> = WorkerSlot::default();
+        assert!(slot.start("t0".into(), || Err("no node".to_string())).is_err());
+        assert!(!slot.status(true).running);
+        assert_eq!(slot.start("t1".into(), || Ok(fake(12).0)), Ok(Some(12)));
+    }
+
+    #[test]
+    fn stale_watchers_and_ports_are_ignored_after_a_stop() {
+        let mut slot = WorkerSlot::default();
+        slot.start("t0".into(), || Ok(fake(20).0)).unwrap();
+        assert_eq!(slot.poll(20, "t1".into()), Poll::Running);
+        drop(slot.take());
+        assert_eq!(slot.poll(20, "t2".into()), Poll::Gone, "a stop isn't a crash");
+        slot.start("t3".into(), || Ok(fake(21).0)).unwrap();
+        assert!(!slot.confirm_port(20, 50000), "the old worker's port");
+        assert_eq!(slot.poll(20, "t4".into()), Poll::Gone);
+        assert_eq!(slot.poll(21, "t5".into()), Poll::Running);
+    }
+
+    #[test]
+    fn crashes_are_restarted_until_the_breaker_trips() {
+        let t0 = Instant::now();
+        let mut slot = WorkerSlot::default();
+        for n in 0..MAX_CRASHES as u32 {
+            let pid = 30 + n;
+            let (child, exit) = fake(pid);
+            slot.start(format!("start {n}"), || Ok(child)).unwrap();
+            slot.confirm_port(pid, 50000);
+            exit.set(Some(CRASH));
+            assert_eq!(slot.poll(pid, format!("exit {n}")), Poll::Exited(CRASH));
+            assert!(!slot.status(true).running);
+            assert_eq!(slot.url(), None, "a dead worker has no address");
+            let decision = slot.on_crash(format!("decide {n}"), t0 + Duration::from_secs(n.into()));
+            if n + 1 < MAX_CRASHES as u32 {
+                assert!(matches!(decision, ExitDecision::Restart { attempt, .. } if attempt == n + 1));
+            } else {
+                assert!(matches!(decision, ExitDecision::GiveUp { .. }));
+            }
+        }
+        assert!(slot.status(true).gave_up);
+        slot.reset_breaker("reset".into());
+        assert!(!slot.status(true).gave_up);
+        assert!(matches!(slot.on_crash("again".into(), t0), ExitDecision::Restart { attempt: 1, .. }));
+    }
+}
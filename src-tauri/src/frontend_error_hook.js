@@ -0,0 +1,37 @@
+// Forwards uncaught renderer errors to desktop.log via `log_from_frontend`, so
+// crashes on user machines are visible without devtools open.
+(function () {
+  if (window.__WM_FRONTEND_ERROR_HOOK__) return;
+  window.__WM_FRONTEND_ERROR_HOOK__ = true;
+
+  function forward(message, context) {
+    try {
+      var internals = window.__TAURI_INTERNALS__;
+      if (!internals || typeof internals.invoke !== 'function') return;
+      internals
+        .invoke('log_from_frontend', { level: 'error', message: String(message), context: context })
+        .catch(function () {});
+    } catch (_) {
+      // Never let error reporting raise its own error.
+    }
+  }
+
+  window.addEventListener('error', function (event) {
+    var error = event.error;
+    forward(event.message || 'Uncaught error', {
+      kind: 'error',
+      source: event.filename || undefined,
+      line: event.lineno || undefined,
+      column: event.colno || undefined,
+      stack: error && error.stack ? String(error.stack) : undefined,
+    });
+  });
+
+  window.addEventListener('unhandledrejection', function (event) {
+    var reason = event.reason;
+    forward(reason && reason.message ? reason.message : String(reason), {
+      kind: 'unhandledrejection',
+      stack: reason && reason.stack ? String(reason.stack) : undefined,
+    });
+  });
+})();
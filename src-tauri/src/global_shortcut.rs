@@ -0,0 +1,259 @@
+//! System-wide shortcut that shows or hides the main window. It is set from
+//! an accelerator such as `Ctrl+Shift+W` and kept in the `globalToggleShortcut`
+//! pref in the plugin's own string form (`shift+control+KeyW`).
+//!
+//! Parsing and registration go through tauri-plugin-global-shortcut; this
+//! module adds the modifier rule and keeps the pref. Wayland has no global
+//! grabs for ordinary clients, so registration fails there with an
+//! explanation.
+
+use std::sync::Mutex;
+
+use serde_json::Value;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_global_shortcut::{self as plugin, Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::{show_main_window, store_runtime_pref, RuntimePrefs};
+
+/// The active shortcut in the plugin's string form; only written by this module.
+pub(crate) const PREF_GLOBAL_TOGGLE_SHORTCUT: &str = "globalToggleShortcut";
+const ACCELERATOR_MAX_CHARS: usize = 64;
+
+/// Parse an accelerator such as `Ctrl+Shift+W` or `CmdOrCtrl+Alt+F5` with
+/// the plugin's grammar. Apart from function keys, a shortcut needs Ctrl,
+/// Alt or Super so it can't swallow ordinary typing.
+pub(crate) fn parse_shortcut(raw: &str) -> Result<Shortcut, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("Shortcut is empty".to_string());
+    }
+    if raw.chars().count() > ACCELERATOR_MAX_CHARS {
+        return Err("Shortcut is too long".to_string());
+    }
+    let shortcut = raw.parse::<Shortcut>().map_err(|e| e.to_string())?;
+    let command_modifiers = Modifiers::CONTROL | Modifiers::ALT | Modifiers::SUPER;
+    if !shortcut.mods.intersects(command_modifiers) && !is_function_key(shortcut.key) {
+        return Err("Shortcut needs Ctrl, Alt or Super".to_string());
+    }
+    Ok(shortcut)
+}
+
+/// F1 to F24.
+fn is_function_key(key: Code) -> bool {
+    key.to_string()
+        .strip_prefix('F')
+        .is_some_and(|number| number.parse::<u8>().is_ok())
+}
+
+#[derive(Default)]
+pub(crate) struct GlobalShortcutState {
+    active: Mutex<Option<Shortcut>>,
+}
+
+/// The plugin, with presses of any registered shortcut toggling the main
+/// window (this module registers only the one).
+pub(crate) fn plugin() -> TauriPlugin<Wry> {
+    plugin::Builder::new().with_handler(on_shortcut).build()
+}
+
+/// Ask the OS for `shortcut`. The plugin hops to the main thread and
+/// waits, so this must be called off it.
+fn register(app: &AppHandle, shortcut: Shortcut) -> Result<(), String> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    if std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session.eq_ignore_ascii_case("wayland")) {
+        return Err("global shortcuts are not available in Wayland sessions; \
+                    bind a desktop shortcut to `world-monitor` instead"
+            .to_string());
+    }
+    app.global_shortcut().register(shortcut).map_err(|e| e.to_string())
+}
+
+fn unregister(app: &AppHandle, shortcut: Shortcut) {
+    if let Err(err) = app.global_shortcut().unregister(shortcut) {
+        log_event(
+            app,
+            "WARN",
+            "global_shortcut_unregister_failed",
+            &[("shortcut", &shortcut.into_string()), ("error", &err.to_string())],
+        );
+    }
+}
+
+/// The registered shortcut, in the plugin's string form. The lock is held across
+/// registrations, which wait on the main thread, so don't call this there.
+pub(crate) fn current_shortcut(app: &AppHandle) -> Option<String> {
+    let state = app.state::<GlobalShortcutState>();
+    let active = state.active.lock().unwrap_or_else(|e| e.into_inner());
+    active.map(Shortcut::into_string)
+}
+
+/// Register `accelerator` (or clear with `None`), replacing the current one.
+/// If the OS refuses, the previous shortcut is kept and the refusal returned.
+pub(crate) fn set_shortcut(app: &AppHandle, accelerator: Option<&str>) -> Result<Option<String>, DesktopError> {
+    let requested = match accelerator.map(str::trim).filter(|raw| !raw.is_empty()) {
+        Some(raw) => Some(parse_shortcut(raw).map_err(DesktopError::InvalidArgument)?),
+        None => None,
+    };
+    let state = app.state::<GlobalShortcutState>();
+    // Held until the pref is stored, so `restore` sees all of this or none.
+    let mut active = state.active.lock().unwrap_or_else(|e| e.into_inner());
+    let previous = *active;
+    if previous != requested {
+        // Unregister first: re-registering our own combination would conflict.
+        if let Some(previous) = active.take() {
+            unregister(app, previous);
+        }
+        if let Some(shortcut) = requested {
+            if let Err(err) = register(app, shortcut) {
+                *active = previous.filter(|previous| register(app, *previous).is_ok());
+                log_event(
+                    app,
+                    "WARN",
+                    "global_shortcut_failed",
+                    &[("shortcut", &shortcut.into_string()), ("error", &err)],
+                );
+                return Err(DesktopError::ShortcutUnavailable(format!("Could not register {shortcut}: {err}")));
+            }
+            *active = Some(shortcut);
+        }
+    }
+    let canonical = requested.map(Shortcut::into_string);
+    store_runtime_pref(app, PREF_GLOBAL_TOGGLE_SHORTCUT, canonical.clone().map_or(Value::Null, Value::from))?;
+    drop(active);
+    log_event(
+        app,
+        "INFO",
+        "global_shortcut_updated",
+        &[("shortcut", canonical.as_deref().unwrap_or("none"))],
+    );
+    Ok(canonical)
+}
+
+/// Register the stored shortcut. Called once at startup, off the main
+/// thread. The pref is read under the state lock, so a `set_shortcut` that
+/// got there first wins instead of being overwritten by the stale value.
+pub(crate) fn restore(app: &AppHandle) {
+    let state = app.state::<GlobalShortcutState>();
+    let mut active = state.active.lock().unwrap_or_else(|e| e.into_inner());
+    if active.is_some() {
+        return;
+    }
+    let Some(stored) = app
+        .state::<RuntimePrefs>()
+        .get(PREF_GLOBAL_TOGGLE_SHORTCUT)
+        .and_then(|value| value.as_str().map(str::to_string))
+    else {
+        return;
+    };
+    let shortcut = match parse_shortcut(&stored) {
+        Ok(shortcut) => shortcut,
+        Err(err) => {
+            log_event(app, "WARN", "global_shortcut_invalid", &[("shortcut", &stored), ("error", &err)]);
+            return;
+        }
+    };
+    match register(app, shortcut) {
+        Ok(()) => {
+            *active = Some(shortcut);
+            log_event(app, "INFO", "global_shortcut_registered", &[("shortcut", &shortcut.into_string())]);
+        }
+        Err(err) => log_event(
+            app,
+            "WARN",
+            "global_shortcut_failed",
+            &[("shortcut", &shortcut.into_string()), ("error", &err)],
+        ),
+    }
+}
+
+fn on_shortcut(app: &AppHandle, _shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state != ShortcutState::Pressed {
+        return;
+    }
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || toggle_main_window(&handle));
+}
+
+/// A window the user is looking at gets hidden; anything else is brought
+/// forward.
+fn should_hide(visible: bool, focused: bool, minimized: bool) -> bool {
+    visible && focused && !minimized
+}
+
+fn toggle_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let visible = window.is_visible().unwrap_or(false);
+    let focused = window.is_focused().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+    if should_hide(visible, focused, minimized) {
+        let _ = window.hide();
+    } else {
+        show_main_window(app);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut};
+
+    use super::{parse_shortcut, should_hide};
+
+    #[test]
+    fn parses_with_the_plugin_grammar() {
+        let parsed = |raw: &str| parse_shortcut(raw).unwrap();
+        assert_eq!(parsed("Ctrl+Shift+W"), Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyW));
+        assert_eq!(parsed(" shift + control + w "), parsed("Ctrl+Shift+W"));
+        assert_eq!(parsed("Alt+Super+0"), Shortcut::new(Some(Modifiers::ALT | Modifiers::SUPER), Code::Digit0));
+        assert_eq!(parsed("Cmd+Option+ArrowUp"), Shortcut::new(Some(Modifiers::ALT | Modifiers::SUPER), Code::ArrowUp));
+        assert_eq!(parsed("Ctrl+Esc"), Shortcut::new(Some(Modifiers::CONTROL), Code::Escape));
+        // CmdOrCtrl is Ctrl off macOS.
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(parsed("CmdOrCtrl+Shift+M"), parsed("Ctrl+Shift+M"));
+        // Function keys may stand alone.
+        assert_eq!(parsed("F13"), Shortcut::new(None, Code::F13));
+        assert_eq!(parsed("Shift+F5"), Shortcut::new(Some(Modifiers::SHIFT), Code::F5));
+    }
+
+    #[test]
+    fn the_stored_form_parses_back_to_the_same_shortcut() {
+        for raw in ["Ctrl+Shift+W", "Alt+Super+Up", "F24", "Ctrl+Alt+PageDown"] {
+            let shortcut = parse_shortcut(raw).unwrap();
+            assert_eq!(parse_shortcut(&shortcut.into_string()).unwrap(), shortcut, "{raw}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_shortcuts() {
+        for bad in [
+            "",
+            "   ",
+            "W",
+            "Shift+W",
+            "Ctrl",
+            "Ctrl+",
+            "+W",
+            "Ctrl++W",
+            "Ctrl+W+Shift",
+            "Hyper+W",
+            "Ctrl+F25",
+            "Ctrl+Ü",
+            "Ctrl+WW",
+            "Ctrl+Shift+Alt+Super+Ctrl+Shift+Alt+Super+Ctrl+Shift+Alt+Super+Ctrl+W",
+        ] {
+            assert!(parse_shortcut(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn only_a_window_in_front_is_hidden() {
+        assert!(should_hide(true, true, false));
+        assert!(!should_hide(true, false, false));
+        assert!(!should_hide(true, true, true));
+        assert!(!should_hide(false, false, false));
+    }
+}
@@ -0,0 +1,170 @@
+//! `--headless`: run only the local API sidecar, for users who host it on a
+//! home server and point the web app at it. No windows or menus are created;
+//! the bound address and access token are printed to stdout, desktop.log is
+//! mirrored to stderr, and SIGINT/SIGTERM (Ctrl+C on Windows) leave through
+//! the normal `RunEvent::Exit` path so the sidecar is stopped cleanly.
+//!
+//! Crashes are restarted by the same watcher as in the desktop app, and the
+//! connection is printed again after each restart. Once its breaker gives up
+//! the process exits with status 1, leaving the next step to the service
+//! manager.
+//!
+//! Tauri still initialises its windowing toolkit, so Linux servers without a
+//! display need one provided (e.g. `xvfb-run world-monitor --headless`).
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::logging::log_event;
+use crate::{keyring_migration, local_api_listen_addr, start_local_api, LocalApiLifecycle, LocalApiState};
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Called from `main` before Tauri starts, so an early Ctrl+C is not lost.
+pub(crate) fn install_process_hooks() {
+    attach_parent_console();
+    install_signal_handlers();
+}
+
+/// Called from setup in place of the splash and main window.
+pub(crate) fn start(app: &AppHandle) {
+    let handle = app.clone();
+    thread::spawn(move || {
+        while !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+            thread::sleep(SHUTDOWN_POLL_INTERVAL);
+        }
+        log_event(&handle, "INFO", "headless_shutdown", &[]);
+        // RunEvent::Exit flushes the cache and runs stop_local_api.
+        handle.exit(0);
+    });
+
+    let handle = app.clone();
+    thread::spawn(move || {
+        keyring_migration::run(&handle);
+        if let Err(err) = start_local_api(&handle) {
+            log_event(&handle, "ERROR", "headless_start_failed", &[("error", &err)]);
+            handle.exit(1);
+            return;
+        }
+        let mut printed = print_connection_info(&handle, None);
+        follow_restarts(&handle, &mut printed);
+    });
+}
+
+fn connection_info(listen: SocketAddr, token: &str) -> String {
+    format!("local_api_listen={listen}\nlocal_api_token={token}")
+}
+
+fn current_connection(app: &AppHandle) -> Option<(SocketAddr, String)> {
+    let state = app.state::<LocalApiState>();
+    let port = (*state.port.lock().unwrap_or_else(|e| e.into_inner()))?;
+    let token = state.token.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    let ip = local_api_listen_addr(app).unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    Some((SocketAddr::new(ip, port), token))
+}
+
+/// Stdout carries only these lines so scripts can parse them; clients send
+/// the token as `Authorization: Bearer <token>`. Skipped when unchanged
+/// from `last`; returns what is current.
+fn print_connection_info(
+    app: &AppHandle,
+    last: Option<(SocketAddr, String)>,
+) -> Option<(SocketAddr, String)> {
+    let current = current_connection(app);
+    if let Some((listen, token)) = current.as_ref().filter(|_| current != last) {
+        println!("{}", connection_info(*listen, token));
+    }
+    current.or(last)
+}
+
+/// `watch_local_api` restarts a crashed sidecar; reprint the connection
+/// once it is back, and exit when it gives up.
+fn follow_restarts(app: &AppHandle, printed: &mut Option<(SocketAddr, String)>) {
+    let mut lifecycle = app.state::<LocalApiState>().lifecycle.subscribe();
+    tauri::async_runtime::block_on(async {
+        while lifecycle.changed().await.is_ok() {
+            let state = *lifecycle.borrow_and_update();
+            match state {
+                LocalApiLifecycle::Ready | LocalApiLifecycle::PortBlocked => {
+                    *printed = print_connection_info(app, printed.take());
+                }
+                LocalApiLifecycle::GaveUp if !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) => {
+                    log_event(app, "ERROR", "headless_sidecar_gave_up", &[]);
+                    app.exit(1);
+                    return;
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+#[cfg(unix)]
+fn install_signal_handlers() {
+    extern "C" fn on_signal(_signal: libc::c_int) {
+        SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, on_signal as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, on_signal as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(windows)]
+fn install_signal_handlers() {
+    type HandlerRoutine = unsafe extern "system" fn(u32) -> i32;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<HandlerRoutine>, add: i32) -> i32;
+    }
+    unsafe extern "system" fn on_ctrl(_event: u32) -> i32 {
+        SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+        1
+    }
+    // SAFETY: registers a static handler that only stores to an atomic.
+    unsafe {
+        SetConsoleCtrlHandler(Some(on_ctrl), 1);
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn install_signal_handlers() {}
+
+/// Release builds use the GUI subsystem on Windows and start without a
+/// console; borrow the launching terminal's so stdout and stderr are visible.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // SAFETY: no pointers involved; failure just means there is no parent console.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::connection_info;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn connection_info_is_line_oriented() {
+        let v4: SocketAddr = "0.0.0.0:46123".parse().unwrap();
+        assert_eq!(connection_info(v4, "abc"), "local_api_listen=0.0.0.0:46123\nlocal_api_token=abc");
+        let v6: SocketAddr = "[::1]:46200".parse().unwrap();
+        assert!(connection_info(v6, "abc").starts_with("local_api_listen=[::1]:46200\n"));
+    }
+}
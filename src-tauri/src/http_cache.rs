@@ -0,0 +1,393 @@
+//! Conditional-request cache for native fetches. Responses carrying an
+//! `ETag` or `Last-Modified` are kept under `http-cache/` in the data
+//! directory; repeat requests send `If-None-Match`/`If-Modified-Since` and a
+//! 304 is answered from disk. Bodies are checked against their hash before
+//! being served, and the directory is capped with LRU eviction.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::cache_limits::{lru_victims, LruEntry};
+use crate::data_dir;
+use crate::disk_space;
+use crate::error::DesktopError;
+use crate::logging::{log_event, now_ms};
+
+pub(crate) const CACHE_DIR: &str = "http-cache";
+const INDEX_FILE: &str = "index.json";
+const MAX_CACHE_BYTES: u64 = 50 * 1024 * 1024;
+/// Bodies above this are fetched every time rather than crowding the cache.
+const MAX_ENTRY_BYTES: u64 = 8 * 1024 * 1024;
+/// Query parameters that carry credentials. They are left out of the cache
+/// key, so the index never stores them and rotating a key keeps the entry.
+const AUTH_PARAMS: [&str; 12] = [
+    "access_token",
+    "api_key",
+    "apikey",
+    "api-key",
+    "auth",
+    "client_secret",
+    "key",
+    "password",
+    "secret",
+    "sig",
+    "signature",
+    "token",
+];
+
+/// The cache key for `url`: fragment dropped, credentials stripped and query
+/// parameters sorted, so equivalent requests share an entry.
+pub(crate) fn cache_key(url: &str) -> Option<String> {
+    let mut parsed = reqwest::Url::parse(url).ok()?;
+    parsed.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| !AUTH_PARAMS.iter().any(|auth| name.eq_ignore_ascii_case(auth)))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+    if pairs.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    Some(parsed.to_string())
+}
+
+fn is_no_store(cache_control: Option<&str>) -> bool {
+    cache_control.is_some_and(|value| {
+        value
+            .split(',')
+            .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+    })
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body_hash: String,
+    body_file: String,
+    size: u64,
+    stored_at: u64,
+    /// Last time upstream confirmed the body (200 or 304).
+    validated_at: u64,
+    /// Last time the entry was read or written, for LRU eviction.
+    last_used: u64,
+}
+
+impl LruEntry for Entry {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn last_used(&self) -> u64 {
+        self.last_used
+    }
+}
+
+/// Validators to send with a repeat request.
+pub(crate) struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// The index and body files in one cache directory.
+struct DiskCache {
+    dir: PathBuf,
+    entries: HashMap<String, Entry>,
+}
+
+impl DiskCache {
+    fn load(dir: PathBuf) -> Self {
+        let entries = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        DiskCache { dir, entries }
+    }
+
+    fn save_index(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let serialized = serde_json::to_string(&self.entries).map_err(io::Error::other)?;
+        let staging = self.dir.join(format!("{INDEX_FILE}.tmp"));
+        fs::write(&staging, serialized)?;
+        fs::rename(&staging, self.dir.join(INDEX_FILE))
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                let _ = fs::remove_file(self.dir.join(entry.body_file));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn validators(&self, key: &str) -> Option<Validators> {
+        self.entries.get(key).map(|entry| Validators {
+            etag: entry.etag.clone(),
+            last_modified: entry.last_modified.clone(),
+        })
+    }
+
+    /// Serve the stored body after a 304, refreshing the entry's metadata
+    /// with any validators the 304 carried. A missing or corrupt body drops
+    /// the entry and returns `None`.
+    fn revalidate(&mut self, key: &str, etag: Option<String>, last_modified: Option<String>, now: u64) -> Option<String> {
+        let entry = self.entries.get(key)?;
+        let body = fs::read_to_string(self.dir.join(&entry.body_file))
+            .ok()
+            .filter(|body| sha256_hex(body.as_bytes()) == entry.body_hash);
+        let Some(body) = body else {
+            self.remove(key);
+            let _ = self.save_index();
+            return None;
+        };
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.etag = etag.or(entry.etag.take());
+            entry.last_modified = last_modified.or(entry.last_modified.take());
+            entry.validated_at = now;
+            entry.last_used = now;
+        }
+        let _ = self.save_index();
+        Some(body)
+    }
+
+    /// Record a fresh 200 response. Returns how many entries were evicted.
+    fn store(&mut self, key: &str, etag: Option<String>, last_modified: Option<String>, body: &str, now: u64) -> io::Result<usize> {
+        let body_file = format!("{}.body", &sha256_hex(key.as_bytes())[..32]);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(&body_file), body)?;
+        let stored_at = self.entries.get(key).map_or(now, |entry| entry.stored_at);
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                etag,
+                last_modified,
+                body_hash: sha256_hex(body.as_bytes()),
+                body_file,
+                size: body.len() as u64,
+                stored_at,
+                validated_at: now,
+                last_used: now,
+            },
+        );
+        let victims = lru_victims(&self.entries, MAX_CACHE_BYTES, key);
+        for victim in &victims {
+            self.remove(victim);
+        }
+        self.save_index()?;
+        Ok(victims.len())
+    }
+
+    fn clear(&mut self) -> io::Result<(usize, u64)> {
+        let cleared = (self.entries.len(), self.total_bytes());
+        self.entries.clear();
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(cleared),
+        }
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct HttpCacheState {
+    cache: Mutex<Option<DiskCache>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HttpCacheStats {
+    entries: usize,
+    bytes: u64,
+    max_bytes: u64,
+    /// Requests answered from disk after a 304, this session.
+    hits: u64,
+    /// Requests that downloaded a body, this session.
+    misses: u64,
+    evictions: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ClearedHttpCache {
+    entries: usize,
+    bytes: u64,
+}
+
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, DesktopError> {
+    data_dir::resolve_data_dir(app)
+        .map(|dir| dir.join(CACHE_DIR))
+        .map_err(|e| DesktopError::Internal(format!("Failed to resolve data dir: {e}")))
+}
+
+/// Run `f` on the cache for the current data directory, loading it first
+/// (or again, after a data-directory migration).
+fn with_cache<T>(app: &AppHandle, f: impl FnOnce(&mut DiskCache) -> T) -> Option<T> {
+    let dir = cache_dir(app).ok()?;
+    let state = app.try_state::<HttpCacheState>()?;
+    let mut cache = state.cache.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.as_ref().is_none_or(|loaded| loaded.dir != dir) {
+        *cache = Some(DiskCache::load(dir));
+    }
+    cache.as_mut().map(f)
+}
+
+pub(crate) fn validators(app: &AppHandle, key: &str) -> Option<Validators> {
+    with_cache(app, |cache| cache.validators(key)).flatten()
+}
+
+/// The cached body for `key` after upstream answered 304, or `None` if it
+/// is gone and the request must be repeated without validators.
+pub(crate) fn revalidated(app: &AppHandle, key: &str, etag: Option<String>, last_modified: Option<String>) -> Option<String> {
+    let body = with_cache(app, |cache| cache.revalidate(key, etag, last_modified, now_ms())).flatten();
+    if body.is_some() {
+        app.state::<HttpCacheState>().hits.fetch_add(1, Ordering::Relaxed);
+    } else {
+        log_event(app, "WARN", "http_cache_body_missing", &[]);
+    }
+    body
+}
+
+/// Record a 200 response. Only responses with a validator are kept;
+/// `Cache-Control: no-store` also drops whatever was cached before.
+pub(crate) fn store_response(
+    app: &AppHandle,
+    key: &str,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    cache_control: Option<&str>,
+    body: &str,
+) {
+    let Some(state) = app.try_state::<HttpCacheState>() else {
+        return;
+    };
+    state.misses.fetch_add(1, Ordering::Relaxed);
+    let cacheable = !is_no_store(cache_control)
+        && (etag.is_some() || last_modified.is_some())
+        && body.len() as u64 <= MAX_ENTRY_BYTES;
+    // Serving uncached is better than filling the disk.
+    let cacheable = cacheable
+        && cache_dir(app).is_ok_and(|dir| disk_space::ensure_space(app, &dir, body.len() as u64).is_ok());
+    let stored = with_cache(app, |cache| {
+        if !cacheable {
+            if cache.remove(key) {
+                let _ = cache.save_index();
+            }
+            return Ok(0);
+        }
+        cache.store(key, etag, last_modified, body, now_ms())
+    });
+    match stored {
+        Some(Ok(evicted)) => {
+            state.evictions.fetch_add(evicted as u64, Ordering::Relaxed);
+        }
+        Some(Err(err)) => log_event(app, "WARN", "http_cache_store_failed", &[("error", &err.to_string())]),
+        None => {}
+    }
+}
+
+pub(crate) fn clear(app: &AppHandle) -> Result<ClearedHttpCache, DesktopError> {
+    let dir = cache_dir(app)?;
+    let (entries, bytes) = with_cache(app, DiskCache::clear)
+        .ok_or_else(|| DesktopError::Internal("HTTP cache unavailable".to_string()))?
+        .map_err(|e| DesktopError::io("Failed to clear", &dir, e))?;
+    log_event(app, "INFO", "http_cache_cleared", &[("entries", &entries.to_string())]);
+    Ok(ClearedHttpCache { entries, bytes })
+}
+
+pub(crate) fn stats(app: &AppHandle) -> HttpCacheStats {
+    let (entries, bytes) = with_cache(app, |cache| (cache.entries.len(), cache.total_bytes())).unwrap_or_default();
+    let state = app.try_state::<HttpCacheState>();
+    let counter = |read: fn(&HttpCacheState) -> &AtomicU64| {
+        state.as_ref().map_or(0, |state| read(state).load(Ordering::Relaxed))
+    };
+    HttpCacheStats {
+        entries,
+        bytes,
+        max_bytes: MAX_CACHE_BYTES,
+        hits: counter(|state| &state.hits),
+        misses: counter(|state| &state.misses),
+        evictions: counter(|state| &state.evictions),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{cache_key, is_no_store, DiskCache};
+
+
+    #[test]
+    fn keys_sort_params_and_strip_credentials() {
+        let a = cache_key("https://API.example.com/v1/quotes?symbol=BTC&limit=5&api_key=secret#top").unwrap();
+        let b = cache_key("https://api.example.com:443/v1/quotes?limit=5&symbol=BTC&apiKey=other").unwrap();
+        assert_eq!(a, "https://api.example.com/v1/quotes?limit=5&symbol=BTC");
+        assert_eq!(a, b);
+        assert_eq!(
+            cache_key("https://api.example.com/v1/quotes?token=x").unwrap(),
+            "https://api.example.com/v1/quotes"
+        );
+        assert_ne!(a, cache_key("https://api.example.com/v1/quotes?symbol=ETH&limit=5").unwrap());
+        // Repeated parameters keep every value, in a stable order.
+        assert_eq!(
+            cache_key("https://x.test/?tag=b&tag=a").unwrap(),
+            cache_key("https://x.test/?tag=a&tag=b").unwrap()
+        );
+        assert!(cache_key("not a url").is_none());
+    }
+
+    #[test]
+    fn no_store_is_detected_among_directives() {
+        assert!(is_no_store(Some("private, No-Store")));
+        assert!(!is_no_store(Some("max-age=60")));
+        assert!(!is_no_store(None));
+    }
+
+    #[test]
+    fn stores_revalidates_and_recovers_from_a_lost_body() {
+        let dir = std::env::temp_dir().join(format!("wm-http-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let key = "https://api.example.com/v1/quotes?limit=5";
+        let mut cache = DiskCache::load(dir.clone());
+        cache.store(key, Some("\"v1\"".to_string()), None, "[1,2,3]", 10).unwrap();
+
+        let mut reloaded = DiskCache::load(dir.clone());
+        assert_eq!(reloaded.validators(key).unwrap().etag.as_deref(), Some("\"v1\""));
+        let body = reloaded.revalidate(key, Some("\"v2\"".to_string()), None, 20);
+        assert_eq!(body.as_deref(), Some("[1,2,3]"));
+        let entry = &reloaded.entries[key];
+        assert_eq!((entry.etag.as_deref(), entry.validated_at, entry.stored_at), (Some("\"v2\""), 20, 10));
+
+        fs::write(dir.join(&entry.body_file), "tampered").unwrap();
+        assert!(reloaded.revalidate(key, None, None, 30).is_none());
+        assert!(reloaded.validators(key).is_none());
+
+        reloaded.store(key, None, Some("Tue, 01 Sep 2026 00:00:00 GMT".to_string()), "{}", 40).unwrap();
+        assert_eq!(reloaded.clear().unwrap(), (1, 2));
+        assert!(!dir.exists());
+    }
+}
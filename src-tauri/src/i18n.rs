@@ -0,0 +1,272 @@
+//! Translations for the native menus and file dialogs. The language follows
+//! the OS locale unless the `uiLanguage` pref names one; a string missing
+//! from a table falls back to English. Web content localizes itself.
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::runtime_info::{self, StaticRuntimeInfo};
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_UI_LANGUAGE: &str = "uiLanguage";
+const FALLBACK: &str = "en";
+
+type Table = &'static [(&'static str, &'static str)];
+
+const EN: Table = &[
+    ("menu.file", "File"),
+    ("menu.file.settings", "Settings..."),
+    ("menu.file.export_screenshot", "Export Screenshot\u{2026}"),
+    ("menu.file.restart", "Restart"),
+    ("menu.file.quit", "Quit"),
+    ("menu.edit", "Edit"),
+    ("menu.edit.undo", "Undo"),
+    ("menu.edit.redo", "Redo"),
+    ("menu.edit.cut", "Cut"),
+    ("menu.edit.copy", "Copy"),
+    ("menu.edit.paste", "Paste"),
+    ("menu.edit.select_all", "Select All"),
+    ("menu.edit.find", "Find\u{2026}"),
+    ("menu.view", "View"),
+    ("menu.view.reload", "Reload"),
+    ("menu.view.force_reload", "Force Reload"),
+    ("menu.view.pause_polling", "Pause Polling"),
+    ("menu.window", "Window"),
+    ("menu.window.new_panel", "New Panel Window\u{2026}"),
+    ("menu.help", "Help"),
+    ("menu.help.about", "About World Monitor"),
+    ("menu.help.check_updates", "Check for Updates\u{2026}"),
+    ("menu.help.export_diagnostics", "Export Diagnostics\u{2026}"),
+    ("menu.help.show_logs", "Show Logs"),
+    ("menu.help.open_data", "Open App Data Folder"),
+    ("menu.help.github", "GitHub Repository"),
+    ("menu.help.devtools", "Toggle Developer Tools"),
+    ("dialog.save", "Save As"),
+    ("dialog.open", "Open"),
+];
+
+const FR: Table = &[
+    ("menu.file", "Fichier"),
+    ("menu.file.settings", "Param\u{e8}tres\u{2026}"),
+    ("menu.file.export_screenshot", "Exporter une capture d\u{2019}\u{e9}cran\u{2026}"),
+    ("menu.file.restart", "Red\u{e9}marrer"),
+    ("menu.file.quit", "Quitter"),
+    ("menu.edit", "\u{c9}dition"),
+    ("menu.edit.undo", "Annuler"),
+    ("menu.edit.redo", "R\u{e9}tablir"),
+    ("menu.edit.cut", "Couper"),
+    ("menu.edit.copy", "Copier"),
+    ("menu.edit.paste", "Coller"),
+    ("menu.edit.select_all", "Tout s\u{e9}lectionner"),
+    ("menu.edit.find", "Rechercher\u{2026}"),
+    ("menu.view", "Pr\u{e9}sentation"),
+    ("menu.view.reload", "Recharger"),
+    ("menu.view.force_reload", "Forcer le rechargement"),
+    ("menu.view.pause_polling", "Suspendre les mises \u{e0} jour"),
+    ("menu.window", "Fen\u{ea}tre"),
+    ("menu.window.new_panel", "Nouvelle fen\u{ea}tre de panneau\u{2026}"),
+    ("menu.help", "Aide"),
+    ("menu.help.about", "\u{c0} propos de World Monitor"),
+    ("menu.help.check_updates", "Rechercher des mises \u{e0} jour\u{2026}"),
+    ("menu.help.export_diagnostics", "Exporter les diagnostics\u{2026}"),
+    ("menu.help.show_logs", "Afficher les journaux"),
+    ("menu.help.open_data", "Ouvrir le dossier des donn\u{e9}es"),
+    ("menu.help.github", "D\u{e9}p\u{f4}t GitHub"),
+    ("menu.help.devtools", "Outils de d\u{e9}veloppement"),
+    ("dialog.save", "Enregistrer sous"),
+    ("dialog.open", "Ouvrir"),
+];
+
+const DE: Table = &[
+    ("menu.file", "Datei"),
+    ("menu.file.settings", "Einstellungen\u{2026}"),
+    ("menu.file.export_screenshot", "Bildschirmfoto exportieren\u{2026}"),
+    ("menu.file.restart", "Neu starten"),
+    ("menu.file.quit", "Beenden"),
+    ("menu.edit", "Bearbeiten"),
+    ("menu.edit.undo", "Widerrufen"),
+    ("menu.edit.redo", "Wiederholen"),
+    ("menu.edit.cut", "Ausschneiden"),
+    ("menu.edit.copy", "Kopieren"),
+    ("menu.edit.paste", "Einf\u{fc}gen"),
+    ("menu.edit.select_all", "Alles ausw\u{e4}hlen"),
+    ("menu.edit.find", "Suchen\u{2026}"),
+    ("menu.view", "Darstellung"),
+    ("menu.view.reload", "Neu laden"),
+    ("menu.view.force_reload", "Neu laden erzwingen"),
+    ("menu.view.pause_polling", "Abrufe pausieren"),
+    ("menu.window", "Fenster"),
+    ("menu.window.new_panel", "Neues Panel-Fenster\u{2026}"),
+    ("menu.help", "Hilfe"),
+    ("menu.help.about", "\u{dc}ber World Monitor"),
+    ("menu.help.check_updates", "Nach Updates suchen\u{2026}"),
+    ("menu.help.export_diagnostics", "Diagnose exportieren\u{2026}"),
+    ("menu.help.show_logs", "Protokolle anzeigen"),
+    ("menu.help.open_data", "App-Datenordner \u{f6}ffnen"),
+    ("menu.help.github", "GitHub-Repository"),
+    ("menu.help.devtools", "Entwicklerwerkzeuge ein/aus"),
+    ("dialog.save", "Speichern unter"),
+    ("dialog.open", "\u{d6}ffnen"),
+];
+
+const ES: Table = &[
+    ("menu.file", "Archivo"),
+    ("menu.file.settings", "Configuraci\u{f3}n\u{2026}"),
+    ("menu.file.export_screenshot", "Exportar captura de pantalla\u{2026}"),
+    ("menu.file.restart", "Reiniciar"),
+    ("menu.file.quit", "Salir"),
+    ("menu.edit", "Edici\u{f3}n"),
+    ("menu.edit.undo", "Deshacer"),
+    ("menu.edit.redo", "Rehacer"),
+    ("menu.edit.cut", "Cortar"),
+    ("menu.edit.copy", "Copiar"),
+    ("menu.edit.paste", "Pegar"),
+    ("menu.edit.select_all", "Seleccionar todo"),
+    ("menu.edit.find", "Buscar\u{2026}"),
+    ("menu.view", "Ver"),
+    ("menu.view.reload", "Recargar"),
+    ("menu.view.force_reload", "Forzar recarga"),
+    ("menu.view.pause_polling", "Pausar actualizaciones"),
+    ("menu.window", "Ventana"),
+    ("menu.window.new_panel", "Nueva ventana de panel\u{2026}"),
+    ("menu.help", "Ayuda"),
+    ("menu.help.about", "Acerca de World Monitor"),
+    ("menu.help.check_updates", "Buscar actualizaciones\u{2026}"),
+    ("menu.help.export_diagnostics", "Exportar diagn\u{f3}stico\u{2026}"),
+    ("menu.help.show_logs", "Mostrar registros"),
+    ("menu.help.open_data", "Abrir carpeta de datos"),
+    ("menu.help.github", "Repositorio de GitHub"),
+    ("menu.help.devtools", "Herramientas de desarrollo"),
+    ("dialog.save", "Guardar como"),
+    ("dialog.open", "Abrir"),
+];
+
+/// Language code and its table; English first.
+pub(crate) const LANGUAGES: [(&str, Table); 4] = [("en", EN), ("fr", FR), ("de", DE), ("es", ES)];
+
+fn table(language: &str) -> Table {
+    LANGUAGES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map_or(EN, |(_, table)| *table)
+}
+
+fn lookup(table: Table, id: &str) -> Option<&'static str> {
+    table.iter().find(|(known, _)| *known == id).map(|(_, text)| *text)
+}
+
+/// `id` in `language`, else in English, else the id itself.
+pub(crate) fn text(language: &str, id: &'static str) -> &'static str {
+    lookup(table(language), id).or_else(|| lookup(EN, id)).unwrap_or(id)
+}
+
+fn supported(code: &str) -> Option<&'static str> {
+    LANGUAGES.iter().map(|(known, _)| *known).find(|known| *known == code)
+}
+
+/// The language for a pref value (`"auto"` or a code) and a BCP 47 system
+/// locale such as `fr-CA`.
+pub(crate) fn resolve(pref: Option<&str>, system_locale: Option<&str>) -> &'static str {
+    pref.filter(|pref| *pref != "auto")
+        .and_then(supported)
+        .or_else(|| {
+            let primary = system_locale?.split(['-', '_']).next()?.to_ascii_lowercase();
+            supported(&primary)
+        })
+        .unwrap_or(FALLBACK)
+}
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    match value.as_str() {
+        Some(code) if code == "auto" || supported(code).is_some() => Ok(()),
+        _ => Err(format!(
+            "Runtime pref {PREF_UI_LANGUAGE} must be \"auto\" or one of: {}",
+            LANGUAGES.map(|(code, _)| code).join(", ")
+        )),
+    }
+}
+
+fn pref(app: &AppHandle) -> Option<String> {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_UI_LANGUAGE))
+        .and_then(|value| value.as_str().map(str::to_string))
+}
+
+/// Collected in setup; the menu is first built before that.
+fn system_locale(app: &AppHandle) -> Option<String> {
+    match app.try_state::<StaticRuntimeInfo>() {
+        Some(info) => info.locale.clone(),
+        None => runtime_info::system_locale(),
+    }
+}
+
+/// The language the menus should use now.
+pub(crate) fn language(app: &AppHandle) -> &'static str {
+    resolve(pref(app).as_deref(), system_locale(app).as_deref())
+}
+
+/// `get_ui_language` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct UiLanguage {
+    language: &'static str,
+    /// `"auto"` unless the pref names a language.
+    setting: String,
+    system_locale: Option<String>,
+    available: Vec<&'static str>,
+}
+
+pub(crate) fn ui_language(app: &AppHandle) -> UiLanguage {
+    UiLanguage {
+        language: language(app),
+        setting: pref(app).unwrap_or_else(|| "auto".to_string()),
+        system_locale: system_locale(app),
+        available: LANGUAGES.iter().map(|(code, _)| *code).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{resolve, text, validate_pref, EN, LANGUAGES};
+
+    #[test]
+    fn every_language_covers_every_english_id() {
+        for (code, table) in LANGUAGES {
+            let mut ids: Vec<&str> = table.iter().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            let mut expected: Vec<&str> = EN.iter().map(|(id, _)| *id).collect();
+            expected.sort_unstable();
+            assert_eq!(ids, expected, "{code}");
+            assert!(table.iter().all(|(_, text)| !text.trim().is_empty()), "{code}");
+        }
+    }
+
+    #[test]
+    fn pref_wins_over_the_system_locale() {
+        assert_eq!(resolve(None, Some("fr-CA")), "fr");
+        assert_eq!(resolve(Some("auto"), Some("de-AT")), "de");
+        assert_eq!(resolve(Some("es"), Some("fr-FR")), "es");
+        assert_eq!(resolve(None, Some("ja-JP")), "en");
+        assert_eq!(resolve(None, None), "en");
+        assert_eq!(resolve(Some("xx"), Some("de_DE")), "de");
+    }
+
+    #[test]
+    fn missing_strings_fall_back_to_english() {
+        assert_eq!(text("de", "menu.file"), "Datei");
+        assert_eq!(text("pt", "menu.file"), "File");
+        assert_eq!(text("fr", "menu.unknown"), "menu.unknown");
+    }
+
+    #[test]
+    fn pref_accepts_auto_and_bundled_languages() {
+        for ok in ["auto", "en", "fr", "de", "es"] {
+            assert!(validate_pref(&json!(ok)).is_ok(), "{ok}");
+        }
+        for bad in [json!("FR"), json!("pt"), json!(1)] {
+            assert!(validate_pref(&bad).is_err(), "{bad}");
+        }
+    }
+}
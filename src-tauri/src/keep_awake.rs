@@ -0,0 +1,344 @@
+//! Keep the machine awake while the app runs on a wall display. `display`
+//! mode keeps the screen on (and therefore the system too); `system` mode
+//! only stops idle sleep. The inhibitor lives in managed state and is
+//! released by `release` on exit, or by the OS when the process dies.
+//!
+//! Backends: an IOKit power assertion on macOS, `SetThreadExecutionState`
+//! held by a parked thread on Windows, and a `systemd-inhibit` child process
+//! elsewhere. The freedesktop ScreenSaver inhibit is tied to the D-Bus
+//! connection that requested it, which a one-shot `gdbus call` closes at
+//! once, so display mode on Linux takes logind's `idle` lock instead.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::logging::log_event;
+use crate::{store_runtime_pref, RuntimePrefs};
+use platform::Inhibitor;
+
+/// The mode to reacquire at startup; absent when keep-awake is off or was
+/// not persisted. Only written by this module.
+pub(crate) const PREF_KEEP_AWAKE: &str = "keepAwake";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum KeepAwakeMode {
+    Display,
+    System,
+}
+
+impl KeepAwakeMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            KeepAwakeMode::Display => "display",
+            KeepAwakeMode::System => "system",
+        }
+    }
+}
+
+/// What is actually held, which can differ from what was asked for.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct KeepAwakeStatus {
+    active: bool,
+    mode: Option<KeepAwakeMode>,
+}
+
+#[derive(Default)]
+pub(crate) struct KeepAwakeState {
+    held: Mutex<Option<(KeepAwakeMode, Inhibitor)>>,
+}
+
+pub(crate) fn status(app: &AppHandle) -> KeepAwakeStatus {
+    let held = app.state::<KeepAwakeState>();
+    let mode = held.held.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|(mode, _)| *mode);
+    KeepAwakeStatus {
+        active: mode.is_some(),
+        mode,
+    }
+}
+
+/// Acquire or release. Failures to acquire are logged and reported through
+/// the returned status rather than as errors, so the UI shows the real state.
+pub(crate) fn set_keep_awake(app: &AppHandle, enabled: bool, mode: KeepAwakeMode, persist: bool) -> KeepAwakeStatus {
+    release(app);
+    if enabled {
+        acquire(app, mode);
+    }
+    let remembered = if enabled && persist { Value::from(mode.as_str()) } else { Value::Null };
+    if let Err(err) = store_runtime_pref(app, PREF_KEEP_AWAKE, remembered) {
+        log_event(app, "WARN", "keep_awake_pref_failed", &[("error", &err.to_string())]);
+    }
+    status(app)
+}
+
+/// Reacquire the persisted mode. Called once from setup.
+pub(crate) fn restore(app: &AppHandle) {
+    let mode = app
+        .state::<RuntimePrefs>()
+        .get(PREF_KEEP_AWAKE)
+        .and_then(|value| serde_json::from_value::<KeepAwakeMode>(value).ok());
+    if let Some(mode) = mode {
+        acquire(app, mode);
+    }
+}
+
+fn acquire(app: &AppHandle, mode: KeepAwakeMode) {
+    match Inhibitor::acquire(mode) {
+        Ok(inhibitor) => {
+            *app.state::<KeepAwakeState>().held.lock().unwrap_or_else(|e| e.into_inner()) = Some((mode, inhibitor));
+            log_event(app, "INFO", "keep_awake_acquired", &[("mode", mode.as_str())]);
+        }
+        Err(err) => log_event(app, "WARN", "keep_awake_failed", &[("mode", mode.as_str()), ("error", &err)]),
+    }
+}
+
+pub(crate) fn release(app: &AppHandle) {
+    let Some(state) = app.try_state::<KeepAwakeState>() else {
+        return;
+    };
+    let held = state.held.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some((mode, inhibitor)) = held {
+        drop(inhibitor);
+        log_event(app, "INFO", "keep_awake_released", &[("mode", mode.as_str())]);
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{c_char, c_void, CString};
+
+    use super::KeepAwakeMode;
+
+    type CFStringRef = *const c_void;
+    const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    const K_IOPM_ASSERTION_LEVEL_ON: u32 = 255;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFStringCreateWithCString(alloc: *const c_void, c_str: *const c_char, encoding: u32) -> CFStringRef;
+        fn CFRelease(cf: *const c_void);
+    }
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOPMAssertionCreateWithName(
+            assertion_type: CFStringRef,
+            level: u32,
+            name: CFStringRef,
+            assertion_id: *mut u32,
+        ) -> i32;
+        fn IOPMAssertionRelease(assertion_id: u32) -> i32;
+    }
+
+    fn cf_string(value: &str) -> CFStringRef {
+        let c_value = CString::new(value).expect("static string without NUL");
+        // SAFETY: `c_value` is a valid NUL-terminated UTF-8 string.
+        unsafe { CFStringCreateWithCString(std::ptr::null(), c_value.as_ptr(), K_CF_STRING_ENCODING_UTF8) }
+    }
+
+    pub(super) struct Inhibitor(u32);
+
+    impl Inhibitor {
+        pub(super) fn acquire(mode: KeepAwakeMode) -> Result<Self, String> {
+            let kind = match mode {
+                KeepAwakeMode::Display => "PreventUserIdleDisplaySleep",
+                KeepAwakeMode::System => "PreventUserIdleSystemSleep",
+            };
+            let kind = cf_string(kind);
+            let name = cf_string("World Monitor keep-awake");
+            let mut assertion_id = 0u32;
+            // SAFETY: both CFStrings are valid (checked below) and released after the call.
+            let result = unsafe {
+                let result = if kind.is_null() || name.is_null() {
+                    -1
+                } else {
+                    IOPMAssertionCreateWithName(kind, K_IOPM_ASSERTION_LEVEL_ON, name, &mut assertion_id)
+                };
+                for value in [kind, name] {
+                    if !value.is_null() {
+                        CFRelease(value);
+                    }
+                }
+                result
+            };
+            if result == 0 {
+                Ok(Inhibitor(assertion_id))
+            } else {
+                Err(format!("IOPMAssertionCreateWithName returned {result:#x}"))
+            }
+        }
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            // SAFETY: the id came from a successful IOPMAssertionCreateWithName.
+            unsafe {
+                IOPMAssertionRelease(self.0);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::sync::mpsc;
+    use std::thread::{self, JoinHandle};
+
+    use super::KeepAwakeMode;
+
+    const ES_CONTINUOUS: u32 = 0x8000_0000;
+    const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+    const ES_DISPLAY_REQUIRED: u32 = 0x0000_0002;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetThreadExecutionState(flags: u32) -> u32;
+    }
+
+    /// The execution state belongs to the calling thread, so a dedicated
+    /// thread sets it and parks until told to clear it.
+    pub(super) struct Inhibitor {
+        stop: Option<mpsc::Sender<()>>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Inhibitor {
+        pub(super) fn acquire(mode: KeepAwakeMode) -> Result<Self, String> {
+            let flags = match mode {
+                KeepAwakeMode::Display => ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED,
+                KeepAwakeMode::System => ES_CONTINUOUS | ES_SYSTEM_REQUIRED,
+            };
+            let (stop_tx, stop_rx) = mpsc::channel::<()>();
+            let (ready_tx, ready_rx) = mpsc::channel::<bool>();
+            let thread = thread::Builder::new()
+                .name("keep-awake".into())
+                .spawn(move || {
+                    // SAFETY: plain flag arguments; 0 means the call failed.
+                    let ok = unsafe { SetThreadExecutionState(flags) } != 0;
+                    let _ = ready_tx.send(ok);
+                    if ok {
+                        let _ = stop_rx.recv();
+                        // SAFETY: as above; clears the thread's requirements.
+                        unsafe {
+                            SetThreadExecutionState(ES_CONTINUOUS);
+                        }
+                    }
+                })
+                .map_err(|e| format!("Failed to start keep-awake thread: {e}"))?;
+            if ready_rx.recv().unwrap_or(false) {
+                Ok(Inhibitor {
+                    stop: Some(stop_tx),
+                    thread: Some(thread),
+                })
+            } else {
+                let _ = thread.join();
+                Err("SetThreadExecutionState failed".to_string())
+            }
+        }
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            drop(self.stop.take());
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::process::{Child, Command, Stdio};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{inhibit_args, KeepAwakeMode};
+
+    /// How long `systemd-inhibit` gets to fail (no logind, policy denial)
+    /// before the lock is considered held.
+    const STARTUP_GRACE: Duration = Duration::from_millis(300);
+
+    pub(super) struct Inhibitor(Child);
+
+    impl Inhibitor {
+        pub(super) fn acquire(mode: KeepAwakeMode) -> Result<Self, String> {
+            let mut child = Command::new("systemd-inhibit")
+                .args(inhibit_args(mode))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .env_remove("LD_LIBRARY_PATH")
+                .env_remove("LD_PRELOAD")
+                .spawn()
+                .map_err(|e| format!("Failed to run systemd-inhibit: {e}"))?;
+            thread::sleep(STARTUP_GRACE);
+            match child.try_wait() {
+                Ok(None) => Ok(Inhibitor(child)),
+                Ok(Some(status)) => Err(format!("systemd-inhibit exited early ({status})")),
+                Err(err) => {
+                    let _ = child.kill();
+                    Err(format!("Failed to check systemd-inhibit: {err}"))
+                }
+            }
+        }
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use super::KeepAwakeMode;
+
+    pub(super) struct Inhibitor;
+
+    impl Inhibitor {
+        pub(super) fn acquire(_mode: KeepAwakeMode) -> Result<Self, String> {
+            Err("Keep-awake is not supported on this platform".to_string())
+        }
+    }
+}
+
+/// `systemd-inhibit` arguments holding the lock until the child is killed.
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn inhibit_args(mode: KeepAwakeMode) -> Vec<String> {
+    let what = match mode {
+        KeepAwakeMode::Display => "idle:sleep",
+        KeepAwakeMode::System => "sleep",
+    };
+    let mut args = vec![format!("--what={what}")];
+    args.extend(
+        ["--who=World Monitor", "--why=Keep-awake is on", "--mode=block", "sleep", "infinity"].map(String::from),
+    );
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inhibit_args, KeepAwakeMode};
+
+    #[test]
+    fn modes_round_trip_through_the_pref() {
+        for mode in [KeepAwakeMode::Display, KeepAwakeMode::System] {
+            let value = serde_json::Value::from(mode.as_str());
+            assert_eq!(serde_json::from_value::<KeepAwakeMode>(value).unwrap(), mode);
+        }
+        assert!(serde_json::from_value::<KeepAwakeMode>(serde_json::json!("screen")).is_err());
+    }
+
+    #[test]
+    fn display_mode_also_blocks_idle() {
+        assert_eq!(inhibit_args(KeepAwakeMode::System)[0], "--what=sleep");
+        assert_eq!(inhibit_args(KeepAwakeMode::Display)[0], "--what=idle:sleep");
+        assert_eq!(inhibit_args(KeepAwakeMode::Display)[4..], ["sleep", "infinity"]);
+    }
+}
@@ -0,0 +1,568 @@
+//! Moves secrets from the old one-entry-per-key keychain layout into the
+//! consolidated vault. Each legacy read is a keychain prompt on macOS, so
+//! this runs on a background thread once the main window is up; until it
+//! finishes `get_secret` serves whatever the vault already held. Headless
+//! runs migrate before the sidecar starts, since nothing is on screen.
+//!
+//! Progress (key names only, never values) is journaled in
+//! `keychain-migration.json` after every step, so a quit mid-way resumes
+//! where it stopped. Some Linux keyring daemons acknowledge a write and then
+//! lose it on restart, so every vault write is read back and compared, and
+//! before each legacy entry is deleted the vault is read again and must hold
+//! that exact value. A key missing from the vault by then is copied again;
+//! one holding a different (newer) value keeps its legacy entry. When keys
+//! were moved, the sidecar is restarted to pick them up and
+//! `secrets-migrated` lists them.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logging::{log_event, LogRedaction};
+use crate::secret_store::SecretStore;
+use crate::{vault_events, vault_sync, SecretsCache, SUPPORTED_SECRET_KEYS};
+
+pub(crate) const PROGRESS_FILE: &str = "keychain-migration.json";
+const MIGRATED_EVENT: &str = "secrets-migrated";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Phase {
+    #[default]
+    NotStarted,
+    /// Copying legacy entries into the vault.
+    Reading,
+    /// Everything is in the vault; removing the legacy entries.
+    Cleaning,
+    Done,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+struct Progress {
+    phase: Phase,
+    /// Keys whose legacy entry has been looked at.
+    #[serde(default)]
+    read: Vec<String>,
+    /// Keys that had a legacy value, now in the vault.
+    #[serde(default)]
+    moved: Vec<String>,
+    /// Moved keys whose legacy entry has been deleted.
+    #[serde(default)]
+    deleted: Vec<String>,
+    /// Moved keys whose legacy entry was left alone: the vault holds a
+    /// different value for them.
+    #[serde(default)]
+    kept: Vec<String>,
+}
+
+fn contains(list: &[String], key: &str) -> bool {
+    list.iter().any(|item| item == key)
+}
+
+/// Write the whole vault and read it back; an acknowledged write that
+/// doesn't read back the same is an error.
+fn write_verified(store: &dyn SecretStore, supported: &[&str], secrets: &HashMap<String, String>) -> Result<(), String> {
+    vault_sync::save_vault(store, secrets).map_err(|e| e.to_string())?;
+    let stored = vault_sync::read_vault(store, supported).map_err(|e| e.to_string())?;
+    if &stored != secrets {
+        return Err("The vault read back differently than it was written".to_string());
+    }
+    Ok(())
+}
+
+/// Run the migration from wherever `progress` left off, calling `merge` to
+/// put each legacy value in the vault and `save` after every step. A
+/// keychain error stops it; the next launch resumes. `vault_in_use` skips
+/// the whole thing for vaults written by a version that had already
+/// migrated.
+fn advance(
+    store: &dyn SecretStore,
+    supported: &[&str],
+    vault_in_use: bool,
+    progress: &mut Progress,
+    merge: &mut dyn FnMut(&str, String) -> Result<(), String>,
+    save: &mut dyn FnMut(&Progress) -> Result<(), String>,
+) -> Result<(), String> {
+    if progress.phase == Phase::NotStarted {
+        progress.phase = if vault_in_use { Phase::Done } else { Phase::Reading };
+        save(progress)?;
+    }
+    let mut recopied = false;
+    loop {
+        if progress.phase == Phase::Reading {
+            read_legacy(store, supported, progress, merge, save)?;
+            progress.phase = Phase::Cleaning;
+            save(progress)?;
+        }
+        if progress.phase != Phase::Cleaning {
+            return Ok(());
+        }
+        let vault = vault_sync::read_vault(store, supported).map_err(|e| e.to_string())?;
+        let lost: Vec<String> = progress
+            .moved
+            .iter()
+            .filter(|key| !vault.contains_key(*key) && !contains(&progress.deleted, key))
+            .cloned()
+            .collect();
+        if lost.is_empty() {
+            delete_confirmed(store, &vault, progress, save)?;
+            progress.phase = Phase::Done;
+            return save(progress);
+        }
+        // The vault lost what was written: copy those keys again, once.
+        if recopied {
+            return Err(format!("The vault did not keep {}", lost.join(", ")));
+        }
+        recopied = true;
+        progress.read.retain(|key| !lost.contains(key));
+        progress.moved.retain(|key| !lost.contains(key));
+        progress.phase = Phase::Reading;
+        save(progress)?;
+    }
+}
+
+fn read_legacy(
+    store: &dyn SecretStore,
+    supported: &[&str],
+    progress: &mut Progress,
+    merge: &mut dyn FnMut(&str, String) -> Result<(), String>,
+    save: &mut dyn FnMut(&Progress) -> Result<(), String>,
+) -> Result<(), String> {
+    for key in supported {
+        if contains(&progress.read, key) {
+            continue;
+        }
+        if let Some(value) = store.get(key).map_err(|e| e.to_string())? {
+            let trimmed = value.trim();
+            if !trimmed.is_empty() {
+                merge(key, trimmed.to_string())?;
+                progress.moved.push((*key).to_string());
+            }
+        }
+        progress.read.push((*key).to_string());
+        save(progress)?;
+    }
+    Ok(())
+}
+
+/// Delete moved legacy entries one at a time, each only once `vault` is
+/// seen to hold its exact value.
+fn delete_confirmed(
+    store: &dyn SecretStore,
+    vault: &HashMap<String, String>,
+    progress: &mut Progress,
+    save: &mut dyn FnMut(&Progress) -> Result<(), String>,
+) -> Result<(), String> {
+    for key in progress.moved.clone() {
+        if contains(&progress.deleted, &key) || contains(&progress.kept, &key) {
+            continue;
+        }
+        match store.get(&key).map_err(|e| e.to_string())? {
+            Some(value) if vault.get(&key).map(String::as_str) == Some(value.trim()) => {
+                store.delete(&key).map_err(|e| e.to_string())?;
+                progress.deleted.push(key);
+            }
+            // Already gone, say by a run that stopped before journaling it.
+            None => progress.deleted.push(key),
+            Some(_) => progress.kept.push(key),
+        }
+        save(progress)?;
+    }
+    Ok(())
+}
+
+fn phase_name(phase: Phase) -> &'static str {
+    match phase {
+        Phase::NotStarted => "not_started",
+        Phase::Reading => "reading",
+        Phase::Cleaning => "cleaning",
+        Phase::Done => "done",
+    }
+}
+
+fn progress_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::data_dir::resolve_data_dir(app).map(|dir| dir.join(PROGRESS_FILE))
+}
+
+fn load_progress(app: &AppHandle) -> Progress {
+    progress_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_progress(app: &AppHandle, progress: &Progress) -> Result<(), String> {
+    let path = progress_path(app)?;
+    let json = serde_json::to_string_pretty(progress).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// The vault to write for one legacy value: `cached` plus `key`, unless
+/// the cache already has that key (set since the upgrade, so newer). The
+/// stored vault is read first and may only have lost entries, as when a
+/// daemon drops them; one that can't be read, or holds anything the cache
+/// doesn't, stops the migration rather than being overwritten.
+fn merged_vault(
+    store: &dyn SecretStore,
+    supported: &[&str],
+    cached: &HashMap<String, String>,
+    key: &str,
+    value: String,
+) -> Result<HashMap<String, String>, String> {
+    let stored = vault_sync::read_vault(store, supported).map_err(|e| e.to_string())?;
+    if stored.iter().any(|(key, value)| cached.get(key) != Some(value)) {
+        return Err("The vault was changed outside the app; reload secrets to resume".to_string());
+    }
+    let mut proposed = cached.clone();
+    proposed.entry(key.to_string()).or_insert(value);
+    Ok(proposed)
+}
+
+/// Add one legacy value to the vault and the cache. The vault is rewritten
+/// either way, which restores entries a daemon lost.
+fn merge_into_vault(app: &AppHandle, key: &str, value: String) -> Result<(), String> {
+    let cache = app.state::<SecretsCache>();
+    let mut secrets = cache.secrets.lock().unwrap_or_else(|e| e.into_inner());
+    let proposed = merged_vault(&*cache.store, &SUPPORTED_SECRET_KEYS, &secrets, key, value)?;
+    write_verified(&*cache.store, &SUPPORTED_SECRET_KEYS, &proposed)?;
+    cache.set_fingerprint(vault_sync::fingerprint(&proposed));
+    app.state::<LogRedaction>().rebuild(&proposed);
+    vault_events::notify(app, &secrets, &proposed);
+    *secrets = proposed;
+    Ok(())
+}
+
+/// Migrate on the calling thread. True when this run moved keys.
+pub(crate) fn run(app: &AppHandle) -> bool {
+    let mut progress = load_progress(app);
+    if progress.phase == Phase::Done {
+        return false;
+    }
+    let cache = app.state::<SecretsCache>();
+    // An empty cache may only mean the vault couldn't be read.
+    if let Some(err) = cache.load_error() {
+        log_event(app, "WARN", "keychain_migration_paused", &[("error", &err), ("read", &progress.read.len().to_string())]);
+        return false;
+    }
+    let store = cache.store.clone();
+    let vault_in_use = !cache.secrets.lock().unwrap_or_else(|e| e.into_inner()).is_empty();
+    let moved_before = progress.moved.len();
+    let mut logged_phase = progress.phase;
+    let result = advance(
+        &*store,
+        &SUPPORTED_SECRET_KEYS,
+        vault_in_use,
+        &mut progress,
+        &mut |key, value| merge_into_vault(app, key, value),
+        &mut |progress| {
+            if progress.phase != logged_phase {
+                logged_phase = progress.phase;
+                log_event(app, "INFO", "keychain_migration_phase", &[("phase", phase_name(progress.phase))]);
+            }
+            save_progress(app, progress)
+        },
+    );
+    if let Err(err) = result {
+        log_event(app, "WARN", "keychain_migration_paused", &[("error", &err), ("read", &progress.read.len().to_string())]);
+        return progress.moved.len() > moved_before;
+    }
+    if !progress.kept.is_empty() {
+        log_event(app, "WARN", "keychain_migration_kept_legacy", &[("keys", &progress.kept.join(","))]);
+    }
+    if progress.moved.is_empty() {
+        return false;
+    }
+    log_event(app, "INFO", "keychain_migrated", &[("keys", &progress.moved.len().to_string())]);
+    let _ = app.emit(MIGRATED_EVENT, &progress.moved);
+    progress.moved.len() > moved_before
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::{advance, merged_vault, write_verified, Phase, Progress};
+    use crate::secret_store::{MemoryStore, SecretStore, StoreError};
+    use crate::vault_sync::{read_vault, save_vault, VAULT_ACCOUNT};
+
+    const SUPPORTED: [&str; 3] = ["GROQ_API_KEY", "FRED_API_KEY", "ACLED_ACCESS_TOKEN"];
+
+    /// A [`MemoryStore`] that records legacy reads and fails on demand.
+    #[derive(Default)]
+    struct MockKeyring {
+        store: MemoryStore,
+        /// Reads of this account fail, as when the user denies the prompt.
+        deny: Option<&'static str>,
+        /// Writes are acknowledged but not kept.
+        drop_writes: bool,
+        /// Deleting this account fails.
+        fail_delete: Option<&'static str>,
+        reads: Mutex<Vec<String>>,
+    }
+
+    impl MockKeyring {
+        fn with(entries: &[(&str, &str)]) -> Self {
+            MockKeyring {
+                store: MemoryStore::with(entries),
+                ..Default::default()
+            }
+        }
+
+        fn reads(&self) -> Vec<String> {
+            self.reads.lock().unwrap().clone()
+        }
+
+        fn vault(&self) -> HashMap<String, String> {
+            read_vault(&self.store, &SUPPORTED).unwrap()
+        }
+    }
+
+    impl SecretStore for MockKeyring {
+        fn get(&self, account: &str) -> Result<Option<String>, StoreError> {
+            if account != VAULT_ACCOUNT {
+                self.reads.lock().unwrap().push(account.to_string());
+            }
+            if self.deny == Some(account) {
+                return Err(StoreError::Backend("denied".to_string()));
+            }
+            self.store.get(account)
+        }
+
+        fn set(&self, account: &str, value: &str) -> Result<(), StoreError> {
+            if self.drop_writes {
+                return Ok(());
+            }
+            self.store.set(account, value)
+        }
+
+        fn delete(&self, account: &str) -> Result<(), StoreError> {
+            if self.fail_delete == Some(account) {
+                return Err(StoreError::Backend("delete failed".to_string()));
+            }
+            self.store.delete(account)
+        }
+    }
+
+    /// Run with the vault kept in the same store and merged the way the app
+    /// does it; `stop_at` makes saving that phase fail, like a quit.
+    fn migrate_until(
+        keyring: &MockKeyring,
+        vault_in_use: bool,
+        progress: &mut Progress,
+        stop_at: Option<Phase>,
+    ) -> (Result<(), String>, Vec<Progress>) {
+        let mut saved = Vec::new();
+        // Loaded at startup, like the app's cache.
+        let mut cache = keyring.vault();
+        let result = advance(
+            keyring,
+            &SUPPORTED,
+            vault_in_use,
+            progress,
+            &mut |key, value| {
+                let proposed = merged_vault(keyring, &SUPPORTED, &cache, key, value)?;
+                write_verified(keyring, &SUPPORTED, &proposed)?;
+                cache = proposed;
+                Ok(())
+            },
+            &mut |progress| {
+                if stop_at == Some(progress.phase) {
+                    return Err("quit".to_string());
+                }
+                saved.push(progress.clone());
+                Ok(())
+            },
+        );
+        (result, saved)
+    }
+
+    fn migrate(keyring: &MockKeyring, vault_in_use: bool, progress: &mut Progress) -> (Result<(), String>, Vec<Progress>) {
+        migrate_until(keyring, vault_in_use, progress, None)
+    }
+
+    #[test]
+    fn legacy_entries_move_into_the_vault_then_are_deleted() {
+        let keyring = MockKeyring::with(&[("GROQ_API_KEY", " gsk_1 "), ("ACLED_ACCESS_TOKEN", "a1"), ("FRED_API_KEY", "  ")]);
+        let mut progress = Progress::default();
+        let (result, saved) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_ok());
+        assert_eq!(progress.phase, Phase::Done);
+        assert_eq!(progress.moved, ["GROQ_API_KEY", "ACLED_ACCESS_TOKEN"]);
+        assert_eq!(progress.deleted, progress.moved);
+        let vault = keyring.vault();
+        assert_eq!(vault.get("GROQ_API_KEY").map(String::as_str), Some("gsk_1"));
+        assert_eq!(vault.get("ACLED_ACCESS_TOKEN").map(String::as_str), Some("a1"));
+        assert!(!vault.contains_key("FRED_API_KEY"));
+        // Only the moved entries are deleted; the blank one was never ours to move.
+        assert_eq!(keyring.store.accounts(), ["FRED_API_KEY", VAULT_ACCOUNT]);
+        // Nothing is deleted before every key has been read, and each
+        // deletion is journaled on its own.
+        let cleaning = saved.iter().position(|p| p.phase == Phase::Cleaning).unwrap();
+        assert_eq!(saved[cleaning].read.len(), SUPPORTED.len());
+        assert!(saved[cleaning].deleted.is_empty());
+        assert_eq!(saved[cleaning + 1].deleted, ["GROQ_API_KEY"]);
+    }
+
+    #[test]
+    fn an_existing_vault_skips_migration() {
+        let keyring = MockKeyring::with(&[("GROQ_API_KEY", "old")]);
+        save_vault(&keyring.store, &HashMap::from([("GROQ_API_KEY".to_string(), "new".to_string())])).unwrap();
+        let mut progress = Progress::default();
+        let (result, _) = migrate(&keyring, true, &mut progress);
+        assert!(result.is_ok());
+        assert_eq!(progress.phase, Phase::Done);
+        assert!(keyring.reads().is_empty(), "no prompts");
+        assert_eq!(keyring.vault()["GROQ_API_KEY"], "new");
+    }
+
+    #[test]
+    fn a_failed_read_pauses_and_the_next_run_resumes_after_it() {
+        let mut keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")]);
+        keyring.deny = Some("FRED_API_KEY");
+        let mut progress = Progress::default();
+        let (result, _) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_err());
+        assert_eq!(progress.phase, Phase::Reading);
+        assert_eq!(progress.read, ["GROQ_API_KEY"]);
+        assert_eq!(keyring.store.accounts(), ["FRED_API_KEY", "GROQ_API_KEY", VAULT_ACCOUNT], "nothing deleted while paused");
+
+        // Next launch: the vault now has GROQ, so it looks in use, but the
+        // journal says the migration is mid-way.
+        keyring.deny = None;
+        keyring.reads.lock().unwrap().clear();
+        let (result, _) = migrate(&keyring, true, &mut progress);
+        assert!(result.is_ok());
+        assert_eq!(&keyring.reads()[..2], ["FRED_API_KEY", "ACLED_ACCESS_TOKEN"]);
+        assert_eq!(progress.moved, ["GROQ_API_KEY", "FRED_API_KEY"]);
+        assert_eq!(keyring.vault().len(), 2);
+        assert_eq!(keyring.store.accounts(), [VAULT_ACCOUNT]);
+    }
+
+    #[test]
+    fn an_unkept_vault_write_fails_verification_and_deletes_nothing() {
+        let mut keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1")]);
+        keyring.drop_writes = true;
+        let mut progress = Progress::default();
+        let (result, _) = migrate(&keyring, false, &mut progress);
+        assert_eq!(result.unwrap_err(), "The vault read back differently than it was written");
+        assert_eq!(progress.phase, Phase::Reading);
+        assert!(progress.moved.is_empty() && progress.read.is_empty());
+        assert_eq!(keyring.store.accounts(), ["GROQ_API_KEY"]);
+    }
+
+    #[test]
+    fn a_vault_lost_before_cleaning_is_copied_again() {
+        let keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")]);
+        let mut progress = Progress::default();
+        let (result, _) = migrate_until(&keyring, false, &mut progress, Some(Phase::Cleaning));
+        assert!(result.is_err());
+        // The keyring daemon restarts and the vault is gone.
+        keyring.store.delete(VAULT_ACCOUNT).unwrap();
+        progress.phase = Phase::Cleaning;
+
+        let (result, saved) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_ok());
+        assert!(saved.iter().any(|p| p.phase == Phase::Reading), "went back to copying");
+        assert_eq!(keyring.vault().len(), 2);
+        assert_eq!(keyring.store.accounts(), [VAULT_ACCOUNT]);
+    }
+
+    #[test]
+    fn a_legacy_value_the_vault_does_not_hold_is_kept() {
+        let keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_old"), ("FRED_API_KEY", "f1")]);
+        let mut progress = Progress::default();
+        let (result, _) = migrate_until(&keyring, false, &mut progress, Some(Phase::Cleaning));
+        assert!(result.is_err());
+        // The user saved a new key in settings before cleanup ran.
+        let mut vault = keyring.vault();
+        vault.insert("GROQ_API_KEY".to_string(), "gsk_new".to_string());
+        save_vault(&keyring.store, &vault).unwrap();
+        progress.phase = Phase::Cleaning;
+
+        let (result, _) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_ok());
+        assert_eq!(progress.kept, ["GROQ_API_KEY"]);
+        assert_eq!(progress.deleted, ["FRED_API_KEY"]);
+        assert_eq!(keyring.store.accounts(), ["GROQ_API_KEY", VAULT_ACCOUNT]);
+        assert_eq!(keyring.vault()["GROQ_API_KEY"], "gsk_new");
+    }
+
+    #[test]
+    fn a_failed_delete_resumes_from_the_journal() {
+        let mut keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")]);
+        keyring.fail_delete = Some("FRED_API_KEY");
+        let mut progress = Progress::default();
+        let (result, _) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_err());
+        assert_eq!(progress.phase, Phase::Cleaning);
+        assert_eq!(progress.deleted, ["GROQ_API_KEY"]);
+
+        keyring.fail_delete = None;
+        let (result, _) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_ok());
+        assert_eq!(progress.deleted, ["GROQ_API_KEY", "FRED_API_KEY"]);
+        assert_eq!(keyring.store.accounts(), [VAULT_ACCOUNT]);
+    }
+
+    #[test]
+    fn a_finished_migration_does_nothing_again() {
+        let keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1")]);
+        let mut progress = Progress {
+            phase: Phase::Done,
+            ..Default::default()
+        };
+        let (result, saved) = migrate(&keyring, false, &mut progress);
+        assert!(result.is_ok());
+        assert!(saved.is_empty());
+        assert!(keyring.reads().is_empty());
+    }
+
+    #[test]
+    fn journal_holds_key_names_only() {
+        let progress = Progress {
+            phase: Phase::Cleaning,
+            read: vec!["GROQ_API_KEY".to_string()],
+            moved: vec!["GROQ_API_KEY".to_string()],
+            deleted: vec!["GROQ_API_KEY".to_string()],
+            kept: Vec::new(),
+        };
+        let json = serde_json::to_value(&progress).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "phase": "cleaning",
+                "read": ["GROQ_API_KEY"],
+                "moved": ["GROQ_API_KEY"],
+                "deleted": ["GROQ_API_KEY"],
+                "kept": [],
+            })
+        );
+        assert_eq!(serde_json::from_value::<Progress>(json).unwrap(), progress);
+        // Journals from before deletions were tracked still load.
+        let old = serde_json::json!({ "phase": "reading", "read": [], "moved": [] });
+        assert_eq!(serde_json::from_value::<Progress>(old).unwrap().phase, Phase::Reading);
+    }
+
+    #[test]
+    fn merging_never_overwrites_a_vault_the_cache_does_not_hold() {
+        let keyring = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1")]);
+        save_vault(&keyring.store, &HashMap::from([("FRED_API_KEY".to_string(), "f1".to_string())])).unwrap();
+        // The startup read failed, so the cache is empty.
+        let empty = HashMap::new();
+        assert!(merged_vault(&keyring, &SUPPORTED, &empty, "GROQ_API_KEY", "gsk_1".to_string()).is_err());
+
+        let mut denied = MockKeyring::with(&[("GROQ_API_KEY", "gsk_1")]);
+        denied.deny = Some(VAULT_ACCOUNT);
+        assert!(merged_vault(&denied, &SUPPORTED, &empty, "GROQ_API_KEY", "gsk_1".to_string()).is_err());
+
+        // Entries the vault lost come back from the cache.
+        let cached = HashMap::from([("FRED_API_KEY".to_string(), "f1".to_string()), ("ACLED_ACCESS_TOKEN".to_string(), "a1".to_string())]);
+        let merged = merged_vault(&keyring, &SUPPORTED, &cached, "GROQ_API_KEY", "gsk_1".to_string()).unwrap();
+        assert_eq!(merged.len(), 3);
+    }
+}
@@ -0,0 +1,1128 @@
+//! WebKitGTK environment workarounds. The decisions are computed up front as
+//! a [`LinuxWebkitEnvPolicy`] (from the current env plus a hardware probe) so
+//! they can be logged and tested, then applied before Tauri starts any threads.
+//! Every variable is only set when the user hasn't configured it already.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data_dir, display_scale};
+
+/// Set to `1`/`true` to force the conservative (software-rendered) env.
+pub(crate) const SAFE_MODE_ENV: &str = "WM_LINUX_WEBKIT_SAFE_MODE";
+/// Optional per-machine overrides, in the app data dir.
+const OVERRIDES_FILE: &str = "webkit-policy.json";
+
+/// Renderer-relevant variables reported back after the policy was applied.
+const REPORTED_ENV_VARS: [&str; 13] = [
+    "WEBKIT_DISABLE_DMABUF_RENDERER",
+    "WEBKIT_DISABLE_COMPOSITING_MODE",
+    "WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS",
+    "LIBGL_ALWAYS_SOFTWARE",
+    "GDK_BACKEND",
+    "GDK_SCALE",
+    "GDK_DPI_SCALE",
+    "__NV_DISABLE_EXPLICIT_SYNC",
+    "GIO_MODULE_DIR",
+    "GST_PLUGIN_PATH_1_0",
+    "GST_PLUGIN_SYSTEM_PATH_1_0",
+    "GST_PLUGIN_SCANNER_1_0",
+    "GST_PLUGIN_BLACKLIST",
+];
+
+/// Distro GStreamer builds of `gst-plugins-bad` ship an ONNX plugin linked
+/// against a specific libonnxruntime soname. When that soname is missing
+/// (onnxruntime upgraded or never installed), the plugin scanner crashes
+/// WebKit's media process on first playback.
+const GST_ONNX_PLUGIN: &str = "onnx";
+const GST_ONNX_PLUGIN_FILE: &str = "libgstonnx.so";
+const ONNXRUNTIME_SONAME_PREFIX: &[u8] = b"libonnxruntime.so";
+const SYSTEM_GST_PLUGIN_DIRS: [&str; 4] = [
+    "/usr/lib/x86_64-linux-gnu/gstreamer-1.0",
+    "/usr/lib/aarch64-linux-gnu/gstreamer-1.0",
+    "/usr/lib64/gstreamer-1.0",
+    "/usr/lib/gstreamer-1.0",
+];
+const SYSTEM_LIB_DIRS: [&str; 6] = [
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/usr/lib64",
+    "/usr/lib",
+    "/usr/local/lib64",
+    "/usr/local/lib",
+];
+
+const PCI_VENDOR_NVIDIA: &str = "0x10de";
+const PCI_VENDOR_AMD: &str = "0x1002";
+const PCI_VENDOR_INTEL: &str = "0x8086";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Other,
+    #[default]
+    Unknown,
+}
+
+impl GpuVendor {
+    fn as_str(self) -> &'static str {
+        match self {
+            GpuVendor::Nvidia => "nvidia",
+            GpuVendor::Amd => "amd",
+            GpuVendor::Intel => "intel",
+            GpuVendor::Other => "other",
+            GpuVendor::Unknown => "unknown",
+        }
+    }
+}
+
+/// Pick the primary GPU vendor from the proprietary NVIDIA driver's version
+/// file (if loaded) and the PCI vendor ids of `/sys/class/drm` devices.
+/// A loaded NVIDIA driver wins; otherwise discrete vendors (NVIDIA, then AMD)
+/// are preferred over an integrated Intel GPU.
+pub(crate) fn detect_gpu_vendor(nvidia_driver_version: Option<&str>, drm_vendor_ids: &[String]) -> GpuVendor {
+    if nvidia_driver_version.is_some_and(|v| !v.trim().is_empty()) {
+        return GpuVendor::Nvidia;
+    }
+    let ids: Vec<String> = drm_vendor_ids.iter().map(|id| id.trim().to_ascii_lowercase()).collect();
+    let has = |vendor: &str| ids.iter().any(|id| id == vendor);
+    if has(PCI_VENDOR_NVIDIA) {
+        GpuVendor::Nvidia
+    } else if has(PCI_VENDOR_AMD) {
+        GpuVendor::Amd
+    } else if has(PCI_VENDOR_INTEL) {
+        GpuVendor::Intel
+    } else if ids.is_empty() {
+        GpuVendor::Unknown
+    } else {
+        GpuVendor::Other
+    }
+}
+
+fn drm_vendor_ids() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("card"))
+        .filter_map(|entry| fs::read_to_string(entry.path().join("device/vendor")).ok())
+        .collect()
+}
+
+/// Hardware/session facts the policy depends on, detected once at launch.
+#[derive(Debug, Default)]
+pub(crate) struct LinuxGraphicsProbe {
+    pub(crate) in_vm: bool,
+    /// The proprietary NVIDIA kernel driver is loaded.
+    pub(crate) has_nvidia: bool,
+    pub(crate) gpu_vendor: GpuVendor,
+    pub(crate) appimage_gio_module_dir: Option<PathBuf>,
+    /// An ONNX GStreamer plugin is installed but the libonnxruntime it was
+    /// linked against is not.
+    pub(crate) gst_onnx_plugin_broken: bool,
+}
+
+impl LinuxGraphicsProbe {
+    pub(crate) fn detect() -> Self {
+        // Detect VM environments via /proc/cpuinfo "hypervisor" flag or
+        // sys_vendor strings.
+        let in_vm = fs::read_to_string("/proc/cpuinfo")
+            .map(|c| c.contains("hypervisor"))
+            .unwrap_or(false)
+            || fs::read_to_string("/sys/class/dmi/id/sys_vendor")
+                .map(|v| {
+                    let v = v.trim().to_lowercase();
+                    v.contains("qemu") || v.contains("vmware") || v.contains("virtualbox")
+                        || v.contains("apple") || v.contains("parallels") || v.contains("xen")
+                        || v.contains("microsoft") || v.contains("innotek")
+                })
+                .unwrap_or(false);
+        let nvidia_driver_version = fs::read_to_string("/proc/driver/nvidia/version").ok();
+        LinuxGraphicsProbe {
+            in_vm,
+            // /proc/driver/nvidia is created by the proprietary nvidia.ko.
+            has_nvidia: Path::new("/proc/driver/nvidia").exists(),
+            gpu_vendor: detect_gpu_vendor(nvidia_driver_version.as_deref(), &drm_vendor_ids()),
+            appimage_gio_module_dir: resolve_appimage_gio_module_dir(),
+            gst_onnx_plugin_broken: detect_gst_onnx_plugin_broken(),
+        }
+    }
+}
+
+fn env_path_dirs(name: &str) -> Vec<PathBuf> {
+    env::var_os(name)
+        .map(|value| env::split_paths(&value).filter(|p| !p.as_os_str().is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// The libonnxruntime soname recorded in a plugin's dynamic string table.
+/// Scanning the raw bytes avoids an ELF parser; the `DT_NEEDED` string is
+/// NUL-terminated like every other entry.
+fn needed_onnxruntime_soname(plugin: &[u8]) -> Option<String> {
+    let start = plugin
+        .windows(ONNXRUNTIME_SONAME_PREFIX.len())
+        .position(|window| window == ONNXRUNTIME_SONAME_PREFIX)?;
+    let len = plugin[start..].iter().position(|&b| b == 0)?;
+    String::from_utf8(plugin[start..start + len].to_vec()).ok()
+}
+
+fn detect_gst_onnx_plugin_broken() -> bool {
+    let mut plugin_dirs = env_path_dirs("GST_PLUGIN_PATH_1_0");
+    plugin_dirs.extend(env_path_dirs("GST_PLUGIN_PATH"));
+    match env::var_os("GST_PLUGIN_SYSTEM_PATH_1_0") {
+        Some(value) => plugin_dirs.extend(env::split_paths(&value).filter(|p| !p.as_os_str().is_empty())),
+        None => plugin_dirs.extend(SYSTEM_GST_PLUGIN_DIRS.iter().map(PathBuf::from)),
+    }
+    let Some(plugin) = plugin_dirs
+        .iter()
+        .map(|dir| dir.join(GST_ONNX_PLUGIN_FILE))
+        .find(|path| path.is_file())
+    else {
+        return false;
+    };
+    let Some(soname) = fs::read(&plugin).ok().and_then(|bytes| needed_onnxruntime_soname(&bytes)) else {
+        return false;
+    };
+    let mut lib_dirs = env_path_dirs("LD_LIBRARY_PATH");
+    lib_dirs.extend(SYSTEM_LIB_DIRS.iter().map(PathBuf::from));
+    !lib_dirs.iter().any(|dir| dir.join(&soname).exists())
+}
+
+/// Append `plugin` to a comma-separated `GST_PLUGIN_BLACKLIST` value.
+/// `None` when the plugin is already listed.
+pub(crate) fn merge_plugin_blacklist(existing: Option<&str>, plugin: &str) -> Option<String> {
+    let existing = existing.unwrap_or("");
+    if existing.split(',').any(|entry| entry.trim() == plugin) {
+        return None;
+    }
+    let base = existing.trim_end_matches(|c: char| c == ',' || c.is_whitespace());
+    if base.is_empty() {
+        Some(plugin.to_string())
+    } else {
+        Some(format!("{base},{plugin}"))
+    }
+}
+
+/// Where a decision came from. Precedence is env > override file > runtime
+/// pref > auto.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PolicySource {
+    Env,
+    OverrideFile,
+    RuntimePref,
+    Auto,
+}
+
+impl PolicySource {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            PolicySource::Env => "env",
+            PolicySource::OverrideFile => "override_file",
+            PolicySource::RuntimePref => "runtime_pref",
+            PolicySource::Auto => "auto",
+        }
+    }
+}
+
+/// One variable the policy will set, with the reason it is needed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct EnvAssignment {
+    pub(crate) name: String,
+    pub(crate) value: String,
+    pub(crate) reason: &'static str,
+    pub(crate) source: PolicySource,
+}
+
+/// How one variable was settled, including ones left alone because the user
+/// already exported them or the override file said not to set them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct PolicyDecision {
+    pub(crate) name: String,
+    /// The value in effect from this decision; `None` means left unset.
+    pub(crate) value: Option<String>,
+    pub(crate) source: PolicySource,
+    pub(crate) reason: &'static str,
+}
+
+/// Response of `get_webkit_policy_sources`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct WebkitPolicySources {
+    pub(crate) override_file: Option<String>,
+    pub(crate) override_error: Option<String>,
+    pub(crate) decisions: Vec<PolicyDecision>,
+}
+
+impl From<&LinuxWebkitEnvPolicy> for WebkitPolicySources {
+    fn from(policy: &LinuxWebkitEnvPolicy) -> Self {
+        WebkitPolicySources {
+            override_file: policy.overrides_file.clone(),
+            override_error: policy.overrides_error.clone(),
+            decisions: policy.decisions.clone(),
+        }
+    }
+}
+
+/// Contents of `webkit-policy.json`. Every field is optional; absent fields
+/// leave the automatic decision in place.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct WebkitPolicyOverrides {
+    pub(crate) force_dmabuf_disable: Option<bool>,
+    pub(crate) disable_sandbox: Option<bool>,
+    pub(crate) extra_env: BTreeMap<String, String>,
+    /// GStreamer plugin names for `GST_PLUGIN_BLACKLIST`; `[]` sets none.
+    pub(crate) gst_plugin_blacklist: Option<Vec<String>>,
+    /// `false` never blacklists the ONNX plugin, `true` always does; unset
+    /// blacklists it only when the plugin looks broken.
+    pub(crate) gst_onnx_blacklist: Option<bool>,
+}
+
+/// The override file as found at launch. A malformed file yields default
+/// overrides plus `error`, which is logged as a WARN once logging is up.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PolicyOverrideFile {
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) overrides: WebkitPolicyOverrides,
+    pub(crate) error: Option<String>,
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(crate) fn parse_policy_overrides(json: &str) -> Result<WebkitPolicyOverrides, String> {
+    let overrides: WebkitPolicyOverrides =
+        serde_json::from_str(json).map_err(|e| format!("invalid JSON: {e}"))?;
+    for (name, value) in &overrides.extra_env {
+        if !is_env_name(name) {
+            return Err(format!("extra_env: invalid variable name {name:?}"));
+        }
+        if value.contains('\0') {
+            return Err(format!("extra_env: value for {name} contains a NUL byte"));
+        }
+    }
+    for plugin in overrides.gst_plugin_blacklist.iter().flatten() {
+        let valid = !plugin.is_empty()
+            && plugin.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("gst_plugin_blacklist: invalid plugin name {plugin:?}"));
+        }
+    }
+    Ok(overrides)
+}
+
+/// Read `webkit-policy.json` from the app data dir (resolved without an app
+/// handle, since this runs before Tauri starts, but honoring the profile and
+/// data dir override). Missing is not an error.
+pub(crate) fn load_policy_overrides(identifier: &str) -> PolicyOverrideFile {
+    let path = data_dir::resolve_data_dir_before_app(identifier).map(|dir| dir.join(OVERRIDES_FILE));
+    let contents = path.as_deref().and_then(|p| fs::read_to_string(p).ok());
+    let (overrides, error) = match contents.as_deref().map(parse_policy_overrides) {
+        None => (WebkitPolicyOverrides::default(), None),
+        Some(Ok(overrides)) => (overrides, None),
+        Some(Err(err)) => (WebkitPolicyOverrides::default(), Some(err)),
+    };
+    PolicyOverrideFile {
+        path,
+        overrides,
+        error,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LinuxWebkitEnvPolicy {
+    /// Why the conservative env was forced: `"env"` ([`SAFE_MODE_ENV`]),
+    /// `"cli"` (`--safe-mode`) or `"crash_loop"`. `None` when not in safe mode.
+    pub(crate) safe_mode: Option<&'static str>,
+    pub(crate) wayland: bool,
+    pub(crate) appimage: bool,
+    pub(crate) in_vm: bool,
+    pub(crate) has_nvidia: bool,
+    pub(crate) gpu_vendor: GpuVendor,
+    /// Why `WEBKIT_DISABLE_DMABUF_RENDERER` was (or wasn't) set: one of
+    /// `existing_env`, `override_file`, `disabled_by_env`, `safe_mode`,
+    /// `wayland`, `x11_nvidia`, `default`.
+    pub(crate) decision_reason: &'static str,
+    /// What happened to the ONNX entry in `GST_PLUGIN_BLACKLIST`: one of
+    /// `not_needed`, `skipped_by_override`, `already_listed`,
+    /// `plugin_broken`, `forced_by_override`.
+    pub(crate) gst_onnx_blacklist: &'static str,
+    /// `XDG_CURRENT_DESKTOP`, e.g. `GNOME` or `KDE`.
+    pub(crate) desktop_environment: Option<String>,
+    /// Best guess at the compositor / window manager from session env vars.
+    pub(crate) compositor: Option<String>,
+    /// Variables to set; ones the user already configured are left out.
+    pub(crate) assignments: Vec<EnvAssignment>,
+    /// Every variable the policy considered and which source settled it.
+    pub(crate) decisions: Vec<PolicyDecision>,
+    pub(crate) overrides_file: Option<String>,
+    /// Why the override file was ignored, if it was malformed.
+    pub(crate) overrides_error: Option<String>,
+    /// The `forceScaleFactor` pref as applied through `GDK_SCALE` and
+    /// `GDK_DPI_SCALE`; `None` for `"auto"` or when the user's own env won.
+    pub(crate) force_scale_factor: Option<f64>,
+    /// Human-readable notes printed to stderr when the policy is applied.
+    pub(crate) notices: Vec<String>,
+    /// Values of [`REPORTED_ENV_VARS`] once applied (`None` = unset).
+    /// Empty until [`apply_linux_webkit_env_policy`] runs.
+    pub(crate) effective_env: BTreeMap<&'static str, Option<String>>,
+}
+
+impl LinuxWebkitEnvPolicy {
+    /// Settle `name` unless something with higher precedence already did.
+    /// Callers run in precedence order (override file first, then automatic
+    /// rules); an exported env var always wins. Returns whether the value
+    /// will be set by this decision.
+    fn decide<F>(&mut self, lookup: &F, name: &str, value: Option<&str>, reason: &'static str, source: PolicySource) -> bool
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        if self.decisions.iter().any(|d| d.name == name) {
+            return false;
+        }
+        if let Some(existing) = lookup(name) {
+            self.decisions.push(PolicyDecision {
+                name: name.to_string(),
+                value: Some(existing.to_string_lossy().into_owned()),
+                source: PolicySource::Env,
+                reason: "existing_env",
+            });
+            return false;
+        }
+        self.decisions.push(PolicyDecision {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+            source,
+            reason,
+        });
+        let Some(value) = value else {
+            return false;
+        };
+        self.assignments.push(EnvAssignment {
+            name: name.to_string(),
+            value: value.to_string(),
+            reason,
+            source,
+        });
+        true
+    }
+
+    fn set_default<F>(&mut self, lookup: &F, name: &str, value: &str, reason: &'static str) -> bool
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        self.decide(lookup, name, Some(value), reason, PolicySource::Auto)
+    }
+
+    fn decision_source(&self, name: &str) -> Option<PolicySource> {
+        self.decisions.iter().find(|d| d.name == name).map(|d| d.source)
+    }
+
+    fn apply_overrides<F>(&mut self, lookup: &F, overrides: &WebkitPolicyOverrides)
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let source = PolicySource::OverrideFile;
+        for (name, value) in &overrides.extra_env {
+            self.decide(lookup, name, Some(value), "override_extra_env", source);
+        }
+        if let Some(force) = overrides.force_dmabuf_disable {
+            self.decide(lookup, "WEBKIT_DISABLE_DMABUF_RENDERER", force.then_some("1"), "override_file", source);
+        }
+        if let Some(disable) = overrides.disable_sandbox {
+            let name = "WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS";
+            self.decide(lookup, name, disable.then_some("1"), "override_file", source);
+        }
+        if let Some(plugins) = &overrides.gst_plugin_blacklist {
+            let joined = plugins.join(",");
+            let value = (!joined.is_empty()).then_some(joined.as_str());
+            self.decide(lookup, "GST_PLUGIN_BLACKLIST", value, "override_file", source);
+        }
+    }
+
+    /// Append `plugin` to `GST_PLUGIN_BLACKLIST` on top of whatever is in
+    /// effect so far (exported, or set by the override file) instead of
+    /// replacing it. Returns `false` when it was already listed.
+    fn append_gst_blacklist<F>(&mut self, lookup: &F, plugin: &str, reason: &'static str) -> bool
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        const NAME: &str = "GST_PLUGIN_BLACKLIST";
+        let current = match self.decisions.iter().find(|d| d.name == NAME) {
+            Some(decision) => decision.value.clone(),
+            None => lookup(NAME).map(|v| v.to_string_lossy().into_owned()),
+        };
+        let Some(merged) = merge_plugin_blacklist(current.as_deref(), plugin) else {
+            return false;
+        };
+        self.decisions.retain(|d| d.name != NAME);
+        self.assignments.retain(|a| a.name != NAME);
+        self.decisions.push(PolicyDecision {
+            name: NAME.to_string(),
+            value: Some(merged.clone()),
+            source: PolicySource::Auto,
+            reason,
+        });
+        self.assignments.push(EnvAssignment {
+            name: NAME.to_string(),
+            value: merged,
+            reason,
+            source: PolicySource::Auto,
+        });
+        true
+    }
+
+    fn is_set<F>(&self, lookup: &F, name: &str) -> bool
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        lookup(name).is_some() || self.assignments.iter().any(|a| a.name == name)
+    }
+
+    /// Add the `forceScaleFactor` pref's variables. Runs after
+    /// [`compute_linux_webkit_policy`], so exported variables and the
+    /// override file's `extra_env` keep precedence; the factor only counts
+    /// as applied when `GDK_SCALE` is ours to set.
+    pub(crate) fn apply_scale_factor<F>(&mut self, lookup: F, factor: Option<f64>)
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let Some(factor) = factor else {
+            return;
+        };
+        let env = display_scale::scale_env(factor);
+        if self.is_set(&lookup, "GDK_SCALE") || self.is_set(&lookup, "GDK_DPI_SCALE") {
+            for (name, _) in &env {
+                self.decide(&lookup, name, None, "force_scale_factor", PolicySource::RuntimePref);
+            }
+            self.notices.push(format!(
+                "forceScaleFactor={factor} ignored; GDK_SCALE or GDK_DPI_SCALE is already set"
+            ));
+            return;
+        }
+        for (name, value) in &env {
+            self.decide(&lookup, name, Some(value), "force_scale_factor", PolicySource::RuntimePref);
+        }
+        self.force_scale_factor = Some(factor);
+    }
+}
+
+/// `Some(true)` / `Some(false)` for recognised on/off values, `None` otherwise.
+fn parse_switch(value: Option<OsString>) -> Option<bool> {
+    let value = value?;
+    let value = value.to_string_lossy();
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Identify the compositor from the sockets/markers each one exports, falling
+/// back to the desktop environment's default.
+fn detect_compositor<F>(lookup: &F, wayland: bool) -> Option<String>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    const MARKERS: [(&str, &str); 5] = [
+        ("HYPRLAND_INSTANCE_SIGNATURE", "hyprland"),
+        ("SWAYSOCK", "sway"),
+        ("NIRI_SOCKET", "niri"),
+        ("WAYFIRE_SOCKET", "wayfire"),
+        ("I3SOCK", "i3"),
+    ];
+    if let Some((_, name)) = MARKERS.iter().find(|(var, _)| lookup(var).is_some()) {
+        return Some((*name).to_string());
+    }
+    let desktop = lookup("XDG_CURRENT_DESKTOP")?.to_string_lossy().to_ascii_lowercase();
+    let name = if desktop.contains("gnome") || desktop.contains("unity") {
+        "mutter"
+    } else if desktop.contains("kde") {
+        "kwin"
+    } else if desktop.contains("xfce") {
+        "xfwm4"
+    } else if desktop.contains("cinnamon") {
+        "muffin"
+    } else if desktop.contains("cosmic") {
+        "cosmic-comp"
+    } else {
+        return wayland.then(|| "unknown-wayland".to_string());
+    };
+    Some(name.to_string())
+}
+
+/// Decide which WebKitGTK workarounds this launch needs. `lookup` reads the
+/// current environment (injectable for tests); `overrides` is the parsed
+/// `webkit-policy.json`; `forced_safe_mode` (`"cli"` or `"crash_loop"`)
+/// forces safe mode when the env switch doesn't already.
+pub(crate) fn compute_linux_webkit_policy<F>(
+    lookup: F,
+    probe: &LinuxGraphicsProbe,
+    overrides: &PolicyOverrideFile,
+    forced_safe_mode: Option<&'static str>,
+) -> LinuxWebkitEnvPolicy
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let safe_mode_switch = parse_switch(lookup(SAFE_MODE_ENV));
+    let safe_mode = if safe_mode_switch == Some(true) {
+        Some("env")
+    } else {
+        forced_safe_mode
+    };
+    let wayland = lookup("WAYLAND_DISPLAY").is_some();
+    let x11 = !wayland && lookup("DISPLAY").is_some();
+    let appimage = lookup("APPIMAGE").is_some();
+    let mut policy = LinuxWebkitEnvPolicy {
+        safe_mode,
+        wayland,
+        appimage,
+        in_vm: probe.in_vm,
+        has_nvidia: probe.has_nvidia,
+        gpu_vendor: probe.gpu_vendor,
+        decision_reason: "default",
+        gst_onnx_blacklist: "not_needed",
+        desktop_environment: lookup("XDG_CURRENT_DESKTOP").map(|v| v.to_string_lossy().into_owned()),
+        compositor: detect_compositor(&lookup, wayland),
+        assignments: Vec::new(),
+        decisions: Vec::new(),
+        overrides_file: overrides.path.as_ref().map(|p| p.display().to_string()),
+        overrides_error: overrides.error.clone(),
+        force_scale_factor: None,
+        notices: Vec::new(),
+        effective_env: BTreeMap::new(),
+    };
+    policy.apply_overrides(&lookup, &overrides.overrides);
+
+    // Work around WebKitGTK rendering issues on Linux that can cause blank white
+    // screens. DMA-BUF renderer failures are common with NVIDIA drivers and on
+    // immutable distros (e.g. Bazzite/Fedora Atomic).  Setting the env var before
+    // WebKit initialises forces a software fallback path.  The safeguard applies
+    // to every session; the reason records which known-bad combination (if any)
+    // this one is — X11 on the proprietary NVIDIA driver breaks the same way
+    // Wayland does.  WM_LINUX_WEBKIT_SAFE_MODE=0 opts out unless a crash loop
+    // forced safe mode.
+    policy.decision_reason = if lookup("WEBKIT_DISABLE_DMABUF_RENDERER").is_some() {
+        "existing_env"
+    } else if policy.decision_source("WEBKIT_DISABLE_DMABUF_RENDERER") == Some(PolicySource::OverrideFile) {
+        "override_file"
+    } else if safe_mode.is_some() {
+        "safe_mode"
+    } else if safe_mode_switch == Some(false) {
+        "disabled_by_env"
+    } else if wayland {
+        "wayland"
+    } else if x11 && (probe.has_nvidia || probe.gpu_vendor == GpuVendor::Nvidia) {
+        "x11_nvidia"
+    } else {
+        "default"
+    };
+    if !matches!(policy.decision_reason, "existing_env" | "override_file" | "disabled_by_env") {
+        let reason = policy.decision_reason;
+        policy.set_default(&lookup, "WEBKIT_DISABLE_DMABUF_RENDERER", "1", reason);
+    }
+
+    // WebKitGTK promotes iframes, <video>, and canvas to GPU-textured
+    // compositing layers.  In VMs (Apple Virtualization.framework,
+    // QEMU/KVM, VMware, etc.) the virtio-gpu driver often only supports
+    // 2D or limited GL — GBM buffer allocation for compositing layers
+    // fails silently, rendering iframe/video content as black while the
+    // main page (software-tiled) works fine.  Disable accelerated
+    // compositing + force software GL so all content renders through the
+    // CPU path.  Safe mode takes the same path on any hardware.
+    if probe.in_vm || safe_mode.is_some() {
+        let reason = if probe.in_vm { "vm" } else { "safe_mode" };
+        policy.set_default(&lookup, "WEBKIT_DISABLE_COMPOSITING_MODE", "1", reason);
+        policy.set_default(&lookup, "LIBGL_ALWAYS_SOFTWARE", "1", reason);
+        if probe.in_vm {
+            policy.notices.push(
+                "VM detected; disabled WebKitGTK accelerated compositing for iframe/video compatibility".to_string(),
+            );
+        } else {
+            policy.notices.push(format!(
+                "Safe mode ({}); forcing software rendering for WebKitGTK",
+                safe_mode.unwrap_or_default()
+            ));
+        }
+    }
+
+    // NVIDIA proprietary drivers often fail to create a surfaceless EGL
+    // display (EGL_BAD_ALLOC) in WebKitGTK's web process, especially on
+    // Wayland where explicit sync can also cause flickering/crashes.
+    if probe.has_nvidia {
+        policy.set_default(&lookup, "__NV_DISABLE_EXPLICIT_SYNC", "1", "nvidia");
+        // Force X11 backend on NVIDIA + Wayland to avoid surfaceless EGL
+        // failures.  Users who prefer native Wayland can override with
+        // GDK_BACKEND=wayland.
+        if wayland && policy.set_default(&lookup, "GDK_BACKEND", "x11", "nvidia_wayland") {
+            policy.notices.push(
+                "NVIDIA GPU + Wayland detected; forcing GDK_BACKEND=x11 to avoid EGL_BAD_ALLOC. \
+                 Set GDK_BACKEND=wayland to override."
+                    .to_string(),
+            );
+        }
+    }
+
+    // On Wayland-only compositors (e.g. niri, river, sway without XWayland),
+    // GTK3 may fail to initialise if it defaults to X11 backend first and no
+    // DISPLAY is set.  Explicitly prefer the Wayland backend when a Wayland
+    // display is available.  Falls back to X11 if Wayland init fails.
+    if wayland {
+        policy.set_default(&lookup, "GDK_BACKEND", "wayland,x11", "wayland");
+    }
+
+    // Work around GLib version mismatch when running as an AppImage on newer
+    // distros.  The AppImage bundles GLib from the CI build system (Ubuntu
+    // 24.04, GLib 2.80).  Host GIO modules (e.g. GVFS's libgvfsdbus.so) may
+    // link against newer GLib symbols absent in the bundled copy, producing:
+    //   "undefined symbol: g_task_set_static_name"
+    // Point GIO_MODULE_DIR at the AppImage's bundled modules to isolate from
+    // host libraries.
+    if appimage && !policy.is_set(&lookup, "GIO_MODULE_DIR") {
+        if let Some(module_dir) = &probe.appimage_gio_module_dir {
+            policy.set_default(&lookup, "GIO_MODULE_DIR", &module_dir.to_string_lossy(), "appimage_gio");
+        } else if policy.set_default(&lookup, "GIO_USE_VFS", "local", "appimage_gio_fallback") {
+            // Last-resort fallback: prefer local VFS backend if module path
+            // discovery fails, which reduces GVFS dependency surface.
+            policy.notices.push(
+                "APPIMAGE detected but bundled gio/modules not found; using GIO_USE_VFS=local fallback".to_string(),
+            );
+        }
+    }
+
+    // WebKit2GTK's bubblewrap sandbox can fail inside an AppImage FUSE
+    // mount, causing blank white screens. Disable it when running as
+    // AppImage — the AppImage itself already provides isolation.
+    if appimage {
+        // WebKitGTK 2.39.3+ deprecated WEBKIT_FORCE_SANDBOX and now expects
+        // WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS=1 instead.  Setting the
+        // old variable on newer WebKitGTK triggers a noisy deprecation
+        // warning in the system journal, so only set the new one.
+        policy.set_default(&lookup, "WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS", "1", "appimage_sandbox");
+        // Prevent GTK from loading host input-method modules that may
+        // link against incompatible library versions.
+        policy.set_default(&lookup, "GTK_IM_MODULE", "gtk-im-context-simple", "appimage_im");
+
+        // The linuxdeploy GStreamer hook sets GST_PLUGIN_PATH_1_0 and
+        // GST_PLUGIN_SYSTEM_PATH_1_0 to only contain bundled plugins.
+        // CI installs the full GStreamer codec suite (base, good, bad,
+        // ugly, libav, gl) so bundleMediaFramework=true bundles everything.
+        //
+        // IMPORTANT: Do NOT append host plugin directories — mixing plugins
+        // compiled against a different GStreamer version causes ABI mismatches
+        // (undefined symbol errors like gst_util_floor_log2, mpg123_open_handle64)
+        // and leaves WebKit without usable codecs.  The AppImage must be fully
+        // self-contained for GStreamer.
+        //
+        // If the linuxdeploy hook didn't set the paths (shouldn't happen),
+        // explicitly block host plugin scanning to prevent ABI conflicts.
+        // An empty string prevents GStreamer from scanning /usr/lib/gstreamer-1.0.
+        policy.set_default(&lookup, "GST_PLUGIN_SYSTEM_PATH_1_0", "", "appimage_gstreamer");
+    }
+
+    policy.gst_onnx_blacklist = match overrides.overrides.gst_onnx_blacklist {
+        Some(false) => "skipped_by_override",
+        Some(true) => "forced_by_override",
+        None if probe.gst_onnx_plugin_broken => "plugin_broken",
+        None => "not_needed",
+    };
+    if matches!(policy.gst_onnx_blacklist, "forced_by_override" | "plugin_broken") {
+        let reason = policy.gst_onnx_blacklist;
+        if !policy.append_gst_blacklist(&lookup, GST_ONNX_PLUGIN, reason) {
+            policy.gst_onnx_blacklist = "already_listed";
+        }
+    }
+
+    policy
+}
+
+/// Set the policy's variables, print its notices, and record the resulting
+/// renderer environment in `effective_env`.
+///
+/// Must run before any threads are spawned (i.e. before Tauri starts).
+pub(crate) fn apply_linux_webkit_env_policy(policy: &mut LinuxWebkitEnvPolicy) {
+    for assignment in &policy.assignments {
+        // SAFETY: called from `main` before any threads are spawned.
+        unsafe { env::set_var(&assignment.name, &assignment.value) };
+    }
+    for notice in &policy.notices {
+        eprintln!("[tauri] {notice}");
+    }
+    policy.effective_env = REPORTED_ENV_VARS
+        .iter()
+        .map(|name| (*name, env::var_os(name).map(|v| v.to_string_lossy().into_owned())))
+        .collect();
+}
+
+/// Single-line summary for stderr and desktop.log.
+pub(crate) fn format_linux_webkit_policy(policy: &LinuxWebkitEnvPolicy) -> String {
+    let assignments: Vec<String> = policy
+        .assignments
+        .iter()
+        .map(|a| format!("{}={:?} ({}/{})", a.name, a.value, a.source.as_str(), a.reason))
+        .collect();
+    let overrides = match (&policy.overrides_file, &policy.overrides_error) {
+        (_, Some(_)) => "invalid",
+        (Some(path), None) if Path::new(path).exists() => "loaded",
+        _ => "none",
+    };
+    format!(
+        "safe_mode={} wayland={} appimage={} vm={} nvidia={} gpu={} dmabuf={} gst_onnx={} overrides={overrides} desktop={} compositor={} scale={} set=[{}]",
+        policy.safe_mode.unwrap_or("off"),
+        policy.wayland,
+        policy.appimage,
+        policy.in_vm,
+        policy.has_nvidia,
+        policy.gpu_vendor.as_str(),
+        policy.decision_reason,
+        policy.gst_onnx_blacklist,
+        policy.desktop_environment.as_deref().unwrap_or("unknown"),
+        policy.compositor.as_deref().unwrap_or("unknown"),
+        policy.force_scale_factor.map_or_else(|| "auto".to_string(), |factor| factor.to_string()),
+        assignments.join(", ")
+    )
+}
+
+fn resolve_appimage_gio_module_dir() -> Option<PathBuf> {
+    let appdir = env::var_os("APPDIR")?;
+    let appdir = PathBuf::from(appdir);
+
+    // Common layouts produced by AppImage/linuxdeploy on Debian and RPM families.
+    let preferred = [
+        "usr/lib/gio/modules",
+        "usr/lib64/gio/modules",
+        "usr/lib/x86_64-linux-gnu/gio/modules",
+        "usr/lib/aarch64-linux-gnu/gio/modules",
+        "usr/lib/arm-linux-gnueabihf/gio/modules",
+        "lib/gio/modules",
+        "lib64/gio/modules",
+    ];
+
+    for relative in preferred {
+        let candidate = appdir.join(relative);
+        if candidate.is_dir() {
+            return Some(candidate);
+        }
+    }
+
+    // Fallback: probe one level of arch-specific directories, e.g. usr/lib/<triplet>/gio/modules.
+    for lib_root in ["usr/lib", "usr/lib64", "lib", "lib64"] {
+        let root = appdir.join(lib_root);
+        if !root.is_dir() {
+            continue;
+        }
+        let entries = match fs::read_dir(&root) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let candidate = entry.path().join("gio/modules");
+            if candidate.is_dir() {
+                return Some(candidate);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compute_linux_webkit_policy, detect_gpu_vendor, format_linux_webkit_policy, merge_plugin_blacklist,
+        needed_onnxruntime_soname, parse_policy_overrides, GpuVendor, LinuxGraphicsProbe, LinuxWebkitEnvPolicy,
+        PolicyOverrideFile, PolicySource,
+    };
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+
+    fn policy(env: &[(&str, &str)], probe: &LinuxGraphicsProbe, crash_loop: bool) -> LinuxWebkitEnvPolicy {
+        policy_with_overrides(env, probe, "{}", crash_loop)
+    }
+
+    fn policy_with_overrides(
+        env: &[(&str, &str)],
+        probe: &LinuxGraphicsProbe,
+        overrides: &str,
+        crash_loop: bool,
+    ) -> LinuxWebkitEnvPolicy {
+        let env: HashMap<String, OsString> = env.iter().map(|(k, v)| (k.to_string(), OsString::from(v))).collect();
+        let overrides = PolicyOverrideFile {
+            overrides: parse_policy_overrides(overrides).unwrap(),
+            ..Default::default()
+        };
+        compute_linux_webkit_policy(
+            |name| env.get(name).cloned(),
+            probe,
+            &overrides,
+            crash_loop.then_some("crash_loop"),
+        )
+    }
+
+    fn assigned<'a>(policy: &'a LinuxWebkitEnvPolicy, name: &str) -> Option<&'a str> {
+        policy.assignments.iter().find(|a| a.name == name).map(|a| a.value.as_str())
+    }
+
+    #[test]
+    fn existing_env_values_win() {
+        let p = policy(
+            &[("WEBKIT_DISABLE_DMABUF_RENDERER", "0"), ("WAYLAND_DISPLAY", "wayland-0"), ("GDK_BACKEND", "x11")],
+            &LinuxGraphicsProbe::default(),
+            false,
+        );
+        assert_eq!(assigned(&p, "WEBKIT_DISABLE_DMABUF_RENDERER"), None);
+        assert_eq!(assigned(&p, "GDK_BACKEND"), None);
+    }
+
+    #[test]
+    fn nvidia_on_wayland_prefers_x11_over_wayland_default() {
+        let probe = LinuxGraphicsProbe { has_nvidia: true, ..Default::default() };
+        let p = policy(&[("WAYLAND_DISPLAY", "wayland-0")], &probe, false);
+        assert_eq!(assigned(&p, "GDK_BACKEND"), Some("x11"));
+        assert_eq!(assigned(&p, "__NV_DISABLE_EXPLICIT_SYNC"), Some("1"));
+    }
+
+    #[test]
+    fn safe_mode_forces_software_rendering() {
+        let bare = policy(&[], &LinuxGraphicsProbe::default(), false);
+        assert_eq!(bare.safe_mode, None);
+        assert_eq!(assigned(&bare, "LIBGL_ALWAYS_SOFTWARE"), None);
+
+        let crash_loop = policy(&[], &LinuxGraphicsProbe::default(), true);
+        assert_eq!(crash_loop.safe_mode, Some("crash_loop"));
+        assert_eq!(assigned(&crash_loop, "LIBGL_ALWAYS_SOFTWARE"), Some("1"));
+        assert_eq!(assigned(&crash_loop, "WEBKIT_DISABLE_COMPOSITING_MODE"), Some("1"));
+
+        let env = policy(&[(super::SAFE_MODE_ENV, "1")], &LinuxGraphicsProbe::default(), false);
+        assert_eq!(env.safe_mode, Some("env"));
+    }
+
+    #[test]
+    fn reports_desktop_environment_and_compositor() {
+        let sway = policy(
+            &[("WAYLAND_DISPLAY", "wayland-1"), ("SWAYSOCK", "/run/user/1000/sway.sock"), ("XDG_CURRENT_DESKTOP", "sway")],
+            &LinuxGraphicsProbe::default(),
+            false,
+        );
+        assert_eq!(sway.desktop_environment.as_deref(), Some("sway"));
+        assert_eq!(sway.compositor.as_deref(), Some("sway"));
+
+        let kde = policy(&[("XDG_CURRENT_DESKTOP", "KDE")], &LinuxGraphicsProbe::default(), false);
+        assert_eq!(kde.compositor.as_deref(), Some("kwin"));
+
+        let bare_x11 = policy(&[], &LinuxGraphicsProbe::default(), false);
+        assert_eq!(bare_x11.desktop_environment, None);
+        assert_eq!(bare_x11.compositor, None);
+    }
+
+    fn gpu(vendor: GpuVendor) -> LinuxGraphicsProbe {
+        LinuxGraphicsProbe {
+            has_nvidia: vendor == GpuVendor::Nvidia,
+            gpu_vendor: vendor,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn force_scale_factor_sets_gdk_scale_unless_already_configured() {
+        let wayland = [("WAYLAND_DISPLAY", "wayland-0")];
+        let lookup = |env: &[(&str, &str)]| {
+            let env: HashMap<String, OsString> = env.iter().map(|(k, v)| (k.to_string(), OsString::from(v))).collect();
+            move |name: &str| env.get(name).cloned()
+        };
+
+        let mut p = policy(&wayland, &LinuxGraphicsProbe::default(), false);
+        p.apply_scale_factor(lookup(&wayland), Some(1.5));
+        assert_eq!(assigned(&p, "GDK_SCALE"), Some("1"));
+        assert_eq!(assigned(&p, "GDK_DPI_SCALE"), Some("1.5"));
+        assert_eq!(p.force_scale_factor, Some(1.5));
+        let scale = p.assignments.iter().find(|a| a.name == "GDK_SCALE").unwrap();
+        assert_eq!((scale.source, scale.reason), (PolicySource::RuntimePref, "force_scale_factor"));
+        assert!(format_linux_webkit_policy(&p).contains("scale=1.5 "));
+
+        let mut p = policy(&wayland, &LinuxGraphicsProbe::default(), false);
+        p.apply_scale_factor(lookup(&wayland), Some(2.0));
+        assert_eq!(assigned(&p, "GDK_SCALE"), Some("2"));
+        assert_eq!(assigned(&p, "GDK_DPI_SCALE"), None);
+
+        // "auto" changes nothing.
+        let mut p = policy(&wayland, &LinuxGraphicsProbe::default(), false);
+        p.apply_scale_factor(lookup(&wayland), None);
+        assert_eq!(assigned(&p, "GDK_SCALE"), None);
+        assert!(format_linux_webkit_policy(&p).contains("scale=auto "));
+
+        // A hand-exported GDK_SCALE or an override file entry wins.
+        let exported = [("WAYLAND_DISPLAY", "wayland-0"), ("GDK_SCALE", "2")];
+        let mut p = policy(&exported, &LinuxGraphicsProbe::default(), false);
+        p.apply_scale_factor(lookup(&exported), Some(1.5));
+        assert_eq!((assigned(&p, "GDK_SCALE"), assigned(&p, "GDK_DPI_SCALE")), (None, None));
+        assert_eq!(p.force_scale_factor, None);
+        assert!(p.notices.iter().any(|n| n.contains("forceScaleFactor=1.5 ignored")));
+
+        let mut p = policy_with_overrides(
+            &wayland,
+            &LinuxGraphicsProbe::default(),
+            r#"{"extra_env": {"GDK_DPI_SCALE": "1.25"}}"#,
+            false,
+        );
+        p.apply_scale_factor(lookup(&wayland), Some(1.5));
+        assert_eq!(assigned(&p, "GDK_DPI_SCALE"), Some("1.25"));
+        assert_eq!(assigned(&p, "GDK_SCALE"), None);
+    }
+
+    #[test]
+    fn detects_gpu_vendor_from_driver_and_drm() {
+        let ids = |v: &[&str]| v.iter().map(|s| format!("{s}\n")).collect::<Vec<_>>();
+        assert_eq!(detect_gpu_vendor(Some("NVRM version: 550.54"), &[]), GpuVendor::Nvidia);
+        assert_eq!(detect_gpu_vendor(None, &ids(&["0x8086", "0x10de"])), GpuVendor::Nvidia);
+        assert_eq!(detect_gpu_vendor(None, &ids(&["0x8086", "0x1002"])), GpuVendor::Amd);
+        assert_eq!(detect_gpu_vendor(None, &ids(&["0x8086"])), GpuVendor::Intel);
+        assert_eq!(detect_gpu_vendor(None, &ids(&["0x1af4"])), GpuVendor::Other);
+        assert_eq!(detect_gpu_vendor(None, &[]), GpuVendor::Unknown);
+    }
+
+    #[test]
+    fn nvidia_on_x11_gets_distinct_dmabuf_reason() {
+        let p = policy(&[("DISPLAY", ":0")], &gpu(GpuVendor::Nvidia), false);
+        assert_eq!(p.decision_reason, "x11_nvidia");
+        assert_eq!(assigned(&p, "WEBKIT_DISABLE_DMABUF_RENDERER"), Some("1"));
+        assert!(format_linux_webkit_policy(&p).contains("gpu=nvidia dmabuf=x11_nvidia"));
+    }
+
+    #[test]
+    fn nvidia_on_wayland_keeps_wayland_reason() {
+        let p = policy(&[("WAYLAND_DISPLAY", "wayland-0"), ("DISPLAY", ":0")], &gpu(GpuVendor::Nvidia), false);
+        assert_eq!(p.decision_reason, "wayland");
+        assert_eq!(assigned(&p, "WEBKIT_DISABLE_DMABUF_RENDERER"), Some("1"));
+    }
+
+    #[test]
+    fn amd_on_x11_uses_default_reason() {
+        let p = policy(&[("DISPLAY", ":0")], &gpu(GpuVendor::Amd), false);
+        assert_eq!(p.decision_reason, "default");
+        assert_eq!(p.gpu_vendor, GpuVendor::Amd);
+        assert_eq!(assigned(&p, "__NV_DISABLE_EXPLICIT_SYNC"), None);
+    }
+
+    #[test]
+    fn dmabuf_respects_existing_env_and_safe_mode_switch() {
+        let existing = policy(&[("DISPLAY", ":0"), ("WEBKIT_DISABLE_DMABUF_RENDERER", "0")], &gpu(GpuVendor::Nvidia), false);
+        assert_eq!(existing.decision_reason, "existing_env");
+        assert_eq!(assigned(&existing, "WEBKIT_DISABLE_DMABUF_RENDERER"), None);
+
+        let opted_out = policy(&[("DISPLAY", ":0"), (super::SAFE_MODE_ENV, "0")], &gpu(GpuVendor::Nvidia), false);
+        assert_eq!(opted_out.decision_reason, "disabled_by_env");
+        assert_eq!(assigned(&opted_out, "WEBKIT_DISABLE_DMABUF_RENDERER"), None);
+
+        let crash_loop = policy(&[(super::SAFE_MODE_ENV, "0")], &gpu(GpuVendor::Amd), true);
+        assert_eq!(crash_loop.decision_reason, "safe_mode");
+        assert_eq!(assigned(&crash_loop, "WEBKIT_DISABLE_DMABUF_RENDERER"), Some("1"));
+    }
+
+    #[test]
+    fn parses_and_validates_override_file() {
+        let parsed = parse_policy_overrides(
+            r#"{"force_dmabuf_disable": false, "extra_env": {"WEBKIT_FOO": "1"}, "gst_plugin_blacklist": ["onnx", "vaapi"]}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.force_dmabuf_disable, Some(false));
+        assert_eq!(parsed.disable_sandbox, None);
+        assert_eq!(parsed.extra_env.get("WEBKIT_FOO").map(String::as_str), Some("1"));
+
+        assert!(parse_policy_overrides("{not json").is_err());
+        assert!(parse_policy_overrides(r#"{"force_dmabuf": true}"#).is_err());
+        assert!(parse_policy_overrides(r#"{"extra_env": {"BAD=NAME": "1"}}"#).is_err());
+        assert!(parse_policy_overrides(r#"{"gst_plugin_blacklist": ["a,b"]}"#).is_err());
+    }
+
+    #[test]
+    fn env_beats_override_file_beats_auto() {
+        let overrides = r#"{"force_dmabuf_disable": false, "disable_sandbox": true, "extra_env": {"GDK_BACKEND": "x11"}, "gst_plugin_blacklist": ["onnx"]}"#;
+        let p = policy_with_overrides(
+            &[("WAYLAND_DISPLAY", "wayland-0"), ("GST_PLUGIN_BLACKLIST", "vaapi")],
+            &LinuxGraphicsProbe::default(),
+            overrides,
+            false,
+        );
+        // Override file wins over the automatic Wayland decisions.
+        assert_eq!(p.decision_reason, "override_file");
+        assert_eq!(assigned(&p, "WEBKIT_DISABLE_DMABUF_RENDERER"), None);
+        assert_eq!(assigned(&p, "GDK_BACKEND"), Some("x11"));
+        assert_eq!(assigned(&p, "WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS"), Some("1"));
+        // Exported env wins over the override file.
+        assert_eq!(assigned(&p, "GST_PLUGIN_BLACKLIST"), None);
+        let blacklist = p.decisions.iter().find(|d| d.name == "GST_PLUGIN_BLACKLIST").unwrap();
+        assert_eq!(blacklist.source, PolicySource::Env);
+        assert_eq!(blacklist.value.as_deref(), Some("vaapi"));
+
+        let env_wins = policy_with_overrides(
+            &[("WEBKIT_DISABLE_DMABUF_RENDERER", "0")],
+            &LinuxGraphicsProbe::default(),
+            r#"{"force_dmabuf_disable": true}"#,
+            false,
+        );
+        assert_eq!(env_wins.decision_reason, "existing_env");
+        assert_eq!(assigned(&env_wins, "WEBKIT_DISABLE_DMABUF_RENDERER"), None);
+    }
+
+    #[test]
+    fn merges_onnx_into_existing_blacklist() {
+        assert_eq!(merge_plugin_blacklist(None, "onnx").as_deref(), Some("onnx"));
+        assert_eq!(merge_plugin_blacklist(Some(""), "onnx").as_deref(), Some("onnx"));
+        assert_eq!(merge_plugin_blacklist(Some("vaapi"), "onnx").as_deref(), Some("vaapi,onnx"));
+        assert_eq!(merge_plugin_blacklist(Some("vaapi,"), "onnx").as_deref(), Some("vaapi,onnx"));
+        assert_eq!(merge_plugin_blacklist(Some("vaapi, nvcodec ,\n"), "onnx").as_deref(), Some("vaapi, nvcodec,onnx"));
+        assert_eq!(merge_plugin_blacklist(Some("vaapi, onnx"), "onnx"), None);
+    }
+
+    #[test]
+    fn reads_onnxruntime_soname_from_plugin_bytes() {
+        let bytes = b"\x7fELF\0libgstreamer-1.0.so.0\0libonnxruntime.so.1.17\0libc.so.6\0";
+        assert_eq!(needed_onnxruntime_soname(bytes).as_deref(), Some("libonnxruntime.so.1.17"));
+        assert_eq!(needed_onnxruntime_soname(b"\x7fELF\0libc.so.6\0"), None);
+    }
+
+    #[test]
+    fn onnx_blacklist_is_conditional_and_overridable() {
+        let healthy = policy(&[("GST_PLUGIN_BLACKLIST", "vaapi")], &LinuxGraphicsProbe::default(), false);
+        assert_eq!(healthy.gst_onnx_blacklist, "not_needed");
+        assert_eq!(assigned(&healthy, "GST_PLUGIN_BLACKLIST"), None);
+
+        let broken = LinuxGraphicsProbe {
+            gst_onnx_plugin_broken: true,
+            ..Default::default()
+        };
+        let appended = policy(&[("GST_PLUGIN_BLACKLIST", "vaapi,")], &broken, false);
+        assert_eq!(appended.gst_onnx_blacklist, "plugin_broken");
+        assert_eq!(assigned(&appended, "GST_PLUGIN_BLACKLIST"), Some("vaapi,onnx"));
+
+        let listed = policy(&[("GST_PLUGIN_BLACKLIST", "onnx")], &broken, false);
+        assert_eq!(listed.gst_onnx_blacklist, "already_listed");
+        assert_eq!(assigned(&listed, "GST_PLUGIN_BLACKLIST"), None);
+
+        let skipped = policy_with_overrides(&[], &broken, r#"{"gst_onnx_blacklist": false}"#, false);
+        assert_eq!(skipped.gst_onnx_blacklist, "skipped_by_override");
+        assert_eq!(assigned(&skipped, "GST_PLUGIN_BLACKLIST"), None);
+
+        let forced = policy_with_overrides(
+            &[],
+            &LinuxGraphicsProbe::default(),
+            r#"{"gst_onnx_blacklist": true, "gst_plugin_blacklist": ["vaapi"]}"#,
+            false,
+        );
+        assert_eq!(forced.gst_onnx_blacklist, "forced_by_override");
+        assert_eq!(assigned(&forced, "GST_PLUGIN_BLACKLIST"), Some("vaapi,onnx"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Post-start checks on the sidecar's socket. The desktop app binds
+//! loopback only, and says so explicitly, so a firewall has nothing to ask
+//! about; if the process is up but its port never accepts a connection, the
+//! likely culprit is security software rather than the app, and the status
+//! says `port_blocked` instead of a generic failure. A sidecar that reports
+//! binding anything but loopback without `--listen` asking for it is stopped.
+
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+/// How long the port gets to accept a connection after the sidecar reports it.
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the sidecar wrote to its port file: the port, then the address it
+/// bound on a second line (older sidecars wrote only the port).
+pub(crate) fn parse_port_file(contents: &str) -> Option<(u16, Option<IpAddr>)> {
+    let mut lines = contents.lines().map(str::trim);
+    let port = lines.next()?.parse::<u16>().ok().filter(|port| *port > 0)?;
+    let host = lines.next().and_then(|line| line.parse::<IpAddr>().ok());
+    Some((port, host))
+}
+
+/// Whether a sidecar bound to `reported` may be used. Without `--listen`
+/// only loopback is acceptable; with it, the operator chose the interface.
+pub(crate) fn check_bind(reported: Option<IpAddr>, requested: Option<IpAddr>) -> Result<(), String> {
+    match (reported, requested) {
+        (Some(bound), None) if !bound.is_loopback() => Err(format!(
+            "The local API bound {bound} instead of loopback; it was stopped so it isn't reachable from other machines"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Try to connect to `addr` until `timeout` runs out.
+pub(crate) fn connectable(addr: SocketAddr, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return false;
+        }
+        if TcpStream::connect_timeout(&addr, left.min(Duration::from_secs(1))).is_ok() {
+            return true;
+        }
+        std::thread::sleep(RETRY_INTERVAL.min(deadline.saturating_duration_since(Instant::now())));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Reachability {
+    Reachable,
+    /// Running, but nothing can connect to its port.
+    PortBlocked,
+    /// Gone before it could be reached.
+    Exited,
+}
+
+pub(crate) fn classify(process_alive: bool, connectable: bool) -> Reachability {
+    match (process_alive, connectable) {
+        (_, true) => Reachability::Reachable,
+        (true, false) => Reachability::PortBlocked,
+        (false, false) => Reachability::Exited,
+    }
+}
+
+/// Log line for a blocked port: what to check, since the app can't tell.
+pub(crate) fn blocked_guidance(addr: SocketAddr) -> String {
+    format!(
+        "The local API is running but {addr} does not accept connections. A firewall or security \
+         product may be blocking loopback traffic for World Monitor; allow it for 127.0.0.1, then restart the local API."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpListener};
+    use std::time::{Duration, Instant};
+
+    use super::{check_bind, classify, connectable, parse_port_file, Reachability};
+
+    #[test]
+    fn a_listening_port_is_connectable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(connectable(addr, Duration::from_secs(1)));
+        drop(listener);
+        let started = Instant::now();
+        assert!(!connectable(addr, Duration::from_millis(300)));
+        assert!(started.elapsed() < Duration::from_secs(2), "gives up at the timeout");
+    }
+
+    #[test]
+    fn status_separates_a_blocked_port_from_an_exit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(classify(true, connectable(addr, Duration::from_millis(500))), Reachability::Reachable);
+        drop(listener);
+        let reached = connectable(addr, Duration::from_millis(200));
+        assert_eq!(classify(true, reached), Reachability::PortBlocked);
+        assert_eq!(classify(false, reached), Reachability::Exited);
+    }
+
+    #[test]
+    fn port_file_carries_the_bound_address() {
+        assert_eq!(
+            parse_port_file("46123\n127.0.0.1\n"),
+            Some((46123, Some(IpAddr::V4(Ipv4Addr::LOCALHOST))))
+        );
+        assert_eq!(parse_port_file("46123"), Some((46123, None)), "older sidecars");
+        assert_eq!(parse_port_file("0\n127.0.0.1"), None);
+        assert_eq!(parse_port_file(""), None);
+    }
+
+    #[test]
+    fn only_loopback_binds_unless_listen_was_asked_for() {
+        let any = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        assert!(check_bind(Some(IpAddr::V4(Ipv4Addr::LOCALHOST)), None).is_ok());
+        assert!(check_bind(Some(IpAddr::V6(Ipv6Addr::LOCALHOST)), None).is_ok());
+        assert!(check_bind(Some(any), None).is_err());
+        assert!(check_bind(Some(any), Some(any)).is_ok(), "--listen 0.0.0.0");
+        assert!(check_bind(None, None).is_ok(), "older sidecars don't say");
+    }
+}
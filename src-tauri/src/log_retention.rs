@@ -0,0 +1,300 @@
+//! Keeps the logs directory bounded. Old generations of the logs
+//! (`desktop.log.1`, `local-api.log.2.gz`, crash reports, ...) are deleted
+//! once they pass the age limit, and when the directory as a whole is over
+//! its size budget the oldest go first. The logs being written right now are
+//! never touched, and files that don't look like logs are left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::{format_iso8601_millis, log_event};
+use crate::maintenance::TaskSpec;
+use crate::{logs_dir_path, RuntimePrefs, DESKTOP_LOG_FILE, LOCAL_API_LOG_FILE};
+
+pub(crate) const PREF_LOG_MAX_TOTAL_MB: &str = "logMaxTotalMb";
+pub(crate) const PREF_LOG_MAX_AGE_DAYS: &str = "logMaxAgeDays";
+const DEFAULT_MAX_TOTAL_MB: u64 = 100;
+const DEFAULT_MAX_AGE_DAYS: u64 = 14;
+const MAX_AGE_DAYS_LIMIT: u64 = 3650;
+const MB: u64 = 1024 * 1024;
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+/// Files being appended to; never pruned.
+const ACTIVE_LOGS: [&str; 2] = [DESKTOP_LOG_FILE, LOCAL_API_LOG_FILE];
+
+#[derive(Clone, Debug, PartialEq)]
+struct LogFile {
+    name: String,
+    size: u64,
+    modified: SystemTime,
+}
+
+fn is_active(name: &str) -> bool {
+    ACTIVE_LOGS.contains(&name)
+}
+
+/// Rotated generations and crash reports. Everything else in the
+/// directory (the port file, stray user files) is out of scope.
+fn is_prunable(name: &str) -> bool {
+    !is_active(name) && (name.contains(".log.") || name.ends_with(".log") || name.starts_with("crash-"))
+}
+
+/// Names to delete: prunable files older than `max_age`, then the oldest
+/// remaining prunable files until the directory fits in `max_total` bytes.
+/// The active logs count toward the total but are never chosen.
+fn select_for_pruning(files: &[LogFile], now: SystemTime, max_total: u64, max_age: Duration) -> Vec<String> {
+    let mut candidates: Vec<&LogFile> = files.iter().filter(|file| is_prunable(&file.name)).collect();
+    candidates.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.name.cmp(&b.name)));
+    let mut total: u64 = files.iter().map(|file| file.size).sum();
+    let mut selected = Vec::new();
+    for file in candidates {
+        let expired = now
+            .duration_since(file.modified)
+            .is_ok_and(|age| age > max_age);
+        if expired || total > max_total {
+            total -= file.size;
+            selected.push(file.name.clone());
+        }
+    }
+    selected
+}
+
+fn list_files(dir: &Path) -> Vec<LogFile> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+            Some(LogFile {
+                name: entry.file_name().to_str()?.to_string(),
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+            })
+        })
+        .collect()
+}
+
+struct Limits {
+    max_total_mb: u64,
+    max_age_days: u64,
+}
+
+fn limits(app: &AppHandle) -> Limits {
+    let pref = |key: &str, default: u64| {
+        app.try_state::<RuntimePrefs>()
+            .and_then(|prefs| prefs.get(key))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(default)
+    };
+    Limits {
+        max_total_mb: pref(PREF_LOG_MAX_TOTAL_MB, DEFAULT_MAX_TOTAL_MB),
+        max_age_days: pref(PREF_LOG_MAX_AGE_DAYS, DEFAULT_MAX_AGE_DAYS),
+    }
+}
+
+pub(crate) fn validate_max_total_mb(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(mb) if mb > 0 => Ok(()),
+        _ => Err(format!("Runtime pref {PREF_LOG_MAX_TOTAL_MB} must be a positive integer")),
+    }
+}
+
+pub(crate) fn validate_max_age_days(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(days) if (1..=MAX_AGE_DAYS_LIMIT).contains(&days) => Ok(()),
+        _ => Err(format!(
+            "Runtime pref {PREF_LOG_MAX_AGE_DAYS} must be an integer from 1 to {MAX_AGE_DAYS_LIMIT}"
+        )),
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PruneResult {
+    removed: Vec<String>,
+    reclaimed_bytes: u64,
+}
+
+/// Prune at startup and once a day after that.
+pub(crate) const MAINTENANCE: TaskSpec = TaskSpec {
+    id: "log_retention",
+    interval: DAY,
+    jitter: Duration::from_secs(3600),
+    first_delay: Duration::ZERO,
+    skip_while_paused: false,
+    run: |app| {
+        Box::pin(async move {
+            match tauri::async_runtime::spawn_blocking(move || prune(&app, None, None)).await {
+                Ok(Ok(_)) => Ok(()),
+                Ok(Err(err)) => Err(format!("log pruning failed: {err}")),
+                Err(err) => Err(format!("log pruning failed: {err}")),
+            }
+        })
+    },
+};
+
+/// Prune the logs directory. Limits left `None` come from the prefs.
+pub(crate) fn prune(
+    app: &AppHandle,
+    max_total_mb: Option<u64>,
+    max_age_days: Option<u64>,
+) -> Result<PruneResult, DesktopError> {
+    let defaults = limits(app);
+    let max_total_mb = max_total_mb.unwrap_or(defaults.max_total_mb);
+    let max_age_days = max_age_days.unwrap_or(defaults.max_age_days);
+    if max_total_mb == 0 || max_age_days == 0 {
+        return Err(DesktopError::InvalidArgument(
+            "Log limits must be at least 1 MB and 1 day".to_string(),
+        ));
+    }
+    let dir = logs_dir_path(app).map_err(DesktopError::Internal)?;
+    let files = list_files(&dir);
+    let max_age = DAY.saturating_mul(max_age_days.min(MAX_AGE_DAYS_LIMIT) as u32);
+    let selected = select_for_pruning(&files, SystemTime::now(), max_total_mb.saturating_mul(MB), max_age);
+    let mut result = PruneResult {
+        removed: Vec::new(),
+        reclaimed_bytes: 0,
+    };
+    for name in selected {
+        let size = files.iter().find(|file| file.name == name).map_or(0, |file| file.size);
+        match fs::remove_file(dir.join(&name)) {
+            Ok(()) => {
+                result.reclaimed_bytes += size;
+                result.removed.push(name);
+            }
+            Err(err) => log_event(
+                app,
+                "WARN",
+                "log_prune_failed",
+                &[("file", &name), ("error", &err.to_string())],
+            ),
+        }
+    }
+    if !result.removed.is_empty() {
+        log_event(
+            app,
+            "INFO",
+            "logs_pruned",
+            &[
+                ("files", &result.removed.join(",")),
+                ("reclaimed_bytes", &result.reclaimed_bytes.to_string()),
+            ],
+        );
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LogFileInfo {
+    name: String,
+    size_bytes: u64,
+    modified_at: String,
+    active: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LogFilesInfo {
+    dir: PathBuf,
+    files: Vec<LogFileInfo>,
+    total_bytes: u64,
+    max_total_bytes: u64,
+    max_age_days: u64,
+}
+
+pub(crate) fn log_files_info(app: &AppHandle) -> Result<LogFilesInfo, DesktopError> {
+    let dir = logs_dir_path(app).map_err(DesktopError::Internal)?;
+    let mut files = list_files(&dir);
+    files.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)));
+    let limits = limits(app);
+    Ok(LogFilesInfo {
+        total_bytes: files.iter().map(|file| file.size).sum(),
+        files: files
+            .into_iter()
+            .map(|file| LogFileInfo {
+                active: is_active(&file.name),
+                modified_at: format_iso8601_millis(
+                    file.modified.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis()),
+                ),
+                size_bytes: file.size,
+                name: file.name,
+            })
+            .collect(),
+        dir,
+        max_total_bytes: limits.max_total_mb.saturating_mul(MB),
+        max_age_days: limits.max_age_days,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{select_for_pruning, LogFile, DAY, MB};
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_790_000_000)
+    }
+
+    fn file(name: &str, size_mb: u64, age_days: u64) -> LogFile {
+        LogFile {
+            name: name.to_string(),
+            size: size_mb * MB,
+            modified: now() - DAY * age_days as u32,
+        }
+    }
+
+    #[test]
+    fn expired_generations_go_regardless_of_size() {
+        let now = now();
+        let files = [
+            file("desktop.log", 1, 40),
+            file("desktop.log.1", 1, 20),
+            file("local-api.log.3.gz", 1, 30),
+            file("crash-2026-09-01.txt", 1, 45),
+            file("sidecar.port", 0, 90),
+            file("local-api.log.1", 1, 2),
+        ];
+        let selected = select_for_pruning(&files, now, 100 * MB, DAY * 14);
+        assert_eq!(selected, ["crash-2026-09-01.txt", "local-api.log.3.gz", "desktop.log.1"]);
+    }
+
+    #[test]
+    fn over_budget_removes_oldest_first_and_spares_active_logs() {
+        let now = now();
+        let files = [
+            file("desktop.log", 30, 10),
+            file("local-api.log", 30, 10),
+            file("desktop.log.2", 10, 3),
+            file("desktop.log.1", 10, 1),
+            file("local-api.log.1", 10, 2),
+        ];
+        // 90 MB total against a 75 MB budget: the two oldest generations go.
+        let selected = select_for_pruning(&files, now, 75 * MB, DAY * 14);
+        assert_eq!(selected, ["desktop.log.2", "local-api.log.1"]);
+
+        // Even a budget smaller than the active logs only ever takes the rest.
+        let selected = select_for_pruning(&files, now, MB, DAY * 14);
+        assert_eq!(selected, ["desktop.log.2", "local-api.log.1", "desktop.log.1"]);
+    }
+
+    #[test]
+    fn ties_and_clock_skew_are_handled() {
+        let now = now();
+        let future = LogFile {
+            name: "desktop.log.9".to_string(),
+            size: MB,
+            modified: now + Duration::from_secs(3600),
+        };
+        let files = [file("desktop.log.2", 1, 20), file("desktop.log.1", 1, 20), future];
+        assert_eq!(select_for_pruning(&files, now, 100 * MB, DAY * 14), ["desktop.log.1", "desktop.log.2"]);
+    }
+}
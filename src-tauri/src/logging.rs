@@ -0,0 +1,705 @@
+//! desktop.log writer. Every line carries an ISO-8601 UTC timestamp with
+//! milliseconds and the process id, and is rendered either in the historical
+//! bracketed text format or as one JSON object per line (`logFormat` runtime
+//! pref, overridden by the `WM_LOG_FORMAT` env var). Stored secret values
+//! are scrubbed from every line before it reaches disk. Lines below the
+//! log level are dropped; the level comes from `set_log_level` for the rest
+//! of the session, else `--log-level`, else the `logLevel` pref, else `info`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aho_corasick::AhoCorasick;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+
+use serde::Serialize;
+
+use crate::cli::CliOptions;
+use crate::{desktop_log_path, RuntimePrefs};
+
+pub(crate) const PREF_LOG_FORMAT: &str = "logFormat";
+pub(crate) const LOG_FORMATS: [&str; 2] = ["text", "json"];
+pub(crate) const PREF_LOG_LEVEL: &str = "logLevel";
+pub(crate) const LOG_LEVELS: [&str; 4] = ["error", "warn", "info", "debug"];
+pub(crate) const MAX_TAIL_LINES: usize = 2000;
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+/// Shorter vault values are not scrubbed; they are too likely to match
+/// ordinary log text.
+const MIN_REDACTED_SECRET_LEN: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub(crate) fn parse(level: &str) -> Option<Self> {
+        match level.trim().to_ascii_lowercase().as_str() {
+            "debug" | "trace" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+/// Level chosen through `set_log_level`; beats the CLI flag until exit.
+#[derive(Default)]
+pub(crate) struct LogLevelState {
+    session: Mutex<Option<LogLevel>>,
+}
+
+impl LogLevelState {
+    pub(crate) fn set(&self, level: LogLevel) {
+        *self.session.lock().unwrap_or_else(|e| e.into_inner()) = Some(level);
+    }
+}
+
+/// Whether a record at `level` passes `threshold`. Unrecognized levels are
+/// treated as `info`.
+fn level_enabled(level: &str, threshold: LogLevel) -> bool {
+    LogLevel::parse(level).unwrap_or(LogLevel::Info) >= threshold
+}
+
+fn resolve_threshold(session: Option<LogLevel>, cli: Option<LogLevel>, pref: Option<&str>) -> LogLevel {
+    session
+        .or(cli)
+        .or_else(|| pref.and_then(LogLevel::parse))
+        .unwrap_or(LogLevel::Info)
+}
+
+pub(crate) fn log_threshold(app: &AppHandle) -> LogLevel {
+    let session = app
+        .try_state::<LogLevelState>()
+        .and_then(|state| *state.session.lock().unwrap_or_else(|e| e.into_inner()));
+    let cli = app.try_state::<CliOptions>().and_then(|cli| cli.log_level);
+    let pref = app
+        .try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_LOG_LEVEL))
+        .and_then(|v| v.as_str().map(str::to_string));
+    resolve_threshold(session, cli, pref.as_deref())
+}
+
+pub(crate) fn validate_log_level(value: &Value) -> Result<(), String> {
+    match value.as_str() {
+        Some(level) if LOG_LEVELS.contains(&level) => Ok(()),
+        _ => Err(format!(
+            "Runtime pref {PREF_LOG_LEVEL} must be one of: {}",
+            LOG_LEVELS.join(", ")
+        )),
+    }
+}
+
+/// Headless runs have no log viewer, so records are echoed to stderr too.
+fn mirror_to_stderr(app: &AppHandle) -> bool {
+    app.try_state::<CliOptions>().is_some_and(|cli| cli.headless)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+/// One desktop.log line before formatting.
+struct LogRecord<'a> {
+    level: &'a str,
+    stage: Option<&'a str>,
+    message: Option<&'a str>,
+    fields: &'a [(&'a str, &'a str)],
+    window: Option<&'a str>,
+}
+
+/// Matcher over the current vault values. Built once per vault change so each
+/// line costs a single Aho-Corasick scan however many keys are configured.
+pub(crate) struct SecretRedactor {
+    matcher: Option<AhoCorasick>,
+    /// Key name for each pattern, in pattern order.
+    keys: Vec<String>,
+}
+
+impl SecretRedactor {
+    pub(crate) fn new(secrets: &HashMap<String, String>) -> Self {
+        let mut entries: Vec<(&str, &str)> = secrets
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.trim()))
+            .filter(|(_, v)| v.len() >= MIN_REDACTED_SECRET_LEN)
+            .collect();
+        entries.sort_unstable();
+        let matcher = if entries.is_empty() {
+            None
+        } else {
+            AhoCorasick::new(entries.iter().map(|(_, v)| v)).ok()
+        };
+        SecretRedactor {
+            matcher,
+            keys: entries.iter().map(|(k, _)| (*k).to_string()).collect(),
+        }
+    }
+
+    /// Replace each stored value in `text` with `«redacted:KEY_NAME»`.
+    /// Overlapping matches (one value embedded in another, or two values
+    /// sharing a prefix/suffix) collapse into one marker naming every key
+    /// involved, so no fragment of either value survives.
+    pub(crate) fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let Some(matcher) = &self.matcher else {
+            return Cow::Borrowed(text);
+        };
+        let mut matches: Vec<(usize, usize, usize)> = matcher
+            .find_overlapping_iter(text)
+            .map(|m| (m.start(), m.end(), m.pattern().as_usize()))
+            .collect();
+        if matches.is_empty() {
+            return Cow::Borrowed(text);
+        }
+        // Overlapping search reports matches by end offset; merge by start.
+        matches.sort_unstable();
+        let mut spans: Vec<(usize, usize, Vec<&str>)> = Vec::new();
+        for (start, end, pattern) in matches {
+            let key = self.keys[pattern].as_str();
+            match spans.last_mut() {
+                Some(last) if start < last.1 => {
+                    last.1 = last.1.max(end);
+                    if !last.2.contains(&key) {
+                        last.2.push(key);
+                    }
+                }
+                _ => spans.push((start, end, vec![key])),
+            }
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for (start, end, mut keys) in spans {
+            keys.sort_unstable();
+            out.push_str(&text[cursor..start]);
+            out.push_str(&format!("\u{ab}redacted:{}\u{bb}", keys.join("+")));
+            cursor = end;
+        }
+        out.push_str(&text[cursor..]);
+        Cow::Owned(out)
+    }
+}
+
+/// Managed handle to the current [`SecretRedactor`], swapped whenever the
+/// vault is written.
+pub(crate) struct LogRedaction {
+    current: Mutex<Arc<SecretRedactor>>,
+}
+
+impl LogRedaction {
+    pub(crate) fn new(secrets: &HashMap<String, String>) -> Self {
+        LogRedaction {
+            current: Mutex::new(Arc::new(SecretRedactor::new(secrets))),
+        }
+    }
+
+    pub(crate) fn rebuild(&self, secrets: &HashMap<String, String>) {
+        let redactor = Arc::new(SecretRedactor::new(secrets));
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = redactor;
+    }
+}
+
+/// Current redactor, or a no-op one before the state is managed.
+pub(crate) fn secret_redactor(app: &AppHandle) -> Arc<SecretRedactor> {
+    app.try_state::<LogRedaction>()
+        .map(|state| state.current.lock().unwrap_or_else(|e| e.into_inner()).clone())
+        .unwrap_or_else(|| Arc::new(SecretRedactor::new(&HashMap::new())))
+}
+
+fn log_format(app: &AppHandle) -> LogFormat {
+    let configured = env::var("WM_LOG_FORMAT").ok().or_else(|| {
+        app.try_state::<RuntimePrefs>()
+            .and_then(|prefs| prefs.get(PREF_LOG_FORMAT))
+            .and_then(|v| v.as_str().map(str::to_string))
+    });
+    match configured.as_deref().map(str::trim) {
+        Some(f) if f.eq_ignore_ascii_case("json") => LogFormat::Json,
+        _ => LogFormat::Text,
+    }
+}
+
+/// Convert days since the Unix epoch to a proleptic Gregorian (y, m, d).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+pub(crate) fn format_iso8601_millis(unix_millis: u128) -> String {
+    let secs = (unix_millis / 1000) as i64;
+    let millis = (unix_millis % 1000) as u32;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Unix milliseconds now; 0 if the clock is before 1970.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub(crate) fn now_iso8601() -> String {
+    format_iso8601_millis(u128::from(now_ms()))
+}
+
+/// Keep each record on a single line in the text format.
+fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn text_field_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '=' || c.is_control());
+    if needs_quotes {
+        format!("\"{}\"", escape_text(value))
+    } else {
+        escape_text(value)
+    }
+}
+
+fn format_record(format: LogFormat, timestamp: &str, pid: u32, record: &LogRecord<'_>) -> String {
+    match format {
+        LogFormat::Text => {
+            let mut line = format!("[{timestamp}][{}][pid {pid}]", record.level);
+            if let Some(window) = record.window {
+                line.push_str(&format!("[{}]", escape_text(window)));
+            }
+            if let Some(stage) = record.stage {
+                line.push(' ');
+                line.push_str(&escape_text(stage));
+            }
+            if let Some(message) = record.message {
+                line.push(' ');
+                line.push_str(&escape_text(message));
+            }
+            for (key, value) in record.fields {
+                line.push_str(&format!(" {}={}", escape_text(key), text_field_value(value)));
+            }
+            line
+        }
+        LogFormat::Json => {
+            let mut obj = Map::new();
+            obj.insert("ts".into(), Value::from(timestamp));
+            obj.insert("level".into(), Value::from(record.level));
+            obj.insert("pid".into(), Value::from(pid));
+            if let Some(window) = record.window {
+                obj.insert("window".into(), Value::from(window));
+            }
+            if let Some(stage) = record.stage {
+                obj.insert("stage".into(), Value::from(stage));
+            }
+            if let Some(message) = record.message {
+                obj.insert("message".into(), Value::from(message));
+            }
+            if !record.fields.is_empty() {
+                let fields: Map<String, Value> = record
+                    .fields
+                    .iter()
+                    .map(|(k, v)| ((*k).to_string(), Value::from(*v)))
+                    .collect();
+                obj.insert("fields".into(), Value::Object(fields));
+            }
+            Value::Object(obj).to_string()
+        }
+    }
+}
+
+fn write_record(app: &AppHandle, record: &LogRecord<'_>) {
+    if !level_enabled(record.level, log_threshold(app)) {
+        return;
+    }
+    write_line(app, log_format(app), &secret_redactor(app), record);
+}
+
+fn write_line(app: &AppHandle, format: LogFormat, redactor: &SecretRedactor, record: &LogRecord<'_>) -> bool {
+    let line = format_record(format, &now_iso8601(), std::process::id(), record);
+    let line = redactor.redact(&line);
+    if mirror_to_stderr(app) {
+        eprintln!("{line}");
+    }
+
+    let Ok(path) = desktop_log_path(app) else {
+        return false;
+    };
+
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
+        return false;
+    };
+
+    writeln!(file, "{line}").is_ok()
+}
+
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Panic-hook variant of [`log_event`]. The panicking thread may already hold
+/// the prefs or redaction lock, so those are only `try_lock`ed: the format
+/// falls back to text, and without a redactor the fields are withheld rather
+/// than written unscrubbed. Returns whether the line reached desktop.log.
+pub(crate) fn log_panic(app: &AppHandle, fields: &[(&str, &str)]) -> bool {
+    let format = match env::var("WM_LOG_FORMAT") {
+        Ok(f) if f.trim().eq_ignore_ascii_case("json") => LogFormat::Json,
+        Ok(_) => LogFormat::Text,
+        Err(_) => app
+            .try_state::<RuntimePrefs>()
+            .and_then(|prefs| {
+                try_lock(&prefs.prefs)?
+                    .get(PREF_LOG_FORMAT)
+                    .and_then(|v| v.as_str())
+                    .map(|f| f.trim().eq_ignore_ascii_case("json"))
+            })
+            .map_or(LogFormat::Text, |json| if json { LogFormat::Json } else { LogFormat::Text }),
+    };
+    let redactor = match app.try_state::<LogRedaction>() {
+        Some(state) => try_lock(&state.current).map(|current| current.clone()),
+        None => Some(Arc::new(SecretRedactor::new(&HashMap::new()))),
+    };
+    let withheld = [("details", "withheld: secret redaction unavailable")];
+    let (redactor, fields) = match redactor {
+        Some(redactor) => (redactor, fields),
+        None => (Arc::new(SecretRedactor::new(&HashMap::new())), &withheld[..]),
+    };
+    write_line(
+        app,
+        format,
+        &redactor,
+        &LogRecord {
+            level: "ERROR",
+            stage: Some("panic"),
+            message: None,
+            fields,
+            window: None,
+        },
+    )
+}
+
+/// Structured entry point: `stage` names what happened, `fields` carry the
+/// details as key/value pairs.
+pub(crate) fn log_event(app: &AppHandle, level: &str, stage: &str, fields: &[(&str, &str)]) {
+    write_record(
+        app,
+        &LogRecord {
+            level,
+            stage: Some(stage),
+            message: None,
+            fields,
+            window: None,
+        },
+    );
+}
+
+/// Same as [`log_event`], tagged with the window the event originated from.
+pub(crate) fn log_window_event(
+    app: &AppHandle,
+    window: &str,
+    level: &str,
+    stage: &str,
+    fields: &[(&str, &str)],
+) {
+    write_record(
+        app,
+        &LogRecord {
+            level,
+            stage: Some(stage),
+            message: None,
+            fields,
+            window: Some(window),
+        },
+    );
+}
+
+/// Free-form message logging kept for existing call sites.
+pub(crate) fn append_desktop_log(app: &AppHandle, level: &str, message: &str) {
+    write_record(
+        app,
+        &LogRecord {
+            level,
+            stage: None,
+            message: Some(message),
+            fields: &[],
+            window: None,
+        },
+    );
+}
+
+/// Troubleshooting detail, written only at the `debug` level.
+pub(crate) fn append_desktop_debug(app: &AppHandle, message: &str) {
+    append_desktop_log(app, "DEBUG", message);
+}
+
+/// Return the last `max_lines` lines of a log without loading the whole file:
+/// scan backwards in chunks for newlines, then read only that suffix. The
+/// length is sampled once, so bytes appended concurrently (the sidecar keeps
+/// writing) are simply left for the next call. Missing files yield no lines and
+/// invalid UTF-8 is replaced lossily.
+pub(crate) fn read_tail_lines(path: &Path, max_lines: usize) -> io::Result<Vec<String>> {
+    if max_lines == 0 {
+        return Ok(Vec::new());
+    }
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut end = file.metadata()?.len();
+    if end == 0 {
+        return Ok(Vec::new());
+    }
+    // A trailing newline terminates the last line rather than starting a new one.
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(end - 1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        end -= 1;
+    }
+
+    let mut start = end;
+    let mut newlines = 0usize;
+    let mut buf = vec![0u8; TAIL_CHUNK_BYTES as usize];
+    'scan: while start > 0 {
+        let chunk = TAIL_CHUNK_BYTES.min(start);
+        start -= chunk;
+        let slice = &mut buf[..chunk as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(slice)?;
+        for i in (0..slice.len()).rev() {
+            if slice[i] == b'\n' {
+                newlines += 1;
+                if newlines == max_lines {
+                    start += i as u64 + 1;
+                    break 'scan;
+                }
+            }
+        }
+    }
+
+    if start >= end {
+        return Ok(Vec::new());
+    }
+    let mut bytes = vec![0u8; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes)
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_iso8601_millis, format_record, level_enabled, read_tail_lines, resolve_threshold, LogFormat,
+        LogLevel, LogRecord, SecretRedactor,
+    };
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn redactor(secrets: &[(&str, &str)]) -> SecretRedactor {
+        let map: HashMap<String, String> = secrets
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect();
+        SecretRedactor::new(&map)
+    }
+
+    fn temp_log(name: &str, contents: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wm-logging-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn record<'a>(message: Option<&'a str>, fields: &'a [(&'a str, &'a str)]) -> LogRecord<'a> {
+        LogRecord {
+            level: "INFO",
+            stage: Some("sidecar_start"),
+            message,
+            fields,
+            window: Some("main"),
+        }
+    }
+
+    #[test]
+    fn formats_iso8601_with_millis() {
+        assert_eq!(format_iso8601_millis(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(format_iso8601_millis(1_700_000_000_123), "2023-11-14T22:13:20.123Z");
+        assert_eq!(format_iso8601_millis(951_782_400_000), "2000-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn text_format_escapes_quotes_and_newlines() {
+        let fields = [("path", "C:\\Program Files\\node.exe"), ("reason", "said \"no\"\nthen left")];
+        let line = format_record(LogFormat::Text, "T", 42, &record(Some("line1\nline2"), &fields));
+        assert_eq!(
+            line,
+            "[T][INFO][pid 42][main] sidecar_start line1\\nline2 path=\"C:\\\\Program Files\\\\node.exe\" reason=\"said \\\"no\\\"\\nthen left\""
+        );
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn json_format_round_trips_special_characters() {
+        let fields = [("reason", "said \"no\"\nthen left")];
+        let line = format_record(LogFormat::Json, "T", 42, &record(None, &fields));
+        assert!(!line.contains('\n'));
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["ts"], "T");
+        assert_eq!(parsed["pid"], 42);
+        assert_eq!(parsed["window"], "main");
+        assert_eq!(parsed["stage"], "sidecar_start");
+        assert_eq!(parsed["fields"]["reason"], "said \"no\"\nthen left");
+    }
+
+    #[test]
+    fn tails_file_with_trailing_newline() {
+        let path = temp_log("trailing.log", b"one\ntwo\nthree\n");
+        assert_eq!(read_tail_lines(&path, 2).unwrap(), vec!["two", "three"]);
+        assert_eq!(read_tail_lines(&path, 10).unwrap(), vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn tails_file_without_trailing_newline() {
+        let path = temp_log("no-trailing.log", b"one\r\ntwo\r\nthree");
+        assert_eq!(read_tail_lines(&path, 2).unwrap(), vec!["two", "three"]);
+        assert_eq!(read_tail_lines(&path, 1).unwrap(), vec!["three"]);
+    }
+
+    #[test]
+    fn tails_across_chunk_boundaries() {
+        let mut contents = Vec::new();
+        for i in 0..20_000 {
+            contents.extend_from_slice(format!("line {i}\n").as_bytes());
+        }
+        let path = temp_log("large.log", &contents);
+        let tail = read_tail_lines(&path, 3).unwrap();
+        assert_eq!(tail, vec!["line 19997", "line 19998", "line 19999"]);
+    }
+
+    #[test]
+    fn handles_missing_empty_and_invalid_utf8() {
+        let missing = std::env::temp_dir().join("wm-logging-tests-missing.log");
+        assert!(read_tail_lines(&missing, 5).unwrap().is_empty());
+        let empty = temp_log("empty.log", b"");
+        assert!(read_tail_lines(&empty, 5).unwrap().is_empty());
+        let binary = temp_log("binary.log", b"ok\n\xff\xfe bad\n");
+        assert_eq!(read_tail_lines(&binary, 1).unwrap(), vec!["\u{fffd}\u{fffd} bad"]);
+    }
+
+    #[test]
+    fn scrubs_stored_key_from_formatted_log_line() {
+        let redactor = redactor(&[("GROQ_API_KEY", "gsk_live_abcdef123456")]);
+        let fields = [("url", "https://api.groq.com/?key=gsk_live_abcdef123456"), ("retry", "gsk_live_abcdef123456")];
+        let line = format_record(LogFormat::Text, "T", 42, &record(None, &fields));
+        let scrubbed = redactor.redact(&line);
+        assert!(!scrubbed.contains("gsk_live_abcdef123456"));
+        assert_eq!(scrubbed.matches("\u{ab}redacted:GROQ_API_KEY\u{bb}").count(), 2);
+    }
+
+    #[test]
+    fn merges_overlapping_and_nested_secrets() {
+        let redactor = redactor(&[
+            ("INNER_KEY", "abcd1234"),
+            ("OUTER_KEY", "prefix-abcd1234-suffix"),
+            ("LEFT_KEY", "aaaaBBBBcc"),
+            ("RIGHT_KEY", "BBBBccDDDD"),
+        ]);
+        assert_eq!(
+            redactor.redact("x=prefix-abcd1234-suffix y=abcd1234 z=aaaaBBBBccDDDD!"),
+            "x=\u{ab}redacted:INNER_KEY+OUTER_KEY\u{bb} y=\u{ab}redacted:INNER_KEY\u{bb} z=\u{ab}redacted:LEFT_KEY+RIGHT_KEY\u{bb}!"
+        );
+    }
+
+    #[test]
+    fn leaves_short_values_and_clean_lines_untouched() {
+        let redactor = redactor(&[("OLLAMA_MODEL", "llama3"), ("EMPTY", "   ")]);
+        assert_eq!(redactor.redact("model=llama3"), "model=llama3");
+        assert!(matches!(
+            super::SecretRedactor::new(&HashMap::new()).redact("plain"),
+            std::borrow::Cow::Borrowed("plain")
+        ));
+    }
+
+    #[test]
+    fn filters_records_below_threshold() {
+        assert!(level_enabled("INFO", LogLevel::Info));
+        assert!(!level_enabled("DEBUG", LogLevel::Info));
+        assert!(level_enabled("debug", LogLevel::Debug));
+        assert!(!level_enabled("WARN", LogLevel::Error));
+        assert!(level_enabled("custom", LogLevel::Info));
+        assert_eq!(LogLevel::parse(" Warning "), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::parse("verbose"), None);
+    }
+
+    #[test]
+    fn session_level_beats_cli_beats_pref() {
+        assert_eq!(resolve_threshold(None, None, None), LogLevel::Info);
+        assert_eq!(resolve_threshold(None, None, Some("debug")), LogLevel::Debug);
+        assert_eq!(resolve_threshold(None, None, Some("bogus")), LogLevel::Info);
+        assert_eq!(resolve_threshold(None, Some(LogLevel::Warn), Some("debug")), LogLevel::Warn);
+        assert_eq!(
+            resolve_threshold(Some(LogLevel::Debug), Some(LogLevel::Warn), Some("error")),
+            LogLevel::Debug
+        );
+        for level in super::LOG_LEVELS {
+            assert_eq!(LogLevel::parse(level).map(LogLevel::as_str), Some(level));
+        }
+    }
+}
@@ -1,33 +1,171 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::env;
 use std::fs::{self, File, OpenOptions};
-use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use keyring::Entry;
 use reqwest::Url;
 use serde::Serialize;
 use serde_json::{Map, Value};
-use tauri::menu::{AboutMetadata, Menu, MenuItem, PredefinedMenuItem, Submenu};
-use tauri::{AppHandle, Manager, RunEvent, Webview, WebviewUrl, WebviewWindowBuilder};
-#[cfg(target_os = "macos")]
+use logging::{append_desktop_debug, append_desktop_log, log_event, log_window_event, LogRedaction};
+use cli::CliOptions;
+use error::DesktopError;
+use runtime_info::StaticRuntimeInfo;
+use safe_mode::{SafeModeState, SafeModeStatus, SAFE_MODE_WINDOW_LABEL};
+use secret_store::SecretStore;
+use startup_profile::StartupProfile;
+use tauri::menu::{AboutMetadata, CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, Webview, WebviewUrl, WebviewWindowBuilder};
+#[cfg(any(windows, target_os = "linux"))]
+use tauri_plugin_deep_link::DeepLinkExt;
 use tauri::WindowEvent;
 
+mod api_usage;
+mod app_paths;
+mod autostart;
+mod blob_store;
+mod broadcast;
+mod cache_entry;
+mod cache_limits;
+mod cli;
+mod clipboard;
+mod clock_skew;
+mod config_bundle;
+mod content_protection;
+mod crash;
+mod data_dir;
+mod data_sources;
+mod diagnostics;
+mod disk_space;
+mod display_scale;
+mod doctor;
+mod error;
+mod find_in_page;
+mod extra_ca;
+mod file_dialog;
+mod forensics_worker;
+mod global_shortcut;
+mod headless;
+mod http_cache;
+mod i18n;
+mod keep_awake;
+mod keyring_migration;
+#[cfg(target_os = "linux")]
+mod linux_webkit;
+mod local_api_probe;
+mod log_retention;
+mod logging;
+mod maintenance;
+mod native_fetch;
+mod network_profile;
+mod node_binary;
+mod offline_cache;
+mod onboarding;
+mod packaging;
+mod panel_windows;
+mod polling;
+mod profile;
+mod reload;
+mod quiet_hours;
+mod quit_guard;
+mod rate_limit;
+mod resource_integrity;
+mod resources;
+mod restart;
+mod reveal;
+mod runtime_info;
+mod safe_mode;
+mod secret_store;
+mod sidecar_history;
+mod sidecar_env;
+mod sidecar_log;
+mod sidecar_options;
+mod sidecar_paths;
+mod source_health;
+mod startup_profile;
+mod taskbar;
+#[cfg(test)]
+mod test_support;
+mod theme;
+mod trusted_hosts;
+#[cfg(feature = "tray")]
+mod tray;
+mod url_safety;
+mod user_agent;
+mod vault_events;
+mod vault_sync;
+mod version_history;
+mod webview_text;
+mod window_capture;
+mod window_creation;
+mod window_geometry;
+mod window_layout;
+mod ws_bridge;
+
 const DEFAULT_LOCAL_API_PORT: u16 = 46123;
-const KEYRING_SERVICE: &str = "world-monitor";
 const LOCAL_API_LOG_FILE: &str = "local-api.log";
 const DESKTOP_LOG_FILE: &str = "desktop.log";
+const RUNTIME_PREFS_FILE: &str = "runtime-prefs.json";
+const PERSISTENT_CACHE_FILE: &str = "persistent-cache.json";
+const PREF_KEEP_SETTINGS_ABOVE_MAIN: &str = "keepSettingsAboveMain";
+const PREF_SETTINGS_MODAL: &str = "settingsModal";
+const PREF_NOTIFICATIONS_MUTED: &str = "notificationsMuted";
+const PREF_NOTIFICATION_CATEGORIES: &str = "notificationCategories";
+const PREF_SUPPRESS_NOTIFICATIONS_WHEN_FOCUSED: &str = "suppressNotificationsWhenFocused";
+const PREF_AUTO_CHECK_UPDATES: &str = "autoCheckUpdates";
+const PREF_LAST_UPDATE_CHECK_AT: &str = "lastUpdateCheckAt";
+/// Offer prerelease builds (`2.6.0-beta.1`) as updates.
+const PREF_INCLUDE_PRERELEASES: &str = "includePrereleaseUpdates";
+const PREF_START_MINIMIZED: &str = "startMinimized";
+const PREF_START_IN_TRAY: &str = "startInTray";
+/// Closing the main window hides it to the tray instead of quitting
+/// (Windows/Linux, `tray` builds only).
+const PREF_CLOSE_TO_TRAY: &str = "closeToTray";
+pub(crate) const PREF_ALLOWED_URL_SCHEMES: &str = "allowedUrlSchemes";
+const PREF_SETTINGS_WINDOW_GEOMETRY: &str = "settingsWindowGeometry";
+/// Sections of settings.html that can be opened directly.
+const SETTINGS_SECTIONS: [&str; 9] = [
+    "overview", "ai", "economy", "markets", "security", "tracking", "climate", "research", "debug",
+];
+const SETTINGS_WINDOW_MIN_SIZE: (f64, f64) = (820.0, 480.0);
+/// Never opened, even if listed in `allowedUrlSchemes`.
+const BLOCKED_URL_SCHEMES: [&str; 6] = ["javascript", "vbscript", "data", "blob", "file", "about"];
+const UPDATE_MANIFEST_URL: &str = "https://worldmonitor.app/api/version";
+const UPDATE_RELEASES_URL: &str = "https://github.com/koala73/worldmonitor/releases/latest";
+const AUTO_UPDATE_CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+/// How often the background task looks at whether a check is due.
+const AUTO_UPDATE_POLL_SECS: u64 = 60 * 60;
+const NOTIFICATION_CATEGORIES: [&str; 3] = ["alerts", "data-source-errors", "sidecar-status"];
+const NOTIFICATION_RATE_WINDOW_SECS: u64 = 10;
+const NOTIFICATION_RATE_MAX: usize = 5;
+const NOTIFICATION_TITLE_MAX_CHARS: usize = 256;
+const NOTIFICATION_BODY_MAX_CHARS: usize = 2048;
+const FRONTEND_LOG_LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+const FRONTEND_LOG_MAX_CHARS: usize = 4096;
+const FRONTEND_LOG_RATE_MAX: usize = 50;
 const MENU_FILE_SETTINGS_ID: &str = "file.settings";
 const MENU_HELP_GITHUB_ID: &str = "help.github";
+const MENU_HELP_CHECK_UPDATES_ID: &str = "help.check_updates";
+const MENU_HELP_EXPORT_DIAGNOSTICS_ID: &str = "help.export_diagnostics";
+const MENU_HELP_SHOW_LOGS_ID: &str = "help.show_logs";
+const MENU_HELP_OPEN_DATA_ID: &str = "help.open_data";
 #[cfg(feature = "devtools")]
 const MENU_HELP_DEVTOOLS_ID: &str = "help.devtools";
+const DEEP_LINK_SCHEME: &str = "worldmonitor";
+const DEEP_LINK_MAX_LEN: usize = 2048;
+const DEEP_LINK_TARGETS: [&str; 3] = ["panel", "view", "settings"];
+const DEEP_LINK_MAX_SEGMENTS: usize = 4;
+const DEEP_LINK_MAX_PARAMS: usize = 16;
+const DEEP_LINK_MAX_PARAM_VALUE_LEN: usize = 256;
+const STARTUP_SPLASH_TIMEOUT_SECS: u64 = 20;
 const TRUSTED_WINDOWS: [&str; 3] = ["main", "settings", "live-channels"];
 const SUPPORTED_SECRET_KEYS: [&str; 36] = [
     "GROQ_API_KEY",
@@ -73,12 +211,254 @@ struct LocalApiState {
     child: Mutex<Option<Child>>,
     token: Mutex<Option<String>>,
     port: Mutex<Option<u16>>,
+    /// The sidecar answered its health check after the last start.
+    ready: Mutex<bool>,
+    started_at: Mutex<Option<String>>,
+    /// Port last sent in `local-api-connection-changed`.
+    announced_port: Mutex<Option<u16>>,
+    history: Mutex<sidecar_history::SidecarHistory>,
+    /// Watched by `wait_for_local_api`; `ready` mirrors `Ready`.
+    lifecycle: tokio::sync::watch::Sender<LocalApiLifecycle>,
+    /// The separate forensics process, when `separateForensicsWorker` is on.
+    forensics: Mutex<forensics_worker::WorkerSlot<Child>>,
+}
+
+const LOCAL_API_DEGRADED_EVENT: &str = "local-api-degraded";
+const LOCAL_API_READY_EVENT: &str = "local-api-ready";
+const LOCAL_API_STARTING_EVENT: &str = "local-api-starting";
+/// Cap on `wait_for_local_api`, and its wait when no timeout is given.
+const LOCAL_API_WAIT_MAX: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Where the sidecar is in its life, as far as the shell knows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum LocalApiLifecycle {
+    #[default]
+    Stopped,
+    Starting,
+    Ready,
+    /// Running, but its port never accepted a connection.
+    PortBlocked,
+    /// Exited on its own; a restart may be scheduled.
+    Exited,
+    /// Crashed too often and won't be restarted.
+    GaveUp,
+}
+
+impl LocalApiLifecycle {
+    fn as_str(self) -> &'static str {
+        match self {
+            LocalApiLifecycle::Stopped => "stopped",
+            LocalApiLifecycle::Starting => "starting",
+            LocalApiLifecycle::Ready => "ready",
+            LocalApiLifecycle::PortBlocked => "port_blocked",
+            LocalApiLifecycle::Exited => "exited",
+            LocalApiLifecycle::GaveUp => "gave_up",
+        }
+    }
+}
+
+/// `local-api-ready` / `local-api-starting` payload.
+#[derive(Clone, Serialize)]
+struct LocalApiLifecycleChanged {
+    state: LocalApiLifecycle,
+}
+
+/// Move to `next` and, if that is a change, tell every window: `local-api-ready`
+/// on reaching `Ready`, `local-api-starting` on any other transition.
+fn set_local_api_lifecycle(app: &AppHandle, next: LocalApiLifecycle) {
+    let changed = app.state::<LocalApiState>().lifecycle.send_if_modified(|current| {
+        let changed = *current != next;
+        *current = next;
+        changed
+    });
+    if !changed {
+        return;
+    }
+    log_event(app, "INFO", "sidecar_lifecycle", &[("state", next.as_str())]);
+    let event = if next == LocalApiLifecycle::Ready {
+        LOCAL_API_READY_EVENT
+    } else {
+        LOCAL_API_STARTING_EVENT
+    };
+    if let Err(err) = app.emit(event, LocalApiLifecycleChanged { state: next }) {
+        log_event(app, "WARN", "local_api_lifecycle_emit_failed", &[("error", &err.to_string())]);
+    }
+}
+
+fn record_local_api_event(app: &AppHandle, kind: sidecar_history::LifecycleKind) {
+    let state = app.state::<LocalApiState>();
+    let mut history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+    history.record(logging::now_iso8601(), kind);
+}
+
+fn local_api_history(app: &AppHandle) -> Option<sidecar_history::HistoryReport> {
+    app.try_state::<LocalApiState>()
+        .map(|state| state.history.lock().unwrap_or_else(|e| e.into_inner()).report())
+}
+
+#[derive(Clone, Serialize)]
+struct LocalApiDegraded {
+    reason: String,
+    history: Vec<sidecar_history::LifecycleEvent>,
+}
+
+/// Poll the sidecar started as `pid` until it exits. An exit nobody asked
+/// for is restarted with backoff until the breaker trips; a stop or restart
+/// through `stop_local_api` swaps the child out and ends the watch quietly.
+fn watch_local_api(app: AppHandle, pid: u32) {
+    let state = app.state::<LocalApiState>();
+    let status = loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut slot = state.child.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(child) = slot.as_mut().filter(|child| child.id() == pid) else {
+            return;
+        };
+        if let Ok(Some(status)) = child.try_wait() {
+            *slot = None;
+            break status;
+        }
+    };
+    *state.port.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    *state.ready.lock().unwrap_or_else(|e| e.into_inner()) = false;
+    set_local_api_lifecycle(&app, LocalApiLifecycle::Exited);
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal = None;
+    let code = status.code();
+    log_event(
+        &app,
+        "WARN",
+        "sidecar_exited",
+        &[
+            ("pid", &pid.to_string()),
+            ("code", &code.map_or_else(|| "none".to_string(), |c| c.to_string())),
+            ("signal", &signal.map_or_else(|| "none".to_string(), |s| s.to_string())),
+        ],
+    );
+    record_local_api_event(&app, sidecar_history::LifecycleKind::Exited { code, signal });
+    // A restart that fails to start counts as another crash.
+    while let Err(err) = restart_after_crash(&app) {
+        log_event(&app, "ERROR", "sidecar_restart_failed", &[("error", &err)]);
+    }
+}
+
+/// Restart with backoff, or trip the breaker. `Err` when the restart failed
+/// to start; `Ok` once restarted or given up.
+fn restart_after_crash(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    let decision = {
+        let mut history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+        let decision = history.on_crash(std::time::Instant::now());
+        let kind = match decision {
+            sidecar_history::ExitDecision::Restart { attempt, delay } => {
+                sidecar_history::LifecycleKind::RestartScheduled {
+                    attempt,
+                    delay_ms: delay.as_millis() as u64,
+                }
+            }
+            sidecar_history::ExitDecision::GiveUp { crashes } => sidecar_history::LifecycleKind::GaveUp { crashes },
+        };
+        history.record(logging::now_iso8601(), kind);
+        decision
+    };
+    match decision {
+        sidecar_history::ExitDecision::Restart { attempt, delay } => {
+            log_event(
+                app,
+                "INFO",
+                "sidecar_restart_scheduled",
+                &[("attempt", &attempt.to_string()), ("delay_ms", &delay.as_millis().to_string())],
+            );
+            std::thread::sleep(delay);
+            start_local_api(app)
+        }
+        sidecar_history::ExitDecision::GiveUp { crashes } => {
+            let reason = format!(
+                "The local API crashed {crashes} times within {} minutes and was not restarted",
+                sidecar_history::CRASH_WINDOW.as_secs() / 60
+            );
+            log_event(app, "ERROR", "sidecar_breaker_tripped", &[("crashes", &crashes.to_string())]);
+            set_local_api_lifecycle(app, LocalApiLifecycle::GaveUp);
+            let payload = LocalApiDegraded {
+                reason,
+                history: state.history.lock().unwrap_or_else(|e| e.into_inner()).events(),
+            };
+            if let Err(err) = app.emit(LOCAL_API_DEGRADED_EVENT, payload) {
+                log_event(app, "WARN", "local_api_degraded_emit_failed", &[("error", &err.to_string())]);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Everything a window needs to talk to the sidecar. `base_url` and `token`
+/// are absent until the sidecar has been started.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct LocalApiConnection {
+    base_url: Option<String>,
+    token: Option<String>,
+    ready: bool,
+    started_at: Option<String>,
+}
+
+const LOCAL_API_CONNECTION_CHANGED_EVENT: &str = "local-api-connection-changed";
+
+fn local_api_base_url(app: &AppHandle, port: u16) -> String {
+    let ip = local_api_listen_addr(app)
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    format!("http://{}", std::net::SocketAddr::new(ip, port))
+}
+
+fn local_api_connection(app: &AppHandle) -> LocalApiConnection {
+    let state = app.state::<LocalApiState>();
+    let port = *state.port.lock().unwrap_or_else(|e| e.into_inner());
+    let token = state.token.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let ready = *state.ready.lock().unwrap_or_else(|e| e.into_inner());
+    let started_at = state.started_at.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    LocalApiConnection {
+        base_url: port.map(|port| local_api_base_url(app, port)),
+        token,
+        ready,
+        started_at,
+    }
+}
+
+/// Record a finished start and, when the sidecar came up on a different
+/// port than the windows were last told about, tell them.
+fn mark_local_api_started(app: &AppHandle, port: u16, reach: local_api_probe::Reachability) {
+    let state = app.state::<LocalApiState>();
+    let ready = reach == local_api_probe::Reachability::Reachable;
+    *state.ready.lock().unwrap_or_else(|e| e.into_inner()) = ready;
+    *state.started_at.lock().unwrap_or_else(|e| e.into_inner()) = Some(logging::now_iso8601());
+    match reach {
+        local_api_probe::Reachability::Reachable => set_local_api_lifecycle(app, LocalApiLifecycle::Ready),
+        local_api_probe::Reachability::PortBlocked => set_local_api_lifecycle(app, LocalApiLifecycle::PortBlocked),
+        // The watcher reports the exit.
+        local_api_probe::Reachability::Exited => {}
+    }
+    let previous = state.announced_port.lock().unwrap_or_else(|e| e.into_inner()).replace(port);
+    if previous != Some(port) {
+        let connection = local_api_connection(app);
+        if let Err(err) = app.emit(LOCAL_API_CONNECTION_CHANGED_EVENT, connection) {
+            log_event(app, "WARN", "local_api_connection_emit_failed", &[("error", &err.to_string())]);
+        }
+    }
 }
 
 /// In-memory cache for keychain secrets. Populated once at startup to avoid
-/// repeated macOS Keychain prompts (each `Entry::get_password()` triggers one).
+/// repeated macOS Keychain prompts (each keychain read triggers one).
 struct SecretsCache {
+    /// Where the vault is persisted; the keychain outside tests.
+    store: Arc<dyn SecretStore>,
     secrets: Mutex<HashMap<String, String>>,
+    /// [`vault_sync::fingerprint`] of the vault as last read or written.
+    fingerprint: Mutex<String>,
+    /// Why the vault couldn't be read at startup. The cache is empty then,
+    /// not the vault, so nothing may be written until a reload succeeds.
+    load_error: Mutex<Option<String>>,
 }
 
 /// In-memory mirror of persistent-cache.json. The file can grow to 10+ MB,
@@ -91,58 +471,40 @@ struct PersistentCache {
 }
 
 impl SecretsCache {
-    fn load_from_keychain() -> Self {
-        // Try consolidated vault first — single keychain prompt
-        if let Ok(entry) = Entry::new(KEYRING_SERVICE, "secrets-vault") {
-            if let Ok(json) = entry.get_password() {
-                if let Ok(map) = serde_json::from_str::<HashMap<String, String>>(&json) {
-                    let secrets: HashMap<String, String> = map
-                        .into_iter()
-                        .filter(|(k, v)| {
-                            SUPPORTED_SECRET_KEYS.contains(&k.as_str()) && !v.trim().is_empty()
-                        })
-                        .map(|(k, v)| (k, v.trim().to_string()))
-                        .collect();
-                    return SecretsCache {
-                        secrets: Mutex::new(secrets),
-                    };
-                }
-            }
-        }
-
-        // Migration: read individual keys (old format), consolidate into vault.
-        // This triggers one keychain prompt per key — happens only once.
-        let mut secrets = HashMap::new();
-        for key in SUPPORTED_SECRET_KEYS.iter() {
-            if let Ok(entry) = Entry::new(KEYRING_SERVICE, key) {
-                if let Ok(value) = entry.get_password() {
-                    let trimmed = value.trim().to_string();
-                    if !trimmed.is_empty() {
-                        secrets.insert((*key).to_string(), trimmed);
-                    }
-                }
-            }
-        }
-
-        // Write consolidated vault and clean up individual entries
-        if !secrets.is_empty() {
-            if let Ok(json) = serde_json::to_string(&secrets) {
-                if let Ok(vault_entry) = Entry::new(KEYRING_SERVICE, "secrets-vault") {
-                    if vault_entry.set_password(&json).is_ok() {
-                        for key in SUPPORTED_SECRET_KEYS.iter() {
-                            if let Ok(entry) = Entry::new(KEYRING_SERVICE, key) {
-                                let _ = entry.delete_credential();
-                            }
-                        }
-                    }
-                }
+    /// The consolidated vault only: a single keychain prompt. Entries still
+    /// in the old one-per-key layout are moved over by [`keyring_migration`]
+    /// once the window is up.
+    fn load(store: Arc<dyn SecretStore>) -> Self {
+        match vault_sync::read_vault(&*store, &SUPPORTED_SECRET_KEYS) {
+            Ok(secrets) => SecretsCache::new(store, secrets),
+            Err(err) => {
+                let cache = SecretsCache::new(store, HashMap::new());
+                cache.set_load_error(Some(err.to_string()));
+                cache
             }
         }
+    }
 
+    fn new(store: Arc<dyn SecretStore>, secrets: HashMap<String, String>) -> Self {
         SecretsCache {
+            store,
+            fingerprint: Mutex::new(vault_sync::fingerprint(&secrets)),
             secrets: Mutex::new(secrets),
+            load_error: Mutex::new(None),
         }
     }
+
+    fn set_fingerprint(&self, fingerprint: String) {
+        *self.fingerprint.lock().unwrap_or_else(|e| e.into_inner()) = fingerprint;
+    }
+
+    fn load_error(&self) -> Option<String> {
+        self.load_error.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set_load_error(&self, error: Option<String>) {
+        *self.load_error.lock().unwrap_or_else(|e| e.into_inner()) = error;
+    }
 }
 
 impl PersistentCache {
@@ -168,6 +530,79 @@ impl PersistentCache {
         data.get(key).cloned()
     }
 
+    /// Insert and flush synchronously under the write lock so concurrent
+    /// writes cannot reorder. Blocking; commands call it via [`run_blocking`].
+    /// A write that would serialize past `limits.max_total_bytes`, or that
+    /// `room` refuses for the size of the whole file, is undone and refused.
+    fn insert_and_flush(
+        &self,
+        path: &Path,
+        key: String,
+        value: Value,
+        limits: &cache_limits::Limits,
+        room: impl FnOnce(u64) -> Result<(), DesktopError>,
+    ) -> Result<(), DesktopError> {
+        let _write_guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = data.insert(key.clone(), value);
+        let serialized = serde_json::to_string(&Value::Object(data.clone()))
+            .map_err(|e| DesktopError::Json(format!("Failed to serialize cache: {e}")))?;
+        let total = serialized.len() as u64;
+        // The file is rewritten whole, so the whole buffer needs room.
+        if let Err(err) = limits.check_total(&key, total).and_then(|()| room(total)) {
+            match previous {
+                Some(previous) => data.insert(key, previous),
+                None => data.remove(&key),
+            };
+            return Err(err);
+        }
+        drop(data);
+        {
+            let mut dirty = self.dirty.lock().unwrap_or_else(|e| e.into_inner());
+            *dirty = true;
+        }
+        std::fs::write(path, &serialized)
+            .map_err(|e| DesktopError::io("Failed to write cache", path, e))?;
+        {
+            let mut dirty = self.dirty.lock().unwrap_or_else(|e| e.into_inner());
+            *dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Insert without writing; the exit handler flushes.
+    fn insert(&self, key: String, value: Value) {
+        self.data.lock().unwrap_or_else(|e| e.into_inner()).insert(key, value);
+        *self.dirty.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    }
+
+    /// Remove matching entries without writing; returns how many went.
+    fn remove_where(&self, mut matches: impl FnMut(&str, &Value) -> bool) -> usize {
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let before = data.len();
+        data.retain(|key, value| !matches(key, value));
+        let removed = before - data.len();
+        if removed > 0 {
+            *self.dirty.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        }
+        removed
+    }
+
+    fn count_where(&self, matches: impl Fn(&str) -> bool) -> usize {
+        let data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        data.keys().filter(|key| matches(key)).count()
+    }
+
+    fn entries_where(&self, matches: impl Fn(&str) -> bool) -> Vec<(String, Value)> {
+        let data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        data.iter().filter(|(key, _)| matches(key)).map(|(key, value)| (key.clone(), value.clone())).collect()
+    }
+
+    fn values_where(&self, matches: impl Fn(&str) -> bool) -> Vec<Value> {
+        let data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        data.iter().filter(|(key, _)| matches(key)).map(|(_, value)| value.clone()).collect()
+    }
+
     /// Flush to disk only if dirty. Returns Ok(true) if written.
     fn flush(&self, path: &Path) -> Result<bool, String> {
         let _write_guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
@@ -192,22 +627,192 @@ impl PersistentCache {
     }
 }
 
+/// Desktop-shell preferences persisted in runtime-prefs.json. The file is tiny
+/// and rarely written, so every change is flushed to disk immediately.
+struct RuntimePrefs {
+    prefs: Mutex<Map<String, Value>>,
+}
+
+impl RuntimePrefs {
+    fn load(path: &Path) -> Self {
+        let prefs = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+            .and_then(|v| v.as_object().cloned())
+            .unwrap_or_default();
+        RuntimePrefs {
+            prefs: Mutex::new(prefs),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        let prefs = self.prefs.lock().unwrap_or_else(|e| e.into_inner());
+        prefs.get(key).cloned()
+    }
+
+    fn get_bool(&self, key: &str, default: bool) -> bool {
+        self.get(key).and_then(|v| v.as_bool()).unwrap_or(default)
+    }
+}
+
+/// Timestamps of recently shown notifications, used to cap bursts.
+#[derive(Default)]
+struct NotificationState {
+    recent: Mutex<VecDeque<std::time::Instant>>,
+}
+
+/// Secret and pref keys written since the settings window opened, reported to
+/// the main window in `settings-closed` so it can reload them. Values are
+/// never included. `dirty` is the frontend's word that a field holds an
+/// unsaved edit; a close is then held for its discard/save/cancel prompt.
+#[derive(Default)]
+struct SettingsSessionState {
+    changes: Mutex<SettingsChanges>,
+    dirty: AtomicBool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct SettingsChanges {
+    secrets_changed: BTreeSet<String>,
+    prefs_changed: BTreeSet<String>,
+}
+
+impl SettingsSessionState {
+    fn record_secret(&self, key: &str) {
+        let mut changes = self.changes.lock().unwrap_or_else(|e| e.into_inner());
+        changes.secrets_changed.insert(key.to_string());
+    }
+
+    fn record_pref(&self, key: &str) {
+        let mut changes = self.changes.lock().unwrap_or_else(|e| e.into_inner());
+        changes.prefs_changed.insert(key.to_string());
+    }
+
+    fn take(&self) -> SettingsChanges {
+        self.dirty.store(false, Ordering::Relaxed);
+        std::mem::take(&mut *self.changes.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn set_dirty(&self, dirty: bool) {
+        self.dirty.store(dirty, Ordering::Relaxed);
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Relaxed)
+    }
+}
+
+/// Fixed one-second budget for `log_from_frontend`, shared by all windows.
+#[derive(Default)]
+struct FrontendLogState {
+    budget: Mutex<FrontendLogBudget>,
+}
+
+#[derive(Default)]
+struct FrontendLogBudget {
+    window_start: Option<std::time::Instant>,
+    admitted: usize,
+    dropped: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum FrontendLogAdmission {
+    /// Write the line; `dropped_before` lines were discarded since the last
+    /// admitted one and should be reported first.
+    Admitted { dropped_before: usize },
+    Dropped,
+}
+
+/// A validated `worldmonitor://<target>/<segments>?<params>` link. Only this
+/// parsed form is ever emitted to the webview, never the raw string.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct DeepLink {
+    target: String,
+    segments: Vec<String>,
+    params: BTreeMap<String, String>,
+}
+
+/// Deep links that arrive before the event loop is ready (initial argv) are
+/// queued here and flushed on `RunEvent::Ready`.
+#[derive(Default)]
+struct DeepLinkState {
+    ready: Mutex<bool>,
+    pending: Mutex<Vec<DeepLink>>,
+}
+
+#[derive(Clone, Default, Serialize)]
+struct StartupStatus {
+    stage: String,
+    error: Option<String>,
+}
+
+/// Latest startup stage, mirrored to the splash window. Events emitted before
+/// the splash script loads are lost, so it reads this once on load.
+#[derive(Default)]
+struct StartupState {
+    status: Mutex<StartupStatus>,
+    /// Bumped each time the splash timeout is armed; only the latest timer
+    /// may reveal the main window.
+    splash_timer: AtomicU64,
+}
+
+/// How the main window appears once startup finishes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StartupVisibility {
+    Shown,
+    Minimized,
+    /// Hidden; the tray icon brings it up.
+    Tray,
+}
+
+impl StartupVisibility {
+    fn as_str(self) -> &'static str {
+        match self {
+            StartupVisibility::Shown => "shown",
+            StartupVisibility::Minimized => "minimized",
+            StartupVisibility::Tray => "tray",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct UpdateCheckResult {
+    current_version: String,
+    latest_version: Option<String>,
+    notes_url: Option<String>,
+    update_available: bool,
+    /// False when the manifest couldn't be fetched or parsed ("couldn't check").
+    checked: bool,
+}
+
 #[derive(Serialize)]
 struct DesktopRuntimeInfo {
     os: String,
     arch: String,
     local_api_port: Option<u16>,
-}
-
-fn save_vault(cache: &HashMap<String, String>) -> Result<(), String> {
-    let json =
-        serde_json::to_string(cache).map_err(|e| format!("Failed to serialize vault: {e}"))?;
-    let entry = Entry::new(KEYRING_SERVICE, "secrets-vault")
-        .map_err(|e| format!("Keyring init failed: {e}"))?;
-    entry
-        .set_password(&json)
-        .map_err(|e| format!("Failed to write vault: {e}"))?;
-    Ok(())
+    /// When the last recorded panic happened, so the UI can offer to export
+    /// diagnostics after an unclean exit.
+    last_crash_at: Option<String>,
+    safe_mode: SafeModeStatus,
+    /// Sent upstream by native fetches and the sidecar.
+    user_agent: String,
+    /// Spellcheck and autofill prefs and what this platform honours.
+    text_input: webview_text::TextInputSettings,
+    /// The `theme` pref and whether it currently means dark or light.
+    theme: theme::ThemeInfo,
+    /// `flatpak`, `appimage`, `deb`, or `native` for everything else.
+    packaging: packaging::Packaging,
+    /// The app version changed upward since the last launch, so the UI can
+    /// show release notes once.
+    upgraded_this_run: bool,
+    /// The version upgraded from; `None` when that predates the record.
+    upgraded_from: Option<String>,
+    /// Prefs, cache or logs went to a temporary session directory because
+    /// the usual one was unusable; see `storage-degraded`.
+    degraded_storage: bool,
+    /// Versions, locale and hardware, collected once at startup.
+    #[serde(flatten)]
+    host: StaticRuntimeInfo,
 }
 
 fn generate_local_token() -> String {
@@ -238,42 +843,117 @@ fn generate_local_token() -> String {
     buf.iter().map(|b| format!("{b:02x}")).collect()
 }
 
-fn require_trusted_window(label: &str) -> Result<(), String> {
-    if TRUSTED_WINDOWS.contains(&label) {
+fn require_trusted_window(label: &str) -> Result<(), DesktopError> {
+    if TRUSTED_WINDOWS.contains(&label) || panel_windows::panel_id_from_label(label).is_some() {
         Ok(())
     } else {
-        Err(format!("Command not allowed from window '{label}'"))
+        Err(DesktopError::UntrustedWindow { label: label.to_string() })
     }
 }
 
-#[tauri::command]
-fn get_local_api_token(webview: Webview, state: tauri::State<'_, LocalApiState>) -> Result<String, String> {
-    require_trusted_window(webview.label())?;
-    let token = state
-        .token
-        .lock()
-        .map_err(|_| "Failed to lock local API token".to_string())?;
-    token
-        .clone()
-        .ok_or_else(|| "Token not generated".to_string())
+fn require_supported_secret_key(key: &str) -> Result<(), DesktopError> {
+    if SUPPORTED_SECRET_KEYS.contains(&key) {
+        Ok(())
+    } else {
+        Err(DesktopError::UnsupportedSecretKey { key: key.to_string() })
+    }
 }
 
-#[tauri::command]
-fn get_desktop_runtime_info(state: tauri::State<'_, LocalApiState>) -> DesktopRuntimeInfo {
-    let port = state.port.lock().ok().and_then(|g| *g);
+fn desktop_runtime_info(app: &AppHandle) -> DesktopRuntimeInfo {
+    let port = app
+        .try_state::<LocalApiState>()
+        .and_then(|state| state.port.lock().ok().and_then(|g| *g));
     DesktopRuntimeInfo {
         os: env::consts::OS.to_string(),
         arch: env::consts::ARCH.to_string(),
         local_api_port: port,
+        last_crash_at: crash::last_crash_at(app),
+        safe_mode: app
+            .try_state::<SafeModeState>()
+            .map(|state| state.status.clone())
+            .unwrap_or_default(),
+        user_agent: user_agent::effective(app),
+        text_input: webview_text::settings(app),
+        theme: theme::info(app),
+        packaging: packaging::current(),
+        upgraded_this_run: version_history::upgraded_this_run(app),
+        upgraded_from: version_history::upgraded_from(app),
+        degraded_storage: data_dir::degraded().is_some(),
+        host: app
+            .try_state::<StaticRuntimeInfo>()
+            .map(|info| info.inner().clone())
+            .unwrap_or_default(),
+    }
+}
+
+#[tauri::command]
+fn get_desktop_runtime_info(app: AppHandle) -> DesktopRuntimeInfo {
+    desktop_runtime_info(&app)
+}
+
+/// Base URL, token and readiness of the sidecar in one call. Changes after
+/// a restart on another port arrive as `local-api-connection-changed`.
+#[tauri::command]
+fn get_local_api_connection(webview: Webview, app: AppHandle) -> Result<LocalApiConnection, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(local_api_connection(&app))
+}
+
+/// Resolve with the connection once the sidecar is ready, so the first data
+/// load doesn't race its startup. Immediate when it already is; after
+/// `timeout_ms` (at most a minute) fails with `sidecar_timeout` and the
+/// lifecycle state it was stuck in.
+#[tauri::command]
+async fn wait_for_local_api(
+    webview: Webview,
+    app: AppHandle,
+    timeout_ms: Option<u64>,
+) -> Result<LocalApiConnection, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let timeout = timeout_ms.map_or(LOCAL_API_WAIT_MAX, |ms| std::time::Duration::from_millis(ms).min(LOCAL_API_WAIT_MAX));
+    // A receiver of its own, so no lock on the state is held while waiting.
+    let mut lifecycle = app.state::<LocalApiState>().lifecycle.subscribe();
+    let reached = tokio::time::timeout(timeout, async {
+        lifecycle.wait_for(|state| *state == LocalApiLifecycle::Ready).await.is_ok()
+    })
+    .await
+    .unwrap_or(false);
+    if reached {
+        return Ok(local_api_connection(&app));
     }
+    let state = *app.state::<LocalApiState>().lifecycle.borrow();
+    Err(DesktopError::SidecarTimeout {
+        state: state.as_str(),
+        message: format!(
+            "The local API was not ready after {} ms (state: {})",
+            timeout.as_millis(),
+            state.as_str()
+        ),
+    })
+}
+
+/// Whether the sidecar is running and, if so, whether it has work a quit
+/// would interrupt; the forensics worker is reported alongside.
+#[tauri::command]
+async fn get_local_api_status(webview: Webview, app: AppHandle) -> Result<quit_guard::LocalApiStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(quit_guard::local_api_status(&app).await)
+}
+
+/// Quit immediately, even while the sidecar reports busy work.
+#[tauri::command]
+fn force_quit(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    quit_guard::force_quit(&app);
+    Ok(())
 }
 
+/// Answer to `quit-blocked`: quit once the sidecar is idle (at most 30 s).
 #[tauri::command]
-fn get_local_api_port(webview: Webview, state: tauri::State<'_, LocalApiState>) -> Result<u16, String> {
+async fn wait_then_quit(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
     require_trusted_window(webview.label())?;
-    state.port.lock()
-        .map_err(|_| "Failed to lock port state".to_string())?
-        .ok_or_else(|| "Port not yet assigned".to_string())
+    quit_guard::quit_when_idle(&app).await;
+    Ok(())
 }
 
 #[tauri::command]
@@ -289,11 +969,9 @@ fn get_secret(
     webview: Webview,
     key: String,
     cache: tauri::State<'_, SecretsCache>,
-) -> Result<Option<String>, String> {
+) -> Result<Option<String>, DesktopError> {
     require_trusted_window(webview.label())?;
-    if !SUPPORTED_SECRET_KEYS.contains(&key.as_str()) {
-        return Err(format!("Unsupported secret key: {key}"));
-    }
+    require_supported_secret_key(&key)?;
     let secrets = cache
         .secrets
         .lock()
@@ -301,8 +979,16 @@ fn get_secret(
     Ok(secrets.get(&key).cloned())
 }
 
+/// Which keys are set, masked, and the vault revision the answer reflects;
+/// `secrets-changed` carries the same revision for each later change.
+#[tauri::command]
+fn get_secrets_summary(webview: Webview, app: AppHandle) -> Result<vault_events::SecretsSummary, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(vault_events::summary(&app))
+}
+
 #[tauri::command]
-fn get_all_secrets(webview: Webview, cache: tauri::State<'_, SecretsCache>) -> Result<HashMap<String, String>, String> {
+fn get_all_secrets(webview: Webview, cache: tauri::State<'_, SecretsCache>) -> Result<HashMap<String, String>, DesktopError> {
     require_trusted_window(webview.label())?;
     Ok(cache
         .secrets
@@ -311,16 +997,15 @@ fn get_all_secrets(webview: Webview, cache: tauri::State<'_, SecretsCache>) -> R
         .clone())
 }
 
-#[tauri::command]
-fn set_secret(
-    webview: Webview,
-    key: String,
-    value: String,
-    cache: tauri::State<'_, SecretsCache>,
-) -> Result<(), String> {
-    require_trusted_window(webview.label())?;
-    if !SUPPORTED_SECRET_KEYS.contains(&key.as_str()) {
-        return Err(format!("Unsupported secret key: {key}"));
+/// Persist the vault with `key` set (or removed when `value` is `None`), then
+/// commit to the cache. Blocking: the keychain write can prompt the user.
+fn update_vault(app: &AppHandle, key: String, value: Option<String>) -> Result<(), DesktopError> {
+    let cache = app.state::<SecretsCache>();
+    if let Some(err) = cache.load_error() {
+        log_event(app, "WARN", "vault_save_refused", &[("reason", "unread")]);
+        return Err(DesktopError::KeyringUnavailable(format!(
+            "The vault could not be read at startup ({err}); reload secrets before saving"
+        )));
     }
     let mut secrets = cache
         .secrets
@@ -330,58 +1015,123 @@ fn set_secret(
             guard.clear();
             guard
         });
-    let trimmed = value.trim().to_string();
     // Build proposed state, persist first, then commit to cache
     let mut proposed = secrets.clone();
-    if trimmed.is_empty() {
-        proposed.remove(&key);
-    } else {
-        proposed.insert(key, trimmed);
+    match value {
+        Some(value) => proposed.insert(key, value),
+        None => proposed.remove(&key),
+    };
+    // Don't clobber an edit made in Keychain Access or synced from elsewhere.
+    let keychain = vault_sync::read_vault(&*cache.store, &SUPPORTED_SECRET_KEYS)?;
+    let loaded = cache.fingerprint.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Err(err) = vault_sync::check_conflict(&loaded, &keychain, &secrets) {
+        log_event(app, "WARN", "vault_save_refused", &[("reason", "changed_externally")]);
+        return Err(err);
     }
-    save_vault(&proposed)?;
+    vault_sync::save_vault(&*cache.store, &proposed)?;
+    cache.set_fingerprint(vault_sync::fingerprint(&proposed));
+    app.state::<LogRedaction>().rebuild(&proposed);
+    vault_events::notify(app, &secrets, &proposed);
     *secrets = proposed;
     Ok(())
 }
 
 #[tauri::command]
-fn delete_secret(webview: Webview, key: String, cache: tauri::State<'_, SecretsCache>) -> Result<(), String> {
+async fn set_secret(webview: Webview, app: AppHandle, key: String, value: String) -> Result<(), DesktopError> {
     require_trusted_window(webview.label())?;
-    if !SUPPORTED_SECRET_KEYS.contains(&key.as_str()) {
-        return Err(format!("Unsupported secret key: {key}"));
+    require_supported_secret_key(&key)?;
+    let trimmed = value.trim().to_string();
+    if !trimmed.is_empty() {
+        trusted_hosts::validate_url_secret(&trusted_hosts::from_prefs(&app), &key, &trimmed)?;
     }
-    let mut secrets = cache
-        .secrets
-        .lock()
-        .unwrap_or_else(|e| {
-            let mut guard = e.into_inner();
-            guard.clear();
-            guard
-        });
-    let mut proposed = secrets.clone();
-    proposed.remove(&key);
-    save_vault(&proposed)?;
-    *secrets = proposed;
+    let value = (!trimmed.is_empty()).then_some(trimmed);
+    let recorder = app.clone();
+    let changed_key = key.clone();
+    run_blocking(move || update_vault(&app, key, value)).await?;
+    recorder.state::<SettingsSessionState>().record_secret(&changed_key);
     Ok(())
 }
 
-fn cache_file_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?;
-    std::fs::create_dir_all(&dir)
-        .map_err(|e| format!("Failed to create app data directory {}: {e}", dir.display()))?;
-    Ok(dir.join("persistent-cache.json"))
+#[tauri::command]
+async fn delete_secret(webview: Webview, app: AppHandle, key: String) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    require_supported_secret_key(&key)?;
+    let recorder = app.clone();
+    let changed_key = key.clone();
+    run_blocking(move || update_vault(&app, key, None)).await?;
+    recorder.state::<SettingsSessionState>().record_secret(&changed_key);
+    Ok(())
+}
+
+/// Replace the cache with the vault as it is in the keychain now, after a
+/// `vault_changed_externally` error or `secrets-externally-changed` event.
+/// Returns the key names now present.
+#[tauri::command]
+async fn reload_secrets_from_keychain(webview: Webview, app: AppHandle) -> Result<Vec<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || {
+        let cache = app.state::<SecretsCache>();
+        let keychain = vault_sync::read_vault(&*cache.store, &SUPPORTED_SECRET_KEYS)?;
+        let mut secrets = cache.secrets.lock().unwrap_or_else(|e| e.into_inner());
+        let session = app.state::<SettingsSessionState>();
+        for key in secrets.keys().chain(keychain.keys()) {
+            if secrets.get(key) != keychain.get(key) {
+                session.record_secret(key);
+            }
+        }
+        cache.set_fingerprint(vault_sync::fingerprint(&keychain));
+        cache.set_load_error(None);
+        app.state::<LogRedaction>().rebuild(&keychain);
+        let mut keys: Vec<String> = keychain.keys().cloned().collect();
+        keys.sort();
+        log_event(&app, "INFO", "vault_reloaded", &[("keys", &keys.len().to_string())]);
+        vault_events::notify(&app, &secrets, &keychain);
+        *secrets = keychain;
+        Ok(keys)
+    })
+    .await
+}
+
+/// The directory is created by `data_dir::prepare_storage` at startup.
+fn cache_file_path(app: &AppHandle) -> Result<PathBuf, DesktopError> {
+    let dir = data_dir::resolve_cache_dir(app).map_err(DesktopError::Internal)?;
+    Ok(dir.join(PERSISTENT_CACHE_FILE))
+}
+
+/// Run blocking fs/keychain work on the blocking pool so the IPC thread
+/// keeps serving other commands. Locks taken inside `task` are released
+/// before it returns; none are held across an await.
+async fn run_blocking<T, F>(task: F) -> Result<T, DesktopError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, DesktopError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| DesktopError::Internal(format!("Background task failed: {e}")))?
 }
 
 #[tauri::command]
-fn read_cache_entry(webview: Webview, cache: tauri::State<'_, PersistentCache>, key: String) -> Result<Option<Value>, String> {
+async fn read_cache_entry(webview: Webview, app: AppHandle, key: String) -> Result<Option<Value>, DesktopError> {
     require_trusted_window(webview.label())?;
-    Ok(cache.get(&key))
+    // Cloning a large entry out of the map is the expensive part.
+    run_blocking(move || Ok(app.state::<PersistentCache>().get(&key).map(cache_entry::into_value))).await
 }
 
+/// The value with when it was written (`null` for entries from before
+/// write times were kept), its age, and the caller's `sourceVersion`.
 #[tauri::command]
-fn delete_cache_entry(webview: Webview, cache: tauri::State<'_, PersistentCache>, key: String) -> Result<(), String> {
+async fn read_cache_entry_with_meta(
+    webview: Webview,
+    app: AppHandle,
+    key: String,
+) -> Result<Option<cache_entry::EntryWithMeta>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || Ok(app.state::<PersistentCache>().get(&key).map(cache_entry::read_with_meta))).await
+}
+
+#[tauri::command]
+fn delete_cache_entry(webview: Webview, cache: tauri::State<'_, PersistentCache>, key: String) -> Result<(), DesktopError> {
     require_trusted_window(webview.label())?;
     {
         let mut data = cache.data.lock().unwrap_or_else(|e| e.into_inner());
@@ -396,314 +1146,2611 @@ fn delete_cache_entry(webview: Webview, cache: tauri::State<'_, PersistentCache>
 }
 
 #[tauri::command]
-fn write_cache_entry(webview: Webview, app: AppHandle, cache: tauri::State<'_, PersistentCache>, key: String, value: String) -> Result<(), String> {
+async fn write_cache_entry(
+    webview: Webview,
+    app: AppHandle,
+    key: String,
+    value: String,
+    source_version: Option<String>,
+) -> Result<(), DesktopError> {
     require_trusted_window(webview.label())?;
-    let parsed_value: Value = serde_json::from_str(&value)
-        .map_err(|e| format!("Invalid cache payload JSON: {e}"))?;
-    let _write_guard = cache.write_lock.lock().unwrap_or_else(|e| e.into_inner());
-    {
-        let mut data = cache.data.lock().unwrap_or_else(|e| e.into_inner());
-        data.insert(key, parsed_value);
-    }
-    {
-        let mut dirty = cache.dirty.lock().unwrap_or_else(|e| e.into_inner());
-        *dirty = true;
-    }
+    run_blocking(move || {
+        cache_limits::check_key(&key)?;
+        if let Some(version) = &source_version {
+            cache_entry::validate_source_version(version)?;
+        }
+        let limits = cache_limits::Limits::from_prefs(&app);
+        let bytes = value.len() as u64;
+        let written = limits.check_entry(&key, bytes).and_then(|()| {
+            // Parsing a multi-MB payload is as costly as writing it.
+            let parsed_value: Value = serde_json::from_str(&value)
+                .map_err(|e| DesktopError::InvalidArgument(format!("Invalid cache payload JSON: {e}")))?;
+            let path = cache_file_path(&app)?;
+            let stored = cache_entry::stamp(parsed_value, source_version);
+            app.state::<PersistentCache>().insert_and_flush(&path, key.clone(), stored, &limits, |total| {
+                disk_space::ensure_space(&app, &path, total)
+            })
+        });
+        if let Err(err) = &written {
+            cache_limits::log_refusal(&app, &key, bytes, err);
+        }
+        written
+    })
+    .await
+}
 
-    // Flush synchronously under write lock so concurrent writes cannot reorder.
-    let path = cache_file_path(&app)?;
-    let data = cache.data.lock().unwrap_or_else(|e| e.into_inner());
-    let serialized = serde_json::to_string(&Value::Object(data.clone()))
-        .map_err(|e| format!("Failed to serialize cache: {e}"))?;
-    drop(data);
-    std::fs::write(&path, &serialized)
-        .map_err(|e| format!("Failed to write cache {}: {e}", path.display()))?;
-    {
-        let mut dirty = cache.dirty.lock().unwrap_or_else(|e| e.into_inner());
-        *dirty = false;
-    }
-    Ok(())
+fn runtime_prefs_path(app: &AppHandle) -> Result<PathBuf, DesktopError> {
+    let dir = data_dir::resolve_data_dir(app).map_err(DesktopError::Internal)?;
+    Ok(dir.join(RUNTIME_PREFS_FILE))
 }
 
-fn logs_dir_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_log_dir()
-        .map_err(|e| format!("Failed to resolve app log dir: {e}"))?;
-    fs::create_dir_all(&dir)
-        .map_err(|e| format!("Failed to create app log dir {}: {e}", dir.display()))?;
-    Ok(dir)
+/// Check a pref value against the type its consumers expect. `null` is always
+/// accepted and resets the pref to its built-in default.
+fn validate_runtime_pref(key: &str, value: &Value) -> Result<(), String> {
+    if value.is_null() {
+        return Ok(());
+    }
+    match key {
+        PREF_KEEP_SETTINGS_ABOVE_MAIN
+        | PREF_SETTINGS_MODAL
+        | PREF_AUTO_CHECK_UPDATES
+        | PREF_INCLUDE_PRERELEASES
+        | PREF_START_MINIMIZED
+        | PREF_START_IN_TRAY
+        | PREF_CLOSE_TO_TRAY
+        | PREF_NOTIFICATIONS_MUTED
+        | PREF_SUPPRESS_NOTIFICATIONS_WHEN_FOCUSED
+        | resources::PREF_RESOURCE_SAMPLING
+        | vault_sync::PREF_VAULT_EXTERNAL_CHANGE_CHECK
+        | content_protection::PREF_PROTECT_SETTINGS_WINDOW
+        | webview_text::PREF_SPELLCHECK_ENABLED
+        | webview_text::PREF_SUPPRESS_AUTOFILL
+        | onboarding::PREF_ONBOARDING_COMPLETE
+        | polling::PREF_POLLING_PAUSED
+        | forensics_worker::PREF_SEPARATE_FORENSICS_WORKER => expect_bool_pref(key, value),
+        resources::PREF_SIDECAR_RSS_WARN_MB => resources::validate_sidecar_rss_warn_mb(value),
+        PREF_ALLOWED_URL_SCHEMES => validate_allowed_url_schemes(value),
+        extra_ca::PREF_EXTRA_CA_CERTIFICATES => extra_ca::validate_pref(value),
+        native_fetch::PREF_NATIVE_FETCH_MAX_BODY_MB => native_fetch::validate_max_body_mb(value),
+        offline_cache::PREF_API_CACHE_MAX_STALE_HOURS => offline_cache::validate_max_stale_hours(value),
+        log_retention::PREF_LOG_MAX_TOTAL_MB => log_retention::validate_max_total_mb(value),
+        log_retention::PREF_LOG_MAX_AGE_DAYS => log_retention::validate_max_age_days(value),
+        logging::PREF_LOG_LEVEL => logging::validate_log_level(value),
+        data_sources::PREF_DATA_SOURCES => data_sources::validate_pref(value),
+        trusted_hosts::PREF_TRUSTED_LOCAL_HOSTS => trusted_hosts::validate_pref(value),
+        quiet_hours::PREF_QUIET_HOURS => quiet_hours::validate_pref(value),
+        network_profile::PREF_METERED_BEHAVIOR => network_profile::validate_behavior(value),
+        display_scale::PREF_FORCE_SCALE_FACTOR => display_scale::validate_pref(value),
+        webview_text::PREF_SPELLCHECK_LANGUAGE => webview_text::validate_language_pref(value),
+        user_agent::PREF_USER_AGENT_SUFFIX => user_agent::validate_suffix(value),
+        i18n::PREF_UI_LANGUAGE => i18n::validate_pref(value),
+        disk_space::PREF_MIN_FREE_DISK_MB => disk_space::validate_min_free_mb(value),
+        api_usage::PREF_API_QUOTAS => api_usage::validate_pref(value),
+        cache_limits::PREF_CACHE_MAX_ENTRY_MB => cache_limits::validate_max_entry_mb(value),
+        cache_limits::PREF_CACHE_MAX_TOTAL_MB => cache_limits::validate_max_total_mb(value),
+        clock_skew::PREF_CLOCK_SKEW_WARN_SECS => clock_skew::validate_warn_secs(value),
+        theme::PREF_THEME => theme::validate_pref(value),
+        sidecar_options::PREF_SIDECAR_NODE_ARGS => {
+            sidecar_options::validate_node_args(value, cfg!(debug_assertions))
+        }
+        sidecar_options::PREF_SIDECAR_EXTRA_ENV => {
+            sidecar_options::validate_extra_env(value, &SUPPORTED_SECRET_KEYS, cfg!(debug_assertions))
+        }
+        logging::PREF_LOG_FORMAT => match value.as_str() {
+            Some(format) if logging::LOG_FORMATS.contains(&format) => Ok(()),
+            _ => Err(format!(
+                "Runtime pref {key} must be one of: {}",
+                logging::LOG_FORMATS.join(", ")
+            )),
+        },
+        PREF_NOTIFICATION_CATEGORIES => {
+            let map = value
+                .as_object()
+                .ok_or_else(|| format!("Runtime pref {key} must be an object"))?;
+            for (category, enabled) in map {
+                if !NOTIFICATION_CATEGORIES.contains(&category.as_str()) {
+                    return Err(format!("Unknown notification category: {category}"));
+                }
+                expect_bool_pref(key, enabled)?;
+            }
+            Ok(())
+        }
+        _ => Err(format!("Unsupported runtime pref: {key}")),
+    }
 }
 
-fn sidecar_log_path(app: &AppHandle) -> Result<PathBuf, String> {
-    Ok(logs_dir_path(app)?.join(LOCAL_API_LOG_FILE))
+fn expect_bool_pref(key: &str, value: &Value) -> Result<(), String> {
+    if value.is_boolean() {
+        Ok(())
+    } else {
+        Err(format!("Runtime pref {key} must be a boolean"))
+    }
 }
 
-fn desktop_log_path(app: &AppHandle) -> Result<PathBuf, String> {
-    Ok(logs_dir_path(app)?.join(DESKTOP_LOG_FILE))
+#[tauri::command]
+fn get_runtime_prefs(webview: Webview, prefs: tauri::State<'_, RuntimePrefs>) -> Result<Map<String, Value>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(prefs.prefs.lock().unwrap_or_else(|e| e.into_inner()).clone())
 }
 
-fn append_desktop_log(app: &AppHandle, level: &str, message: &str) {
-    let Ok(path) = desktop_log_path(app) else {
-        return;
-    };
+/// Persist a single pref (or remove it when `value` is null), then commit it
+/// to memory. Shared by the command and by shell-internal bookkeeping keys.
+fn store_runtime_pref(app: &AppHandle, key: &str, value: Value) -> Result<(), DesktopError> {
+    let prefs = app.state::<RuntimePrefs>();
+    let mut current = prefs.prefs.lock().unwrap_or_else(|e| e.into_inner());
+    // Build proposed state, persist first, then commit to memory
+    let mut proposed = current.clone();
+    if value.is_null() {
+        proposed.remove(key);
+    } else {
+        proposed.insert(key.to_string(), value);
+    }
+    let path = runtime_prefs_path(app)?;
+    let serialized = serde_json::to_string_pretty(&Value::Object(proposed.clone()))
+        .map_err(|e| DesktopError::Json(format!("Failed to serialize runtime prefs: {e}")))?;
+    std::fs::write(&path, serialized)
+        .map_err(|e| DesktopError::io("Failed to write runtime prefs", &path, e))?;
+    *current = proposed;
+    Ok(())
+}
 
-    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) else {
-        return;
-    };
+#[tauri::command]
+async fn set_runtime_pref(webview: Webview, app: AppHandle, key: String, value: Value) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    validate_runtime_pref(&key, &value).map_err(DesktopError::InvalidArgument)?;
+    let recorder = app.clone();
+    let changed_key = key.clone();
+    run_blocking(move || store_runtime_pref(&app, &key, value)).await?;
+    recorder.state::<SettingsSessionState>().record_pref(&changed_key);
+    apply_runtime_pref_change(&recorder, &changed_key);
+    Ok(())
+}
 
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let _ = writeln!(file, "[{timestamp}][{level}] {message}");
+/// Side effects of a pref that can't wait for the next launch.
+fn apply_runtime_pref_change(app: &AppHandle, key: &str) {
+    match key {
+        sidecar_options::PREF_SIDECAR_NODE_ARGS
+        | sidecar_options::PREF_SIDECAR_EXTRA_ENV
+        | user_agent::PREF_USER_AGENT_SUFFIX => {
+            let app = app.clone();
+            let key = key.to_string();
+            std::thread::spawn(move || restart_local_api_if_running(&app, &key));
+        }
+        data_sources::PREF_DATA_SOURCES => data_sources::publish(app),
+        trusted_hosts::PREF_TRUSTED_LOCAL_HOSTS => trusted_hosts::publish(app),
+        polling::PREF_POLLING_PAUSED => polling::apply(app),
+        forensics_worker::PREF_SEPARATE_FORENSICS_WORKER => forensics_worker::apply(app),
+        network_profile::PREF_METERED_BEHAVIOR => network_profile::apply(app),
+        quiet_hours::PREF_QUIET_HOURS => {
+            quiet_hours::refresh(app);
+        }
+        content_protection::PREF_PROTECT_SETTINGS_WINDOW => {
+            if let Err(err) = content_protection::apply(app, "settings") {
+                log_event(app, "WARN", "content_protection_failed", &[("error", &err.to_string())]);
+            }
+        }
+        i18n::PREF_UI_LANGUAGE => rebuild_app_menu(app),
+        theme::PREF_THEME => theme::apply(app, true),
+        webview_text::PREF_SPELLCHECK_ENABLED
+        | webview_text::PREF_SPELLCHECK_LANGUAGE
+        | webview_text::PREF_SUPPRESS_AUTOFILL => {
+            let app = app.clone();
+            std::thread::spawn(move || webview_text::apply_all(&app));
+        }
+        _ => {}
+    }
 }
 
-fn open_in_shell(arg: &str) -> Result<(), String> {
-    #[cfg(target_os = "macos")]
-    let mut command = {
-        let mut cmd = Command::new("open");
-        cmd.arg(arg);
-        cmd
-    };
+/// Base URL and token of the running sidecar; `None` when it isn't up.
+fn local_api_endpoint(app: &AppHandle) -> Option<(String, String)> {
+    let state = app.try_state::<LocalApiState>()?;
+    let port = (*state.port.lock().unwrap_or_else(|e| e.into_inner()))?;
+    let token = state.token.lock().unwrap_or_else(|e| e.into_inner()).clone()?;
+    Some((local_api_base_url(app, port), token))
+}
 
-    #[cfg(target_os = "windows")]
-    let mut command = {
-        let mut cmd = Command::new("explorer");
-        cmd.arg(arg);
-        cmd
-    };
+/// The sidecar is on loopback, so a system proxy must not see the token.
+fn local_api_client(timeout: std::time::Duration) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .no_proxy()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())
+}
 
-    #[cfg(all(unix, not(target_os = "macos")))]
-    let mut command = {
-        let mut cmd = Command::new("xdg-open");
-        cmd.arg(arg);
-        cmd.env_remove("LD_LIBRARY_PATH");
-        cmd.env_remove("LD_PRELOAD");
-        cmd
+/// POST `body` to an authenticated sidecar endpoint. `Ok` without a request
+/// when the sidecar isn't running: it reads the same state at its next start.
+async fn post_to_local_api(app: &AppHandle, path: &str, body: Value) -> Result<(), String> {
+    let Some((base_url, token)) = local_api_endpoint(app) else {
+        return Ok(());
     };
+    let response = local_api_client(std::time::Duration::from_secs(5))?
+        .post(format!("{base_url}{path}"))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("sidecar answered HTTP {}", response.status().as_u16()));
+    }
+    Ok(())
+}
 
-    command
-        .spawn()
-        .map(|_| ())
-        .map_err(|e| format!("Failed to open {}: {e}", arg))
+/// GET an authenticated sidecar endpoint and parse its JSON. `None` when the
+/// sidecar isn't running or didn't answer with a success within `timeout`.
+async fn get_from_local_api<T: serde::de::DeserializeOwned>(
+    app: &AppHandle,
+    path: &str,
+    timeout: std::time::Duration,
+) -> Option<T> {
+    let (base_url, token) = local_api_endpoint(app)?;
+    let response = local_api_client(timeout)
+        .ok()?
+        .get(format!("{base_url}{path}"))
+        .bearer_auth(token)
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json::<T>().await.ok()
 }
 
-fn open_path_in_shell(path: &Path) -> Result<(), String> {
-    open_in_shell(&path.to_string_lossy())
+/// Restart the sidecar so it picks up changed launch settings; a stopped
+/// sidecar is left alone.
+fn restart_local_api_if_running(app: &AppHandle, reason: &str) {
+    let running = app
+        .state::<LocalApiState>()
+        .child
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some();
+    if !running {
+        return;
+    }
+    log_event(app, "INFO", "sidecar_restarting", &[("reason", reason)]);
+    stop_local_api(app);
+    if let Err(err) = start_local_api(app) {
+        log_event(app, "ERROR", "sidecar_restart_failed", &[("error", &err)]);
+    }
 }
 
+/// Every known data source and whether it is enabled.
 #[tauri::command]
-fn open_url(url: String) -> Result<(), String> {
-    let parsed = Url::parse(&url).map_err(|_| "Invalid URL".to_string())?;
+fn get_data_source_toggles(webview: Webview, app: AppHandle) -> Result<BTreeMap<String, bool>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(data_sources::toggles(data_sources::stored(&app).as_ref()))
+}
 
-    match parsed.scheme() {
-        "https" => open_in_shell(parsed.as_str()),
-        "http" => match parsed.host_str() {
-            Some("localhost") | Some("127.0.0.1") => open_in_shell(parsed.as_str()),
-            _ => Err("Only https:// URLs are allowed (http:// only for localhost)".to_string()),
-        },
-        _ => Err("Only https:// URLs are allowed (http:// only for localhost)".to_string()),
+#[tauri::command]
+async fn set_data_source_toggle(webview: Webview, app: AppHandle, id: String, enabled: bool) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    if !data_sources::is_known(&id) {
+        return Err(DesktopError::InvalidArgument(format!("Unknown data source: {id}")));
     }
+    let persisted = app.clone();
+    run_blocking(move || {
+        let value = data_sources::with_toggle(data_sources::stored(&persisted), &id, enabled);
+        store_runtime_pref(&persisted, data_sources::PREF_DATA_SOURCES, value)
+    })
+    .await?;
+    app.state::<SettingsSessionState>().record_pref(data_sources::PREF_DATA_SOURCES);
+    apply_runtime_pref_change(&app, data_sources::PREF_DATA_SOURCES);
+    Ok(())
 }
 
-fn open_logs_folder_impl(app: &AppHandle) -> Result<PathBuf, String> {
-    let dir = logs_dir_path(app)?;
-    open_path_in_shell(&dir)?;
-    Ok(dir)
+/// `trustedLocalHosts` in normal form.
+#[tauri::command]
+fn get_trusted_local_hosts(webview: Webview, app: AppHandle) -> Result<Vec<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(trusted_hosts::patterns(&app))
 }
 
-fn open_sidecar_log_impl(app: &AppHandle) -> Result<PathBuf, String> {
-    let log_path = sidecar_log_path(app)?;
-    if !log_path.exists() {
-        File::create(&log_path)
-            .map_err(|e| format!("Failed to create sidecar log {}: {e}", log_path.display()))?;
-    }
-    open_path_in_shell(&log_path)?;
-    Ok(log_path)
+/// Replace the list; every pattern must be valid. Returns it as stored.
+#[tauri::command]
+async fn set_trusted_local_hosts(webview: Webview, app: AppHandle, hosts: Vec<String>) -> Result<Vec<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let hosts = trusted_hosts::normalize(&hosts)?;
+    let persisted = app.clone();
+    let stored = Value::from(hosts.clone());
+    run_blocking(move || store_runtime_pref(&persisted, trusted_hosts::PREF_TRUSTED_LOCAL_HOSTS, stored)).await?;
+    app.state::<SettingsSessionState>().record_pref(trusted_hosts::PREF_TRUSTED_LOCAL_HOSTS);
+    apply_runtime_pref_change(&app, trusted_hosts::PREF_TRUSTED_LOCAL_HOSTS);
+    Ok(hosts)
 }
 
 #[tauri::command]
-fn open_logs_folder(app: AppHandle) -> Result<String, String> {
-    open_logs_folder_impl(&app).map(|path| path.display().to_string())
+fn get_quiet_hours(webview: Webview, app: AppHandle) -> Result<quiet_hours::QuietHoursConfig, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(quiet_hours::config(&app))
 }
 
 #[tauri::command]
-fn open_sidecar_log_file(app: AppHandle) -> Result<String, String> {
-    open_sidecar_log_impl(&app).map(|path| path.display().to_string())
+async fn set_quiet_hours(
+    webview: Webview,
+    app: AppHandle,
+    config: quiet_hours::QuietHoursConfig,
+) -> Result<quiet_hours::QuietHoursStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    config.window().map_err(DesktopError::InvalidArgument)?;
+    let value = serde_json::to_value(&config)?;
+    let persisted = app.clone();
+    run_blocking(move || store_runtime_pref(&persisted, quiet_hours::PREF_QUIET_HOURS, value)).await?;
+    app.state::<SettingsSessionState>().record_pref(quiet_hours::PREF_QUIET_HOURS);
+    apply_runtime_pref_change(&app, quiet_hours::PREF_QUIET_HOURS);
+    Ok(quiet_hours::status(&app))
 }
 
+/// Whether quiet hours are in effect now and when that next changes.
 #[tauri::command]
-async fn open_settings_window_command(app: AppHandle) -> Result<(), String> {
-    open_settings_window(&app)
+fn get_quiet_hours_status(webview: Webview, app: AppHandle) -> Result<quiet_hours::QuietHoursStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(quiet_hours::status(&app))
 }
 
+/// Keep the window `label` out of screen captures. Remembered for the
+/// session, so a settings or panel window reopens the way it was left.
 #[tauri::command]
-fn close_settings_window(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("settings") {
-        window
-            .close()
-            .map_err(|e| format!("Failed to close settings window: {e}"))?;
-    }
-    Ok(())
+fn set_content_protected(
+    webview: Webview,
+    app: AppHandle,
+    label: String,
+    enabled: bool,
+) -> Result<content_protection::WindowProtection, DesktopError> {
+    require_trusted_window(webview.label())?;
+    content_protection::set(&app, &label, enabled)
 }
 
 #[tauri::command]
-async fn open_live_channels_window_command(
+fn get_content_protection_state(
+    webview: Webview,
     app: AppHandle,
-    base_url: Option<String>,
-) -> Result<(), String> {
-    open_live_channels_window(&app, base_url)
+) -> Result<content_protection::ContentProtectionReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(content_protection::report(&app))
 }
 
+/// Send `payload` to the other windows as `broadcast:<channel>`. Returns
+/// how many windows it was emitted to.
 #[tauri::command]
-fn close_live_channels_window(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("live-channels") {
-        window
-            .close()
-            .map_err(|e| format!("Failed to close live channels window: {e}"))?;
-    }
-    Ok(())
+fn broadcast_event(
+    webview: Webview,
+    app: AppHandle,
+    channel: String,
+    payload: Value,
+    include_self: Option<bool>,
+) -> Result<usize, DesktopError> {
+    require_trusted_window(webview.label())?;
+    broadcast::broadcast(&app, webview.label(), &channel, payload, include_self.unwrap_or(false))
 }
 
-/// Fetch JSON from Polymarket Gamma API using native TLS (bypasses Cloudflare JA3 blocking).
-/// Called from frontend when browser CORS and sidecar Node.js TLS both fail.
 #[tauri::command]
-async fn fetch_polymarket(webview: Webview, path: String, params: String) -> Result<String, String> {
+fn list_broadcast_channels(webview: Webview) -> Result<Vec<broadcast::ChannelInfo>, DesktopError> {
     require_trusted_window(webview.label())?;
-    let allowed = ["events", "markets", "tags"];
-    let segment = path.trim_start_matches('/');
-    if !allowed.iter().any(|a| segment.starts_with(a)) {
-        return Err("Invalid Polymarket path".into());
-    }
-    let url = format!("https://gamma-api.polymarket.com/{}?{}", segment, params);
-    let client = reqwest::Client::builder()
-        .use_native_tls()
-        .build()
-        .map_err(|e| format!("HTTP client error: {e}"))?;
-    let resp = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-        .map_err(|e| format!("Polymarket fetch failed: {e}"))?;
-    if !resp.status().is_success() {
-        return Err(format!("Polymarket HTTP {}", resp.status()));
-    }
-    resp.text()
-        .await
-        .map_err(|e| format!("Read body failed: {e}"))
+    Ok(broadcast::channels())
 }
 
-fn open_settings_window(app: &AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("settings") {
-        let _ = window.show();
-        window
-            .set_focus()
-            .map_err(|e| format!("Failed to focus settings window: {e}"))?;
-        return Ok(());
-    }
-
-    let _settings_window = WebviewWindowBuilder::new(app, "settings", WebviewUrl::App("settings.html".into()))
-        .title("World Monitor Settings")
-        .inner_size(980.0, 600.0)
-        .min_inner_size(820.0, 480.0)
-        .resizable(true)
-        .background_color(tauri::webview::Color(26, 28, 30, 255))
-        .build()
-        .map_err(|e| format!("Failed to create settings window: {e}"))?;
-
-    // On Windows/Linux, menus are per-window. Remove the inherited app menu
-    // from the settings window (macOS uses a shared app-wide menu bar instead).
-    #[cfg(not(target_os = "macos"))]
-    let _ = _settings_window.remove_menu();
+/// Save the open windows and their placement as the layout `name`.
+#[tauri::command]
+async fn save_window_layout_snapshot(
+    webview: Webview,
+    app: AppHandle,
+    name: String,
+) -> Result<window_layout::Layout, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || window_layout::save_snapshot(&app, &name)).await
+}
 
-    Ok(())
+/// Reopen, close and move windows to match the layout saved as `name`.
+#[tauri::command]
+async fn restore_window_layout(
+    webview: Webview,
+    app: AppHandle,
+    name: String,
+) -> Result<window_layout::RestoreResult, DesktopError> {
+    require_trusted_window(webview.label())?;
+    window_layout::restore_named(&app, &name)
 }
 
-fn open_live_channels_window(app: &AppHandle, base_url: Option<String>) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("live-channels") {
-        let _ = window.show();
-        window
-            .set_focus()
-            .map_err(|e| format!("Failed to focus live channels window: {e}"))?;
-        return Ok(());
-    }
+#[tauri::command]
+fn list_window_layouts(webview: Webview, app: AppHandle) -> Result<Vec<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(window_layout::names(&app))
+}
 
-    // In dev, use the same origin as the main window (e.g. http://localhost:3001) so we don't
-    // get "connection refused" when Vite runs on a different port than devUrl.
-    let url = match base_url {
-        Some(ref origin) if !origin.is_empty() => {
-            let path = origin.trim_end_matches('/');
-            let full_url = format!("{}/live-channels.html", path);
-            WebviewUrl::External(Url::parse(&full_url).map_err(|_| "Invalid base URL".to_string())?)
-        }
-        _ => WebviewUrl::App("live-channels.html".into()),
-    };
+/// Highlight `query` in window `label`; repeating it moves to the next
+/// (or, with `forward: false`, previous) match.
+#[tauri::command]
+async fn find_in_page(
+    webview: Webview,
+    app: AppHandle,
+    label: String,
+    query: String,
+    options: Option<find_in_page::FindOptions>,
+) -> Result<find_in_page::FindResult, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || find_in_page::find(&app, &label, &query, options.unwrap_or_default())).await
+}
 
-    let _live_channels_window = WebviewWindowBuilder::new(app, "live-channels", url)
-    .title("Channel management - World Monitor")
-    .inner_size(680.0, 760.0)
-    .min_inner_size(520.0, 600.0)
-    .resizable(true)
-    .background_color(tauri::webview::Color(26, 28, 30, 255))
-    .build()
-    .map_err(|e| format!("Failed to create live channels window: {e}"))?;
+#[tauri::command]
+fn stop_find(webview: Webview, app: AppHandle, label: String) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    find_in_page::stop(&app, &label)
+}
 
-    #[cfg(not(target_os = "macos"))]
-    let _ = _live_channels_window.remove_menu();
+/// Save a PNG of window `label`'s content to `dest`, picked with
+/// `pick_save_path` (`dest_grant`), or to Downloads. Refused for protected
+/// windows and while settings overlaps, unless `include_settings`.
+#[tauri::command]
+async fn capture_window(
+    webview: Webview,
+    app: AppHandle,
+    label: String,
+    dest: Option<String>,
+    dest_grant: Option<String>,
+    include_settings: Option<bool>,
+) -> Result<window_capture::Capture, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let dest = file_dialog::redeem_optional(&app, dest_grant.as_deref(), dest.as_deref(), file_dialog::Access::Save)?;
+    run_blocking(move || window_capture::capture_window(&app, &label, dest, include_settings.unwrap_or(false))).await
+}
 
-    Ok(())
+/// Native save dialog, opening where the user last saved for `purpose` or in
+/// Downloads. `None` when cancelled; otherwise the canonical path and the
+/// grant a command writing it needs.
+#[tauri::command]
+async fn pick_save_path(
+    webview: Webview,
+    app: AppHandle,
+    default_name: String,
+    filters: Option<Vec<file_dialog::DialogFilter>>,
+    purpose: Option<String>,
+) -> Result<Option<file_dialog::PickedPath>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || {
+        file_dialog::pick_save(&app, &default_name, &filters.unwrap_or_default(), purpose.as_deref())
+    })
+    .await
 }
 
-fn open_youtube_login_window(app: &AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("youtube-login") {
-        let _ = window.show();
-        window
-            .set_focus()
-            .map_err(|e| format!("Failed to focus YouTube login window: {e}"))?;
-        return Ok(());
-    }
+/// Native open dialog; empty when cancelled. Each path comes with its grant.
+#[tauri::command]
+async fn pick_open_path(
+    webview: Webview,
+    app: AppHandle,
+    filters: Option<Vec<file_dialog::DialogFilter>>,
+    multiple: Option<bool>,
+    purpose: Option<String>,
+) -> Result<Vec<file_dialog::PickedPath>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || {
+        file_dialog::pick_open(&app, &filters.unwrap_or_default(), multiple.unwrap_or(false), purpose.as_deref())
+    })
+    .await
+}
 
-    let url = WebviewUrl::External(
-        Url::parse("https://accounts.google.com/ServiceLogin?service=youtube&continue=https://www.youtube.com/")
-            .map_err(|e| format!("Invalid URL: {e}"))?
-    );
+/// Pause or resume all background fetching.
+#[tauri::command]
+async fn set_polling_paused(webview: Webview, app: AppHandle, paused: bool) -> Result<polling::PollingStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let recorder = app.clone();
+    let status = run_blocking(move || polling::set_paused(&app, paused)).await?;
+    recorder.state::<SettingsSessionState>().record_pref(polling::PREF_POLLING_PAUSED);
+    Ok(status)
+}
 
-    let _yt_window = WebviewWindowBuilder::new(app, "youtube-login", url)
-        .title("Sign in to YouTube")
-        .inner_size(500.0, 700.0)
-        .resizable(true)
-        .build()
-        .map_err(|e| format!("Failed to create YouTube login window: {e}"))?;
+#[tauri::command]
+fn get_polling_paused(webview: Webview, app: AppHandle) -> Result<polling::PollingStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(polling::status(&app))
+}
 
-    #[cfg(not(target_os = "macos"))]
-    let _ = _yt_window.remove_menu();
+/// Whether the connection is metered and what the app does about it.
+#[tauri::command]
+fn get_network_profile(webview: Webview, app: AppHandle) -> Result<network_profile::NetworkProfile, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(network_profile::current(&app))
+}
 
-    Ok(())
+/// When each data source last answered, from the sidecar's reports.
+#[tauri::command]
+fn get_data_source_health(webview: Webview, app: AppHandle) -> Result<Vec<source_health::SourceHealth>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(source_health::report(&app))
 }
 
+/// Upstream calls per data source today and over the last week, with quotas.
 #[tauri::command]
-async fn open_youtube_login(app: AppHandle) -> Result<(), String> {
-    open_youtube_login_window(&app)
+fn get_api_usage_stats(webview: Webview, app: AppHandle) -> Result<Vec<api_usage::ProviderUsage>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(api_usage::report(&app))
 }
 
-fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+/// The last measured clock skew; `last` is null until one succeeds.
+#[tauri::command]
+fn get_clock_skew(webview: Webview, app: AppHandle) -> Result<clock_skew::ClockSkewReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(clock_skew::report(&app))
+}
+
+#[tauri::command]
+fn get_theme(webview: Webview, app: AppHandle) -> Result<theme::ThemeInfo, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(theme::info(&app))
+}
+
+/// Quit and start again, optionally with one extra flag such as
+/// `--safe-mode`. Refused after three restarts in five minutes.
+#[tauri::command]
+async fn restart_app(
+    webview: Webview,
+    app: AppHandle,
+    reason: Option<String>,
+    flag: Option<String>,
+) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || restart::restart_app(&app, reason.as_deref(), flag.as_deref())).await
+}
+
+/// Set the native theme (`system`, `dark` or `light`) for every window.
+#[tauri::command]
+async fn set_theme(webview: Webview, app: AppHandle, theme: String) -> Result<theme::ThemeInfo, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let value = Value::from(theme);
+    theme::validate_pref(&value).map_err(DesktopError::InvalidArgument)?;
+    let value = if value == "system" { Value::Null } else { value };
+    let persisted = app.clone();
+    run_blocking(move || store_runtime_pref(&persisted, theme::PREF_THEME, value)).await?;
+    app.state::<SettingsSessionState>().record_pref(theme::PREF_THEME);
+    theme::apply(&app, true);
+    Ok(theme::info(&app))
+}
+
+/// Mark the calling window as mid-save (or done), so View > Reload waits.
+#[tauri::command]
+fn set_reload_guard(webview: Webview, app: AppHandle, dirty: bool) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    app.state::<reload::ReloadGuardState>().set(webview.label(), dirty);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_ui_language(webview: Webview, app: AppHandle) -> Result<i18n::UiLanguage, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(i18n::ui_language(&app))
+}
+
+/// Set the menu language (`"auto"` follows the OS) and rebuild the menu.
+#[tauri::command]
+async fn set_ui_language(webview: Webview, app: AppHandle, language: String) -> Result<i18n::UiLanguage, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let value = Value::from(language);
+    i18n::validate_pref(&value).map_err(DesktopError::InvalidArgument)?;
+    let value = if value == "auto" { Value::Null } else { value };
+    let persisted = app.clone();
+    run_blocking(move || store_runtime_pref(&persisted, i18n::PREF_UI_LANGUAGE, value)).await?;
+    app.state::<SettingsSessionState>().record_pref(i18n::PREF_UI_LANGUAGE);
+    rebuild_app_menu(&app);
+    Ok(i18n::ui_language(&app))
+}
+
+/// Set the spellcheck dictionary, e.g. `en_US`; `null` follows the system
+/// locale. WebKitGTK switches at once, WebView2 at the next launch
+/// (`restart_required`); elsewhere the result says it isn't supported.
+#[tauri::command]
+async fn set_spellcheck_language(
+    webview: Webview,
+    app: AppHandle,
+    lang: Option<String>,
+) -> Result<webview_text::TextInputChange, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let value = match lang {
+        Some(lang) => Value::from(webview_text::normalize_language(&lang).map_err(DesktopError::InvalidArgument)?),
+        None => Value::Null,
+    };
+    let persisted = app.clone();
+    run_blocking(move || store_runtime_pref(&persisted, webview_text::PREF_SPELLCHECK_LANGUAGE, value)).await?;
+    app.state::<SettingsSessionState>().record_pref(webview_text::PREF_SPELLCHECK_LANGUAGE);
+    run_blocking(move || Ok(webview_text::apply_all(&app))).await
+}
+
+/// Toggle spellcheck and autofill suppression; omitted values are kept.
+#[tauri::command]
+async fn set_text_input_options(
+    webview: Webview,
+    app: AppHandle,
+    spellcheck_enabled: Option<bool>,
+    suppress_autofill: Option<bool>,
+) -> Result<webview_text::TextInputChange, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let changes: Vec<(&'static str, bool)> = [
+        (webview_text::PREF_SPELLCHECK_ENABLED, spellcheck_enabled),
+        (webview_text::PREF_SUPPRESS_AUTOFILL, suppress_autofill),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.map(|value| (key, value)))
+    .collect();
+    let persisted = app.clone();
+    let stored = changes.clone();
+    run_blocking(move || {
+        stored
+            .into_iter()
+            .try_for_each(|(key, value)| store_runtime_pref(&persisted, key, Value::Bool(value)))
+    })
+    .await?;
+    for (key, _) in &changes {
+        app.state::<SettingsSessionState>().record_pref(key);
+    }
+    run_blocking(move || Ok(webview_text::apply_all(&app))).await
+}
+
+/// Monitors and their scale factors, plus the `forceScaleFactor` in effect.
+#[tauri::command]
+fn get_display_info(webview: Webview, app: AppHandle) -> Result<display_scale::DisplayInfo, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(display_scale::display_info(&app))
+}
+
+/// Save `forceScaleFactor` (a number or `"auto"`). It is read before the
+/// first window is created, so the result says whether a restart is needed.
+#[tauri::command]
+async fn set_force_scale_factor(
+    webview: Webview,
+    app: AppHandle,
+    value: Value,
+) -> Result<display_scale::ScaleFactorChange, DesktopError> {
+    require_trusted_window(webview.label())?;
+    display_scale::validate_pref(&value).map_err(DesktopError::InvalidArgument)?;
+    let persisted = app.clone();
+    run_blocking(move || store_runtime_pref(&persisted, display_scale::PREF_FORCE_SCALE_FACTOR, value)).await?;
+    app.state::<SettingsSessionState>().record_pref(display_scale::PREF_FORCE_SCALE_FACTOR);
+    Ok(display_scale::change_result(&app))
+}
+
+/// Change the desktop.log level now and persist it as the `logLevel` pref.
+/// The sidecar picks up `debug` the next time it is started.
+#[tauri::command]
+async fn set_log_level(webview: Webview, app: AppHandle, level: String) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    let level = logging::LogLevel::parse(&level)
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("Unknown log level: {level}")))?;
+    let persisted = app.clone();
+    run_blocking(move || store_runtime_pref(&persisted, logging::PREF_LOG_LEVEL, Value::from(level.as_str()))).await?;
+    app.state::<logging::LogLevelState>().set(level);
+    log_event(&app, "INFO", "log_level_changed", &[("level", level.as_str())]);
+    Ok(())
+}
+
+fn logs_dir_path(app: &AppHandle) -> Result<PathBuf, String> {
+    data_dir::resolve_log_dir(app)
+}
+
+fn sidecar_log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(logs_dir_path(app)?.join(LOCAL_API_LOG_FILE))
+}
+
+fn desktop_log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(logs_dir_path(app)?.join(DESKTOP_LOG_FILE))
+}
+
+/// Timed in `StartupProfile`: each stage runs until the next one begins,
+/// and "ready" ends the sidecar boot.
+fn log_startup_stage(app: &AppHandle, stage: &str) {
+    match app.try_state::<StartupVisibility>() {
+        Some(visibility) => log_event(app, "INFO", "startup", &[("stage", stage), ("visibility", visibility.as_str())]),
+        None => log_event(app, "INFO", "startup", &[("stage", stage)]),
+    }
+    if let Some(profile) = app.try_state::<StartupProfile>() {
+        if stage == "ready" {
+            profile.settle_sidecar(Instant::now());
+        } else {
+            profile.begin(stage, Instant::now());
+        }
+        log_startup_summary(app);
+    }
+    let Some(state) = app.try_state::<StartupState>() else {
+        return;
+    };
+    let status = {
+        let mut status = state.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.stage = stage.to_string();
+        status.error = None;
+        status.clone()
+    };
+    let _ = app.emit("startup-stage", status);
+}
+
+fn log_startup_summary(app: &AppHandle) {
+    if let Some(line) = app.try_state::<StartupProfile>().and_then(|profile| profile.take_summary()) {
+        append_desktop_log(app, "INFO", &line);
+    }
+}
+
+fn log_startup_failure(app: &AppHandle, err: &str) {
+    log_event(app, "ERROR", "sidecar_start_failed", &[("error", err)]);
+    eprintln!("[tauri] local API sidecar failed to start: {err}");
+    if let Some(profile) = app.try_state::<StartupProfile>() {
+        profile.settle_sidecar(Instant::now());
+        log_startup_summary(app);
+    }
+    let Some(state) = app.try_state::<StartupState>() else {
+        return;
+    };
+    let status = {
+        let mut status = state.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.error = Some(err.to_string());
+        status.clone()
+    };
+    let _ = app.emit("startup-failed", status);
+}
+
+/// URL/file openers tried in order on Linux; minimal and immutable distros
+/// don't always ship `xdg-open`.
+#[cfg(all(unix, not(target_os = "macos")))]
+const LINUX_OPENERS: [(&str, &[&str]); 4] = [
+    ("xdg-open", &[]),
+    ("gio", &["open"]),
+    ("kde-open5", &[]),
+    ("kde-open", &[]),
+];
+
+/// Open a URL with the default handler. Folders and files go through
+/// [`open_path_in_shell`].
+fn open_in_shell(arg: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(arg)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to open {}: {e}", arg))
+    }
+
+    // explorer.exe truncates URLs at the first `&`, so hand them to the shell
+    // directly.
+    #[cfg(target_os = "windows")]
+    {
+        shell_execute_open(arg)
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        // The sandbox has no host openers to spawn; the portal asks the host.
+        #[cfg(target_os = "linux")]
+        if packaging::is_flatpak() {
+            return packaging::portal::open_uri(arg);
+        }
+        let mut tried = Vec::new();
+        for (program, args) in LINUX_OPENERS {
+            let mut cmd = Command::new(program);
+            cmd.args(args).arg(arg);
+            cmd.env_remove("LD_LIBRARY_PATH");
+            cmd.env_remove("LD_PRELOAD");
+            match cmd.spawn() {
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    tried.push(std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" "));
+                }
+                Err(e) => return Err(format!("Failed to open {arg} with {program}: {e}")),
+            }
+        }
+        Err(format!(
+            "Failed to open {arg}: no opener found (tried {}). Install xdg-utils.",
+            tried.join(", ")
+        ))
+    }
+}
+
+fn open_path_in_shell(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("explorer")
+            .arg(path)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        #[cfg(target_os = "linux")]
+        if packaging::is_flatpak() {
+            return packaging::portal::open_file(path);
+        }
+        open_in_shell(&path.to_string_lossy())
+    }
+}
+
+/// NUL-terminated UTF-16 for Win32 `W` APIs. `None` if `text` has an interior
+/// NUL, which would silently truncate it.
+#[cfg(any(target_os = "windows", test))]
+fn to_wide_null(text: &str) -> Option<Vec<u16>> {
+    if text.contains('\0') {
+        return None;
+    }
+    Some(text.encode_utf16().chain(std::iter::once(0)).collect())
+}
+
+#[cfg(target_os = "windows")]
+fn shell_execute_open(target: &str) -> Result<(), String> {
+    use std::ffi::c_void;
+    #[link(name = "shell32")]
+    extern "system" {
+        fn ShellExecuteW(
+            hwnd: *mut c_void,
+            operation: *const u16,
+            file: *const u16,
+            parameters: *const u16,
+            directory: *const u16,
+            show_cmd: i32,
+        ) -> *mut c_void;
+    }
+    const SW_SHOWNORMAL: i32 = 1;
+
+    let operation = to_wide_null("open").unwrap_or_default();
+    let file = to_wide_null(target).ok_or_else(|| format!("Failed to open {target}: contains NUL"))?;
+    // SAFETY: both strings are NUL-terminated and outlive the call.
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            operation.as_ptr(),
+            file.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    } as usize;
+    // Values above 32 mean success; smaller ones are SE_ERR_* codes.
+    if result > 32 {
+        Ok(())
+    } else {
+        Err(format!("Failed to open {target}: ShellExecuteW error {result}"))
+    }
+}
+
+fn is_blocked_url_scheme(scheme: &str) -> bool {
+    BLOCKED_URL_SCHEMES.contains(&scheme)
+}
+
+/// Percent-decode a URL component; `None` on malformed escapes or non-UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn is_plausible_email(address: &str) -> bool {
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | '"' | '(' | ')'))
+}
+
+/// `mailto:` needs at least one plausible recipient. Line breaks are only
+/// allowed in `body`; anywhere else they would inject mail headers.
+fn validate_mailto(url: &Url) -> Result<(), String> {
+    let recipients = percent_decode(url.path()).ok_or("Malformed mailto: address")?;
+    if recipients.is_empty() || !recipients.split(',').all(|a| is_plausible_email(a.trim())) {
+        return Err("mailto: needs a valid email address".to_string());
+    }
+    for field in url.query().unwrap_or("").split('&').filter(|f| !f.is_empty()) {
+        let (name, value) = field.split_once('=').unwrap_or((field, ""));
+        let name = percent_decode(name).ok_or("Malformed mailto: field")?;
+        let value = percent_decode(value).ok_or("Malformed mailto: field")?;
+        if name.is_empty() || name.chars().any(|c| c.is_control()) {
+            return Err("Malformed mailto: field name".to_string());
+        }
+        let allow_line_breaks = name.eq_ignore_ascii_case("body");
+        if value
+            .chars()
+            .any(|c| c.is_control() && !(allow_line_breaks && matches!(c, '\r' | '\n' | '\t')))
+        {
+            return Err(format!("mailto: field {name} may not contain control characters"));
+        }
+    }
+    Ok(())
+}
+
+/// https is always allowed, http only for localhost and trusted LAN hosts,
+/// mailto after validation, and anything else only when listed in
+/// `allowedUrlSchemes`. Script-capable schemes are refused even if someone
+/// lists them.
+fn check_open_url(
+    raw: &str,
+    extra_schemes: &[String],
+    trusted: &[trusted_hosts::TrustedHost],
+) -> Result<Url, DesktopError> {
+    // The parser lowercases the scheme, so `HTTPS:` and `MailTo:` normalize.
+    let parsed = Url::parse(raw).map_err(|e| DesktopError::InvalidUrl(format!("Invalid URL: {e}")))?;
+    let scheme = parsed.scheme();
+    let allowed = match scheme {
+        "https" => true,
+        "http" => {
+            let host = parsed.host_str().unwrap_or("");
+            matches!(host, "localhost" | "127.0.0.1")
+                || trusted_hosts::is_trusted(trusted, host, parsed.port_or_known_default().unwrap_or(80))
+        }
+        "mailto" => {
+            validate_mailto(&parsed).map_err(DesktopError::InvalidUrl)?;
+            true
+        }
+        _ if is_blocked_url_scheme(scheme) => false,
+        _ => extra_schemes.iter().any(|allowed| allowed.eq_ignore_ascii_case(scheme)),
+    };
+    if !allowed {
+        return Err(DesktopError::InvalidUrl(format!(
+            "Only https:// and mailto: URLs are allowed (http:// only for localhost and trusted local hosts); \
+             add {scheme}: to {PREF_ALLOWED_URL_SCHEMES} to open it"
+        )));
+    }
+    Ok(parsed)
+}
+
+fn validate_allowed_url_schemes(value: &Value) -> Result<(), String> {
+    let schemes = value
+        .as_array()
+        .ok_or_else(|| format!("Runtime pref {PREF_ALLOWED_URL_SCHEMES} must be an array of schemes"))?;
+    for scheme in schemes {
+        let scheme = scheme
+            .as_str()
+            .ok_or_else(|| format!("Runtime pref {PREF_ALLOWED_URL_SCHEMES} must be an array of schemes"))?;
+        let valid_syntax = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+        if !valid_syntax {
+            return Err(format!("Invalid URL scheme {scheme:?}; list schemes without ':' or '//'"));
+        }
+        if is_blocked_url_scheme(&scheme.to_ascii_lowercase()) {
+            return Err(format!("URL scheme {scheme} cannot be allowed"));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum OpenUrlOutcome {
+    Opened,
+    /// Nothing was opened; call again with `confirmed: true` once the user
+    /// has seen `host` (punycode) next to `display_host`.
+    NeedsConfirmation {
+        reason: url_safety::HostConcern,
+        host: String,
+        display_host: String,
+    },
+}
+
+#[tauri::command]
+fn open_url(app: AppHandle, url: String, confirmed: Option<bool>) -> Result<OpenUrlOutcome, DesktopError> {
+    let extra_schemes: Vec<String> = app
+        .try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_ALLOWED_URL_SCHEMES))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    let parsed = check_open_url(&url, &extra_schemes, &trusted_hosts::from_prefs(&app))?;
+    let host = parsed.host_str().unwrap_or("");
+    match url_safety::inspect_url(&parsed) {
+        url_safety::HostVerdict::Plain => {}
+        url_safety::HostVerdict::Idn { unicode } => {
+            log_event(&app, "INFO", "open_url_idn", &[("host", host), ("display_host", &unicode)]);
+        }
+        url_safety::HostVerdict::Suspicious { concern, unicode } => {
+            let reason = serde_json::to_value(concern)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            if confirmed != Some(true) {
+                log_event(&app, "WARN", "open_url_needs_confirmation", &[("host", host), ("reason", &reason)]);
+                return Ok(OpenUrlOutcome::NeedsConfirmation {
+                    reason: concern,
+                    host: host.to_string(),
+                    display_host: unicode,
+                });
+            }
+            log_event(&app, "INFO", "open_url_confirmed", &[("host", host), ("reason", &reason)]);
+        }
+    }
+    if parsed.scheme() != "https" {
+        log_event(&app, "INFO", "open_url", &[("scheme", parsed.scheme())]);
+    }
+    open_in_shell(parsed.as_str())?;
+    Ok(OpenUrlOutcome::Opened)
+}
+
+/// Sliding-window limiter: drop timestamps older than the window, then admit
+/// the notification only if fewer than `max` remain.
+fn admit_notification(
+    recent: &mut VecDeque<std::time::Instant>,
+    now: std::time::Instant,
+    window: std::time::Duration,
+    max: usize,
+) -> bool {
+    while recent
+        .front()
+        .is_some_and(|t| now.duration_since(*t) >= window)
+    {
+        recent.pop_front();
+    }
+    if recent.len() >= max {
+        return false;
+    }
+    recent.push_back(now);
+    true
+}
+
+fn notification_category_enabled(prefs: &RuntimePrefs, category: &str) -> bool {
+    prefs
+        .get(PREF_NOTIFICATION_CATEGORIES)
+        .and_then(|v| v.get(category).and_then(Value::as_bool))
+        .unwrap_or(true)
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// Show an OS notification. Returns Ok(false) when it was suppressed by prefs,
+/// window focus, or the rate limiter. Do Not Disturb / Focus modes are applied
+/// by the OS notification centers themselves.
+#[tauri::command]
+fn show_notification(
+    webview: Webview,
+    app: AppHandle,
+    title: String,
+    body: String,
+    category: String,
+    urgency: Option<String>,
+) -> Result<bool, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let prefs = app.state::<RuntimePrefs>();
+    let state = app.state::<NotificationState>();
+    if !NOTIFICATION_CATEGORIES.contains(&category.as_str()) {
+        return Err(DesktopError::InvalidArgument(format!("Unsupported notification category: {category}")));
+    }
+    if prefs.get_bool(PREF_NOTIFICATIONS_MUTED, false)
+        || !notification_category_enabled(&prefs, &category)
+        || quiet_hours::is_active(&app)
+    {
+        return Ok(false);
+    }
+    if prefs.get_bool(PREF_SUPPRESS_NOTIFICATIONS_WHEN_FOCUSED, false) {
+        let main_focused = app
+            .get_webview_window("main")
+            .and_then(|w| w.is_focused().ok())
+            .unwrap_or(false);
+        if main_focused {
+            return Ok(false);
+        }
+    }
+    {
+        let mut recent = state.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if !admit_notification(
+            &mut recent,
+            std::time::Instant::now(),
+            std::time::Duration::from_secs(NOTIFICATION_RATE_WINDOW_SECS),
+            NOTIFICATION_RATE_MAX,
+        ) {
+            log_window_event(
+                &app,
+                webview.label(),
+                "WARN",
+                "notification_rate_limited",
+                &[("category", &category)],
+            );
+            return Ok(false);
+        }
+    }
+
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .summary(&truncate_chars(&title, NOTIFICATION_TITLE_MAX_CHARS))
+        .body(&truncate_chars(&body, NOTIFICATION_BODY_MAX_CHARS))
+        .appname("World Monitor");
+    #[cfg(not(target_os = "macos"))]
+    {
+        let level = match urgency.as_deref() {
+            Some("low") => notify_rust::Urgency::Low,
+            Some("critical") => notify_rust::Urgency::Critical,
+            _ => notify_rust::Urgency::Normal,
+        };
+        notification.urgency(level);
+    }
+    #[cfg(target_os = "macos")]
+    let _ = urgency;
+
+    let handle = notification
+        .show()
+        .map_err(|e| DesktopError::Internal(format!("Failed to show notification: {e}")))?;
+    let app_for_click = app.clone();
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "__closed" {
+                return;
+            }
+            show_main_window(&app_for_click);
+            let _ = app_for_click.emit_to(
+                "main",
+                "notification-activated",
+                serde_json::json!({ "category": category }),
+            );
+        });
+    });
+    Ok(true)
+}
+
+fn admit_frontend_log(
+    budget: &mut FrontendLogBudget,
+    now: std::time::Instant,
+    max_per_second: usize,
+) -> FrontendLogAdmission {
+    let expired = budget
+        .window_start
+        .is_none_or(|start| now.duration_since(start) >= std::time::Duration::from_secs(1));
+    if expired {
+        budget.window_start = Some(now);
+        budget.admitted = 0;
+    }
+    if budget.admitted >= max_per_second {
+        budget.dropped += 1;
+        return FrontendLogAdmission::Dropped;
+    }
+    budget.admitted += 1;
+    FrontendLogAdmission::Admitted {
+        dropped_before: std::mem::take(&mut budget.dropped),
+    }
+}
+
+/// Write a renderer-side log line to desktop.log, tagged with the calling
+/// window. Lines over the per-second budget are dropped and counted.
+#[tauri::command]
+fn log_from_frontend(
+    webview: Webview,
+    app: AppHandle,
+    level: String,
+    message: String,
+    context: Option<Value>,
+) -> Result<(), DesktopError> {
+    let normalized = level.trim().to_ascii_lowercase();
+    if !FRONTEND_LOG_LEVELS.contains(&normalized.as_str()) {
+        return Err(DesktopError::InvalidArgument(format!("Unsupported log level: {level}")));
+    }
+    let state = app.state::<FrontendLogState>();
+    let admission = admit_frontend_log(
+        &mut state.budget.lock().unwrap_or_else(|e| e.into_inner()),
+        std::time::Instant::now(),
+        FRONTEND_LOG_RATE_MAX,
+    );
+    let FrontendLogAdmission::Admitted { dropped_before } = admission else {
+        return Ok(());
+    };
+    let window = webview.label();
+    if dropped_before > 0 {
+        log_window_event(
+            &app,
+            window,
+            "WARN",
+            "frontend_log_dropped",
+            &[("dropped", &dropped_before.to_string())],
+        );
+    }
+    let message = truncate_chars(&message, FRONTEND_LOG_MAX_CHARS);
+    let context = context
+        .filter(|v| !v.is_null())
+        .map(|v| truncate_chars(&v.to_string(), FRONTEND_LOG_MAX_CHARS));
+    let mut fields = vec![("message", message.as_str())];
+    if let Some(context) = &context {
+        fields.push(("context", context.as_str()));
+    }
+    log_window_event(&app, window, &normalized.to_ascii_uppercase(), "frontend", &fields);
+    Ok(())
+}
+
+/// `beta.1` in `2.6.0-beta.1`; build metadata (`+abc`) alone doesn't count.
+fn prerelease_tag(version: &str) -> Option<&str> {
+    version.trim().split('+').next()?.split_once('-').map(|(_, tag)| tag)
+}
+
+fn is_prerelease_version(version: &str) -> bool {
+    prerelease_tag(version).is_some()
+}
+
+/// Semver precedence for prerelease tags: numeric parts compare as numbers
+/// and sort before words; a longer tag wins a tie.
+fn compare_prerelease(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a, mut b) = (a.split('.'), b.split('.'));
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+                (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Numeric dotted-version comparison; missing components count as zero. A
+/// prerelease is older than the release with the same numbers.
+fn is_newer_version(remote: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim()
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse::<u64>().unwrap_or(0))
+            .collect()
+    };
+    let (r, c) = (parse(remote), parse(current));
+    for i in 0..r.len().max(c.len()) {
+        let (rv, cv) = (r.get(i).copied().unwrap_or(0), c.get(i).copied().unwrap_or(0));
+        if rv != cv {
+            return rv > cv;
+        }
+    }
+    match (prerelease_tag(remote), prerelease_tag(current)) {
+        (None, Some(_)) => true,
+        (Some(r), Some(c)) => compare_prerelease(r, c).is_gt(),
+        _ => false,
+    }
+}
+
+fn update_manifest_url() -> String {
+    env::var("WM_UPDATE_MANIFEST_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| UPDATE_MANIFEST_URL.to_string())
+}
+
+/// The manifest's version and notes URL, and whether it is a prerelease.
+async fn fetch_latest_release(app: &AppHandle) -> Result<(String, Option<String>, bool), String> {
+    let client = extra_ca::client_builder(app)
+        .map_err(|e| format!("HTTP client error: {e}"))?
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("HTTP client error: {e}"))?;
+    let resp = client
+        .get(update_manifest_url())
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| format!("update manifest fetch failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("update manifest HTTP {}", resp.status()));
+    }
+    let manifest: Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("update manifest parse failed: {e}"))?;
+    let version = manifest
+        .get("version")
+        .and_then(Value::as_str)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| "update manifest missing version".to_string())?;
+    let notes_url = manifest.get("url").and_then(Value::as_str).map(str::to_string);
+    let prerelease = manifest.get("prerelease").and_then(Value::as_bool).unwrap_or(false) || is_prerelease_version(version);
+    Ok((version.to_string(), notes_url, prerelease))
+}
+
+/// Network and manifest failures are logged and reported as `checked: false`
+/// rather than surfaced as errors. A prerelease is only offered with
+/// `includePrereleaseUpdates` on.
+async fn check_for_updates_impl(app: &AppHandle, manual: bool) -> UpdateCheckResult {
+    let current_version = env!("CARGO_PKG_VERSION").to_string();
+    let include_prereleases = app
+        .try_state::<RuntimePrefs>()
+        .is_some_and(|prefs| prefs.get_bool(PREF_INCLUDE_PRERELEASES, false));
+    let result = match fetch_latest_release(app).await {
+        Ok((latest, notes_url, prerelease)) => UpdateCheckResult {
+            update_available: (include_prereleases || !prerelease) && is_newer_version(&latest, &current_version),
+            current_version,
+            latest_version: Some(latest),
+            notes_url: notes_url.or_else(|| Some(UPDATE_RELEASES_URL.to_string())),
+            checked: true,
+        },
+        Err(err) => {
+            append_desktop_log(app, "WARN", &format!("update check failed: {err}"));
+            UpdateCheckResult {
+                current_version,
+                latest_version: None,
+                notes_url: None,
+                update_available: false,
+                checked: false,
+            }
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if result.checked {
+        let _ = store_runtime_pref(app, PREF_LAST_UPDATE_CHECK_AT, Value::from(now));
+    }
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "update check manual={manual} current={} latest={} available={}",
+            result.current_version,
+            result.latest_version.as_deref().unwrap_or("unknown"),
+            result.update_available
+        ),
+    );
+    if result.update_available {
+        let _ = app.emit("update-available", result.clone());
+    }
+    result
+}
+
+#[tauri::command]
+async fn check_for_updates(app: AppHandle, manual: bool) -> UpdateCheckResult {
+    check_for_updates_impl(&app, manual).await
+}
+
+/// Whether the once-per-day background check is due; only when the user
+/// opted in.
+fn auto_update_check_due(app: &AppHandle) -> bool {
+    let prefs = app.state::<RuntimePrefs>();
+    if !prefs.get_bool(PREF_AUTO_CHECK_UPDATES, false) {
+        return false;
+    }
+    let last = prefs
+        .get(PREF_LAST_UPDATE_CHECK_AT)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    now.saturating_sub(last) >= AUTO_UPDATE_CHECK_INTERVAL_SECS
+}
+
+/// Check now if due, then keep looking every hour, so a session left
+/// running for days still hears about releases. A failed check isn't
+/// recorded and is retried on the next look.
+fn schedule_auto_update_check(app: &AppHandle) {
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if auto_update_check_due(&handle) {
+                check_for_updates_impl(&handle, false).await;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(AUTO_UPDATE_POLL_SECS)).await;
+        }
+    });
+}
+
+/// Reveal desktop.log in the file manager, or just open the folder when
+/// nothing has been logged yet.
+fn open_logs_folder_impl(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = logs_dir_path(app)?;
+    match desktop_log_path(app) {
+        Ok(log_path) if log_path.exists() => reveal::reveal_path(&log_path)?,
+        _ => open_path_in_shell(&dir)?,
+    }
+    Ok(dir)
+}
+
+fn open_app_data_folder_impl(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = data_dir::resolve_data_dir(app)?;
+    open_path_in_shell(&dir)?;
+    Ok(dir)
+}
+
+fn open_sidecar_log_impl(app: &AppHandle) -> Result<PathBuf, String> {
+    let log_path = sidecar_log_path(app)?;
+    if !log_path.exists() {
+        File::create(&log_path)
+            .map_err(|e| format!("Failed to create sidecar log {}: {e}", log_path.display()))?;
+    }
+    reveal::reveal_path(&log_path)?;
+    Ok(log_path)
+}
+
+#[tauri::command]
+fn read_desktop_log_tail(webview: Webview, app: AppHandle, lines: usize) -> Result<Vec<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let path = desktop_log_path(&app).map_err(DesktopError::Internal)?;
+    let redactor = logging::secret_redactor(&app);
+    logging::read_tail_lines(&path, lines.min(logging::MAX_TAIL_LINES))
+        .map(|tail| tail.iter().map(|line| redactor.redact(line).into_owned()).collect())
+        .map_err(|e| DesktopError::io("Failed to read", &path, e))
+}
+
+#[tauri::command]
+fn read_sidecar_log_tail(webview: Webview, app: AppHandle, lines: usize) -> Result<Vec<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let path = sidecar_log_path(&app).map_err(DesktopError::Internal)?;
+    let redactor = logging::secret_redactor(&app);
+    logging::read_tail_lines(&path, lines.min(logging::MAX_TAIL_LINES))
+        .map(|tail| tail.iter().map(|line| redactor.redact(line).into_owned()).collect())
+        .map_err(|e| DesktopError::io("Failed to read", &path, e))
+}
+
+/// Send this window the sidecar's output as it is written, redacted, as
+/// `sidecar-log-line` events (or `sidecar-log-burst` when it falls behind).
+/// Pair with `read_sidecar_log_tail` for what came before.
+#[tauri::command]
+fn subscribe_sidecar_log(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    sidecar_log::subscribe(&app, webview.label());
+    Ok(())
+}
+
+#[tauri::command]
+fn unsubscribe_sidecar_log(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    sidecar_log::unsubscribe(&app, webview.label());
+    Ok(())
+}
+
+/// The sidecar's environment as it would be assembled now, secret values
+/// masked, and whether the running sidecar was started with something else.
+#[tauri::command]
+async fn get_sidecar_env_preview(webview: Webview, app: AppHandle) -> Result<sidecar_env::EnvPreview, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || sidecar_env::preview(&app).map_err(DesktopError::Internal)).await
+}
+
+/// Write prefs, data-source toggles and named layouts (and, sealed with
+/// `passphrase`, the vault) to a `.json` bundle at `path`, which must come
+/// from `pick_save_path` with its `path_grant`.
+#[tauri::command]
+async fn export_app_config(
+    webview: Webview,
+    app: AppHandle,
+    path: String,
+    path_grant: String,
+    include_secrets: bool,
+    passphrase: Option<String>,
+) -> Result<config_bundle::ExportSummary, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let path = file_dialog::redeem(&app, &path_grant, &path, file_dialog::Access::Save)?;
+    run_blocking(move || {
+        config_bundle::export(&app, &path.to_string_lossy(), include_secrets, passphrase.as_deref())
+    })
+    .await
+}
+
+/// Apply a bundle from `export_app_config`, picked with `pick_open_path`;
+/// `merge_strategy` is `merge` (default) or `replace`. Security-sensitive
+/// prefs are only applied when named in `confirmed_prefs`.
+#[tauri::command]
+async fn import_app_config(
+    webview: Webview,
+    app: AppHandle,
+    path: String,
+    path_grant: String,
+    passphrase: Option<String>,
+    merge_strategy: Option<config_bundle::MergeStrategy>,
+    confirmed_prefs: Option<Vec<String>>,
+) -> Result<config_bundle::ImportReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let path = file_dialog::redeem(&app, &path_grant, &path, file_dialog::Access::Open)?;
+    let strategy = merge_strategy.unwrap_or_default();
+    let confirmed = confirmed_prefs.unwrap_or_default();
+    run_blocking(move || config_bundle::import(&app, &path.to_string_lossy(), passphrase.as_deref(), strategy, &confirmed)).await
+}
+
+/// Write a redacted diagnostics zip to `path` (picked with `pick_save_path`,
+/// or Downloads) and return where it landed.
+#[tauri::command]
+async fn export_diagnostics_bundle(
+    webview: Webview,
+    app: AppHandle,
+    path: Option<String>,
+    path_grant: Option<String>,
+) -> Result<String, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let dest = file_dialog::redeem_optional(&app, path_grant.as_deref(), path.as_deref(), file_dialog::Access::Save)?;
+    run_blocking(move || diagnostics::export_diagnostics_bundle(&app, dest).map(|path| path.display().to_string())).await
+}
+
+#[tauri::command]
+async fn reveal_in_file_manager(webview: Webview, app: AppHandle, path: String) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || {
+        let path = reveal::resolve_allowed_path(Path::new(&path), &reveal::allowed_roots(&app))
+            .map_err(DesktopError::InvalidArgument)?;
+        Ok(reveal::reveal_path(&path)?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn open_logs_folder(app: AppHandle) -> Result<String, DesktopError> {
+    open_logs_folder_impl(&app)
+        .map(|path| path.display().to_string())
+        .map_err(DesktopError::Internal)
+}
+
+/// Files in the logs directory with their sizes, plus the retention limits.
+#[tauri::command]
+async fn get_log_files_info(
+    webview: Webview,
+    app: AppHandle,
+) -> Result<log_retention::LogFilesInfo, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || log_retention::log_files_info(&app)).await
+}
+
+/// Delete rotated logs past the age limit, then the oldest until the
+/// directory fits. Limits not given come from the prefs.
+#[tauri::command]
+async fn prune_logs(
+    webview: Webview,
+    app: AppHandle,
+    max_total_mb: Option<u64>,
+    max_age_days: Option<u64>,
+) -> Result<log_retention::PruneResult, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || log_retention::prune(&app, max_total_mb, max_age_days)).await
+}
+
+#[tauri::command]
+fn open_app_data_folder(app: AppHandle) -> Result<String, DesktopError> {
+    open_app_data_folder_impl(&app)
+        .map(|path| path.display().to_string())
+        .map_err(DesktopError::Internal)
+}
+
+/// Where the app keeps its files, with existence and writability for each.
+#[tauri::command]
+fn get_app_paths(webview: Webview, app: AppHandle) -> Result<app_paths::AppPaths, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(app_paths::app_paths(&app))
+}
+
+/// The profile this instance runs as, for the title bar and settings.
+#[tauri::command]
+fn get_active_profile(webview: Webview) -> Result<profile::ActiveProfile, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(profile::active())
+}
+
+/// First-run and setup progress for the welcome flow in settings.
+#[tauri::command]
+async fn get_onboarding_state(webview: Webview, app: AppHandle) -> Result<onboarding::OnboardingReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || {
+        let node_found = resolve_node_binary(&app).is_some();
+        Ok(onboarding::report(&app, node_found))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn set_onboarding_complete(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || store_runtime_pref(&app, onboarding::PREF_ONBOARDING_COMPLETE, Value::Bool(true))).await
+}
+
+/// Hash every bundled sidecar resource against the build's manifest.
+#[tauri::command]
+async fn verify_resources(webview: Webview, app: AppHandle) -> Result<resource_integrity::IntegrityReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || Ok(resource_integrity::verify(&local_api_paths(&app), true))).await
+}
+
+/// Which sidecar resource root was picked and why, plus the script and the
+/// Node binary a start would use.
+#[tauri::command]
+async fn get_sidecar_paths(webview: Webview, app: AppHandle) -> Result<sidecar_paths::SidecarPathsReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || {
+        let paths = local_api_paths(&app);
+        Ok(sidecar_paths::SidecarPathsReport {
+            script_exists: paths.script.is_file(),
+            node_binary: resolve_node_binary(&app),
+            paths,
+        })
+    })
+    .await
+}
+
+/// Move the cache, prefs and logs to `new_path`, reporting progress through
+/// `data-migration-progress` events. The sidecar is restarted afterwards.
+#[tauri::command]
+async fn migrate_data_directory(
+    webview: Webview,
+    app: AppHandle,
+    new_path: String,
+) -> Result<data_dir::MigrationResult, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || data_dir::migrate(&app, &new_path)).await
+}
+
+/// Per-host limiter state: queue depths, rejections and upstream 429s.
+#[tauri::command]
+fn get_rate_limit_stats(webview: Webview, app: AppHandle) -> Result<Vec<rate_limit::HostStats>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(app.state::<rate_limit::RateLimiter>().stats())
+}
+
+/// Drop every cached native-fetch response.
+#[tauri::command]
+async fn clear_http_cache(webview: Webview, app: AppHandle) -> Result<http_cache::ClearedHttpCache, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || http_cache::clear(&app)).await
+}
+
+/// Store the raw request body as a blob. The key goes in the `x-blob-key`
+/// header and an optional content type in `x-blob-content-type`.
+#[tauri::command]
+async fn write_blob(webview: Webview, app: AppHandle, request: tauri::ipc::Request<'_>) -> Result<blob_store::BlobInfo, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let (key, content_type, bytes) = blob_store::parse_write(&request)?;
+    run_blocking(move || blob_store::write(&app, &key, bytes, content_type)).await
+}
+
+/// The blob's bytes as an `ArrayBuffer`; `not_found` if there is none.
+#[tauri::command]
+async fn read_blob(webview: Webview, app: AppHandle, key: String) -> Result<tauri::ipc::Response, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let bytes = run_blocking(move || blob_store::read(&app, &key)).await?;
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
+#[tauri::command]
+async fn delete_blob(webview: Webview, app: AppHandle, key: String) -> Result<bool, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || blob_store::delete(&app, &key)).await
+}
+
+#[tauri::command]
+async fn list_blobs(webview: Webview, app: AppHandle) -> Result<Vec<blob_store::BlobInfo>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || blob_store::list(&app)).await
+}
+
+/// Blob count, total size against the cap, and evictions this session.
+#[tauri::command]
+async fn get_blob_store_stats(webview: Webview, app: AppHandle) -> Result<blob_store::BlobStoreStats, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || Ok(blob_store::stats(&app))).await
+}
+
+/// Open a WebSocket owned by the calling window. Messages arrive as
+/// `ws-message` events tagged with `id`; the end of the connection as
+/// `ws-closed`.
+#[tauri::command]
+async fn ws_connect(
+    webview: Webview,
+    app: AppHandle,
+    id: String,
+    url: String,
+    protocols: Option<Vec<String>>,
+) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    ws_bridge::connect(&app, webview.label(), id, &url, protocols.unwrap_or_default()).await
+}
+
+#[tauri::command]
+fn ws_send(webview: Webview, app: AppHandle, id: String, payload: String) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    ws_bridge::send(&app, webview.label(), &id, payload)
+}
+
+/// Returns false when the connection was already gone.
+#[tauri::command]
+fn ws_close(webview: Webview, app: AppHandle, id: String) -> Result<bool, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(ws_bridge::close(&app, webview.label(), &id))
+}
+
+#[tauri::command]
+fn open_sidecar_log_file(app: AppHandle) -> Result<String, DesktopError> {
+    open_sidecar_log_impl(&app)
+        .map(|path| path.display().to_string())
+        .map_err(DesktopError::Internal)
+}
+
+#[tauri::command]
+async fn open_settings_window_command(app: AppHandle, section: Option<String>) -> Result<(), DesktopError> {
+    let section = section
+        .as_deref()
+        .map(validate_settings_section)
+        .transpose()
+        .map_err(DesktopError::InvalidArgument)?;
+    open_settings_window_at(&app, section).map_err(DesktopError::Internal)
+}
+
+fn validate_settings_section(section: &str) -> Result<&str, String> {
+    SETTINGS_SECTIONS
+        .iter()
+        .copied()
+        .find(|known| *known == section)
+        .ok_or_else(|| format!("Unknown settings section: {section}"))
+}
+
+/// The run loop emits `settings-closed` once the window is destroyed.
+#[tauri::command]
+fn close_settings_window(webview: Webview, app: AppHandle, force: Option<bool>) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    let Some(window) = app.get_webview_window("settings") else {
+        return Ok(());
+    };
+    if force.unwrap_or(false) {
+        // `destroy` skips CloseRequested, so save what it would have.
+        save_settings_window_geometry(&app);
+        app.state::<SettingsSessionState>().set_dirty(false);
+        return window
+            .destroy()
+            .map_err(|e| DesktopError::Internal(format!("Failed to close settings window: {e}")));
+    }
+    // CloseRequested in the run loop holds it if an edit is unsaved.
+    window
+        .close()
+        .map_err(|e| DesktopError::Internal(format!("Failed to close settings window: {e}")))
+}
+
+/// Called by the settings window as fields gain or lose unsaved edits.
+#[tauri::command]
+fn set_settings_dirty(webview: Webview, app: AppHandle, dirty: bool) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    app.state::<SettingsSessionState>().set_dirty(dirty);
+    Ok(())
+}
+
+#[tauri::command]
+async fn open_live_channels_window_command(
+    app: AppHandle,
+    base_url: Option<String>,
+) -> Result<(), DesktopError> {
+    open_live_channels_window(&app, base_url).map_err(DesktopError::Internal)
+}
+
+/// Pop a panel out into its own window (`panel-<id>`), or focus it if open.
+#[tauri::command]
+async fn create_panel_window(webview: Webview, app: AppHandle, panel_id: String) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    let panel_id = panel_windows::validate_panel_id(&panel_id)?;
+    panel_windows::create_panel_window(&app, panel_id, None)
+}
+
+#[tauri::command]
+fn close_live_channels_window(app: AppHandle) -> Result<(), DesktopError> {
+    if let Some(window) = app.get_webview_window("live-channels") {
+        window
+            .close()
+            .map_err(|e| DesktopError::Internal(format!("Failed to close live channels window: {e}")))?;
+    }
+    Ok(())
+}
+
+const POLYMARKET_HOST: &str = "gamma-api.polymarket.com";
+
+/// Fetch JSON from Polymarket Gamma API using native TLS (bypasses Cloudflare JA3 blocking).
+/// Called from frontend when browser CORS and sidecar Node.js TLS both fail.
+/// Identical queries already in flight are shared unless `coalesce` is false.
+/// Offline, the last good body is returned as `{ stale, cached_at, body }`.
+#[tauri::command]
+async fn fetch_polymarket(
+    webview: Webview,
+    app: AppHandle,
+    path: String,
+    params: String,
+    coalesce: Option<bool>,
+) -> Result<offline_cache::CachedBody, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let allowed = ["events", "markets", "tags"];
+    let segment = path.trim_start_matches('/');
+    if !allowed.iter().any(|a| segment.starts_with(a)) {
+        return Err(DesktopError::InvalidArgument("Invalid Polymarket path".into()));
+    }
+    let url = format!("https://{POLYMARKET_HOST}/{}?{}", segment, params);
+    let result = native_fetch::get_text(&app, POLYMARKET_HOST, &url, "Polymarket", coalesce.unwrap_or(true)).await;
+    run_blocking(move || offline_cache::through(&app, &url, result)).await
+}
+
+/// Drop offline fallback copies older than `apiCacheMaxStaleHours`.
+#[tauri::command]
+async fn purge_stale_api_cache(webview: Webview, app: AppHandle) -> Result<offline_cache::PurgedApiCache, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || Ok(offline_cache::purge_stale(&app))).await
+}
+
+fn open_settings_window(app: &AppHandle) -> Result<(), String> {
+    open_settings_window_at(app, None)
+}
+
+/// Open (or focus) the settings window, switching to `section` if given.
+/// `section` must already be one of `SETTINGS_SECTIONS`.
+fn open_settings_window_at(app: &AppHandle, section: Option<&str>) -> Result<(), String> {
+    window_creation::create_or_focus(
+        app,
+        "settings",
+        || focus_settings_window(app, section),
+        || build_settings_window(app, section),
+    )
+}
+
+fn focus_settings_window(app: &AppHandle, section: Option<&str>) -> Result<(), String> {
+    let Some(window) = app.get_webview_window("settings") else {
+        return Ok(());
+    };
+    let _ = window.show();
+    window
+        .set_focus()
+        .map_err(|e| format!("Failed to focus settings window: {e}"))?;
+    if let Some(section) = section {
+        app.emit_to("settings", "settings-navigate", section)
+            .map_err(|e| format!("Failed to switch settings section: {e}"))?;
+    }
+    Ok(())
+}
+
+fn build_settings_window(app: &AppHandle, section: Option<&str>) -> Result<(), String> {
+    // Changes made before this window opened were not made in it.
+    app.state::<SettingsSessionState>().take();
+    let page = match section {
+        Some(section) => format!("settings.html#{section}"),
+        None => "settings.html".to_string(),
+    };
+    let (min_width, min_height) = SETTINGS_WINDOW_MIN_SIZE;
+    let (window_theme, background) = theme::for_new_window(app);
+    let mut builder = WebviewWindowBuilder::new(app, "settings", WebviewUrl::App(page.into()))
+        .title("World Monitor Settings")
+        .min_inner_size(min_width, min_height)
+        .resizable(true)
+        .content_protected(content_protection::for_new_window(app, "settings"))
+        .theme(window_theme)
+        .background_color(background);
+    builder = match window_geometry::restore(app, PREF_SETTINGS_WINDOW_GEOMETRY, SETTINGS_WINDOW_MIN_SIZE) {
+        Some(geometry) => builder
+            .inner_size(geometry.width, geometry.height)
+            .position(geometry.x, geometry.y),
+        None => builder.inner_size(980.0, 600.0),
+    };
+
+    // Opt-in: attach settings to main as an owned window so the OS keeps it
+    // stacked above main without us ever moving keyboard focus. Clicking main
+    // still focuses main, unless the modal pref also disables it.
+    let pref = |key| {
+        app.try_state::<RuntimePrefs>()
+            .map(|prefs| prefs.get_bool(key, false))
+            .unwrap_or(false)
+    };
+    let modal = pref(PREF_SETTINGS_MODAL);
+    let main_window = app.get_webview_window("main");
+    if pref(PREF_KEEP_SETTINGS_ABOVE_MAIN) || modal {
+        if let Some(main_window) = &main_window {
+            builder = builder
+                .parent(main_window)
+                .map_err(|e| format!("Failed to attach settings window to main: {e}"))?;
+        }
+    }
+
+    let Some(_settings_window) =
+        window_creation::build(builder).map_err(|e| format!("Failed to create settings window: {e}"))?
+    else {
+        return focus_settings_window(app, section);
+    };
+    if let (true, Some(main_window)) = (modal, &main_window) {
+        // Re-enabled when settings is destroyed.
+        if let Err(err) = main_window.set_enabled(false) {
+            log_event(app, "WARN", "settings_modal_failed", &[("error", &err.to_string())]);
+        }
+    }
+
+    // On Windows/Linux, menus are per-window. Remove the inherited app menu
+    // from the settings window (macOS uses a shared app-wide menu bar instead).
+    #[cfg(not(target_os = "macos"))]
+    let _ = _settings_window.remove_menu();
+
+    if let Err(err) = webview_text::apply_to_window(app, &_settings_window) {
+        log_event(app, "WARN", "text_input_apply_failed", &[("window", "settings"), ("error", &err)]);
+    }
+
+    Ok(())
+}
+
+fn save_settings_window_geometry(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("settings") else {
+        return;
+    };
+    if let Err(err) = window_geometry::save(app, PREF_SETTINGS_WINDOW_GEOMETRY, &window) {
+        log_event(app, "WARN", "settings_geometry_save_failed", &[("error", &err)]);
+    }
+}
+
+/// Tell the main window what the settings session changed. Runs on
+/// `Destroyed`, which follows both `close_settings_window` and the OS close
+/// button.
+fn notify_settings_closed(app: &AppHandle) {
+    if let Some(main_window) = app.get_webview_window("main") {
+        if !main_window.is_enabled().unwrap_or(true) {
+            let _ = main_window.set_enabled(true);
+        }
+    }
+    let changes = app.state::<SettingsSessionState>().take();
+    let secrets = changes.secrets_changed.len().to_string();
+    let prefs = changes.prefs_changed.len().to_string();
+    log_event(app, "INFO", "settings_closed", &[("secrets_changed", &secrets), ("prefs_changed", &prefs)]);
+    if let Err(err) = app.emit_to("main", "settings-closed", changes) {
+        log_event(app, "WARN", "settings_closed_emit_failed", &[("error", &err.to_string())]);
+    }
+}
+
+/// Whether closing the main window should hide it to the tray.
+#[cfg(not(target_os = "macos"))]
+fn close_to_tray(app: &AppHandle) -> bool {
+    #[cfg(feature = "tray")]
+    {
+        tray::is_available(app) && app.state::<RuntimePrefs>().get_bool(PREF_CLOSE_TO_TRAY, false)
+    }
+    #[cfg(not(feature = "tray"))]
+    {
+        let _ = app;
+        false
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        taskbar::reapply(app);
+    }
+}
+
+fn is_deep_link_token(token: &str) -> bool {
+    !token.is_empty()
+        && token.len() <= 64
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn parse_deep_link(raw: &str) -> Result<DeepLink, String> {
+    if raw.len() > DEEP_LINK_MAX_LEN {
+        return Err(format!("Deep link exceeds {DEEP_LINK_MAX_LEN} characters"));
+    }
+    let parsed = Url::parse(raw).map_err(|_| "Invalid deep link URL".to_string())?;
+    if parsed.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!("Unsupported deep link scheme: {}", parsed.scheme()));
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() || parsed.port().is_some() {
+        return Err("Deep link must not contain credentials or a port".to_string());
+    }
+    if parsed.fragment().is_some() {
+        return Err("Deep link must not contain a fragment".to_string());
+    }
+
+    let target = parsed
+        .host_str()
+        .map(|h| h.to_ascii_lowercase())
+        .ok_or_else(|| "Deep link is missing a target".to_string())?;
+    if !DEEP_LINK_TARGETS.contains(&target.as_str()) {
+        return Err(format!("Unsupported deep link target: {target}"));
+    }
+
+    let segments: Vec<String> = parsed
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect();
+    if segments.len() > DEEP_LINK_MAX_SEGMENTS {
+        return Err("Deep link path has too many segments".to_string());
+    }
+    if let Some(bad) = segments.iter().find(|s| !is_deep_link_token(s)) {
+        return Err(format!("Invalid deep link path segment: {bad}"));
+    }
+
+    let mut params = BTreeMap::new();
+    for (key, value) in parsed.query_pairs() {
+        if params.len() >= DEEP_LINK_MAX_PARAMS {
+            return Err("Deep link has too many query parameters".to_string());
+        }
+        if !is_deep_link_token(&key) {
+            return Err(format!("Invalid deep link parameter name: {key}"));
+        }
+        if value.chars().count() > DEEP_LINK_MAX_PARAM_VALUE_LEN || value.chars().any(char::is_control) {
+            return Err(format!("Invalid value for deep link parameter {key}"));
+        }
+        params.insert(key.into_owned(), value.into_owned());
+    }
+
+    Ok(DeepLink {
+        target,
+        segments,
+        params,
+    })
+}
+
+fn looks_like_deep_link(arg: &str) -> bool {
+    arg.get(..DEEP_LINK_SCHEME.len() + 1)
+        .map(|prefix| prefix.eq_ignore_ascii_case(&format!("{DEEP_LINK_SCHEME}:")))
+        .unwrap_or(false)
+}
+
+fn emit_deep_link(app: &AppHandle, link: &DeepLink) {
+    show_main_window(app);
+    if let Err(err) = app.emit_to("main", "deep-link", link) {
+        append_desktop_log(app, "WARN", &format!("failed to emit deep link: {err}"));
+    }
+}
+
+fn dispatch_deep_link(app: &AppHandle, raw: &str) {
+    let link = match parse_deep_link(raw) {
+        Ok(link) => link,
+        Err(err) => {
+            append_desktop_log(app, "WARN", &format!("rejected deep link: {err}"));
+            return;
+        }
+    };
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!("deep link received target={} segments={}", link.target, link.segments.len()),
+    );
+
+    let Some(state) = app.try_state::<DeepLinkState>() else {
+        return;
+    };
+    let ready = *state.ready.lock().unwrap_or_else(|e| e.into_inner());
+    if ready {
+        emit_deep_link(app, &link);
+    } else {
+        state
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(link);
+    }
+}
+
+fn flush_pending_deep_links(app: &AppHandle) {
+    let Some(state) = app.try_state::<DeepLinkState>() else {
+        return;
+    };
+    *state.ready.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    let pending = std::mem::take(&mut *state.pending.lock().unwrap_or_else(|e| e.into_inner()));
+    for link in pending {
+        emit_deep_link(app, &link);
+    }
+}
+
+/// Entry point for arguments forwarded by a second launch. The single-instance
+/// plugin detects a live first instance via OS primitives (D-Bus name on Linux,
+/// named mutex on Windows, socket connect on macOS), so a crashed instance never
+/// leaves a stale lock behind that could block startup.
+fn handle_forwarded_args(app: &AppHandle, args: Vec<String>) {
+    append_desktop_log(
+        app,
+        "INFO",
+        &format!(
+            "second instance launch forwarded {} argument(s); focusing existing window",
+            args.len().saturating_sub(1)
+        ),
+    );
+    let mut handled_link = false;
+    for arg in args.iter().skip(1).filter(|arg| looks_like_deep_link(arg)) {
+        dispatch_deep_link(app, arg);
+        handled_link = true;
+    }
+    if !handled_link {
+        show_main_window(app);
+    }
+}
+
+fn open_splash_window(app: &AppHandle) -> Result<(), String> {
+    // A splash that is already up stays as it is.
+    window_creation::create_or_focus(app, "splash", || Ok(()), || {
+        let builder = WebviewWindowBuilder::new(app, "splash", WebviewUrl::App("splash.html".into()))
+            .title("World Monitor")
+            .inner_size(420.0, 260.0)
+            .resizable(false)
+            .decorations(false)
+            .center()
+            .background_color(tauri::webview::Color(26, 28, 30, 255));
+        window_creation::build(builder).map_err(|e| format!("Failed to create splash window: {e}"))?;
+        Ok(())
+    })
+}
+
+/// Small window explaining the crash-loop safe mode, with recovery actions.
+fn open_safe_mode_window(app: &AppHandle) -> Result<(), String> {
+    WebviewWindowBuilder::new(app, SAFE_MODE_WINDOW_LABEL, WebviewUrl::App("safe-mode.html".into()))
+        .title("World Monitor \u{2014} Safe Mode")
+        .inner_size(480.0, 300.0)
+        .resizable(false)
+        .center()
+        .always_on_top(true)
+        .background_color(tauri::webview::Color(26, 28, 30, 255))
+        .build()
+        .map_err(|e| format!("Failed to create safe mode window: {e}"))?;
+    Ok(())
+}
+
+/// The WebKitGTK env policy computed at launch, with the renderer env it
+/// produced. Read-only; `None` on other platforms.
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn get_linux_webkit_policy(app: AppHandle) -> Option<linux_webkit::LinuxWebkitEnvPolicy> {
+    app.try_state::<linux_webkit::LinuxWebkitEnvPolicy>()
+        .map(|policy| policy.inner().clone())
+}
+
+#[cfg(not(target_os = "linux"))]
+#[tauri::command]
+fn get_linux_webkit_policy() -> Option<()> {
+    None
+}
+
+/// Where each WebKitGTK env decision came from (env, `webkit-policy.json`,
+/// or automatic), plus the override file's path and any parse error.
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn get_webkit_policy_sources(app: AppHandle) -> Option<linux_webkit::WebkitPolicySources> {
+    app.try_state::<linux_webkit::LinuxWebkitEnvPolicy>()
+        .map(|policy| linux_webkit::WebkitPolicySources::from(policy.inner()))
+}
+
+#[cfg(not(target_os = "linux"))]
+#[tauri::command]
+fn get_webkit_policy_sources() -> Option<()> {
+    None
+}
+
+/// Current memory/CPU of the app and sidecar plus cache and log sizes.
+#[tauri::command]
+fn get_resource_usage(webview: Webview, app: AppHandle) -> Result<resources::ResourceUsage, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(resources::resource_usage(&app))
+}
+
+/// Samples recorded while the `resourceSampling` pref is on, oldest first.
+#[tauri::command]
+fn get_resource_history(
+    webview: Webview,
+    monitor: tauri::State<'_, resources::ResourceMonitor>,
+) -> Result<Vec<resources::ResourceSample>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(monitor.history())
+}
+
+/// Run the settings "Doctor" environment checks. Includes a keyring
+/// round-trip, so only trusted windows may call it.
+#[tauri::command]
+async fn run_environment_checks(webview: Webview, app: AppHandle) -> Result<doctor::EnvironmentReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || Ok(doctor::run_environment_checks(&app))).await
+}
+
+/// Each periodic maintenance task with its last run, for the Doctor tab.
+#[tauri::command]
+fn get_maintenance_status(webview: Webview, app: AppHandle) -> Result<Vec<maintenance::TaskStatus>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(maintenance::status(&app))
+}
+
+/// Run one maintenance task now, unless it is already running, and return
+/// its status once it finishes.
+#[tauri::command]
+async fn run_maintenance_task(
+    webview: Webview,
+    app: AppHandle,
+    id: String,
+) -> Result<maintenance::TaskStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    maintenance::run_now(&app, &id).await
+}
+
+#[tauri::command]
+async fn get_autostart_enabled(webview: Webview, app: AppHandle) -> Result<bool, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || autostart::autostart_enabled(&app)).await
+}
+
+#[tauri::command]
+async fn set_autostart_enabled(
+    webview: Webview,
+    app: AppHandle,
+    enabled: bool,
+    start_minimized: Option<bool>,
+) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || autostart::set_autostart_enabled(&app, enabled, start_minimized)).await
+}
+
+#[tauri::command]
+fn get_keep_awake(webview: Webview, app: AppHandle) -> Result<keep_awake::KeepAwakeStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(keep_awake::status(&app))
+}
+
+/// Returns what is actually held afterwards; a failed acquisition is logged
+/// and reported as inactive rather than as an error.
+#[tauri::command]
+async fn set_keep_awake(
+    webview: Webview,
+    app: AppHandle,
+    enabled: bool,
+    mode: Option<keep_awake::KeepAwakeMode>,
+    persist: Option<bool>,
+) -> Result<keep_awake::KeepAwakeStatus, DesktopError> {
+    require_trusted_window(webview.label())?;
+    let mode = mode.unwrap_or(keep_awake::KeepAwakeMode::Display);
+    let persist = persist.unwrap_or(true);
+    run_blocking(move || Ok(keep_awake::set_keep_awake(&app, enabled, mode, persist))).await
+}
+
+/// Async so it never waits on the main thread, which a registration in
+/// progress may be blocked on.
+#[tauri::command]
+async fn get_global_shortcut(webview: Webview, app: AppHandle) -> Result<Option<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(global_shortcut::current_shortcut(&app))
+}
+
+/// Registers `accelerator` as the show/hide shortcut, or clears it when
+/// `None` or blank. Returns the canonical form that was stored.
+#[tauri::command]
+async fn set_global_shortcut(
+    webview: Webview,
+    app: AppHandle,
+    accelerator: Option<String>,
+) -> Result<Option<String>, DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || global_shortcut::set_shortcut(&app, accelerator.as_deref())).await
+}
+
+/// `None` or 0 clears the badge. Before the main window exists the count is
+/// only remembered, and shown once it is.
+#[tauri::command]
+async fn set_badge_count(webview: Webview, app: AppHandle, count: Option<u32>) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    taskbar::set_badge_count(&app, count);
+    Ok(())
+}
+
+/// `value` is a percentage, clamped to 0..=100; `state: "none"` clears.
+#[tauri::command]
+async fn set_progress(
+    webview: Webview,
+    app: AppHandle,
+    state: taskbar::ProgressState,
+    value: Option<f64>,
+) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    taskbar::set_progress(&app, state, value);
+    Ok(())
+}
+
+/// Sensitive copies are cleared after `clear_after_secs` (default 30) unless
+/// the user has copied something else since.
+#[tauri::command]
+async fn copy_to_clipboard(
+    webview: Webview,
+    app: AppHandle,
+    text: String,
+    sensitive: bool,
+    clear_after_secs: Option<u32>,
+) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || clipboard::copy(&app, text, sensitive, clear_after_secs)).await
+}
+
+#[tauri::command]
+async fn clear_clipboard(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    run_blocking(move || clipboard::clear(&app)).await
+}
+
+#[tauri::command]
+fn dismiss_safe_mode(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    if webview.label() != SAFE_MODE_WINDOW_LABEL {
+        require_trusted_window(webview.label())?;
+    }
+    if let Some(window) = app.get_webview_window(SAFE_MODE_WINDOW_LABEL) {
+        let _ = window.close();
+    }
+    Ok(())
+}
+
+/// Drop every runtime pref back to its default. Also offered by the safe
+/// mode window, which is otherwise untrusted.
+#[tauri::command]
+fn reset_runtime_prefs(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    if webview.label() != SAFE_MODE_WINDOW_LABEL {
+        require_trusted_window(webview.label())?;
+    }
+    let prefs = app.state::<RuntimePrefs>();
+    let mut current = prefs.prefs.lock().unwrap_or_else(|e| e.into_inner());
+    let path = runtime_prefs_path(&app)?;
+    std::fs::write(&path, "{}")
+        .map_err(|e| DesktopError::io("Failed to write runtime prefs", &path, e))?;
+    current.clear();
+    log_event(&app, "INFO", "runtime_prefs_reset", &[("window", webview.label())]);
+    Ok(())
+}
+
+/// `--start-minimized` minimizes this launch even when `startMinimized` is
+/// off; `startInTray` keeps the window hidden instead, but only when a tray
+/// icon exists to bring it back.
+fn startup_visibility(
+    cli_start_minimized: bool,
+    pref_start_minimized: bool,
+    pref_start_in_tray: bool,
+    tray_available: bool,
+) -> StartupVisibility {
+    if pref_start_in_tray && tray_available {
+        StartupVisibility::Tray
+    } else if cli_start_minimized || pref_start_minimized || pref_start_in_tray {
+        StartupVisibility::Minimized
+    } else {
+        StartupVisibility::Shown
+    }
+}
+
+/// Swap the splash for the main window, honouring the startup visibility.
+/// Safe to call more than once.
+fn finish_startup(app: &AppHandle) {
+    let visibility = app
+        .try_state::<StartupVisibility>()
+        .map_or(StartupVisibility::Shown, |state| *state);
+    match visibility {
+        StartupVisibility::Shown => show_main_window(app),
+        StartupVisibility::Minimized => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.minimize();
+                let _ = window.show();
+                taskbar::reapply(app);
+            }
+        }
+        StartupVisibility::Tray => {}
+    }
+    if let Some(splash) = app.get_webview_window("splash") {
+        let _ = splash.close();
+    }
+    if !app.state::<CliOptions>().headless && !app.state::<SafeModeState>().status.active {
+        window_layout::restore_at_startup(app);
+    }
+}
+
+/// Start the sidecar off the main thread so the splash can render progress.
+/// On failure the splash stays up and offers "Open sidecar log" / "Retry".
+fn boot_local_api(app: &AppHandle) {
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        match start_local_api(&handle) {
+            Ok(()) => {
+                log_startup_stage(&handle, "ready");
+                finish_startup(&handle);
+            }
+            Err(err) => {
+                log_startup_failure(&handle, &err);
+                if handle.get_webview_window("splash").is_none() {
+                    show_main_window(&handle);
+                }
+            }
+        }
+        // Legacy keychain entries prompt one by one on macOS; with the
+        // window up, that no longer looks like a hung launch.
+        if keyring_migration::run(&handle) {
+            restart_local_api_if_running(&handle, "secrets_migrated");
+        }
+    });
+}
+
+/// Stage timings for this launch, in order.
+#[tauri::command]
+fn get_startup_profile(
+    webview: Webview,
+    profile: tauri::State<'_, StartupProfile>,
+) -> Result<startup_profile::StartupProfileReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(profile.report())
+}
+
+#[tauri::command]
+fn get_startup_status(state: tauri::State<'_, StartupState>) -> StartupStatus {
+    state.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Reveal the main window if the sidecar handshake hasn't finished (and
+/// hasn't failed) within the timeout. Arming again supersedes an earlier
+/// timer, so a retry gets the full timeout.
+fn arm_splash_timeout(app: &AppHandle) {
+    let Some(state) = app.try_state::<StartupState>() else {
+        return;
+    };
+    let timer = state.splash_timer.fetch_add(1, Ordering::SeqCst) + 1;
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_secs(STARTUP_SPLASH_TIMEOUT_SECS));
+        let state = handle.state::<StartupState>();
+        if state.splash_timer.load(Ordering::SeqCst) != timer {
+            return;
+        }
+        let failed = state.status.lock().unwrap_or_else(|e| e.into_inner()).error.is_some();
+        if !failed && handle.get_webview_window("splash").is_some() {
+            append_desktop_log(&handle, "WARN", "sidecar handshake timed out; showing main window");
+            finish_startup(&handle);
+        }
+    });
+}
+
+/// The splash calls its own commands but is otherwise untrusted.
+fn require_splash_or_trusted_window(label: &str) -> Result<(), DesktopError> {
+    if label == "splash" {
+        Ok(())
+    } else {
+        require_trusted_window(label)
+    }
+}
+
+#[tauri::command]
+fn retry_local_api_start(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_splash_or_trusted_window(webview.label())?;
+    append_desktop_log(&app, "INFO", "retrying local API sidecar start from splash");
+    boot_local_api(&app);
+    arm_splash_timeout(&app);
+    Ok(())
+}
+
+/// Recent sidecar starts, exits and restart decisions, oldest first.
+#[tauri::command]
+fn get_local_api_history(
+    webview: Webview,
+    app: AppHandle,
+) -> Result<sidecar_history::HistoryReport, DesktopError> {
+    require_trusted_window(webview.label())?;
+    Ok(app.state::<LocalApiState>().history.lock().unwrap_or_else(|e| e.into_inner()).report())
+}
+
+/// Clear the crash count after the breaker tripped and start the sidecar
+/// again.
+#[tauri::command]
+async fn reset_local_api_breaker(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_trusted_window(webview.label())?;
+    app.state::<LocalApiState>()
+        .history
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .reset(logging::now_iso8601());
+    log_event(&app, "INFO", "sidecar_breaker_reset", &[]);
+    run_blocking(move || start_local_api(&app).map_err(DesktopError::Internal)).await
+}
+
+#[tauri::command]
+fn dismiss_splash(webview: Webview, app: AppHandle) -> Result<(), DesktopError> {
+    require_splash_or_trusted_window(webview.label())?;
+    finish_startup(&app);
+    Ok(())
+}
+
+fn open_live_channels_window(app: &AppHandle, base_url: Option<String>) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("live-channels") {
+        let _ = window.show();
+        window
+            .set_focus()
+            .map_err(|e| format!("Failed to focus live channels window: {e}"))?;
+        return Ok(());
+    }
+
+    // In dev, use the same origin as the main window (e.g. http://localhost:3001) so we don't
+    // get "connection refused" when Vite runs on a different port than devUrl.
+    let url = match base_url {
+        Some(ref origin) if !origin.is_empty() => {
+            let path = origin.trim_end_matches('/');
+            let full_url = format!("{}/live-channels.html", path);
+            WebviewUrl::External(Url::parse(&full_url).map_err(|_| "Invalid base URL".to_string())?)
+        }
+        _ => WebviewUrl::App("live-channels.html".into()),
+    };
+
+    let _live_channels_window = WebviewWindowBuilder::new(app, "live-channels", url)
+    .title("Channel management - World Monitor")
+    .inner_size(680.0, 760.0)
+    .min_inner_size(520.0, 600.0)
+    .resizable(true)
+    .background_color(tauri::webview::Color(26, 28, 30, 255))
+    .build()
+    .map_err(|e| format!("Failed to create live channels window: {e}"))?;
+
+    #[cfg(not(target_os = "macos"))]
+    let _ = _live_channels_window.remove_menu();
+
+    Ok(())
+}
+
+fn open_youtube_login_window(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("youtube-login") {
+        let _ = window.show();
+        window
+            .set_focus()
+            .map_err(|e| format!("Failed to focus YouTube login window: {e}"))?;
+        return Ok(());
+    }
+
+    let url = WebviewUrl::External(
+        Url::parse("https://accounts.google.com/ServiceLogin?service=youtube&continue=https://www.youtube.com/")
+            .map_err(|e| format!("Invalid URL: {e}"))?
+    );
+
+    let _yt_window = WebviewWindowBuilder::new(app, "youtube-login", url)
+        .title("Sign in to YouTube")
+        .inner_size(500.0, 700.0)
+        .resizable(true)
+        .build()
+        .map_err(|e| format!("Failed to create YouTube login window: {e}"))?;
+
+    #[cfg(not(target_os = "macos"))]
+    let _ = _yt_window.remove_menu();
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn open_youtube_login(app: AppHandle) -> Result<(), DesktopError> {
+    open_youtube_login_window(&app).map_err(DesktopError::Internal)
+}
+
+fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let language = i18n::language(handle);
+    let t = |id| i18n::text(language, id);
     let settings_item = MenuItem::with_id(
         handle,
         MENU_FILE_SETTINGS_ID,
-        "Settings...",
+        t("menu.file.settings"),
         true,
         Some("CmdOrCtrl+,"),
     )?;
+    let export_screenshot_item = MenuItem::with_id(
+        handle,
+        window_capture::MENU_FILE_EXPORT_SCREENSHOT_ID,
+        t("menu.file.export_screenshot"),
+        true,
+        None::<&str>,
+    )?;
+    let restart_item = MenuItem::with_id(
+        handle,
+        restart::MENU_FILE_RESTART_ID,
+        t("menu.file.restart"),
+        true,
+        None::<&str>,
+    )?;
     let separator = PredefinedMenuItem::separator(handle)?;
-    let quit_item = PredefinedMenuItem::quit(handle, Some("Quit"))?;
+    let quit_item = PredefinedMenuItem::quit(handle, Some(t("menu.file.quit")))?;
     let file_menu = Submenu::with_items(
         handle,
-        "File",
+        t("menu.file"),
         true,
-        &[&settings_item, &separator, &quit_item],
+        &[&settings_item, &export_screenshot_item, &separator, &restart_item, &quit_item],
     )?;
 
     let about_metadata = AboutMetadata {
@@ -715,11 +3762,39 @@ fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
         ..Default::default()
     };
     let about_item =
-        PredefinedMenuItem::about(handle, Some("About World Monitor"), Some(about_metadata))?;
+        PredefinedMenuItem::about(handle, Some(t("menu.help.about")), Some(about_metadata))?;
     let github_item = MenuItem::with_id(
         handle,
         MENU_HELP_GITHUB_ID,
-        "GitHub Repository",
+        t("menu.help.github"),
+        true,
+        None::<&str>,
+    )?;
+    let check_updates_item = MenuItem::with_id(
+        handle,
+        MENU_HELP_CHECK_UPDATES_ID,
+        t("menu.help.check_updates"),
+        true,
+        None::<&str>,
+    )?;
+    let export_diagnostics_item = MenuItem::with_id(
+        handle,
+        MENU_HELP_EXPORT_DIAGNOSTICS_ID,
+        t("menu.help.export_diagnostics"),
+        true,
+        None::<&str>,
+    )?;
+    let show_logs_item = MenuItem::with_id(
+        handle,
+        MENU_HELP_SHOW_LOGS_ID,
+        t("menu.help.show_logs"),
+        true,
+        None::<&str>,
+    )?;
+    let open_data_item = MenuItem::with_id(
+        handle,
+        MENU_HELP_OPEN_DATA_ID,
+        t("menu.help.open_data"),
         true,
         None::<&str>,
     )?;
@@ -730,56 +3805,177 @@ fn build_app_menu(handle: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
         let devtools_item = MenuItem::with_id(
             handle,
             MENU_HELP_DEVTOOLS_ID,
-            "Toggle Developer Tools",
+            t("menu.help.devtools"),
             true,
             Some("CmdOrCtrl+Alt+I"),
         )?;
         Submenu::with_items(
             handle,
-            "Help",
+            t("menu.help"),
             true,
-            &[&about_item, &help_separator, &github_item, &devtools_item],
+            &[
+                &about_item,
+                &check_updates_item,
+                &export_diagnostics_item,
+                &show_logs_item,
+                &open_data_item,
+                &help_separator,
+                &github_item,
+                &devtools_item,
+            ],
         )?
     };
 
     #[cfg(not(feature = "devtools"))]
     let help_menu = Submenu::with_items(
         handle,
-        "Help",
+        t("menu.help"),
         true,
-        &[&about_item, &help_separator, &github_item],
+        &[
+            &about_item,
+            &check_updates_item,
+            &export_diagnostics_item,
+            &show_logs_item,
+            &open_data_item,
+            &help_separator,
+            &github_item,
+        ],
     )?;
 
     let edit_menu = {
-        let undo = PredefinedMenuItem::undo(handle, None)?;
-        let redo = PredefinedMenuItem::redo(handle, None)?;
+        let undo = PredefinedMenuItem::undo(handle, Some(t("menu.edit.undo")))?;
+        let redo = PredefinedMenuItem::redo(handle, Some(t("menu.edit.redo")))?;
         let sep1 = PredefinedMenuItem::separator(handle)?;
-        let cut = PredefinedMenuItem::cut(handle, None)?;
-        let copy = PredefinedMenuItem::copy(handle, None)?;
-        let paste = PredefinedMenuItem::paste(handle, None)?;
-        let select_all = PredefinedMenuItem::select_all(handle, None)?;
+        let cut = PredefinedMenuItem::cut(handle, Some(t("menu.edit.cut")))?;
+        let copy = PredefinedMenuItem::copy(handle, Some(t("menu.edit.copy")))?;
+        let paste = PredefinedMenuItem::paste(handle, Some(t("menu.edit.paste")))?;
+        let select_all = PredefinedMenuItem::select_all(handle, Some(t("menu.edit.select_all")))?;
+        let sep2 = PredefinedMenuItem::separator(handle)?;
+        let find = MenuItem::with_id(
+            handle,
+            find_in_page::MENU_EDIT_FIND_ID,
+            t("menu.edit.find"),
+            true,
+            Some("CmdOrCtrl+F"),
+        )?;
+        Submenu::with_items(
+            handle,
+            t("menu.edit"),
+            true,
+            &[&undo, &redo, &sep1, &cut, &copy, &paste, &select_all, &sep2, &find],
+        )?
+    };
+
+    let view_menu = {
+        let reload = MenuItem::with_id(
+            handle,
+            reload::MENU_VIEW_RELOAD_ID,
+            t("menu.view.reload"),
+            true,
+            Some("CmdOrCtrl+R"),
+        )?;
+        let force_reload = MenuItem::with_id(
+            handle,
+            reload::MENU_VIEW_FORCE_RELOAD_ID,
+            t("menu.view.force_reload"),
+            true,
+            Some("CmdOrCtrl+Shift+R"),
+        )?;
+        let pause_polling = CheckMenuItem::with_id(
+            handle,
+            polling::MENU_VIEW_PAUSE_POLLING_ID,
+            t("menu.view.pause_polling"),
+            true,
+            polling::is_paused(handle),
+            None::<&str>,
+        )?;
+        let separator = PredefinedMenuItem::separator(handle)?;
         Submenu::with_items(
             handle,
-            "Edit",
+            t("menu.view"),
             true,
-            &[&undo, &redo, &sep1, &cut, &copy, &paste, &select_all],
+            &[&reload, &force_reload, &separator, &pause_polling],
         )?
     };
 
-    Menu::with_items(handle, &[&file_menu, &edit_menu, &help_menu])
+    let window_menu = panel_windows::window_submenu(handle, language)?;
+
+    Menu::with_items(handle, &[&file_menu, &edit_menu, &view_menu, &window_menu, &help_menu])
+}
+
+/// Replace the app menu, e.g. after the UI language changed. Menus are
+/// built on the main thread; a no-op when running without one (headless).
+fn rebuild_app_menu(app: &AppHandle) {
+    if app.menu().is_none() {
+        return;
+    }
+    let handle = app.clone();
+    let result = app.run_on_main_thread(move || {
+        let result = build_app_menu(&handle).and_then(|menu| handle.set_menu(menu).map(|_| ()));
+        match result {
+            Ok(()) => panel_windows::refresh_window_menu(&handle, None),
+            Err(err) => log_event(&handle, "WARN", "menu_rebuild_failed", &[("error", &err.to_string())]),
+        }
+    });
+    if let Err(err) = result {
+        log_event(app, "WARN", "menu_rebuild_failed", &[("error", &err.to_string())]);
+    }
 }
 
 fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
     match event.id().as_ref() {
         MENU_FILE_SETTINGS_ID => {
             if let Err(err) = open_settings_window(app) {
-                append_desktop_log(app, "ERROR", &format!("settings menu failed: {err}"));
+                log_event(app, "ERROR", "menu_action_failed", &[("item", MENU_FILE_SETTINGS_ID), ("error", &err)]);
                 eprintln!("[tauri] settings menu failed: {err}");
             }
         }
+        window_capture::MENU_FILE_EXPORT_SCREENSHOT_ID => {
+            // The snapshot comes back on the main thread, so wait elsewhere.
+            let handle = app.clone();
+            std::thread::spawn(move || match window_capture::capture_window(&handle, "main", None, false) {
+                Ok(capture) => {
+                    let _ = handle.emit("screenshot-exported", &capture);
+                }
+                Err(err) => {
+                    log_event(&handle, "WARN", "menu_action_failed", &[("item", window_capture::MENU_FILE_EXPORT_SCREENSHOT_ID), ("error", &err.to_string())]);
+                    let _ = handle.emit("screenshot-failed", &err);
+                }
+            });
+        }
         MENU_HELP_GITHUB_ID => {
             let _ = open_in_shell("https://github.com/koala73/worldmonitor");
         }
+        MENU_HELP_CHECK_UPDATES_ID => {
+            let handle = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let result = check_for_updates_impl(&handle, true).await;
+                let _ = handle.emit("update-check-result", result);
+            });
+        }
+        MENU_HELP_EXPORT_DIAGNOSTICS_ID => {
+            let handle = app.clone();
+            std::thread::spawn(move || match diagnostics::export_diagnostics_bundle(&handle, None) {
+                Ok(path) => {
+                    let _ = reveal::reveal_path(&path);
+                    let _ = handle.emit("diagnostics-exported", path.display().to_string());
+                }
+                Err(err) => {
+                    log_event(&handle, "ERROR", "menu_action_failed", &[("item", MENU_HELP_EXPORT_DIAGNOSTICS_ID), ("error", &err.to_string())]);
+                    eprintln!("[tauri] diagnostics export failed: {err}");
+                }
+            });
+        }
+        MENU_HELP_SHOW_LOGS_ID => {
+            if let Err(err) = open_logs_folder_impl(app) {
+                log_event(app, "ERROR", "menu_action_failed", &[("item", MENU_HELP_SHOW_LOGS_ID), ("error", &err)]);
+            }
+        }
+        MENU_HELP_OPEN_DATA_ID => {
+            if let Err(err) = open_app_data_folder_impl(app) {
+                log_event(app, "ERROR", "menu_action_failed", &[("item", MENU_HELP_OPEN_DATA_ID), ("error", &err)]);
+            }
+        }
         #[cfg(feature = "devtools")]
         MENU_HELP_DEVTOOLS_ID => {
             if let Some(window) = app.get_webview_window("main") {
@@ -790,7 +3986,15 @@ fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
                 }
             }
         }
-        _ => {}
+        id => {
+            if !reload::handle_menu_event(app, id)
+                && !find_in_page::handle_menu_event(app, id)
+                && !restart::handle_menu_event(app, id)
+                && !polling::handle_menu_event(app, id)
+            {
+                panel_windows::handle_menu_event(app, id);
+            }
+        }
     }
 }
 
@@ -808,117 +4012,44 @@ fn sanitize_path_for_node(p: &Path) -> String {
     }
 }
 
-#[cfg(test)]
-mod sanitize_path_tests {
-    use super::sanitize_path_for_node;
-    use std::path::Path;
-
-    #[test]
-    fn strips_extended_drive_prefix() {
-        let raw = Path::new(r"\\?\C:\Program Files\nodejs\node.exe");
-        assert_eq!(
-            sanitize_path_for_node(raw),
-            r"C:\Program Files\nodejs\node.exe".to_string()
-        );
-    }
-
-    #[test]
-    fn strips_extended_unc_prefix_and_preserves_unc_root() {
-        let raw = Path::new(r"\\?\UNC\server\share\sidecar\local-api-server.mjs");
-        assert_eq!(
-            sanitize_path_for_node(raw),
-            r"\\server\share\sidecar\local-api-server.mjs".to_string()
-        );
-    }
-
-    #[test]
-    fn leaves_standard_paths_unchanged() {
-        let raw = Path::new(r"C:\Users\alice\sidecar\local-api-server.mjs");
-        assert_eq!(
-            sanitize_path_for_node(raw),
-            r"C:\Users\alice\sidecar\local-api-server.mjs".to_string()
-        );
+fn local_api_paths(app: &AppHandle) -> sidecar_paths::LocalApiPaths {
+    if cfg!(debug_assertions) {
+        return sidecar_paths::select_dev(Path::new(env!("CARGO_MANIFEST_DIR")));
     }
-}
-
-fn local_api_paths(app: &AppHandle) -> (PathBuf, PathBuf) {
     let resource_dir = app
         .path()
         .resource_dir()
         .unwrap_or_else(|_| PathBuf::from("."));
-
-    let sidecar_script = if cfg!(debug_assertions) {
-        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sidecar/local-api-server.mjs")
-    } else {
-        resource_dir.join("sidecar/local-api-server.mjs")
-    };
-
-    let api_dir_root = if cfg!(debug_assertions) {
-        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .parent()
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."))
-    } else {
-        let direct_api = resource_dir.join("api");
-        let lifted_root = resource_dir.join("_up_");
-        let lifted_api = lifted_root.join("api");
-        if direct_api.exists() {
-            resource_dir
-        } else if lifted_api.exists() {
-            lifted_root
-        } else {
-            resource_dir
-        }
-    };
-
-    (sidecar_script, api_dir_root)
+    sidecar_paths::select_packaged(&resource_dir)
 }
 
 fn resolve_node_binary(app: &AppHandle) -> Option<PathBuf> {
-    if let Ok(explicit) = env::var("LOCAL_API_NODE_BIN") {
-        let explicit_path = PathBuf::from(explicit);
-        if explicit_path.is_file() {
-            return Some(explicit_path);
-        }
-        append_desktop_log(
-            app,
-            "WARN",
-            &format!(
-                "LOCAL_API_NODE_BIN is set but not a valid file: {}",
-                explicit_path.display()
-            ),
-        );
-    }
+    let mut roots = node_binary::SearchRoots {
+        explicit: env::var_os("LOCAL_API_NODE_BIN").map(PathBuf::from),
+        ..node_binary::SearchRoots::default()
+    };
 
     if !cfg!(debug_assertions) {
-        let node_name = if cfg!(windows) { "node.exe" } else { "node" };
         if let Ok(resource_dir) = app.path().resource_dir() {
-            let mut candidates = vec![resource_dir.join("sidecar").join("node").join(node_name)];
+            roots.bundled.push(resource_dir.join("sidecar").join("node").join(node_binary::NODE_NAME));
             if cfg!(windows) {
                 // NSIS resource paths can flatten nested names in some upgrade scenarios.
                 // Keep this fallback so sidecar startup still succeeds if the runtime is
                 // materialized as sidecar\node.node.exe instead of sidecar\node\node.exe.
-                candidates.push(resource_dir.join("sidecar").join("node.node.exe"));
-            }
-            for bundled in candidates {
-                if bundled.is_file() {
-                    return Some(bundled);
-                }
+                roots.bundled.push(resource_dir.join("sidecar").join("node.node.exe"));
             }
         }
     }
 
-    let node_name = if cfg!(windows) { "node.exe" } else { "node" };
     if let Some(path_var) = env::var_os("PATH") {
-        for dir in env::split_paths(&path_var) {
-            let candidate = dir.join(node_name);
-            if candidate.is_file() {
-                return Some(candidate);
-            }
-        }
+        roots.path_dirs = env::split_paths(&path_var).collect();
     }
 
-    let common_locations = if cfg!(windows) {
+    // Inside a Flatpak, PATH and /usr are the runtime's, not the host's.
+    if packaging::is_flatpak() {
+        roots.common.extend(packaging::flatpak_node_candidates(Path::new("/usr/lib/sdk")));
+    }
+    roots.common.extend(if cfg!(windows) {
         vec![
             PathBuf::from(r"C:\Program Files\nodejs\node.exe"),
             PathBuf::from(r"C:\Program Files (x86)\nodejs\node.exe"),
@@ -930,28 +4061,51 @@ fn resolve_node_binary(app: &AppHandle) -> Option<PathBuf> {
             PathBuf::from("/usr/bin/node"),
             PathBuf::from("/opt/local/bin/node"),
         ]
-    };
+    });
 
-    common_locations.into_iter().find(|path| path.is_file())
+    let roots = roots.with_version_managers(|var| env::var_os(var).map(PathBuf::from), dirs::home_dir().as_deref());
+    node_binary::resolve(app, &roots)
 }
 
-fn read_port_file(path: &Path, timeout_ms: u64) -> Option<u16> {
+/// The port the sidecar bound and, from current sidecars, the address.
+fn read_port_file(path: &Path, timeout_ms: u64) -> Option<(u16, Option<IpAddr>)> {
     let start = std::time::Instant::now();
     let interval = std::time::Duration::from_millis(100);
     let timeout = std::time::Duration::from_millis(timeout_ms);
     while start.elapsed() < timeout {
-        if let Ok(contents) = fs::read_to_string(path) {
-            if let Ok(port) = contents.trim().parse::<u16>() {
-                if port > 0 {
-                    return Some(port);
-                }
-            }
+        if let Some(bound) = fs::read_to_string(path).ok().as_deref().and_then(local_api_probe::parse_port_file) {
+            return Some(bound);
         }
         std::thread::sleep(interval);
     }
     None
 }
 
+/// `--port` for this session, otherwise the built-in default.
+fn preferred_local_api_port(app: &AppHandle) -> u16 {
+    app.try_state::<CliOptions>()
+        .and_then(|cli| cli.port)
+        .unwrap_or(DEFAULT_LOCAL_API_PORT)
+}
+
+/// `--listen` address for a headless session; otherwise the sidecar binds
+/// loopback only.
+fn local_api_listen_addr(app: &AppHandle) -> Option<IpAddr> {
+    app.try_state::<CliOptions>().and_then(|cli| cli.listen)
+}
+
+/// Where the sidecar writes the port it bound.
+fn sidecar_port_file(app: &AppHandle) -> Result<PathBuf, String> {
+    #[cfg(target_os = "linux")]
+    if let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") {
+        let dir = profile::scope_dir(&PathBuf::from(runtime_dir).join("world-monitor"));
+        if fs::create_dir_all(&dir).is_ok() {
+            return Ok(dir.join("sidecar.port"));
+        }
+    }
+    Ok(logs_dir_path(app)?.join("sidecar.port"))
+}
+
 fn start_local_api(app: &AppHandle) -> Result<(), String> {
     let state = app.state::<LocalApiState>();
     let mut slot = state
@@ -967,36 +4121,34 @@ fn start_local_api(app: &AppHandle) -> Result<(), String> {
         *port_slot = None;
     }
 
-    let (script, resource_root) = local_api_paths(app);
-    if !script.exists() {
+    let preferred_port = preferred_local_api_port(app);
+    let listen_addr = local_api_listen_addr(app);
+    let paths = local_api_paths(app);
+    log_event(
+        app,
+        "INFO",
+        "sidecar_paths",
+        &[
+            ("selected", paths.selected),
+            ("resource_root", &paths.resource_root.display().to_string()),
+            ("candidates", &paths.describe_candidates()),
+        ],
+    );
+    if !paths.script.exists() {
         return Err(format!(
             "Local API sidecar script missing at {}",
-            script.display()
+            paths.script.display()
         ));
     }
+    resource_integrity::check_on_start(app, paths.clone());
+    paths.validate()?;
+    let sidecar_paths::LocalApiPaths { script, resource_root, .. } = paths;
+    log_startup_stage(app, "resolving_node");
     let node_binary = resolve_node_binary(app).ok_or_else(|| {
         "Node.js executable not found. Install Node 18+ or set LOCAL_API_NODE_BIN".to_string()
     })?;
 
-    let port_file = {
-        #[cfg(target_os = "linux")]
-        {
-            if let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") {
-                let dir = PathBuf::from(runtime_dir).join("world-monitor");
-                if fs::create_dir_all(&dir).is_ok() {
-                    dir.join("sidecar.port")
-                } else {
-                    logs_dir_path(app)?.join("sidecar.port")
-                }
-            } else {
-                logs_dir_path(app)?.join("sidecar.port")
-            }
-        }
-        #[cfg(not(target_os = "linux"))]
-        {
-            logs_dir_path(app)?.join("sidecar.port")
-        }
-    };
+    let port_file = sidecar_port_file(app)?;
     let _ = fs::remove_file(&port_file);
 
     let log_path = sidecar_log_path(app)?;
@@ -1009,29 +4161,19 @@ fn start_local_api(app: &AppHandle) -> Result<(), String> {
         .try_clone()
         .map_err(|e| format!("Failed to clone local API log handle: {e}"))?;
 
-    append_desktop_log(
-        app,
-        "INFO",
-        &format!(
-            "starting local API sidecar script={} resource_root={} log={}",
-            script.display(),
-            resource_root.display(),
-            log_path.display()
-        ),
-    );
-    append_desktop_log(
-        app,
-        "INFO",
-        &format!("resolved node binary={}", node_binary.display()),
-    );
-    append_desktop_log(
+    log_event(
         app,
         "INFO",
-        &format!(
-            "local API sidecar preferred port={} port_file={}",
-            DEFAULT_LOCAL_API_PORT,
-            port_file.display()
-        ),
+        "sidecar_starting",
+        &[
+            ("script", &script.display().to_string()),
+            ("resource_root", &resource_root.display().to_string()),
+            ("log", &log_path.display().to_string()),
+            ("node_binary", &node_binary.display().to_string()),
+            ("preferred_port", &preferred_port.to_string()),
+            ("host", &listen_addr.map_or_else(|| "127.0.0.1".to_string(), |addr| addr.to_string())),
+            ("port_file", &port_file.display().to_string()),
+        ],
     );
 
     // Generate a unique token for local API auth (prevents other local processes from accessing sidecar)
@@ -1042,12 +4184,9 @@ fn start_local_api(app: &AppHandle) -> Result<(), String> {
     if token_slot.is_none() {
         *token_slot = Some(generate_local_token());
     }
-    let local_api_token = token_slot
-        .clone()
-        .ok_or_else(|| "Local API token not initialized after generation".to_string())?;
     drop(token_slot);
 
-    let mut cmd = Command::new(&node_binary);
+    let mut cmd = node_binary::command(&node_binary);
     #[cfg(windows)]
     cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW — hide the node.exe console
                                     // Sanitize paths for Node.js on Windows: strip \\?\ UNC prefix and set
@@ -1055,374 +4194,719 @@ fn start_local_api(app: &AppHandle) -> Result<(), String> {
                                     // cause EISDIR errors in Node.js module resolution.
     let script_for_node = sanitize_path_for_node(&script);
     let resource_for_node = sanitize_path_for_node(&resource_root);
-    append_desktop_log(
+    log_event(
         app,
         "INFO",
-        &format!("node args: script={script_for_node} resource_dir={resource_for_node}"),
+        "sidecar_node_args",
+        &[("script", &script_for_node), ("resource_dir", &resource_for_node)],
     );
-    let data_dir = logs_dir_path(app)
-        .map(|p| sanitize_path_for_node(&p))
-        .unwrap_or_else(|_| resource_for_node.clone());
-    cmd.arg(&script_for_node)
-        .env("LOCAL_API_PORT", DEFAULT_LOCAL_API_PORT.to_string())
-        .env("LOCAL_API_PORT_FILE", &port_file)
-        .env("LOCAL_API_RESOURCE_DIR", &resource_for_node)
-        .env("LOCAL_API_DATA_DIR", &data_dir)
-        .env("LOCAL_API_MODE", "tauri-sidecar")
-        .env("LOCAL_API_TOKEN", &local_api_token)
-        .stdout(Stdio::from(log_file))
-        .stderr(Stdio::from(log_file_err));
+    let node_args = sidecar_options::node_args(app);
+    cmd.args(&node_args)
+        .arg(&script_for_node)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
     if let Some(parent) = script.parent() {
         cmd.current_dir(parent);
     }
-
-    // Pass cached keychain secrets to sidecar as env vars (no keychain re-read)
-    let mut secret_count = 0u32;
-    let secrets_cache = app.state::<SecretsCache>();
-    if let Ok(secrets) = secrets_cache.secrets.lock() {
-        for (key, value) in secrets.iter() {
-            cmd.env(key, value);
-            secret_count += 1;
-        }
-    }
-    append_desktop_log(
+    let env_inputs = sidecar_env::inputs(
+        app,
+        forensics_worker::start_if_enabled(app),
+        extra_ca::prepare_sidecar_bundle(app),
+    )?;
+    let sidecar_env = sidecar_env::build_sidecar_env(&env_inputs);
+    sidecar_env::apply(&mut cmd, &sidecar_env);
+    log_event(
         app,
         "INFO",
-        &format!("injected {secret_count} keychain secrets into sidecar env"),
+        "sidecar_secrets_injected",
+        &[("count", &env_inputs.secrets.len().to_string())],
     );
-
-    // Inject build-time secrets (CI) with runtime env fallback (dev)
-    if let Some(url) = option_env!("CONVEX_URL") {
-        cmd.env("CONVEX_URL", url);
-    } else if let Ok(url) = std::env::var("CONVEX_URL") {
-        cmd.env("CONVEX_URL", url);
-    }
-
-    let child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to launch local API: {e}"))?;
-    append_desktop_log(
+    let argv: Vec<String> = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let extra_env_names: Vec<&str> = env_inputs.extra_env.iter().map(|(name, _)| name.as_str()).collect();
+    log_event(
         app,
         "INFO",
-        &format!("local API sidecar started pid={}", child.id()),
+        "sidecar_argv",
+        &[("argv", &argv.join(" ")), ("extra_env", &extra_env_names.join(","))],
     );
+    let mut injected: Vec<&str> = sidecar_env.iter().map(|(name, _)| name.as_str()).collect();
+    injected.sort_unstable();
+    append_desktop_debug(app, &format!("sidecar env injected: {}", injected.join(",")));
+
+    log_startup_stage(app, "spawning_sidecar");
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to launch local API: {e}"))?;
+    log_event(app, "INFO", "sidecar_started", &[("pid", &child.id().to_string())]);
+    sidecar_env::record_launch(app, &sidecar_env);
+    if let Some(stdout) = child.stdout.take() {
+        sidecar_log::forward(app, sidecar_log::Stream::Stdout, stdout, log_file);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        sidecar_log::forward(app, sidecar_log::Stream::Stderr, stderr, log_file_err);
+    }
+    let pid = child.id();
     *slot = Some(child);
+    record_local_api_event(app, sidecar_history::LifecycleKind::Started { pid });
+    set_local_api_lifecycle(app, LocalApiLifecycle::Starting);
+    let watcher = app.clone();
+    std::thread::spawn(move || watch_local_api(watcher, pid));
     drop(slot);
 
     // Wait for sidecar to write confirmed port (up to 5s)
-    if let Some(confirmed_port) = read_port_file(&port_file, 5000) {
-        append_desktop_log(
+    log_startup_stage(app, "waiting_for_health_check");
+    if let Some((confirmed_port, bound_host)) = read_port_file(&port_file, 5000) {
+        log_event(
             app,
             "INFO",
-            &format!("sidecar confirmed port={confirmed_port}"),
+            "sidecar_port_confirmed",
+            &[
+                ("port", &confirmed_port.to_string()),
+                ("host", &bound_host.map_or_else(|| "unknown".to_string(), |host| host.to_string())),
+            ],
         );
+        if let Err(err) = local_api_probe::check_bind(bound_host, listen_addr) {
+            log_event(app, "ERROR", "sidecar_bind_refused", &[("error", &err)]);
+            stop_local_api(app);
+            return Err(err);
+        }
         if let Ok(mut port_slot) = state.port.lock() {
             *port_slot = Some(confirmed_port);
         }
     } else {
-        append_desktop_log(
+        log_event(
             app,
             "WARN",
-            "sidecar port file not found within timeout, using default",
+            "sidecar_port_timeout",
+            &[("fallback_port", &preferred_port.to_string())],
         );
         if let Ok(mut port_slot) = state.port.lock() {
-            *port_slot = Some(DEFAULT_LOCAL_API_PORT);
+            *port_slot = Some(preferred_port);
         }
     }
 
     // Verify sidecar is listening on the assigned port
-    let health_port = state.port.lock().ok().and_then(|g| *g).unwrap_or(DEFAULT_LOCAL_API_PORT);
-    let addr: std::net::SocketAddr = ([127, 0, 0, 1], health_port).into();
-    match std::net::TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(2)) {
-        Ok(_) => append_desktop_log(app, "INFO", "sidecar health check passed"),
-        Err(e) => append_desktop_log(
+    let health_port = state.port.lock().ok().and_then(|g| *g).unwrap_or(preferred_port);
+    let health_ip = listen_addr
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    let addr = std::net::SocketAddr::new(health_ip, health_port);
+    let connectable = local_api_probe::connectable(addr, local_api_probe::HANDSHAKE_TIMEOUT);
+    let alive = state
+        .child
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|child| child.id() == pid);
+    let reach = local_api_probe::classify(alive, connectable);
+    match reach {
+        local_api_probe::Reachability::Reachable => log_event(
+            app,
+            "INFO",
+            "sidecar_health_check",
+            &[("port", &health_port.to_string()), ("result", "pass")],
+        ),
+        local_api_probe::Reachability::PortBlocked => log_event(
+            app,
+            "WARN",
+            "sidecar_health_check",
+            &[
+                ("port", &health_port.to_string()),
+                ("result", "port_blocked"),
+                ("guidance", &local_api_probe::blocked_guidance(addr)),
+            ],
+        ),
+        local_api_probe::Reachability::Exited => log_event(
             app,
             "WARN",
-            &format!("sidecar health check failed on port {health_port}: {e}"),
+            "sidecar_health_check",
+            &[("port", &health_port.to_string()), ("result", "exited")],
         ),
     }
+    mark_local_api_started(app, health_port, reach);
 
     Ok(())
 }
 
+/// SIGTERM, then a kill if the process is still there after 3 seconds
+/// (a kill straight away where there are no signals).
+fn graceful_kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        let pid = child.id() as i32;
+        let kill_result = unsafe { libc::kill(pid, libc::SIGTERM) };
+        if kill_result == 0 {
+            // Wait up to 3 seconds for graceful exit
+            for _ in 0..30 {
+                match child.try_wait() {
+                    Ok(Some(_)) => break,
+                    _ => std::thread::sleep(std::time::Duration::from_millis(100)),
+                }
+            }
+            // If still running, force kill
+            if child.try_wait().ok().flatten().is_none() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        } else {
+            // SIGTERM failed, force kill
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
 fn stop_local_api(app: &AppHandle) {
     if let Ok(state) = app.try_state::<LocalApiState>().ok_or(()) {
         if let Ok(mut slot) = state.child.lock() {
             if let Some(mut child) = slot.take() {
-                #[cfg(unix)]
-                {
-                    let pid = child.id() as i32;
-                    let kill_result = unsafe { libc::kill(pid, libc::SIGTERM) };
-                    if kill_result == 0 {
-                        // Wait up to 3 seconds for graceful exit
-                        for _ in 0..30 {
-                            match child.try_wait() {
-                                Ok(Some(_)) => break,
-                                _ => std::thread::sleep(std::time::Duration::from_millis(100)),
-                            }
-                        }
-                        // If still running, force kill
-                        if child.try_wait().ok().flatten().is_none() {
-                            let _ = child.kill();
-                            let _ = child.wait();
-                        }
-                    } else {
-                        // SIGTERM failed, force kill
-                        let _ = child.kill();
-                        let _ = child.wait();
-                    }
-                }
-                #[cfg(not(unix))]
-                {
-                    let _ = child.kill();
-                    let _ = child.wait();
-                }
-                append_desktop_log(app, "INFO", "local API sidecar stopped");
+                graceful_kill(&mut child);
+                log_event(app, "INFO", "sidecar_stopped", &[]);
             }
         }
+        // After the API process, so nothing calls the worker mid-shutdown.
+        forensics_worker::stop(app);
         if let Ok(mut port_slot) = state.port.lock() {
             *port_slot = None;
         }
-        // Clean up port file from both possible locations
+        *state.ready.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        *state.started_at.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        set_local_api_lifecycle(app, LocalApiLifecycle::Stopped);
+        // The profile's own port file, plus the log-dir fallback in case the
+        // runtime dir only became usable after the sidecar started.
+        if let Ok(port_file) = sidecar_port_file(app) {
+            let _ = fs::remove_file(port_file);
+        }
         if let Ok(log_dir) = logs_dir_path(app) {
             let _ = fs::remove_file(log_dir.join("sidecar.port"));
         }
-        #[cfg(target_os = "linux")]
-        {
-            if let Some(runtime_dir) = env::var_os("XDG_RUNTIME_DIR") {
-                let xdg_port = PathBuf::from(runtime_dir).join("world-monitor/sidecar.port");
-                let _ = fs::remove_file(xdg_port);
-            }
-        }
     }
 }
 
-#[cfg(target_os = "linux")]
-fn resolve_appimage_gio_module_dir() -> Option<PathBuf> {
-    let appdir = env::var_os("APPDIR")?;
-    let appdir = PathBuf::from(appdir);
-
-    // Common layouts produced by AppImage/linuxdeploy on Debian and RPM families.
-    let preferred = [
-        "usr/lib/gio/modules",
-        "usr/lib64/gio/modules",
-        "usr/lib/x86_64-linux-gnu/gio/modules",
-        "usr/lib/aarch64-linux-gnu/gio/modules",
-        "usr/lib/arm-linux-gnueabihf/gio/modules",
-        "lib/gio/modules",
-        "lib64/gio/modules",
-    ];
-
-    for relative in preferred {
-        let candidate = appdir.join(relative);
-        if candidate.is_dir() {
-            return Some(candidate);
-        }
-    }
-
-    // Fallback: probe one level of arch-specific directories, e.g. usr/lib/<triplet>/gio/modules.
-    for lib_root in ["usr/lib", "usr/lib64", "lib", "lib64"] {
-        let root = appdir.join(lib_root);
-        if !root.is_dir() {
-            continue;
-        }
-        let entries = match fs::read_dir(&root) {
-            Ok(entries) => entries,
-            Err(_) => continue,
-        };
-        for entry in entries.flatten() {
-            let candidate = entry.path().join("gio/modules");
-            if candidate.is_dir() {
-                return Some(candidate);
-            }
-        }
+fn main() {
+    let startup_profile = StartupProfile::new(Instant::now());
+    crash::install_panic_hook();
+
+    let cli_options = cli::parse_cli_args(env::args().skip(1));
+    if cli_options.print_version {
+        println!("World Monitor {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    if cli_options.print_help {
+        println!("{}", cli::USAGE);
+        return;
     }
 
-    None
-}
+    profile::activate(profile::resolve(
+        cli_options.profile.as_deref(),
+        env::var("WM_KEYRING_SERVICE").ok().as_deref(),
+        cfg!(debug_assertions),
+    ));
+
+    let headless = cli_options.headless;
+    if headless {
+        headless::install_process_hooks();
+    }
+    let mut context = tauri::generate_context!();
+    if headless {
+        // The main window is declared in tauri.conf.json; drop it so nothing
+        // is created.
+        context.config_mut().app.windows.clear();
+    }
+    let marker_path = safe_mode::startup_marker_path(&context.config().identifier);
+    let unclean_launches = marker_path
+        .as_deref()
+        .map_or(0, safe_mode::read_unclean_launches);
+    let crash_loop = safe_mode::is_crash_loop(unclean_launches);
+    // A crash loop wins so the safe-mode window is still offered.
+    let forced_safe_mode = if crash_loop {
+        Some("crash_loop")
+    } else if cli_options.safe_mode {
+        Some("cli")
+    } else {
+        None
+    };
 
-fn main() {
-    // Work around WebKitGTK rendering issues on Linux that can cause blank white
-    // screens. DMA-BUF renderer failures are common with NVIDIA drivers and on
-    // immutable distros (e.g. Bazzite/Fedora Atomic).  Setting the env var before
-    // WebKit initialises forces a software fallback path.  Only set when the user
-    // hasn't explicitly configured the variable.
     #[cfg(target_os = "linux")]
-    {
-        if env::var_os("WEBKIT_DISABLE_DMABUF_RENDERER").is_none() {
-            // SAFETY: called before any threads are spawned (Tauri hasn't started yet).
-            unsafe { env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1") };
-        }
-
-        // WebKitGTK promotes iframes, <video>, and canvas to GPU-textured
-        // compositing layers.  In VMs (Apple Virtualization.framework,
-        // QEMU/KVM, VMware, etc.) the virtio-gpu driver often only supports
-        // 2D or limited GL — GBM buffer allocation for compositing layers
-        // fails silently, rendering iframe/video content as black while the
-        // main page (software-tiled) works fine.
-        //
-        // Detect VM environments via /proc/cpuinfo "hypervisor" flag or
-        // sys_vendor strings and disable accelerated compositing + force
-        // software GL so all content renders through the CPU path.
-        let in_vm = std::fs::read_to_string("/proc/cpuinfo")
-            .map(|c| c.contains("hypervisor"))
-            .unwrap_or(false)
-            || std::fs::read_to_string("/sys/class/dmi/id/sys_vendor")
-                .map(|v| {
-                    let v = v.trim().to_lowercase();
-                    v.contains("qemu") || v.contains("vmware") || v.contains("virtualbox")
-                        || v.contains("apple") || v.contains("parallels") || v.contains("xen")
-                        || v.contains("microsoft") || v.contains("innotek")
-                })
-                .unwrap_or(false);
-
-        if in_vm {
-            if env::var_os("WEBKIT_DISABLE_COMPOSITING_MODE").is_none() {
-                unsafe { env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1") };
-            }
-            if env::var_os("LIBGL_ALWAYS_SOFTWARE").is_none() {
-                unsafe { env::set_var("LIBGL_ALWAYS_SOFTWARE", "1") };
-            }
-            eprintln!("[tauri] VM detected; disabled WebKitGTK accelerated compositing for iframe/video compatibility");
-        }
-
-        // NVIDIA proprietary drivers often fail to create a surfaceless EGL
-        // display (EGL_BAD_ALLOC) in WebKitGTK's web process, especially on
-        // Wayland where explicit sync can also cause flickering/crashes.
-        // Detect NVIDIA by checking for /proc/driver/nvidia (created by
-        // nvidia.ko) and apply Wayland-specific workarounds.
-        let has_nvidia = std::path::Path::new("/proc/driver/nvidia").exists();
-        if has_nvidia {
-            if env::var_os("__NV_DISABLE_EXPLICIT_SYNC").is_none() {
-                unsafe { env::set_var("__NV_DISABLE_EXPLICIT_SYNC", "1") };
-            }
-            // Force X11 backend on NVIDIA + Wayland to avoid surfaceless EGL
-            // failures.  Users who prefer native Wayland can override with
-            // GDK_BACKEND=wayland.
-            if env::var_os("WAYLAND_DISPLAY").is_some() && env::var_os("GDK_BACKEND").is_none() {
-                unsafe { env::set_var("GDK_BACKEND", "x11") };
-                eprintln!(
-                    "[tauri] NVIDIA GPU + Wayland detected; forcing GDK_BACKEND=x11 to avoid EGL_BAD_ALLOC. \
-                     Set GDK_BACKEND=wayland to override."
-                );
-            }
-        }
+    let linux_webkit_policy = {
+        let mut policy = linux_webkit::compute_linux_webkit_policy(
+            |name| env::var_os(name),
+            &linux_webkit::LinuxGraphicsProbe::detect(),
+            &linux_webkit::load_policy_overrides(&context.config().identifier),
+            forced_safe_mode,
+        );
+        policy.apply_scale_factor(
+            |name| env::var_os(name),
+            display_scale::read_launch_pref(&context.config().identifier),
+        );
+        linux_webkit::apply_linux_webkit_env_policy(&mut policy);
+        eprintln!(
+            "[tauri] Linux WebKit policy: {}",
+            linux_webkit::format_linux_webkit_policy(&policy)
+        );
+        policy
+    };
+    #[cfg(target_os = "linux")]
+    let display_scale_state = display_scale::DisplayScaleState {
+        applied: linux_webkit_policy.force_scale_factor,
+    };
+    #[cfg(not(target_os = "linux"))]
+    let display_scale_state = display_scale::DisplayScaleState::default();
+    #[cfg(target_os = "windows")]
+    let text_input_state = webview_text::TextInputState {
+        launch_language: webview_text::apply_launch_language(&context.config().identifier),
+    };
+    #[cfg(not(target_os = "windows"))]
+    let text_input_state = webview_text::TextInputState::default();
+    #[cfg(target_os = "linux")]
+    let env_safe_mode = linux_webkit_policy.safe_mode == Some("env");
+    #[cfg(not(target_os = "linux"))]
+    let env_safe_mode = false;
 
-        // On Wayland-only compositors (e.g. niri, river, sway without XWayland),
-        // GTK3 may fail to initialise if it defaults to X11 backend first and no
-        // DISPLAY is set.  Explicitly prefer the Wayland backend when a Wayland
-        // display is available.  Falls back to X11 if Wayland init fails.
-        if env::var_os("WAYLAND_DISPLAY").is_some() && env::var_os("GDK_BACKEND").is_none() {
-            unsafe { env::set_var("GDK_BACKEND", "wayland,x11") };
-        }
-
-        // Work around GLib version mismatch when running as an AppImage on newer
-        // distros.  The AppImage bundles GLib from the CI build system (Ubuntu
-        // 24.04, GLib 2.80).  Host GIO modules (e.g. GVFS's libgvfsdbus.so) may
-        // link against newer GLib symbols absent in the bundled copy, producing:
-        //   "undefined symbol: g_task_set_static_name"
-        // Point GIO_MODULE_DIR at the AppImage's bundled modules to isolate from
-        // host libraries.  Also disable the WebKit bubblewrap sandbox which fails
-        // inside AppImage's FUSE mount (causes blank screen on many distros).
-        if env::var_os("APPIMAGE").is_some() && env::var_os("GIO_MODULE_DIR").is_none() {
-            if let Some(module_dir) = resolve_appimage_gio_module_dir() {
-                unsafe { env::set_var("GIO_MODULE_DIR", &module_dir) };
-            } else if env::var_os("GIO_USE_VFS").is_none() {
-                // Last-resort fallback: prefer local VFS backend if module path
-                // discovery fails, which reduces GVFS dependency surface.
-                unsafe { env::set_var("GIO_USE_VFS", "local") };
-                eprintln!(
-                    "[tauri] APPIMAGE detected but bundled gio/modules not found; using GIO_USE_VFS=local fallback"
-                );
-            }
-        }
+    let safe_mode_reason = if env_safe_mode && !crash_loop {
+        Some("env")
+    } else {
+        forced_safe_mode
+    };
+    let safe_mode_state = SafeModeState {
+        status: SafeModeStatus {
+            active: safe_mode_reason.is_some(),
+            reason: safe_mode_reason.map(str::to_string),
+            unclean_launches,
+        },
+        marker_path,
+    };
 
-        // WebKit2GTK's bubblewrap sandbox can fail inside an AppImage FUSE
-        // mount, causing blank white screens. Disable it when running as
-        // AppImage — the AppImage itself already provides isolation.
-        if env::var_os("APPIMAGE").is_some() {
-            // WebKitGTK 2.39.3+ deprecated WEBKIT_FORCE_SANDBOX and now expects
-            // WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS=1 instead.  Setting the
-            // old variable on newer WebKitGTK triggers a noisy deprecation
-            // warning in the system journal, so only set the new one.
-            if env::var_os("WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS").is_none() {
-                unsafe { env::set_var("WEBKIT_DISABLE_SANDBOX_THIS_IS_DANGEROUS", "1") };
-            }
-            // Prevent GTK from loading host input-method modules that may
-            // link against incompatible library versions.
-            if env::var_os("GTK_IM_MODULE").is_none() {
-                unsafe { env::set_var("GTK_IM_MODULE", "gtk-im-context-simple") };
-            }
+    startup_profile.begin("keychain", Instant::now());
+    let secrets_cache = SecretsCache::load(secret_store::open());
+    startup_profile.end_current(Instant::now());
+    let log_redaction =
+        LogRedaction::new(&secrets_cache.secrets.lock().unwrap_or_else(|e| e.into_inner()));
 
-            // The linuxdeploy GStreamer hook sets GST_PLUGIN_PATH_1_0 and
-            // GST_PLUGIN_SYSTEM_PATH_1_0 to only contain bundled plugins.
-            // CI installs the full GStreamer codec suite (base, good, bad,
-            // ugly, libav, gl) so bundleMediaFramework=true bundles everything.
-            //
-            // IMPORTANT: Do NOT append host plugin directories — mixing plugins
-            // compiled against a different GStreamer version causes ABI mismatches
-            // (undefined symbol errors like gst_util_floor_log2, mpg123_open_handle64)
-            // and leaves WebKit without usable codecs.  The AppImage must be fully
-            // self-contained for GStreamer.
-            //
-            // If the linuxdeploy hook didn't set the paths (shouldn't happen),
-            // explicitly block host plugin scanning to prevent ABI conflicts.
-            if env::var_os("GST_PLUGIN_SYSTEM_PATH_1_0").is_none() {
-                // Empty string prevents GStreamer from scanning /usr/lib/gstreamer-1.0
-                unsafe { env::set_var("GST_PLUGIN_SYSTEM_PATH_1_0", "") };
-            }
-        }
+    let mut builder = tauri::Builder::default();
+    // Must be registered first so a second launch exits before setup tries
+    // to spawn another sidecar on the same port. Named profiles have their own
+    // files and port, and are meant to run next to the default instance.
+    if profile::active().is_default() {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            handle_forwarded_args(app, args);
+        }));
     }
-
-    tauri::Builder::default()
-        .menu(build_app_menu)
-        .on_menu_event(handle_menu_event)
+    if !headless {
+        builder = builder.menu(build_app_menu).on_menu_event(handle_menu_event);
+    }
+    builder
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(global_shortcut::plugin())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(
+            tauri::plugin::Builder::<tauri::Wry>::new("frontend-error-hook")
+                .js_init_script(include_str!("frontend_error_hook.js"))
+                .build(),
+        )
+        .on_page_load(|webview, payload| {
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                find_in_page::clear_session(webview.app_handle(), webview.label());
+            }
+        })
         .manage(LocalApiState::default())
-        .manage(SecretsCache::load_from_keychain())
-        .invoke_handler(tauri::generate_handler![
+        .manage(DeepLinkState::default())
+        .manage(StartupState::default())
+        .manage(NotificationState::default())
+        .manage(FrontendLogState::default())
+        .manage(SettingsSessionState::default())
+        .manage(quit_guard::QuitGuardState::default())
+        .manage(keep_awake::KeepAwakeState::default())
+        .manage(global_shortcut::GlobalShortcutState::default())
+        .manage(taskbar::TaskbarState::default())
+        .manage(clipboard::ClipboardState::default())
+        .manage(content_protection::ContentProtectionState::default())
+        .manage(quiet_hours::QuietHoursState::default())
+        .manage(display_scale_state)
+        .manage(text_input_state)
+        .manage(rate_limit::RateLimiter::default())
+        .manage(broadcast::BroadcastState::default())
+        .manage(window_layout::WindowLayoutState::default())
+        .manage(reload::ReloadGuardState::default())
+        .manage(find_in_page::FindState::default())
+        .manage(disk_space::DiskSpaceState::default())
+        .manage(clock_skew::ClockSkewState::default())
+        .manage(source_health::SourceHealthState::default())
+        .manage(api_usage::ApiUsageState::default())
+        .manage(vault_events::VaultRevision::default())
+        .manage(polling::PollingState::default())
+        .manage(window_creation::WindowCreationState::default())
+        .manage(file_dialog::FileDialogState::default())
+        .manage(network_profile::NetworkProfileState::default())
+        .manage(theme::ThemeState::default())
+        .manage(restart::RestartState::default())
+        .manage(version_history::VersionState::default())
+        .manage(maintenance::MaintenanceScheduler::default())
+        .manage(sidecar_log::SidecarLogState::default())
+        .manage(sidecar_env::SidecarEnvState::default())
+        .manage(user_agent::UserAgentState::new(env!("CARGO_PKG_VERSION")))
+        .manage(native_fetch::NativeFetchState::default())
+        .manage(http_cache::HttpCacheState::default())
+        .manage(blob_store::BlobStoreState::default())
+        .manage(ws_bridge::WsBridgeState::default())
+        .manage(resources::ResourceMonitor::default())
+        .manage(secrets_cache)
+        .manage(startup_profile)
+        .manage(log_redaction)
+        .manage(safe_mode_state)
+        .manage(cli_options)
+        .manage(logging::LogLevelState::default())
+        .invoke_handler(crash::catch_command_panics(tauri::generate_handler![
             list_supported_secret_keys,
             get_secret,
             get_all_secrets,
+            get_secrets_summary,
             set_secret,
             delete_secret,
-            get_local_api_token,
-            get_local_api_port,
+            reload_secrets_from_keychain,
+            get_local_api_connection,
+            wait_for_local_api,
+            get_local_api_status,
             get_desktop_runtime_info,
+            get_linux_webkit_policy,
+            get_webkit_policy_sources,
+            run_environment_checks,
+            get_maintenance_status,
+            run_maintenance_task,
+            get_autostart_enabled,
+            set_autostart_enabled,
+            get_keep_awake,
+            set_keep_awake,
+            get_global_shortcut,
+            set_global_shortcut,
+            set_badge_count,
+            set_progress,
+            copy_to_clipboard,
+            clear_clipboard,
+            get_resource_usage,
+            get_resource_history,
+            get_runtime_prefs,
+            set_runtime_pref,
             read_cache_entry,
+            read_cache_entry_with_meta,
             write_cache_entry,
             delete_cache_entry,
             open_logs_folder,
+            open_app_data_folder,
+            get_app_paths,
+            get_sidecar_paths,
+            verify_resources,
+            get_active_profile,
+            get_data_source_toggles,
+            set_data_source_toggle,
+            get_trusted_local_hosts,
+            set_trusted_local_hosts,
+            get_quiet_hours,
+            set_quiet_hours,
+            get_quiet_hours_status,
+            set_content_protected,
+            get_content_protection_state,
+            set_reload_guard,
+            find_in_page,
+            stop_find,
+            capture_window,
+            pick_save_path,
+            pick_open_path,
+            get_clock_skew,
+            get_data_source_health,
+            get_api_usage_stats,
+            set_polling_paused,
+            get_polling_paused,
+            get_network_profile,
+            get_theme,
+            set_theme,
+            restart_app,
+            get_ui_language,
+            set_ui_language,
+            save_window_layout_snapshot,
+            restore_window_layout,
+            list_window_layouts,
+            broadcast_event,
+            list_broadcast_channels,
+            set_spellcheck_language,
+            set_text_input_options,
+            get_display_info,
+            set_force_scale_factor,
+            get_onboarding_state,
+            set_onboarding_complete,
+            migrate_data_directory,
             open_sidecar_log_file,
+            reveal_in_file_manager,
+            read_desktop_log_tail,
+            read_sidecar_log_tail,
+            subscribe_sidecar_log,
+            unsubscribe_sidecar_log,
+            get_sidecar_env_preview,
+            export_diagnostics_bundle,
+            export_app_config,
+            import_app_config,
+            log_from_frontend,
+            dismiss_safe_mode,
+            reset_runtime_prefs,
             open_settings_window_command,
             close_settings_window,
+            set_settings_dirty,
             open_live_channels_window_command,
             close_live_channels_window,
+            create_panel_window,
             open_url,
             open_youtube_login,
-            fetch_polymarket
-        ])
-        .setup(|app| {
+            fetch_polymarket,
+            get_rate_limit_stats,
+            clear_http_cache,
+            write_blob,
+            read_blob,
+            delete_blob,
+            list_blobs,
+            get_blob_store_stats,
+            purge_stale_api_cache,
+            get_log_files_info,
+            prune_logs,
+            set_log_level,
+            ws_connect,
+            ws_send,
+            ws_close,
+            get_startup_status,
+            get_startup_profile,
+            retry_local_api_start,
+            get_local_api_history,
+            reset_local_api_breaker,
+            force_quit,
+            wait_then_quit,
+            dismiss_splash,
+            show_notification,
+            check_for_updates
+        ]))
+        .setup(move |app| {
+            crash::attach_app_handle(app.handle());
+            // `setup` runs on the event loop, which window creation must not block.
+            app.state::<window_creation::WindowCreationState>().mark_main_thread();
+            // Before any path below is used; falls back to a session dir.
+            data_dir::prepare_storage(app.handle());
+            // Loaded first so the log format and level apply from the first line.
+            let prefs_path = runtime_prefs_path(app.handle()).unwrap_or_default();
+            data_dir::relocate_cache(app.handle());
+            // Before anything this session writes the prefs or cache file.
+            let vault_secrets = app.state::<SecretsCache>().secrets.lock().unwrap_or_else(|e| e.into_inner()).len();
+            let evidence = onboarding::InstallEvidence::detect(
+                &prefs_path,
+                &cache_file_path(app.handle()).unwrap_or_default(),
+                vault_secrets,
+            );
+            app.manage(onboarding::OnboardingState::new(evidence));
+            app.manage(RuntimePrefs::load(&prefs_path));
+            version_history::record_launch(app.handle());
+            polling::restore(app.handle());
+            // The menu was built before prefs were loaded, from the OS locale.
+            if app.state::<RuntimePrefs>().get(i18n::PREF_UI_LANGUAGE).is_some() {
+                rebuild_app_menu(app.handle());
+            }
+            data_dir::report_storage(app.handle());
+            for warning in &app.state::<CliOptions>().warnings {
+                log_event(app.handle(), "WARN", "cli_argument_ignored", &[("detail", warning)]);
+            }
+            let active_profile = profile::active();
+            log_event(
+                app.handle(),
+                "INFO",
+                "profile",
+                &[
+                    ("name", active_profile.name().unwrap_or("default")),
+                    ("keyring_service", &profile::keyring_service()),
+                ],
+            );
+            onboarding::log_detection(app.handle());
+            let safe_mode = app.state::<SafeModeState>();
+            if let Some(path) = &safe_mode.marker_path {
+                if let Err(err) = safe_mode::record_startup(path, safe_mode.status.unclean_launches) {
+                    log_event(app.handle(), "WARN", "startup_marker_failed", &[("error", &err.to_string())]);
+                }
+            }
+            #[cfg(target_os = "linux")]
+            {
+                if let Some(error) = &linux_webkit_policy.overrides_error {
+                    log_event(
+                        app.handle(),
+                        "WARN",
+                        "webkit_policy_override_invalid",
+                        &[
+                            ("path", linux_webkit_policy.overrides_file.as_deref().unwrap_or("")),
+                            ("error", error),
+                        ],
+                    );
+                }
+                log_event(
+                    app.handle(),
+                    "INFO",
+                    "linux_webkit_policy",
+                    &[("policy", &linux_webkit::format_linux_webkit_policy(&linux_webkit_policy))],
+                );
+                for assignment in &linux_webkit_policy.assignments {
+                    append_desktop_debug(
+                        app.handle(),
+                        &format!(
+                            "webkit policy: set {}={} source={} reason={}",
+                            assignment.name,
+                            assignment.value,
+                            assignment.source.as_str(),
+                            assignment.reason
+                        ),
+                    );
+                }
+                let session_type = if linux_webkit_policy.wayland { "wayland" } else { "x11" };
+                app.manage(StaticRuntimeInfo::collect(Some(session_type)));
+                app.manage(linux_webkit_policy);
+            }
+            #[cfg(not(target_os = "linux"))]
+            app.manage(StaticRuntimeInfo::collect(None));
+            if let Some(reason) = &safe_mode.status.reason {
+                log_event(
+                    app.handle(),
+                    "WARN",
+                    "safe_mode",
+                    &[
+                        ("reason", reason),
+                        ("unclean_launches", &safe_mode.status.unclean_launches.to_string()),
+                    ],
+                );
+            }
+            if safe_mode.status.reason.as_deref() == Some("crash_loop") && !headless {
+                if let Err(err) = open_safe_mode_window(app.handle()) {
+                    append_desktop_log(app.handle(), "WARN", &err);
+                }
+            }
             // Load persistent cache into memory (avoids 14MB file I/O on every IPC call)
-            let cache_path = cache_file_path(&app.handle()).unwrap_or_default();
+            let cache_path = cache_file_path(app.handle()).unwrap_or_default();
             app.manage(PersistentCache::load(&cache_path));
+            vault_sync::start_watcher(app.handle(), &SUPPORTED_SECRET_KEYS);
+            quiet_hours::start_scheduler(app.handle());
+            source_health::restore(app.handle());
+            api_usage::restore(app.handle());
+            vault_events::restore(app.handle());
+            maintenance::start(app.handle());
+            let handle = app.handle().clone();
+            std::thread::spawn(move || autostart::refresh_registration(&handle));
+            let handle = app.handle().clone();
+            std::thread::spawn(move || keep_awake::restore(&handle));
+            if !headless {
+                let handle = app.handle().clone();
+                std::thread::spawn(move || global_shortcut::restore(&handle));
+            }
+            if headless {
+                #[cfg(target_os = "macos")]
+                app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+                headless::start(app.handle());
+                return Ok(());
+            }
+            // Attribute notifications to our bundle instead of the default
+            // Finder identity used by NSUserNotificationCenter.
+            #[cfg(target_os = "macos")]
+            let _ = notify_rust::set_application(&app.config().identifier);
 
-            if let Err(err) = start_local_api(&app.handle()) {
+            // Installers register the scheme; re-register at runtime so AppImage
+            // and portable builds still receive links.
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(err) = app.deep_link().register_all() {
                 append_desktop_log(
-                    &app.handle(),
-                    "ERROR",
-                    &format!("local API sidecar failed to start: {err}"),
+                    app.handle(),
+                    "WARN",
+                    &format!("failed to register {DEEP_LINK_SCHEME}:// handler: {err}"),
                 );
-                eprintln!("[tauri] local API sidecar failed to start: {err}");
+            }
+            // Windows/Linux deliver the initial link as a CLI argument.
+            for arg in env::args().skip(1).filter(|arg| looks_like_deep_link(arg)) {
+                dispatch_deep_link(app.handle(), &arg);
             }
 
+            theme::apply(app.handle(), false);
+            if let Some(window) = app.get_webview_window("main") {
+                if let Err(err) = webview_text::apply_to_window(app.handle(), &window) {
+                    log_event(app.handle(), "WARN", "text_input_apply_failed", &[("window", "main"), ("error", &err)]);
+                }
+            }
+
+            #[cfg(feature = "tray")]
+            let tray_available = match tray::create_tray(app.handle()) {
+                Ok(()) => true,
+                Err(err) => {
+                    log_event(app.handle(), "WARN", "tray_unavailable", &[("error", &err.to_string())]);
+                    false
+                }
+            };
+            #[cfg(not(feature = "tray"))]
+            let tray_available = false;
+            let visibility = {
+                let prefs = app.state::<RuntimePrefs>();
+                startup_visibility(
+                    app.state::<CliOptions>().start_minimized,
+                    prefs.get_bool(PREF_START_MINIMIZED, false),
+                    prefs.get_bool(PREF_START_IN_TRAY, false),
+                    tray_available,
+                )
+            };
+            app.manage(visibility);
+            log_startup_stage(app.handle(), "window_visibility");
+
+            // The main window is created hidden (tauri.conf.json) and revealed
+            // once the sidecar handshake finishes or the splash times out. A
+            // minimized or tray start skips the splash; the sidecar still boots.
+            if visibility == StartupVisibility::Shown {
+                if let Err(err) = open_splash_window(app.handle()) {
+                    append_desktop_log(app.handle(), "WARN", &err);
+                    show_main_window(app.handle());
+                }
+            }
+            boot_local_api(app.handle());
+            schedule_auto_update_check(app.handle());
+            arm_splash_timeout(app.handle());
+
             Ok(())
         })
-        .build(tauri::generate_context!())
+        .build(context)
         .unwrap_or_else(|e| {
             eprintln!("[tauri] fatal: failed to run application: {e}");
             std::process::exit(1);
         })
         .run(|app, event| {
+            if let RunEvent::WindowEvent {
+                label,
+                event: WindowEvent::Destroyed,
+                ..
+            } = &event
+            {
+                ws_bridge::close_window(app, label);
+                reload::forget_window(app, label);
+                find_in_page::forget_window(app, label);
+                sidecar_log::unsubscribe(app, label);
+            }
+            if let RunEvent::WindowEvent {
+                event: WindowEvent::ThemeChanged(os_theme),
+                ..
+            } = &event
+            {
+                theme::os_theme_changed(app, *os_theme);
+            }
+            if let RunEvent::WindowEvent {
+                label,
+                event: WindowEvent::Moved(_) | WindowEvent::Resized(_),
+                ..
+            } = &event
+            {
+                window_layout::record_when_settled(app, label);
+            }
             match &event {
                 // macOS: hide window on close instead of quitting (standard behavior)
                 #[cfg(target_os = "macos")]
@@ -1436,38 +4920,618 @@ fn main() {
                         let _ = w.hide();
                     }
                 }
-                // macOS: reshow window when dock icon is clicked
-                #[cfg(target_os = "macos")]
-                RunEvent::Reopen { .. } => {
-                    if let Some(w) = app.get_webview_window("main") {
-                        let _ = w.show();
-                        let _ = w.set_focus();
+                RunEvent::WindowEvent {
+                    label,
+                    event: WindowEvent::CloseRequested { api, .. },
+                    ..
+                } if label == "settings" => {
+                    if app.state::<SettingsSessionState>().is_dirty() {
+                        // The frontend asks, then calls close_settings_window(force).
+                        api.prevent_close();
+                        let _ = app.emit_to("settings", "settings-close-requested", ());
+                        if let Some(window) = app.get_webview_window("settings") {
+                            let _ = window.set_focus();
+                        }
+                    } else {
+                        save_settings_window_geometry(app);
                     }
                 }
-                // Only macOS needs explicit re-raising to keep settings above the main window.
-                // On Windows, focusing the settings window here can trigger rapid focus churn
-                // between windows and present as a UI hang.
-                #[cfg(target_os = "macos")]
                 RunEvent::WindowEvent {
                     label,
-                    event: WindowEvent::Focused(true),
+                    event: WindowEvent::Destroyed,
+                    ..
+                } if label == "settings" => {
+                    notify_settings_closed(app);
+                }
+                // Windows/Linux: the app only exits once every window is
+                // closed, so closing main would strand detached panels.
+                #[cfg(not(target_os = "macos"))]
+                RunEvent::WindowEvent {
+                    label,
+                    event: WindowEvent::CloseRequested { api, .. },
                     ..
                 } if label == "main" => {
-                    if let Some(sw) = app.get_webview_window("settings") {
-                        let _ = sw.show();
-                        let _ = sw.set_focus();
+                    if close_to_tray(app) {
+                        api.prevent_close();
+                        if let Some(w) = app.get_webview_window("main") {
+                            let _ = w.hide();
+                        }
+                    } else if panel_windows::has_open_panel_windows(app) {
+                        app.exit(0);
+                    }
+                }
+                RunEvent::WindowEvent {
+                    label,
+                    event: WindowEvent::CloseRequested { .. },
+                    ..
+                } if panel_windows::panel_id_from_label(label).is_some() => {
+                    window_layout::record_current(app, &[label.as_str()]);
+                }
+                RunEvent::WindowEvent {
+                    label,
+                    event: WindowEvent::Destroyed,
+                    ..
+                } if panel_windows::panel_id_from_label(label).is_some() => {
+                    panel_windows::refresh_window_menu(app, Some(label));
+                }
+                // macOS: reshow window when dock icon is clicked
+                #[cfg(target_os = "macos")]
+                RunEvent::Reopen { .. } => {
+                    show_main_window(app);
+                }
+                RunEvent::Ready => {
+                    app.state::<StartupProfile>().mark_ready(Instant::now());
+                    log_startup_summary(app);
+                    flush_pending_deep_links(app);
+                    if app.state::<CliOptions>().open_settings {
+                        if let Err(err) = open_settings_window(app) {
+                            log_event(app, "WARN", "cli_open_settings_failed", &[("error", &err)]);
+                        }
+                    }
+                    let cli = app.state::<CliOptions>();
+                    let prompt = onboarding::should_prompt(
+                        app.state::<onboarding::OnboardingState>().first_run,
+                        onboarding::is_complete(app),
+                        cli.no_onboarding || cli.open_settings || cli.headless,
+                    );
+                    if prompt {
+                        log_event(app, "INFO", "onboarding_open_settings", &[]);
+                        if let Err(err) = open_settings_window(app) {
+                            log_event(app, "WARN", "onboarding_open_settings_failed", &[("error", &err)]);
+                        }
+                    }
+                    if let Some(path) = app.state::<SafeModeState>().marker_path.clone() {
+                        safe_mode::clear_startup_marker_after_grace(path);
+                    }
+                }
+                // macOS delivers deep links through Apple Events, not argv.
+                #[cfg(target_os = "macos")]
+                RunEvent::Opened { urls } => {
+                    for url in urls {
+                        dispatch_deep_link(app, url.as_str());
                     }
                 }
+                RunEvent::ExitRequested { api, code, .. } if quit_guard::hold_exit(app, *code) => {
+                    api.prevent_exit();
+                }
                 RunEvent::ExitRequested { .. } | RunEvent::Exit => {
+                    // Quitting destroys windows without a CloseRequested.
+                    save_settings_window_geometry(app);
+                    window_layout::record_current(app, &[]);
                     // Flush in-memory cache to disk before quitting
                     if let Ok(path) = cache_file_path(app) {
                         if let Some(cache) = app.try_state::<PersistentCache>() {
                             let _ = cache.flush(&path);
                         }
                     }
+                    maintenance::shutdown(app);
                     stop_local_api(app);
+                    keep_awake::release(app);
+                    ws_bridge::close_all(app);
+                    if matches!(event, RunEvent::Exit) {
+                        restart::relaunch_pending(app);
+                    }
                 }
                 _ => {}
             }
         });
 }
+
+#[cfg(test)]
+mod sanitize_path_tests {
+    use super::sanitize_path_for_node;
+    use std::path::Path;
+
+    #[test]
+    fn strips_extended_drive_prefix() {
+        let raw = Path::new(r"\\?\C:\Program Files\nodejs\node.exe");
+        assert_eq!(
+            sanitize_path_for_node(raw),
+            r"C:\Program Files\nodejs\node.exe".to_string()
+        );
+    }
+
+    #[test]
+    fn strips_extended_unc_prefix_and_preserves_unc_root() {
+        let raw = Path::new(r"\\?\UNC\server\share\sidecar\local-api-server.mjs");
+        assert_eq!(
+            sanitize_path_for_node(raw),
+            r"\\server\share\sidecar\local-api-server.mjs".to_string()
+        );
+    }
+
+    #[test]
+    fn leaves_standard_paths_unchanged() {
+        let raw = Path::new(r"C:\Users\alice\sidecar\local-api-server.mjs");
+        assert_eq!(
+            sanitize_path_for_node(raw),
+            r"C:\Users\alice\sidecar\local-api-server.mjs".to_string()
+        );
+    }
+}
+
+#[cfg(test)]
+mod deep_link_tests {
+    use super::{looks_like_deep_link, parse_deep_link};
+
+    #[test]
+    fn parses_panel_link_with_params() {
+        let link = parse_deep_link("worldmonitor://panel/conflicts?region=sahel").unwrap();
+        assert_eq!(link.target, "panel");
+        assert_eq!(link.segments, vec!["conflicts".to_string()]);
+        assert_eq!(link.params.get("region").map(String::as_str), Some("sahel"));
+    }
+
+    #[test]
+    fn rejects_foreign_scheme_and_unknown_target() {
+        assert!(parse_deep_link("https://panel/conflicts").is_err());
+        assert!(parse_deep_link("worldmonitor://devtools/open").is_err());
+    }
+
+    #[test]
+    fn rejects_injection_attempts() {
+        assert!(parse_deep_link("worldmonitor://panel/<script>").is_err());
+        assert!(parse_deep_link("worldmonitor://panel/a%2Fb").is_err());
+        assert!(parse_deep_link("worldmonitor://panel/x?q=a%0Ab").is_err());
+        assert!(parse_deep_link("worldmonitor://user:pw@panel/x").is_err());
+        assert!(parse_deep_link("worldmonitor://panel/x#frag").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_links() {
+        let long = format!("worldmonitor://panel/x?q={}", "a".repeat(3000));
+        assert!(parse_deep_link(&long).is_err());
+        let many_segments = "worldmonitor://panel/a/b/c/d/e";
+        assert!(parse_deep_link(many_segments).is_err());
+    }
+
+    #[test]
+    fn detects_deep_link_arguments_case_insensitively() {
+        assert!(looks_like_deep_link("WorldMonitor://panel/x"));
+        assert!(!looks_like_deep_link("--settings"));
+        assert!(!looks_like_deep_link("world"));
+    }
+}
+
+#[cfg(test)]
+mod notification_tests {
+    use super::{admit_notification, truncate_chars};
+    use std::collections::VecDeque;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn limits_bursts_within_window() {
+        let mut recent = VecDeque::new();
+        let start = Instant::now();
+        let window = Duration::from_secs(10);
+        for i in 0..5 {
+            assert!(admit_notification(&mut recent, start + Duration::from_millis(i), window, 5));
+        }
+        assert!(!admit_notification(&mut recent, start + Duration::from_secs(1), window, 5));
+        assert!(admit_notification(&mut recent, start + Duration::from_secs(11), window, 5));
+    }
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        assert_eq!(truncate_chars("héllo", 2), "hé…");
+        assert_eq!(truncate_chars("short", 10), "short");
+    }
+}
+
+#[cfg(test)]
+mod update_check_tests {
+    use super::{is_newer_version, is_prerelease_version};
+
+    #[test]
+    fn compares_dotted_versions_numerically() {
+        assert!(is_newer_version("2.5.24", "2.5.23"));
+        assert!(is_newer_version("2.10.0", "2.9.9"));
+        assert!(is_newer_version("v3.0", "2.99.99"));
+        assert!(!is_newer_version("2.5.23", "2.5.23"));
+        assert!(!is_newer_version("2.5", "2.5.0"));
+        assert!(!is_newer_version("2.4.99", "2.5.0"));
+    }
+
+    #[test]
+    fn prereleases_sort_before_their_release() {
+        assert!(is_prerelease_version("2.6.0-beta.1"));
+        assert!(!is_prerelease_version("2.6.0+build.7"));
+        assert!(is_newer_version("2.6.0-beta.1", "2.5.9"));
+        assert!(!is_newer_version("2.6.0-beta.1", "2.6.0"));
+        assert!(is_newer_version("2.6.0", "2.6.0-rc.2"));
+        assert!(!is_newer_version("2.6.0-rc.2", "2.6.0-rc.2"));
+        assert!(is_newer_version("2.6.0-rc.10", "2.6.0-rc.2"));
+        assert!(is_newer_version("2.6.0-rc.1", "2.6.0-beta.3"));
+    }
+}
+
+#[cfg(test)]
+mod frontend_log_tests {
+    use super::{admit_frontend_log, FrontendLogAdmission, FrontendLogBudget};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn drops_over_budget_and_reports_count_in_next_window() {
+        let mut budget = FrontendLogBudget::default();
+        let start = Instant::now();
+        for i in 0..3 {
+            assert_eq!(
+                admit_frontend_log(&mut budget, start + Duration::from_millis(i), 3),
+                FrontendLogAdmission::Admitted { dropped_before: 0 }
+            );
+        }
+        assert_eq!(admit_frontend_log(&mut budget, start + Duration::from_millis(500), 3), FrontendLogAdmission::Dropped);
+        assert_eq!(admit_frontend_log(&mut budget, start + Duration::from_millis(900), 3), FrontendLogAdmission::Dropped);
+        assert_eq!(
+            admit_frontend_log(&mut budget, start + Duration::from_millis(1000), 3),
+            FrontendLogAdmission::Admitted { dropped_before: 2 }
+        );
+        assert_eq!(
+            admit_frontend_log(&mut budget, start + Duration::from_millis(1001), 3),
+            FrontendLogAdmission::Admitted { dropped_before: 0 }
+        );
+    }
+}
+
+#[cfg(test)]
+mod local_api_connection_tests {
+    use super::LocalApiConnection;
+
+    #[test]
+    fn serializes_the_frontend_field_names() {
+        let connection = LocalApiConnection {
+            base_url: Some("http://127.0.0.1:46123".to_string()),
+            token: Some("abc123".to_string()),
+            ready: true,
+            started_at: Some("2026-10-18T09:30:00Z".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(&connection).unwrap(),
+            serde_json::json!({
+                "base_url": "http://127.0.0.1:46123",
+                "token": "abc123",
+                "ready": true,
+                "started_at": "2026-10-18T09:30:00Z",
+            })
+        );
+        let stopped = LocalApiConnection {
+            base_url: None,
+            token: None,
+            ready: false,
+            started_at: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&stopped).unwrap(),
+            serde_json::json!({ "base_url": null, "token": null, "ready": false, "started_at": null })
+        );
+    }
+}
+
+#[cfg(test)]
+mod desktop_runtime_info_tests {
+    use super::{DesktopRuntimeInfo, SafeModeStatus, StaticRuntimeInfo};
+    use crate::packaging::Packaging;
+    use crate::theme::ThemeInfo;
+    use crate::webview_text::TextInputSettings;
+
+    #[test]
+    fn serializes_the_frontend_field_names() {
+        let info = DesktopRuntimeInfo {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            local_api_port: Some(46123),
+            last_crash_at: None,
+            safe_mode: SafeModeStatus::default(),
+            user_agent: "WorldMonitor/2.5.23 (linux; x86_64)".to_string(),
+            text_input: TextInputSettings::default(),
+            theme: ThemeInfo::default(),
+            packaging: Packaging::Native,
+            upgraded_this_run: false,
+            upgraded_from: None,
+            degraded_storage: false,
+            host: StaticRuntimeInfo::default(),
+        };
+        let value = serde_json::to_value(&info).unwrap();
+        let mut fields: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            [
+                "app_version",
+                "arch",
+                "cpu_count",
+                "debug_build",
+                "degraded_storage",
+                "last_crash_at",
+                "local_api_port",
+                "locale",
+                "os",
+                "packaging",
+                "safe_mode",
+                "session_type",
+                "tauri_version",
+                "text_input",
+                "theme",
+                "total_memory_bytes",
+                "upgraded_from",
+                "upgraded_this_run",
+                "user_agent",
+                "webview_engine",
+                "webview_version",
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod persistent_cache_tests {
+    use super::{cache_entry, cache_limits::Limits, run_blocking, DesktopError, PersistentCache};
+    use serde_json::{json, Value};
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    #[test]
+    fn large_cache_write_does_not_block_other_commands() {
+        let dir = std::env::temp_dir().join(format!("wm-cache-stress-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("persistent-cache.json");
+        let cache = Arc::new(PersistentCache::load(&path));
+        let large = Value::String("x".repeat(16 * 1024 * 1024));
+        let (started_tx, started_rx) = mpsc::channel();
+        let (others_done_tx, others_done_rx) = mpsc::channel::<()>();
+
+        tauri::async_runtime::block_on(async move {
+            let writer_cache = Arc::clone(&cache);
+            let write = tauri::async_runtime::spawn(run_blocking(move || {
+                started_tx.send(()).unwrap();
+                writer_cache.insert_and_flush(&path, "large".to_string(), large, &Limits::default(), |_| Ok(()))?;
+                // Only succeeds if the commands below ran while this one was
+                // still occupying its thread.
+                others_done_rx
+                    .recv_timeout(Duration::from_secs(10))
+                    .map_err(|_| DesktopError::Internal("other commands were starved".to_string()))
+            }));
+
+            started_rx.recv().unwrap();
+            for i in 0..20 {
+                let reader = Arc::clone(&cache);
+                let key = format!("small-{i}");
+                let found = run_blocking(move || Ok(reader.get(&key))).await.unwrap();
+                assert!(found.is_none());
+            }
+            others_done_tx.send(()).unwrap();
+
+            write.await.unwrap().unwrap();
+            assert!(cache.get("large").is_some());
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn legacy_values_and_envelopes_coexist() {
+        let dir = std::env::temp_dir().join(format!("wm-cache-envelope-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("persistent-cache.json");
+        let legacy = json!({ "key": "feed:world", "updatedAt": 5, "data": ["a"] });
+        std::fs::write(&path, json!({ "feed:world": legacy }).to_string()).unwrap();
+
+        let cache = PersistentCache::load(&path);
+        let stored = cache_entry::wrap(json!(["b"]), 1_000, Some("2.6.1".to_string()));
+        cache.insert_and_flush(&path, "feed:asia".to_string(), stored, &Limits::default(), |_| Ok(())).unwrap();
+
+        let reloaded = PersistentCache::load(&path);
+        let old = reloaded.get("feed:world").unwrap();
+        assert_eq!(cache_entry::into_value(old.clone()), legacy);
+        let old = serde_json::to_value(cache_entry::with_meta(old, 61_000)).unwrap();
+        assert_eq!(old["writtenAt"], Value::Null);
+        let new = serde_json::to_value(cache_entry::with_meta(reloaded.get("feed:asia").unwrap(), 61_000)).unwrap();
+        assert_eq!(new, json!({ "value": ["b"], "writtenAt": 1_000, "ageSecs": 60, "sourceVersion": "2.6.1" }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn write_past_the_total_limit_is_undone() {
+        let dir = std::env::temp_dir().join(format!("wm-cache-total-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("persistent-cache.json");
+        let cache = PersistentCache::load(&path);
+        let limits = Limits {
+            max_entry_bytes: 1024,
+            max_total_bytes: 200,
+        };
+        cache.insert_and_flush(&path, "a".to_string(), json!("x".repeat(50)), &limits, |_| Ok(())).unwrap();
+        cache.insert_and_flush(&path, "b".to_string(), json!("y".repeat(50)), &limits, |_| Ok(())).unwrap();
+
+        let err = cache
+            .insert_and_flush(&path, "c".to_string(), json!("z".repeat(100)), &limits, |_| Ok(()))
+            .unwrap_err();
+        assert_eq!(err.code(), "cache_full");
+        assert!(cache.get("c").is_none());
+        let err = cache
+            .insert_and_flush(&path, "a".to_string(), json!("w".repeat(150)), &limits, |_| Ok(()))
+            .unwrap_err();
+        assert_eq!(err.code(), "cache_full");
+        assert_eq!(cache.get("a"), Some(json!("x".repeat(50))), "previous value restored");
+
+        let on_disk: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk.as_object().unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn space_is_checked_for_the_whole_file_not_the_new_value() {
+        let dir = std::env::temp_dir().join(format!("wm-cache-space-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("persistent-cache.json");
+        let cache = PersistentCache::load(&path);
+        cache
+            .insert_and_flush(&path, "big".to_string(), json!("x".repeat(500)), &Limits::default(), |_| Ok(()))
+            .unwrap();
+
+        let mut asked = 0;
+        let err = cache
+            .insert_and_flush(&path, "small".to_string(), json!("y"), &Limits::default(), |total| {
+                asked = total;
+                Err(DesktopError::DiskFull("no room".to_string()))
+            })
+            .unwrap_err();
+        assert!(asked > 500, "sized by the serialized cache, got {asked}");
+        assert_eq!(err.code(), "disk_full");
+        assert!(cache.get("small").is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+#[cfg(test)]
+mod startup_visibility_tests {
+    use super::{startup_visibility, StartupVisibility};
+
+    #[test]
+    fn cli_flag_and_prefs_pick_visibility() {
+        assert_eq!(startup_visibility(false, false, false, true), StartupVisibility::Shown);
+        assert_eq!(startup_visibility(true, false, false, true), StartupVisibility::Minimized);
+        assert_eq!(startup_visibility(false, true, false, false), StartupVisibility::Minimized);
+        assert_eq!(startup_visibility(true, false, true, true), StartupVisibility::Tray);
+        // Without a tray icon nothing could bring a hidden window back.
+        assert_eq!(startup_visibility(false, false, true, false), StartupVisibility::Minimized);
+    }
+}
+
+#[cfg(test)]
+mod open_url_tests {
+    use super::{check_open_url, to_wide_null, validate_allowed_url_schemes};
+    use reqwest::Url;
+    use serde_json::json;
+
+    #[test]
+    fn mailto_accepts_subject_and_multiline_body() {
+        let url = check_open_url("mailto:desk@example.org?subject=Source%20error&body=Line%201%0D%0ALine%202", &[], &[]).unwrap();
+        assert_eq!(url.scheme(), "mailto");
+        assert!(check_open_url("mailto:a@example.org,b@example.com?cc=c@example.net", &[], &[]).is_ok());
+
+        assert!(check_open_url("mailto:?subject=hi", &[], &[]).is_err());
+        assert!(check_open_url("mailto:not-an-address", &[], &[]).is_err());
+        // CRLF outside the body would add headers.
+        assert!(check_open_url("mailto:a@example.org?subject=hi%0D%0ABcc:%20x@evil.test", &[], &[]).is_err());
+        assert!(check_open_url("mailto:a@example.org%0ABcc:x@evil.test", &[], &[]).is_err());
+        assert!(check_open_url("mailto:a@example.org?subject=%zz", &[], &[]).is_err());
+    }
+
+    #[test]
+    fn schemes_are_normalized_and_opt_in() {
+        assert_eq!(check_open_url("HTTPS://worldmonitor.app/", &[], &[]).unwrap().scheme(), "https");
+        assert!(check_open_url("MAILTO:Desk@Example.org", &[], &[]).is_ok());
+        assert!(check_open_url("http://example.com/", &[], &[]).is_err());
+        assert!(check_open_url("http://localhost:46123/api", &[], &[]).is_ok());
+        let lan = [crate::trusted_hosts::parse_pattern("nas.local:8080").unwrap()];
+        assert!(check_open_url("http://nas.local:8080/status", &[], &lan).is_ok());
+        assert!(check_open_url("http://nas.local:8081/status", &[], &lan).is_err());
+        assert!(check_open_url("http://192.168.1.5/", &[], &lan).is_err());
+
+        assert!(check_open_url("zotero://select/items/ABC", &[], &[]).is_err());
+        let allowed = vec!["Zotero".to_string(), "javascript".to_string()];
+        assert!(check_open_url("ZOTERO://select/items/ABC", &allowed, &[]).is_ok());
+        // Rejected even when someone slips it into the pref file.
+        assert!(check_open_url("javascript:alert(1)", &allowed, &[]).is_err());
+        assert!(check_open_url("JavaScript:alert(1)", &allowed, &[]).is_err());
+    }
+
+    #[test]
+    fn allowed_schemes_pref_is_validated() {
+        assert!(validate_allowed_url_schemes(&json!(["zotero", "obsidian", "x-web+search"])).is_ok());
+        assert!(validate_allowed_url_schemes(&json!(["JavaScript"])).is_err());
+        assert!(validate_allowed_url_schemes(&json!(["zotero://"])).is_err());
+        assert!(validate_allowed_url_schemes(&json!("zotero")).is_err());
+        assert!(validate_allowed_url_schemes(&json!([1])).is_err());
+    }
+
+    #[test]
+    fn urls_reach_the_shell_unmangled() {
+        for raw in [
+            "https://worldmonitor.app/?view=markets&region=eu&t=1#panel-news",
+            "https://example.com/search?q=a%20b%26c&lang=en",
+            "https://example.com/a path/?q=x y",
+        ] {
+            let url = Url::parse(raw).unwrap();
+            let wide = to_wide_null(url.as_str()).unwrap();
+            assert_eq!(wide.last(), Some(&0));
+            assert_eq!(String::from_utf16(&wide[..wide.len() - 1]).unwrap(), url.as_str());
+        }
+        // Spaces are percent-encoded before anything is launched.
+        let spaced = Url::parse("https://example.com/a path/?q=x y").unwrap();
+        assert_eq!(spaced.as_str(), "https://example.com/a%20path/?q=x%20y");
+        assert_eq!(
+            Url::parse("https://example.com/?a=1&b=%2F#frag").unwrap().as_str(),
+            "https://example.com/?a=1&b=%2F#frag"
+        );
+        assert_eq!(to_wide_null("https://x/\0evil"), None);
+    }
+}
+
+#[cfg(test)]
+mod settings_window_tests {
+    use super::validate_settings_section;
+
+    #[test]
+    fn only_known_sections_are_accepted() {
+        assert_eq!(validate_settings_section("ai"), Ok("ai"));
+        assert_eq!(validate_settings_section("debug"), Ok("debug"));
+        for bad in ["", "AI", "api-keys", "ai#x", "../index", "overview?x=1"] {
+            assert!(validate_settings_section(bad).is_err(), "{bad}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod settings_session_tests {
+    use super::SettingsSessionState;
+
+    #[test]
+    fn take_reports_each_key_once_and_resets() {
+        let state = SettingsSessionState::default();
+        state.record_secret("OPENAI_API_KEY");
+        state.record_secret("OPENAI_API_KEY");
+        state.record_pref("autoCheckUpdates");
+        let changes = state.take();
+        assert_eq!(changes.secrets_changed.into_iter().collect::<Vec<_>>(), ["OPENAI_API_KEY"]);
+        assert_eq!(changes.prefs_changed.into_iter().collect::<Vec<_>>(), ["autoCheckUpdates"]);
+        assert_eq!(state.take(), Default::default());
+    }
+
+    #[test]
+    fn payload_lists_keys_only() {
+        let state = SettingsSessionState::default();
+        state.record_secret("FRED_API_KEY");
+        let json = serde_json::to_value(state.take()).unwrap();
+        assert_eq!(json, serde_json::json!({ "secrets_changed": ["FRED_API_KEY"], "prefs_changed": [] }));
+    }
+    #[test]
+    fn a_new_session_starts_clean() {
+        let state = SettingsSessionState::default();
+        assert!(!state.is_dirty());
+        state.set_dirty(true);
+        assert!(state.is_dirty());
+        state.take();
+        assert!(!state.is_dirty());
+    }
+}
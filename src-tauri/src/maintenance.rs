@@ -0,0 +1,438 @@
+//! One scheduler for the periodic chores (clock-skew checks, source-health
+//! polls, metered-network probes, resource sampling, log pruning) instead of
+//! a sleeping thread each. A task is a [`TaskSpec`]: an id, an interval with
+//! random jitter added so chores don't line up, a first delay, and an async
+//! `run`. A one-second tick on the async runtime starts whatever is due. A
+//! task never overlaps itself; the next run is scheduled from when the last
+//! one finished, so a failure is logged and simply tried again next time.
+//! `get_maintenance_status` reports each task and `run_maintenance_task`
+//! runs one now from the Doctor tab. [`shutdown`] runs on exit before the
+//! sidecar is stopped, and gives running tasks a moment to finish.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::error::DesktopError;
+use crate::logging::{log_event, now_iso8601};
+use crate::{api_usage, clock_skew, log_retention, network_profile, polling, resources, source_health};
+
+const TICK: Duration = Duration::from_secs(1);
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+const SIDECAR_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(crate) type TaskFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// One registered chore. `C` is what `run` is handed: the app handle in
+/// production, anything in tests.
+pub(crate) struct TaskSpec<C = AppHandle> {
+    pub(crate) id: &'static str,
+    pub(crate) interval: Duration,
+    /// Up to this much is added to each interval.
+    pub(crate) jitter: Duration,
+    pub(crate) first_delay: Duration,
+    /// Left for the next interval while the user has paused polling.
+    /// Manual runs go ahead regardless.
+    pub(crate) skip_while_paused: bool,
+    pub(crate) run: fn(C) -> TaskFuture,
+}
+
+impl<C> Clone for TaskSpec<C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C> Copy for TaskSpec<C> {}
+
+impl<C> TaskSpec<C> {
+    /// A once-a-minute poll of the sidecar, first run a minute after startup
+    /// and skipped while polling is paused.
+    pub(crate) const fn sidecar_poll(id: &'static str, run: fn(C) -> TaskFuture) -> Self {
+        TaskSpec {
+            id,
+            interval: SIDECAR_POLL_INTERVAL,
+            jitter: Duration::from_secs(5),
+            first_delay: SIDECAR_POLL_INTERVAL,
+            skip_while_paused: true,
+            run,
+        }
+    }
+}
+
+/// The chores every session runs.
+fn builtin_tasks() -> [TaskSpec; 6] {
+    [
+        clock_skew::MAINTENANCE,
+        source_health::MAINTENANCE,
+        api_usage::MAINTENANCE,
+        network_profile::MAINTENANCE,
+        resources::MAINTENANCE,
+        log_retention::MAINTENANCE,
+    ]
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct LastRun {
+    at: String,
+    ok: bool,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+/// One entry of `get_maintenance_status`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct TaskStatus {
+    id: &'static str,
+    interval_secs: u64,
+    jitter_secs: u64,
+    running: bool,
+    /// `None` while running.
+    next_run_in_secs: Option<u64>,
+    last_run: Option<LastRun>,
+    runs: u64,
+    failures: u64,
+}
+
+/// `interval` plus `roll` (0 to 1) of `jitter`.
+fn next_delay(interval: Duration, jitter: Duration, roll: f64) -> Duration {
+    interval + jitter.mul_f64(roll.clamp(0.0, 1.0))
+}
+
+fn random_roll() -> f64 {
+    let mut bytes = [0u8; 4];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        return 0.5;
+    }
+    f64::from(u32::from_le_bytes(bytes)) / (f64::from(u32::MAX) + 1.0)
+}
+
+struct Entry<C> {
+    spec: TaskSpec<C>,
+    next_due: Instant,
+    running: bool,
+    last_run: Option<LastRun>,
+    runs: u64,
+    failures: u64,
+}
+
+#[derive(Debug, PartialEq)]
+enum BeginError {
+    Unknown,
+    Running,
+}
+
+/// The bookkeeping, apart from the runtime; every call takes the time.
+struct Schedule<C> {
+    entries: Vec<Entry<C>>,
+}
+
+impl<C> Default for Schedule<C> {
+    fn default() -> Self {
+        Schedule { entries: Vec::new() }
+    }
+}
+
+impl<C> Schedule<C> {
+    /// False if `spec.id` is already registered.
+    fn add(&mut self, spec: TaskSpec<C>, now: Instant) -> bool {
+        if self.entries.iter().any(|entry| entry.spec.id == spec.id) {
+            return false;
+        }
+        self.entries.push(Entry {
+            next_due: now + spec.first_delay,
+            spec,
+            running: false,
+            last_run: None,
+            runs: 0,
+            failures: 0,
+        });
+        true
+    }
+
+    /// Mark every due, idle task running and return them. While `paused`,
+    /// tasks that skip it are pushed to their next interval instead.
+    fn take_due(&mut self, now: Instant, paused: bool, mut roll: impl FnMut() -> f64) -> Vec<TaskSpec<C>> {
+        let mut due = Vec::new();
+        for entry in &mut self.entries {
+            if entry.running || entry.next_due > now {
+                continue;
+            }
+            if paused && entry.spec.skip_while_paused {
+                entry.next_due = now + next_delay(entry.spec.interval, entry.spec.jitter, roll());
+                continue;
+            }
+            entry.running = true;
+            due.push(entry.spec);
+        }
+        due
+    }
+
+    /// Claim `id` for a manual run.
+    fn begin(&mut self, id: &str) -> Result<TaskSpec<C>, BeginError> {
+        let entry = self.entries.iter_mut().find(|entry| entry.spec.id == id).ok_or(BeginError::Unknown)?;
+        if entry.running {
+            return Err(BeginError::Running);
+        }
+        entry.running = true;
+        Ok(entry.spec)
+    }
+
+    /// Record a finished run and schedule the next one from `now`.
+    fn finish(&mut self, id: &str, now: Instant, at: String, duration: Duration, result: Result<(), String>, roll: f64) {
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.spec.id == id) else {
+            return;
+        };
+        entry.running = false;
+        entry.runs += 1;
+        if result.is_err() {
+            entry.failures += 1;
+        }
+        entry.last_run = Some(LastRun {
+            at,
+            ok: result.is_ok(),
+            error: result.err(),
+            duration_ms: duration.as_millis() as u64,
+        });
+        entry.next_due = now + next_delay(entry.spec.interval, entry.spec.jitter, roll);
+    }
+
+    fn running(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.running).count()
+    }
+
+    fn status(&self, id: &str, now: Instant) -> Option<TaskStatus> {
+        let entry = self.entries.iter().find(|entry| entry.spec.id == id)?;
+        Some(TaskStatus {
+            id: entry.spec.id,
+            interval_secs: entry.spec.interval.as_secs(),
+            jitter_secs: entry.spec.jitter.as_secs(),
+            running: entry.running,
+            next_run_in_secs: (!entry.running).then(|| entry.next_due.saturating_duration_since(now).as_secs()),
+            last_run: entry.last_run.clone(),
+            runs: entry.runs,
+            failures: entry.failures,
+        })
+    }
+
+    fn statuses(&self, now: Instant) -> Vec<TaskStatus> {
+        self.entries.iter().filter_map(|entry| self.status(entry.spec.id, now)).collect()
+    }
+}
+
+/// Managed state.
+#[derive(Default)]
+pub(crate) struct MaintenanceScheduler {
+    schedule: Mutex<Schedule<AppHandle>>,
+    stopping: AtomicBool,
+    stop: Notify,
+}
+
+impl MaintenanceScheduler {
+    fn schedule(&self) -> MutexGuard<'_, Schedule<AppHandle>> {
+        self.schedule.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a task, first due after its `first_delay`. A second task with
+    /// the same id is ignored.
+    pub(crate) fn register(&self, spec: TaskSpec) {
+        self.schedule().add(spec, Instant::now());
+    }
+}
+
+/// Run one claimed task to the end and record how it went. A panic counts
+/// as a failure, so the task isn't left marked running.
+async fn run_task(app: AppHandle, spec: TaskSpec) {
+    let started = Instant::now();
+    let at = now_iso8601();
+    let result = match tauri::async_runtime::spawn((spec.run)(app.clone())).await {
+        Ok(result) => result,
+        Err(err) => Err(format!("Task panicked: {err}")),
+    };
+    let duration = started.elapsed();
+    let duration_ms = duration.as_millis().to_string();
+    match &result {
+        Ok(()) => log_event(&app, "DEBUG", "maintenance_task_ran", &[("id", spec.id), ("duration_ms", &duration_ms)]),
+        Err(err) => log_event(
+            &app,
+            "WARN",
+            "maintenance_task_failed",
+            &[("id", spec.id), ("duration_ms", &duration_ms), ("error", err)],
+        ),
+    }
+    let scheduler = app.state::<MaintenanceScheduler>();
+    scheduler
+        .schedule()
+        .finish(spec.id, Instant::now(), at, duration, result, random_roll());
+}
+
+/// Register the built-in tasks and start ticking.
+pub(crate) fn start(app: &AppHandle) {
+    let scheduler = app.state::<MaintenanceScheduler>();
+    for spec in builtin_tasks() {
+        scheduler.register(spec);
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let scheduler = app.state::<MaintenanceScheduler>();
+        loop {
+            let _ = tokio::time::timeout(TICK, scheduler.stop.notified()).await;
+            if scheduler.stopping.load(Ordering::Relaxed) {
+                break;
+            }
+            let due = scheduler
+                .schedule()
+                .take_due(Instant::now(), polling::is_paused(&app), random_roll);
+            for spec in due {
+                tauri::async_runtime::spawn(run_task(app.clone(), spec));
+            }
+        }
+    });
+}
+
+pub(crate) fn status(app: &AppHandle) -> Vec<TaskStatus> {
+    app.try_state::<MaintenanceScheduler>()
+        .map(|scheduler| scheduler.schedule().statuses(Instant::now()))
+        .unwrap_or_default()
+}
+
+/// Run `id` now and return its status afterwards.
+pub(crate) async fn run_now(app: &AppHandle, id: &str) -> Result<TaskStatus, DesktopError> {
+    let scheduler = app.state::<MaintenanceScheduler>();
+    if scheduler.stopping.load(Ordering::Relaxed) {
+        return Err(DesktopError::Internal("The app is shutting down".to_string()));
+    }
+    let spec = scheduler.schedule().begin(id).map_err(|err| {
+        DesktopError::InvalidArgument(match err {
+            BeginError::Unknown => format!("Unknown maintenance task: {id}"),
+            BeginError::Running => format!("Maintenance task {id} is already running"),
+        })
+    })?;
+    log_event(app, "INFO", "maintenance_task_manual", &[("id", id)]);
+    run_task(app.clone(), spec).await;
+    let status = scheduler.schedule().status(id, Instant::now());
+    status.ok_or_else(|| DesktopError::Internal(format!("Maintenance task {id} disappeared")))
+}
+
+/// Stop starting tasks and wait up to [`SHUTDOWN_GRACE`] for running ones.
+/// Safe to call more than once.
+pub(crate) fn shutdown(app: &AppHandle) {
+    let Some(scheduler) = app.try_state::<MaintenanceScheduler>() else {
+        return;
+    };
+    if scheduler.stopping.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    scheduler.stop.notify_one();
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    loop {
+        let running = scheduler.schedule().running();
+        if running == 0 {
+            return;
+        }
+        if Instant::now() >= deadline {
+            log_event(app, "WARN", "maintenance_shutdown_timeout", &[("running", &running.to_string())]);
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{next_delay, BeginError, Schedule, TaskFuture, TaskSpec};
+
+    fn idle(_: ()) -> TaskFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn spec(id: &'static str, interval_secs: u64, jitter_secs: u64, skip_while_paused: bool) -> TaskSpec<()> {
+        TaskSpec {
+            id,
+            interval: Duration::from_secs(interval_secs),
+            jitter: Duration::from_secs(jitter_secs),
+            first_delay: Duration::from_secs(10),
+            skip_while_paused,
+            run: idle,
+        }
+    }
+
+    fn ids(due: &[TaskSpec<()>]) -> Vec<&'static str> {
+        due.iter().map(|spec| spec.id).collect()
+    }
+
+    #[test]
+    fn jitter_adds_up_to_its_full_span() {
+        let minute = Duration::from_secs(60);
+        let ten = Duration::from_secs(10);
+        assert_eq!(next_delay(minute, ten, 0.0), minute);
+        assert_eq!(next_delay(minute, ten, 0.5), Duration::from_secs(65));
+        assert_eq!(next_delay(minute, ten, 1.0), Duration::from_secs(70));
+        assert_eq!(next_delay(minute, ten, 7.0), Duration::from_secs(70), "clamped");
+        assert_eq!(next_delay(minute, ten, -1.0), minute);
+        assert_eq!(next_delay(minute, Duration::ZERO, 0.9), minute);
+    }
+
+    #[test]
+    fn tasks_come_due_after_the_first_delay_then_each_interval_from_finishing() {
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+        let mut schedule = Schedule::default();
+        assert!(schedule.add(spec("probe", 60, 20, false), t0));
+        assert!(!schedule.add(spec("probe", 5, 0, false), t0), "ids are unique");
+
+        assert!(schedule.take_due(at(9), false, || 0.0).is_empty());
+        assert_eq!(ids(&schedule.take_due(at(10), false, || 0.0)), ["probe"]);
+        // Finished after a 5 s run; a roll of 0.5 adds half the jitter.
+        schedule.finish("probe", at(15), "t".to_string(), Duration::from_secs(5), Ok(()), 0.5);
+        assert!(schedule.take_due(at(84), false, || 0.0).is_empty());
+        assert_eq!(ids(&schedule.take_due(at(85), false, || 0.0)), ["probe"]);
+
+        // A failure is recorded and retried on the next interval like any run.
+        schedule.finish("probe", at(85), "t".to_string(), Duration::ZERO, Err("offline".to_string()), 0.0);
+        let status = schedule.status("probe", at(85)).unwrap();
+        assert_eq!((status.runs, status.failures, status.next_run_in_secs), (2, 1, Some(60)));
+        assert_eq!(status.last_run.unwrap().error.as_deref(), Some("offline"));
+        assert_eq!(ids(&schedule.take_due(at(145), false, || 0.0)), ["probe"]);
+    }
+
+    #[test]
+    fn a_task_never_overlaps_itself() {
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+        let mut schedule = Schedule::default();
+        schedule.add(spec("slow", 1, 0, false), t0);
+        assert_eq!(ids(&schedule.take_due(at(10), false, || 0.0)), ["slow"]);
+        // Long past due while still running: neither the tick nor a manual
+        // run starts it again.
+        assert!(schedule.take_due(at(600), false, || 0.0).is_empty());
+        assert_eq!(schedule.begin("slow").err(), Some(BeginError::Running));
+        assert_eq!(schedule.running(), 1);
+        assert_eq!(schedule.status("slow", at(600)).unwrap().next_run_in_secs, None);
+
+        schedule.finish("slow", at(600), "t".to_string(), Duration::from_secs(590), Ok(()), 0.0);
+        assert!(schedule.begin("slow").is_ok(), "free again once finished");
+        assert!(schedule.take_due(at(700), false, || 0.0).is_empty(), "claimed by the manual run");
+        assert_eq!(schedule.begin("missing").err(), Some(BeginError::Unknown));
+    }
+
+    #[test]
+    fn pausing_defers_only_the_tasks_that_ask_for_it() {
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+        let mut schedule = Schedule::default();
+        schedule.add(spec("network", 60, 0, true), t0);
+        schedule.add(spec("logs", 60, 0, false), t0);
+        assert_eq!(ids(&schedule.take_due(at(10), true, || 0.0)), ["logs"]);
+        assert!(schedule.take_due(at(69), false, || 0.0).is_empty());
+        assert_eq!(ids(&schedule.take_due(at(70), false, || 0.0)), ["network"]);
+    }
+}
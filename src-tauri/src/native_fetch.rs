@@ -0,0 +1,502 @@
+//! Shared path for fetches the shell makes on the frontend's behalf with
+//! native TLS. Identical in-flight requests are coalesced: the first caller
+//! performs the request and everyone who asks for the same method and URL
+//! before it settles gets a copy of its result, errors included. Only the
+//! request that actually goes upstream counts against the host's rate limit.
+//! Responses go through the conditional cache in `http_cache`. Hosts on the
+//! local network are refused unless listed in `trustedLocalHosts`.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::{extra_ca, http_cache, polling, rate_limit, trusted_hosts, user_agent, RuntimePrefs};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const PREF_NATIVE_FETCH_MAX_BODY_MB: &str = "nativeFetchMaxBodyMb";
+const DEFAULT_MAX_BODY_MB: u64 = 10;
+const MAX_BODY_MB_LIMIT: u64 = 256;
+/// How much of an unexpected body to quote in the error, e.g. to recognise
+/// a captive portal's login page.
+const SNIPPET_BYTES: usize = 200;
+
+pub(crate) fn validate_max_body_mb(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(mb) if (1..=MAX_BODY_MB_LIMIT).contains(&mb) => Ok(()),
+        _ => Err(format!(
+            "Runtime pref {PREF_NATIVE_FETCH_MAX_BODY_MB} must be an integer from 1 to {MAX_BODY_MB_LIMIT}"
+        )),
+    }
+}
+
+fn max_body_bytes(app: &AppHandle) -> usize {
+    let mb = app
+        .try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_NATIVE_FETCH_MAX_BODY_MB))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_MAX_BODY_MB);
+    (mb.min(MAX_BODY_MB_LIMIT) * 1024 * 1024) as usize
+}
+
+enum SlotState<V> {
+    Pending(Vec<Waker>),
+    Done(Result<V, DesktopError>),
+    /// The leading request was dropped or panicked before settling.
+    Abandoned,
+}
+
+struct Slot<V> {
+    state: Mutex<SlotState<V>>,
+}
+
+impl<V> Slot<V> {
+    fn settle(&self, state: SlotState<V>) {
+        let previous = std::mem::replace(&mut *self.state.lock().unwrap_or_else(|e| e.into_inner()), state);
+        if let SlotState::Pending(wakers) = previous {
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// Resolves once the slot's leader settles.
+struct Follow<V> {
+    slot: Arc<Slot<V>>,
+}
+
+impl<V: Clone> Future for Follow<V> {
+    type Output = Result<V, DesktopError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.state.lock().unwrap_or_else(|e| e.into_inner());
+        match &mut *state {
+            SlotState::Done(result) => Poll::Ready(result.clone()),
+            SlotState::Abandoned => Poll::Ready(Err(DesktopError::Internal(
+                "The shared request was cancelled before it finished".to_string(),
+            ))),
+            SlotState::Pending(wakers) => {
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Clears the in-flight entry when the leader finishes, and marks the slot
+/// abandoned if it never got to settle (panic or cancellation).
+struct LeaderGuard<'a, V> {
+    coalescer: &'a Coalescer<V>,
+    key: &'a str,
+    slot: Arc<Slot<V>>,
+}
+
+impl<V> Drop for LeaderGuard<'_, V> {
+    fn drop(&mut self) {
+        let mut inflight = self.coalescer.inflight.lock().unwrap_or_else(|e| e.into_inner());
+        if inflight.get(self.key).is_some_and(|slot| Arc::ptr_eq(slot, &self.slot)) {
+            inflight.remove(self.key);
+        }
+        drop(inflight);
+        let unsettled = matches!(
+            *self.slot.state.lock().unwrap_or_else(|e| e.into_inner()),
+            SlotState::Pending(_)
+        );
+        if unsettled {
+            self.slot.settle(SlotState::Abandoned);
+        }
+    }
+}
+
+pub(crate) struct Coalescer<V> {
+    inflight: Mutex<HashMap<String, Arc<Slot<V>>>>,
+}
+
+impl<V> Default for Coalescer<V> {
+    fn default() -> Self {
+        Coalescer {
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<V: Clone> Coalescer<V> {
+    /// Run `request` for `key`, or join the identical request already in
+    /// flight. `bypass` always runs a fresh request.
+    pub(crate) async fn run<F, Fut>(&self, key: &str, bypass: bool, request: F) -> Result<V, DesktopError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, DesktopError>>,
+    {
+        if bypass {
+            return request().await;
+        }
+        let slot = {
+            let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(slot) = inflight.get(key) {
+                Err(Arc::clone(slot))
+            } else {
+                let slot = Arc::new(Slot {
+                    state: Mutex::new(SlotState::Pending(Vec::new())),
+                });
+                inflight.insert(key.to_string(), Arc::clone(&slot));
+                Ok(slot)
+            }
+        };
+        let slot = match slot {
+            Ok(slot) => slot,
+            Err(existing) => return Follow { slot: existing }.await,
+        };
+        let guard = LeaderGuard {
+            coalescer: self,
+            key,
+            slot,
+        };
+        let result = request().await;
+        guard.slot.settle(SlotState::Done(result.clone()));
+        drop(guard);
+        result
+    }
+
+    #[cfg(test)]
+    fn inflight_len(&self) -> usize {
+        self.inflight.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct NativeFetchState {
+    text: Coalescer<String>,
+}
+
+/// The start of `bytes` as one printable line.
+fn snippet(bytes: &[u8]) -> String {
+    let end = bytes.len().min(SNIPPET_BYTES);
+    String::from_utf8_lossy(&bytes[..end])
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn is_json_content_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || essence == "text/json" || essence.ends_with("+json")
+}
+
+/// Read a successful response's body, refusing anything over `max_bytes`
+/// (checked as it streams, not just from `Content-Length`) and anything
+/// labelled as something other than JSON. A missing `Content-Type` is
+/// tolerated.
+async fn read_json_body(mut resp: reqwest::Response, label: &str, max_bytes: usize) -> Result<String, DesktopError> {
+    let status = Some(resp.status().as_u16());
+    let too_large = || DesktopError::Http {
+        status,
+        message: format!("{label} response exceeds the {} MB limit", max_bytes / (1024 * 1024)),
+    };
+    if resp.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Err(too_large());
+    }
+    let content_type = header_value(&resp, reqwest::header::CONTENT_TYPE).filter(|ct| !is_json_content_type(ct));
+    let mut body = Vec::new();
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| DesktopError::http("Read body failed", e))?
+    {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+        if content_type.is_some() && body.len() >= SNIPPET_BYTES {
+            break;
+        }
+    }
+    if let Some(content_type) = content_type {
+        return Err(DesktopError::Http {
+            status,
+            message: format!("{label} returned {content_type} instead of JSON: {}", snippet(&body)),
+        });
+    }
+    Ok(String::from_utf8(body).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+}
+
+fn header_value(resp: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    resp.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+}
+
+/// Send one GET, counted against `host`'s rate limit, with the cache's
+/// validators when there are any.
+async fn send(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    host: &str,
+    url: &str,
+    label: &str,
+    validators: Option<&http_cache::Validators>,
+) -> Result<reqwest::Response, DesktopError> {
+    rate_limit::acquire(app, host).await?;
+    let mut request = client
+        .get(url)
+        .header("Accept", "application/json")
+        .timeout(REQUEST_TIMEOUT);
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| DesktopError::http(&format!("{label} fetch failed"), e))?;
+    let retry_after = header_value(&resp, reqwest::header::RETRY_AFTER);
+    rate_limit::record_response(app, host, resp.status().as_u16(), retry_after.as_deref());
+    Ok(resp)
+}
+
+/// GET `url` on `host` and return the body. `label` names the upstream in
+/// error messages ("Polymarket fetch failed"). Bodies with validators are
+/// cached, and a 304 is answered from the cache.
+pub(crate) async fn get_text(
+    app: &AppHandle,
+    host: &str,
+    url: &str,
+    label: &str,
+    coalesce: bool,
+) -> Result<String, DesktopError> {
+    polling::ensure_active(app)?;
+    let parsed = reqwest::Url::parse(url).map_err(|e| DesktopError::InvalidUrl(format!("Invalid URL: {e}")))?;
+    trusted_hosts::require_allowed(app, &parsed)?;
+    let state = app.state::<NativeFetchState>();
+    let key = format!("GET {url}");
+    state
+        .text
+        .run(&key, !coalesce, || async {
+            let client = extra_ca::client_builder(app)?
+                .user_agent(user_agent::effective(app))
+                .build()
+                .map_err(|e| DesktopError::http("HTTP client error", e))?;
+            let cache_key = http_cache::cache_key(url);
+            let validators = cache_key.as_deref().and_then(|key| http_cache::validators(app, key));
+            let mut resp = send(app, &client, host, url, label, validators.as_ref()).await?;
+            if let (Some(cache_key), true) = (&cache_key, resp.status() == reqwest::StatusCode::NOT_MODIFIED) {
+                let etag = header_value(&resp, reqwest::header::ETAG);
+                let last_modified = header_value(&resp, reqwest::header::LAST_MODIFIED);
+                match http_cache::revalidated(app, cache_key, etag, last_modified) {
+                    Some(body) => return Ok(body),
+                    // The cached copy is gone; ask again without validators.
+                    None => resp = send(app, &client, host, url, label, None).await?,
+                }
+            }
+            if !resp.status().is_success() {
+                return Err(DesktopError::Http {
+                    status: Some(resp.status().as_u16()),
+                    message: format!("{label} HTTP {}", resp.status()),
+                });
+            }
+            let etag = header_value(&resp, reqwest::header::ETAG);
+            let last_modified = header_value(&resp, reqwest::header::LAST_MODIFIED);
+            let cache_control = header_value(&resp, reqwest::header::CACHE_CONTROL);
+            let body = read_json_body(resp, label, max_body_bytes(app)).await?;
+            if let Some(cache_key) = &cache_key {
+                http_cache::store_response(app, cache_key, etag, last_modified, cache_control.as_deref(), &body);
+            }
+            Ok(body)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{read_json_body, Coalescer};
+    use crate::error::DesktopError;
+
+    const CALLERS: usize = 25;
+
+    /// Stand-in transport: counts upstream hits and answers after a delay
+    /// long enough for every caller to pile on.
+    async fn mock_upstream(hits: &AtomicUsize, answer: Result<String, DesktopError>) -> Result<String, DesktopError> {
+        hits.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        answer
+    }
+
+    fn run_callers(
+        coalescer: &Arc<Coalescer<String>>,
+        hits: &Arc<AtomicUsize>,
+        bypass: bool,
+        answer: Result<String, DesktopError>,
+    ) -> Vec<Result<String, DesktopError>> {
+        tauri::async_runtime::block_on(async {
+            let tasks: Vec<_> = (0..CALLERS)
+                .map(|_| {
+                    let coalescer = Arc::clone(coalescer);
+                    let hits = Arc::clone(hits);
+                    let answer = answer.clone();
+                    tauri::async_runtime::spawn(async move {
+                        coalescer
+                            .run("GET https://example.test/markets?limit=5", bypass, || mock_upstream(&hits, answer))
+                            .await
+                    })
+                })
+                .collect();
+            let mut results = Vec::new();
+            for task in tasks {
+                results.push(task.await.unwrap());
+            }
+            results
+        })
+    }
+
+    #[test]
+    fn identical_concurrent_calls_hit_upstream_once() {
+        let coalescer = Arc::new(Coalescer::default());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let results = run_callers(&coalescer, &hits, false, Ok("[1,2,3]".to_string()));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|result| matches!(result, Ok(body) if body == "[1,2,3]")));
+        assert_eq!(coalescer.inflight_len(), 0);
+
+        // Settled requests are not cached: the next round goes upstream again.
+        run_callers(&coalescer, &hits, false, Ok("[]".to_string()));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn errors_reach_every_waiter() {
+        let coalescer = Arc::new(Coalescer::default());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let failure = DesktopError::Http {
+            status: Some(502),
+            message: "Polymarket HTTP 502".to_string(),
+        };
+        let results = run_callers(&coalescer, &hits, false, Err(failure));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(results.len(), CALLERS);
+        assert!(results
+            .iter()
+            .all(|result| matches!(result, Err(DesktopError::Http { status: Some(502), .. }))));
+        assert_eq!(coalescer.inflight_len(), 0);
+    }
+
+    #[test]
+    fn bypass_issues_every_request() {
+        let coalescer = Arc::new(Coalescer::default());
+        let hits = Arc::new(AtomicUsize::new(0));
+        run_callers(&coalescer, &hits, true, Ok("{}".to_string()));
+        assert_eq!(hits.load(Ordering::SeqCst), CALLERS);
+    }
+
+    #[test]
+    fn a_panicking_leader_releases_its_waiters_and_entry() {
+        let coalescer: Arc<Coalescer<String>> = Arc::new(Coalescer::default());
+        tauri::async_runtime::block_on(async {
+            let leader = {
+                let coalescer = Arc::clone(&coalescer);
+                tauri::async_runtime::spawn(async move {
+                    coalescer
+                        .run("GET https://example.test/boom", false, || async {
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                            panic!("upstream handler blew up");
+                        })
+                        .await
+                })
+            };
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let follower = coalescer.run("GET https://example.test/boom", false, || async {
+                Ok("should not run".to_string())
+            });
+            assert!(matches!(follower.await, Err(DesktopError::Internal(_))));
+            assert!(leader.await.is_err());
+        });
+        assert_eq!(coalescer.inflight_len(), 0);
+    }
+
+    /// Answer one request on a loopback port with `head` followed by `body`.
+    fn serve_once(head: String, body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(head.as_bytes());
+                // The client may hang up early; that is what's under test.
+                let _ = stream.write_all(&body);
+            }
+        });
+        format!("http://{addr}/markets")
+    }
+
+    fn fetch(content_type: Option<&str>, content_length: bool, body: Vec<u8>) -> Result<String, DesktopError> {
+        let mut head = "HTTP/1.1 200 OK\r\nConnection: close\r\n".to_string();
+        if let Some(content_type) = content_type {
+            head.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        if content_length {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        head.push_str("\r\n");
+        let url = serve_once(head, body);
+        tauri::async_runtime::block_on(async {
+            let resp = reqwest::Client::new()
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| DesktopError::http("test fetch failed", e))?;
+            read_json_body(resp, "Upstream", 1024 * 1024).await
+        })
+    }
+
+    #[test]
+    fn json_bodies_within_the_limit_pass() {
+        let body = br#"[{"id":1}]"#.to_vec();
+        assert_eq!(fetch(Some("application/json; charset=utf-8"), true, body.clone()).unwrap(), r#"[{"id":1}]"#);
+        assert!(fetch(Some("application/vnd.api+json"), false, body.clone()).is_ok());
+        assert!(fetch(None, false, body).is_ok());
+    }
+
+    #[test]
+    fn oversized_bodies_are_refused_declared_or_streamed() {
+        let big = vec![b' '; 2 * 1024 * 1024];
+        for content_length in [true, false] {
+            let err = fetch(Some("application/json"), content_length, big.clone()).unwrap_err();
+            assert!(
+                matches!(&err, DesktopError::Http { status: Some(200), message } if message.contains("exceeds the 1 MB limit")),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn html_is_refused_with_a_snippet() {
+        let portal = format!(
+            "<html>\n<head><title>Sign in to Hotel WiFi</title></head><body>{}</body></html>",
+            "x".repeat(4096)
+        );
+        let err = fetch(Some("text/html; charset=utf-8"), true, portal.into_bytes()).unwrap_err();
+        let DesktopError::Http { message, .. } = err else {
+            panic!("expected an HTTP error");
+        };
+        assert!(message.starts_with("Upstream returned text/html; charset=utf-8 instead of JSON: <html> <head>"));
+        assert!(message.contains("Hotel WiFi"));
+        assert!(!message.contains(&"x".repeat(200)));
+    }
+}
@@ -0,0 +1,405 @@
+//! Metered-connection detection, so a phone hotspot isn't drained by the
+//! dashboard's polling. The OS is asked through a [`CostProbe`]: Windows'
+//! `INetworkCostManager`, NetworkManager's `Metered` property over D-Bus on
+//! Linux, and nothing elsewhere, where the connection counts as unmetered.
+//! What happens on a metered link follows the `meteredBehavior` pref:
+//! `ask` (the default) leaves traffic alone and flags the profile so the UI
+//! can offer the choice, `reduce` stretches the sidecar's polling intervals
+//! and turns off its bootstrap prefetch, and `ignore` does nothing.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logging::log_event;
+use crate::maintenance::TaskSpec;
+use crate::{post_to_local_api, RuntimePrefs};
+
+pub(crate) const PREF_METERED_BEHAVIOR: &str = "meteredBehavior";
+pub(crate) const POLL_MULTIPLIER_ENV: &str = "LOCAL_API_POLL_MULTIPLIER";
+const CHANGED_EVENT: &str = "network-profile-changed";
+/// Polling intervals are this many times longer while reducing.
+const REDUCED_POLL_MULTIPLIER: u32 = 4;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What the OS says the current connection costs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Cost {
+    Metered,
+    Unmetered,
+    #[default]
+    Unknown,
+}
+
+/// A source of [`Cost`]. Probes may block briefly; they run on the monitor
+/// thread.
+pub(crate) trait CostProbe: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn probe(&self) -> Cost;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MeteredBehavior {
+    #[default]
+    Ask,
+    Reduce,
+    Ignore,
+}
+
+impl MeteredBehavior {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ask" => Some(MeteredBehavior::Ask),
+            "reduce" => Some(MeteredBehavior::Reduce),
+            "ignore" => Some(MeteredBehavior::Ignore),
+            _ => None,
+        }
+    }
+}
+
+/// `get_network_profile` payload and `network-profile-changed` event.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct NetworkProfile {
+    metered: bool,
+    cost: Cost,
+    detector: &'static str,
+    behavior: MeteredBehavior,
+    reducing: bool,
+    /// Metered with `ask`: the UI should offer to reduce traffic.
+    needs_decision: bool,
+    poll_multiplier: u32,
+}
+
+fn evaluate(cost: Cost, behavior: MeteredBehavior, detector: &'static str) -> NetworkProfile {
+    let metered = cost == Cost::Metered;
+    let reducing = metered && behavior == MeteredBehavior::Reduce;
+    NetworkProfile {
+        metered,
+        cost,
+        detector,
+        behavior,
+        reducing,
+        needs_decision: metered && behavior == MeteredBehavior::Ask,
+        poll_multiplier: if reducing { REDUCED_POLL_MULTIPLIER } else { 1 },
+    }
+}
+
+pub(crate) fn validate_behavior(value: &Value) -> Result<(), String> {
+    match value.as_str().and_then(MeteredBehavior::parse) {
+        Some(_) => Ok(()),
+        None => Err(format!("Runtime pref {PREF_METERED_BEHAVIOR} must be \"ask\", \"reduce\" or \"ignore\"")),
+    }
+}
+
+/// NetworkManager's `NMMetered`: 1 yes, 2 no, 3 guessed yes, 4 guessed no.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn cost_from_nm_metered(value: u32) -> Cost {
+    match value {
+        1 | 3 => Cost::Metered,
+        2 | 4 => Cost::Unmetered,
+        _ => Cost::Unknown,
+    }
+}
+
+/// `NLM_CONNECTION_COST` flags. Anything billed by volume, roaming or near
+/// its cap is metered.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn cost_from_nlm_flags(flags: u32) -> Cost {
+    const UNRESTRICTED: u32 = 0x1;
+    const FIXED: u32 = 0x2;
+    const VARIABLE: u32 = 0x4;
+    const OVER_DATA_LIMIT: u32 = 0x1_0000;
+    const ROAMING: u32 = 0x4_0000;
+    const APPROACHING_DATA_LIMIT: u32 = 0x8_0000;
+    if flags & (FIXED | VARIABLE | OVER_DATA_LIMIT | ROAMING | APPROACHING_DATA_LIMIT) != 0 {
+        Cost::Metered
+    } else if flags & UNRESTRICTED != 0 {
+        Cost::Unmetered
+    } else {
+        Cost::Unknown
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct NetworkManagerProbe;
+
+#[cfg(target_os = "linux")]
+impl CostProbe for NetworkManagerProbe {
+    fn name(&self) -> &'static str {
+        "networkmanager"
+    }
+
+    fn probe(&self) -> Cost {
+        use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+        // No system bus or no NetworkManager: nothing to go on.
+        let Ok(conn) = dbus::blocking::Connection::new_system() else {
+            return Cost::Unknown;
+        };
+        let proxy = conn.with_proxy(
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            Duration::from_secs(2),
+        );
+        proxy
+            .get::<u32>("org.freedesktop.NetworkManager", "Metered")
+            .map(cost_from_nm_metered)
+            .unwrap_or(Cost::Unknown)
+    }
+}
+
+#[cfg(windows)]
+struct CostManagerProbe;
+
+#[cfg(windows)]
+impl CostProbe for CostManagerProbe {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    fn probe(&self) -> Cost {
+        use windows::Win32::Networking::NetworkListManager::{INetworkCostManager, NetworkListManager};
+        use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+        // SAFETY: COM is initialised on this thread before use, and `flags`
+        // outlives the call that writes it.
+        unsafe {
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let Ok(manager) = CoCreateInstance::<_, INetworkCostManager>(&NetworkListManager, None, CLSCTX_ALL) else {
+                return Cost::Unknown;
+            };
+            let mut flags = 0u32;
+            if manager.GetCost(&mut flags, std::ptr::null()).is_err() {
+                return Cost::Unknown;
+            }
+            cost_from_nlm_flags(flags)
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+struct UnsupportedProbe;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl CostProbe for UnsupportedProbe {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn probe(&self) -> Cost {
+        Cost::Unknown
+    }
+}
+
+fn platform_probe() -> Box<dyn CostProbe> {
+    #[cfg(target_os = "linux")]
+    return Box::new(NetworkManagerProbe);
+    #[cfg(windows)]
+    return Box::new(CostManagerProbe);
+    #[cfg(not(any(target_os = "linux", windows)))]
+    return Box::new(UnsupportedProbe);
+}
+
+/// Managed state: the probe, its last answer and the profile last applied.
+pub(crate) struct NetworkProfileState {
+    probe: Box<dyn CostProbe>,
+    cost: Mutex<Cost>,
+    applied: Mutex<Option<NetworkProfile>>,
+}
+
+impl Default for NetworkProfileState {
+    fn default() -> Self {
+        NetworkProfileState::new(platform_probe())
+    }
+}
+
+impl NetworkProfileState {
+    fn new(probe: Box<dyn CostProbe>) -> Self {
+        NetworkProfileState {
+            probe,
+            cost: Mutex::new(Cost::Unknown),
+            applied: Mutex::new(None),
+        }
+    }
+
+    /// Ask the OS again and remember the answer.
+    fn observe(&self) -> Cost {
+        let cost = self.probe.probe();
+        *self.cost.lock().unwrap_or_else(|e| e.into_inner()) = cost;
+        cost
+    }
+
+    fn profile(&self, behavior: MeteredBehavior) -> NetworkProfile {
+        let cost = *self.cost.lock().unwrap_or_else(|e| e.into_inner());
+        evaluate(cost, behavior, self.probe.name())
+    }
+}
+
+fn behavior(app: &AppHandle) -> MeteredBehavior {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_METERED_BEHAVIOR))
+        .and_then(|value| value.as_str().and_then(MeteredBehavior::parse))
+        .unwrap_or_default()
+}
+
+/// The profile from the last probe and the current pref.
+pub(crate) fn current(app: &AppHandle) -> NetworkProfile {
+    match app.try_state::<NetworkProfileState>() {
+        Some(state) => state.profile(behavior(app)),
+        None => evaluate(Cost::Unknown, behavior(app), "none"),
+    }
+}
+
+/// `LOCAL_API_POLL_MULTIPLIER` for the sidecar, set only while reducing.
+pub(crate) fn env_value(app: &AppHandle) -> Option<String> {
+    let profile = current(app);
+    profile.reducing.then(|| profile.poll_multiplier.to_string())
+}
+
+/// Tell the UI and the sidecar when the profile changed since last time.
+fn publish(app: &AppHandle, profile: NetworkProfile) {
+    {
+        let state = app.state::<NetworkProfileState>();
+        let mut applied = state.applied.lock().unwrap_or_else(|e| e.into_inner());
+        if applied.as_ref() == Some(&profile) {
+            return;
+        }
+        *applied = Some(profile.clone());
+    }
+    log_event(
+        app,
+        "INFO",
+        "network_profile_changed",
+        &[
+            ("metered", &profile.metered.to_string()),
+            ("reducing", &profile.reducing.to_string()),
+        ],
+    );
+    if let Err(err) = app.emit(CHANGED_EVENT, profile.clone()) {
+        log_event(app, "WARN", "network_profile_emit_failed", &[("error", &err.to_string())]);
+    }
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let body = serde_json::json!({ "metered": profile.metered, "pollMultiplier": profile.poll_multiplier });
+        if let Err(err) = post_to_local_api(&handle, "/api/local-network-profile", body).await {
+            log_event(&handle, "WARN", "network_profile_push_failed", &[("error", &err)]);
+        }
+    });
+}
+
+/// Probe the connection and apply the result. Blocks for the probe.
+pub(crate) fn refresh(app: &AppHandle) {
+    let state = app.state::<NetworkProfileState>();
+    state.observe();
+    publish(app, state.profile(behavior(app)));
+}
+
+/// Follow a changed `meteredBehavior` without probing again.
+pub(crate) fn apply(app: &AppHandle) {
+    publish(app, current(app));
+}
+
+/// Probe at startup and every minute; the probe blocks, so it runs on the
+/// blocking pool.
+pub(crate) const MAINTENANCE: TaskSpec = TaskSpec {
+    id: "network_profile",
+    interval: CHECK_INTERVAL,
+    jitter: Duration::from_secs(5),
+    first_delay: Duration::ZERO,
+    skip_while_paused: false,
+    run: |app| {
+        Box::pin(async move {
+            tauri::async_runtime::spawn_blocking(move || refresh(&app))
+                .await
+                .map_err(|e| format!("Network probe failed: {e}"))
+        })
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        cost_from_nlm_flags, cost_from_nm_metered, evaluate, validate_behavior, Cost, CostProbe, MeteredBehavior,
+        NetworkProfileState,
+    };
+
+    struct Fixed(Cost);
+
+    impl CostProbe for Fixed {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn probe(&self) -> Cost {
+            self.0
+        }
+    }
+
+    #[test]
+    fn only_reduce_changes_traffic_on_a_metered_link() {
+        let reduce = evaluate(Cost::Metered, MeteredBehavior::Reduce, "fixed");
+        assert!(reduce.reducing && !reduce.needs_decision);
+        assert_eq!(reduce.poll_multiplier, 4);
+        let ask = evaluate(Cost::Metered, MeteredBehavior::Ask, "fixed");
+        assert!(!ask.reducing && ask.needs_decision);
+        assert_eq!(ask.poll_multiplier, 1);
+        let ignore = evaluate(Cost::Metered, MeteredBehavior::Ignore, "fixed");
+        assert!(ignore.metered && !ignore.reducing && !ignore.needs_decision);
+        let unmetered = evaluate(Cost::Unmetered, MeteredBehavior::Reduce, "fixed");
+        assert!(!unmetered.metered && !unmetered.reducing);
+    }
+
+    #[test]
+    fn unknown_counts_as_unmetered() {
+        let state = NetworkProfileState::new(Box::new(Fixed(Cost::Unknown)));
+        assert_eq!(state.observe(), Cost::Unknown);
+        let profile = state.profile(MeteredBehavior::Reduce);
+        assert!(!profile.metered && !profile.reducing && !profile.needs_decision);
+        assert_eq!(
+            serde_json::to_value(&profile).unwrap(),
+            json!({
+                "metered": false,
+                "cost": "unknown",
+                "detector": "fixed",
+                "behavior": "reduce",
+                "reducing": false,
+                "needs_decision": false,
+                "poll_multiplier": 1,
+            })
+        );
+    }
+
+    #[test]
+    fn the_profile_follows_the_last_probe() {
+        let state = NetworkProfileState::new(Box::new(Fixed(Cost::Metered)));
+        assert_eq!(state.profile(MeteredBehavior::Reduce).cost, Cost::Unknown, "not probed yet");
+        state.observe();
+        assert!(state.profile(MeteredBehavior::Reduce).reducing);
+    }
+
+    #[test]
+    fn platform_answers_map_to_costs() {
+        assert_eq!(cost_from_nm_metered(1), Cost::Metered);
+        assert_eq!(cost_from_nm_metered(3), Cost::Metered);
+        assert_eq!(cost_from_nm_metered(4), Cost::Unmetered);
+        assert_eq!(cost_from_nm_metered(0), Cost::Unknown);
+        assert_eq!(cost_from_nlm_flags(0x1), Cost::Unmetered);
+        assert_eq!(cost_from_nlm_flags(0x4), Cost::Metered);
+        assert_eq!(cost_from_nlm_flags(0x1 | 0x4_0000), Cost::Metered, "roaming");
+        assert_eq!(cost_from_nlm_flags(0), Cost::Unknown);
+    }
+
+    #[test]
+    fn pref_is_one_of_the_behaviors() {
+        for ok in ["ask", "reduce", "ignore"] {
+            assert!(validate_behavior(&json!(ok)).is_ok());
+        }
+        assert!(validate_behavior(&json!("Reduce")).is_err());
+        assert!(validate_behavior(&json!(true)).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! Finding a Node.js executable for the sidecar. Candidates are tried in a
+//! fixed order: `LOCAL_API_NODE_BIN`, the bundled runtime, `PATH`, the
+//! version-manager install roots (nvm, Volta, asdf), then a few well-known
+//! install locations. Symlinks are followed, and version-manager shims or
+//! wrapper scripts are asked for the real binary by running them once with
+//! `-e "console.log(process.execPath)"`. On Windows a `.cmd`/`.bat` shim is
+//! only ever used for that one fixed query: the sidecar's arguments come
+//! partly from prefs, and `cmd /C` would re-parse them, so a batch file that
+//! doesn't report a real `node.exe` is refused.
+
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tauri::AppHandle;
+
+use crate::logging::{append_desktop_debug, append_desktop_log};
+
+/// How long a shim gets to print the real binary's path.
+const SHIM_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) const NODE_NAME: &str = if cfg!(windows) { "node.exe" } else { "node" };
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Candidate {
+    pub(crate) path: PathBuf,
+    pub(crate) source: &'static str,
+}
+
+/// Inputs to the candidate list, gathered from the environment by
+/// `resolve_node_binary` and built by hand in tests.
+#[derive(Debug, Default)]
+pub(crate) struct SearchRoots {
+    pub(crate) explicit: Option<PathBuf>,
+    pub(crate) bundled: Vec<PathBuf>,
+    pub(crate) path_dirs: Vec<PathBuf>,
+    pub(crate) nvm_dir: Option<PathBuf>,
+    pub(crate) volta_home: Option<PathBuf>,
+    pub(crate) asdf_dir: Option<PathBuf>,
+    pub(crate) common: Vec<PathBuf>,
+}
+
+impl SearchRoots {
+    /// Version-manager roots from their env vars, else their default
+    /// locations under the home directory.
+    pub(crate) fn with_version_managers(mut self, env: impl Fn(&str) -> Option<PathBuf>, home: Option<&Path>) -> Self {
+        let root = |var: &str, default: &str| env(var).or_else(|| home.map(|home| home.join(default)));
+        self.nvm_dir = root("NVM_DIR", ".nvm");
+        self.volta_home = root("VOLTA_HOME", ".volta");
+        self.asdf_dir = root("ASDF_DATA_DIR", ".asdf");
+        self
+    }
+}
+
+type Version = (u32, u32, u32);
+
+/// `v20.11.1` -> (20, 11, 1); anything else sorts after every real version.
+fn parse_version(name: &str) -> Option<Version> {
+    let mut parts = name.strip_prefix('v')?.splitn(3, '.').map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// Installed nvm versions, newest first.
+fn nvm_versions(nvm_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(nvm_dir.join("versions").join("node")) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<(Option<Version>, PathBuf)> = entries
+        .flatten()
+        .map(|entry| (parse_version(&entry.file_name().to_string_lossy()), entry.path()))
+        .collect();
+    versions.sort_by_key(|(version, path)| (version.is_none(), Reverse(*version), path.clone()));
+    versions.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Every place worth checking, in the order they are tried.
+pub(crate) fn candidates(roots: &SearchRoots) -> Vec<Candidate> {
+    let mut out = Vec::new();
+    let mut push = |path: PathBuf, source: &'static str| out.push(Candidate { path, source });
+    if let Some(explicit) = &roots.explicit {
+        push(explicit.clone(), "env");
+    }
+    for bundled in &roots.bundled {
+        push(bundled.clone(), "bundled");
+    }
+    for dir in &roots.path_dirs {
+        push(dir.join(NODE_NAME), "path");
+    }
+    if let Some(nvm_dir) = &roots.nvm_dir {
+        for version in nvm_versions(nvm_dir) {
+            let bin = if cfg!(windows) { version } else { version.join("bin") };
+            push(bin.join(NODE_NAME), "nvm");
+        }
+    }
+    if let Some(volta_home) = &roots.volta_home {
+        push(volta_home.join("bin").join(NODE_NAME), "volta");
+    }
+    if let Some(asdf_dir) = &roots.asdf_dir {
+        push(asdf_dir.join("shims").join(NODE_NAME), "asdf");
+    }
+    for common in &roots.common {
+        push(common.clone(), "common");
+    }
+    out
+}
+
+fn is_batch(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cmd") || ext.eq_ignore_ascii_case("bat"))
+}
+
+/// Why `path` looks like a stand-in for node rather than node itself:
+/// a batch file, a script with a shebang, something in a `shims`
+/// directory, or a launcher binary with another name (Volta's symlinks
+/// point at `volta-shim`). `None` for a plain node binary.
+pub(crate) fn shim_kind(invoked: &Path, real: &Path) -> Option<&'static str> {
+    if is_batch(real) {
+        return Some("batch");
+    }
+    let mut head = [0u8; 2];
+    if File::open(real).and_then(|mut file| file.read_exact(&mut head)).is_ok() && &head == b"#!" {
+        return Some("script");
+    }
+    if invoked.components().any(|part| part.as_os_str() == "shims") {
+        return Some("shim_dir");
+    }
+    let stem = real.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    if !stem.to_ascii_lowercase().starts_with("node") {
+        return Some("launcher");
+    }
+    None
+}
+
+/// A `Command` for a resolved `node`, which is never a batch file.
+pub(crate) fn command(node: &Path) -> Command {
+    Command::new(node)
+}
+
+/// Run the shim once and return the binary it reports as `process.execPath`.
+/// The shim is invoked by its own path, since some dispatch on argv[0];
+/// batch files go through `cmd /C`, with nothing but the fixed query.
+pub(crate) fn resolve_shim(shim: &Path) -> Option<PathBuf> {
+    let mut command = if is_batch(shim) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(shim);
+        cmd
+    } else {
+        Command::new(shim)
+    };
+    let mut child = command
+        .args(["-e", "console.log(process.execPath)"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < SHIM_TIMEOUT => std::thread::sleep(Duration::from_millis(25)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    let mut stdout = String::new();
+    child.stdout.take()?.read_to_string(&mut stdout).ok()?;
+    if !status.success() {
+        return None;
+    }
+    let path = PathBuf::from(stdout.lines().rev().map(str::trim).find(|line| !line.is_empty())?);
+    (path.is_file() && !is_batch(&path)).then_some(path)
+}
+
+/// The binary `candidate` stands for, or `None` when it isn't usable.
+fn resolve_candidate(app: &AppHandle, candidate: &Candidate) -> Option<PathBuf> {
+    let path = &candidate.path;
+    if !path.is_file() {
+        append_desktop_debug(app, &format!("node: {} candidate missing: {}", candidate.source, path.display()));
+        return None;
+    }
+    let is_symlink = fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink());
+    let real = if is_symlink {
+        fs::canonicalize(path).unwrap_or_else(|_| path.clone())
+    } else {
+        path.clone()
+    };
+    let Some(kind) = shim_kind(path, &real) else {
+        append_desktop_debug(app, &format!("node: using {} binary {}", candidate.source, real.display()));
+        return Some(real);
+    };
+    if let Some(resolved) = resolve_shim(path) {
+        append_desktop_debug(
+            app,
+            &format!("node: {} {kind} {} resolved to {}", candidate.source, path.display(), resolved.display()),
+        );
+        return Some(resolved);
+    }
+    append_desktop_debug(
+        app,
+        &format!("node: {} {kind} {} did not report a node binary", candidate.source, path.display()),
+    );
+    None
+}
+
+pub(crate) fn resolve(app: &AppHandle, roots: &SearchRoots) -> Option<PathBuf> {
+    for candidate in candidates(roots) {
+        if let Some(found) = resolve_candidate(app, &candidate) {
+            return Some(found);
+        }
+        if candidate.source == "env" {
+            append_desktop_log(
+                app,
+                "WARN",
+                &format!(
+                    "LOCAL_API_NODE_BIN is set but not a usable Node.js binary: {}",
+                    candidate.path.display()
+                ),
+            );
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{candidates, parse_version, shim_kind, SearchRoots, NODE_NAME};
+    use crate::test_support::temp_dir;
+
+    fn write(path: &Path, contents: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn candidates_follow_documented_order() {
+        let home = temp_dir("node-binary-order");
+        for version in ["v18.19.0", "v20.11.1", "v20.9.0", "system"] {
+            fs::create_dir_all(home.join(".nvm/versions/node").join(version)).unwrap();
+        }
+        let roots = SearchRoots {
+            explicit: Some(PathBuf::from("/custom/node")),
+            bundled: vec![PathBuf::from("/res/sidecar/node/node")],
+            path_dirs: vec![PathBuf::from("/usr/bin")],
+            common: vec![PathBuf::from("/opt/homebrew/bin/node")],
+            ..SearchRoots::default()
+        }
+        .with_version_managers(|_| None, Some(&home));
+        let order: Vec<(&str, PathBuf)> = candidates(&roots)
+            .into_iter()
+            .map(|candidate| (candidate.source, candidate.path))
+            .collect();
+        let nvm = |version: &str| {
+            let dir = home.join(".nvm/versions/node").join(version);
+            if cfg!(windows) { dir } else { dir.join("bin") }.join(NODE_NAME)
+        };
+        assert_eq!(
+            order,
+            [
+                ("env", PathBuf::from("/custom/node")),
+                ("bundled", PathBuf::from("/res/sidecar/node/node")),
+                ("path", PathBuf::from("/usr/bin").join(NODE_NAME)),
+                ("nvm", nvm("v20.11.1")),
+                ("nvm", nvm("v20.9.0")),
+                ("nvm", nvm("v18.19.0")),
+                ("nvm", nvm("system")),
+                ("volta", home.join(".volta/bin").join(NODE_NAME)),
+                ("asdf", home.join(".asdf/shims").join(NODE_NAME)),
+                ("common", PathBuf::from("/opt/homebrew/bin/node")),
+            ]
+        );
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn env_vars_override_version_manager_homes() {
+        let roots = SearchRoots::default().with_version_managers(
+            |var| (var == "VOLTA_HOME").then(|| PathBuf::from("/tools/volta")),
+            None,
+        );
+        assert_eq!(roots.volta_home, Some(PathBuf::from("/tools/volta")));
+        assert_eq!(roots.nvm_dir, None);
+        assert_eq!(parse_version("v22.1.0"), Some((22, 1, 0)));
+        assert_eq!(parse_version("lts"), None);
+    }
+
+    #[test]
+    fn detects_shims_and_plain_binaries() {
+        let dir = temp_dir("node-binary-shims");
+        let script = dir.join("bin/node");
+        write(&script, b"#!/usr/bin/env bash\nexec \"$NVM_BIN/node\" \"$@\"\n");
+        assert_eq!(shim_kind(&script, &script), Some("script"));
+
+        let batch = dir.join("nodejs/node.cmd");
+        write(&batch, b"@echo off\r\n\"%~dp0\\node.exe\" %*\r\n");
+        assert_eq!(shim_kind(&batch, &batch), Some("batch"));
+
+        let asdf = dir.join(".asdf/shims/node");
+        write(&asdf, b"\x7fELF\x02\x01");
+        assert_eq!(shim_kind(&asdf, &asdf), Some("shim_dir"));
+
+        let volta = dir.join(".volta/bin/volta-shim");
+        write(&volta, b"\x7fELF\x02\x01");
+        assert_eq!(shim_kind(&dir.join(".volta/bin/node"), &volta), Some("launcher"));
+
+        let real = dir.join("versions/node/v20.11.1/bin/node");
+        write(&real, b"\x7fELF\x02\x01");
+        assert_eq!(shim_kind(&real, &real), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shim_reports_real_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("node-binary-resolve");
+        let real = dir.join("real/node");
+        write(&real, b"\x7fELF\x02\x01");
+        let shim = dir.join("shims/node");
+        write(&shim, format!("#!/bin/sh\necho some banner\necho {}\n", real.display()).as_bytes());
+        fs::set_permissions(&shim, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(super::resolve_shim(&shim), Some(real));
+
+        let broken = dir.join("shims/broken");
+        write(&broken, b"#!/bin/sh\nexit 3\n");
+        fs::set_permissions(&broken, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(super::resolve_shim(&broken), None);
+
+        // A shim pointing at another batch file is no way around cmd.
+        let batch = dir.join("real/node.cmd");
+        write(&batch, b"@echo off\r\n");
+        let to_batch = dir.join("shims/to-batch");
+        write(&to_batch, format!("#!/bin/sh\necho {}\n", batch.display()).as_bytes());
+        fs::set_permissions(&to_batch, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(super::resolve_shim(&to_batch), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
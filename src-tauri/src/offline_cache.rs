@@ -0,0 +1,325 @@
+//! Last-known-good responses for native fetches, served when the network is
+//! down. Every successful body is kept in the persistent cache under a hash
+//! of its request (credentials stripped, as in `http_cache`), so it survives
+//! restarts. A request that fails without reaching the server falls back to
+//! that copy when it is younger than `apiCacheMaxStaleHours` (6 by default),
+//! marked `stale` so the UI can badge it. HTTP errors from the server are
+//! passed through: it answered, so the network is not the problem.
+//!
+//! Bodies are written like `write_cache_entry` values, under the cache size
+//! limits and the free-space check. Each write first drops copies past the
+//! age cap and, beyond [`MAX_ENTRIES`], the oldest ones.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::cache_entry;
+use crate::cache_limits::{self, Limits};
+use crate::disk_space;
+use crate::error::DesktopError;
+use crate::http_cache;
+use crate::logging::{log_event, now_ms};
+use crate::{cache_file_path, PersistentCache, RuntimePrefs};
+
+pub(crate) const PREF_API_CACHE_MAX_STALE_HOURS: &str = "apiCacheMaxStaleHours";
+const DEFAULT_MAX_STALE_HOURS: u64 = 6;
+const KEY_PREFIX: &str = "api-fallback:";
+/// Fallback copies kept at most; the oldest go first.
+const MAX_ENTRIES: usize = 200;
+
+/// A command's body: fresh from upstream as before, or the cached copy.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum CachedBody {
+    Fresh(String),
+    Stale {
+        stale: bool,
+        /// Unix milliseconds.
+        cached_at: u64,
+        body: String,
+    },
+}
+
+/// Fallback cache age, for diagnostics.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct OfflineCacheStats {
+    entries: usize,
+    oldest_age_secs: Option<u64>,
+    newest_age_secs: Option<u64>,
+    max_stale_hours: u64,
+}
+
+/// Result of `purge_stale_api_cache`.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct PurgedApiCache {
+    removed: usize,
+    kept: usize,
+}
+
+pub(crate) fn validate_max_stale_hours(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(hours) if hours > 0 => Ok(()),
+        _ => Err(format!("Runtime pref {PREF_API_CACHE_MAX_STALE_HOURS} must be a positive integer")),
+    }
+}
+
+fn max_stale_hours(app: &AppHandle) -> u64 {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_API_CACHE_MAX_STALE_HOURS))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_MAX_STALE_HOURS)
+}
+
+fn max_stale_ms(app: &AppHandle) -> u64 {
+    max_stale_hours(app).saturating_mul(3600 * 1000)
+}
+
+/// Persistent-cache key for `url`; the URL itself isn't stored.
+fn entry_key(url: &str) -> Option<String> {
+    let signature = http_cache::cache_key(url)?;
+    let digest = Sha256::digest(signature.as_bytes());
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    Some(format!("{KEY_PREFIX}{hex}"))
+}
+
+/// The request never got an answer: DNS, connect, TLS or timeout.
+fn is_network_error(err: &DesktopError) -> bool {
+    matches!(err, DesktopError::Http { status: None, .. })
+}
+
+/// When the body was stored: the envelope's write time, or `cachedAt` in
+/// entries from before the envelope.
+fn cached_at(entry: &Value) -> Option<u64> {
+    cache_entry::written_at(entry).or_else(|| entry.get("cachedAt").and_then(Value::as_u64))
+}
+
+/// Fallback keys to drop before storing a new copy, given the others as
+/// `(key, cached_at)`: undated or past the age cap, then the oldest until
+/// `keep` remain.
+fn evictions(mut others: Vec<(String, Option<u64>)>, now: u64, max_stale_ms: u64, keep: usize) -> Vec<String> {
+    others.sort_by_key(|(_, at)| std::cmp::Reverse(*at));
+    let mut evicted = Vec::new();
+    let mut kept = 0;
+    for (key, at) in others {
+        match at {
+            Some(at) if now.saturating_sub(at) <= max_stale_ms && kept < keep => kept += 1,
+            _ => evicted.push(key),
+        }
+    }
+    evicted
+}
+
+/// Store a good body under the same limits as `write_cache_entry`. A refused
+/// write only costs the offline copy, so it is logged and not returned.
+fn remember(app: &AppHandle, cache: &PersistentCache, key: String, body: &str) {
+    let now = now_ms();
+    let others = cache
+        .entries_where(|other| other.starts_with(KEY_PREFIX) && other != key)
+        .into_iter()
+        .map(|(other, entry)| (other, cached_at(&entry)))
+        .collect();
+    let evicted = evictions(others, now, max_stale_ms(app), MAX_ENTRIES - 1);
+    if !evicted.is_empty() {
+        cache.remove_where(|other, _| evicted.iter().any(|key| key == other));
+    }
+    let limits = Limits::from_prefs(app);
+    let bytes = body.len() as u64;
+    let written = limits.check_entry(&key, bytes).and_then(|()| {
+        let path = cache_file_path(app)?;
+        let stored = cache_entry::wrap(json!({ "body": body }), now, None);
+        cache.insert_and_flush(&path, key.clone(), stored, &limits, |total| {
+            disk_space::ensure_space(app, &path, total)
+        })
+    });
+    if let Err(err) = &written {
+        cache_limits::log_refusal(app, &key, bytes, err);
+    }
+}
+
+/// What to answer for a fetch that returned `result`, given the cached
+/// `entry` for the same request.
+fn resolve(result: Result<String, DesktopError>, entry: Option<&Value>, now: u64, max_stale_ms: u64) -> Result<CachedBody, DesktopError> {
+    let err = match result {
+        Ok(body) => return Ok(CachedBody::Fresh(body)),
+        Err(err) if is_network_error(&err) => err,
+        Err(err) => return Err(err),
+    };
+    let Some((cached_at, body)) =
+        entry.and_then(|entry| Some((cached_at(entry)?, cache_entry::value(entry).get("body")?.as_str()?)))
+    else {
+        return Err(err);
+    };
+    if now.saturating_sub(cached_at) > max_stale_ms {
+        return Err(err);
+    }
+    Ok(CachedBody::Stale {
+        stale: true,
+        cached_at,
+        body: body.to_string(),
+    })
+}
+
+/// Remember a good body, or answer from the last one when offline.
+pub(crate) fn through(app: &AppHandle, url: &str, result: Result<String, DesktopError>) -> Result<CachedBody, DesktopError> {
+    let (Some(key), Some(cache)) = (entry_key(url), app.try_state::<PersistentCache>()) else {
+        return result.map(CachedBody::Fresh);
+    };
+    if let Ok(body) = &result {
+        remember(app, &cache, key, body);
+        return result.map(CachedBody::Fresh);
+    }
+    let entry = cache.get(&key);
+    let resolved = resolve(result, entry.as_ref(), now_ms(), max_stale_ms(app));
+    if let Ok(CachedBody::Stale { cached_at, .. }) = &resolved {
+        let age_secs = now_ms().saturating_sub(*cached_at) / 1000;
+        log_event(app, "INFO", "offline_cache_served", &[("age_secs", &age_secs.to_string())]);
+    }
+    resolved
+}
+
+/// Drop fallback entries older than the cap.
+pub(crate) fn purge_stale(app: &AppHandle) -> PurgedApiCache {
+    let Some(cache) = app.try_state::<PersistentCache>() else {
+        return PurgedApiCache { removed: 0, kept: 0 };
+    };
+    let (now, cap) = (now_ms(), max_stale_ms(app));
+    let removed = cache.remove_where(|key, entry| {
+        key.starts_with(KEY_PREFIX) && cached_at(entry).is_none_or(|at| now.saturating_sub(at) > cap)
+    });
+    let kept = cache.count_where(|key| key.starts_with(KEY_PREFIX));
+    log_event(app, "INFO", "offline_cache_purged", &[("removed", &removed.to_string())]);
+    PurgedApiCache { removed, kept }
+}
+
+pub(crate) fn stats(app: &AppHandle) -> OfflineCacheStats {
+    let max_stale_hours = max_stale_hours(app);
+    let Some(cache) = app.try_state::<PersistentCache>() else {
+        return OfflineCacheStats {
+            max_stale_hours,
+            ..Default::default()
+        };
+    };
+    let now = now_ms();
+    let ages: Vec<u64> = cache
+        .values_where(|key| key.starts_with(KEY_PREFIX))
+        .iter()
+        .filter_map(cached_at)
+        .map(|at| now.saturating_sub(at) / 1000)
+        .collect();
+    OfflineCacheStats {
+        entries: ages.len(),
+        oldest_age_secs: ages.iter().copied().max(),
+        newest_age_secs: ages.iter().copied().min(),
+        max_stale_hours,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{cached_at, entry_key, evictions, resolve, validate_max_stale_hours, CachedBody};
+    use crate::cache_entry::wrap;
+    use crate::error::DesktopError;
+
+    const HOUR_MS: u64 = 3600 * 1000;
+    const CAP: u64 = 6 * HOUR_MS;
+    const NOW: u64 = 100 * HOUR_MS;
+
+    fn offline() -> Result<String, DesktopError> {
+        Err(DesktopError::Http {
+            status: None,
+            message: "Polymarket fetch failed: dns error".to_string(),
+        })
+    }
+
+    fn cached(age_ms: u64) -> serde_json::Value {
+        json!({ "cachedAt": NOW - age_ms, "body": "[{\"id\":1}]" })
+    }
+
+    #[test]
+    fn a_fresh_response_is_served_as_is() {
+        let result = resolve(Ok("[]".to_string()), Some(&cached(HOUR_MS)), NOW, CAP).unwrap();
+        assert_eq!(result, CachedBody::Fresh("[]".to_string()));
+        assert_eq!(serde_json::to_value(&result).unwrap(), json!("[]"));
+    }
+
+    #[test]
+    fn offline_falls_back_to_a_recent_copy_marked_stale() {
+        let result = resolve(offline(), Some(&cached(2 * HOUR_MS)), NOW, CAP).unwrap();
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            json!({ "stale": true, "cached_at": NOW - 2 * HOUR_MS, "body": "[{\"id\":1}]" })
+        );
+    }
+
+    #[test]
+    fn a_copy_over_the_cap_is_not_served() {
+        let err = resolve(offline(), Some(&cached(CAP + 1)), NOW, CAP).unwrap_err();
+        assert_eq!(err.code(), "http_error");
+        assert!(resolve(offline(), Some(&cached(CAP)), NOW, CAP).is_ok(), "the cap itself still counts");
+    }
+
+    #[test]
+    fn offline_without_a_copy_keeps_the_error() {
+        let err = resolve(offline(), None, NOW, CAP).unwrap_err();
+        assert_eq!(err.to_string(), "Polymarket fetch failed: dns error");
+        assert!(resolve(offline(), Some(&json!({ "body": "[]" })), NOW, CAP).is_err(), "undated entry");
+    }
+
+    #[test]
+    fn enveloped_and_legacy_entries_age_the_same_way() {
+        let enveloped = wrap(json!({ "body": "[{\"id\":1}]" }), NOW - 2 * HOUR_MS, None);
+        assert_eq!(cached_at(&enveloped), Some(NOW - 2 * HOUR_MS));
+        assert_eq!(cached_at(&cached(2 * HOUR_MS)), Some(NOW - 2 * HOUR_MS));
+        assert_eq!(
+            resolve(offline(), Some(&enveloped), NOW, CAP).unwrap(),
+            resolve(offline(), Some(&cached(2 * HOUR_MS)), NOW, CAP).unwrap()
+        );
+        let old = wrap(json!({ "body": "[]" }), NOW - CAP - 1, None);
+        assert!(resolve(offline(), Some(&old), NOW, CAP).is_err());
+        assert_eq!(cached_at(&json!({ "body": "[]" })), None, "undated, so purged");
+    }
+
+    #[test]
+    fn server_errors_are_not_hidden_by_the_cache() {
+        let upstream = Err(DesktopError::Http {
+            status: Some(503),
+            message: "Polymarket HTTP 503".to_string(),
+        });
+        assert!(resolve(upstream, Some(&cached(HOUR_MS)), NOW, CAP).is_err());
+    }
+
+    #[test]
+    fn keys_ignore_credentials_and_parameter_order() {
+        let a = entry_key("https://gamma-api.polymarket.com/events?limit=5&closed=false&api_key=x");
+        let b = entry_key("https://gamma-api.polymarket.com/events?closed=false&limit=5");
+        assert_eq!(a, b);
+        assert!(a.unwrap().starts_with("api-fallback:"));
+        assert_ne!(b, entry_key("https://gamma-api.polymarket.com/events?closed=true&limit=5"));
+    }
+
+    #[test]
+    fn pref_is_a_positive_integer() {
+        assert!(validate_max_stale_hours(&json!(6)).is_ok());
+        assert!(validate_max_stale_hours(&json!(0)).is_err());
+        assert!(validate_max_stale_hours(&json!("6")).is_err());
+    }
+
+    #[test]
+    fn stale_and_undated_copies_go_then_the_oldest_past_the_cap() {
+        let others = vec![
+            ("old".to_string(), Some(NOW - 3 * HOUR_MS)),
+            ("expired".to_string(), Some(NOW - CAP - 1)),
+            ("new".to_string(), Some(NOW - HOUR_MS)),
+            ("undated".to_string(), None),
+            ("mid".to_string(), Some(NOW - 2 * HOUR_MS)),
+        ];
+        let mut evicted = evictions(others.clone(), NOW, CAP, 2);
+        evicted.sort();
+        assert_eq!(evicted, vec!["expired", "old", "undated"]);
+        assert_eq!(evictions(others, NOW, CAP, 10).len(), 2, "only the expired and undated");
+    }
+}
@@ -0,0 +1,152 @@
+//! First-run detection. A fresh install has no runtime prefs, no persistent
+//! cache and nothing in the vault; any one of them means an earlier version
+//! ran here, so upgrades from builds that predate `onboardingComplete` are
+//! not mistaken for new installs. The answer is fixed at launch, before this
+//! session writes any of those files.
+
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::logging::log_event;
+use crate::{LocalApiState, RuntimePrefs, SecretsCache};
+
+pub(crate) const PREF_ONBOARDING_COMPLETE: &str = "onboardingComplete";
+
+/// Traces an earlier launch leaves behind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct InstallEvidence {
+    prefs_file: bool,
+    cache_file: bool,
+    vault_secrets: usize,
+}
+
+impl InstallEvidence {
+    pub(crate) fn detect(prefs_path: &Path, cache_path: &Path, vault_secrets: usize) -> Self {
+        InstallEvidence {
+            prefs_file: prefs_path.is_file(),
+            cache_file: cache_path.is_file(),
+            vault_secrets,
+        }
+    }
+
+    pub(crate) fn is_first_run(&self) -> bool {
+        !self.prefs_file && !self.cache_file && self.vault_secrets == 0
+    }
+}
+
+/// Managed state: what launch-time detection found.
+pub(crate) struct OnboardingState {
+    pub(crate) first_run: bool,
+    evidence: InstallEvidence,
+}
+
+impl OnboardingState {
+    pub(crate) fn new(evidence: InstallEvidence) -> Self {
+        OnboardingState {
+            first_run: evidence.is_first_run(),
+            evidence,
+        }
+    }
+}
+
+/// `get_onboarding_state` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct OnboardingReport {
+    first_run: bool,
+    onboarding_complete: bool,
+    /// Names of the secrets in the vault, sorted.
+    configured_secrets: Vec<String>,
+    node_found: bool,
+    sidecar_ready: bool,
+}
+
+pub(crate) fn is_complete(app: &AppHandle) -> bool {
+    app.try_state::<RuntimePrefs>()
+        .is_some_and(|prefs| prefs.get_bool(PREF_ONBOARDING_COMPLETE, false))
+}
+
+/// Whether to open settings once the app is ready.
+pub(crate) fn should_prompt(first_run: bool, complete: bool, opted_out: bool) -> bool {
+    first_run && !complete && !opted_out
+}
+
+pub(crate) fn report(app: &AppHandle, node_found: bool) -> OnboardingReport {
+    let mut configured_secrets: Vec<String> = app
+        .state::<SecretsCache>()
+        .secrets
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect();
+    configured_secrets.sort();
+    let state = app.state::<LocalApiState>();
+    let sidecar_ready = *state.ready.lock().unwrap_or_else(|e| e.into_inner());
+    OnboardingReport {
+        first_run: app.state::<OnboardingState>().first_run,
+        onboarding_complete: is_complete(app),
+        configured_secrets,
+        node_found,
+        sidecar_ready,
+    }
+}
+
+/// One line in desktop.log so support can tell fresh installs from upgrades.
+pub(crate) fn log_detection(app: &AppHandle) {
+    let state = app.state::<OnboardingState>();
+    let evidence = state.evidence;
+    log_event(
+        app,
+        "INFO",
+        "onboarding",
+        &[
+            ("first_run", &state.first_run.to_string()),
+            ("complete", &is_complete(app).to_string()),
+            ("prefs_file", &evidence.prefs_file.to_string()),
+            ("cache_file", &evidence.cache_file.to_string()),
+            ("vault_secrets", &evidence.vault_secrets.to_string()),
+        ],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{should_prompt, InstallEvidence};
+
+    #[test]
+    fn any_earlier_artifact_means_an_upgrade() {
+        let dir = std::env::temp_dir().join(format!("wm-onboarding-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let prefs = dir.join("runtime-prefs.json");
+        let cache = dir.join("persistent-cache.json");
+
+        assert!(InstallEvidence::detect(&prefs, &cache, 0).is_first_run());
+        // A vault written by a version that had no prefs file.
+        assert!(!InstallEvidence::detect(&prefs, &cache, 2).is_first_run());
+
+        fs::write(&cache, "{}").unwrap();
+        assert!(!InstallEvidence::detect(&prefs, &cache, 0).is_first_run());
+        fs::remove_file(&cache).unwrap();
+
+        fs::write(&prefs, "{}").unwrap();
+        assert!(!InstallEvidence::detect(&prefs, &cache, 0).is_first_run());
+
+        // A directory where the file should be isn't evidence of anything.
+        fs::remove_file(&prefs).unwrap();
+        fs::create_dir_all(&prefs).unwrap();
+        assert!(InstallEvidence::detect(&prefs, &cache, 0).is_first_run());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prompts_once_and_respects_opt_out() {
+        assert!(should_prompt(true, false, false));
+        assert!(!should_prompt(true, true, false));
+        assert!(!should_prompt(true, false, true));
+        assert!(!should_prompt(false, false, false));
+    }
+}
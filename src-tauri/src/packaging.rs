@@ -0,0 +1,172 @@
+//! How this copy was installed, reported in `get_desktop_runtime_info`.
+//! A Flatpak sandbox changes a few things: the host's `xdg-open` and file
+//! manager are out of reach, PATH is the runtime's, and the XDG dirs point
+//! into `~/.var/app`. There, links and files open through the desktop
+//! portal, the persistent cache lives in the cache dir, and Node is also
+//! looked for under `/app` and the SDK extensions. Every other install
+//! behaves as it did before.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Packaging {
+    Flatpak,
+    AppImage,
+    /// Installed from the `.deb` into `/usr`.
+    Deb,
+    /// Anything else, including every macOS and Windows install.
+    Native,
+}
+
+/// Classify a Linux install from its environment, which paths exist, and
+/// where the executable lives.
+fn detect(env: impl Fn(&str) -> Option<String>, exists: impl Fn(&Path) -> bool, exe: Option<&Path>) -> Packaging {
+    let set = |name| env(name).is_some_and(|value| !value.is_empty());
+    if set("FLATPAK_ID") || exists(Path::new("/.flatpak-info")) {
+        Packaging::Flatpak
+    } else if set("APPIMAGE") {
+        Packaging::AppImage
+    } else if exe.is_some_and(|exe| exe.starts_with("/usr")) && exists(Path::new("/var/lib/dpkg")) {
+        Packaging::Deb
+    } else {
+        Packaging::Native
+    }
+}
+
+pub(crate) fn current() -> Packaging {
+    static CURRENT: OnceLock<Packaging> = OnceLock::new();
+    *CURRENT.get_or_init(|| {
+        if !cfg!(target_os = "linux") {
+            return Packaging::Native;
+        }
+        let exe = std::env::current_exe().ok();
+        detect(|name| std::env::var(name).ok(), Path::exists, exe.as_deref())
+    })
+}
+
+pub(crate) fn is_flatpak() -> bool {
+    current() == Packaging::Flatpak
+}
+
+/// Where Node can be inside a Flatpak: bundled into `/app`, or from a
+/// `org.freedesktop.Sdk.Extension.nodeNN` mounted under `sdk_root`, newest
+/// first.
+pub(crate) fn flatpak_node_candidates(sdk_root: &Path) -> Vec<PathBuf> {
+    let mut extensions: Vec<(u32, PathBuf)> = std::fs::read_dir(sdk_root)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let major = entry.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            Some((major, entry.path().join("bin").join("node")))
+        })
+        .collect();
+    extensions.sort_by_key(|(major, _)| std::cmp::Reverse(*major));
+    std::iter::once(PathBuf::from("/app/bin/node"))
+        .chain(extensions.into_iter().map(|(_, path)| path))
+        .collect()
+}
+
+/// The `org.freedesktop.portal.OpenURI` calls that stand in for spawning
+/// openers inside the sandbox.
+#[cfg(target_os = "linux")]
+pub(crate) mod portal {
+    use std::fs::File;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use dbus::arg::{AppendAll, PropMap};
+    use dbus::blocking::Connection;
+
+    const DEST: &str = "org.freedesktop.portal.Desktop";
+    const OBJECT: &str = "/org/freedesktop/portal/desktop";
+    const OPEN_URI: &str = "org.freedesktop.portal.OpenURI";
+
+    fn call(method: &str, args: impl AppendAll, what: &str) -> Result<(), String> {
+        let conn = Connection::new_session().map_err(|e| format!("Failed to open {what}: no session bus ({e})"))?;
+        let proxy = conn.with_proxy(DEST, OBJECT, Duration::from_secs(5));
+        proxy
+            .method_call::<(dbus::Path<'static>,), _, _, _>(OPEN_URI, method, args)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to open {what} through the desktop portal: {e}"))
+    }
+
+    /// The portal takes files as descriptors, so it only sees what the
+    /// sandbox can.
+    fn descriptor(path: &Path) -> Result<dbus::arg::OwnedFd, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        // SAFETY: the descriptor was just released by `file`, so this is its
+        // only owner.
+        Ok(unsafe { dbus::arg::OwnedFd::from_raw_fd(file.into_raw_fd()) })
+    }
+
+    pub(crate) fn open_uri(uri: &str) -> Result<(), String> {
+        call("OpenURI", ("", uri, PropMap::new()), uri)
+    }
+
+    pub(crate) fn open_file(path: &Path) -> Result<(), String> {
+        let what = path.display().to_string();
+        call("OpenFile", ("", descriptor(path)?, PropMap::new()), &what)
+    }
+
+    /// Open the folder holding `path` with `path` selected where the file
+    /// manager supports it.
+    pub(crate) fn open_directory(path: &Path) -> Result<(), String> {
+        let what = path.display().to_string();
+        call("OpenDirectory", ("", descriptor(path)?, PropMap::new()), &what)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{detect, flatpak_node_candidates, Packaging};
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+    }
+
+    #[test]
+    fn detects_each_packaging_format() {
+        let nothing = |_: &Path| false;
+        let dpkg = |path: &Path| path == Path::new("/var/lib/dpkg");
+        let usr = Some(Path::new("/usr/bin/world-monitor"));
+        assert_eq!(detect(env(&[("FLATPAK_ID", "app.worldmonitor")]), nothing, usr), Packaging::Flatpak);
+        assert_eq!(
+            detect(env(&[]), |path: &Path| path == Path::new("/.flatpak-info"), None),
+            Packaging::Flatpak
+        );
+        assert_eq!(detect(env(&[("APPIMAGE", "/home/me/WM.AppImage")]), dpkg, usr), Packaging::AppImage);
+        assert_eq!(detect(env(&[("APPIMAGE", "")]), dpkg, usr), Packaging::Deb, "empty means unset");
+        assert_eq!(detect(env(&[]), nothing, usr), Packaging::Native, "no dpkg database");
+        assert_eq!(
+            detect(env(&[]), dpkg, Some(Path::new("/opt/wm/world-monitor"))),
+            Packaging::Native
+        );
+    }
+
+    #[test]
+    fn flatpak_node_prefers_the_bundle_then_the_newest_extension() {
+        let root = std::env::temp_dir().join(format!("wm-packaging-sdk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for name in ["node18", "node22", "node20", "rust-stable", "nodeless"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+        }
+        let expected: Vec<PathBuf> = ["node22", "node20", "node18"]
+            .iter()
+            .map(|name| root.join(name).join("bin").join("node"))
+            .collect();
+        let candidates = flatpak_node_candidates(&root);
+        assert_eq!(candidates[0], PathBuf::from("/app/bin/node"));
+        assert_eq!(candidates[1..], expected[..]);
+        assert_eq!(flatpak_node_candidates(&root.join("missing")), [PathBuf::from("/app/bin/node")]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,239 @@
+//! Detached panel windows, so a single panel (the map, a news feed) can live
+//! on a second monitor. Each is a `panel-<id>` window loading the app with
+//! `?panel=<id>`; the frontend shows only that panel and reaches the local
+//! API through `get_local_api_connection` like the main window does.
+//!
+//! Panels still open at quit are recorded with the rest of the window
+//! layout (see `window_layout`) and reopened at the next launch. Closing one
+//! by hand drops it from the layout.
+
+use serde::Serialize;
+use tauri::menu::{IsMenuItem, MenuItem, MenuItemKind, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder, Wry};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::window_geometry::WindowGeometry;
+use crate::{content_protection, i18n, show_main_window, theme, window_creation, window_layout};
+
+const LABEL_PREFIX: &str = "panel-";
+pub(crate) const MIN_SIZE: (f64, f64) = (360.0, 240.0);
+const DEFAULT_SIZE: (f64, f64) = (720.0, 540.0);
+
+pub(crate) const MENU_WINDOW_ID: &str = "window";
+pub(crate) const MENU_NEW_PANEL_WINDOW_ID: &str = "window.new_panel";
+const MENU_FOCUS_PREFIX: &str = "window.focus.";
+/// Items before the per-window list in the Window submenu.
+const MENU_FIXED_ITEMS: usize = 2;
+
+/// Panels that work on their own, with the names shown in window titles and
+/// the Window menu.
+pub(crate) const DETACHABLE_PANELS: [(&str, &str); 14] = [
+    ("map", "Global Map"),
+    ("live-news", "Live News"),
+    ("live-webcams", "Live Webcams"),
+    ("insights", "AI Insights"),
+    ("strategic-posture", "AI Strategic Posture"),
+    ("cii", "Country Instability"),
+    ("strategic-risk", "Strategic Risk Overview"),
+    ("intel", "Intel Feed"),
+    ("gdelt-intel", "Live Intelligence"),
+    ("polymarket", "Predictions"),
+    ("markets", "Markets"),
+    ("monitors", "My Monitors"),
+    ("telegram-intel", "Telegram Intel"),
+    ("world-clock", "World Clock"),
+];
+
+#[derive(Clone, Debug, Serialize)]
+struct PanelChoice {
+    id: &'static str,
+    name: &'static str,
+}
+
+pub(crate) fn validate_panel_id(id: &str) -> Result<&'static str, DesktopError> {
+    DETACHABLE_PANELS
+        .iter()
+        .find(|(known, _)| *known == id)
+        .map(|(known, _)| *known)
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("Unknown panel: {id}")))
+}
+
+fn panel_name(id: &str) -> &'static str {
+    DETACHABLE_PANELS
+        .iter()
+        .find(|(known, _)| *known == id)
+        .map_or("Panel", |(_, name)| *name)
+}
+
+fn label_for(id: &str) -> String {
+    format!("{LABEL_PREFIX}{id}")
+}
+
+/// The panel id behind a `panel-<id>` label, if it names a detachable panel.
+pub(crate) fn panel_id_from_label(label: &str) -> Option<&'static str> {
+    label
+        .strip_prefix(LABEL_PREFIX)
+        .and_then(|id| validate_panel_id(id).ok())
+}
+
+/// Open panel windows as `(panel id, window)`, in menu order.
+fn open_panel_windows(app: &AppHandle) -> Vec<(&'static str, tauri::WebviewWindow)> {
+    open_panel_windows_except(app, None)
+}
+
+fn open_panel_windows_except(app: &AppHandle, closing: Option<&str>) -> Vec<(&'static str, tauri::WebviewWindow)> {
+    let mut windows: Vec<_> = app
+        .webview_windows()
+        .into_iter()
+        .filter(|(label, _)| Some(label.as_str()) != closing)
+        .filter_map(|(label, window)| panel_id_from_label(&label).map(|id| (id, window)))
+        .collect();
+    windows.sort_by_key(|(id, _)| *id);
+    windows
+}
+
+pub(crate) fn has_open_panel_windows(app: &AppHandle) -> bool {
+    !open_panel_windows(app).is_empty()
+}
+
+/// Open (or focus) the window for `id`, which must already be validated.
+pub(crate) fn create_panel_window(
+    app: &AppHandle,
+    id: &'static str,
+    geometry: Option<WindowGeometry>,
+) -> Result<(), DesktopError> {
+    let label = label_for(id);
+    window_creation::create_or_focus(app, &label, || focus(app, &label), || build(app, id, &label, geometry))
+}
+
+fn focus(app: &AppHandle, label: &str) -> Result<(), DesktopError> {
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    Ok(())
+}
+
+fn build(app: &AppHandle, id: &'static str, label: &str, geometry: Option<WindowGeometry>) -> Result<(), DesktopError> {
+    let url = WebviewUrl::App(format!("index.html?panel={id}").into());
+    let (window_theme, background) = theme::for_new_window(app);
+    let builder = WebviewWindowBuilder::new(app, label, url)
+        .title(format!("World Monitor \u{2014} {}", panel_name(id)))
+        .min_inner_size(MIN_SIZE.0, MIN_SIZE.1)
+        .resizable(true)
+        .content_protected(content_protection::for_new_window(app, label))
+        .theme(window_theme)
+        .background_color(background);
+    let builder = match geometry {
+        Some(geometry) => builder
+            .inner_size(geometry.width, geometry.height)
+            .position(geometry.x, geometry.y),
+        None => builder.inner_size(DEFAULT_SIZE.0, DEFAULT_SIZE.1),
+    };
+    let Some(_window) = window_creation::build(builder)
+        .map_err(|e| DesktopError::Internal(format!("Failed to create panel window: {e}")))?
+    else {
+        return focus(app, label);
+    };
+
+    // Same as the settings window: Windows/Linux menus are per-window.
+    #[cfg(not(target_os = "macos"))]
+    let _ = _window.remove_menu();
+
+    window_layout::record_current(app, &[]);
+    log_event(app, "INFO", "panel_window_opened", &[("panel", id)]);
+    refresh_window_menu(app, None);
+    Ok(())
+}
+
+/// The Window submenu; the per-window list is filled by `refresh_window_menu`.
+pub(crate) fn window_submenu(handle: &AppHandle, language: &str) -> tauri::Result<Submenu<Wry>> {
+    let new_panel = MenuItem::with_id(
+        handle,
+        MENU_NEW_PANEL_WINDOW_ID,
+        i18n::text(language, "menu.window.new_panel"),
+        true,
+        None::<&str>,
+    )?;
+    let separator = PredefinedMenuItem::separator(handle)?;
+    let main = focus_item(handle, "main", "World Monitor")?;
+    let title = i18n::text(language, "menu.window");
+    Submenu::with_id_and_items(handle, MENU_WINDOW_ID, title, true, &[&new_panel, &separator, &main])
+}
+
+fn focus_item(handle: &AppHandle, label: &str, title: &str) -> tauri::Result<MenuItem<Wry>> {
+    MenuItem::with_id(handle, format!("{MENU_FOCUS_PREFIX}{label}"), title, true, None::<&str>)
+}
+
+/// Rebuild the list of windows below "New Panel Window…". `closing` names a
+/// window being destroyed, which may still be registered.
+pub(crate) fn refresh_window_menu(app: &AppHandle, closing: Option<&str>) {
+    let Some(MenuItemKind::Submenu(submenu)) = app.menu().and_then(|menu| menu.get(MENU_WINDOW_ID)) else {
+        return;
+    };
+    let result = (|| -> tauri::Result<()> {
+        for item in submenu.items()?.into_iter().skip(MENU_FIXED_ITEMS) {
+            submenu.remove(&item as &dyn IsMenuItem<Wry>)?;
+        }
+        submenu.append(&focus_item(app, "main", "World Monitor")?)?;
+        for (id, _) in open_panel_windows_except(app, closing) {
+            submenu.append(&focus_item(app, &label_for(id), panel_name(id))?)?;
+        }
+        Ok(())
+    })();
+    if let Err(err) = result {
+        log_event(app, "WARN", "window_menu_refresh_failed", &[("error", &err.to_string())]);
+    }
+}
+
+/// Handle Window menu items; returns false for ids this module doesn't own.
+pub(crate) fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+    if id == MENU_NEW_PANEL_WINDOW_ID {
+        // The main window owns the picker; the shell supplies the choices.
+        show_main_window(app);
+        let choices: Vec<PanelChoice> = DETACHABLE_PANELS
+            .iter()
+            .map(|(id, name)| PanelChoice { id, name })
+            .collect();
+        let _ = app.emit_to("main", "panel-window-picker", choices);
+        return true;
+    }
+    let Some(label) = id.strip_prefix(MENU_FOCUS_PREFIX) else {
+        return false;
+    };
+    if label == "main" {
+        show_main_window(app);
+    } else if let Some(window) = app.get_webview_window(label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{label_for, panel_id_from_label, validate_panel_id, DETACHABLE_PANELS};
+
+    #[test]
+    fn only_allowlisted_panels_are_accepted() {
+        assert_eq!(validate_panel_id("map").unwrap(), "map");
+        assert_eq!(validate_panel_id("live-news").unwrap(), "live-news");
+        for bad in ["", "MAP", "settings", "map?x=1", "../map", "map#a", "panel-map"] {
+            assert!(validate_panel_id(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn labels_round_trip_and_stay_window_safe() {
+        for (id, _) in DETACHABLE_PANELS {
+            assert!(id.chars().all(|c| c.is_ascii_lowercase() || c == '-'), "{id}");
+            assert_eq!(panel_id_from_label(&label_for(id)), Some(id));
+        }
+        assert_eq!(panel_id_from_label("panel-unknown"), None);
+        assert_eq!(panel_id_from_label("main"), None);
+        assert_eq!(panel_id_from_label("map"), None);
+    }
+}
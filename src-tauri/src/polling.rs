@@ -0,0 +1,137 @@
+//! One switch to stop all background fetching, for demos and metered
+//! connections. While paused the sidecar drops its long-lived upstream
+//! connections and answers data routes with 503, native fetch commands fail
+//! with `polling_paused` so panels can badge themselves instead of showing
+//! an error, WebSocket bridge feeds are closed and refused, and the shell's
+//! own samplers skip their rounds. The state lives in the `pollingPaused`
+//! pref so it survives a restart; every window hears
+//! `polling-state-changed`, and resuming sends `data-refresh-requested` so
+//! panels catch up at once rather than at their next interval.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::{post_to_local_api, store_runtime_pref, ws_bridge, RuntimePrefs};
+
+pub(crate) const PREF_POLLING_PAUSED: &str = "pollingPaused";
+pub(crate) const POLLING_PAUSED_ENV: &str = "LOCAL_API_POLLING_PAUSED";
+pub(crate) const MENU_VIEW_PAUSE_POLLING_ID: &str = "view.pause_polling";
+const CHANGED_EVENT: &str = "polling-state-changed";
+const REFRESH_EVENT: &str = "data-refresh-requested";
+
+/// Managed state: whether polling is paused right now.
+#[derive(Default)]
+pub(crate) struct PollingState {
+    paused: AtomicBool,
+}
+
+/// `get_polling_paused` payload and `polling-state-changed` event.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct PollingStatus {
+    paused: bool,
+}
+
+fn stored(app: &AppHandle) -> bool {
+    app.try_state::<RuntimePrefs>()
+        .is_some_and(|prefs| prefs.get_bool(PREF_POLLING_PAUSED, false))
+}
+
+pub(crate) fn is_paused(app: &AppHandle) -> bool {
+    app.try_state::<PollingState>()
+        .is_some_and(|state| state.paused.load(Ordering::Relaxed))
+}
+
+pub(crate) fn status(app: &AppHandle) -> PollingStatus {
+    PollingStatus { paused: is_paused(app) }
+}
+
+/// For commands that would reach upstream.
+pub(crate) fn ensure_active(app: &AppHandle) -> Result<(), DesktopError> {
+    if is_paused(app) {
+        return Err(DesktopError::PollingPaused(
+            "Polling is paused; resume it to fetch new data".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Take the state from the pref at startup, before anything polls.
+pub(crate) fn restore(app: &AppHandle) {
+    app.state::<PollingState>().paused.store(stored(app), Ordering::Relaxed);
+}
+
+pub(crate) fn set_paused(app: &AppHandle, paused: bool) -> Result<PollingStatus, DesktopError> {
+    store_runtime_pref(app, PREF_POLLING_PAUSED, Value::Bool(paused))?;
+    apply(app);
+    Ok(status(app))
+}
+
+/// Follow the pref after it changed, from any of the command, the menus or
+/// `set_runtime_pref`. A no-op when nothing changed.
+pub(crate) fn apply(app: &AppHandle) {
+    let paused = stored(app);
+    if app.state::<PollingState>().paused.swap(paused, Ordering::Relaxed) == paused {
+        return;
+    }
+    log_event(app, "INFO", if paused { "polling_paused" } else { "polling_resumed" }, &[]);
+    sync_menus(app, paused);
+    if paused {
+        // Feeds reconnect after `data-refresh-requested`; new ones are refused.
+        ws_bridge::close_all(app);
+    }
+    if let Err(err) = app.emit(CHANGED_EVENT, PollingStatus { paused }) {
+        log_event(app, "WARN", "polling_emit_failed", &[("error", &err.to_string())]);
+    }
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let body = serde_json::json!({ "paused": paused });
+        if let Err(err) = post_to_local_api(&handle, "/api/local-polling", body).await {
+            // The next start passes the state through the environment anyway.
+            log_event(&handle, "WARN", "polling_push_failed", &[("error", &err)]);
+        }
+        // Sent once the sidecar serves data again, so the catch-up isn't refused.
+        if !paused {
+            let _ = handle.emit(REFRESH_EVENT, ());
+        }
+    });
+}
+
+/// Keep the check marks on View > Pause Polling and the tray item honest.
+fn sync_menus(app: &AppHandle, paused: bool) {
+    let item = app
+        .menu()
+        .and_then(|menu| menu.get(MENU_VIEW_PAUSE_POLLING_ID))
+        .and_then(|item| item.as_check_menuitem().cloned());
+    if let Some(item) = item {
+        let _ = item.set_checked(paused);
+    }
+    #[cfg(feature = "tray")]
+    crate::tray::set_polling_checked(app, paused);
+}
+
+fn toggle(app: &AppHandle) {
+    if let Err(err) = set_paused(app, !is_paused(app)) {
+        log_event(
+            app,
+            "WARN",
+            "menu_action_failed",
+            &[("item", MENU_VIEW_PAUSE_POLLING_ID), ("error", &err.to_string())],
+        );
+        // The OS flipped the check mark on click; put it back.
+        sync_menus(app, is_paused(app));
+    }
+}
+
+/// App menu and tray both use [`MENU_VIEW_PAUSE_POLLING_ID`].
+pub(crate) fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+    if id != MENU_VIEW_PAUSE_POLLING_ID {
+        return false;
+    }
+    toggle(app);
+    true
+}
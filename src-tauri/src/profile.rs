@@ -0,0 +1,140 @@
+//! Which keychain vault and which set of files this launch uses. Dev builds
+//! get their own keychain service so testing secret flows can't touch the
+//! production vault; `--profile NAME` goes further and also keeps the data,
+//! prefs, cache, logs and sidecar port file under a `profiles/NAME`
+//! subdirectory so two instances can run side by side. `WM_KEYRING_SERVICE`
+//! overrides the service name alone.
+//!
+//! Resolved once at the top of `main`, before the vault is read.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+const DEFAULT_KEYRING_SERVICE: &str = "world-monitor";
+const DEV_KEYRING_SERVICE: &str = "world-monitor-dev";
+const PROFILES_SUBDIR: &str = "profiles";
+const MAX_PROFILE_NAME_LEN: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct ActiveProfile {
+    /// `None` for the default profile.
+    name: Option<String>,
+    keyring_service: String,
+    /// What chose the service: `env`, `cli`, `dev_build` or `default`.
+    source: &'static str,
+}
+
+impl ActiveProfile {
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        self.name.is_none()
+    }
+}
+
+static ACTIVE: OnceLock<ActiveProfile> = OnceLock::new();
+
+/// Profile names end up in paths and service names, so they're kept to
+/// letters, digits, `-` and `_`.
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_PROFILE_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid --profile {name:?}: use up to {MAX_PROFILE_NAME_LEN} letters, digits, '-' or '_'"
+        ))
+    }
+}
+
+/// `WM_KEYRING_SERVICE`, then the `--profile` name, then the build channel.
+pub(crate) fn resolve(cli_profile: Option<&str>, env_service: Option<&str>, dev_build: bool) -> ActiveProfile {
+    let name = cli_profile.map(str::to_string);
+    let env_service = env_service.map(str::trim).filter(|service| !service.is_empty());
+    let (keyring_service, source) = match (env_service, &name) {
+        (Some(service), _) => (service.to_string(), "env"),
+        (None, Some(name)) => (format!("{DEFAULT_KEYRING_SERVICE}-{name}"), "cli"),
+        (None, None) if dev_build => (DEV_KEYRING_SERVICE.to_string(), "dev_build"),
+        (None, None) => (DEFAULT_KEYRING_SERVICE.to_string(), "default"),
+    };
+    ActiveProfile {
+        name,
+        keyring_service,
+        source,
+    }
+}
+
+/// Fix the profile for the rest of the process. Later calls are ignored.
+pub(crate) fn activate(profile: ActiveProfile) {
+    let _ = ACTIVE.set(profile);
+}
+
+pub(crate) fn active() -> ActiveProfile {
+    ACTIVE
+        .get()
+        .cloned()
+        .unwrap_or_else(|| resolve(None, None, cfg!(debug_assertions)))
+}
+
+pub(crate) fn keyring_service() -> String {
+    active().keyring_service
+}
+
+/// `base` for the default profile, `base/profiles/NAME` otherwise.
+pub(crate) fn scope_dir(base: &Path) -> PathBuf {
+    scoped(base, active().name.as_deref())
+}
+
+fn scoped(base: &Path, name: Option<&str>) -> PathBuf {
+    match name {
+        Some(name) => base.join(PROFILES_SUBDIR).join(name),
+        None => base.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{resolve, scoped, validate_name};
+
+    #[test]
+    fn env_beats_cli_beats_build_channel() {
+        let default = resolve(None, None, false);
+        assert_eq!((default.keyring_service.as_str(), default.source), ("world-monitor", "default"));
+        let dev = resolve(None, None, true);
+        assert_eq!((dev.keyring_service.as_str(), dev.source), ("world-monitor-dev", "dev_build"));
+
+        let cli = resolve(Some("staging"), None, true);
+        assert_eq!((cli.keyring_service.as_str(), cli.source), ("world-monitor-staging", "cli"));
+        assert_eq!(cli.name.as_deref(), Some("staging"));
+
+        let env = resolve(Some("staging"), Some(" wm-test "), true);
+        assert_eq!((env.keyring_service.as_str(), env.source), ("wm-test", "env"));
+        // The env var only renames the vault; files still follow the profile.
+        assert_eq!(env.name.as_deref(), Some("staging"));
+
+        assert_eq!(resolve(None, Some("  "), false).source, "default");
+    }
+
+    #[test]
+    fn named_profiles_get_their_own_directories() {
+        let base = Path::new("/data/app.worldmonitor");
+        assert_eq!(scoped(base, None), base);
+        assert_eq!(scoped(base, Some("dev")), base.join("profiles").join("dev"));
+    }
+
+    #[test]
+    fn profile_names_are_path_safe() {
+        assert!(validate_name("dev_2-b").is_ok());
+        for bad in ["", "../prod", "a b", "prod/x", &"x".repeat(33)] {
+            assert!(validate_name(bad).is_err(), "{bad:?}");
+        }
+    }
+}
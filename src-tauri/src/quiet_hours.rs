@@ -0,0 +1,374 @@
+//! Quiet hours: a daily local-time window (`22:00`–`07:00` by default) in
+//! which notifications are dropped and the sidecar is asked to poll less.
+//! The boundary math works on Unix seconds and takes the UTC offset as a
+//! function, so it is pure and DST can be tested with synthetic offsets:
+//! a boundary inside a spring-forward gap falls on the first instant after
+//! it, and one inside a repeated fall-back hour on its first occurrence.
+
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logging::{format_iso8601_millis, log_event};
+use crate::{post_to_local_api, RuntimePrefs};
+
+pub(crate) const PREF_QUIET_HOURS: &str = "quietHours";
+pub(crate) const QUIET_HOURS_ENV: &str = "LOCAL_API_QUIET_HOURS";
+const CHANGED_EVENT: &str = "quiet-hours-changed";
+/// Upper bound on the scheduler's sleep, so clock changes and suspend are
+/// noticed within a minute.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+const DAY: i64 = 86_400;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct QuietHoursConfig {
+    pub(crate) enabled: bool,
+    /// Local `HH:MM`.
+    pub(crate) start: String,
+    pub(crate) end: String,
+}
+
+impl Default for QuietHoursConfig {
+    fn default() -> Self {
+        QuietHoursConfig {
+            enabled: false,
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+        }
+    }
+}
+
+/// Start and end as minutes after local midnight. `end < start` spans
+/// midnight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Window {
+    start: i64,
+    end: i64,
+}
+
+fn parse_hhmm(value: &str) -> Option<i64> {
+    let (hours, minutes) = value.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl QuietHoursConfig {
+    pub(crate) fn window(&self) -> Result<Window, String> {
+        let parse = |field: &str, value: &str| {
+            parse_hhmm(value).ok_or_else(|| format!("Runtime pref {PREF_QUIET_HOURS}.{field} must be HH:MM, got {value:?}"))
+        };
+        let window = Window {
+            start: parse("start", &self.start)?,
+            end: parse("end", &self.end)?,
+        };
+        if window.start == window.end {
+            return Err(format!("Runtime pref {PREF_QUIET_HOURS}: start and end must differ"));
+        }
+        Ok(window)
+    }
+}
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    let config: QuietHoursConfig = serde_json::from_value(value.clone())
+        .map_err(|e| format!("Runtime pref {PREF_QUIET_HOURS}: {e}"))?;
+    config.window().map(|_| ())
+}
+
+/// The earliest instant whose local time reads `wall` (local seconds since
+/// the epoch) or later.
+fn resolve_wall(wall: i64, offset_at: &impl Fn(i64) -> i64) -> i64 {
+    let before = offset_at(wall - DAY);
+    let after = offset_at(wall + DAY);
+    let valid = [wall - before, wall - after]
+        .into_iter()
+        .filter(|t| wall - offset_at(*t) == *t)
+        .min();
+    if let Some(t) = valid {
+        return t;
+    }
+    // `wall` is skipped by a forward jump: find the jump.
+    let (mut lo, mut hi) = (wall - after, wall - before);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if mid + offset_at(mid) >= wall {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Phase {
+    pub(crate) quiet: bool,
+    /// Unix seconds of the next change of `quiet`.
+    pub(crate) next_transition: i64,
+}
+
+/// Quiet-or-not at `now` and when that changes. Each quiet period runs from
+/// the start on some local day to the next end after it.
+pub(crate) fn phase_at(now: i64, window: Window, offset_at: impl Fn(i64) -> i64) -> Phase {
+    let today = (now + offset_at(now)).div_euclid(DAY);
+    let spans_midnight = i64::from(window.end < window.start);
+    let period = |day: i64| {
+        (
+            resolve_wall(day * DAY + window.start * 60, &offset_at),
+            resolve_wall((day + spans_midnight) * DAY + window.end * 60, &offset_at),
+        )
+    };
+    for day in [today - 1, today] {
+        let (start, end) = period(day);
+        if start <= now && now < end {
+            return Phase {
+                quiet: true,
+                next_transition: end,
+            };
+        }
+    }
+    let next_start = [today, today + 1]
+        .into_iter()
+        .map(|day| period(day).0)
+        .filter(|start| *start > now)
+        .min()
+        .unwrap_or_else(|| period(today + 2).0);
+    Phase {
+        quiet: false,
+        next_transition: next_start,
+    }
+}
+
+fn local_offset(unix_secs: i64) -> i64 {
+    chrono::DateTime::from_timestamp(unix_secs, 0)
+        .map(|utc| i64::from(chrono::Local.offset_from_utc_datetime(&utc.naive_utc()).local_minus_utc()))
+        .unwrap_or(0)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+pub(crate) fn config(app: &AppHandle) -> QuietHoursConfig {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_QUIET_HOURS))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// The phase now, or `None` when quiet hours are off or misconfigured.
+fn current_phase(app: &AppHandle) -> Option<Phase> {
+    let config = config(app);
+    if !config.enabled {
+        return None;
+    }
+    let window = config.window().ok()?;
+    Some(phase_at(unix_now(), window, local_offset))
+}
+
+/// Managed state: the phase last applied to notifications and the sidecar.
+#[derive(Default)]
+pub(crate) struct QuietHoursState {
+    applied: Mutex<Option<bool>>,
+}
+
+pub(crate) fn is_active(app: &AppHandle) -> bool {
+    app.try_state::<QuietHoursState>()
+        .is_some_and(|state| *state.applied.lock().unwrap_or_else(|e| e.into_inner()) == Some(true))
+}
+
+/// `get_quiet_hours_status` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct QuietHoursStatus {
+    enabled: bool,
+    active: bool,
+    /// ISO 8601 UTC; `None` while quiet hours are off.
+    next_transition: Option<String>,
+}
+
+pub(crate) fn status(app: &AppHandle) -> QuietHoursStatus {
+    let phase = current_phase(app);
+    QuietHoursStatus {
+        enabled: phase.is_some(),
+        active: phase.is_some_and(|phase| phase.quiet),
+        next_transition: phase.map(|phase| format_iso8601_millis(phase.next_transition.max(0) as u128 * 1000)),
+    }
+}
+
+/// Apply the current phase if it changed and return how long until the
+/// next check.
+pub(crate) fn refresh(app: &AppHandle) -> Duration {
+    let phase = current_phase(app);
+    let quiet = phase.is_some_and(|phase| phase.quiet);
+    let changed = {
+        let state = app.state::<QuietHoursState>();
+        let mut applied = state.applied.lock().unwrap_or_else(|e| e.into_inner());
+        applied.replace(quiet) != Some(quiet)
+    };
+    if changed {
+        log_event(app, "INFO", if quiet { "quiet_hours_started" } else { "quiet_hours_ended" }, &[]);
+        if let Err(err) = app.emit(CHANGED_EVENT, status(app)) {
+            log_event(app, "WARN", "quiet_hours_emit_failed", &[("error", &err.to_string())]);
+        }
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let body = serde_json::json!({ "quiet": quiet });
+            if let Err(err) = post_to_local_api(&handle, "/api/local-quiet-hours", body).await {
+                log_event(&handle, "WARN", "quiet_hours_push_failed", &[("error", &err)]);
+            }
+        });
+    }
+    phase
+        .map(|phase| Duration::from_secs((phase.next_transition - unix_now()).max(1) as u64))
+        .unwrap_or(MAX_SLEEP)
+        .min(MAX_SLEEP)
+}
+
+pub(crate) fn start_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        let wait = refresh(&app);
+        std::thread::sleep(wait);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{phase_at, resolve_wall, validate_pref, Phase, QuietHoursConfig, Window, DAY};
+
+    const HOUR: i64 = 3600;
+    /// 2026-03-08, a Sunday; used as "day 0" of the synthetic calendars.
+    const D0: i64 = 20_520 * DAY;
+
+    fn window(start: &str, end: &str) -> Window {
+        QuietHoursConfig {
+            enabled: true,
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+        .window()
+        .unwrap()
+    }
+
+    fn utc(_: i64) -> i64 {
+        0
+    }
+
+    /// UTC-5, jumping to UTC-4 at 02:00 local (07:00 UTC) on D0.
+    fn spring_forward(t: i64) -> i64 {
+        if t < D0 + 7 * HOUR { -5 * HOUR } else { -4 * HOUR }
+    }
+
+    /// UTC-4, falling back to UTC-5 at 02:00 local (06:00 UTC) on D0.
+    fn fall_back(t: i64) -> i64 {
+        if t < D0 + 6 * HOUR { -4 * HOUR } else { -5 * HOUR }
+    }
+
+    #[test]
+    fn same_day_window() {
+        let w = window("09:00", "17:30");
+        assert_eq!(
+            phase_at(D0 + 8 * HOUR, w, utc),
+            Phase { quiet: false, next_transition: D0 + 9 * HOUR }
+        );
+        assert_eq!(
+            phase_at(D0 + 9 * HOUR, w, utc),
+            Phase { quiet: true, next_transition: D0 + 17 * HOUR + 1800 }
+        );
+        assert_eq!(
+            phase_at(D0 + 17 * HOUR + 1800, w, utc),
+            Phase { quiet: false, next_transition: D0 + DAY + 9 * HOUR }
+        );
+    }
+
+    #[test]
+    fn window_spanning_midnight() {
+        let w = window("22:00", "07:00");
+        // Before midnight, after midnight, and during the day.
+        assert_eq!(
+            phase_at(D0 + 23 * HOUR, w, utc),
+            Phase { quiet: true, next_transition: D0 + DAY + 7 * HOUR }
+        );
+        assert_eq!(
+            phase_at(D0 + 3 * HOUR, w, utc),
+            Phase { quiet: true, next_transition: D0 + 7 * HOUR }
+        );
+        assert_eq!(
+            phase_at(D0 + 12 * HOUR, w, utc),
+            Phase { quiet: false, next_transition: D0 + 22 * HOUR }
+        );
+        // Offsets shift the UTC instants, not the local window.
+        let tokyo = |_| 9 * HOUR;
+        assert_eq!(
+            phase_at(D0 + 14 * HOUR, w, tokyo),
+            Phase { quiet: true, next_transition: D0 + DAY - 2 * HOUR }
+        );
+    }
+
+    #[test]
+    fn boundary_in_spring_forward_gap_falls_on_the_jump() {
+        // 02:30 doesn't exist on D0; the first instant after it is 03:00 EDT.
+        assert_eq!(resolve_wall(D0 + 2 * HOUR + 1800, &spring_forward), D0 + 7 * HOUR);
+        let w = window("02:30", "06:00");
+        assert_eq!(
+            phase_at(D0 + 6 * HOUR, w, spring_forward),
+            Phase { quiet: false, next_transition: D0 + 7 * HOUR }
+        );
+        // 06:00 EDT is 10:00 UTC: the period is an hour shorter.
+        assert_eq!(
+            phase_at(D0 + 7 * HOUR, w, spring_forward),
+            Phase { quiet: true, next_transition: D0 + 10 * HOUR }
+        );
+    }
+
+    #[test]
+    fn boundary_in_repeated_hour_uses_first_occurrence() {
+        // 01:30 happens at 05:30 UTC (EDT) and again at 06:30 UTC (EST).
+        assert_eq!(resolve_wall(D0 + HOUR + 1800, &fall_back), D0 + 5 * HOUR + 1800);
+        let w = window("22:00", "01:30");
+        let start = D0 - 2 * HOUR + 4 * HOUR;
+        assert_eq!(
+            phase_at(start, w, fall_back),
+            Phase { quiet: true, next_transition: D0 + 5 * HOUR + 1800 }
+        );
+        // The second 01:30–02:00 is not quiet again.
+        assert_eq!(
+            phase_at(D0 + 6 * HOUR + 600, w, fall_back),
+            Phase { quiet: false, next_transition: D0 + 22 * HOUR + 5 * HOUR }
+        );
+        // A window across the change is an hour longer in UTC.
+        let w = window("00:00", "03:00");
+        assert_eq!(
+            phase_at(D0 + 4 * HOUR, w, fall_back),
+            Phase { quiet: true, next_transition: D0 + 8 * HOUR }
+        );
+    }
+
+    #[test]
+    fn pref_validation() {
+        assert!(validate_pref(&json!({"enabled": true, "start": "22:00", "end": "07:00"})).is_ok());
+        for bad in [
+            json!({"enabled": true, "start": "22:00", "end": "22:00"}),
+            json!({"enabled": true, "start": "24:00", "end": "07:00"}),
+            json!({"enabled": true, "start": "7:00", "end": "22:00"}),
+            json!({"enabled": true, "start": "22:00"}),
+            json!({"enabled": "yes", "start": "22:00", "end": "07:00"}),
+            json!({"enabled": true, "start": "22:00", "end": "07:00", "tz": "UTC"}),
+        ] {
+            assert!(validate_pref(&bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Quit protection while the sidecar is doing work that must not be cut off.
+//! `stop_local_api` only gives the sidecar a few seconds after SIGTERM, so on
+//! `ExitRequested` the shell first asks `/api/local-busy`. If jobs are open it
+//! holds the exit and lets the main window choose between quitting anyway
+//! (`force_quit`) and waiting (`quit_when_idle`), which polls until the
+//! sidecar is idle or `WAIT_CAP` passes. Without a main window (headless, or
+//! already closed) it waits on its own.
+//!
+//! A second quit while the first is still held goes through immediately, so
+//! a stuck sidecar or an unanswered prompt never traps the user.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::cli::CliOptions;
+use crate::forensics_worker;
+use crate::logging::log_event;
+use crate::{get_from_local_api, show_main_window, LocalApiLifecycle, LocalApiState};
+
+const BUSY_QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const WAIT_CAP: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+pub(crate) struct QuitGuardState {
+    /// Set once the exit has been cleared; the next `ExitRequested` proceeds.
+    approved: AtomicBool,
+    /// An exit is being held for a busy check, a prompt, or a wait.
+    held: AtomicBool,
+    exit_code: AtomicI32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub(crate) struct BusyJob {
+    pub label: String,
+    #[serde(rename = "elapsedMs", default)]
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub(crate) struct SidecarBusy {
+    pub busy: bool,
+    #[serde(default)]
+    pub jobs: Vec<BusyJob>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct LocalApiStatus {
+    running: bool,
+    /// Lifecycle state; `port_blocked` means running but unreachable.
+    status: LocalApiLifecycle,
+    port: Option<u16>,
+    busy: bool,
+    jobs: Vec<BusyJob>,
+    forensics_worker: forensics_worker::WorkerStatus,
+}
+
+/// Decide what to do with an `ExitRequested`. Returns true when the exit must
+/// be prevented; the guard then finishes it later through `approve_exit`.
+pub(crate) fn hold_exit(app: &AppHandle, code: Option<i32>) -> bool {
+    let state = app.state::<QuitGuardState>();
+    if state.approved.load(Ordering::SeqCst) || !sidecar_running(app) {
+        return false;
+    }
+    if state.held.swap(true, Ordering::SeqCst) {
+        log_event(app, "WARN", "quit_forced", &[("reason", "repeated_quit")]);
+        state.approved.store(true, Ordering::SeqCst);
+        return false;
+    }
+    state.exit_code.store(code.unwrap_or(0), Ordering::SeqCst);
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let busy = query_busy(&handle).await.unwrap_or_default();
+        if !busy.busy {
+            approve_exit(&handle);
+            return;
+        }
+        let labels: Vec<&str> = busy.jobs.iter().map(|job| job.label.as_str()).collect();
+        log_event(&handle, "INFO", "quit_held_busy", &[("jobs", &labels.join(","))]);
+        let headless = handle.state::<CliOptions>().headless;
+        if !headless && handle.get_webview_window("main").is_some() {
+            show_main_window(&handle);
+            if handle.emit_to("main", "quit-blocked", &busy).is_ok() {
+                return;
+            }
+        }
+        wait_until_idle(&handle).await;
+        approve_exit(&handle);
+    });
+    true
+}
+
+fn sidecar_running(app: &AppHandle) -> bool {
+    app.try_state::<LocalApiState>()
+        .is_some_and(|state| state.child.lock().unwrap_or_else(|e| e.into_inner()).is_some())
+}
+
+fn approve_exit(app: &AppHandle) {
+    let state = app.state::<QuitGuardState>();
+    state.approved.store(true, Ordering::SeqCst);
+    app.exit(state.exit_code.load(Ordering::SeqCst));
+}
+
+/// `None` when the sidecar is not running or did not answer in time; a quit
+/// should not wait on a sidecar that cannot report.
+pub(crate) async fn query_busy(app: &AppHandle) -> Option<SidecarBusy> {
+    get_from_local_api(app, "/api/local-busy", BUSY_QUERY_TIMEOUT).await
+}
+
+/// Poll until the sidecar reports idle or `WAIT_CAP` passes. Returns whether
+/// it went idle.
+async fn wait_until_idle(app: &AppHandle) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < WAIT_CAP {
+        if !query_busy(app).await.is_some_and(|busy| busy.busy) {
+            return true;
+        }
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+    log_event(app, "WARN", "quit_wait_timed_out", &[("waited_secs", &WAIT_CAP.as_secs().to_string())]);
+    false
+}
+
+pub(crate) async fn local_api_status(app: &AppHandle) -> LocalApiStatus {
+    let running = sidecar_running(app);
+    let port = app
+        .try_state::<LocalApiState>()
+        .and_then(|state| *state.port.lock().unwrap_or_else(|e| e.into_inner()));
+    let status = app
+        .try_state::<LocalApiState>()
+        .map(|state| *state.lifecycle.borrow())
+        .unwrap_or_default();
+    let busy = if running { query_busy(app).await.unwrap_or_default() } else { SidecarBusy::default() };
+    LocalApiStatus {
+        running,
+        status,
+        port,
+        busy: busy.busy,
+        jobs: busy.jobs,
+        forensics_worker: forensics_worker::status(app),
+    }
+}
+
+/// Quit now, whatever the sidecar is doing.
+pub(crate) fn force_quit(app: &AppHandle) {
+    log_event(app, "WARN", "quit_forced", &[("reason", "user")]);
+    approve_exit(app);
+}
+
+/// Quit once the sidecar is idle, or after `WAIT_CAP`.
+pub(crate) async fn quit_when_idle(app: &AppHandle) {
+    let went_idle = wait_until_idle(app).await;
+    log_event(app, "INFO", "quit_after_wait", &[("idle", if went_idle { "true" } else { "false" })]);
+    approve_exit(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SidecarBusy;
+
+    #[test]
+    fn parses_the_sidecar_busy_payload() {
+        let busy: SidecarBusy = serde_json::from_str(
+            r#"{"busy":true,"jobs":[{"label":"register-interest","elapsedMs":1200}]}"#,
+        )
+        .unwrap();
+        assert!(busy.busy);
+        assert_eq!(busy.jobs[0].label, "register-interest");
+        assert_eq!(busy.jobs[0].elapsed_ms, 1200);
+        let idle: SidecarBusy = serde_json::from_str(r#"{"busy":false}"#).unwrap();
+        assert_eq!(idle, SidecarBusy::default());
+    }
+}
@@ -0,0 +1,317 @@
+//! Per-host token buckets for native fetches, so a burst of panel refreshes
+//! can't get the whole app banned by an upstream API. A request over the
+//! limit reserves the next free slot and waits for it, up to
+//! `QUEUE_TIMEOUT`; past that it fails with `rate_limited`. A 429 from
+//! upstream tightens the host's bucket for a cooldown.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+
+/// Longest a request will queue for its host before giving up.
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Cooldown after a 429 without a usable `Retry-After`.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+/// Share of the normal rate allowed during a cooldown.
+const COOLDOWN_RATE_FACTOR: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Limit {
+    /// Sustained requests per second.
+    pub rate: f64,
+    /// Requests allowed back to back after a quiet spell.
+    pub burst: f64,
+}
+
+const DEFAULT_LIMIT: Limit = Limit { rate: 5.0, burst: 10.0 };
+
+/// Hosts that need something other than `DEFAULT_LIMIT`.
+const HOST_LIMITS: [(&str, Limit); 1] = [("gamma-api.polymarket.com", Limit { rate: 4.0, burst: 8.0 })];
+
+fn limit_for(host: &str) -> Limit {
+    HOST_LIMITS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(host))
+        .map_or(DEFAULT_LIMIT, |(_, limit)| *limit)
+}
+
+/// Tokens may go negative: each queued request reserves the next token, so
+/// waiters are spaced out instead of all waking at once.
+#[derive(Debug)]
+struct TokenBucket {
+    limit: Limit,
+    tokens: f64,
+    updated: Instant,
+    cooldown_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(limit: Limit, now: Instant) -> Self {
+        TokenBucket {
+            limit,
+            tokens: limit.burst,
+            updated: now,
+            cooldown_until: None,
+        }
+    }
+
+    fn in_cooldown(&self, now: Instant) -> bool {
+        self.cooldown_until.is_some_and(|until| now < until)
+    }
+
+    fn rate(&self, now: Instant) -> f64 {
+        if self.in_cooldown(now) {
+            self.limit.rate * COOLDOWN_RATE_FACTOR
+        } else {
+            self.limit.rate
+        }
+    }
+
+    fn capacity(&self, now: Instant) -> f64 {
+        if self.in_cooldown(now) {
+            1.0
+        } else {
+            self.limit.burst
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate(now)).min(self.capacity(now));
+        self.updated = self.updated.max(now);
+    }
+
+    /// Take a token, returning how long to wait before using it, or `None`
+    /// (taking nothing) when that wait would exceed `max_wait`.
+    fn reserve(&mut self, now: Instant, max_wait: Duration) -> Option<Duration> {
+        self.refill(now);
+        let deficit = 1.0 - self.tokens;
+        let wait = if deficit <= 0.0 { 0.0 } else { deficit / self.rate(now) };
+        if wait > max_wait.as_secs_f64() {
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(Duration::from_secs_f64(wait))
+    }
+
+    /// Upstream said 429: slow down for `cooldown` and drop any saved burst.
+    fn penalize(&mut self, now: Instant, cooldown: Duration) {
+        self.refill(now);
+        self.cooldown_until = Some(now + cooldown);
+        self.tokens = self.tokens.min(0.0);
+    }
+}
+
+#[derive(Debug)]
+struct HostEntry {
+    bucket: TokenBucket,
+    queued: usize,
+    admitted: u64,
+    rejected: u64,
+    throttled: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    hosts: Mutex<HashMap<String, HostEntry>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HostStats {
+    host: String,
+    rate_per_sec: f64,
+    burst: f64,
+    available_tokens: f64,
+    queued: usize,
+    admitted: u64,
+    rejected: u64,
+    /// 429 responses seen from upstream.
+    throttled: u64,
+    cooldown_remaining_ms: u64,
+}
+
+impl RateLimiter {
+    fn with_entry<T>(&self, host: &str, edit: impl FnOnce(&mut HostEntry, Instant) -> T) -> T {
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = hosts.entry(host.to_ascii_lowercase()).or_insert_with(|| HostEntry {
+            bucket: TokenBucket::new(limit_for(host), now),
+            queued: 0,
+            admitted: 0,
+            rejected: 0,
+            throttled: 0,
+        });
+        edit(entry, now)
+    }
+
+    pub(crate) fn stats(&self) -> Vec<HostStats> {
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stats: Vec<HostStats> = hosts
+            .iter_mut()
+            .map(|(host, entry)| {
+                entry.bucket.refill(now);
+                HostStats {
+                    host: host.clone(),
+                    rate_per_sec: entry.bucket.rate(now),
+                    burst: entry.bucket.capacity(now),
+                    available_tokens: entry.bucket.tokens,
+                    queued: entry.queued,
+                    admitted: entry.admitted,
+                    rejected: entry.rejected,
+                    throttled: entry.throttled,
+                    cooldown_remaining_ms: entry
+                        .bucket
+                        .cooldown_until
+                        .map_or(0, |until| until.saturating_duration_since(now).as_millis() as u64),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.host.cmp(&b.host));
+        stats
+    }
+}
+
+/// Counts a request as queued until it is dropped, including when the
+/// awaiting command is cancelled.
+struct QueuedGuard<'a> {
+    limiter: &'a RateLimiter,
+    host: &'a str,
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.limiter.with_entry(self.host, |entry, _| entry.queued = entry.queued.saturating_sub(1));
+    }
+}
+
+/// Wait for a slot to call `host`, or fail with `RateLimited` when the queue
+/// is longer than `QUEUE_TIMEOUT`.
+pub(crate) async fn acquire(app: &AppHandle, host: &str) -> Result<(), DesktopError> {
+    let limiter = app.state::<RateLimiter>();
+    let wait = limiter.with_entry(host, |entry, now| match entry.bucket.reserve(now, QUEUE_TIMEOUT) {
+        Some(wait) => {
+            entry.admitted += 1;
+            if !wait.is_zero() {
+                entry.queued += 1;
+            }
+            Some(wait)
+        }
+        None => {
+            entry.rejected += 1;
+            None
+        }
+    });
+    let Some(wait) = wait else {
+        log_event(app, "WARN", "rate_limit_rejected", &[("host", host)]);
+        return Err(DesktopError::RateLimited(format!("Too many requests to {host}; try again shortly")));
+    };
+    if !wait.is_zero() {
+        let _queued = QueuedGuard {
+            limiter: &limiter,
+            host,
+        };
+        tokio::time::sleep(wait).await;
+    }
+    Ok(())
+}
+
+/// Feed an upstream response status back into `host`'s bucket.
+pub(crate) fn record_response(app: &AppHandle, host: &str, status: u16, retry_after: Option<&str>) {
+    if status != 429 {
+        return;
+    }
+    let cooldown = retry_after
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(DEFAULT_COOLDOWN, |secs| Duration::from_secs(secs).min(MAX_COOLDOWN));
+    app.state::<RateLimiter>().with_entry(host, |entry, now| {
+        entry.throttled += 1;
+        entry.bucket.penalize(now, cooldown);
+    });
+    log_event(
+        app,
+        "WARN",
+        "rate_limit_upstream_429",
+        &[("host", host), ("cooldown_secs", &cooldown.as_secs().to_string())],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{limit_for, Limit, TokenBucket, DEFAULT_LIMIT};
+
+    const LIMIT: Limit = Limit { rate: 5.0, burst: 10.0 };
+    const NO_WAIT: Duration = Duration::ZERO;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn burst_is_free_then_requests_are_spaced_at_the_rate() {
+        let t0 = Instant::now();
+        let mut bucket = TokenBucket::new(LIMIT, t0);
+        for _ in 0..10 {
+            assert_eq!(bucket.reserve(t0, NO_WAIT), Some(Duration::ZERO));
+        }
+        assert_eq!(bucket.reserve(t0, NO_WAIT), None);
+        // Queued callers get successive 200 ms slots.
+        assert_eq!(bucket.reserve(t0, ms(1_000)), Some(ms(200)));
+        assert_eq!(bucket.reserve(t0, ms(1_000)), Some(ms(400)));
+        // A slot further out than the queue timeout is refused.
+        assert_eq!(bucket.reserve(t0, ms(500)), None);
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_the_burst() {
+        let t0 = Instant::now();
+        let mut bucket = TokenBucket::new(LIMIT, t0);
+        for _ in 0..10 {
+            bucket.reserve(t0, NO_WAIT).unwrap();
+        }
+        let t1 = t0 + ms(1_000);
+        for _ in 0..5 {
+            assert_eq!(bucket.reserve(t1, NO_WAIT), Some(Duration::ZERO));
+        }
+        assert_eq!(bucket.reserve(t1, NO_WAIT), None);
+        bucket.refill(t1 + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, 10.0);
+        // A clock that steps backwards never mints tokens.
+        bucket.refill(t0);
+        assert_eq!(bucket.tokens, 10.0);
+    }
+
+    #[test]
+    fn upstream_429_tightens_until_the_cooldown_ends() {
+        let t0 = Instant::now();
+        let mut bucket = TokenBucket::new(LIMIT, t0);
+        bucket.penalize(t0, Duration::from_secs(30));
+        assert_eq!(bucket.reserve(t0, NO_WAIT), None);
+        // One request per second (5/s * 0.2), and no burst to bank.
+        assert_eq!(bucket.reserve(t0, ms(2_000)), Some(ms(1_000)));
+        let t1 = t0 + Duration::from_secs(20);
+        bucket.refill(t1);
+        assert_eq!(bucket.tokens, 1.0);
+        let after = t0 + Duration::from_secs(31);
+        bucket.reserve(after, NO_WAIT).unwrap();
+        assert_eq!(bucket.rate(after), 5.0);
+        assert_eq!(bucket.capacity(after), 10.0);
+    }
+
+    #[test]
+    fn hosts_use_their_table_entry_or_the_default() {
+        assert_eq!(limit_for("gamma-api.polymarket.com").rate, 4.0);
+        assert_eq!(limit_for("Gamma-API.Polymarket.com").rate, 4.0);
+        assert_eq!(limit_for("example.com"), DEFAULT_LIMIT);
+    }
+}
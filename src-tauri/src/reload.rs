@@ -0,0 +1,204 @@
+//! View > Reload and Force Reload for a frontend in a bad state, without
+//! restarting the app or the sidecar. The window gets `window-reloading`
+//! first and a short grace period to flush to the persistent cache. Force
+//! Reload also drops the HTTP cache (WebKitGTK, WebView2; WKWebView doesn't
+//! expose it) and, from the page, Cache Storage and service workers.
+//!
+//! A window mid-save marks itself dirty with `set_reload_guard`; reloading
+//! it is refused with `window-reload-blocked` until it clears the flag.
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::logging::log_event;
+
+pub(crate) const MENU_VIEW_RELOAD_ID: &str = "view.reload";
+pub(crate) const MENU_VIEW_FORCE_RELOAD_ID: &str = "view.force_reload";
+const RELOADING_EVENT: &str = "window-reloading";
+const BLOCKED_EVENT: &str = "window-reload-blocked";
+/// Time the page gets to flush pending state after `window-reloading`.
+const FLUSH_GRACE: Duration = Duration::from_millis(300);
+/// Longest wait for WebView2 to report the HTTP cache cleared.
+#[cfg(target_os = "windows")]
+const CLEAR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Empties Cache Storage and unregisters service workers, then reloads
+/// whether or not that worked.
+const FORCE_RELOAD_SCRIPT: &str = r#"(async () => {
+  try {
+    if (self.caches) { for (const key of await caches.keys()) await caches.delete(key); }
+    if (navigator.serviceWorker) {
+      for (const registration of await navigator.serviceWorker.getRegistrations()) await registration.unregister();
+    }
+  } finally {
+    location.reload();
+  }
+})();"#;
+
+/// Managed state: windows that asked not to be reloaded right now.
+#[derive(Default)]
+pub(crate) struct ReloadGuardState {
+    dirty: Mutex<BTreeSet<String>>,
+}
+
+impl ReloadGuardState {
+    pub(crate) fn set(&self, label: &str, dirty: bool) {
+        let mut windows = self.dirty.lock().unwrap_or_else(|e| e.into_inner());
+        if dirty {
+            windows.insert(label.to_string());
+        } else {
+            windows.remove(label);
+        }
+    }
+
+    fn is_dirty(&self, label: &str) -> bool {
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).contains(label)
+    }
+}
+
+/// A window's flag goes with it.
+pub(crate) fn forget_window(app: &AppHandle, label: &str) {
+    if let Some(state) = app.try_state::<ReloadGuardState>() {
+        state.set(label, false);
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct Reloading {
+    force: bool,
+}
+
+/// The focused app window, or main.
+fn target_window(app: &AppHandle) -> Option<WebviewWindow> {
+    app.webview_windows()
+        .into_values()
+        .filter(|window| crate::require_trusted_window(window.label()).is_ok())
+        .find(|window| window.is_focused().unwrap_or(false))
+        .or_else(|| app.get_webview_window("main"))
+}
+
+/// Handle the View menu items; false for ids this module doesn't own.
+pub(crate) fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+    let force = match id {
+        MENU_VIEW_RELOAD_ID => false,
+        MENU_VIEW_FORCE_RELOAD_ID => true,
+        _ => return false,
+    };
+    if let Some(window) = target_window(app) {
+        reload(app, window, force);
+    }
+    true
+}
+
+fn reload(app: &AppHandle, window: WebviewWindow, force: bool) {
+    let label = window.label().to_string();
+    if app.state::<ReloadGuardState>().is_dirty(&label) {
+        log_event(app, "INFO", "window_reload_blocked", &[("window", &label)]);
+        let _ = window.emit_to(label.as_str(), BLOCKED_EVENT, Reloading { force });
+        return;
+    }
+    log_event(app, "INFO", "window_reload", &[("window", &label), ("force", &force.to_string())]);
+    let _ = window.emit_to(label.as_str(), RELOADING_EVENT, Reloading { force });
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(FLUSH_GRACE);
+        let result = if force {
+            if let Err(err) = clear_http_cache(&window) {
+                log_event(&app, "WARN", "webview_cache_clear_failed", &[("window", &label), ("error", &err)]);
+            }
+            window.eval(FORCE_RELOAD_SCRIPT)
+        } else {
+            window.reload()
+        };
+        if let Err(err) = result {
+            log_event(&app, "WARN", "window_reload_failed", &[("window", &label), ("error", &err.to_string())]);
+        }
+    });
+}
+
+/// The context is shared by all windows, so this clears it for each.
+#[cfg(target_os = "linux")]
+fn clear_http_cache(window: &WebviewWindow) -> Result<(), String> {
+    let queued = window.with_webview(|webview| {
+        use webkit2gtk::{WebContextExt, WebViewExt};
+        if let Some(context) = webview.inner().context() {
+            context.clear_cache();
+        }
+    });
+    queued.map_err(|err| err.to_string())
+}
+
+/// Returns once WebView2 reports the clear done, so the reload that follows
+/// can't be served from the old cache.
+#[cfg(target_os = "windows")]
+fn clear_http_cache(window: &WebviewWindow) -> Result<(), String> {
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    let (done_tx, done_rx) = mpsc::channel::<Result<(), String>>();
+    let queued = window.with_webview(move |webview| unsafe {
+        use webview2_com::ClearBrowsingDataCompletedHandler;
+        use webview2_com::Microsoft::Web::WebView2::Win32::{
+            ICoreWebView2Profile2, ICoreWebView2_13, COREWEBVIEW2_BROWSING_DATA_KINDS,
+            COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE, COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE,
+        };
+        use windows_core::Interface;
+        let profile = webview
+            .controller()
+            .CoreWebView2()
+            .and_then(|core| core.cast::<ICoreWebView2_13>())
+            .and_then(|core| core.Profile())
+            .and_then(|profile| profile.cast::<ICoreWebView2Profile2>());
+        let profile = match profile {
+            Ok(profile) => profile,
+            Err(err) => {
+                let _ = done_tx.send(Err(err.to_string()));
+                return;
+            }
+        };
+        let kinds = COREWEBVIEW2_BROWSING_DATA_KINDS(
+            COREWEBVIEW2_BROWSING_DATA_KINDS_DISK_CACHE.0 | COREWEBVIEW2_BROWSING_DATA_KINDS_CACHE_STORAGE.0,
+        );
+        let completed = done_tx.clone();
+        let handler = ClearBrowsingDataCompletedHandler::create(Box::new(move |result| {
+            let _ = completed.send(result.map_err(|err| err.to_string()));
+            Ok(())
+        }));
+        if let Err(err) = profile.ClearBrowsingData(kinds, &handler) {
+            let _ = done_tx.send(Err(err.to_string()));
+        }
+    });
+    queued.map_err(|err| err.to_string())?;
+    match done_rx.recv_timeout(CLEAR_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(format!("not cleared after {}s", CLEAR_TIMEOUT.as_secs())),
+        Err(RecvTimeoutError::Disconnected) => Err("the webview dropped the request".to_string()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn clear_http_cache(_window: &WebviewWindow) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReloadGuardState;
+
+    #[test]
+    fn a_guard_holds_only_its_own_window_until_cleared() {
+        let state = ReloadGuardState::default();
+        state.set("settings", true);
+        assert!(state.is_dirty("settings"));
+        assert!(!state.is_dirty("main"));
+
+        state.set("settings", true);
+        state.set("settings", false);
+        assert!(!state.is_dirty("settings"), "one clear undoes repeated sets");
+        state.set("main", false);
+        assert!(!state.is_dirty("main"));
+    }
+}
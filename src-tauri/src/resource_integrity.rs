@@ -0,0 +1,253 @@
+//! Checks the bundled sidecar resources against the manifest `build.rs`
+//! compiles into the binary (path to SHA-256, plus the app version). An update
+//! that was cut short, such as an AppImage replaced while running, can
+//! leave the sidecar script and `api/` from different versions, which fails
+//! in ways that are hard to read. Release builds check a few critical files
+//! whenever the sidecar starts; a mismatch is logged and sent to the UI as
+//! `integrity-warning`, and the sidecar is started anyway since it may work.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter};
+
+use crate::logging::log_event;
+use crate::sidecar_paths::LocalApiPaths;
+
+const MANIFEST: &str = include_str!(concat!(env!("OUT_DIR"), "/resource-manifest.json"));
+const WARNING_EVENT: &str = "integrity-warning";
+/// Checked at every sidecar start: the entry point and what every route
+/// loads first.
+const CRITICAL_FILES: [&str; 5] = [
+    "sidecar/local-api-server.mjs",
+    "sidecar/package.json",
+    "api/_cors.js",
+    "api/_api-key.js",
+    "api/bootstrap.js",
+];
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Manifest {
+    version: String,
+    files: BTreeMap<String, String>,
+}
+
+pub(crate) fn parse_manifest(text: &str) -> Result<Manifest, String> {
+    let manifest: Manifest = serde_json::from_str(text).map_err(|e| format!("Unreadable resource manifest: {e}"))?;
+    for (path, hash) in &manifest.files {
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("Resource manifest has a malformed hash for {path}"));
+        }
+        if path.split('/').any(|part| part.is_empty() || part == "..") {
+            return Err(format!("Resource manifest has an unsafe path: {path}"));
+        }
+    }
+    Ok(manifest)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Problem {
+    /// Listed in the manifest but not on disk.
+    Missing,
+    /// On disk with a different hash.
+    Modified,
+    /// Checked but not in the manifest at all.
+    Unlisted,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Mismatch {
+    path: String,
+    problem: Problem,
+}
+
+/// `verify_resources` payload and `integrity-warning` event.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct IntegrityReport {
+    ok: bool,
+    app_version: String,
+    manifest_version: Option<String>,
+    checked: usize,
+    mismatches: Vec<Mismatch>,
+    /// The manifest couldn't be parsed; nothing was checked.
+    error: Option<String>,
+}
+
+/// Compare `paths` against `manifest`, hashing each through `hash_of`
+/// (`None` when the file can't be read).
+pub(crate) fn compare(
+    manifest: &Manifest,
+    app_version: &str,
+    paths: &[&str],
+    hash_of: impl Fn(&str) -> Option<String>,
+) -> IntegrityReport {
+    let mismatches: Vec<Mismatch> = paths
+        .iter()
+        .filter_map(|path| {
+            let problem = match (manifest.files.get(*path), hash_of(path)) {
+                (None, _) => Problem::Unlisted,
+                (Some(_), None) => Problem::Missing,
+                (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(&actual) => Problem::Modified,
+                _ => return None,
+            };
+            Some(Mismatch {
+                path: path.to_string(),
+                problem,
+            })
+        })
+        .collect();
+    IntegrityReport {
+        ok: mismatches.is_empty() && manifest.version == app_version,
+        app_version: app_version.to_string(),
+        manifest_version: Some(manifest.version.clone()),
+        checked: paths.len(),
+        mismatches,
+        error: None,
+    }
+}
+
+/// Where a manifest path lives: `sidecar/…` beside the script, `api/…`
+/// under the resource root the sidecar was pointed at.
+fn locate(paths: &LocalApiPaths, relative: &str) -> Option<PathBuf> {
+    match relative.strip_prefix("sidecar/") {
+        Some(rest) => Some(paths.script.parent()?.join(rest)),
+        None => Some(paths.resource_root.join(relative)),
+    }
+}
+
+fn hash_file(path: &PathBuf) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    Some(Sha256::digest(&bytes).iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Check the critical files, or with `all` every file in the manifest.
+pub(crate) fn verify(paths: &LocalApiPaths, all: bool) -> IntegrityReport {
+    let app_version = env!("CARGO_PKG_VERSION");
+    let manifest = match parse_manifest(MANIFEST) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            return IntegrityReport {
+                app_version: app_version.to_string(),
+                error: Some(error),
+                ..Default::default()
+            }
+        }
+    };
+    let listed: Vec<&str> = if all {
+        manifest.files.keys().map(String::as_str).collect()
+    } else {
+        CRITICAL_FILES.to_vec()
+    };
+    compare(&manifest, app_version, &listed, |relative| {
+        locate(paths, relative).and_then(|path| hash_file(&path))
+    })
+}
+
+/// The check `start_local_api` runs; skipped in debug builds, where the
+/// tree is being edited under the manifest.
+pub(crate) fn check_on_start(app: &AppHandle, paths: LocalApiPaths) {
+    if cfg!(debug_assertions) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let report = verify(&paths, false);
+        if report.ok {
+            log_event(&app, "INFO", "resource_integrity", &[("checked", &report.checked.to_string())]);
+            return;
+        }
+        let files = report
+            .mismatches
+            .iter()
+            .map(|mismatch| mismatch.path.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        log_event(
+            &app,
+            "ERROR",
+            "resource_integrity_failed",
+            &[
+                ("files", &files),
+                ("manifest_version", report.manifest_version.as_deref().unwrap_or("none")),
+                ("error", report.error.as_deref().unwrap_or("")),
+            ],
+        );
+        if let Err(err) = app.emit(WARNING_EVENT, report) {
+            log_event(&app, "WARN", "integrity_warning_emit_failed", &[("error", &err.to_string())]);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare, parse_manifest, Problem, CRITICAL_FILES, MANIFEST};
+
+    const HASH_A: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const HASH_B: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    fn manifest(version: &str) -> super::Manifest {
+        parse_manifest(&format!(
+            r#"{{"version":"{version}","files":{{"sidecar/local-api-server.mjs":"{HASH_A}","api/bootstrap.js":"{HASH_B}"}}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn disk(path: &str) -> Option<String> {
+        match path {
+            "sidecar/local-api-server.mjs" => Some(HASH_A.to_string()),
+            "api/bootstrap.js" => Some(HASH_B.to_uppercase()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn matching_files_and_version_pass() {
+        let report = compare(&manifest("2.5.23"), "2.5.23", &["sidecar/local-api-server.mjs", "api/bootstrap.js"], disk);
+        assert!(report.ok, "{report:?}");
+        assert_eq!(report.checked, 2);
+        assert!(report.mismatches.is_empty());
+    }
+
+    #[test]
+    fn each_kind_of_mismatch_is_named() {
+        let modified = |path: &str| if path == "api/bootstrap.js" { Some(HASH_A.to_string()) } else { disk(path) };
+        let report = compare(&manifest("2.5.23"), "2.5.23", &["api/bootstrap.js", "api/_cors.js"], modified);
+        assert!(!report.ok);
+        let problems: Vec<_> = report.mismatches.iter().map(|m| (m.path.as_str(), m.problem.clone())).collect();
+        assert_eq!(problems, [("api/bootstrap.js", Problem::Modified), ("api/_cors.js", Problem::Unlisted)]);
+
+        let report = compare(&manifest("2.5.23"), "2.5.23", &["sidecar/local-api-server.mjs"], |_| None);
+        assert_eq!(report.mismatches[0].problem, Problem::Missing);
+    }
+
+    #[test]
+    fn a_manifest_from_another_version_fails_even_when_hashes_match() {
+        let report = compare(&manifest("2.5.22"), "2.5.23", &["sidecar/local-api-server.mjs"], disk);
+        assert!(!report.ok);
+        assert!(report.mismatches.is_empty());
+        assert_eq!(report.manifest_version.as_deref(), Some("2.5.22"));
+    }
+
+    #[test]
+    fn malformed_manifests_are_rejected() {
+        assert!(parse_manifest("not json").is_err());
+        assert!(parse_manifest(r#"{"version":"1","files":{"api/x.js":"abc"}}"#).is_err());
+        assert!(parse_manifest(&format!(r#"{{"version":"1","files":{{"../etc/passwd":"{HASH_A}"}}}}"#)).is_err());
+        assert!(parse_manifest(&format!(r#"{{"version":"1","files":{{}},"extra":"{HASH_A}"}}"#)).is_err());
+        assert!(parse_manifest(r#"{"version":"1","files":{}}"#).is_ok());
+    }
+
+    #[test]
+    fn the_built_in_manifest_lists_the_critical_files() {
+        let manifest = parse_manifest(MANIFEST).unwrap();
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+        for path in CRITICAL_FILES {
+            assert!(manifest.files.contains_key(path), "{path}");
+        }
+    }
+}
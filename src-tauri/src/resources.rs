@@ -0,0 +1,416 @@
+//! Memory/CPU usage of the app and the Node sidecar, for the settings page.
+//! `get_resource_usage` samples on demand; when the `resourceSampling` pref is
+//! on, a maintenance task also records one sample a minute into a bounded
+//! history. With the pref off the task only reads one bool per interval.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::disk_space;
+use crate::logging::{log_event, now_iso8601};
+use crate::maintenance::TaskSpec;
+use crate::{cache_file_path, desktop_log_path, sidecar_log_path, LocalApiState, RuntimePrefs};
+
+pub(crate) const PREF_RESOURCE_SAMPLING: &str = "resourceSampling";
+/// Sidecar RSS, in MB, above which a WARN is logged and the UI notified.
+pub(crate) const PREF_SIDECAR_RSS_WARN_MB: &str = "sidecarRssWarnMb";
+const DEFAULT_SIDECAR_RSS_WARN_MB: u64 = 1536;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// Two hours of one-minute samples.
+const HISTORY_CAPACITY: usize = 120;
+const SIDECAR_MEMORY_HIGH_EVENT: &str = "sidecar-memory-high";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ProcessReading {
+    rss_bytes: u64,
+    /// User + system CPU time consumed so far.
+    cpu_time: Duration,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ProcessUsage {
+    pub(crate) pid: u32,
+    pub(crate) rss_bytes: u64,
+    /// Percent of one core since the previous reading of this pid; `None` on
+    /// the first reading.
+    pub(crate) cpu_percent: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct DiskUsage {
+    pub(crate) persistent_cache_bytes: Option<u64>,
+    pub(crate) desktop_log_bytes: Option<u64>,
+    pub(crate) local_api_log_bytes: Option<u64>,
+    /// Free space on the data directory's volume.
+    pub(crate) data_dir_free_bytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ResourceUsage {
+    pub(crate) sampled_at: String,
+    pub(crate) app: Option<ProcessUsage>,
+    /// `None` when the sidecar isn't running.
+    pub(crate) sidecar: Option<ProcessUsage>,
+    pub(crate) disk: DiskUsage,
+}
+
+/// One history point; disk sizes are left out to keep the buffer small.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ResourceSample {
+    pub(crate) sampled_at: String,
+    pub(crate) app_rss_bytes: Option<u64>,
+    pub(crate) app_cpu_percent: Option<f64>,
+    pub(crate) sidecar_rss_bytes: Option<u64>,
+    pub(crate) sidecar_cpu_percent: Option<f64>,
+}
+
+#[derive(Default)]
+pub(crate) struct ResourceMonitor {
+    last_cpu: Mutex<HashMap<u32, (Instant, Duration)>>,
+    history: Mutex<VecDeque<ResourceSample>>,
+    sidecar_over_threshold: AtomicBool,
+}
+
+impl ResourceMonitor {
+    pub(crate) fn history(&self) -> Vec<ResourceSample> {
+        self.history.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    fn usage(&self, pid: u32) -> Option<ProcessUsage> {
+        let reading = read_process(pid)?;
+        let now = Instant::now();
+        let mut last_cpu = self.last_cpu.lock().unwrap_or_else(|e| e.into_inner());
+        let cpu_percent = last_cpu
+            .insert(pid, (now, reading.cpu_time))
+            .and_then(|(at, cpu_time)| cpu_percent(cpu_time, reading.cpu_time, now.duration_since(at)));
+        Some(ProcessUsage {
+            pid,
+            rss_bytes: reading.rss_bytes,
+            cpu_percent,
+        })
+    }
+}
+
+/// CPU use between two readings as a percentage of one core.
+fn cpu_percent(before: Duration, after: Duration, elapsed: Duration) -> Option<f64> {
+    if elapsed.is_zero() || after < before {
+        return None;
+    }
+    Some((after - before).as_secs_f64() / elapsed.as_secs_f64() * 100.0)
+}
+
+fn push_sample(history: &mut VecDeque<ResourceSample>, sample: ResourceSample, capacity: usize) {
+    while history.len() >= capacity {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+/// Edge-triggered threshold: true only on the reading that crosses above it,
+/// re-armed once usage drops back below.
+fn crossed_threshold(over: &AtomicBool, rss_bytes: u64, threshold_bytes: u64) -> bool {
+    let now_over = rss_bytes > threshold_bytes;
+    let was_over = over.swap(now_over, Ordering::Relaxed);
+    now_over && !was_over
+}
+
+fn file_size<E>(path: Result<std::path::PathBuf, E>) -> Option<u64> {
+    fs::metadata(path.ok()?).ok().map(|m| m.len())
+}
+
+fn sidecar_pid(app: &AppHandle) -> Option<u32> {
+    let state = app.try_state::<LocalApiState>()?;
+    let child = state.child.lock().unwrap_or_else(|e| e.into_inner());
+    child.as_ref().map(|child| child.id())
+}
+
+fn sidecar_rss_threshold_bytes(app: &AppHandle) -> u64 {
+    let mb = app
+        .try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_SIDECAR_RSS_WARN_MB))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_SIDECAR_RSS_WARN_MB);
+    mb.saturating_mul(1024 * 1024)
+}
+
+pub(crate) fn validate_sidecar_rss_warn_mb(value: &Value) -> Result<(), String> {
+    match value.as_u64() {
+        Some(mb) if mb > 0 => Ok(()),
+        _ => Err(format!("Runtime pref {PREF_SIDECAR_RSS_WARN_MB} must be a positive integer")),
+    }
+}
+
+/// Read current usage and run the sidecar memory check.
+pub(crate) fn resource_usage(app: &AppHandle) -> ResourceUsage {
+    let monitor = app.state::<ResourceMonitor>();
+    let sidecar = sidecar_pid(app).and_then(|pid| monitor.usage(pid));
+    if let Some(sidecar) = &sidecar {
+        let threshold = sidecar_rss_threshold_bytes(app);
+        if crossed_threshold(&monitor.sidecar_over_threshold, sidecar.rss_bytes, threshold) {
+            log_event(
+                app,
+                "WARN",
+                "sidecar_memory_high",
+                &[
+                    ("pid", &sidecar.pid.to_string()),
+                    ("rss_bytes", &sidecar.rss_bytes.to_string()),
+                    ("threshold_bytes", &threshold.to_string()),
+                ],
+            );
+            let _ = app.emit(SIDECAR_MEMORY_HIGH_EVENT, sidecar.clone());
+        }
+    }
+    ResourceUsage {
+        sampled_at: now_iso8601(),
+        app: monitor.usage(std::process::id()),
+        sidecar,
+        disk: DiskUsage {
+            persistent_cache_bytes: file_size(cache_file_path(app)),
+            desktop_log_bytes: file_size(desktop_log_path(app)),
+            local_api_log_bytes: file_size(sidecar_log_path(app)),
+            data_dir_free_bytes: disk_space::data_dir_free_bytes(app),
+        },
+    }
+}
+
+/// The once-a-minute sample; it does nothing while the pref is off.
+pub(crate) const MAINTENANCE: TaskSpec = TaskSpec {
+    id: "resource_sampling",
+    interval: SAMPLE_INTERVAL,
+    jitter: Duration::ZERO,
+    first_delay: SAMPLE_INTERVAL,
+    skip_while_paused: true,
+    run: |app| {
+        Box::pin(async move {
+            tauri::async_runtime::spawn_blocking(move || record_sample(&app))
+                .await
+                .map_err(|e| format!("Resource sampling failed: {e}"))
+        })
+    },
+};
+
+fn record_sample(app: &AppHandle) {
+    let enabled = app
+        .try_state::<RuntimePrefs>()
+        .is_some_and(|prefs| prefs.get_bool(PREF_RESOURCE_SAMPLING, false));
+    if !enabled {
+        return;
+    }
+    let usage = resource_usage(app);
+    let sample = ResourceSample {
+        sampled_at: usage.sampled_at,
+        app_rss_bytes: usage.app.as_ref().map(|p| p.rss_bytes),
+        app_cpu_percent: usage.app.as_ref().and_then(|p| p.cpu_percent),
+        sidecar_rss_bytes: usage.sidecar.as_ref().map(|p| p.rss_bytes),
+        sidecar_cpu_percent: usage.sidecar.as_ref().and_then(|p| p.cpu_percent),
+    };
+    let monitor = app.state::<ResourceMonitor>();
+    let mut history = monitor.history.lock().unwrap_or_else(|e| e.into_inner());
+    push_sample(&mut history, sample, HISTORY_CAPACITY);
+}
+
+/// RSS pages from /proc/<pid>/statm and utime+stime ticks from
+/// /proc/<pid>/stat. The command name in `stat` may contain spaces and
+/// parentheses, so fields are counted from the last `)`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_reading(statm: &str, stat: &str, page_size: u64, ticks_per_sec: u64) -> Option<ProcessReading> {
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 1..)?.split_whitespace().collect();
+    // After the command: state(3) ... utime(14) stime(15), 1-based per proc(5).
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    if ticks_per_sec == 0 {
+        return None;
+    }
+    let ticks = utime + stime;
+    Some(ProcessReading {
+        rss_bytes: resident_pages * page_size,
+        cpu_time: Duration::from_secs(ticks / ticks_per_sec)
+            + Duration::from_nanos((ticks % ticks_per_sec) * 1_000_000_000 / ticks_per_sec),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn read_process(pid: u32) -> Option<ProcessReading> {
+    let statm = fs::read_to_string(format!("/proc/{pid}/statm")).ok()?;
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // SAFETY: sysconf has no preconditions.
+    let (page_size, ticks) = unsafe { (libc::sysconf(libc::_SC_PAGESIZE), libc::sysconf(libc::_SC_CLK_TCK)) };
+    parse_proc_reading(&statm, &stat, u64::try_from(page_size).ok()?, u64::try_from(ticks).ok()?)
+}
+
+#[cfg(target_os = "macos")]
+fn read_process(pid: u32) -> Option<ProcessReading> {
+    let mut info: libc::proc_taskinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    // SAFETY: `info` is a properly sized, writable proc_taskinfo.
+    let written = unsafe {
+        libc::proc_pidinfo(pid as libc::c_int, libc::PROC_PIDTASKINFO, 0, (&mut info as *mut libc::proc_taskinfo).cast(), size)
+    };
+    if written != size {
+        return None;
+    }
+    let mut timebase = mach2::mach_time::mach_timebase_info::default();
+    // SAFETY: `timebase` is a writable mach_timebase_info.
+    if unsafe { mach2::mach_time::mach_timebase_info(&mut timebase) } != mach2::kern_return::KERN_SUCCESS {
+        return None;
+    }
+    Some(ProcessReading {
+        rss_bytes: info.pti_resident_size,
+        // Reported in Mach absolute time units: nanoseconds on Intel, 125/3
+        // ns per tick on Apple silicon.
+        cpu_time: Duration::from_nanos(mach_ticks_to_nanos(
+            info.pti_total_user + info.pti_total_system,
+            timebase.numer,
+            timebase.denom,
+        )),
+    })
+}
+
+/// Scale Mach absolute time by the host's `mach_timebase_info` ratio.
+#[cfg(any(target_os = "macos", test))]
+fn mach_ticks_to_nanos(ticks: u64, numer: u32, denom: u32) -> u64 {
+    if denom == 0 {
+        return ticks;
+    }
+    u64::try_from(u128::from(ticks) * u128::from(numer) / u128::from(denom)).unwrap_or(u64::MAX)
+}
+
+#[cfg(windows)]
+fn read_process(pid: u32) -> Option<ProcessReading> {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct FileTime {
+        low: u32,
+        high: u32,
+    }
+    #[repr(C)]
+    #[derive(Default)]
+    struct ProcessMemoryCounters {
+        cb: u32,
+        page_fault_count: u32,
+        peak_working_set_size: usize,
+        working_set_size: usize,
+        quota_peak_paged_pool_usage: usize,
+        quota_paged_pool_usage: usize,
+        quota_peak_non_paged_pool_usage: usize,
+        quota_non_paged_pool_usage: usize,
+        pagefile_usage: usize,
+        peak_pagefile_usage: usize,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, pid: u32) -> *mut c_void;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+        fn GetProcessTimes(
+            process: *mut c_void,
+            creation: *mut FileTime,
+            exit: *mut FileTime,
+            kernel: *mut FileTime,
+            user: *mut FileTime,
+        ) -> i32;
+        fn K32GetProcessMemoryInfo(process: *mut c_void, counters: *mut ProcessMemoryCounters, cb: u32) -> i32;
+    }
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    // SAFETY: handles and out-params are valid for the duration of the calls.
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return None;
+        }
+        let mut counters = ProcessMemoryCounters {
+            cb: std::mem::size_of::<ProcessMemoryCounters>() as u32,
+            ..Default::default()
+        };
+        let (mut creation, mut exit, mut kernel, mut user) =
+            (FileTime::default(), FileTime::default(), FileTime::default(), FileTime::default());
+        let ok = K32GetProcessMemoryInfo(handle, &mut counters, counters.cb) != 0
+            && GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) != 0;
+        CloseHandle(handle);
+        if !ok {
+            return None;
+        }
+        // FILETIME counts 100ns intervals.
+        let hundred_ns = |t: &FileTime| (u64::from(t.high) << 32 | u64::from(t.low)) * 100;
+        Some(ProcessReading {
+            rss_bytes: counters.working_set_size as u64,
+            cpu_time: Duration::from_nanos(hundred_ns(&kernel) + hundred_ns(&user)),
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn read_process(_pid: u32) -> Option<ProcessReading> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cpu_percent, crossed_threshold, mach_ticks_to_nanos, parse_proc_reading, push_sample, ResourceSample};
+    use std::collections::VecDeque;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_proc_statm_and_stat() {
+        let statm = "123456 2048 512 10 0 4096 0\n";
+        let stat = "4242 (node (worker) x) S 1 4242 4242 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 11 0 1000 0 0";
+        let reading = parse_proc_reading(statm, stat, 4096, 100).unwrap();
+        assert_eq!(reading.rss_bytes, 2048 * 4096);
+        assert_eq!(reading.cpu_time, Duration::from_secs(3));
+        assert!(parse_proc_reading("", stat, 4096, 100).is_none());
+    }
+
+    #[test]
+    fn mach_ticks_are_scaled_by_the_timebase() {
+        assert_eq!(mach_ticks_to_nanos(1_000, 1, 1), 1_000);
+        // Apple silicon: 24 MHz ticks.
+        assert_eq!(mach_ticks_to_nanos(24_000_000, 125, 3), 1_000_000_000);
+        assert_eq!(mach_ticks_to_nanos(u64::MAX, 125, 3), u64::MAX);
+    }
+
+    #[test]
+    fn computes_cpu_percent_between_readings() {
+        let pct = cpu_percent(Duration::from_secs(1), Duration::from_millis(1500), Duration::from_secs(2));
+        assert_eq!(pct, Some(25.0));
+        assert_eq!(cpu_percent(Duration::ZERO, Duration::ZERO, Duration::ZERO), None);
+        assert_eq!(cpu_percent(Duration::from_secs(2), Duration::from_secs(1), Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = VecDeque::new();
+        for i in 0..5 {
+            let sample = ResourceSample {
+                sampled_at: i.to_string(),
+                app_rss_bytes: None,
+                app_cpu_percent: None,
+                sidecar_rss_bytes: None,
+                sidecar_cpu_percent: None,
+            };
+            push_sample(&mut history, sample, 3);
+        }
+        let kept: Vec<&str> = history.iter().map(|s| s.sampled_at.as_str()).collect();
+        assert_eq!(kept, ["2", "3", "4"]);
+    }
+
+    #[test]
+    fn threshold_fires_once_per_crossing() {
+        let over = AtomicBool::new(false);
+        assert!(!crossed_threshold(&over, 50, 100));
+        assert!(crossed_threshold(&over, 150, 100));
+        assert!(!crossed_threshold(&over, 200, 100));
+        assert!(!crossed_threshold(&over, 80, 100));
+        assert!(crossed_threshold(&over, 120, 100));
+    }
+}
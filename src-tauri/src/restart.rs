@@ -0,0 +1,204 @@
+//! File > Restart and the `restart_app` command. The exit is an ordinary
+//! quit, so `quit_guard` can hold it while the sidecar is busy and the exit
+//! handler flushes the cache and stops the sidecar; the new process is
+//! spawned from the `Exit` event, after the single-instance plugin has let
+//! go of its lock, so the relaunch isn't forwarded back to the instance that
+//! is going away.
+//!
+//! A restart that crashes into another restart would loop forever, so each
+//! one is recorded in `restart-history.json` and more than
+//! [`MAX_RESTARTS`] inside [`RESTART_WINDOW`] are refused.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+
+pub(crate) const MENU_FILE_RESTART_ID: &str = "file.restart";
+pub(crate) const HISTORY_FILE: &str = "restart-history.json";
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Flags a restart may add for the next launch; value flags such as
+/// `--port` would need their own validation.
+const EXTRA_FLAGS: [&str; 4] = ["--safe-mode", "--settings", "--start-minimized", "--no-onboarding"];
+
+/// The process to start once this one exits.
+#[derive(Debug)]
+struct Relaunch {
+    exe: PathBuf,
+    args: Vec<OsString>,
+}
+
+/// Managed state: the relaunch waiting for `RunEvent::Exit`.
+#[derive(Default)]
+pub(crate) struct RestartState {
+    pending: Mutex<Option<Relaunch>>,
+}
+
+fn validate_flag(flag: Option<&str>) -> Result<(), DesktopError> {
+    match flag {
+        Some(flag) if !EXTRA_FLAGS.contains(&flag) => Err(DesktopError::InvalidArgument(format!(
+            "Restart flag must be one of: {}",
+            EXTRA_FLAGS.join(", ")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Restarts (unix seconds) still inside the window at `now`.
+fn recent(history: &[u64], now: u64) -> Vec<u64> {
+    history
+        .iter()
+        .copied()
+        .filter(|&at| at <= now && now - at < RESTART_WINDOW.as_secs())
+        .collect()
+}
+
+fn allowed(history: &[u64], now: u64) -> bool {
+    recent(history, now).len() < MAX_RESTARTS
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf, DesktopError> {
+    crate::data_dir::resolve_data_dir(app)
+        .map(|dir| dir.join(HISTORY_FILE))
+        .map_err(DesktopError::Internal)
+}
+
+fn load_history(path: &Path) -> Vec<u64> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// The same arguments again, plus `flag` unless it is already there.
+fn relaunch_args(current: Vec<OsString>, flag: Option<&str>) -> Vec<OsString> {
+    let mut args = current;
+    if let Some(flag) = flag {
+        if !args.iter().any(|arg| arg == flag) {
+            args.push(OsString::from(flag));
+        }
+    }
+    args
+}
+
+/// The file to run again. AppImages run from a fresh `/tmp/.mount_*`
+/// directory that disappears with this process; `$APPIMAGE` is the image.
+fn relaunch_executable() -> Result<PathBuf, DesktopError> {
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE").filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe()
+        .map_err(|e| DesktopError::Internal(format!("Failed to resolve the app executable: {e}")))
+}
+
+/// Quit through the normal exit path; the new instance starts from
+/// [`relaunch_pending`]. `reason` only goes to desktop.log.
+pub(crate) fn restart_app(app: &AppHandle, reason: Option<&str>, flag: Option<&str>) -> Result<(), DesktopError> {
+    validate_flag(flag)?;
+    let exe = relaunch_executable()?;
+    let path = history_path(app)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let mut history = recent(&load_history(&path), now);
+    if !allowed(&history, now) {
+        log_event(app, "WARN", "app_restart_refused", &[("reason", reason.unwrap_or("unspecified"))]);
+        return Err(DesktopError::RateLimited(format!(
+            "Refusing to restart more than {MAX_RESTARTS} times in {} minutes",
+            RESTART_WINDOW.as_secs() / 60
+        )));
+    }
+    history.push(now);
+    let serialized = serde_json::to_string(&history)
+        .map_err(|e| DesktopError::Json(format!("Failed to serialize restart history: {e}")))?;
+    fs::write(&path, serialized).map_err(|e| DesktopError::io("Failed to write restart history", &path, e))?;
+
+    log_event(
+        app,
+        "INFO",
+        "app_restart",
+        &[("reason", reason.unwrap_or("unspecified")), ("flag", flag.unwrap_or(""))],
+    );
+    let args = relaunch_args(std::env::args_os().skip(1).collect(), flag);
+    *app.state::<RestartState>().pending.lock().unwrap_or_else(|e| e.into_inner()) = Some(Relaunch { exe, args });
+    // Raises `ExitRequested` like any quit: the busy check may hold it, and
+    // the relaunch waits in `pending` until the exit really happens.
+    app.exit(0);
+    Ok(())
+}
+
+/// Start the new instance, if a restart asked for one. Called from
+/// `RunEvent::Exit`, which plugins (single-instance included) see first.
+pub(crate) fn relaunch_pending(app: &AppHandle) {
+    let Some(state) = app.try_state::<RestartState>() else {
+        return;
+    };
+    let Some(relaunch) = state.pending.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    if let Err(err) = Command::new(&relaunch.exe).args(&relaunch.args).spawn() {
+        log_event(
+            app,
+            "ERROR",
+            "app_relaunch_failed",
+            &[("exe", &relaunch.exe.display().to_string()), ("error", &err.to_string())],
+        );
+    }
+}
+
+pub(crate) fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+    if id != MENU_FILE_RESTART_ID {
+        return false;
+    }
+    if let Err(err) = restart_app(app, Some("menu"), None) {
+        log_event(app, "WARN", "menu_action_failed", &[("item", MENU_FILE_RESTART_ID), ("error", &err.to_string())]);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{allowed, recent, relaunch_args, validate_flag};
+
+    #[test]
+    fn at_most_three_restarts_in_five_minutes() {
+        let now = 10_000;
+        assert!(allowed(&[], now));
+        assert!(allowed(&[now - 100, now - 50], now));
+        assert!(!allowed(&[now - 290, now - 100, now - 50], now));
+        // The oldest has aged out.
+        assert!(allowed(&[now - 300, now - 100, now - 50], now));
+        // A clock that went backwards doesn't count against the limit.
+        assert_eq!(recent(&[now + 60, now - 10], now), vec![now - 10]);
+    }
+
+    #[test]
+    fn relaunch_keeps_the_arguments_and_adds_the_flag_once() {
+        let current = vec![OsString::from("--profile"), OsString::from("work")];
+        let args = relaunch_args(current.clone(), Some("--safe-mode"));
+        assert_eq!(args, ["--profile", "work", "--safe-mode"]);
+        assert_eq!(relaunch_args(args.clone(), Some("--safe-mode")), args);
+        assert_eq!(relaunch_args(current.clone(), None), current);
+    }
+
+    #[test]
+    fn only_known_boolean_flags_are_passed_on() {
+        assert!(validate_flag(None).is_ok());
+        assert!(validate_flag(Some("--safe-mode")).is_ok());
+        for bad in ["--port", "--headless", "safe-mode", "--safe-mode=1"] {
+            assert!(validate_flag(Some(bad)).is_err(), "{bad}");
+        }
+    }
+}
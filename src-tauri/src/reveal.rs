@@ -0,0 +1,175 @@
+//! Show a file selected in the platform file manager, rather than opening it
+//! with whatever app handles its extension (a 100 MB log in a text editor).
+//! Paths requested by a webview are confined to the app's own directories.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tauri::{AppHandle, Manager};
+
+use crate::data_dir;
+
+/// The directories a webview may reveal files under.
+pub(crate) fn allowed_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let resolver = app.path();
+    let mut roots: Vec<PathBuf> = [resolver.app_data_dir(), resolver.app_local_data_dir(), resolver.app_log_dir()]
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect();
+    // A migrated data directory, and the logs under it or in degraded
+    // storage, live outside the platform defaults.
+    roots.extend(data_dir::resolve_data_dir(app));
+    roots.extend(data_dir::resolve_cache_dir(app));
+    roots.extend(data_dir::resolve_log_dir(app));
+    roots
+}
+
+/// Canonicalize `requested` and accept it only if it sits under one of
+/// `roots` (also canonicalized), so `..` segments and symlinks can't escape.
+pub(crate) fn resolve_allowed_path(requested: &Path, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let canonical = requested
+        .canonicalize()
+        .map_err(|e| format!("Cannot reveal {}: {e}", requested.display()))?;
+    let allowed = roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| canonical.starts_with(root));
+    if allowed {
+        Ok(canonical)
+    } else {
+        Err(format!("Revealing {} is not allowed", requested.display()))
+    }
+}
+
+/// Select `path` in Finder, Explorer, or the freedesktop file manager.
+pub(crate) fn reveal_path(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg("-R")
+            .arg(path)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to reveal {}: {e}", path.display()))
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // explorer parses its own command line; `/select,"path"` must not be
+        // re-quoted as a single argument.
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to reveal {}: {e}", path.display()))
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        // FileManager1 isn't reachable from the sandbox.
+        #[cfg(target_os = "linux")]
+        if crate::packaging::is_flatpak() {
+            return crate::packaging::portal::open_directory(path);
+        }
+        if show_items_over_dbus(path) {
+            return Ok(());
+        }
+        // No FileManager1 service: open the containing folder instead.
+        crate::open_path_in_shell(path.parent().unwrap_or(path))
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show_items_over_dbus(path: &Path) -> bool {
+    let Ok(uri) = reqwest::Url::from_file_path(path) else {
+        return false;
+    };
+    Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.FileManager1",
+            "--object-path",
+            "/org/freedesktop/FileManager1",
+            "--method",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(gvariant_string_array(&[uri.as_str()]))
+        .arg("''")
+        .env_remove("LD_LIBRARY_PATH")
+        .env_remove("LD_PRELOAD")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// GVariant text for an `as` argument, as `gdbus call` parses it.
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn gvariant_string_array(items: &[&str]) -> String {
+    let quoted: Vec<String> = items
+        .iter()
+        .map(|item| format!("'{}'", item.replace('\\', "\\\\").replace('\'', "\\'")))
+        .collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gvariant_string_array, resolve_allowed_path};
+    use crate::test_support::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn accepts_files_under_roots_and_rejects_traversal() {
+        let base = temp_dir("reveal-roots");
+        let logs = base.join("logs");
+        fs::create_dir_all(&logs).unwrap();
+        fs::write(logs.join("desktop.log"), "").unwrap();
+        fs::write(base.join("outside.txt"), "").unwrap();
+        let roots = [logs.clone(), base.join("missing-root")];
+
+        let resolved = resolve_allowed_path(&logs.join("desktop.log"), &roots).unwrap();
+        assert!(resolved.ends_with("logs/desktop.log"));
+        assert!(resolve_allowed_path(&logs, &roots).is_ok());
+        assert!(resolve_allowed_path(&logs.join("../outside.txt"), &roots).is_err());
+        assert!(resolve_allowed_path(&logs.join("nope.log"), &roots).is_err());
+        assert!(resolve_allowed_path(&base.join("logs-sibling"), &roots).is_err());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks_that_escape_the_roots() {
+        use std::os::unix::fs::symlink;
+        let base = temp_dir("reveal-symlinks");
+        let logs = base.join("logs");
+        let secret_dir = base.join("elsewhere");
+        fs::create_dir_all(&logs).unwrap();
+        fs::create_dir_all(&secret_dir).unwrap();
+        fs::write(secret_dir.join("id_rsa"), "").unwrap();
+        symlink(secret_dir.join("id_rsa"), logs.join("innocent.log")).unwrap();
+        symlink(&secret_dir, logs.join("dir-link")).unwrap();
+        fs::write(logs.join("real.log"), "").unwrap();
+        symlink(logs.join("real.log"), logs.join("alias.log")).unwrap();
+        let roots = [logs.clone()];
+
+        assert!(resolve_allowed_path(&logs.join("innocent.log"), &roots).is_err());
+        assert!(resolve_allowed_path(&logs.join("dir-link/id_rsa"), &roots).is_err());
+        assert!(resolve_allowed_path(&logs.join("alias.log"), &roots).is_ok());
+
+        // A root that is itself a symlink still admits its own contents.
+        let linked_root = base.join("linked-logs");
+        symlink(&logs, &linked_root).unwrap();
+        assert!(resolve_allowed_path(&logs.join("real.log"), &[linked_root]).is_ok());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn quotes_gvariant_strings() {
+        assert_eq!(
+            gvariant_string_array(&["file:///home/me/it's%20here", "a\\b"]),
+            r"['file:///home/me/it\'s%20here', 'a\\b']"
+        );
+    }
+}
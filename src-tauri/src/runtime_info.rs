@@ -0,0 +1,183 @@
+//! Host facts reported by `get_desktop_runtime_info`. Some of these shell out
+//! or read /proc, so they are collected once in `setup` and kept in managed
+//! state; the command only clones them.
+
+use serde::Serialize;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct StaticRuntimeInfo {
+    pub(crate) app_version: &'static str,
+    pub(crate) tauri_version: &'static str,
+    /// `WebKitGTK`, `WebView2` or `WKWebView`.
+    pub(crate) webview_engine: &'static str,
+    pub(crate) webview_version: Option<String>,
+    /// BCP 47 tag such as `en-US`.
+    pub(crate) locale: Option<String>,
+    /// `wayland` or `x11` on Linux; `None` elsewhere.
+    pub(crate) session_type: Option<&'static str>,
+    pub(crate) total_memory_bytes: Option<u64>,
+    pub(crate) cpu_count: Option<usize>,
+    pub(crate) debug_build: bool,
+}
+
+impl StaticRuntimeInfo {
+    pub(crate) fn collect(session_type: Option<&'static str>) -> Self {
+        StaticRuntimeInfo {
+            app_version: env!("CARGO_PKG_VERSION"),
+            tauri_version: tauri::VERSION,
+            webview_engine: webview_engine(),
+            webview_version: tauri::webview_version().ok(),
+            locale: system_locale(),
+            session_type,
+            total_memory_bytes: total_memory_bytes(),
+            cpu_count: std::thread::available_parallelism().ok().map(|n| n.get()),
+            debug_build: cfg!(debug_assertions),
+        }
+    }
+}
+
+fn webview_engine() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "WebView2"
+    } else if cfg!(target_os = "macos") {
+        "WKWebView"
+    } else {
+        "WebKitGTK"
+    }
+}
+
+/// Normalize a POSIX locale (`en_US.UTF-8@euro`) or Apple locale (`en_US`)
+/// to a BCP 47 tag. `C`/`POSIX` carry no language and yield `None`.
+pub(crate) fn normalize_locale(raw: &str) -> Option<String> {
+    let tag = raw.trim().split(['.', '@']).next().unwrap_or("");
+    if tag.is_empty() || tag == "C" || tag == "POSIX" {
+        return None;
+    }
+    Some(tag.replace('_', "-"))
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn system_locale() -> Option<String> {
+    let output = std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleLocale"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    normalize_locale(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(windows)]
+pub(crate) fn system_locale() -> Option<String> {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetUserDefaultLocaleName(name: *mut u16, len: i32) -> i32;
+    }
+    let mut buf = [0u16; 85]; // LOCALE_NAME_MAX_LENGTH
+    // SAFETY: the buffer length passed matches the buffer.
+    let len = unsafe { GetUserDefaultLocaleName(buf.as_mut_ptr(), buf.len() as i32) };
+    if len <= 1 {
+        return None;
+    }
+    normalize_locale(&String::from_utf16_lossy(&buf[..len as usize - 1]))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| normalize_locale(&value))
+}
+
+/// `MemTotal` from /proc/meminfo contents, in bytes.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn parse_meminfo_total(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(target_os = "linux")]
+fn total_memory_bytes() -> Option<u64> {
+    parse_meminfo_total(&std::fs::read_to_string("/proc/meminfo").ok()?)
+}
+
+#[cfg(target_os = "macos")]
+fn total_memory_bytes() -> Option<u64> {
+    let mut bytes: u64 = 0;
+    let mut len = std::mem::size_of::<u64>();
+    // SAFETY: `hw.memsize` is a u64 and `len` describes the output buffer.
+    let rc = unsafe {
+        libc::sysctlbyname(
+            c"hw.memsize".as_ptr(),
+            (&mut bytes as *mut u64).cast(),
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    (rc == 0).then_some(bytes)
+}
+
+#[cfg(windows)]
+fn total_memory_bytes() -> Option<u64> {
+    #[repr(C)]
+    struct MemoryStatusEx {
+        length: u32,
+        memory_load: u32,
+        total_phys: u64,
+        avail_phys: u64,
+        total_page_file: u64,
+        avail_page_file: u64,
+        total_virtual: u64,
+        avail_virtual: u64,
+        avail_extended_virtual: u64,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GlobalMemoryStatusEx(buffer: *mut MemoryStatusEx) -> i32;
+    }
+    let mut status = MemoryStatusEx {
+        length: std::mem::size_of::<MemoryStatusEx>() as u32,
+        memory_load: 0,
+        total_phys: 0,
+        avail_phys: 0,
+        total_page_file: 0,
+        avail_page_file: 0,
+        total_virtual: 0,
+        avail_virtual: 0,
+        avail_extended_virtual: 0,
+    };
+    // SAFETY: `length` is initialised as the API requires.
+    let ok = unsafe { GlobalMemoryStatusEx(&mut status) };
+    (ok != 0).then_some(status.total_phys)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn total_memory_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_locale, parse_meminfo_total};
+
+    #[test]
+    fn normalizes_posix_and_apple_locales() {
+        assert_eq!(normalize_locale("en_US.UTF-8").as_deref(), Some("en-US"));
+        assert_eq!(normalize_locale("de_DE@euro").as_deref(), Some("de-DE"));
+        assert_eq!(normalize_locale("fr-CA\n").as_deref(), Some("fr-CA"));
+        assert_eq!(normalize_locale("C.UTF-8"), None);
+        assert_eq!(normalize_locale("POSIX"), None);
+    }
+
+    #[test]
+    fn parses_meminfo_total() {
+        let meminfo = "MemTotal:       16318444 kB\nMemFree:         1234 kB\n";
+        assert_eq!(parse_meminfo_total(meminfo), Some(16318444 * 1024));
+        assert_eq!(parse_meminfo_total("MemFree: 1 kB"), None);
+    }
+}
@@ -0,0 +1,116 @@
+//! Crash-loop detection. `setup` writes a startup marker holding the number of
+//! consecutive launches that never reached a stable state; it is removed a
+//! grace period after `RunEvent::Ready`. Finding a marker at launch means the
+//! previous run died during startup, and enough of those in a row put the app
+//! into safe mode.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::profile;
+
+pub(crate) const CRASH_LOOP_THRESHOLD: u32 = 3;
+pub(crate) const SAFE_MODE_WINDOW_LABEL: &str = "safe-mode";
+const STARTUP_MARKER_FILE: &str = "startup-marker";
+const CLEAN_STARTUP_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct SafeModeStatus {
+    pub(crate) active: bool,
+    /// `"crash_loop"` or `"env"` (Linux `WM_LINUX_WEBKIT_SAFE_MODE`).
+    pub(crate) reason: Option<String>,
+    /// Consecutive unclean launches found when this one started.
+    pub(crate) unclean_launches: u32,
+}
+
+/// Managed state: the status plus where this launch's marker lives.
+pub(crate) struct SafeModeState {
+    pub(crate) status: SafeModeStatus,
+    pub(crate) marker_path: Option<PathBuf>,
+}
+
+/// Same location as Tauri's `app_data_dir` (scoped to the active profile),
+/// resolvable before the app exists.
+pub(crate) fn startup_marker_path(identifier: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| profile::scope_dir(&dir.join(identifier)).join(STARTUP_MARKER_FILE))
+}
+
+/// Unclean launches recorded in a marker's contents; a missing marker means
+/// the previous run exited cleanly. Unparseable markers count as one.
+pub(crate) fn unclean_launches(marker: Option<&str>) -> u32 {
+    marker.map_or(0, |contents| contents.trim().parse::<u32>().unwrap_or(1).max(1))
+}
+
+pub(crate) fn is_crash_loop(unclean_launches: u32) -> bool {
+    unclean_launches >= CRASH_LOOP_THRESHOLD
+}
+
+pub(crate) fn read_unclean_launches(path: &Path) -> u32 {
+    unclean_launches(fs::read_to_string(path).ok().as_deref())
+}
+
+/// Mark this launch as in progress, on top of the unclean streak so far.
+pub(crate) fn record_startup(path: &Path, unclean_launches: u32) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, (unclean_launches.saturating_add(1)).to_string())
+}
+
+pub(crate) fn clear_startup_marker(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+/// Clear the marker once the app has stayed up for the grace period.
+pub(crate) fn clear_startup_marker_after_grace(path: PathBuf) {
+    std::thread::spawn(move || {
+        std::thread::sleep(CLEAN_STARTUP_GRACE);
+        clear_startup_marker(&path);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clear_startup_marker, is_crash_loop, read_unclean_launches, record_startup, unclean_launches,
+        CRASH_LOOP_THRESHOLD,
+    };
+
+    #[test]
+    fn counts_unclean_launches_from_marker_contents() {
+        assert_eq!(unclean_launches(None), 0);
+        assert_eq!(unclean_launches(Some("2\n")), 2);
+        assert_eq!(unclean_launches(Some("garbage")), 1);
+        assert_eq!(unclean_launches(Some("0")), 1);
+    }
+
+    #[test]
+    fn threshold_trips_after_consecutive_unclean_launches() {
+        assert!(!is_crash_loop(CRASH_LOOP_THRESHOLD - 1));
+        assert!(is_crash_loop(CRASH_LOOP_THRESHOLD));
+    }
+
+    #[test]
+    fn marker_accumulates_until_cleared() {
+        let path = std::env::temp_dir()
+            .join(format!("wm-safe-mode-tests-{}", std::process::id()))
+            .join("startup-marker");
+        clear_startup_marker(&path);
+
+        let mut streak = read_unclean_launches(&path);
+        for expected in 0..CRASH_LOOP_THRESHOLD {
+            assert_eq!(streak, expected);
+            assert!(!is_crash_loop(streak));
+            record_startup(&path, streak).unwrap();
+            // Process dies here without clearing the marker.
+            streak = read_unclean_launches(&path);
+        }
+        assert!(is_crash_loop(streak));
+
+        clear_startup_marker(&path);
+        assert_eq!(read_unclean_launches(&path), 0);
+    }
+}
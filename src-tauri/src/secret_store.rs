@@ -0,0 +1,166 @@
+//! Where secrets are persisted. Everything above this (the vault, the legacy
+//! migration, the doctor probe) goes through [`SecretStore`], so it runs the
+//! same against the platform keychain and against [`MemoryStore`] in tests,
+//! where CI has no keychain. [`open`] picks the backend at startup.
+
+use std::fmt;
+use std::sync::Arc;
+
+use keyring::Entry;
+
+use crate::error::DesktopError;
+use crate::logging::now_iso8601;
+use crate::profile;
+
+const PROBE_ACCOUNT: &str = "doctor-probe";
+
+#[derive(Debug)]
+pub(crate) enum StoreError {
+    /// The backend couldn't be opened for the account.
+    Init(String),
+    /// The read, write or delete itself failed.
+    Backend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Init(message) | StoreError::Backend(message) => f.write_str(message),
+        }
+    }
+}
+
+impl StoreError {
+    /// `keyring_unavailable`, with `action` naming what failed.
+    pub(crate) fn into_desktop(self, action: &str) -> DesktopError {
+        match self {
+            StoreError::Init(e) => DesktopError::KeyringUnavailable(format!("Keyring init failed: {e}")),
+            StoreError::Backend(e) => DesktopError::KeyringUnavailable(format!("{action}: {e}")),
+        }
+    }
+}
+
+/// Named string entries under the active profile.
+pub(crate) trait SecretStore: Send + Sync {
+    /// `None` when there is no entry.
+    fn get(&self, account: &str) -> Result<Option<String>, StoreError>;
+    fn set(&self, account: &str, value: &str) -> Result<(), StoreError>;
+    /// Removing a missing entry succeeds.
+    fn delete(&self, account: &str) -> Result<(), StoreError>;
+
+    /// Write, read back and delete a throwaway entry. `Ok(false)` when the
+    /// value read back differs.
+    fn probe(&self) -> Result<bool, StoreError> {
+        let value = format!("probe-{}", now_iso8601());
+        self.set(PROBE_ACCOUNT, &value)?;
+        let read = self.get(PROBE_ACCOUNT);
+        let _ = self.delete(PROBE_ACCOUNT);
+        Ok(read? == Some(value))
+    }
+}
+
+/// The platform keychain (Keychain, Credential Manager, Secret Service).
+pub(crate) struct KeyringStore {
+    service: String,
+}
+
+impl KeyringStore {
+    fn entry(&self, account: &str) -> Result<Entry, StoreError> {
+        Entry::new(&self.service, account).map_err(|e| StoreError::Init(e.to_string()))
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, account: &str) -> Result<Option<String>, StoreError> {
+        match self.entry(account)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(StoreError::Backend(e.to_string())),
+        }
+    }
+
+    fn set(&self, account: &str, value: &str) -> Result<(), StoreError> {
+        self.entry(account)?
+            .set_password(value)
+            .map_err(|e| StoreError::Backend(e.to_string()))
+    }
+
+    fn delete(&self, account: &str) -> Result<(), StoreError> {
+        match self.entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(StoreError::Backend(e.to_string())),
+        }
+    }
+}
+
+/// The store for this launch: the keychain under the profile's service.
+pub(crate) fn open() -> Arc<dyn SecretStore> {
+    Arc::new(KeyringStore {
+        service: profile::keyring_service(),
+    })
+}
+
+/// A store that lives and dies with the process.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct MemoryStore {
+    entries: std::sync::Mutex<std::collections::HashMap<String, String>>,
+}
+
+#[cfg(test)]
+impl MemoryStore {
+    pub(crate) fn with(entries: &[(&str, &str)]) -> Self {
+        let store = MemoryStore::default();
+        for (account, value) in entries {
+            store.set(account, value).unwrap();
+        }
+        store
+    }
+
+    pub(crate) fn accounts(&self) -> Vec<String> {
+        let mut accounts: Vec<String> = self.entries.lock().unwrap().keys().cloned().collect();
+        accounts.sort();
+        accounts
+    }
+}
+
+#[cfg(test)]
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.entries.lock().unwrap().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, value: &str) -> Result<(), StoreError> {
+        self.entries.lock().unwrap().insert(account.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<(), StoreError> {
+        self.entries.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryStore, SecretStore, StoreError};
+
+    #[test]
+    fn delete_is_idempotent_and_probe_leaves_nothing_behind() {
+        let store = MemoryStore::with(&[("GROQ_API_KEY", "gsk_1")]);
+        store.delete("GROQ_API_KEY").unwrap();
+        store.delete("GROQ_API_KEY").unwrap();
+        assert_eq!(store.get("GROQ_API_KEY").unwrap(), None);
+        assert!(store.probe().unwrap());
+        assert!(store.accounts().is_empty());
+    }
+
+    #[test]
+    fn errors_keep_the_keychain_messages() {
+        let init = StoreError::Init("no backend".to_string()).into_desktop("Failed to read vault");
+        assert_eq!(init.to_string(), "Keyring init failed: no backend");
+        let backend = StoreError::Backend("locked".to_string()).into_desktop("Failed to write vault");
+        assert_eq!(backend.to_string(), "Failed to write vault: locked");
+        assert_eq!(backend.code(), "keyring_unavailable");
+    }
+}
@@ -0,0 +1,442 @@
+//! Everything the sidecar gets in its environment, assembled in one place.
+//! [`inputs`] gathers the values from app state, [`build_sidecar_env`] turns
+//! them into the variable list, and `start_local_api` applies that list to
+//! the command. `get_sidecar_env_preview` runs the same assembly without
+//! spawning anything, masks the secrets, and compares the result with what
+//! the running sidecar was started with, so settings can say when a restart
+//! is needed. Variables the app also pushes to a running sidecar (quiet
+//! hours, polling, network profile, clock skew, the forensics worker,
+//! disabled sources and trusted local hosts) never count as a reason to
+//! restart.
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::forensics_worker::{self, WorkerEndpoint};
+use crate::{
+    clock_skew, data_sources, extra_ca, local_api_listen_addr, local_api_paths, logging, logs_dir_path,
+    network_profile, polling, preferred_local_api_port, quiet_hours, sanitize_path_for_node, sidecar_options,
+    sidecar_port_file, trusted_hosts, user_agent, LocalApiState, SecretsCache, SUPPORTED_SECRET_KEYS,
+};
+
+/// Updated in place while the sidecar runs, so they never need a restart.
+const LIVE_VARS: [&str; 8] = [
+    quiet_hours::QUIET_HOURS_ENV,
+    polling::POLLING_PAUSED_ENV,
+    network_profile::POLL_MULTIPLIER_ENV,
+    clock_skew::CLOCK_SKEW_ENV,
+    forensics_worker::URL_ENV,
+    forensics_worker::SECRET_ENV,
+    data_sources::DISABLED_SOURCES_ENV,
+    trusted_hosts::TRUSTED_HOSTS_ENV,
+];
+
+/// One variable's value and where it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum EnvValueKind {
+    /// From the vault, or a credential; never shown.
+    Secret(String),
+    /// Set directly by a pref, a CLI flag or the build.
+    Config(String),
+    /// Worked out by the app from its paths and state.
+    Derived(String),
+}
+
+impl EnvValueKind {
+    pub(crate) fn value(&self) -> &str {
+        match self {
+            EnvValueKind::Secret(value) | EnvValueKind::Config(value) | EnvValueKind::Derived(value) => value,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            EnvValueKind::Secret(_) => "secret",
+            EnvValueKind::Config(_) => "config",
+            EnvValueKind::Derived(_) => "derived",
+        }
+    }
+
+    /// The value as settings may show it, masked like the log redactor does.
+    fn display(&self, name: &str) -> String {
+        match self {
+            EnvValueKind::Secret(_) => format!("\u{ab}redacted:{name}\u{bb}"),
+            other => other.value().to_string(),
+        }
+    }
+}
+
+/// What the environment is built from, read from app state by [`inputs`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SidecarEnvInputs {
+    pub(crate) port: u16,
+    pub(crate) port_file: String,
+    pub(crate) resource_dir: String,
+    pub(crate) data_dir: String,
+    pub(crate) token: Option<String>,
+    pub(crate) host: Option<IpAddr>,
+    pub(crate) extra_ca_bundle: Option<String>,
+    /// Vault contents, sorted by name.
+    pub(crate) secrets: Vec<(String, String)>,
+    pub(crate) build_convex_url: Option<String>,
+    pub(crate) runtime_convex_url: Option<String>,
+    pub(crate) debug_logging: bool,
+    pub(crate) user_agent: String,
+    pub(crate) quiet_hours: bool,
+    pub(crate) polling_paused: bool,
+    pub(crate) poll_multiplier: Option<String>,
+    pub(crate) clock_skew: Option<String>,
+    pub(crate) forensics_worker: Option<WorkerEndpoint>,
+    pub(crate) disabled_sources: Option<String>,
+    pub(crate) trusted_hosts: Option<String>,
+    pub(crate) extra_env: Vec<(String, String)>,
+}
+
+fn set(env: &mut Vec<(String, EnvValueKind)>, name: &str, kind: EnvValueKind) {
+    env.retain(|(existing, _)| existing != name);
+    env.push((name.to_string(), kind));
+}
+
+/// The sidecar's variables in the order they are applied. A name set twice
+/// keeps only its later value, as it would on the command.
+pub(crate) fn build_sidecar_env(inputs: &SidecarEnvInputs) -> Vec<(String, EnvValueKind)> {
+    use EnvValueKind::{Config, Derived, Secret};
+
+    let mut env = Vec::new();
+    set(&mut env, "LOCAL_API_PORT", Config(inputs.port.to_string()));
+    set(&mut env, "LOCAL_API_PORT_FILE", Derived(inputs.port_file.clone()));
+    set(&mut env, "LOCAL_API_RESOURCE_DIR", Derived(inputs.resource_dir.clone()));
+    set(&mut env, "LOCAL_API_DATA_DIR", Derived(inputs.data_dir.clone()));
+    set(&mut env, "LOCAL_API_MODE", Derived("tauri-sidecar".to_string()));
+    if let Some(token) = &inputs.token {
+        set(&mut env, "LOCAL_API_TOKEN", Secret(token.clone()));
+    }
+    // Explicit even though it is the sidecar's default, so nothing binds a
+    // wildcard address that firewalls prompt about.
+    let host = inputs.host.unwrap_or(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+    set(&mut env, "LOCAL_API_HOST", Config(host.to_string()));
+    if let Some(bundle) = &inputs.extra_ca_bundle {
+        set(&mut env, extra_ca::NODE_EXTRA_CA_CERTS_ENV, Derived(bundle.clone()));
+    }
+    for (name, value) in &inputs.secrets {
+        set(&mut env, name, Secret(value.clone()));
+    }
+    // Build-time value (CI) first, then the runtime environment (dev).
+    if let Some(url) = inputs.build_convex_url.as_ref().or(inputs.runtime_convex_url.as_ref()) {
+        set(&mut env, "CONVEX_URL", Config(url.clone()));
+    }
+    if inputs.debug_logging {
+        set(&mut env, "LOCAL_API_LOG_LEVEL", Config("debug".to_string()));
+    }
+    set(&mut env, user_agent::USER_AGENT_ENV, Config(inputs.user_agent.clone()));
+    if inputs.quiet_hours {
+        set(&mut env, quiet_hours::QUIET_HOURS_ENV, Derived("1".to_string()));
+    }
+    if inputs.polling_paused {
+        set(&mut env, polling::POLLING_PAUSED_ENV, Config("1".to_string()));
+    }
+    if let Some(multiplier) = &inputs.poll_multiplier {
+        set(&mut env, network_profile::POLL_MULTIPLIER_ENV, Derived(multiplier.clone()));
+    }
+    if let Some(skew) = &inputs.clock_skew {
+        set(&mut env, clock_skew::CLOCK_SKEW_ENV, Derived(skew.clone()));
+    }
+    if let Some(worker) = &inputs.forensics_worker {
+        set(&mut env, forensics_worker::URL_ENV, Derived(worker.url.clone()));
+        set(&mut env, forensics_worker::SECRET_ENV, Secret(worker.secret.clone()));
+    }
+    if let Some(disabled) = &inputs.disabled_sources {
+        set(&mut env, data_sources::DISABLED_SOURCES_ENV, Config(disabled.clone()));
+    }
+    if let Some(hosts) = &inputs.trusted_hosts {
+        set(&mut env, trusted_hosts::TRUSTED_HOSTS_ENV, Config(hosts.clone()));
+    }
+    for (name, value) in &inputs.extra_env {
+        set(&mut env, name, Config(value.clone()));
+    }
+    env
+}
+
+/// Read the current inputs. `forensics_worker` and `extra_ca_bundle` are
+/// passed in because starting the sidecar creates them while a preview only
+/// looks them up.
+pub(crate) fn inputs(
+    app: &AppHandle,
+    forensics_worker: Option<WorkerEndpoint>,
+    extra_ca_bundle: Option<PathBuf>,
+) -> Result<SidecarEnvInputs, String> {
+    let resource_dir = sanitize_path_for_node(&local_api_paths(app).resource_root);
+    let data_dir = logs_dir_path(app)
+        .map(|path| sanitize_path_for_node(&path))
+        .unwrap_or_else(|_| resource_dir.clone());
+    let token = app
+        .state::<LocalApiState>()
+        .token
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let mut secrets: Vec<(String, String)> = app
+        .state::<SecretsCache>()
+        .secrets
+        .lock()
+        .map(|secrets| secrets.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+    secrets.sort();
+    let disabled = data_sources::disabled(data_sources::stored(app).as_ref());
+    let trusted = trusted_hosts::patterns(app);
+    Ok(SidecarEnvInputs {
+        port: preferred_local_api_port(app),
+        port_file: sidecar_port_file(app)?.display().to_string(),
+        resource_dir,
+        data_dir,
+        token,
+        host: local_api_listen_addr(app),
+        extra_ca_bundle: extra_ca_bundle.map(|path| path.display().to_string()),
+        secrets,
+        build_convex_url: option_env!("CONVEX_URL").map(str::to_string),
+        runtime_convex_url: std::env::var("CONVEX_URL").ok(),
+        debug_logging: logging::log_threshold(app) == logging::LogLevel::Debug,
+        user_agent: user_agent::effective(app),
+        quiet_hours: quiet_hours::is_active(app),
+        polling_paused: polling::is_paused(app),
+        poll_multiplier: network_profile::env_value(app),
+        clock_skew: clock_skew::env_value(app),
+        forensics_worker,
+        disabled_sources: (!disabled.is_empty()).then(|| data_sources::env_value(&disabled)),
+        trusted_hosts: (!trusted.is_empty()).then(|| trusted_hosts::env_value(&trusted)),
+        extra_env: sidecar_options::extra_env(app, &SUPPORTED_SECRET_KEYS),
+    })
+}
+
+/// Set every variable on `cmd`. An inherited `NODE_EXTRA_CA_CERTS` is
+/// cleared rather than passed on when no bundle is configured.
+pub(crate) fn apply(cmd: &mut Command, env: &[(String, EnvValueKind)]) {
+    for (name, kind) in env {
+        cmd.env(name, kind.value());
+    }
+    if !env.iter().any(|(name, _)| name == extra_ca::NODE_EXTRA_CA_CERTS_ENV) {
+        cmd.env_remove(extra_ca::NODE_EXTRA_CA_CERTS_ENV);
+    }
+}
+
+type Fingerprint = BTreeMap<String, [u8; 32]>;
+
+fn fingerprint(env: &[(String, EnvValueKind)]) -> Fingerprint {
+    env.iter()
+        .map(|(name, kind)| (name.clone(), Sha256::digest(kind.value().as_bytes()).into()))
+        .collect()
+}
+
+/// Names whose value differs between the two, or that only one has,
+/// leaving out [`LIVE_VARS`].
+fn changed(launched: &Fingerprint, current: &Fingerprint) -> Vec<String> {
+    let mut names: Vec<String> = launched
+        .keys()
+        .chain(current.keys())
+        .filter(|name| !LIVE_VARS.contains(&name.as_str()))
+        .filter(|name| launched.get(*name) != current.get(*name))
+        .cloned()
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Managed state: hashes of what the running sidecar was started with, so
+/// secret values aren't kept around a second time.
+#[derive(Default)]
+pub(crate) struct SidecarEnvState {
+    launched: Mutex<Option<Fingerprint>>,
+}
+
+/// Called by `start_local_api` once the sidecar has spawned.
+pub(crate) fn record_launch(app: &AppHandle, env: &[(String, EnvValueKind)]) {
+    if let Some(state) = app.try_state::<SidecarEnvState>() {
+        *state.launched.lock().unwrap_or_else(|e| e.into_inner()) = Some(fingerprint(env));
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct EnvVarPreview {
+    name: String,
+    kind: &'static str,
+    value: String,
+}
+
+/// `get_sidecar_env_preview` result.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EnvPreview {
+    running: bool,
+    /// The running sidecar was started with something other than `vars`.
+    restart_required: bool,
+    /// Which variables differ; names only.
+    changed: Vec<String>,
+    vars: Vec<EnvVarPreview>,
+}
+
+/// What the sidecar would be started with now, secrets masked.
+pub(crate) fn preview(app: &AppHandle) -> Result<EnvPreview, String> {
+    let env = build_sidecar_env(&inputs(
+        app,
+        forensics_worker::current_endpoint(app),
+        extra_ca::sidecar_bundle_path(app),
+    )?);
+    let running = app
+        .state::<LocalApiState>()
+        .child
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some();
+    let changed = match app.try_state::<SidecarEnvState>() {
+        Some(state) if running => state
+            .launched
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|launched| changed(launched, &fingerprint(&env)))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    Ok(EnvPreview {
+        running,
+        restart_required: !changed.is_empty(),
+        changed,
+        vars: env
+            .iter()
+            .map(|(name, kind)| EnvVarPreview {
+                name: name.clone(),
+                kind: kind.label(),
+                value: kind.display(name),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::process::Command;
+
+    use super::{apply, build_sidecar_env, changed, fingerprint, EnvValueKind, SidecarEnvInputs};
+    use crate::forensics_worker::WorkerEndpoint;
+
+    fn value<'a>(env: &'a [(String, EnvValueKind)], name: &str) -> Option<&'a EnvValueKind> {
+        env.iter().find(|(key, _)| key == name).map(|(_, kind)| kind)
+    }
+
+    #[test]
+    fn convex_url_prefers_the_build_value_then_the_runtime_one() {
+        let mut inputs = SidecarEnvInputs {
+            build_convex_url: Some("https://build.convex.cloud".to_string()),
+            runtime_convex_url: Some("https://dev.convex.cloud".to_string()),
+            ..SidecarEnvInputs::default()
+        };
+        let env = build_sidecar_env(&inputs);
+        assert_eq!(
+            value(&env, "CONVEX_URL"),
+            Some(&EnvValueKind::Config("https://build.convex.cloud".to_string()))
+        );
+
+        inputs.build_convex_url = None;
+        let env = build_sidecar_env(&inputs);
+        assert_eq!(value(&env, "CONVEX_URL").map(EnvValueKind::value), Some("https://dev.convex.cloud"));
+
+        inputs.runtime_convex_url = None;
+        assert_eq!(value(&build_sidecar_env(&inputs), "CONVEX_URL"), None);
+    }
+
+    #[test]
+    fn forensics_worker_url_is_shown_and_its_secret_is_masked() {
+        let inputs = SidecarEnvInputs {
+            forensics_worker: Some(WorkerEndpoint {
+                url: "http://127.0.0.1:46200/api".to_string(),
+                secret: "shh".to_string(),
+            }),
+            secrets: vec![("GROQ_API_KEY".to_string(), "gsk_live".to_string())],
+            ..SidecarEnvInputs::default()
+        };
+        let env = build_sidecar_env(&inputs);
+        let url = value(&env, "FORENSICS_WORKER_URL").unwrap();
+        assert_eq!(url, &EnvValueKind::Derived("http://127.0.0.1:46200/api".to_string()));
+        assert_eq!(url.display("FORENSICS_WORKER_URL"), "http://127.0.0.1:46200/api");
+        let secret = value(&env, "FORENSICS_WORKER_SHARED_SECRET").unwrap();
+        assert_eq!(secret.display("FORENSICS_WORKER_SHARED_SECRET"), "\u{ab}redacted:FORENSICS_WORKER_SHARED_SECRET\u{bb}");
+        let key = value(&env, "GROQ_API_KEY").unwrap();
+        assert_eq!(key.label(), "secret");
+        assert!(!key.display("GROQ_API_KEY").contains("gsk_live"));
+
+        let without = build_sidecar_env(&SidecarEnvInputs::default());
+        assert_eq!(value(&without, "FORENSICS_WORKER_URL"), None);
+    }
+
+    #[test]
+    fn a_later_value_replaces_an_earlier_one_of_the_same_name() {
+        let inputs = SidecarEnvInputs {
+            secrets: vec![("OLLAMA_API_URL".to_string(), "http://vault:11434".to_string())],
+            extra_env: vec![("OLLAMA_API_URL".to_string(), "http://extra:11434".to_string())],
+            ..SidecarEnvInputs::default()
+        };
+        let env = build_sidecar_env(&inputs);
+        assert_eq!(env.iter().filter(|(name, _)| name == "OLLAMA_API_URL").count(), 1);
+        assert_eq!(
+            value(&env, "OLLAMA_API_URL"),
+            Some(&EnvValueKind::Config("http://extra:11434".to_string()))
+        );
+        assert_eq!(value(&env, "LOCAL_API_HOST").map(EnvValueKind::value), Some("127.0.0.1"));
+    }
+
+    #[test]
+    fn only_restart_relevant_changes_are_reported() {
+        let mut inputs = SidecarEnvInputs {
+            secrets: vec![("FRED_API_KEY".to_string(), "one".to_string())],
+            ..SidecarEnvInputs::default()
+        };
+        let launched = fingerprint(&build_sidecar_env(&inputs));
+
+        inputs.quiet_hours = true;
+        inputs.polling_paused = true;
+        inputs.clock_skew = Some("1500".to_string());
+        assert!(changed(&launched, &fingerprint(&build_sidecar_env(&inputs))).is_empty());
+
+        inputs.secrets[0].1 = "two".to_string();
+        inputs.debug_logging = true;
+        assert_eq!(
+            changed(&launched, &fingerprint(&build_sidecar_env(&inputs))),
+            ["FRED_API_KEY", "LOCAL_API_LOG_LEVEL"]
+        );
+
+        inputs.secrets.clear();
+        inputs.debug_logging = false;
+        assert_eq!(changed(&launched, &fingerprint(&build_sidecar_env(&inputs))), ["FRED_API_KEY"]);
+    }
+
+    #[test]
+    fn extra_ca_bundle_is_passed_or_an_inherited_one_cleared() {
+        let mut inputs = SidecarEnvInputs {
+            extra_ca_bundle: Some("/data/extra-ca-bundle.pem".to_string()),
+            ..SidecarEnvInputs::default()
+        };
+        let mut cmd = Command::new("node");
+        apply(&mut cmd, &build_sidecar_env(&inputs));
+        let bundle = cmd
+            .get_envs()
+            .find(|(key, _)| *key == OsStr::new("NODE_EXTRA_CA_CERTS"))
+            .and_then(|(_, value)| value);
+        assert_eq!(bundle, Some(OsStr::new("/data/extra-ca-bundle.pem")));
+
+        inputs.extra_ca_bundle = None;
+        let mut cmd = Command::new("node");
+        apply(&mut cmd, &build_sidecar_env(&inputs));
+        assert!(cmd
+            .get_envs()
+            .any(|(key, value)| key == OsStr::new("NODE_EXTRA_CA_CERTS") && value.is_none()));
+    }
+}
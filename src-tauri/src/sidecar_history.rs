@@ -0,0 +1,184 @@
+//! Sidecar lifecycle history and the restart circuit breaker. The last
+//! [`HISTORY_LEN`] starts, exits and restart decisions are kept for support;
+//! an unexpected exit is restarted with backoff until [`MAX_CRASHES`] exits
+//! land inside [`CRASH_WINDOW`], after which the breaker stays open until
+//! `reset_local_api_breaker`.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+pub(crate) const HISTORY_LEN: usize = 50;
+pub(crate) const MAX_CRASHES: usize = 5;
+pub(crate) const CRASH_WINDOW: Duration = Duration::from_secs(10 * 60);
+const BASE_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum LifecycleKind {
+    Started { pid: u32 },
+    Exited { code: Option<i32>, signal: Option<i32> },
+    RestartScheduled { attempt: u32, delay_ms: u64 },
+    GaveUp { crashes: u32 },
+    BreakerReset,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct LifecycleEvent {
+    at: String,
+    #[serde(flatten)]
+    kind: LifecycleKind,
+}
+
+/// `get_local_api_history` payload.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct HistoryReport {
+    breaker_tripped: bool,
+    events: Vec<LifecycleEvent>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ExitDecision {
+    Restart { attempt: u32, delay: Duration },
+    GiveUp { crashes: u32 },
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SidecarHistory {
+    events: VecDeque<LifecycleEvent>,
+    /// Unexpected exits inside the current window.
+    crashes: VecDeque<Instant>,
+    tripped: bool,
+}
+
+fn restart_delay(attempt: u32) -> Duration {
+    BASE_RESTART_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RESTART_DELAY)
+}
+
+impl SidecarHistory {
+    pub(crate) fn record(&mut self, at: String, kind: LifecycleKind) {
+        if self.events.len() == HISTORY_LEN {
+            self.events.pop_front();
+        }
+        self.events.push_back(LifecycleEvent { at, kind });
+    }
+
+    pub(crate) fn events(&self) -> Vec<LifecycleEvent> {
+        self.events.iter().cloned().collect()
+    }
+
+    pub(crate) fn report(&self) -> HistoryReport {
+        HistoryReport {
+            breaker_tripped: self.tripped,
+            events: self.events(),
+        }
+    }
+
+    /// Count an unexpected exit at `now` and decide whether to restart.
+    pub(crate) fn on_crash(&mut self, now: Instant) -> ExitDecision {
+        while self
+            .crashes
+            .front()
+            .is_some_and(|crash| now.saturating_duration_since(*crash) > CRASH_WINDOW)
+        {
+            self.crashes.pop_front();
+        }
+        self.crashes.push_back(now);
+        let crashes = self.crashes.len() as u32;
+        if self.tripped || self.crashes.len() >= MAX_CRASHES {
+            self.tripped = true;
+            return ExitDecision::GiveUp { crashes };
+        }
+        ExitDecision::Restart {
+            attempt: crashes,
+            delay: restart_delay(crashes),
+        }
+    }
+
+    /// Close the breaker and forget past crashes; the history is kept.
+    pub(crate) fn reset(&mut self, at: String) {
+        self.crashes.clear();
+        self.tripped = false;
+        self.record(at, LifecycleKind::BreakerReset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ExitDecision, LifecycleKind, SidecarHistory, CRASH_WINDOW, HISTORY_LEN, MAX_CRASHES};
+
+    #[test]
+    fn trips_after_max_crashes_in_window_and_resets() {
+        let t0 = Instant::now();
+        let mut history = SidecarHistory::default();
+        let delays: Vec<ExitDecision> = (0..MAX_CRASHES as u64 - 1)
+            .map(|i| history.on_crash(t0 + Duration::from_secs(60 * i)))
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                ExitDecision::Restart { attempt: 1, delay: Duration::from_secs(1) },
+                ExitDecision::Restart { attempt: 2, delay: Duration::from_secs(2) },
+                ExitDecision::Restart { attempt: 3, delay: Duration::from_secs(4) },
+                ExitDecision::Restart { attempt: 4, delay: Duration::from_secs(8) },
+            ]
+        );
+        assert!(!history.report().breaker_tripped);
+        assert_eq!(
+            history.on_crash(t0 + Duration::from_secs(300)),
+            ExitDecision::GiveUp { crashes: 5 }
+        );
+        assert!(history.report().breaker_tripped);
+        // Stays open even once the window has passed.
+        assert!(matches!(history.on_crash(t0 + CRASH_WINDOW * 3), ExitDecision::GiveUp { .. }));
+
+        history.reset("2026-01-01T00:00:00.000Z".to_string());
+        assert!(!history.report().breaker_tripped);
+        assert_eq!(
+            history.on_crash(t0 + CRASH_WINDOW * 4),
+            ExitDecision::Restart { attempt: 1, delay: Duration::from_secs(1) }
+        );
+    }
+
+    #[test]
+    fn crashes_outside_window_do_not_count() {
+        let t0 = Instant::now();
+        let mut history = SidecarHistory::default();
+        for i in 0..20u64 {
+            let decision = history.on_crash(t0 + CRASH_WINDOW / 2 * i as u32 + Duration::from_secs(i));
+            assert!(matches!(decision, ExitDecision::Restart { attempt: 1 | 2, .. }), "{i}: {decision:?}");
+        }
+        assert!(!history.report().breaker_tripped);
+    }
+
+    #[test]
+    fn history_is_bounded_and_serializes_flat() {
+        let mut history = SidecarHistory::default();
+        for pid in 0..(HISTORY_LEN as u32 + 10) {
+            history.record(format!("t{pid}"), LifecycleKind::Started { pid });
+        }
+        let events = history.events();
+        assert_eq!(events.len(), HISTORY_LEN);
+        assert_eq!(
+            serde_json::to_value(&events[0]).unwrap(),
+            serde_json::json!({"at": "t10", "kind": "started", "pid": 10})
+        );
+        history.record(
+            "t".to_string(),
+            LifecycleKind::Exited {
+                code: None,
+                signal: Some(9),
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(history.events().last().unwrap()).unwrap(),
+            serde_json::json!({"at": "t", "kind": "exited", "code": null, "signal": 9})
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Live tail of the sidecar's output for the settings logs tab. The
+//! sidecar's stdout and stderr are piped through [`forward`], which redacts
+//! every line once, writes it to `local-api.log` and, while any window has
+//! called `subscribe_sidecar_log`, hands the same text to that window as a
+//! `sidecar-log-line` event. Each subscriber has its own [`Outbox`], emptied
+//! a few times a second; when one holds more than [`OUTBOX_CAPACITY`] lines
+//! the oldest are dropped and the window gets a single `sidecar-log-burst`
+//! with the count instead, so a chatty sidecar can't swamp a webview.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logging::{log_event, now_iso8601, secret_redactor, SecretRedactor};
+
+const LINE_EVENT: &str = "sidecar-log-line";
+const BURST_EVENT: &str = "sidecar-log-burst";
+/// Lines a subscriber may have waiting before the oldest are dropped.
+const OUTBOX_CAPACITY: usize = 500;
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+/// Lines emitted to one window per flush.
+const LINES_PER_FLUSH: usize = 100;
+/// Longer lines are cut here, at a character boundary.
+const MAX_LINE_BYTES: usize = 8 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// `sidecar-log-line` payload.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct LogLine {
+    stream: Stream,
+    line: String,
+    ts: String,
+}
+
+/// `sidecar-log-burst` payload: lines this window never got.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct LogBurst {
+    dropped: u64,
+    ts: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Delivery {
+    Burst(u64),
+    Line(LogLine),
+}
+
+/// What one subscriber hasn't been sent yet.
+#[derive(Debug)]
+struct Outbox {
+    queue: VecDeque<LogLine>,
+    dropped: u64,
+    capacity: usize,
+}
+
+impl Outbox {
+    fn new(capacity: usize) -> Self {
+        Outbox {
+            queue: VecDeque::new(),
+            dropped: 0,
+            capacity,
+        }
+    }
+
+    /// Queue `line`, dropping the oldest waiting line if full.
+    fn push(&mut self, line: LogLine) {
+        if self.queue.len() >= self.capacity {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+        self.queue.push_back(line);
+    }
+
+    /// Up to `budget` lines in order, after one burst for anything dropped
+    /// since the last drain.
+    fn drain(&mut self, budget: usize) -> Vec<Delivery> {
+        let mut out = Vec::new();
+        if self.dropped > 0 {
+            out.push(Delivery::Burst(std::mem::take(&mut self.dropped)));
+        }
+        let take = budget.min(self.queue.len());
+        out.extend(self.queue.drain(..take).map(Delivery::Line));
+        out
+    }
+}
+
+/// Managed state: subscribers by window label.
+#[derive(Default)]
+pub(crate) struct SidecarLogState {
+    subscribers: Mutex<HashMap<String, Outbox>>,
+    pumping: AtomicBool,
+}
+
+/// `line` without its line ending, cut to [`MAX_LINE_BYTES`].
+fn clip(line: &str) -> &str {
+    let line = line.trim_end_matches(['\n', '\r']);
+    if line.len() <= MAX_LINE_BYTES {
+        return line;
+    }
+    let mut end = MAX_LINE_BYTES;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Queue an already redacted line for every subscriber. Costs one lock when
+/// there are none.
+fn publish(app: &AppHandle, stream: Stream, redacted: &str) {
+    let Some(state) = app.try_state::<SidecarLogState>() else {
+        return;
+    };
+    let mut subscribers = state.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+    if subscribers.is_empty() {
+        return;
+    }
+    let line = LogLine {
+        stream,
+        line: clip(redacted).to_string(),
+        ts: now_iso8601(),
+    };
+    for outbox in subscribers.values_mut() {
+        outbox.push(line.clone());
+    }
+}
+
+/// Redact one line of output and append it to the log file; returns the
+/// redacted text for the subscribers.
+fn write_line(redactor: &SecretRedactor, raw: &[u8], file: &mut impl Write) -> String {
+    let redacted = redactor.redact(&String::from_utf8_lossy(raw)).into_owned();
+    let _ = file.write_all(redacted.as_bytes());
+    redacted
+}
+
+/// Copy one of the sidecar's output streams into the log file line by
+/// line, publishing each. Returns when the sidecar closes the stream.
+pub(crate) fn forward(app: &AppHandle, stream: Stream, source: impl Read + Send + 'static, mut file: File) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {
+                    // Fetched per line: the vault may change while the sidecar runs.
+                    let line = write_line(&secret_redactor(&app), &buf, &mut file);
+                    publish(&app, stream, &line);
+                }
+                Err(err) => {
+                    log_event(&app, "WARN", "sidecar_output_read_failed", &[("error", &err.to_string())]);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Empty every outbox a few times a second until nobody is subscribed.
+fn start_pump(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(FLUSH_INTERVAL);
+        let state = app.state::<SidecarLogState>();
+        let batches: Vec<(String, Vec<Delivery>)> = {
+            let mut subscribers = state.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+            if subscribers.is_empty() {
+                // Under the lock, so a concurrent subscribe starts a new pump.
+                state.pumping.store(false, Ordering::Relaxed);
+                return;
+            }
+            subscribers
+                .iter_mut()
+                .map(|(label, outbox)| (label.clone(), outbox.drain(LINES_PER_FLUSH)))
+                .collect()
+        };
+        for (label, deliveries) in batches {
+            for delivery in deliveries {
+                let sent = match delivery {
+                    Delivery::Burst(dropped) => app.emit_to(
+                        label.as_str(),
+                        BURST_EVENT,
+                        LogBurst {
+                            dropped,
+                            ts: now_iso8601(),
+                        },
+                    ),
+                    Delivery::Line(line) => app.emit_to(label.as_str(), LINE_EVENT, line),
+                };
+                if let Err(err) = sent {
+                    log_event(&app, "DEBUG", "sidecar_log_emit_failed", &[("window", &label), ("error", &err.to_string())]);
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn subscribe(app: &AppHandle, label: &str) {
+    let state = app.state::<SidecarLogState>();
+    let mut subscribers = state.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+    subscribers
+        .entry(label.to_string())
+        .or_insert_with(|| Outbox::new(OUTBOX_CAPACITY));
+    if !state.pumping.swap(true, Ordering::Relaxed) {
+        start_pump(app);
+    }
+}
+
+/// Also called when the window is destroyed.
+pub(crate) fn unsubscribe(app: &AppHandle, label: &str) {
+    if let Some(state) = app.try_state::<SidecarLogState>() {
+        state.subscribers.lock().unwrap_or_else(|e| e.into_inner()).remove(label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{clip, write_line, Delivery, LogLine, Outbox, Stream, MAX_LINE_BYTES};
+    use crate::logging::SecretRedactor;
+
+    /// Numbered lines, as a steady sidecar would print them.
+    fn source(count: usize) -> impl Iterator<Item = LogLine> {
+        (0..count).map(|n| LogLine {
+            stream: if n % 2 == 0 { Stream::Stdout } else { Stream::Stderr },
+            line: format!("line {n}"),
+            ts: "2026-10-18T00:00:00.000Z".to_string(),
+        })
+    }
+
+    fn lines(deliveries: &[Delivery]) -> Vec<&str> {
+        deliveries
+            .iter()
+            .filter_map(|delivery| match delivery {
+                Delivery::Line(line) => Some(line.line.as_str()),
+                Delivery::Burst(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_subscriber_keeping_up_gets_every_line_in_order() {
+        let mut outbox = Outbox::new(10);
+        source(8).for_each(|line| outbox.push(line));
+        let first = outbox.drain(5);
+        assert_eq!(lines(&first), ["line 0", "line 1", "line 2", "line 3", "line 4"]);
+        assert_eq!(first.len(), 5, "no burst");
+        assert_eq!(lines(&outbox.drain(5)), ["line 5", "line 6", "line 7"]);
+        assert!(outbox.drain(5).is_empty());
+    }
+
+    #[test]
+    fn falling_behind_coalesces_into_one_burst_then_the_newest_lines() {
+        let mut outbox = Outbox::new(100);
+        source(1000).for_each(|line| outbox.push(line));
+        let deliveries = outbox.drain(40);
+        assert_eq!(deliveries[0], Delivery::Burst(900));
+        assert_eq!(lines(&deliveries).first(), Some(&"line 900"));
+        assert_eq!(deliveries.len(), 41);
+
+        // The burst is reported once; the rest of the backlog follows.
+        let rest = outbox.drain(100);
+        assert!(rest.iter().all(|delivery| matches!(delivery, Delivery::Line(_))));
+        assert_eq!(lines(&rest).len(), 60);
+        assert_eq!(lines(&rest).last(), Some(&"line 999"));
+
+        // A second overflow is counted from zero.
+        source(105).for_each(|line| outbox.push(line));
+        assert_eq!(outbox.drain(0), [Delivery::Burst(5)]);
+    }
+
+    #[test]
+    fn lines_lose_their_ending_and_are_clipped_on_a_char_boundary() {
+        assert_eq!(clip("ready on 46123\r\n"), "ready on 46123");
+        let long = "é".repeat(MAX_LINE_BYTES);
+        let clipped = clip(&long);
+        assert!(clipped.len() <= MAX_LINE_BYTES);
+        assert!(clipped.len() > MAX_LINE_BYTES - 2);
+        assert!(clipped.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn the_log_file_gets_the_redacted_line() {
+        let secrets = HashMap::from([("GROQ_API_KEY".to_string(), "gsk_live_abcdef123456".to_string())]);
+        let redactor = SecretRedactor::new(&secrets);
+        let mut file = Vec::new();
+        let line = write_line(&redactor, b"calling groq with gsk_live_abcdef123456\n", &mut file);
+        let written = String::from_utf8(file).unwrap();
+        assert_eq!(written, line);
+        assert!(!written.contains("gsk_live_abcdef123456"), "{written}");
+        assert!(written.ends_with('\n'));
+    }
+}
@@ -0,0 +1,232 @@
+//! User-supplied Node flags and environment for the sidecar, e.g.
+//! `--max-old-space-size=4096` for large local datasets. Only a fixed list of
+//! tuning and diagnostic flags is accepted, and the extra environment
+//! cannot shadow vault secrets or the variables the shell itself passes to
+//! the sidecar. Both prefs are re-checked when read, since the prefs file can
+//! be edited by hand.
+
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::logging::log_event;
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_SIDECAR_NODE_ARGS: &str = "sidecarNodeArgs";
+pub(crate) const PREF_SIDECAR_EXTRA_ENV: &str = "sidecarExtraEnv";
+const MAX_NODE_ARGS: usize = 32;
+const MAX_EXTRA_ENV: usize = 64;
+const MAX_ARG_LEN: usize = 1024;
+
+/// The only flags accepted: V8 and runtime tuning plus diagnostics. Anything
+/// else (`--eval`, `--require`, `--import`, `--`, a script path, combined
+/// short flags like `-pe`) could run code other than the sidecar script.
+/// Flags taking a value must use the `--flag=value` form.
+const VALUE_FLAGS: [&str; 9] = [
+    "--max-old-space-size",
+    "--max-semi-space-size",
+    "--stack-size",
+    "--stack-trace-limit",
+    "--max-http-header-size",
+    "--unhandled-rejections",
+    "--dns-result-order",
+    "--disable-warning",
+    "--heapsnapshot-near-heap-limit",
+];
+const SWITCH_FLAGS: [&str; 10] = [
+    "--trace-warnings",
+    "--trace-deprecation",
+    "--trace-uncaught",
+    "--no-deprecation",
+    "--no-warnings",
+    "--enable-source-maps",
+    "--expose-gc",
+    "--use-openssl-ca",
+    "--use-bundled-ca",
+    "--report-on-fatalerror",
+];
+/// Debugger flags, allowed in debug builds only; each may take `=host:port`.
+const INSPECT_FLAGS: [&str; 4] = ["--inspect", "--inspect-brk", "--inspect-wait", "--inspect-port"];
+/// Variables the shell sets itself.
+const RESERVED_ENV_PREFIXES: [&str; 1] = ["LOCAL_API_"];
+const RESERVED_ENV: [&str; 2] = ["NODE_EXTRA_CA_CERTS", "CONVEX_URL"];
+
+fn check_flag(arg: &str, allow_inspect: bool) -> Result<(), String> {
+    if arg.len() > MAX_ARG_LEN {
+        return Err(format!("flags are limited to {MAX_ARG_LEN} characters"));
+    }
+    if !arg.starts_with('-') {
+        return Err(format!("{arg:?} is not a flag; positional arguments are not allowed"));
+    }
+    if !arg.starts_with("--") || arg == "--" {
+        return Err(format!("{arg} is not allowed; use a long --flag"));
+    }
+    let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (arg, None),
+    };
+    if INSPECT_FLAGS.contains(&name) {
+        if !allow_inspect {
+            return Err(format!("{name} is only allowed in debug builds"));
+        }
+        return Ok(());
+    }
+    if VALUE_FLAGS.contains(&name) {
+        return match value {
+            Some(value) if !value.is_empty() => Ok(()),
+            _ => Err(format!("{name} needs a value, as {name}=value")),
+        };
+    }
+    if SWITCH_FLAGS.contains(&name) {
+        return match value {
+            None => Ok(()),
+            Some(_) => Err(format!("{name} does not take a value")),
+        };
+    }
+    Err(format!("{name} is not allowed"))
+}
+
+pub(crate) fn validate_node_args(value: &Value, allow_inspect: bool) -> Result<(), String> {
+    let invalid = |reason: String| format!("Runtime pref {PREF_SIDECAR_NODE_ARGS}: {reason}");
+    let args = value
+        .as_array()
+        .ok_or_else(|| invalid("must be a list of strings".to_string()))?;
+    if args.len() > MAX_NODE_ARGS {
+        return Err(invalid(format!("at most {MAX_NODE_ARGS} flags")));
+    }
+    for arg in args {
+        let arg = arg.as_str().ok_or_else(|| invalid("entries must be strings".to_string()))?;
+        check_flag(arg, allow_inspect).map_err(invalid)?;
+    }
+    Ok(())
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(crate) fn validate_extra_env(value: &Value, secret_keys: &[&str], allow_inspect: bool) -> Result<(), String> {
+    let invalid = |reason: String| format!("Runtime pref {PREF_SIDECAR_EXTRA_ENV}: {reason}");
+    let map = value
+        .as_object()
+        .ok_or_else(|| invalid("must be an object of string values".to_string()))?;
+    if map.len() > MAX_EXTRA_ENV {
+        return Err(invalid(format!("at most {MAX_EXTRA_ENV} variables")));
+    }
+    for (name, value) in map {
+        if !is_env_name(name) {
+            return Err(invalid(format!("{name:?} is not a valid variable name")));
+        }
+        let upper = name.to_ascii_uppercase();
+        if secret_keys.iter().any(|key| key.eq_ignore_ascii_case(name)) {
+            return Err(invalid(format!("{name} is a secret key; set it in the vault instead")));
+        }
+        if RESERVED_ENV.contains(&upper.as_str()) || RESERVED_ENV_PREFIXES.iter().any(|prefix| upper.starts_with(prefix)) {
+            return Err(invalid(format!("{name} is set by the app and cannot be overridden")));
+        }
+        let value = value
+            .as_str()
+            .ok_or_else(|| invalid(format!("{name} must be a string")))?;
+        // NODE_OPTIONS is another way in for the same flags.
+        if upper == "NODE_OPTIONS" {
+            for flag in value.split_whitespace() {
+                check_flag(flag, allow_inspect).map_err(|reason| invalid(format!("NODE_OPTIONS {reason}")))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn pref(app: &AppHandle, key: &str) -> Option<Value> {
+    app.try_state::<RuntimePrefs>().and_then(|prefs| prefs.get(key))
+}
+
+/// Flags to insert before the script path; empty when unset or invalid.
+pub(crate) fn node_args(app: &AppHandle) -> Vec<String> {
+    let Some(value) = pref(app, PREF_SIDECAR_NODE_ARGS) else {
+        return Vec::new();
+    };
+    if let Err(err) = validate_node_args(&value, cfg!(debug_assertions)) {
+        log_event(app, "WARN", "sidecar_node_args_ignored", &[("error", &err)]);
+        return Vec::new();
+    }
+    value
+        .as_array()
+        .map(|args| args.iter().filter_map(|arg| arg.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// Variables to merge into the sidecar's environment; empty when unset or
+/// invalid.
+pub(crate) fn extra_env(app: &AppHandle, secret_keys: &[&str]) -> Vec<(String, String)> {
+    let Some(value) = pref(app, PREF_SIDECAR_EXTRA_ENV) else {
+        return Vec::new();
+    };
+    if let Err(err) = validate_extra_env(&value, secret_keys, cfg!(debug_assertions)) {
+        log_event(app, "WARN", "sidecar_extra_env_ignored", &[("error", &err)]);
+        return Vec::new();
+    }
+    value
+        .as_object()
+        .map(Map::iter)
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{validate_extra_env, validate_node_args};
+
+    const SECRETS: [&str; 2] = ["GROQ_API_KEY", "FRED_API_KEY"];
+
+    #[test]
+    fn node_args_accept_tuning_flags_and_refuse_code_loading() {
+        assert!(validate_node_args(&json!(["--max-old-space-size=4096", "--trace-warnings"]), false).is_ok());
+        assert!(validate_node_args(&json!([]), false).is_ok());
+        for denied in ["-e", "--eval=process.exit()", "-p", "-pe", "--require=./x.js", "--import", "--loader=x", "--", "--openssl-config=x"] {
+            let err = validate_node_args(&json!([denied]), true).unwrap_err();
+            assert!(err.contains("not allowed"), "{denied}: {err}");
+        }
+        assert!(validate_node_args(&json!(["--", "evil.mjs"]), false).is_err());
+        assert!(validate_node_args(&json!(["evil.mjs"]), false).unwrap_err().contains("positional"));
+        assert!(validate_node_args(&json!(["--max-old-space-size"]), false).unwrap_err().contains("needs a value"));
+        assert!(validate_node_args(&json!(["--trace-warnings=1"]), false).is_err());
+        assert!(validate_node_args(&json!("--max-old-space-size=4096"), false).is_err());
+        assert!(validate_node_args(&json!([4096]), false).is_err());
+    }
+
+    #[test]
+    fn inspect_only_in_debug_builds() {
+        for flag in ["--inspect", "--inspect=127.0.0.1:9229", "--inspect-brk", "--inspect-port=9230"] {
+            assert!(validate_node_args(&json!([flag]), true).is_ok(), "{flag}");
+            assert!(validate_node_args(&json!([flag]), false).unwrap_err().contains("debug builds"), "{flag}");
+        }
+        // Only the --inspect family, not anything sharing the prefix.
+        assert!(validate_node_args(&json!(["--inspector"]), true).unwrap_err().contains("not allowed"));
+    }
+
+    #[test]
+    fn extra_env_cannot_shadow_secrets_or_app_variables() {
+        let tuning = json!({"UV_THREADPOOL_SIZE": "8", "NODE_OPTIONS": "--max-old-space-size=4096"});
+        assert!(validate_extra_env(&tuning, &SECRETS, false).is_ok());
+        let cases = [
+            (json!({"GROQ_API_KEY": "x"}), "vault"),
+            (json!({"groq_api_key": "x"}), "vault"),
+            (json!({"LOCAL_API_TOKEN": "x"}), "cannot be overridden"),
+            (json!({"NODE_EXTRA_CA_CERTS": "/tmp/ca.pem"}), "cannot be overridden"),
+            (json!({"NODE_OPTIONS": "--require ./x.js"}), "NODE_OPTIONS --require is not allowed"),
+            (json!({"BAD NAME": "x"}), "not a valid variable name"),
+            (json!({"PORT": 8080}), "must be a string"),
+            (json!(["A=1"]), "must be an object"),
+        ];
+        for (value, expected) in cases {
+            let err = validate_extra_env(&value, &SECRETS, false).unwrap_err();
+            assert!(err.contains(expected), "{value}: {err}");
+        }
+    }
+}
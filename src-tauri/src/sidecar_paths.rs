@@ -0,0 +1,172 @@
+//! Where the sidecar script and its `api/` modules are looked up. Bundlers
+//! sometimes lift `../api` into `<resources>/_up_/api`, so the resource root
+//! is probed in a fixed order and every candidate is kept so a wrong pick can
+//! be diagnosed from the log or `get_sidecar_paths` instead of guessed at.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+pub(crate) const SIDECAR_SCRIPT: &str = "sidecar/local-api-server.mjs";
+const LIFTED_ROOT: &str = "_up_";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RootCandidate {
+    /// `direct`, `lifted` or `dev_checkout`.
+    kind: &'static str,
+    root: PathBuf,
+    /// Whether `root/api` is a directory.
+    has_api: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LocalApiPaths {
+    pub(crate) script: PathBuf,
+    pub(crate) resource_root: PathBuf,
+    /// Kind of the candidate that was chosen, or `fallback` when none had
+    /// an `api/` directory and the resource dir was used as is.
+    pub(crate) selected: &'static str,
+    pub(crate) candidates: Vec<RootCandidate>,
+}
+
+fn candidate(kind: &'static str, root: PathBuf) -> RootCandidate {
+    RootCandidate {
+        has_api: root.join("api").is_dir(),
+        kind,
+        root,
+    }
+}
+
+/// Packaged layout: `<resources>/api`, then `<resources>/_up_/api`.
+pub(crate) fn select_packaged(resource_dir: &Path) -> LocalApiPaths {
+    let candidates = vec![
+        candidate("direct", resource_dir.to_path_buf()),
+        candidate("lifted", resource_dir.join(LIFTED_ROOT)),
+    ];
+    let chosen = candidates.iter().find(|candidate| candidate.has_api);
+    LocalApiPaths {
+        script: resource_dir.join(SIDECAR_SCRIPT),
+        resource_root: chosen.map_or_else(|| resource_dir.to_path_buf(), |candidate| candidate.root.clone()),
+        selected: chosen.map_or("fallback", |candidate| candidate.kind),
+        candidates,
+    }
+}
+
+/// Debug builds run against the source tree: the script next to the crate,
+/// `api/` in the repository root above it.
+pub(crate) fn select_dev(manifest_dir: &Path) -> LocalApiPaths {
+    let root = manifest_dir.parent().map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let dev = candidate("dev_checkout", root.clone());
+    LocalApiPaths {
+        script: manifest_dir.join(SIDECAR_SCRIPT),
+        resource_root: root,
+        selected: if dev.has_api { dev.kind } else { "fallback" },
+        candidates: vec![dev],
+    }
+}
+
+impl LocalApiPaths {
+    /// One-line description of every probe for desktop.log.
+    pub(crate) fn describe_candidates(&self) -> String {
+        self.candidates
+            .iter()
+            .map(|candidate| {
+                format!(
+                    "{}:{}:{}",
+                    candidate.kind,
+                    candidate.root.display(),
+                    if candidate.has_api { "api" } else { "no_api" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Fail before Node is spawned when the chosen root has no `api/`;
+    /// otherwise the sidecar starts and dies on its first module import.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.resource_root.join("api").is_dir() {
+            return Ok(());
+        }
+        Err(format!(
+            "Packaging error: no api/ directory under the sidecar resource root {} (probed {})",
+            self.resource_root.display(),
+            self.describe_candidates()
+        ))
+    }
+}
+
+/// `get_sidecar_paths` payload.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SidecarPathsReport {
+    #[serde(flatten)]
+    pub(crate) paths: LocalApiPaths,
+    pub(crate) script_exists: bool,
+    pub(crate) node_binary: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{select_dev, select_packaged};
+
+    fn layout(name: &str, dirs: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("wm-sidecar-paths-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for dir in dirs {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn prefers_direct_api_over_lifted_root() {
+        let resources = layout("both", &["api", "_up_/api"]);
+        let paths = select_packaged(&resources);
+        assert_eq!(paths.selected, "direct");
+        assert_eq!(paths.resource_root, resources);
+        assert!(paths.candidates.iter().all(|candidate| candidate.has_api));
+        assert!(paths.validate().is_ok());
+        let _ = fs::remove_dir_all(&resources);
+    }
+
+    #[test]
+    fn falls_back_to_lifted_root() {
+        let resources = layout("lifted", &["_up_/api", "sidecar"]);
+        let paths = select_packaged(&resources);
+        assert_eq!(paths.selected, "lifted");
+        assert_eq!(paths.resource_root, resources.join("_up_"));
+        assert_eq!(paths.script, resources.join("sidecar/local-api-server.mjs"));
+        assert!(paths.validate().is_ok());
+        let _ = fs::remove_dir_all(&resources);
+    }
+
+    #[test]
+    fn missing_api_is_a_packaging_error() {
+        // `_up_` without `api/` inside must not be chosen.
+        let resources = layout("missing", &["_up_/src"]);
+        let paths = select_packaged(&resources);
+        assert_eq!(paths.selected, "fallback");
+        assert_eq!(paths.resource_root, resources);
+        let err = paths.validate().unwrap_err();
+        assert!(err.starts_with("Packaging error"), "{err}");
+        assert!(err.contains("lifted:") && err.contains(":no_api"), "{err}");
+        let _ = fs::remove_dir_all(&resources);
+    }
+
+    #[test]
+    fn dev_checkout_uses_repository_root() {
+        let repo = layout("dev", &["api", "src-tauri/sidecar"]);
+        let paths = select_dev(&repo.join("src-tauri"));
+        assert_eq!(paths.selected, "dev_checkout");
+        assert_eq!(paths.resource_root, repo);
+        assert_eq!(paths.script, repo.join("src-tauri/sidecar/local-api-server.mjs"));
+        let _ = fs::remove_dir_all(&repo);
+    }
+}
@@ -0,0 +1,325 @@
+//! When each data source last answered. The sidecar counts outcomes per
+//! handler and serves them on `/api/local-source-health`; the shell polls
+//! that with the local API token, keeps the merged picture here and in the
+//! persistent cache (so it survives restarts, when the sidecar's counters
+//! start over), and fires `data-source-degraded` when a source reaches
+//! [`DEGRADED_AFTER_FAILURES`] failures in a row. A quiet panel can then be
+//! told apart from a feed that has been down for hours.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::logging::{log_event, now_ms};
+use crate::maintenance::TaskSpec;
+use crate::{get_from_local_api, PersistentCache};
+
+const CACHE_KEY: &str = "data-source-health";
+const DEGRADED_EVENT: &str = "data-source-degraded";
+const DEGRADED_AFTER_FAILURES: u32 = 3;
+/// A source that has failed since its last success, and hasn't succeeded
+/// for this long, is stale even below the failure threshold.
+const STALE_AFTER: Duration = Duration::from_secs(6 * 3600);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One source as the sidecar reports it. Times are unix milliseconds.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub(crate) struct SourceRecord {
+    pub(crate) source: String,
+    #[serde(alias = "lastSuccess", default)]
+    pub(crate) last_success: Option<u64>,
+    #[serde(alias = "lastError", default)]
+    pub(crate) last_error: Option<String>,
+    #[serde(alias = "lastErrorAt", default)]
+    pub(crate) last_error_at: Option<u64>,
+    #[serde(alias = "consecutiveFailures", default)]
+    pub(crate) consecutive_failures: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SourceStatus {
+    Healthy,
+    /// Failing, but not yet for long or often enough to alert.
+    Failing,
+    Stale,
+    Degraded,
+}
+
+/// `get_data_source_health` entry.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct SourceHealth {
+    #[serde(flatten)]
+    record: SourceRecord,
+    status: SourceStatus,
+    /// Seconds since the last success; `None` if there never was one.
+    since_success_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SidecarReport {
+    sources: Vec<SourceRecord>,
+}
+
+/// Managed state: the merged records, streaks carried over sidecar restarts
+/// and which sources already alerted.
+#[derive(Default)]
+pub(crate) struct SourceHealthState {
+    records: Mutex<BTreeMap<String, SourceRecord>>,
+    carried: Mutex<BTreeMap<String, u32>>,
+    degraded: Mutex<BTreeSet<String>>,
+}
+
+pub(crate) fn status(record: &SourceRecord, now: u64) -> SourceStatus {
+    if record.consecutive_failures >= DEGRADED_AFTER_FAILURES {
+        return SourceStatus::Degraded;
+    }
+    if record.consecutive_failures == 0 {
+        return SourceStatus::Healthy;
+    }
+    let stale_ms = STALE_AFTER.as_millis() as u64;
+    match record.last_success {
+        Some(at) if now.saturating_sub(at) <= stale_ms => SourceStatus::Failing,
+        _ => SourceStatus::Stale,
+    }
+}
+
+/// Fold a sidecar report into what we knew. A restarted sidecar forgets
+/// its history, so the newest timestamps win; the failure streak is the
+/// sidecar's unless it has no news for the source since our last record.
+/// A sidecar that has only failed since it started continues the streak
+/// from before the restart: `carried` holds that earlier part per source.
+pub(crate) fn merge(
+    known: &mut BTreeMap<String, SourceRecord>,
+    carried: &mut BTreeMap<String, u32>,
+    report: Vec<SourceRecord>,
+) {
+    for incoming in report {
+        let Some(current) = known.get_mut(&incoming.source) else {
+            known.insert(incoming.source.clone(), incoming);
+            continue;
+        };
+        let newest = |a: Option<u64>, b: Option<u64>| a.max(b);
+        let latest_known = newest(current.last_success, current.last_error_at);
+        let latest_incoming = newest(incoming.last_success, incoming.last_error_at);
+        if latest_incoming < latest_known {
+            continue;
+        }
+        if incoming.last_error_at >= current.last_error_at && incoming.last_error.is_some() {
+            current.last_error = incoming.last_error;
+            current.last_error_at = incoming.last_error_at;
+        }
+        current.last_success = newest(current.last_success, incoming.last_success);
+        let restarted_failing =
+            incoming.last_success.is_none() && current.last_success.is_some_and(|at| Some(at) < incoming.last_error_at);
+        current.consecutive_failures = if incoming.last_success >= incoming.last_error_at {
+            carried.remove(&incoming.source);
+            0
+        } else if restarted_failing {
+            let base = carried.entry(incoming.source.clone()).or_insert(current.consecutive_failures);
+            // The sidecar's count went down without a success: it restarted again.
+            if incoming.consecutive_failures < current.consecutive_failures.saturating_sub(*base) {
+                *base = current.consecutive_failures;
+            }
+            base.saturating_add(incoming.consecutive_failures)
+        } else {
+            carried.remove(&incoming.source);
+            incoming.consecutive_failures
+        };
+    }
+}
+
+/// Sources that are degraded now but weren't at the last check. `alerted`
+/// is updated so each source alerts once per outage.
+pub(crate) fn newly_degraded(alerted: &mut BTreeSet<String>, records: &BTreeMap<String, SourceRecord>, now: u64) -> Vec<String> {
+    let degraded: BTreeSet<String> = records
+        .values()
+        .filter(|record| status(record, now) == SourceStatus::Degraded)
+        .map(|record| record.source.clone())
+        .collect();
+    let crossed = degraded.difference(alerted).cloned().collect();
+    *alerted = degraded;
+    crossed
+}
+
+async fn query(app: &AppHandle) -> Option<Vec<SourceRecord>> {
+    get_from_local_api::<SidecarReport>(app, "/api/local-source-health", REQUEST_TIMEOUT)
+        .await
+        .map(|report| report.sources)
+}
+
+/// Poll the sidecar once, record the result and alert on new outages.
+pub(crate) async fn refresh(app: &AppHandle) {
+    let Some(report) = query(app).await else {
+        return;
+    };
+    let state = app.state::<SourceHealthState>();
+    let now = now_ms();
+    let (snapshot, crossed) = {
+        let mut records = state.records.lock().unwrap_or_else(|e| e.into_inner());
+        let mut carried = state.carried.lock().unwrap_or_else(|e| e.into_inner());
+        merge(&mut records, &mut carried, report);
+        let mut alerted = state.degraded.lock().unwrap_or_else(|e| e.into_inner());
+        let crossed = newly_degraded(&mut alerted, &records, now);
+        (records.values().cloned().collect::<Vec<_>>(), crossed)
+    };
+    if let (Some(cache), Ok(value)) = (app.try_state::<PersistentCache>(), serde_json::to_value(&snapshot)) {
+        // Written out with the rest of the cache on exit.
+        cache.insert(CACHE_KEY.to_string(), value);
+    }
+    for source in crossed {
+        let Some(record) = snapshot.iter().find(|record| record.source == source) else {
+            continue;
+        };
+        log_event(
+            app,
+            "WARN",
+            "data_source_degraded",
+            &[
+                ("source", &source),
+                ("consecutive_failures", &record.consecutive_failures.to_string()),
+            ],
+        );
+        let _ = app.emit(DEGRADED_EVENT, health(record, now));
+    }
+}
+
+fn health(record: &SourceRecord, now: u64) -> SourceHealth {
+    SourceHealth {
+        status: status(record, now),
+        since_success_secs: record.last_success.map(|at| now.saturating_sub(at) / 1000),
+        record: record.clone(),
+    }
+}
+
+/// Every source seen so far, by id.
+pub(crate) fn report(app: &AppHandle) -> Vec<SourceHealth> {
+    let Some(state) = app.try_state::<SourceHealthState>() else {
+        return Vec::new();
+    };
+    let now = now_ms();
+    let records = state.records.lock().unwrap_or_else(|e| e.into_inner());
+    records.values().map(|record| health(record, now)).collect()
+}
+
+/// Restore the last snapshot from the persistent cache. Sources that were
+/// already degraded don't alert again for the same outage.
+pub(crate) fn restore(app: &AppHandle) {
+    let Some(saved) = app
+        .try_state::<PersistentCache>()
+        .and_then(|cache| cache.get(CACHE_KEY))
+        .and_then(|value: Value| serde_json::from_value::<Vec<SourceRecord>>(value).ok())
+    else {
+        return;
+    };
+    let state = app.state::<SourceHealthState>();
+    let mut records = state.records.lock().unwrap_or_else(|e| e.into_inner());
+    records.extend(saved.into_iter().map(|record| (record.source.clone(), record)));
+    newly_degraded(&mut state.degraded.lock().unwrap_or_else(|e| e.into_inner()), &records, now_ms());
+}
+
+/// Poll the sidecar every minute, after [`restore`] at startup.
+pub(crate) const MAINTENANCE: TaskSpec = TaskSpec::sidecar_poll("source_health", |app| {
+    Box::pin(async move {
+        refresh(&app).await;
+        Ok(())
+    })
+});
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::{merge, newly_degraded, status, SourceRecord, SourceStatus};
+
+    const HOUR_MS: u64 = 3600 * 1000;
+    const NOW: u64 = 100 * HOUR_MS;
+
+    fn record(last_success: Option<u64>, last_error_at: Option<u64>, failures: u32) -> SourceRecord {
+        SourceRecord {
+            source: "fred-data".to_string(),
+            last_success,
+            last_error: last_error_at.map(|_| "HTTP 502".to_string()),
+            last_error_at,
+            consecutive_failures: failures,
+        }
+    }
+
+    fn known(record: SourceRecord) -> BTreeMap<String, SourceRecord> {
+        BTreeMap::from([(record.source.clone(), record)])
+    }
+
+    #[test]
+    fn status_follows_the_failure_streak_and_age() {
+        assert_eq!(status(&record(Some(NOW), None, 0), NOW), SourceStatus::Healthy);
+        assert_eq!(status(&record(Some(NOW - HOUR_MS), Some(NOW), 1), NOW), SourceStatus::Failing);
+        assert_eq!(status(&record(Some(NOW - 7 * HOUR_MS), Some(NOW), 1), NOW), SourceStatus::Stale);
+        assert_eq!(status(&record(None, Some(NOW), 2), NOW), SourceStatus::Stale);
+        assert_eq!(status(&record(Some(NOW - HOUR_MS), Some(NOW), 3), NOW), SourceStatus::Degraded);
+    }
+
+    #[test]
+    fn degraded_alerts_once_per_outage() {
+        let mut alerted = BTreeSet::new();
+        let failing = known(record(Some(NOW - HOUR_MS), Some(NOW), 3));
+        assert_eq!(newly_degraded(&mut alerted, &failing, NOW), ["fred-data"]);
+        assert!(newly_degraded(&mut alerted, &failing, NOW).is_empty());
+        let recovered = known(record(Some(NOW), Some(NOW - 1), 0));
+        assert!(newly_degraded(&mut alerted, &recovered, NOW).is_empty());
+        assert_eq!(newly_degraded(&mut alerted, &failing, NOW), ["fred-data"]);
+    }
+
+    #[test]
+    fn merge_keeps_history_across_a_sidecar_restart() {
+        let mut carried = BTreeMap::new();
+        let mut records = known(record(Some(NOW - 2 * HOUR_MS), Some(NOW - HOUR_MS), 2));
+        // The new sidecar has only failed, once.
+        merge(&mut records, &mut carried, vec![record(None, Some(NOW), 1)]);
+        let merged = &records["fred-data"];
+        assert_eq!(merged.last_success, Some(NOW - 2 * HOUR_MS));
+        assert_eq!(merged.last_error_at, Some(NOW));
+        assert_eq!(merged.consecutive_failures, 3, "two before the restart and one since");
+
+        merge(&mut records, &mut carried, vec![record(None, Some(NOW + 1), 2)]);
+        assert_eq!(records["fred-data"].consecutive_failures, 4, "each poll counts only the new failures");
+
+        merge(&mut records, &mut carried, vec![record(Some(NOW + 2), Some(NOW + 1), 0)]);
+        assert_eq!(records["fred-data"].consecutive_failures, 0);
+        assert_eq!(records["fred-data"].last_success, Some(NOW + 2));
+        assert!(carried.is_empty());
+    }
+
+    #[test]
+    fn a_second_restart_while_failing_keeps_adding_up() {
+        let mut carried = BTreeMap::new();
+        let mut records = known(record(Some(NOW - 2 * HOUR_MS), Some(NOW - HOUR_MS), 2));
+        merge(&mut records, &mut carried, vec![record(None, Some(NOW), 3)]);
+        assert_eq!(records["fred-data"].consecutive_failures, 5);
+        // Restarted again: its count starts over.
+        merge(&mut records, &mut carried, vec![record(None, Some(NOW + 1), 1)]);
+        assert_eq!(records["fred-data"].consecutive_failures, 6);
+    }
+
+    #[test]
+    fn an_older_report_does_not_overwrite_newer_news() {
+        let mut records = known(record(Some(NOW), None, 0));
+        merge(&mut records, &mut BTreeMap::new(), vec![record(None, Some(NOW - HOUR_MS), 5)]);
+        assert_eq!(records["fred-data"], record(Some(NOW), None, 0));
+    }
+
+    #[test]
+    fn parses_the_sidecar_payload() {
+        let parsed: SourceRecord = serde_json::from_str(
+            r#"{"source":"fred-data","lastSuccess":1000,"lastError":"HTTP 502","lastErrorAt":2000,"consecutiveFailures":1}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed, record(Some(1000), Some(2000), 1));
+        let json = serde_json::to_value(&parsed).unwrap();
+        assert_eq!(json["last_success"], 1000);
+        assert_eq!(json["consecutive_failures"], 1);
+    }
+}
@@ -0,0 +1,210 @@
+//! Per-launch startup timings. `log_startup_stage` opens a stage and closes
+//! the one before it; the sidecar reaching "ready" (or failing) closes the
+//! last. Time-to-ready is taken at `RunEvent::Ready`, and once both that and
+//! the sidecar boot have happened a one-line summary goes to desktop.log.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+#[derive(Debug)]
+struct Span {
+    name: String,
+    begin: Instant,
+    end: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Timeline {
+    spans: Vec<Span>,
+    ready_at: Option<Instant>,
+    sidecar_settled: bool,
+    summary_logged: bool,
+}
+
+impl Timeline {
+    fn close_open(&mut self, now: Instant) {
+        if let Some(span) = self.spans.last_mut().filter(|span| span.end.is_none()) {
+            span.end = Some(now.max(span.begin));
+        }
+    }
+}
+
+pub(crate) struct StartupProfile {
+    /// Process start, or as close to it as `main` gets.
+    origin: Instant,
+    timeline: Mutex<Timeline>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct StageTiming {
+    name: String,
+    /// Offset from process start.
+    start_ms: u64,
+    /// `None` while the stage is still running.
+    duration_ms: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct StartupProfileReport {
+    stages: Vec<StageTiming>,
+    total_to_ready_ms: Option<u64>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+impl StartupProfile {
+    pub(crate) fn new(origin: Instant) -> Self {
+        StartupProfile {
+            origin,
+            timeline: Mutex::new(Timeline::default()),
+        }
+    }
+
+    fn timeline(&self) -> std::sync::MutexGuard<'_, Timeline> {
+        self.timeline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start `name`, ending whichever stage was running.
+    pub(crate) fn begin(&self, name: &str, now: Instant) {
+        let mut timeline = self.timeline();
+        timeline.close_open(now);
+        timeline.spans.push(Span {
+            name: name.to_string(),
+            begin: now,
+            end: None,
+        });
+    }
+
+    /// End the running stage without starting another.
+    pub(crate) fn end_current(&self, now: Instant) {
+        self.timeline().close_open(now);
+    }
+
+    /// The sidecar boot finished, successfully or not.
+    pub(crate) fn settle_sidecar(&self, now: Instant) {
+        let mut timeline = self.timeline();
+        timeline.close_open(now);
+        timeline.sidecar_settled = true;
+    }
+
+    pub(crate) fn mark_ready(&self, now: Instant) {
+        self.timeline().ready_at.get_or_insert(now);
+    }
+
+    pub(crate) fn report(&self) -> StartupProfileReport {
+        let timeline = self.timeline();
+        StartupProfileReport {
+            stages: timeline
+                .spans
+                .iter()
+                .map(|span| StageTiming {
+                    name: span.name.clone(),
+                    start_ms: millis(span.begin.saturating_duration_since(self.origin)),
+                    duration_ms: span.end.map(|end| millis(end.saturating_duration_since(span.begin))),
+                })
+                .collect(),
+            total_to_ready_ms: timeline
+                .ready_at
+                .map(|ready| millis(ready.saturating_duration_since(self.origin))),
+        }
+    }
+
+    /// The summary line, the first time both the app and the sidecar are
+    /// ready; `None` before that and afterwards.
+    pub(crate) fn take_summary(&self) -> Option<String> {
+        {
+            let mut timeline = self.timeline();
+            if timeline.summary_logged || !timeline.sidecar_settled || timeline.ready_at.is_none() {
+                return None;
+            }
+            timeline.summary_logged = true;
+        }
+        Some(summary_line(&self.report()))
+    }
+}
+
+/// `startup total=2310ms keychain=1650ms resolving_node=12ms ...`; stages
+/// still running are left out.
+fn summary_line(report: &StartupProfileReport) -> String {
+    let mut line = String::from("startup");
+    if let Some(total) = report.total_to_ready_ms {
+        line.push_str(&format!(" total={total}ms"));
+    }
+    for stage in &report.stages {
+        if let Some(duration) = stage.duration_ms {
+            line.push_str(&format!(" {}={duration}ms", stage.name));
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{StageTiming, StartupProfile};
+
+    fn at(origin: Instant, ms: u64) -> Instant {
+        origin + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn stages_end_when_the_next_begins() {
+        let t0 = Instant::now();
+        let profile = StartupProfile::new(t0);
+        profile.begin("keychain", at(t0, 5));
+        profile.end_current(at(t0, 1655));
+        profile.begin("window_visibility", at(t0, 1700));
+        profile.begin("resolving_node", at(t0, 1750));
+        profile.begin("spawning_sidecar", at(t0, 1762));
+        let report = profile.report();
+        let durations: Vec<(&str, Option<u64>)> = report
+            .stages
+            .iter()
+            .map(|stage| (stage.name.as_str(), stage.duration_ms))
+            .collect();
+        assert_eq!(
+            durations,
+            [
+                ("keychain", Some(1650)),
+                ("window_visibility", Some(50)),
+                ("resolving_node", Some(12)),
+                ("spawning_sidecar", None),
+            ]
+        );
+        assert_eq!(
+            report.stages[1],
+            StageTiming {
+                name: "window_visibility".to_string(),
+                start_ms: 1700,
+                duration_ms: Some(50)
+            }
+        );
+        assert_eq!(report.total_to_ready_ms, None);
+    }
+
+    #[test]
+    fn summary_waits_for_both_ready_points_and_is_written_once() {
+        let t0 = Instant::now();
+        let profile = StartupProfile::new(t0);
+        profile.begin("keychain", t0);
+        profile.end_current(at(t0, 1650));
+        profile.begin("spawning_sidecar", at(t0, 1800));
+        profile.mark_ready(at(t0, 2310));
+        assert_eq!(profile.take_summary(), None);
+
+        profile.settle_sidecar(at(t0, 2005));
+        assert_eq!(
+            profile.take_summary().as_deref(),
+            Some("startup total=2310ms keychain=1650ms spawning_sidecar=205ms")
+        );
+        assert_eq!(profile.take_summary(), None);
+        // Only the first Ready counts.
+        profile.mark_ready(at(t0, 9000));
+        assert_eq!(profile.report().total_to_ready_ms, Some(2310));
+    }
+}
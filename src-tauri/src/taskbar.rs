@@ -0,0 +1,245 @@
+//! Dock/taskbar badge and progress. The last values set are kept here and
+//! reapplied whenever the main window is shown, because Windows drops the
+//! overlay icon and progress with the taskbar button when a window is hidden
+//! (e.g. closed to the tray), and calls made before the window exists would
+//! otherwise be lost.
+//!
+//! Badges use the dock badge on macOS and a rendered overlay icon on Windows;
+//! Linux has no portable badge, so the count is only remembered there.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+use crate::logging::log_event;
+
+/// The dock shows whatever it is given; keep it to a sane width.
+const MAX_BADGE_COUNT: u32 = 9_999;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProgressState {
+    None,
+    Normal,
+    Indeterminate,
+    Paused,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Progress {
+    state: ProgressState,
+    /// Percent, 0 to 100.
+    value: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct TaskbarState {
+    badge: Mutex<Option<u32>>,
+    progress: Mutex<Option<Progress>>,
+}
+
+/// `None` and zero both clear the badge.
+fn clamp_badge(count: Option<u32>) -> Option<u32> {
+    count.filter(|count| *count > 0).map(|count| count.min(MAX_BADGE_COUNT))
+}
+
+/// Percent from whatever the frontend sent: NaN counts as 0 and the result
+/// is rounded into 0..=100.
+fn clamp_percent(value: Option<f64>) -> u64 {
+    let value = value.filter(|v| !v.is_nan()).unwrap_or(0.0);
+    value.clamp(0.0, 100.0).round() as u64
+}
+
+pub(crate) fn set_badge_count(app: &AppHandle, count: Option<u32>) {
+    let count = clamp_badge(count);
+    *app.state::<TaskbarState>().badge.lock().unwrap_or_else(|e| e.into_inner()) = count;
+    apply_badge(app, count);
+}
+
+pub(crate) fn set_progress(app: &AppHandle, state: ProgressState, value: Option<f64>) {
+    let progress = (state != ProgressState::None).then(|| Progress {
+        state,
+        value: clamp_percent(value),
+    });
+    *app.state::<TaskbarState>().progress.lock().unwrap_or_else(|e| e.into_inner()) = progress;
+    apply_progress(app, progress);
+}
+
+/// Push the remembered badge and progress to the main window again.
+pub(crate) fn reapply(app: &AppHandle) {
+    let Some(state) = app.try_state::<TaskbarState>() else {
+        return;
+    };
+    let badge = *state.badge.lock().unwrap_or_else(|e| e.into_inner());
+    let progress = *state.progress.lock().unwrap_or_else(|e| e.into_inner());
+    if badge.is_some() {
+        apply_badge(app, badge);
+    }
+    if progress.is_some() {
+        apply_progress(app, progress);
+    }
+}
+
+fn apply_badge(app: &AppHandle, count: Option<u32>) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    #[cfg(target_os = "macos")]
+    let result = window.set_badge_count(count.map(i64::from));
+    #[cfg(windows)]
+    let result = window.set_overlay_icon(
+        count.map(|count| tauri::image::Image::new_owned(overlay_pixels(&badge_label(count)), OVERLAY_SIZE, OVERLAY_SIZE)),
+    );
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let result: tauri::Result<()> = {
+        let _ = (window, count);
+        Ok(())
+    };
+    if let Err(err) = result {
+        log_event(app, "WARN", "taskbar_badge_failed", &[("error", &err.to_string())]);
+    }
+}
+
+fn apply_progress(app: &AppHandle, progress: Option<Progress>) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let state = match progress {
+        Some(Progress { state, value }) => ProgressBarState {
+            status: Some(match state {
+                ProgressState::None => ProgressBarStatus::None,
+                ProgressState::Normal => ProgressBarStatus::Normal,
+                ProgressState::Indeterminate => ProgressBarStatus::Indeterminate,
+                ProgressState::Paused => ProgressBarStatus::Paused,
+                ProgressState::Error => ProgressBarStatus::Error,
+            }),
+            progress: Some(value),
+        },
+        None => ProgressBarState {
+            status: Some(ProgressBarStatus::None),
+            progress: None,
+        },
+    };
+    if let Err(err) = window.set_progress_bar(state) {
+        log_event(app, "WARN", "taskbar_progress_failed", &[("error", &err.to_string())]);
+    }
+}
+
+/// Windows overlay icons are drawn at 16x16.
+#[cfg_attr(not(windows), allow(dead_code))]
+const OVERLAY_SIZE: u32 = 16;
+
+/// Text drawn on the overlay icon; it only has room for two digits.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn badge_label(count: u32) -> String {
+    if count > 99 {
+        "99+".to_string()
+    } else {
+        count.to_string()
+    }
+}
+
+/// 3x5 glyphs, one row per byte, high bit on the left.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// RGBA pixels for the overlay: white `label` on a red disc, drawn at double
+/// size when it fits.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn overlay_pixels(label: &str) -> Vec<u8> {
+    const RED: [u8; 4] = [0xD9, 0x2D, 0x20, 0xFF];
+    const WHITE: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+    let size = OVERLAY_SIZE as usize;
+    let mut pixels = vec![0u8; size * size * 4];
+    let centre = (size as f64 - 1.0) / 2.0;
+    let radius = size as f64 / 2.0;
+    for y in 0..size {
+        for x in 0..size {
+            let (dx, dy) = (x as f64 - centre, y as f64 - centre);
+            if dx * dx + dy * dy <= radius * radius {
+                pixels[(y * size + x) * 4..][..4].copy_from_slice(&RED);
+            }
+        }
+    }
+    let chars = label.chars().count();
+    let width_at = |scale: usize| (chars * 4).saturating_sub(1) * scale;
+    let scale = if width_at(2) <= size - 2 { 2 } else { 1 };
+    let (width, height) = (width_at(scale), 5 * scale);
+    let (left, top) = ((size - width) / 2, (size - height) / 2);
+    for (index, c) in label.chars().enumerate() {
+        let origin = left + index * 4 * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        let (x, y) = (origin + column * scale + sx, top + row * scale + sy);
+                        pixels[(y * size + x) * 4..][..4].copy_from_slice(&WHITE);
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{badge_label, clamp_badge, clamp_percent, overlay_pixels, ProgressState, OVERLAY_SIZE};
+
+    #[test]
+    fn badge_counts_are_cleared_and_capped() {
+        assert_eq!(clamp_badge(None), None);
+        assert_eq!(clamp_badge(Some(0)), None);
+        assert_eq!(clamp_badge(Some(7)), Some(7));
+        assert_eq!(clamp_badge(Some(u32::MAX)), Some(9_999));
+        assert_eq!(badge_label(42), "42");
+        assert_eq!(badge_label(100), "99+");
+    }
+
+    #[test]
+    fn progress_is_clamped_to_a_percentage() {
+        assert_eq!(clamp_percent(Some(42.4)), 42);
+        assert_eq!(clamp_percent(Some(-5.0)), 0);
+        assert_eq!(clamp_percent(Some(250.0)), 100);
+        assert_eq!(clamp_percent(Some(f64::NAN)), 0);
+        assert_eq!(clamp_percent(Some(f64::INFINITY)), 100);
+        assert_eq!(clamp_percent(None), 0);
+        let state: ProgressState = serde_json::from_str(r#""indeterminate""#).unwrap();
+        assert_eq!(state, ProgressState::Indeterminate);
+        assert!(serde_json::from_str::<ProgressState>(r#""busy""#).is_err());
+    }
+
+    #[test]
+    fn overlay_labels_fit_the_icon() {
+        let size = OVERLAY_SIZE as usize;
+        for label in ["1", "42", "99+"] {
+            let pixels = overlay_pixels(label);
+            assert_eq!(pixels.len(), size * size * 4);
+            let white = pixels.chunks(4).filter(|p| p == &[0xFF, 0xFF, 0xFF, 0xFF]).count();
+            assert!(white > 0, "{label}");
+        }
+        // Corners stay transparent around the disc.
+        assert_eq!(overlay_pixels("1")[3], 0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Native chrome that matches the web UI's theme: titlebars, menus and the
+//! webview background before the page paints, so dark mode doesn't flash
+//! white. The `theme` pref is `system`, `dark` or `light`; `system` follows
+//! the OS and re-emits `theme-changed` when it flips.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::webview::Color;
+use tauri::{AppHandle, Emitter, Manager, Theme};
+
+use crate::logging::log_event;
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_THEME: &str = "theme";
+const CHANGED_EVENT: &str = "theme-changed";
+const SETTINGS: [&str; 3] = ["system", "dark", "light"];
+/// Page backgrounds from the stylesheet (`--bg`, approximately, for dark).
+const DARK_BACKGROUND: Color = Color(26, 28, 30, 255);
+const LIGHT_BACKGROUND: Color = Color(248, 249, 250, 255);
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    match value.as_str() {
+        Some(setting) if SETTINGS.contains(&setting) => Ok(()),
+        _ => Err(format!("Runtime pref {PREF_THEME} must be one of: {}", SETTINGS.join(", "))),
+    }
+}
+
+fn setting(app: &AppHandle) -> &'static str {
+    let stored = app
+        .try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_THEME))
+        .and_then(|value| value.as_str().map(str::to_string));
+    SETTINGS
+        .into_iter()
+        .find(|known| stored.as_deref() == Some(*known))
+        .unwrap_or("system")
+}
+
+/// The theme forced on windows; `None` lets the OS decide.
+fn forced(setting: &str) -> Option<Theme> {
+    match setting {
+        "dark" => Some(Theme::Dark),
+        "light" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+/// Dark when the OS theme is unknown: that is what the app looked like
+/// before there was a choice.
+fn effective_for(setting: &str, os_theme: Option<Theme>) -> Theme {
+    match forced(setting).or(os_theme) {
+        Some(Theme::Light) => Theme::Light,
+        _ => Theme::Dark,
+    }
+}
+
+fn name(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Light => "light",
+        _ => "dark",
+    }
+}
+
+fn background(theme: Theme) -> Color {
+    match theme {
+        Theme::Light => LIGHT_BACKGROUND,
+        _ => DARK_BACKGROUND,
+    }
+}
+
+/// Managed state: the OS theme as last reported, and the effective theme
+/// last announced so one OS flip seen by every window emits once.
+#[derive(Default)]
+pub(crate) struct ThemeState {
+    os_theme: Mutex<Option<Theme>>,
+    announced: Mutex<Option<Theme>>,
+}
+
+fn os_theme(app: &AppHandle) -> Option<Theme> {
+    let state = app.try_state::<ThemeState>()?;
+    let known = *state.os_theme.lock().unwrap_or_else(|e| e.into_inner());
+    known
+}
+
+pub(crate) fn effective(app: &AppHandle) -> Theme {
+    effective_for(setting(app), os_theme(app))
+}
+
+/// `get_theme` payload and `theme-changed` event.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ThemeInfo {
+    setting: &'static str,
+    /// `dark` or `light`.
+    effective: &'static str,
+}
+
+impl Default for ThemeInfo {
+    fn default() -> Self {
+        ThemeInfo {
+            setting: "system",
+            effective: "dark",
+        }
+    }
+}
+
+pub(crate) fn info(app: &AppHandle) -> ThemeInfo {
+    ThemeInfo {
+        setting: setting(app),
+        effective: name(effective(app)),
+    }
+}
+
+/// Builder settings for a new window: forced theme and first-paint color.
+pub(crate) fn for_new_window(app: &AppHandle) -> (Option<Theme>, Color) {
+    (forced(setting(app)), background(effective(app)))
+}
+
+/// Push the pref to every window and tell them, if the effective theme
+/// changed. Called at startup (with `announce` off) and on pref changes.
+pub(crate) fn apply(app: &AppHandle, announce: bool) {
+    let setting = setting(app);
+    app.set_theme(forced(setting));
+    if setting == "system" {
+        // Windows report the OS theme again once nothing is forced.
+        if let Some(theme) = app.get_webview_window("main").and_then(|w| w.theme().ok()) {
+            if let Some(state) = app.try_state::<ThemeState>() {
+                *state.os_theme.lock().unwrap_or_else(|e| e.into_inner()) = Some(theme);
+            }
+        }
+    }
+    let effective = effective(app);
+    for window in app.webview_windows().values() {
+        if let Err(err) = window.set_background_color(Some(background(effective))) {
+            log_event(app, "DEBUG", "theme_background_failed", &[("window", window.label()), ("error", &err.to_string())]);
+        }
+    }
+    if announce {
+        announce_if_changed(app, effective);
+    } else if let Some(state) = app.try_state::<ThemeState>() {
+        *state.announced.lock().unwrap_or_else(|e| e.into_inner()) = Some(effective);
+    }
+}
+
+fn announce_if_changed(app: &AppHandle, effective: Theme) {
+    let Some(state) = app.try_state::<ThemeState>() else {
+        return;
+    };
+    let previous = state.announced.lock().unwrap_or_else(|e| e.into_inner()).replace(effective);
+    if previous != Some(effective) {
+        log_event(app, "INFO", "theme_changed", &[("effective", name(effective))]);
+        let _ = app.emit(CHANGED_EVENT, info(app));
+    }
+}
+
+/// A window saw the OS theme change. Ignored while a theme is forced:
+/// windows then report the forced one.
+pub(crate) fn os_theme_changed(app: &AppHandle, theme: Theme) {
+    if forced(setting(app)).is_some() {
+        return;
+    }
+    if let Some(state) = app.try_state::<ThemeState>() {
+        *state.os_theme.lock().unwrap_or_else(|e| e.into_inner()) = Some(theme);
+    }
+    let effective = effective(app);
+    for window in app.webview_windows().values() {
+        let _ = window.set_background_color(Some(background(effective)));
+    }
+    announce_if_changed(app, effective);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tauri::Theme;
+
+    use super::{effective_for, forced, validate_pref};
+
+    #[test]
+    fn forced_themes_ignore_the_os() {
+        assert_eq!(forced("system"), None);
+        assert_eq!(effective_for("dark", Some(Theme::Light)), Theme::Dark);
+        assert_eq!(effective_for("light", Some(Theme::Dark)), Theme::Light);
+        assert_eq!(effective_for("system", Some(Theme::Light)), Theme::Light);
+        assert_eq!(effective_for("system", None), Theme::Dark);
+    }
+
+    #[test]
+    fn pref_is_one_of_the_three_settings() {
+        for ok in ["system", "dark", "light"] {
+            assert!(validate_pref(&json!(ok)).is_ok(), "{ok}");
+        }
+        for bad in [json!("Dark"), json!("auto"), json!(true)] {
+            assert!(validate_pref(&bad).is_err(), "{bad}");
+        }
+    }
+}
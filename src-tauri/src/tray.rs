@@ -0,0 +1,72 @@
+//! System tray icon, behind the `tray` cargo feature. Its menu is the way back
+//! to a main window that started hidden (`startInTray`).
+
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::{polling, show_main_window};
+
+const TRAY_ID: &str = "main";
+const TRAY_SHOW_ID: &str = "tray.show";
+const TRAY_QUIT_ID: &str = "tray.quit";
+
+/// Managed once the tray exists: its Pause Polling item, kept in step with
+/// the app menu's.
+struct TrayPollingItem(CheckMenuItem<Wry>);
+
+pub(crate) fn set_polling_checked(app: &AppHandle, paused: bool) {
+    if let Some(item) = app.try_state::<TrayPollingItem>() {
+        let _ = item.0.set_checked(paused);
+    }
+}
+
+pub(crate) fn is_available(app: &AppHandle) -> bool {
+    app.tray_by_id(TRAY_ID).is_some()
+}
+
+pub(crate) fn create_tray(app: &AppHandle) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, TRAY_SHOW_ID, "Show World Monitor", true, None::<&str>)?;
+    let pause_polling = CheckMenuItem::with_id(
+        app,
+        polling::MENU_VIEW_PAUSE_POLLING_ID,
+        "Pause Polling",
+        true,
+        polling::is_paused(app),
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, TRAY_QUIT_ID, "Quit World Monitor", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[&show, &pause_polling, &PredefinedMenuItem::separator(app)?, &quit],
+    )?;
+    app.manage(TrayPollingItem(pause_polling));
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("World Monitor")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        // Pause Polling is left to the app's menu handler, which sees tray
+        // menu events too; handling it here as well would toggle it twice.
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            TRAY_SHOW_ID => show_main_window(app),
+            // RunEvent::Exit stops the sidecar.
+            TRAY_QUIT_ID => app.exit(0),
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
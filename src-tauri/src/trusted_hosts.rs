@@ -0,0 +1,438 @@
+//! LAN services the user has vouched for, kept in the `trustedLocalHosts`
+//! pref as `host:port` patterns: `192.168.1.20:11434`, `192.168.1.*:8080`,
+//! `[fd12::7]:3004`, `nas.local:*`. Without a match, private and link-local
+//! addresses and LAN names are off limits to the shell's native fetches, the
+//! WebSocket bridge, relay and Ollama URLs saved to the vault, and plain-http
+//! links in `open_url`; loopback stays allowed wherever it was. Patterns may
+//! only name LAN targets: no public addresses or names, and no wildcard wider
+//! than the last octet of an IPv4 /24. The sidecar gets the same list as
+//! `LOCAL_API_TRUSTED_HOSTS` at start and on `/api/local-trusted-hosts`
+//! when it changes, so its SSRF guard agrees with ours.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use reqwest::Url;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::{post_to_local_api, RuntimePrefs};
+
+pub(crate) const PREF_TRUSTED_LOCAL_HOSTS: &str = "trustedLocalHosts";
+pub(crate) const TRUSTED_HOSTS_ENV: &str = "LOCAL_API_TRUSTED_HOSTS";
+const MAX_PATTERNS: usize = 32;
+/// Suffixes that only resolve on the local network (mDNS and RFC 8375).
+const LAN_SUFFIXES: [&str; 4] = [".local", ".lan", ".home.arpa", ".internal"];
+/// Vault keys holding a URL the sidecar or the bridge will connect to.
+const URL_SECRET_KEYS: [&str; 4] = ["WS_RELAY_URL", "VITE_WS_RELAY_URL", "VITE_OPENSKY_RELAY_URL", "OLLAMA_API_URL"];
+
+/// Where a host points, which decides whether it must be trusted first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum HostScope {
+    Loopback,
+    /// Private, link-local or unique-local address, or a LAN-only name.
+    Local,
+    Public,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum HostPattern {
+    /// The first three octets, and the last or `None` for `*`.
+    V4([u8; 3], Option<u8>),
+    V6(Ipv6Addr),
+    /// Lowercase, without a trailing dot.
+    Name(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TrustedHost {
+    host: HostPattern,
+    /// `None` for `*`.
+    port: Option<u16>,
+}
+
+impl fmt::Display for TrustedHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.host {
+            HostPattern::V4([a, b, c], Some(d)) => write!(f, "{a}.{b}.{c}.{d}")?,
+            HostPattern::V4([a, b, c], None) => write!(f, "{a}.{b}.{c}.*")?,
+            HostPattern::V6(addr) => write!(f, "[{addr}]")?,
+            HostPattern::Name(name) => f.write_str(name)?,
+        }
+        match self.port {
+            Some(port) => write!(f, ":{port}"),
+            None => f.write_str(":*"),
+        }
+    }
+}
+
+/// An address as it would be dialled: IPv4-mapped IPv6 counts as IPv4.
+fn parse_ip(host: &str) -> Option<IpAddr> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>().ok()? {
+        IpAddr::V6(v6) => Some(v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4)),
+        v4 => Some(v4),
+    }
+}
+
+fn ip_scope(ip: IpAddr) -> HostScope {
+    match ip {
+        IpAddr::V4(v4) if v4.is_loopback() => HostScope::Loopback,
+        IpAddr::V4(v4) if v4.is_private() || v4.is_link_local() || v4.is_unspecified() => HostScope::Local,
+        IpAddr::V6(v6) if v6.is_loopback() => HostScope::Loopback,
+        // fc00::/7 unique-local and fe80::/10 link-local.
+        IpAddr::V6(v6) if (v6.segments()[0] & 0xfe00) == 0xfc00 || (v6.segments()[0] & 0xffc0) == 0xfe80 => {
+            HostScope::Local
+        }
+        IpAddr::V6(v6) if v6.is_unspecified() => HostScope::Local,
+        _ => HostScope::Public,
+    }
+}
+
+fn normalize_name(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// `host` as a URL carries it: a name, an IPv4 address, or IPv6 with or
+/// without brackets. Names are judged by their shape; resolving them is
+/// the connection's business.
+pub(crate) fn scope(host: &str) -> HostScope {
+    if let Some(ip) = parse_ip(host) {
+        return ip_scope(ip);
+    }
+    let name = normalize_name(host);
+    if name == "localhost" || name.ends_with(".localhost") {
+        HostScope::Loopback
+    } else if !name.contains('.') || LAN_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+        HostScope::Local
+    } else {
+        HostScope::Public
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+pub(crate) fn parse_pattern(raw: &str) -> Result<TrustedHost, String> {
+    let raw = raw.trim();
+    let (host, port) = if let Some(rest) = raw.strip_prefix('[') {
+        let (v6, port) = rest
+            .split_once("]:")
+            .ok_or_else(|| format!("{raw:?} must be [IPv6]:port"))?;
+        (format!("[{v6}]"), port)
+    } else {
+        let (host, port) = raw
+            .rsplit_once(':')
+            .ok_or_else(|| format!("{raw:?} must be host:port; use host:* for any port"))?;
+        if host.contains(':') {
+            return Err(format!("{raw:?}: put IPv6 addresses in brackets, as [fd00::1]:port"));
+        }
+        (host.to_string(), port)
+    };
+    let port = match port {
+        "*" => None,
+        digits => match digits.parse::<u16>() {
+            Ok(port) if port > 0 => Some(port),
+            _ => return Err(format!("{raw:?} has an invalid port; use 1-65535 or *")),
+        },
+    };
+    let not_lan = |host: &str| format!("{host} is not a private network address or LAN name");
+    let host = if host.contains('*') {
+        let prefix = host.strip_suffix(".*").filter(|prefix| !prefix.contains('*'));
+        let octets: Option<Vec<u8>> = prefix.and_then(|prefix| prefix.split('.').map(|o| o.parse().ok()).collect());
+        let Some([a, b, c]) = octets.and_then(|octets| <[u8; 3]>::try_from(octets).ok()) else {
+            return Err(format!("{raw:?}: * may only replace the last IPv4 octet (at most a /24)"));
+        };
+        if ip_scope(IpAddr::V4(Ipv4Addr::new(a, b, c, 0))) == HostScope::Public {
+            return Err(not_lan(&host));
+        }
+        HostPattern::V4([a, b, c], None)
+    } else if let Some(ip) = parse_ip(&host) {
+        if ip_scope(ip) == HostScope::Public {
+            return Err(format!("{host} is a public address; only LAN hosts can be trusted"));
+        }
+        match ip {
+            IpAddr::V4(v4) => {
+                let [a, b, c, d] = v4.octets();
+                HostPattern::V4([a, b, c], Some(d))
+            }
+            IpAddr::V6(v6) => HostPattern::V6(v6),
+        }
+    } else {
+        let name = normalize_name(&host);
+        if host.starts_with('[') || !is_valid_name(&name) {
+            return Err(format!("{raw:?} has an invalid host"));
+        }
+        if scope(&name) == HostScope::Public {
+            return Err(format!("{}; use a .local or single-label name", not_lan(&name)));
+        }
+        HostPattern::Name(name)
+    };
+    Ok(TrustedHost { host, port })
+}
+
+impl TrustedHost {
+    fn matches(&self, host: &str, port: u16) -> bool {
+        if self.port.is_some_and(|trusted| trusted != port) {
+            return false;
+        }
+        match (&self.host, parse_ip(host)) {
+            (HostPattern::V4(prefix, last), Some(IpAddr::V4(v4))) => {
+                let [a, b, c, d] = v4.octets();
+                *prefix == [a, b, c] && last.is_none_or(|last| last == d)
+            }
+            (HostPattern::V6(trusted), Some(IpAddr::V6(v6))) => *trusted == v6,
+            (HostPattern::Name(name), None) => *name == normalize_name(host),
+            _ => false,
+        }
+    }
+}
+
+pub(crate) fn is_trusted(trusted: &[TrustedHost], host: &str, port: u16) -> bool {
+    trusted.iter().any(|pattern| pattern.matches(host, port))
+}
+
+/// Why `url` may not be reached, if it may not: public and loopback hosts
+/// pass, LAN hosts only when trusted.
+fn refusal(trusted: &[TrustedHost], url: &Url) -> Option<String> {
+    let host = url.host_str().unwrap_or("");
+    let port = url.port_or_known_default().unwrap_or(0);
+    (scope(host) == HostScope::Local && !is_trusted(trusted, host, port))
+        .then(|| format!("{host} is on the local network; add {host}:{port} to trusted local hosts to allow it"))
+}
+
+pub(crate) fn check(trusted: &[TrustedHost], url: &Url) -> Result<(), DesktopError> {
+    refusal(trusted, url).map_or(Ok(()), |reason| Err(DesktopError::InvalidUrl(reason)))
+}
+
+pub(crate) fn validate_pref(value: &Value) -> Result<(), String> {
+    let list = value
+        .as_array()
+        .ok_or_else(|| format!("Runtime pref {PREF_TRUSTED_LOCAL_HOSTS} must be an array of host:port strings"))?;
+    if list.len() > MAX_PATTERNS {
+        return Err(format!("Runtime pref {PREF_TRUSTED_LOCAL_HOSTS} takes at most {MAX_PATTERNS} entries"));
+    }
+    for entry in list {
+        let raw = entry
+            .as_str()
+            .ok_or_else(|| format!("Runtime pref {PREF_TRUSTED_LOCAL_HOSTS} must be an array of host:port strings"))?;
+        parse_pattern(raw).map_err(|e| format!("Runtime pref {PREF_TRUSTED_LOCAL_HOSTS}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Parse `raw` for storing, in its normal form, duplicates dropped.
+pub(crate) fn normalize(raw: &[String]) -> Result<Vec<String>, DesktopError> {
+    let mut normalized: Vec<String> = Vec::new();
+    for entry in raw {
+        let pattern = parse_pattern(entry).map_err(DesktopError::InvalidArgument)?.to_string();
+        if !normalized.contains(&pattern) {
+            normalized.push(pattern);
+        }
+    }
+    if normalized.len() > MAX_PATTERNS {
+        return Err(DesktopError::InvalidArgument(format!(
+            "At most {MAX_PATTERNS} trusted local hosts are allowed"
+        )));
+    }
+    Ok(normalized)
+}
+
+/// The pref's patterns; entries that no longer parse are skipped.
+pub(crate) fn from_prefs(app: &AppHandle) -> Vec<TrustedHost> {
+    app.try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_TRUSTED_LOCAL_HOSTS))
+        .and_then(|value| value.as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Value::as_str)
+        .filter_map(|raw| parse_pattern(raw).ok())
+        .collect()
+}
+
+/// The pref in normal form, for settings and the sidecar.
+pub(crate) fn patterns(app: &AppHandle) -> Vec<String> {
+    from_prefs(app).iter().map(ToString::to_string).collect()
+}
+
+/// Value for [`TRUSTED_HOSTS_ENV`]; empty when nothing is trusted.
+pub(crate) fn env_value(patterns: &[String]) -> String {
+    patterns.join(",")
+}
+
+/// [`check`] against the pref.
+pub(crate) fn require_allowed(app: &AppHandle, url: &Url) -> Result<(), DesktopError> {
+    check(&from_prefs(app), url)
+}
+
+/// Format check for the vault's URL keys: a parseable http(s) URL (or
+/// ws(s) for relays) whose host, when on the LAN, is trusted.
+pub(crate) fn validate_url_secret(trusted: &[TrustedHost], key: &str, value: &str) -> Result<(), DesktopError> {
+    if !URL_SECRET_KEYS.contains(&key) {
+        return Ok(());
+    }
+    let url = Url::parse(value).map_err(|_| DesktopError::InvalidArgument(format!("{key} must be a URL")))?;
+    let schemes: &[&str] = if key == "OLLAMA_API_URL" {
+        &["http", "https"]
+    } else {
+        &["http", "https", "ws", "wss"]
+    };
+    if !schemes.contains(&url.scheme()) || url.host_str().is_none() {
+        return Err(DesktopError::InvalidArgument(format!(
+            "{key} must be a {} URL",
+            schemes.join("/")
+        )));
+    }
+    refusal(trusted, &url).map_or(Ok(()), |reason| Err(DesktopError::InvalidArgument(format!("{key}: {reason}"))))
+}
+
+/// Push the list to a running sidecar.
+pub(crate) fn publish(app: &AppHandle) {
+    let hosts = patterns(app);
+    log_event(app, "INFO", "trusted_local_hosts_changed", &[("hosts", &env_value(&hosts))]);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let body = serde_json::json!({ "hosts": hosts });
+        if let Err(err) = post_to_local_api(&app, "/api/local-trusted-hosts", body).await {
+            // The next start passes the list through the environment anyway.
+            log_event(&app, "WARN", "trusted_local_hosts_push_failed", &[("error", &err)]);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+    use serde_json::json;
+
+    use super::{check, is_trusted, normalize, parse_pattern, scope, validate_pref, validate_url_secret, HostScope};
+
+    fn trusted(patterns: &[&str]) -> Vec<super::TrustedHost> {
+        patterns.iter().map(|raw| parse_pattern(raw).unwrap()).collect()
+    }
+
+    #[test]
+    fn scopes_addresses_and_names() {
+        for host in ["127.0.0.1", "[::1]", "::1", "localhost", "app.localhost", "[::ffff:127.0.0.1]"] {
+            assert_eq!(scope(host), HostScope::Loopback, "{host}");
+        }
+        for host in [
+            "192.168.1.20",
+            "10.0.0.5",
+            "172.20.1.1",
+            "169.254.10.10",
+            "[fd12:3456::7]",
+            "[fe80::1]",
+            "[::ffff:192.168.1.9]",
+            "nas.local",
+            "NAS.Local.",
+            "ollama-box",
+            "printer.home.arpa",
+        ] {
+            assert_eq!(scope(host), HostScope::Local, "{host}");
+        }
+        for host in ["8.8.8.8", "[2001:db8::1]", "172.32.0.1", "relay.example.org", "local.example.com"] {
+            assert_eq!(scope(host), HostScope::Public, "{host}");
+        }
+    }
+
+    #[test]
+    fn patterns_are_lan_only_and_at_most_a_slash_24() {
+        for (raw, normal) in [
+            ("192.168.1.20:11434", "192.168.1.20:11434"),
+            (" 192.168.1.*:8080 ", "192.168.1.*:8080"),
+            ("[fd12:3456:0:0::7]:3004", "[fd12:3456::7]:3004"),
+            ("[fe80::1]:*", "[fe80::1]:*"),
+            ("NAS.local.:*", "nas.local:*"),
+            ("ollama-box:11434", "ollama-box:11434"),
+        ] {
+            assert_eq!(parse_pattern(raw).map(|p| p.to_string()).as_deref(), Ok(normal), "{raw}");
+        }
+        for raw in [
+            "192.168.*.*:80",
+            "192.168.*:80",
+            "*:80",
+            "*.local:80",
+            "10.0.0.*5:80",
+            "8.8.8.*:53",
+            "8.8.8.8:53",
+            "[2001:db8::1]:80",
+            "relay.example.org:443",
+            "fd12::7:3004",
+            "192.168.1.20",
+            "192.168.1.20:0",
+            "192.168.1.20:70000",
+            "[fd12::7]",
+            "nas_box.local:80",
+            "[nas.local]:80",
+        ] {
+            assert!(parse_pattern(raw).is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn matches_host_and_port() {
+        let list = trusted(&["192.168.1.*:11434", "[fd12::7]:*", "nas.local:3004"]);
+        assert!(is_trusted(&list, "192.168.1.77", 11434));
+        assert!(!is_trusted(&list, "192.168.1.77", 80));
+        assert!(!is_trusted(&list, "192.168.2.77", 11434));
+        assert!(is_trusted(&list, "[fd12::7]", 9));
+        assert!(is_trusted(&list, "fd12:0::7", 9));
+        assert!(is_trusted(&list, "NAS.local.", 3004));
+        assert!(!is_trusted(&list, "nas.local", 3005));
+        // A name doesn't match an address pattern or the other way round.
+        assert!(!is_trusted(&trusted(&["nas.local:*"]), "192.168.1.2", 1));
+    }
+
+    #[test]
+    fn urls_need_trust_only_for_lan_hosts() {
+        let list = trusted(&["192.168.1.20:11434", "nas.local:*"]);
+        let url = |raw: &str| Url::parse(raw).unwrap();
+        assert!(check(&list, &url("https://example.org/")).is_ok());
+        assert!(check(&list, &url("http://127.0.0.1:3000/")).is_ok());
+        assert!(check(&list, &url("http://192.168.1.20:11434/v1")).is_ok());
+        assert!(check(&list, &url("ws://nas.local/ws")).is_ok());
+        let err = check(&list, &url("http://192.168.1.21:11434/")).unwrap_err();
+        assert_eq!(err.code(), "invalid_url");
+        assert!(err.to_string().contains("192.168.1.21:11434"));
+        // The default port counts.
+        assert!(check(&list, &url("http://192.168.1.20/")).is_err());
+        assert!(check(&[], &url("http://[fe80::2]:80/")).is_err());
+    }
+
+    #[test]
+    fn url_secrets_are_checked_against_the_list() {
+        let list = trusted(&["192.168.1.20:11434"]);
+        assert!(validate_url_secret(&list, "OLLAMA_API_URL", "http://192.168.1.20:11434").is_ok());
+        assert!(validate_url_secret(&list, "OLLAMA_API_URL", "http://localhost:11434").is_ok());
+        assert!(validate_url_secret(&list, "OLLAMA_API_URL", "http://192.168.1.30:11434").is_err());
+        assert!(validate_url_secret(&list, "OLLAMA_API_URL", "ws://192.168.1.20:11434").is_err());
+        assert!(validate_url_secret(&list, "WS_RELAY_URL", "wss://relay.example.org").is_ok());
+        assert!(validate_url_secret(&list, "WS_RELAY_URL", "relay.example.org").is_err());
+        // Other keys aren't URLs.
+        assert!(validate_url_secret(&list, "GROQ_API_KEY", "gsk_123").is_ok());
+    }
+
+    #[test]
+    fn pref_validation_and_normal_form() {
+        assert!(validate_pref(&json!(["192.168.1.*:80", "nas.local:*"])).is_ok());
+        assert!(validate_pref(&json!([])).is_ok());
+        assert!(validate_pref(&json!(["8.8.8.8:53"])).unwrap_err().contains("public"));
+        assert!(validate_pref(&json!("nas.local:80")).is_err());
+        assert!(validate_pref(&json!([1])).is_err());
+        assert!(validate_pref(&json!(vec!["nas.local:80"; 33])).is_err());
+        let normal = normalize(&["NAS.local:80".to_string(), "nas.local:80".to_string()]).unwrap();
+        assert_eq!(normal, ["nas.local:80"]);
+        assert_eq!(normalize(&["*:*".to_string()]).unwrap_err().code(), "invalid_argument");
+    }
+}
@@ -0,0 +1,106 @@
+//! The User-Agent the app sends upstream: `WorldMonitor/<version> (<os>;
+//! <arch>)`, plus the optional `userAgentSuffix` pref for providers that
+//! ask integrators for a contact address. Native fetches set it on their
+//! client and the sidecar gets it as `LOCAL_API_USER_AGENT`, using it for
+//! requests that don't pick a UA of their own.
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_USER_AGENT_SUFFIX: &str = "userAgentSuffix";
+pub(crate) const USER_AGENT_ENV: &str = "LOCAL_API_USER_AGENT";
+const MAX_SUFFIX_CHARS: usize = 200;
+
+pub(crate) fn validate_suffix(value: &Value) -> Result<(), String> {
+    match value.as_str() {
+        Some(suffix) if suffix.chars().count() <= MAX_SUFFIX_CHARS => Ok(()),
+        Some(_) => Err(format!(
+            "Runtime pref {PREF_USER_AGENT_SUFFIX} must be at most {MAX_SUFFIX_CHARS} characters"
+        )),
+        None => Err(format!("Runtime pref {PREF_USER_AGENT_SUFFIX} must be a string")),
+    }
+}
+
+/// Control characters (CR/LF would split the header) become spaces, and
+/// runs of whitespace collapse to one.
+fn sanitize_suffix(suffix: &str) -> String {
+    suffix
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(MAX_SUFFIX_CHARS)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn build(version: &str, os: &str, arch: &str, suffix: Option<&str>) -> String {
+    let base = format!("WorldMonitor/{version} ({os}; {arch})");
+    match suffix.map(sanitize_suffix) {
+        Some(suffix) if !suffix.is_empty() => format!("{base} {suffix}"),
+        _ => base,
+    }
+}
+
+/// Managed state: the UA without the suffix, built once at startup.
+pub(crate) struct UserAgentState {
+    base: String,
+}
+
+impl UserAgentState {
+    pub(crate) fn new(version: &str) -> Self {
+        UserAgentState {
+            base: build(version, std::env::consts::OS, std::env::consts::ARCH, None),
+        }
+    }
+}
+
+/// The UA to send now, with the current suffix.
+pub(crate) fn effective(app: &AppHandle) -> String {
+    let suffix = app
+        .try_state::<RuntimePrefs>()
+        .and_then(|prefs| prefs.get(PREF_USER_AGENT_SUFFIX))
+        .and_then(|value| value.as_str().map(sanitize_suffix))
+        .filter(|suffix| !suffix.is_empty());
+    let base = app
+        .try_state::<UserAgentState>()
+        .map(|state| state.base.clone())
+        .unwrap_or_else(|| UserAgentState::new(env!("CARGO_PKG_VERSION")).base);
+    match suffix {
+        Some(suffix) => format!("{base} {suffix}"),
+        None => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{build, validate_suffix};
+
+    #[test]
+    fn formats_version_platform_and_suffix() {
+        assert_eq!(build("2.5.23", "linux", "x86_64", None), "WorldMonitor/2.5.23 (linux; x86_64)");
+        assert_eq!(
+            build("2.5.23", "macos", "aarch64", Some("ops@example.com")),
+            "WorldMonitor/2.5.23 (macos; aarch64) ops@example.com"
+        );
+        assert_eq!(build("1.0.0", "windows", "x86_64", Some("  ")), "WorldMonitor/1.0.0 (windows; x86_64)");
+    }
+
+    #[test]
+    fn suffix_cannot_inject_headers() {
+        let ua = build("1.0.0", "linux", "x86_64", Some("me@example.com\r\nX-Evil: 1\t\u{7f}\0end"));
+        assert_eq!(ua, "WorldMonitor/1.0.0 (linux; x86_64) me@example.com X-Evil: 1 end");
+        assert!(!ua.chars().any(char::is_control));
+    }
+
+    #[test]
+    fn pref_is_a_bounded_string() {
+        assert!(validate_suffix(&json!("contact: ops@example.com")).is_ok());
+        assert!(validate_suffix(&json!("x".repeat(201))).is_err());
+        assert!(validate_suffix(&json!(42)).is_err());
+    }
+}
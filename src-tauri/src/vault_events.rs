@@ -0,0 +1,212 @@
+//! Tells every window when the vault changes, so the settings window and the
+//! main window's onboarding flow don't drift apart until a reload. Each
+//! change that reaches the cache bumps a vault revision and fires
+//! `secrets-changed` with the affected key names and their configured state
+//! (masked, never the values). The revision lives in the shell, not the
+//! sidecar, and is kept in the persistent cache so it only ever grows; a
+//! window that sees a gap, or a different revision from
+//! `get_secrets_summary`, refetches.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{PersistentCache, SecretsCache, SUPPORTED_SECRET_KEYS};
+
+const CHANGED_EVENT: &str = "secrets-changed";
+const CACHE_KEY: &str = "vault-revision";
+/// Values this short are masked completely.
+const MASK_MIN_CHARS: usize = 12;
+const MASK_VISIBLE_CHARS: usize = 4;
+
+#[derive(Default)]
+pub(crate) struct VaultRevision {
+    current: Mutex<u64>,
+}
+
+impl VaultRevision {
+    pub(crate) fn get(&self) -> u64 {
+        *self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The next revision, now current.
+    fn advance(&self) -> u64 {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        *current += 1;
+        *current
+    }
+
+    /// Never moves backwards: a stale saved value is ignored.
+    fn seed(&self, saved: u64) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        *current = (*current).max(saved);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KeyState {
+    key: String,
+    configured: bool,
+    masked: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SecretsChanged {
+    revision: u64,
+    keys: Vec<KeyState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SecretsSummary {
+    revision: u64,
+    keys: Vec<KeyState>,
+}
+
+/// The last few characters of a long value behind a fixed-width mask.
+fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < MASK_MIN_CHARS {
+        return "\u{2022}".repeat(8);
+    }
+    let tail: String = chars[chars.len() - MASK_VISIBLE_CHARS..].iter().collect();
+    format!("{}{tail}", "\u{2022}".repeat(8))
+}
+
+fn key_state(secrets: &HashMap<String, String>, key: &str) -> KeyState {
+    let value = secrets.get(key);
+    KeyState {
+        key: key.to_string(),
+        configured: value.is_some(),
+        masked: value.map(|value| mask(value)),
+    }
+}
+
+/// Keys added, removed or given a new value, sorted.
+fn changed_keys(before: &HashMap<String, String>, after: &HashMap<String, String>) -> Vec<String> {
+    let mut keys: Vec<String> = before
+        .keys()
+        .chain(after.keys().filter(|key| !before.contains_key(*key)))
+        .filter(|key| before.get(*key) != after.get(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// Bump the revision for a change from `before` to `after`; `None` when
+/// nothing changed.
+fn change(
+    revision: &VaultRevision,
+    before: &HashMap<String, String>,
+    after: &HashMap<String, String>,
+) -> Option<SecretsChanged> {
+    let keys = changed_keys(before, after);
+    if keys.is_empty() {
+        return None;
+    }
+    Some(SecretsChanged {
+        revision: revision.advance(),
+        keys: keys.iter().map(|key| key_state(after, key)).collect(),
+    })
+}
+
+/// Record a committed change to the cache and announce it to every window.
+pub(crate) fn notify(app: &AppHandle, before: &HashMap<String, String>, after: &HashMap<String, String>) {
+    let Some(revision) = app.try_state::<VaultRevision>() else {
+        return;
+    };
+    let Some(event) = change(&revision, before, after) else {
+        return;
+    };
+    if let Some(cache) = app.try_state::<PersistentCache>() {
+        // Written out with the rest of the cache on exit.
+        cache.insert(CACHE_KEY.to_string(), Value::from(event.revision));
+    }
+    let _ = app.emit(CHANGED_EVENT, event);
+}
+
+/// Every supported key's state, with the revision it was read at.
+pub(crate) fn summary(app: &AppHandle) -> SecretsSummary {
+    let cache = app.state::<SecretsCache>();
+    let secrets = cache.secrets.lock().unwrap_or_else(|e| e.into_inner());
+    SecretsSummary {
+        // Read under the cache lock, so no change can land in between.
+        revision: app.state::<VaultRevision>().get(),
+        keys: SUPPORTED_SECRET_KEYS.iter().map(|key| key_state(&secrets, key)).collect(),
+    }
+}
+
+/// Pick up the revision from the last session.
+pub(crate) fn restore(app: &AppHandle) {
+    let Some(saved) = app
+        .try_state::<PersistentCache>()
+        .and_then(|cache| cache.get(CACHE_KEY))
+        .and_then(|value| value.as_u64())
+    else {
+        return;
+    };
+    app.state::<VaultRevision>().seed(saved);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{change, changed_keys, mask, VaultRevision};
+
+    fn vault(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn set_replace_and_delete_are_changes_but_rewriting_a_value_is_not() {
+        let before = vault(&[("GROQ_API_KEY", "gsk_old"), ("FRED_API_KEY", "fred")]);
+        let after = vault(&[("GROQ_API_KEY", "gsk_new"), ("FRED_API_KEY", "fred"), ("EIA_API_KEY", "eia")]);
+        assert_eq!(changed_keys(&before, &after), vec!["EIA_API_KEY", "GROQ_API_KEY"]);
+        assert_eq!(changed_keys(&after, &vault(&[("FRED_API_KEY", "fred")])), vec!["EIA_API_KEY", "GROQ_API_KEY"]);
+        assert!(changed_keys(&before, &before.clone()).is_empty());
+    }
+
+    #[test]
+    fn each_change_gets_the_next_revision_and_no_ops_keep_it() {
+        let revision = VaultRevision::default();
+        let empty = HashMap::new();
+        let one = vault(&[("GROQ_API_KEY", "gsk_live_abcdef123456")]);
+
+        let set = change(&revision, &empty, &one).unwrap();
+        assert_eq!(set.revision, 1);
+        assert!(change(&revision, &one, &one.clone()).is_none());
+        assert_eq!(revision.get(), 1);
+        let deleted = change(&revision, &one, &empty).unwrap();
+        assert_eq!(deleted.revision, 2);
+        assert!(!deleted.keys[0].configured);
+        assert_eq!(deleted.keys[0].masked, None);
+    }
+
+    #[test]
+    fn saved_revision_is_resumed_but_never_lowers_the_current_one() {
+        let revision = VaultRevision::default();
+        revision.seed(41);
+        let next = change(&revision, &HashMap::new(), &vault(&[("FRED_API_KEY", "fred")])).unwrap();
+        assert_eq!(next.revision, 42);
+        revision.seed(7);
+        assert_eq!(revision.get(), 42);
+    }
+
+    #[test]
+    fn event_carries_masked_state_and_never_the_value() {
+        let revision = VaultRevision::default();
+        let event = change(&revision, &HashMap::new(), &vault(&[("GROQ_API_KEY", "gsk_live_abcdef123456")])).unwrap();
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(!json.contains("gsk_live_abcdef"), "{json}");
+        assert!(json.contains("\"configured\":true"), "{json}");
+        assert!(json.contains("3456"), "{json}");
+        assert!(!mask("short").contains("short"));
+    }
+}
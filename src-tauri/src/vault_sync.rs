@@ -0,0 +1,240 @@
+//! Guards the keychain vault against edits made outside the app (Keychain
+//! Access, another Mac syncing through iCloud Keychain). The cache remembers
+//! a fingerprint of the vault it loaded; every save re-reads the vault first
+//! and refuses to overwrite one whose fingerprint has moved on. With the
+//! `vaultExternalChangeCheck` pref on, the vault is also polled and a
+//! `secrets-externally-changed` event tells the UI to offer a reload. The
+//! poll is opt-in because reading the keychain can prompt on macOS.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::secret_store::SecretStore;
+use crate::{RuntimePrefs, SecretsCache};
+
+pub(crate) const VAULT_ACCOUNT: &str = "secrets-vault";
+pub(crate) const PREF_VAULT_EXTERNAL_CHANGE_CHECK: &str = "vaultExternalChangeCheck";
+const EXTERNAL_CHANGE_EVENT: &str = "secrets-externally-changed";
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Supported, non-blank entries with values trimmed; the form the cache
+/// holds and the fingerprint is taken over.
+pub(crate) fn normalize(raw: HashMap<String, String>, supported: &[&str]) -> HashMap<String, String> {
+    raw.into_iter()
+        .filter(|(k, v)| supported.contains(&k.as_str()) && !v.trim().is_empty())
+        .map(|(k, v)| (k, v.trim().to_string()))
+        .collect()
+}
+
+/// Order-independent content hash. A missing vault hashes like an empty one.
+pub(crate) fn fingerprint(secrets: &HashMap<String, String>) -> String {
+    let mut entries: Vec<(&String, &String)> = secrets.iter().collect();
+    entries.sort();
+    let mut hasher = Sha256::new();
+    for (key, value) in entries {
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn sorted_keys(secrets: &HashMap<String, String>) -> Vec<String> {
+    let mut keys: Vec<String> = secrets.keys().cloned().collect();
+    keys.sort();
+    keys
+}
+
+/// `Ok` when `keychain` is still the vault the cache was loaded from.
+pub(crate) fn check_conflict(
+    loaded: &str,
+    keychain: &HashMap<String, String>,
+    local: &HashMap<String, String>,
+) -> Result<(), DesktopError> {
+    if fingerprint(keychain) == loaded {
+        return Ok(());
+    }
+    Err(DesktopError::VaultChanged {
+        local_keys: sorted_keys(local),
+        keychain_keys: sorted_keys(keychain),
+    })
+}
+
+/// The vault as stored now, normalized; empty when there is none.
+pub(crate) fn read_vault(store: &dyn SecretStore, supported: &[&str]) -> Result<HashMap<String, String>, DesktopError> {
+    let json = match store.get(VAULT_ACCOUNT) {
+        Ok(Some(json)) => json,
+        Ok(None) => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into_desktop("Failed to read vault")),
+    };
+    let raw: HashMap<String, String> = serde_json::from_str(&json)
+        .map_err(|e| DesktopError::Json(format!("Failed to parse vault: {e}")))?;
+    Ok(normalize(raw, supported))
+}
+
+/// Replace the whole vault with `secrets`.
+pub(crate) fn save_vault(store: &dyn SecretStore, secrets: &HashMap<String, String>) -> Result<(), DesktopError> {
+    let json = serde_json::to_string(secrets)
+        .map_err(|e| DesktopError::Json(format!("Failed to serialize vault: {e}")))?;
+    store
+        .set(VAULT_ACCOUNT, &json)
+        .map_err(|e| e.into_desktop("Failed to write vault"))
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalChange {
+    local_keys: Vec<String>,
+    keychain_keys: Vec<String>,
+}
+
+/// Poll the vault while the pref is on. Each external version is announced
+/// once; saving or reloading moves the cache's fingerprint along with it.
+pub(crate) fn start_watcher(app: &AppHandle, supported: &'static [&'static str]) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut announced: Option<String> = None;
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            let enabled = app
+                .try_state::<RuntimePrefs>()
+                .is_some_and(|prefs| prefs.get_bool(PREF_VAULT_EXTERNAL_CHANGE_CHECK, false));
+            if !enabled {
+                continue;
+            }
+            let cache = app.state::<SecretsCache>();
+            let keychain = match read_vault(&*cache.store, supported) {
+                Ok(keychain) => keychain,
+                Err(err) => {
+                    log_event(&app, "WARN", "vault_check_failed", &[("error", &err.to_string())]);
+                    continue;
+                }
+            };
+            let current = fingerprint(&keychain);
+            let loaded = cache.fingerprint.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            if current == loaded || announced.as_deref() == Some(current.as_str()) {
+                continue;
+            }
+            let change = ExternalChange {
+                local_keys: sorted_keys(&cache.secrets.lock().unwrap_or_else(|e| e.into_inner())),
+                keychain_keys: sorted_keys(&keychain),
+            };
+            log_event(
+                &app,
+                "WARN",
+                "vault_changed_externally",
+                &[("keychain_keys", &change.keychain_keys.join(","))],
+            );
+            if let Err(err) = app.emit(EXTERNAL_CHANGE_EVENT, change) {
+                log_event(&app, "WARN", "vault_change_emit_failed", &[("error", &err.to_string())]);
+            }
+            announced = Some(current);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::{check_conflict, fingerprint, normalize, read_vault, save_vault, VAULT_ACCOUNT};
+    use crate::secret_store::{MemoryStore, SecretStore};
+
+    const SUPPORTED: [&str; 3] = ["GROQ_API_KEY", "FRED_API_KEY", "ACLED_ACCESS_TOKEN"];
+
+    fn vault(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(k, v)| ((*k).to_string(), (*v).to_string())).collect()
+    }
+
+    #[test]
+    fn fingerprint_ignores_order_and_formatting() {
+        let a = normalize(vault(&[("GROQ_API_KEY", " gsk_1 "), ("FRED_API_KEY", "f1")]), &SUPPORTED);
+        let b = normalize(
+            vault(&[("FRED_API_KEY", "f1"), ("GROQ_API_KEY", "gsk_1"), ("UNKNOWN", "x"), ("ACLED_ACCESS_TOKEN", " ")]),
+            &SUPPORTED,
+        );
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&a), fingerprint(&vault(&[("GROQ_API_KEY", "gsk_2"), ("FRED_API_KEY", "f1")])));
+        // Values can't be shifted between keys without changing the hash.
+        assert_ne!(
+            fingerprint(&vault(&[("GROQ_API_KEY", "ab")])),
+            fingerprint(&vault(&[("GROQ_API_KEYa", "b")]))
+        );
+        assert_eq!(fingerprint(&HashMap::new()), fingerprint(&normalize(vault(&[("X", "y")]), &SUPPORTED)));
+    }
+
+    #[test]
+    fn unchanged_vault_can_be_saved() {
+        let loaded = vault(&[("GROQ_API_KEY", "gsk_1")]);
+        let local = vault(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")]);
+        assert!(check_conflict(&fingerprint(&loaded), &loaded, &local).is_ok());
+    }
+
+    #[test]
+    fn external_edit_or_deletion_is_a_conflict() {
+        let loaded = vault(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")]);
+        let local = vault(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1"), ("ACLED_ACCESS_TOKEN", "a1")]);
+        let edited = vault(&[("GROQ_API_KEY", "gsk_rotated"), ("FRED_API_KEY", "f1")]);
+        let err = check_conflict(&fingerprint(&loaded), &edited, &local).unwrap_err();
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "code": "vault_changed_externally",
+                "message": "The keychain vault was changed outside World Monitor; reload secrets before saving",
+                "localKeys": ["ACLED_ACCESS_TOKEN", "FRED_API_KEY", "GROQ_API_KEY"],
+                "keychainKeys": ["FRED_API_KEY", "GROQ_API_KEY"],
+            })
+        );
+
+        let deleted = HashMap::new();
+        let err = check_conflict(&fingerprint(&loaded), &deleted, &local).unwrap_err();
+        assert_eq!(serde_json::to_value(&err).unwrap()["keychainKeys"], json!([]));
+    }
+
+    #[test]
+    fn vault_round_trips_through_the_store() {
+        let store = MemoryStore::default();
+        assert!(read_vault(&store, &SUPPORTED).unwrap().is_empty(), "no vault reads as empty");
+        let secrets = vault(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")]);
+        save_vault(&store, &secrets).unwrap();
+        assert_eq!(store.accounts(), [VAULT_ACCOUNT]);
+        assert_eq!(read_vault(&store, &SUPPORTED).unwrap(), secrets);
+    }
+
+    #[test]
+    fn removing_a_key_rewrites_the_vault_without_it() {
+        let store = MemoryStore::default();
+        save_vault(&store, &vault(&[("GROQ_API_KEY", "gsk_1"), ("FRED_API_KEY", "f1")])).unwrap();
+        let mut secrets = read_vault(&store, &SUPPORTED).unwrap();
+        secrets.remove("FRED_API_KEY");
+        secrets.remove("ACLED_ACCESS_TOKEN");
+        save_vault(&store, &secrets).unwrap();
+        assert_eq!(read_vault(&store, &SUPPORTED).unwrap(), vault(&[("GROQ_API_KEY", "gsk_1")]));
+        save_vault(&store, &HashMap::new()).unwrap();
+        assert_eq!(store.get(VAULT_ACCOUNT).unwrap().as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn corrupted_vault_is_a_json_error() {
+        let store = MemoryStore::with(&[(VAULT_ACCOUNT, "{\"GROQ_API_KEY\": ")]);
+        let err = read_vault(&store, &SUPPORTED).unwrap_err();
+        assert_eq!(err.code(), "json_error");
+        assert!(err.to_string().starts_with("Failed to parse vault: "), "{err}");
+        // Unknown keys and blank values are dropped, not errors.
+        let store = MemoryStore::with(&[(VAULT_ACCOUNT, r#"{"GROQ_API_KEY":" gsk_1 ","OTHER":"x","FRED_API_KEY":""}"#)]);
+        assert_eq!(read_vault(&store, &SUPPORTED).unwrap(), vault(&[("GROQ_API_KEY", "gsk_1")]));
+    }
+}
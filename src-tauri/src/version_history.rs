@@ -0,0 +1,353 @@
+//! Which versions this install has run. When a migration bug shows up after
+//! an auto-update, support needs to know where the user came from. At launch
+//! the running version is compared with `lastRunVersion`. A change appends
+//! `{from, to, at}` to `versionHistory` (the last [`MAX_HISTORY`] entries)
+//! and is logged; an upgrade also shows up as `upgraded_this_run` in
+//! `get_desktop_runtime_info`, so the frontend can show release notes once.
+//!
+//! An upgrade runs the one-time [`MIGRATIONS`] for every version after the
+//! old one, before this session touches the prefs or cache. Each is added to
+//! `completedMigrations` once it succeeds, and `lastRunVersion` only moves
+//! when all of them have, so a failed run is retried at the next launch
+//! without repeating the migrations that finished.
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use crate::logging::{self, log_event};
+use crate::onboarding::OnboardingState;
+use crate::{store_runtime_pref, RuntimePrefs};
+
+// Shell bookkeeping, like window geometry: not settable from a webview.
+const PREF_LAST_RUN_VERSION: &str = "lastRunVersion";
+const PREF_VERSION_HISTORY: &str = "versionHistory";
+const PREF_COMPLETED_MIGRATIONS: &str = "completedMigrations";
+const MAX_HISTORY: usize = 20;
+
+/// A one-time step for installs upgrading to `version` or later from an
+/// earlier one. It must be safe to run again: a crash can land between the
+/// step and its record.
+pub(crate) struct Migration<C: ?Sized> {
+    version: &'static str,
+    name: &'static str,
+    run: fn(&C) -> Result<(), String>,
+}
+
+/// In version order. Cache format changes and prefs schema bumps go here,
+/// under the first version that needs them.
+const MIGRATIONS: &[Migration<AppHandle>] = &[];
+
+/// One `versionHistory` entry.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct VersionChange {
+    /// `None` when the earlier version predates this record.
+    from: Option<String>,
+    to: String,
+    at: String,
+}
+
+/// Managed state: set once at launch, holding the version upgraded from
+/// (`None` inside when that predates the record).
+#[derive(Default)]
+pub(crate) struct VersionState {
+    upgrade: OnceLock<Option<String>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Launch {
+    FreshInstall,
+    SameVersion,
+    Changed { from: Option<String> },
+}
+
+fn detect(last: Option<&str>, current: &str, first_run: bool) -> Launch {
+    match last {
+        Some(last) if last == current => Launch::SameVersion,
+        Some(last) => Launch::Changed {
+            from: Some(last.to_string()),
+        },
+        None if first_run => Launch::FreshInstall,
+        None => Launch::Changed { from: None },
+    }
+}
+
+/// `major.minor.patch`, ignoring any pre-release or build suffix.
+fn parse_version(text: &str) -> Option<(u64, u64, u64)> {
+    let core = text.trim().split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+/// Whether `from` to `to` moves forward; an unrecorded `from` is older than
+/// anything.
+fn is_upgrade(from: Option<&str>, to: &str) -> bool {
+    match (from.map(parse_version), parse_version(to)) {
+        (None, _) => true,
+        (Some(Some(from)), Some(to)) => from < to,
+        _ => false,
+    }
+}
+
+/// The migrations for versions after `from`, up to and including `to`, in
+/// version order.
+fn due<'a, C: ?Sized>(migrations: &'a [Migration<C>], from: Option<&str>, to: &str) -> Vec<&'a Migration<C>> {
+    let from = from.and_then(parse_version);
+    let Some(to) = parse_version(to) else {
+        return Vec::new();
+    };
+    let mut due: Vec<_> = migrations
+        .iter()
+        .filter_map(|migration| parse_version(migration.version).map(|version| (version, migration)))
+        .filter(|(version, _)| from.is_none_or(|from| *version > from) && *version <= to)
+        .collect();
+    due.sort_by_key(|(version, _)| *version);
+    due.into_iter().map(|(_, migration)| migration).collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Ran,
+    AlreadyDone,
+    Failed(String),
+}
+
+/// Run `due` in order, skipping those named in `done` and adding each one
+/// that succeeds. Stops at the first failure, since later steps may build
+/// on it.
+fn run_migrations<C: ?Sized>(due: &[&Migration<C>], context: &C, done: &mut Vec<String>) -> Vec<(&'static str, Outcome)> {
+    let mut outcomes = Vec::new();
+    for migration in due {
+        if done.iter().any(|name| name == migration.name) {
+            outcomes.push((migration.name, Outcome::AlreadyDone));
+            continue;
+        }
+        match (migration.run)(context) {
+            Ok(()) => {
+                done.push(migration.name.to_string());
+                outcomes.push((migration.name, Outcome::Ran));
+            }
+            Err(err) => {
+                outcomes.push((migration.name, Outcome::Failed(err)));
+                break;
+            }
+        }
+    }
+    outcomes
+}
+
+/// Add `change` unless it is already the latest entry (a retried launch),
+/// keeping the last [`MAX_HISTORY`].
+fn append(history: &mut Vec<VersionChange>, change: VersionChange) {
+    let repeated = history
+        .last()
+        .is_some_and(|last| last.from == change.from && last.to == change.to);
+    if !repeated {
+        history.push(change);
+    }
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+}
+
+pub(crate) fn upgraded_this_run(app: &AppHandle) -> bool {
+    app.try_state::<VersionState>()
+        .is_some_and(|state| state.upgrade.get().is_some())
+}
+
+pub(crate) fn upgraded_from(app: &AppHandle) -> Option<String> {
+    app.try_state::<VersionState>()
+        .and_then(|state| state.upgrade.get().cloned().flatten())
+}
+
+fn store(app: &AppHandle, key: &str, value: Value) {
+    if let Err(err) = store_runtime_pref(app, key, value) {
+        log_event(app, "WARN", "version_history_save_failed", &[("key", key), ("error", &err.to_string())]);
+    }
+}
+
+/// Compare this launch's version with the last one and, on a change,
+/// record it and run the migrations it calls for. Called from `setup` once
+/// prefs are loaded.
+pub(crate) fn record_launch(app: &AppHandle) {
+    let current = env!("CARGO_PKG_VERSION");
+    let prefs = app.state::<RuntimePrefs>();
+    let last = prefs.get(PREF_LAST_RUN_VERSION).and_then(|value| value.as_str().map(str::to_string));
+    let first_run = app.state::<OnboardingState>().first_run;
+    let from = match detect(last.as_deref(), current, first_run) {
+        Launch::SameVersion => return,
+        Launch::FreshInstall => {
+            store(app, PREF_LAST_RUN_VERSION, Value::String(current.to_string()));
+            return;
+        }
+        Launch::Changed { from } => from,
+    };
+    let upgrade = is_upgrade(from.as_deref(), current);
+    log_event(
+        app,
+        "INFO",
+        "app_version_changed",
+        &[
+            ("from", from.as_deref().unwrap_or("unknown")),
+            ("to", current),
+            ("upgrade", &upgrade.to_string()),
+        ],
+    );
+    if upgrade {
+        let _ = app.state::<VersionState>().upgrade.set(from.clone());
+    }
+    let mut history: Vec<VersionChange> = prefs
+        .get(PREF_VERSION_HISTORY)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    append(
+        &mut history,
+        VersionChange {
+            from: from.clone(),
+            to: current.to_string(),
+            at: logging::now_iso8601(),
+        },
+    );
+    store(app, PREF_VERSION_HISTORY, serde_json::to_value(&history).unwrap_or(Value::Null));
+
+    if upgrade {
+        let mut done: Vec<String> = prefs
+            .get(PREF_COMPLETED_MIGRATIONS)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        let outcomes = run_migrations(&due(MIGRATIONS, from.as_deref(), current), app, &mut done);
+        store(app, PREF_COMPLETED_MIGRATIONS, serde_json::to_value(&done).unwrap_or(Value::Null));
+        let mut failed = false;
+        for (name, outcome) in outcomes {
+            match outcome {
+                Outcome::Ran => log_event(app, "INFO", "migration_ran", &[("name", name)]),
+                Outcome::AlreadyDone => log_event(app, "INFO", "migration_skipped", &[("name", name)]),
+                Outcome::Failed(err) => {
+                    failed = true;
+                    log_event(app, "ERROR", "migration_failed", &[("name", name), ("error", &err)]);
+                }
+            }
+        }
+        if failed {
+            return;
+        }
+    }
+    store(app, PREF_LAST_RUN_VERSION, Value::String(current.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::{
+        append, detect, due, is_upgrade, parse_version, run_migrations, Launch, Migration, Outcome, VersionChange,
+        MAX_HISTORY, MIGRATIONS,
+    };
+
+    /// Records which migrations ran, and fails the one named in `fail`.
+    #[derive(Default)]
+    struct Log {
+        ran: RefCell<Vec<&'static str>>,
+        fail: RefCell<Option<&'static str>>,
+    }
+
+    fn step(log: &Log, name: &'static str) -> Result<(), String> {
+        if *log.fail.borrow() == Some(name) {
+            return Err("disk full".to_string());
+        }
+        log.ran.borrow_mut().push(name);
+        Ok(())
+    }
+
+    const TEST_MIGRATIONS: &[Migration<Log>] = &[
+        Migration { version: "2.6.0", name: "cache-v3", run: |log| step(log, "cache-v3") },
+        Migration { version: "2.5.10", name: "prefs-rename", run: |log| step(log, "prefs-rename") },
+        Migration { version: "2.6.0", name: "cache-v3-index", run: |log| step(log, "cache-v3-index") },
+        Migration { version: "2.7.0", name: "future", run: |log| step(log, "future") },
+    ];
+
+    fn names(due: &[&Migration<Log>]) -> Vec<&'static str> {
+        due.iter().map(|migration| migration.name).collect()
+    }
+
+    #[test]
+    fn launches_are_told_apart() {
+        assert_eq!(detect(Some("2.5.23"), "2.5.23", false), Launch::SameVersion);
+        assert_eq!(detect(None, "2.5.23", true), Launch::FreshInstall);
+        assert_eq!(detect(None, "2.5.23", false), Launch::Changed { from: None }, "predates the record");
+        assert_eq!(
+            detect(Some("2.5.22"), "2.5.23", false),
+            Launch::Changed { from: Some("2.5.22".to_string()) }
+        );
+        assert!(is_upgrade(Some("2.5.9"), "2.5.10"), "numeric, not lexical");
+        assert!(is_upgrade(None, "2.5.23"));
+        assert!(!is_upgrade(Some("2.6.0"), "2.5.23"), "a downgrade");
+        assert_eq!(parse_version("2.6.0-beta.1"), Some((2, 6, 0)));
+        assert_eq!(parse_version("2.6"), None);
+    }
+
+    #[test]
+    fn due_migrations_run_in_version_order_within_the_range() {
+        assert_eq!(
+            names(&due(TEST_MIGRATIONS, Some("2.5.9"), "2.6.0")),
+            ["prefs-rename", "cache-v3", "cache-v3-index"],
+            "by version, then registry order; none past the new version"
+        );
+        assert_eq!(names(&due(TEST_MIGRATIONS, Some("2.5.10"), "2.6.1")), ["cache-v3", "cache-v3-index"]);
+        assert_eq!(names(&due(TEST_MIGRATIONS, None, "2.5.23")), ["prefs-rename"]);
+        assert!(due(TEST_MIGRATIONS, Some("2.6.0"), "2.6.0").is_empty());
+    }
+
+    #[test]
+    fn a_failed_run_resumes_without_repeating_finished_steps() {
+        let log = Log::default();
+        let due = due(TEST_MIGRATIONS, Some("2.5.0"), "2.6.0");
+        let mut done = Vec::new();
+        *log.fail.borrow_mut() = Some("cache-v3");
+        let outcomes = run_migrations(&due, &log, &mut done);
+        assert_eq!(outcomes.last().map(|(name, _)| *name), Some("cache-v3"));
+        assert!(matches!(outcomes.last(), Some((_, Outcome::Failed(_)))));
+        assert_eq!(*log.ran.borrow(), ["prefs-rename"], "stops at the failure");
+        assert_eq!(done, ["prefs-rename"]);
+
+        *log.fail.borrow_mut() = None;
+        let outcomes = run_migrations(&due, &log, &mut done);
+        assert_eq!(outcomes[0], ("prefs-rename", Outcome::AlreadyDone));
+        assert_eq!(*log.ran.borrow(), ["prefs-rename", "cache-v3", "cache-v3-index"]);
+
+        let again = run_migrations(&due, &log, &mut done);
+        assert!(again.iter().all(|(_, outcome)| *outcome == Outcome::AlreadyDone));
+        assert_eq!(log.ran.borrow().len(), 3);
+    }
+
+    #[test]
+    fn history_is_bounded_and_a_retried_launch_is_recorded_once() {
+        let change = |from: u32, to: u32| VersionChange {
+            from: Some(format!("2.5.{from}")),
+            to: format!("2.5.{to}"),
+            at: "2026-10-18T00:00:00.000Z".to_string(),
+        };
+        let mut history = Vec::new();
+        append(&mut history, change(1, 2));
+        append(&mut history, change(1, 2));
+        assert_eq!(history.len(), 1);
+        for n in 2..40 {
+            append(&mut history, change(n, n + 1));
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history.last(), Some(&change(39, 40)));
+    }
+
+    #[test]
+    fn the_registry_is_ordered_and_unique() {
+        let versions: Vec<_> = MIGRATIONS.iter().map(|migration| parse_version(migration.version)).collect();
+        assert!(versions.iter().all(Option::is_some), "every version parses");
+        assert!(versions.windows(2).all(|pair| pair[0] <= pair[1]), "declared in version order");
+        let mut names: Vec<_> = MIGRATIONS.iter().map(|migration| migration.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), MIGRATIONS.len());
+    }
+}
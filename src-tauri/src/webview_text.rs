@@ -0,0 +1,327 @@
+//! Spellcheck and autofill in the main and settings webviews. What each
+//! engine lets us configure differs:
+//!
+//! - WebKitGTK: spellcheck on/off and its dictionaries, on the shared web
+//!   context. No autofill to suppress.
+//! - WebView2: general and password autofill, through the settings object.
+//!   Spellcheck is always on; its language is passed as `--lang` when the
+//!   webview environment is created, so a new language needs a restart.
+//! - WKWebView: neither.
+//!
+//! The prefs are applied right after each window is built and again when
+//! they change; `restart_required` says when a window didn't take them.
+//! Unsupported settings are still saved and reported with `*_supported:
+//! false`.
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::logging::log_event;
+use crate::RuntimePrefs;
+
+pub(crate) const PREF_SPELLCHECK_ENABLED: &str = "spellcheckEnabled";
+pub(crate) const PREF_SPELLCHECK_LANGUAGE: &str = "spellcheckLanguage";
+pub(crate) const PREF_SUPPRESS_AUTOFILL: &str = "suppressAutofill";
+/// Windows these prefs are applied to.
+const WINDOW_LABELS: [&str; 2] = ["main", "settings"];
+/// How long a window gets to confirm it took the prefs.
+const APPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Read by WebView2 when it creates its environment.
+#[cfg(target_os = "windows")]
+const WEBVIEW2_ARGS_ENV: &str = "WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS";
+/// wry's own WebView2 arguments, which any we pass replace.
+#[cfg(target_os = "windows")]
+const WRY_BROWSER_ARGS: &str = "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection";
+
+pub(crate) fn spellcheck_supported() -> bool {
+    cfg!(target_os = "linux")
+}
+
+pub(crate) fn spellcheck_language_supported() -> bool {
+    cfg!(any(target_os = "linux", target_os = "windows"))
+}
+
+pub(crate) fn autofill_supported() -> bool {
+    cfg!(target_os = "windows")
+}
+
+/// `en-us`, `EN_US` and `en_US` all become `en_US`, the form WebKit's
+/// dictionaries use.
+pub(crate) fn normalize_language(lang: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid spellcheck language: {lang}");
+    let mut parts = lang.trim().split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+    let mut normalized = language.to_ascii_lowercase();
+    match parts.next() {
+        None => {}
+        Some(region)
+            if (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+                || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit())) =>
+        {
+            normalized.push('_');
+            normalized.push_str(&region.to_ascii_uppercase());
+        }
+        Some(_) => return Err(invalid()),
+    }
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(normalized)
+}
+
+pub(crate) fn validate_language_pref(value: &Value) -> Result<(), String> {
+    match value.as_str() {
+        Some(lang) => normalize_language(lang).map(|_| ()),
+        None => Err(format!("Runtime pref {PREF_SPELLCHECK_LANGUAGE} must be a string")),
+    }
+}
+
+/// The dictionary for a POSIX locale such as `de_DE.UTF-8@euro`; `None` for
+/// `C`/`POSIX` or anything unparsable.
+pub(crate) fn language_from_locale(locale: &str) -> Option<String> {
+    let base = locale.split(['.', '@']).next()?;
+    if base.eq_ignore_ascii_case("c") || base.eq_ignore_ascii_case("posix") {
+        return None;
+    }
+    normalize_language(base).ok()
+}
+
+/// Used while `spellcheckLanguage` is unset.
+fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|locale| language_from_locale(&locale))
+}
+
+/// The `spellcheckLanguage` pref as saved on disk, read before Tauri (and
+/// `RuntimePrefs`) start. Anything unreadable or invalid counts as unset.
+#[cfg(target_os = "windows")]
+fn read_launch_language(identifier: &str) -> Option<String> {
+    let path = crate::data_dir::resolve_data_dir_before_app(identifier)?.join(crate::RUNTIME_PREFS_FILE);
+    let prefs: Value = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    normalize_language(prefs.get(PREF_SPELLCHECK_LANGUAGE)?.as_str()?).ok()
+}
+
+/// Chromium's `--lang` takes `de-DE`, not the dictionary name `de_DE`.
+#[cfg(any(target_os = "windows", test))]
+fn browser_lang_arg(language: &str) -> String {
+    format!("--lang={}", language.replace('_', "-"))
+}
+
+/// Hand the saved language to WebView2, after any arguments already in the
+/// environment, and return it.
+///
+/// Must run before any threads are spawned (i.e. before Tauri starts).
+#[cfg(target_os = "windows")]
+pub(crate) fn apply_launch_language(identifier: &str) -> Option<String> {
+    let language = read_launch_language(identifier)?;
+    let args = match std::env::var(WEBVIEW2_ARGS_ENV) {
+        Ok(existing) if !existing.trim().is_empty() => format!("{existing} {}", browser_lang_arg(&language)),
+        _ => format!("{WRY_BROWSER_ARGS} {}", browser_lang_arg(&language)),
+    };
+    // SAFETY: called from `main` before any threads are spawned.
+    unsafe { std::env::set_var(WEBVIEW2_ARGS_ENV, args) };
+    Some(language)
+}
+
+/// Managed state: the spellcheck language this launch's webviews were
+/// created with. Only WebView2 takes it at creation; `None` elsewhere.
+#[derive(Default)]
+pub(crate) struct TextInputState {
+    pub(crate) launch_language: Option<String>,
+}
+
+/// Current values, also part of `get_desktop_runtime_info`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct TextInputSettings {
+    spellcheck_enabled: bool,
+    /// `None` follows the system locale.
+    spellcheck_language: Option<String>,
+    suppress_autofill: bool,
+    spellcheck_supported: bool,
+    spellcheck_language_supported: bool,
+    autofill_supported: bool,
+}
+
+pub(crate) fn settings(app: &AppHandle) -> TextInputSettings {
+    let prefs = app.try_state::<RuntimePrefs>();
+    let prefs = prefs.as_deref();
+    TextInputSettings {
+        spellcheck_enabled: prefs.is_none_or(|prefs| prefs.get_bool(PREF_SPELLCHECK_ENABLED, true)),
+        spellcheck_language: prefs
+            .and_then(|prefs| prefs.get(PREF_SPELLCHECK_LANGUAGE))
+            .and_then(|value| value.as_str().and_then(|lang| normalize_language(lang).ok())),
+        suppress_autofill: prefs.is_some_and(|prefs| prefs.get_bool(PREF_SUPPRESS_AUTOFILL, false)),
+        spellcheck_supported: spellcheck_supported(),
+        spellcheck_language_supported: spellcheck_language_supported(),
+        autofill_supported: autofill_supported(),
+    }
+}
+
+/// Returned by the setters.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TextInputChange {
+    #[serde(flatten)]
+    settings: TextInputSettings,
+    /// An open window didn't take the new values (or, on WebView2, the
+    /// language differs from the one it was created with); they apply once
+    /// it is next created.
+    restart_required: bool,
+}
+
+/// Apply the prefs to `window` if it is one of ours, without waiting for
+/// the webview to take them.
+pub(crate) fn apply_to_window(app: &AppHandle, window: &WebviewWindow) -> Result<(), String> {
+    if !WINDOW_LABELS.contains(&window.label()) {
+        return Ok(());
+    }
+    apply_platform(window, &settings(app), |_| {}).map_err(|e| e.to_string())
+}
+
+/// Only WebView2 fixes the language at creation.
+fn language_needs_restart(app: &AppHandle, settings: &TextInputSettings) -> bool {
+    cfg!(target_os = "windows")
+        && app
+            .try_state::<TextInputState>()
+            .is_some_and(|state| state.launch_language != settings.spellcheck_language)
+}
+
+/// Re-apply to every open window after a pref change and wait for each to
+/// confirm. Blocks; don't call on the main thread.
+pub(crate) fn apply_all(app: &AppHandle) -> TextInputChange {
+    let settings = settings(app);
+    let mut restart_required = language_needs_restart(app, &settings);
+    let (tx, rx) = mpsc::channel();
+    let mut waiting = Vec::new();
+    for label in WINDOW_LABELS {
+        let Some(window) = app.get_webview_window(label) else {
+            continue;
+        };
+        let done = tx.clone();
+        match apply_platform(&window, &settings, move |applied| {
+            let _ = done.send((label, applied));
+        }) {
+            Ok(()) => waiting.push(label),
+            Err(err) => {
+                restart_required = true;
+                log_event(app, "WARN", "text_input_apply_failed", &[("window", label), ("error", &err.to_string())]);
+            }
+        }
+    }
+    drop(tx);
+    while !waiting.is_empty() {
+        let Ok((label, applied)) = rx.recv_timeout(APPLY_TIMEOUT) else {
+            break;
+        };
+        waiting.retain(|waiting| *waiting != label);
+        if !applied {
+            restart_required = true;
+            log_event(app, "WARN", "text_input_apply_failed", &[("window", label), ("error", "webview refused")]);
+        }
+    }
+    for label in waiting {
+        restart_required = true;
+        log_event(app, "WARN", "text_input_apply_failed", &[("window", label), ("error", "no answer")]);
+    }
+    TextInputChange {
+        settings,
+        restart_required,
+    }
+}
+
+/// Queue the settings on the webview's thread; `done` hears whether they
+/// were taken.
+#[cfg(target_os = "linux")]
+fn apply_platform(
+    window: &WebviewWindow,
+    settings: &TextInputSettings,
+    done: impl FnOnce(bool) + Send + 'static,
+) -> tauri::Result<()> {
+    let enabled = settings.spellcheck_enabled;
+    let language = settings.spellcheck_language.clone().or_else(system_language);
+    window.with_webview(move |webview| {
+        use webkit2gtk::{WebContextExt, WebViewExt};
+        let Some(context) = webview.inner().context() else {
+            return done(false);
+        };
+        context.set_spell_checking_enabled(enabled);
+        if let Some(language) = &language {
+            context.set_spell_checking_languages(&[language.as_str()]);
+        }
+        done(true);
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn apply_platform(
+    window: &WebviewWindow,
+    settings: &TextInputSettings,
+    done: impl FnOnce(bool) + Send + 'static,
+) -> tauri::Result<()> {
+    let autofill = !settings.suppress_autofill;
+    window.with_webview(move |webview| unsafe {
+        use webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2Settings4;
+        use windows_core::Interface;
+        let settings = webview
+            .controller()
+            .CoreWebView2()
+            .and_then(|core| core.Settings())
+            .and_then(|settings| settings.cast::<ICoreWebView2Settings4>());
+        let applied = settings.and_then(|settings| {
+            settings.SetIsGeneralAutofillEnabled(autofill)?;
+            settings.SetIsPasswordAutosaveEnabled(autofill)
+        });
+        done(applied.is_ok());
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn apply_platform(
+    _window: &WebviewWindow,
+    _settings: &TextInputSettings,
+    done: impl FnOnce(bool) + Send + 'static,
+) -> tauri::Result<()> {
+    // Nothing to apply.
+    done(true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{browser_lang_arg, language_from_locale, normalize_language};
+
+    #[test]
+    fn languages_normalize_to_webkit_dictionary_names() {
+        assert_eq!(normalize_language("en-us").as_deref(), Ok("en_US"));
+        assert_eq!(normalize_language("DE").as_deref(), Ok("de"));
+        assert_eq!(normalize_language(" pt_BR ").as_deref(), Ok("pt_BR"));
+        assert_eq!(normalize_language("es-419").as_deref(), Ok("es_419"));
+        for bad in ["", "e", "english", "en-USA", "en-US-x", "en/US", "../en"] {
+            assert!(normalize_language(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn locale_strips_encoding_and_modifier() {
+        assert_eq!(language_from_locale("de_DE.UTF-8@euro").as_deref(), Some("de_DE"));
+        assert_eq!(language_from_locale("fr_CA.utf8").as_deref(), Some("fr_CA"));
+        assert_eq!(language_from_locale("C.UTF-8"), None);
+        assert_eq!(language_from_locale("POSIX"), None);
+    }
+
+    #[test]
+    fn webview2_language_uses_a_hyphen() {
+        assert_eq!(browser_lang_arg("de_DE"), "--lang=de-DE");
+        assert_eq!(browser_lang_arg("es_419"), "--lang=es-419");
+        assert_eq!(browser_lang_arg("fr"), "--lang=fr");
+    }
+}
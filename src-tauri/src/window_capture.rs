@@ -0,0 +1,414 @@
+//! File > Export Screenshot: a PNG of one window's web content for
+//! briefings, without the rest of the desktop. The webview renders the
+//! snapshot itself at device resolution, so HiDPI captures are sharp and
+//! other windows can't appear in them: WebKitGTK's `snapshot`, WKWebView's
+//! `takeSnapshotWithConfiguration` and WebView2's `CapturePreview`.
+//!
+//! A window under content protection is never captured, and neither is
+//! anything while the settings window (with its key fields) overlaps the
+//! target, unless the caller passes `include_settings`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::content_protection;
+use crate::disk_space;
+use crate::error::DesktopError;
+use crate::logging::{log_event, now_iso8601};
+
+pub(crate) const MENU_FILE_EXPORT_SCREENSHOT_ID: &str = "file.export_screenshot";
+const SETTINGS_LABEL: &str = "settings";
+
+/// `capture_window` result.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Capture {
+    path: String,
+    /// Device pixels.
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+}
+
+/// An RGBA image, 8 bits per channel, rows packed.
+struct Snapshot {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+/// A window's outer frame in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Frame {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl Frame {
+    fn of(window: &WebviewWindow) -> Option<Frame> {
+        let position = window.outer_position().ok()?;
+        let size = window.outer_size().ok()?;
+        Some(Frame {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        })
+    }
+
+    fn overlaps(&self, other: &Frame) -> bool {
+        let (ax, ay) = (i64::from(self.x), i64::from(self.y));
+        let (bx, by) = (i64::from(other.x), i64::from(other.y));
+        ax < bx + i64::from(other.width)
+            && bx < ax + i64::from(self.width)
+            && ay < by + i64::from(other.height)
+            && by < ay + i64::from(self.height)
+    }
+}
+
+fn screenshot_file_name() -> String {
+    let stamp: String = now_iso8601()
+        .chars()
+        .take(19)
+        .map(|c| if c == ':' { '-' } else { c })
+        .collect();
+    format!("world-monitor-{stamp}.png")
+}
+
+fn destination(app: &AppHandle, dest: Option<PathBuf>) -> Result<PathBuf, DesktopError> {
+    if let Some(path) = dest {
+        return match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => Ok(path),
+            _ => Err(DesktopError::InvalidArgument(format!(
+                "Screenshot path must end in .png: {}",
+                path.display()
+            ))),
+        };
+    }
+    // Without XDG_DOWNLOAD_DIR and the like, the conventional folder.
+    let dir = app
+        .path()
+        .download_dir()
+        .or_else(|_| app.path().home_dir().map(|home| home.join("Downloads")))
+        .map_err(|e| DesktopError::Internal(format!("Failed to resolve the Downloads folder: {e}")))?;
+    Ok(dir.join(screenshot_file_name()))
+}
+
+/// Refuse captures that could leak something protected.
+fn check_allowed(app: &AppHandle, window: &WebviewWindow, include_settings: bool) -> Result<(), DesktopError> {
+    let label = window.label();
+    if content_protection::for_new_window(app, label) {
+        return Err(DesktopError::CaptureBlocked(format!(
+            "Window '{label}' is protected from capture"
+        )));
+    }
+    if include_settings {
+        return Ok(());
+    }
+    if label == SETTINGS_LABEL {
+        return Err(DesktopError::CaptureBlocked(
+            "Capturing the settings window needs include_settings".to_string(),
+        ));
+    }
+    let Some(settings) = app.get_webview_window(SETTINGS_LABEL) else {
+        return Ok(());
+    };
+    let shown = settings.is_visible().unwrap_or(false) && !settings.is_minimized().unwrap_or(false);
+    let overlapping = match (Frame::of(window), Frame::of(&settings)) {
+        (Some(target), Some(settings)) => target.overlaps(&settings),
+        // Unknown placement: assume the worst.
+        _ => true,
+    };
+    if shown && overlapping {
+        return Err(DesktopError::CaptureBlocked(
+            "The settings window overlaps; close or move it, or pass include_settings".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Capture `label` to `dest` (or a timestamped file in Downloads).
+pub(crate) fn capture_window(
+    app: &AppHandle,
+    label: &str,
+    dest: Option<PathBuf>,
+    include_settings: bool,
+) -> Result<Capture, DesktopError> {
+    crate::require_trusted_window(label)?;
+    let window = app
+        .get_webview_window(label)
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("No open window: {label}")))?;
+    check_allowed(app, &window, include_settings)?;
+    let dest = destination(app, dest)?;
+    let snapshot = platform_snapshot(&window)?;
+    disk_space::ensure_space(app, &dest, snapshot.rgba.len() as u64)?;
+    write_png(&dest, &snapshot)?;
+    log_event(
+        app,
+        "INFO",
+        "window_captured",
+        &[
+            ("window", label),
+            ("size", &format!("{}x{}", snapshot.width, snapshot.height)),
+        ],
+    );
+    Ok(Capture {
+        path: dest.display().to_string(),
+        width: snapshot.width,
+        height: snapshot.height,
+        scale_factor: window.scale_factor().unwrap_or(1.0),
+    })
+}
+
+fn write_png(path: &Path, snapshot: &Snapshot) -> Result<(), DesktopError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| DesktopError::io("Failed to create", dir, e))?;
+    }
+    let file = fs::File::create(path).map_err(|e| DesktopError::io("Failed to create", path, e))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), snapshot.width, snapshot.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&snapshot.rgba))
+        .map_err(|e| DesktopError::io("Failed to write screenshot", path, e))
+}
+
+/// Cairo's ARGB32 (premultiplied, native-endian words, padded rows) to
+/// straight RGBA bytes.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn argb32_to_rgba(data: &[u8], width: usize, height: usize, stride: usize) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in data.chunks(stride).take(height) {
+        for pixel in row[..width * 4].chunks_exact(4) {
+            let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            let [a, r, g, b] = argb.to_be_bytes();
+            let unpremultiply = |c: u8| match a {
+                0 => 0,
+                255 => c,
+                _ => ((u32::from(c) * 255 + u32::from(a) / 2) / u32::from(a)).min(255) as u8,
+            };
+            rgba.extend_from_slice(&[unpremultiply(r), unpremultiply(g), unpremultiply(b), a]);
+        }
+    }
+    rgba
+}
+
+/// A PNG from the webview to straight RGBA.
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn decode_png(bytes: &[u8]) -> Result<Snapshot, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16 | png::Transformations::ALPHA);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| e.to_string())?;
+    data.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => data,
+        png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|px| [px[0], px[0], px[0], px[1]]).collect(),
+        other => return Err(format!("Unexpected snapshot color type {other:?}")),
+    };
+    Ok(Snapshot {
+        width: info.width,
+        height: info.height,
+        rgba,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn platform_snapshot(window: &WebviewWindow) -> Result<Snapshot, DesktopError> {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let (tx, rx) = mpsc::channel::<Result<Snapshot, String>>();
+    window
+        .with_webview(move |webview| {
+            use webkit2gtk::{SnapshotOptions, SnapshotRegion, WebViewExt};
+            webview.inner().snapshot(
+                SnapshotRegion::Visible,
+                SnapshotOptions::NONE,
+                None::<&webkit2gtk::gio::Cancellable>,
+                move |result| {
+                    let snapshot = result.map_err(|e| e.to_string()).and_then(|surface| {
+                        let surface = cairo::ImageSurface::try_from(surface)
+                            .map_err(|_| "Snapshot is not an image surface".to_string())?;
+                        surface.flush();
+                        let (width, height) = (surface.width().max(0) as usize, surface.height().max(0) as usize);
+                        let stride = surface.stride().max(0) as usize;
+                        let mut rgba = Vec::new();
+                        surface
+                            .with_data(|data| rgba = argb32_to_rgba(data, width, height, stride))
+                            .map_err(|e| e.to_string())?;
+                        Ok(Snapshot {
+                            width: width as u32,
+                            height: height as u32,
+                            rgba,
+                        })
+                    });
+                    let _ = tx.send(snapshot);
+                },
+            );
+        })
+        .map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))?;
+    rx.recv_timeout(Duration::from_secs(10))
+        .map_err(|_| DesktopError::Internal("The webview did not return a snapshot".to_string()))?
+        .map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))
+}
+
+/// The visible page at the screen's backing scale, as PNG via TIFF.
+#[cfg(target_os = "macos")]
+fn platform_snapshot(window: &WebviewWindow) -> Result<Snapshot, DesktopError> {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>();
+    window
+        .with_webview(move |webview| unsafe {
+            use block2::RcBlock;
+            use objc2::runtime::AnyObject;
+            use objc2_app_kit::{NSBitmapImageFileType, NSBitmapImageRep, NSBitmapImageRepPropertyKey, NSImage};
+            use objc2_foundation::{NSDictionary, NSError};
+            use objc2_web_kit::WKWebView;
+
+            let view: &WKWebView = &*webview.inner().cast();
+            let completion = RcBlock::new(move |image: *mut NSImage, error: *mut NSError| {
+                let png = match image.as_ref() {
+                    Some(image) => image
+                        .TIFFRepresentation()
+                        .and_then(|tiff| NSBitmapImageRep::imageRepWithData(&tiff))
+                        .and_then(|bitmap| {
+                            let properties = NSDictionary::<NSBitmapImageRepPropertyKey, AnyObject>::new();
+                            bitmap.representationUsingType_properties(NSBitmapImageFileType::PNG, &properties)
+                        })
+                        .map(|data| data.to_vec())
+                        .ok_or_else(|| "Snapshot could not be encoded".to_string()),
+                    None => Err(error
+                        .as_ref()
+                        .map_or_else(|| "No snapshot".to_string(), |error| error.localizedDescription().to_string())),
+                };
+                let _ = tx.send(png);
+            });
+            view.takeSnapshotWithConfiguration_completionHandler(None, &completion);
+        })
+        .map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))?;
+    let png = rx
+        .recv_timeout(Duration::from_secs(10))
+        .map_err(|_| DesktopError::Internal("The webview did not return a snapshot".to_string()))?
+        .map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))?;
+    decode_png(&png).map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))
+}
+
+/// The visible page as PNG, written by WebView2 into a memory stream.
+#[cfg(target_os = "windows")]
+fn platform_snapshot(window: &WebviewWindow) -> Result<Snapshot, DesktopError> {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>();
+    window
+        .with_webview(move |webview| unsafe {
+            use webview2_com::CapturePreviewCompletedHandler;
+            use webview2_com::Microsoft::Web::WebView2::Win32::COREWEBVIEW2_CAPTURE_PREVIEW_IMAGE_FORMAT_PNG;
+            use windows::Win32::System::Com::{IStream, STREAM_SEEK_SET};
+            use windows::Win32::UI::Shell::SHCreateMemStream;
+
+            fn read_all(stream: &IStream) -> Result<Vec<u8>, String> {
+                unsafe {
+                    stream.Seek(0, STREAM_SEEK_SET, None).map_err(|e| e.to_string())?;
+                    let mut png = Vec::new();
+                    let mut chunk = [0u8; 64 * 1024];
+                    loop {
+                        let mut read = 0u32;
+                        stream
+                            .Read(chunk.as_mut_ptr().cast(), chunk.len() as u32, Some(&raw mut read))
+                            .ok()
+                            .map_err(|e| e.to_string())?;
+                        if read == 0 {
+                            return Ok(png);
+                        }
+                        png.extend_from_slice(&chunk[..read as usize]);
+                    }
+                }
+            }
+
+            let Some(stream) = SHCreateMemStream(None) else {
+                let _ = tx.send(Err("Could not allocate a stream".to_string()));
+                return;
+            };
+            let core = match webview.controller().CoreWebView2() {
+                Ok(core) => core,
+                Err(e) => {
+                    let _ = tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let (completed, written) = (tx.clone(), stream.clone());
+            let handler = CapturePreviewCompletedHandler::create(Box::new(move |result| {
+                let _ = completed.send(result.map_err(|e| e.to_string()).and_then(|()| read_all(&written)));
+                Ok(())
+            }));
+            if let Err(e) = core.CapturePreview(COREWEBVIEW2_CAPTURE_PREVIEW_IMAGE_FORMAT_PNG, &stream, &handler) {
+                let _ = tx.send(Err(e.to_string()));
+            }
+        })
+        .map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))?;
+    let png = rx
+        .recv_timeout(Duration::from_secs(10))
+        .map_err(|_| DesktopError::Internal("The webview did not return a snapshot".to_string()))?
+        .map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))?;
+    decode_png(&png).map_err(|e| DesktopError::Internal(format!("Capture window: {e}")))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_snapshot(_window: &WebviewWindow) -> Result<Snapshot, DesktopError> {
+    Err(DesktopError::Unsupported(
+        "Window capture is not available on this platform".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{argb32_to_rgba, decode_png, Frame};
+
+    #[test]
+    fn settings_overlap_is_detected_on_any_shared_pixel() {
+        let main = Frame { x: 0, y: 0, width: 1200, height: 800 };
+        let over = Frame { x: 1100, y: 700, width: 600, height: 500 };
+        let beside = Frame { x: 1200, y: 0, width: 600, height: 500 };
+        let other_monitor = Frame { x: -1920, y: 0, width: 1920, height: 1080 };
+        assert!(main.overlaps(&over) && over.overlaps(&main));
+        assert!(!main.overlaps(&beside));
+        assert!(!main.overlaps(&other_monitor));
+    }
+
+    #[test]
+    fn premultiplied_argb_becomes_straight_rgba() {
+        let words: [u32; 3] = [0xff10_2030, 0x8040_0000, 0x0000_0000];
+        let mut data: Vec<u8> = words.iter().flat_map(|w| w.to_ne_bytes()).collect();
+        // Row padding beyond the pixels is skipped.
+        data.extend_from_slice(&[9, 9, 9, 9]);
+        let rgba = argb32_to_rgba(&data, 3, 1, 16);
+        assert_eq!(rgba, [0x10, 0x20, 0x30, 0xff, 0x80, 0x00, 0x00, 0x80, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn webview_pngs_decode_to_rgba() {
+        let encode = |color, pixels: &[u8]| {
+            let mut png = Vec::new();
+            let mut encoder = png::Encoder::new(&mut png, 2, 1);
+            encoder.set_color(color);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+            png
+        };
+        let rgb = decode_png(&encode(png::ColorType::Rgb, &[1, 2, 3, 4, 5, 6])).unwrap();
+        assert_eq!((rgb.width, rgb.height), (2, 1));
+        assert_eq!(rgb.rgba, [1, 2, 3, 255, 4, 5, 6, 255]);
+        let gray = decode_png(&encode(png::ColorType::GrayscaleAlpha, &[9, 128, 7, 255])).unwrap();
+        assert_eq!(gray.rgba, [9, 9, 9, 128, 7, 7, 7, 255]);
+    }
+}
@@ -0,0 +1,183 @@
+//! One creation at a time per window label. "Is it open? No: build it" is
+//! not atomic, so a double-clicked menu item or a command racing the menu
+//! could build the same label twice and fail with a duplicate label. A
+//! caller that finds the label being created waits for that to finish and
+//! then focuses the result, except on the main thread: builds from other
+//! threads are completed there, so it must not block, and the window being
+//! built comes up focused anyway.
+
+use std::collections::HashSet;
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread::ThreadId;
+
+use tauri::{AppHandle, Manager, WebviewWindow, WebviewWindowBuilder, Wry};
+
+/// Managed state: labels whose windows are being built right now.
+#[derive(Default)]
+pub(crate) struct WindowCreationState {
+    creating: Mutex<HashSet<String>>,
+    finished: Condvar,
+    main_thread: OnceLock<ThreadId>,
+}
+
+/// What a caller should do with a label.
+pub(crate) enum Claim<'a> {
+    /// Build it; others wait until the guard drops.
+    Create(CreationGuard<'a>),
+    /// It is open: focus it.
+    Existing,
+    /// Another caller is building it and this one can't wait.
+    InProgress,
+}
+
+/// Releases the label on drop, including when the build panics.
+pub(crate) struct CreationGuard<'a> {
+    state: &'a WindowCreationState,
+    label: String,
+}
+
+impl Drop for CreationGuard<'_> {
+    fn drop(&mut self) {
+        let mut creating = self.state.creating.lock().unwrap_or_else(|e| e.into_inner());
+        creating.remove(&self.label);
+        self.state.finished.notify_all();
+    }
+}
+
+impl WindowCreationState {
+    /// Record the event-loop thread; called once from `setup`.
+    pub(crate) fn mark_main_thread(&self) {
+        let _ = self.main_thread.set(std::thread::current().id());
+    }
+
+    fn on_main_thread(&self) -> bool {
+        self.main_thread.get() == Some(&std::thread::current().id())
+    }
+
+    pub(crate) fn claim(&self, label: &str, may_wait: bool, exists: impl Fn() -> bool) -> Claim<'_> {
+        let mut creating = self.creating.lock().unwrap_or_else(|e| e.into_inner());
+        while creating.contains(label) {
+            if !may_wait {
+                return Claim::InProgress;
+            }
+            creating = self.finished.wait(creating).unwrap_or_else(|e| e.into_inner());
+        }
+        if exists() {
+            return Claim::Existing;
+        }
+        creating.insert(label.to_string());
+        Claim::Create(CreationGuard {
+            state: self,
+            label: label.to_string(),
+        })
+    }
+}
+
+/// Focus the `label` window if it is open or being opened, otherwise
+/// `create` it, with no other creation of `label` running alongside.
+pub(crate) fn create_or_focus<E>(
+    app: &AppHandle,
+    label: &str,
+    focus: impl FnOnce() -> Result<(), E>,
+    create: impl FnOnce() -> Result<(), E>,
+) -> Result<(), E> {
+    let state = app.state::<WindowCreationState>();
+    let claim = state.claim(label, !state.on_main_thread(), || app.get_webview_window(label).is_some());
+    match claim {
+        Claim::Create(_guard) => create(),
+        Claim::Existing => focus(),
+        Claim::InProgress => Ok(()),
+    }
+}
+
+/// `builder.build()`, with a duplicate label reported as `None`: something
+/// outside the guard opened the window first, so the caller should focus it.
+pub(crate) fn build<M: Manager<Wry>>(builder: WebviewWindowBuilder<'_, Wry, M>) -> tauri::Result<Option<WebviewWindow>> {
+    match builder.build() {
+        Ok(window) => Ok(Some(window)),
+        Err(tauri::Error::WindowLabelAlreadyExists(_) | tauri::Error::WebviewLabelAlreadyExists(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{Claim, WindowCreationState};
+
+    /// Fake window system: `create` takes a while, then the window exists.
+    #[derive(Default)]
+    struct Fake {
+        state: WindowCreationState,
+        open: AtomicBool,
+        created: AtomicUsize,
+        focused: AtomicUsize,
+    }
+
+    impl Fake {
+        fn open_or_focus(&self, may_wait: bool) -> &'static str {
+            match self.state.claim("settings", may_wait, || self.open.load(Ordering::SeqCst)) {
+                Claim::Create(_guard) => {
+                    thread::sleep(Duration::from_millis(50));
+                    self.created.fetch_add(1, Ordering::SeqCst);
+                    self.open.store(true, Ordering::SeqCst);
+                    "created"
+                }
+                Claim::Existing => {
+                    self.focused.fetch_add(1, Ordering::SeqCst);
+                    "focused"
+                }
+                Claim::InProgress => "in progress",
+            }
+        }
+    }
+
+    #[test]
+    fn concurrent_callers_create_once_and_the_rest_focus() {
+        let fake = Arc::new(Fake::default());
+        let start = Arc::new(Barrier::new(4));
+        let callers: Vec<_> = (0..4)
+            .map(|_| {
+                let (fake, start) = (Arc::clone(&fake), Arc::clone(&start));
+                thread::spawn(move || {
+                    start.wait();
+                    fake.open_or_focus(true)
+                })
+            })
+            .collect();
+        let mut outcomes: Vec<_> = callers.into_iter().map(|caller| caller.join().unwrap()).collect();
+        outcomes.sort();
+        assert_eq!(outcomes, ["created", "focused", "focused", "focused"]);
+        assert_eq!(fake.created.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn a_caller_that_cannot_wait_backs_off() {
+        let fake = Fake::default();
+        let Claim::Create(guard) = fake.state.claim("settings", true, || false) else {
+            panic!("first caller should create");
+        };
+        assert!(matches!(fake.state.claim("settings", false, || false), Claim::InProgress));
+        assert!(matches!(fake.state.claim("panel-news", false, || false), Claim::Create(_)), "other labels are free");
+        drop(guard);
+        assert_eq!(fake.open_or_focus(false), "created");
+        assert_eq!(fake.open_or_focus(false), "focused");
+    }
+
+    #[test]
+    fn a_failed_or_panicking_creation_releases_the_label() {
+        let fake = Arc::new(Fake::default());
+        let panicking = Arc::clone(&fake);
+        let result = thread::spawn(move || {
+            let _claim = panicking.state.claim("settings", true, || false);
+            panic!("builder failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(fake.open_or_focus(true), "created", "not left waiting on the dead creator");
+    }
+}
@@ -0,0 +1,207 @@
+//! Remembered window placement. Geometry is stored in logical pixels in
+//! runtime prefs and clamped back onto a connected monitor when restored, so
+//! a window saved on an unplugged display still opens somewhere visible.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, Monitor, WebviewWindow};
+
+use crate::{store_runtime_pref, RuntimePrefs};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl WindowGeometry {
+    fn is_usable(&self) -> bool {
+        [self.x, self.y, self.width, self.height].iter().all(|v| v.is_finite())
+            && self.width > 0.0
+            && self.height > 0.0
+    }
+
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Fit inside `area`, shrinking to it (but not below `min_size`) and then
+    /// sliding the window back on screen.
+    pub(crate) fn clamped_to(self, area: WindowGeometry, min_size: (f64, f64)) -> WindowGeometry {
+        let width = self.width.min(area.width).max(min_size.0);
+        let height = self.height.min(area.height).max(min_size.1);
+        WindowGeometry {
+            x: self.x.clamp(area.x, area.x + (area.width - width).max(0.0)),
+            y: self.y.clamp(area.y, area.y + (area.height - height).max(0.0)),
+            width,
+            height,
+        }
+    }
+}
+
+/// Where a window saved on `monitor` (by name) should be placed: that
+/// monitor if it is still connected, the primary one if it is gone, and
+/// without a name the monitor holding the window's centre.
+pub(crate) fn pick_area(
+    saved: WindowGeometry,
+    monitor: Option<&str>,
+    areas: &[(Option<String>, WindowGeometry)],
+    primary: Option<WindowGeometry>,
+) -> Option<WindowGeometry> {
+    let found = match monitor {
+        Some(name) => areas.iter().find(|(known, _)| known.as_deref() == Some(name)),
+        None => {
+            let (centre_x, centre_y) = (saved.x + saved.width / 2.0, saved.y + saved.height / 2.0);
+            areas.iter().find(|(_, area)| area.contains_point(centre_x, centre_y))
+        }
+    };
+    found.map(|(_, area)| *area).or(primary)
+}
+
+/// A monitor's work area (excluding taskbars and docks) in logical pixels.
+fn logical_work_area(monitor: &Monitor) -> WindowGeometry {
+    let scale = monitor.scale_factor();
+    let area = monitor.work_area();
+    WindowGeometry {
+        x: f64::from(area.position.x) / scale,
+        y: f64::from(area.position.y) / scale,
+        width: f64::from(area.size.width) / scale,
+        height: f64::from(area.size.height) / scale,
+    }
+}
+
+/// The saved geometry under `pref_key`, clamped to the monitor holding its
+/// centre (or the primary monitor when that display is gone).
+pub(crate) fn restore(app: &AppHandle, pref_key: &str, min_size: (f64, f64)) -> Option<WindowGeometry> {
+    let saved = app.try_state::<RuntimePrefs>().and_then(|prefs| prefs.get(pref_key))?;
+    restore_value(app, saved, min_size)
+}
+
+/// Like `restore`, for geometry stored inside a larger pref value.
+pub(crate) fn restore_value(app: &AppHandle, saved: Value, min_size: (f64, f64)) -> Option<WindowGeometry> {
+    let saved: WindowGeometry = serde_json::from_value(saved).ok()?;
+    place(app, saved, None, min_size)
+}
+
+/// `saved` clamped onto the monitor [`pick_area`] chooses.
+pub(crate) fn place(
+    app: &AppHandle,
+    saved: WindowGeometry,
+    monitor: Option<&str>,
+    min_size: (f64, f64),
+) -> Option<WindowGeometry> {
+    if !saved.is_usable() {
+        return None;
+    }
+    let areas: Vec<(Option<String>, WindowGeometry)> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| (monitor.name().cloned(), logical_work_area(monitor)))
+        .collect();
+    let primary = app.primary_monitor().ok().flatten().as_ref().map(logical_work_area);
+    let area = pick_area(saved, monitor, &areas, primary)?;
+    Some(saved.clamped_to(area, min_size))
+}
+
+/// Name of the monitor `window` is on, if the platform reports one.
+pub(crate) fn monitor_name(window: &WebviewWindow) -> Option<String> {
+    window.current_monitor().ok().flatten().and_then(|monitor| monitor.name().cloned())
+}
+
+/// Move and resize `window` to `geometry` (logical pixels).
+pub(crate) fn apply(window: &WebviewWindow, geometry: WindowGeometry) -> Result<(), String> {
+    window
+        .set_size(tauri::LogicalSize::new(geometry.width, geometry.height))
+        .map_err(|e| e.to_string())?;
+    window
+        .set_position(tauri::LogicalPosition::new(geometry.x, geometry.y))
+        .map_err(|e| e.to_string())
+}
+
+/// `window`'s current placement, or `None` while it is minimized or
+/// maximized so the normal placement survives.
+pub(crate) fn capture(window: &WebviewWindow) -> Result<Option<WindowGeometry>, String> {
+    if window.is_minimized().unwrap_or(false) || window.is_maximized().unwrap_or(false) {
+        return Ok(None);
+    }
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    let position = window.outer_position().map_err(|e| e.to_string())?.to_logical::<f64>(scale);
+    let size = window.inner_size().map_err(|e| e.to_string())?.to_logical::<f64>(scale);
+    Ok(Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    }))
+}
+
+/// Record `window`'s current placement under `pref_key`.
+pub(crate) fn save(app: &AppHandle, pref_key: &str, window: &WebviewWindow) -> Result<(), String> {
+    let Some(geometry) = capture(window)? else {
+        return Ok(());
+    };
+    let value = serde_json::to_value(geometry).unwrap_or(Value::Null);
+    store_runtime_pref(app, pref_key, value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pick_area, WindowGeometry};
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> WindowGeometry {
+        WindowGeometry { x, y, width, height }
+    }
+
+    const MIN: (f64, f64) = (820.0, 480.0);
+
+    #[test]
+    fn geometry_inside_the_work_area_is_kept() {
+        let area = rect(0.0, 25.0, 1920.0, 1055.0);
+        let saved = rect(300.0, 120.0, 1100.0, 700.0);
+        assert_eq!(saved.clamped_to(area, MIN), saved);
+    }
+
+    #[test]
+    fn offscreen_and_oversized_geometry_is_pulled_back() {
+        let area = rect(0.0, 0.0, 1440.0, 900.0);
+        assert_eq!(rect(3000.0, -400.0, 1000.0, 700.0).clamped_to(area, MIN), rect(440.0, 0.0, 1000.0, 700.0));
+        assert_eq!(rect(100.0, 100.0, 2560.0, 1600.0).clamped_to(area, MIN), rect(0.0, 0.0, 1440.0, 900.0));
+        // Monitors to the left of the primary have negative origins.
+        let left = rect(-1280.0, 0.0, 1280.0, 1024.0);
+        assert_eq!(rect(-2000.0, 50.0, 900.0, 600.0).clamped_to(left, MIN), rect(-1280.0, 50.0, 900.0, 600.0));
+    }
+
+    #[test]
+    fn minimum_size_wins_over_a_tiny_work_area() {
+        let area = rect(0.0, 0.0, 800.0, 450.0);
+        assert_eq!(rect(50.0, 50.0, 900.0, 600.0).clamped_to(area, MIN), rect(0.0, 0.0, 820.0, 480.0));
+    }
+
+    #[test]
+    fn saved_monitor_falls_back_to_the_primary_when_gone() {
+        let laptop = rect(0.0, 0.0, 1440.0, 900.0);
+        let external = rect(1440.0, 0.0, 2560.0, 1440.0);
+        let areas = [(Some("eDP-1".to_string()), laptop), (Some("DP-2".to_string()), external)];
+        let saved = rect(2000.0, 100.0, 900.0, 600.0);
+
+        assert_eq!(pick_area(saved, Some("DP-2"), &areas, Some(laptop)), Some(external));
+        // Saved on a monitor that is unplugged: primary, even though the
+        // coordinates now land on another display.
+        assert_eq!(pick_area(saved, Some("HDMI-1"), &areas, Some(laptop)), Some(laptop));
+        assert_eq!(pick_area(saved, None, &areas, Some(laptop)), Some(external));
+        assert_eq!(pick_area(rect(9000.0, 0.0, 100.0, 100.0), None, &areas, Some(laptop)), Some(laptop));
+        assert_eq!(pick_area(saved, Some("HDMI-1"), &[], None), None);
+    }
+
+    #[test]
+    fn rejects_degenerate_saved_values() {
+        assert!(!rect(0.0, 0.0, 0.0, 600.0).is_usable());
+        assert!(!rect(f64::NAN, 0.0, 900.0, 600.0).is_usable());
+        assert!(rect(-100.0, 0.0, 900.0, 600.0).is_usable());
+        let parsed: WindowGeometry = serde_json::from_str(r#"{"x":10,"y":20,"width":900,"height":600}"#).unwrap();
+        assert_eq!(parsed, rect(10.0, 20.0, 900.0, 600.0));
+    }
+}
@@ -0,0 +1,499 @@
+//! The arrangement of open windows, kept in `window-layout.json` in the app
+//! data dir: `current` is rewritten whenever a panel window opens or closes,
+//! once a moved or resized window settles, and at quit, and `named` holds
+//! layouts the user saved
+//! ("two-monitor ops"). At startup, once the sidecar is up, the panel
+//! windows in `current` are reopened; main and settings restore their own
+//! placement. Restoring a named layout also moves main and settings.
+//!
+//! Builds before this file kept open panels in the `panelWindows` pref,
+//! which is read once when there is no layout yet.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::error::DesktopError;
+use crate::logging::log_event;
+use crate::panel_windows::{self, panel_id_from_label, validate_panel_id};
+use crate::window_geometry::{self, WindowGeometry};
+use crate::{data_dir, open_settings_window, store_runtime_pref, RuntimePrefs, SETTINGS_WINDOW_MIN_SIZE};
+
+pub(crate) const LAYOUT_FILE: &str = "window-layout.json";
+const LEGACY_PREF_PANEL_WINDOWS: &str = "panelWindows";
+const MAX_NAME_CHARS: usize = 64;
+/// `minWidth`/`minHeight` of main in tauri.conf.json.
+const MAIN_MIN_SIZE: (f64, f64) = (1200.0, 720.0);
+/// How long a window must stay put after a move or resize before `current`
+/// is rewritten, so a drag is one write.
+const SETTLE_DELAY: Duration = Duration::from_millis(750);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WindowKind {
+    Main,
+    Settings,
+    Panel,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WindowEntry {
+    label: String,
+    kind: WindowKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    panel: Option<String>,
+    /// `None` while minimized or maximized with nothing saved before.
+    geometry: Option<WindowGeometry>,
+    monitor: Option<String>,
+    /// 0 for the focused window, then the rest. Restores open the highest
+    /// first so the focused one ends on top.
+    z_order: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Layout {
+    windows: Vec<WindowEntry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LayoutFile {
+    #[serde(default)]
+    current: Option<Layout>,
+    #[serde(default)]
+    named: BTreeMap<String, Layout>,
+}
+
+/// Managed state: set while windows are being reopened, so each new window
+/// doesn't record a half-restored layout.
+#[derive(Default)]
+pub(crate) struct WindowLayoutState {
+    restoring: AtomicBool,
+    restored_at_startup: AtomicBool,
+    /// Bumped by every move or resize; a pending write runs only if no
+    /// later one came in.
+    moves: AtomicU64,
+}
+
+impl WindowLayoutState {
+    fn bump(&self) -> u64 {
+        self.moves.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn is_latest(&self, generation: u64) -> bool {
+        self.moves.load(Ordering::SeqCst) == generation
+    }
+}
+
+fn classify(label: &str) -> Option<(WindowKind, Option<&'static str>)> {
+    match label {
+        "main" => Some((WindowKind::Main, None)),
+        "settings" => Some((WindowKind::Settings, None)),
+        _ => panel_id_from_label(label).map(|id| (WindowKind::Panel, Some(id))),
+    }
+}
+
+pub(crate) fn validate_name(name: &str) -> Result<String, DesktopError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS || name.chars().any(char::is_control) {
+        return Err(DesktopError::InvalidArgument(format!(
+            "Layout names must be 1 to {MAX_NAME_CHARS} characters without control characters"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+fn layout_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(data_dir::resolve_data_dir(app)?.join(LAYOUT_FILE))
+}
+
+fn load(app: &AppHandle) -> LayoutFile {
+    layout_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, file: &LayoutFile) -> io::Result<()> {
+    let path = layout_path(app).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let serialized = serde_json::to_string_pretty(file).map_err(io::Error::other)?;
+    let staging = path.with_file_name(format!("{LAYOUT_FILE}.tmp"));
+    fs::write(&staging, serialized)?;
+    fs::rename(&staging, &path)
+}
+
+/// Order windows for `z_order`: focused first, the rest by label.
+fn z_ordered(mut windows: Vec<(String, bool)>) -> Vec<String> {
+    windows.sort_by(|(a, a_focused), (b, b_focused)| b_focused.cmp(a_focused).then_with(|| a.cmp(b)));
+    windows.into_iter().map(|(label, _)| label).collect()
+}
+
+/// Every open main/settings/panel window except `closing`. Windows that
+/// can't report geometry (minimized) keep what `previous` had for them.
+fn capture(app: &AppHandle, closing: &[&str], previous: Option<&Layout>) -> Layout {
+    let open: BTreeMap<String, WebviewWindow> = app
+        .webview_windows()
+        .into_iter()
+        .filter(|(label, _)| !closing.contains(&label.as_str()) && classify(label).is_some())
+        .collect();
+    let order = z_ordered(
+        open.iter()
+            .map(|(label, window)| (label.clone(), window.is_focused().unwrap_or(false)))
+            .collect(),
+    );
+    let windows = order
+        .iter()
+        .enumerate()
+        .filter_map(|(z_order, label)| {
+            let (kind, panel) = classify(label)?;
+            let window = open.get(label)?;
+            let earlier = previous.and_then(|layout| layout.windows.iter().find(|entry| &entry.label == label));
+            let geometry = window_geometry::capture(window)
+                .ok()
+                .flatten()
+                .or_else(|| earlier.and_then(|entry| entry.geometry));
+            let monitor = window_geometry::monitor_name(window).or_else(|| earlier.and_then(|entry| entry.monitor.clone()));
+            Some(WindowEntry {
+                label: label.clone(),
+                kind,
+                panel: panel.map(str::to_string),
+                geometry,
+                monitor,
+                z_order,
+            })
+        })
+        .collect();
+    Layout { windows }
+}
+
+/// Rewrite `current` from the open windows. `closing` are windows going
+/// away that may still be registered.
+pub(crate) fn record_current(app: &AppHandle, closing: &[&str]) {
+    if app
+        .try_state::<WindowLayoutState>()
+        .is_some_and(|state| state.restoring.load(Ordering::SeqCst))
+    {
+        return;
+    }
+    let mut file = load(app);
+    file.current = Some(capture(app, closing, file.current.as_ref()));
+    if let Err(err) = save(app, &file) {
+        log_event(app, "WARN", "window_layout_save_failed", &[("error", &err.to_string())]);
+    }
+}
+
+/// `label` was moved or resized: rewrite `current` once it has settled, so
+/// the arrangement survives a crash and not just a clean quit.
+pub(crate) fn record_when_settled(app: &AppHandle, label: &str) {
+    if classify(label).is_none() {
+        return;
+    }
+    let Some(state) = app.try_state::<WindowLayoutState>() else {
+        return;
+    };
+    let generation = state.bump();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SETTLE_DELAY).await;
+        if app.state::<WindowLayoutState>().is_latest(generation) {
+            record_current(&app, &[]);
+        }
+    });
+}
+
+/// `panelWindows` from older builds, as a layout of panels only.
+fn legacy_layout(saved: &Value) -> Option<Layout> {
+    let entries = saved.as_object()?;
+    let windows = entries
+        .iter()
+        .filter_map(|(id, geometry)| {
+            let id = validate_panel_id(id).ok()?;
+            Some(WindowEntry {
+                label: format!("panel-{id}"),
+                kind: WindowKind::Panel,
+                panel: Some(id.to_string()),
+                geometry: serde_json::from_value(geometry.clone()).ok(),
+                monitor: None,
+                z_order: 0,
+            })
+        })
+        .collect();
+    Some(Layout { windows })
+}
+
+/// Panels in `layout`, back to front, with validated ids.
+fn panels_back_to_front(layout: &Layout) -> Vec<(&'static str, &WindowEntry)> {
+    let mut panels: Vec<_> = layout
+        .windows
+        .iter()
+        .filter(|entry| entry.kind == WindowKind::Panel)
+        .filter_map(|entry| {
+            let id = validate_panel_id(entry.panel.as_deref()?).ok()?;
+            Some((id, entry))
+        })
+        .collect();
+    panels.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.z_order));
+    panels
+}
+
+fn placed(app: &AppHandle, entry: &WindowEntry, min_size: (f64, f64)) -> Option<WindowGeometry> {
+    window_geometry::place(app, entry.geometry?, entry.monitor.as_deref(), min_size)
+}
+
+/// Open the panels in `layout`, back to front. Returns the labels opened.
+fn open_panels(app: &AppHandle, layout: &Layout) -> Vec<String> {
+    let mut opened = Vec::new();
+    for (id, entry) in panels_back_to_front(layout) {
+        let geometry = placed(app, entry, panel_windows::MIN_SIZE);
+        match panel_windows::create_panel_window(app, id, geometry) {
+            Ok(()) => opened.push(entry.label.clone()),
+            Err(err) => {
+                log_event(app, "WARN", "panel_window_restore_failed", &[("panel", id), ("error", &err.to_string())]);
+            }
+        }
+    }
+    opened
+}
+
+/// Reopen the panel windows open at the last quit. Runs once, after the
+/// sidecar handshake (or its timeout).
+pub(crate) fn restore_at_startup(app: &AppHandle) {
+    let state = app.state::<WindowLayoutState>();
+    if state.restored_at_startup.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let mut file = load(app);
+    let legacy = app.try_state::<RuntimePrefs>().and_then(|prefs| prefs.get(LEGACY_PREF_PANEL_WINDOWS));
+    if file.current.is_none() {
+        file.current = legacy.as_ref().and_then(legacy_layout);
+    }
+    let Some(layout) = file.current.clone() else {
+        return;
+    };
+    state.restoring.store(true, Ordering::SeqCst);
+    open_panels(app, &layout);
+    state.restoring.store(false, Ordering::SeqCst);
+    record_current(app, &[]);
+    if legacy.is_some() {
+        if let Err(err) = store_runtime_pref(app, LEGACY_PREF_PANEL_WINDOWS, Value::Null) {
+            log_event(app, "WARN", "window_layout_migrate_failed", &[("error", &err.to_string())]);
+        }
+    }
+}
+
+/// Save the open windows as `name`, replacing any layout with that name.
+pub(crate) fn save_snapshot(app: &AppHandle, name: &str) -> Result<Layout, DesktopError> {
+    let name = validate_name(name)?;
+    let mut file = load(app);
+    let layout = capture(app, &[], file.current.as_ref());
+    file.named.insert(name.clone(), layout.clone());
+    save(app, &file).map_err(|e| DesktopError::Internal(format!("Failed to save window layout: {e}")))?;
+    log_event(app, "INFO", "window_layout_saved", &[("name", &name), ("windows", &layout.windows.len().to_string())]);
+    Ok(layout)
+}
+
+pub(crate) fn names(app: &AppHandle) -> Vec<String> {
+    load(app).named.into_keys().collect()
+}
+
+/// The saved layouts by name, for a configuration export.
+pub(crate) fn named_layouts(app: &AppHandle) -> BTreeMap<String, Layout> {
+    load(app).named
+}
+
+/// What `import_named` did.
+#[derive(Debug, Default)]
+pub(crate) struct ImportedLayouts {
+    pub(crate) written: Vec<String>,
+    /// Name and why it was refused.
+    pub(crate) refused: Vec<(String, String)>,
+}
+
+/// Save `layouts` next to the existing ones, or instead of them when
+/// `replace`.
+pub(crate) fn import_named(app: &AppHandle, layouts: BTreeMap<String, Layout>, replace: bool) -> Result<ImportedLayouts, DesktopError> {
+    let mut file = load(app);
+    if replace {
+        file.named.clear();
+    }
+    let mut imported = ImportedLayouts::default();
+    for (name, layout) in layouts {
+        match validate_name(&name) {
+            Ok(valid) => {
+                file.named.insert(valid.clone(), layout);
+                imported.written.push(valid);
+            }
+            Err(err) => imported.refused.push((name, err.to_string())),
+        }
+    }
+    save(app, &file).map_err(|e| DesktopError::Internal(format!("Failed to save window layout: {e}")))?;
+    Ok(imported)
+}
+
+/// What `restore_window_layout` did.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct RestoreResult {
+    opened: Vec<String>,
+    closed: Vec<String>,
+    moved: Vec<String>,
+}
+
+/// Apply the layout saved as `name`: panels not in it close, missing ones
+/// open, and main and settings move to their saved places.
+pub(crate) fn restore_named(app: &AppHandle, name: &str) -> Result<RestoreResult, DesktopError> {
+    let name = validate_name(name)?;
+    let layout = load(app)
+        .named
+        .remove(&name)
+        .ok_or_else(|| DesktopError::InvalidArgument(format!("No window layout named {name}")))?;
+    let mut result = RestoreResult::default();
+    let state = app.state::<WindowLayoutState>();
+    state.restoring.store(true, Ordering::SeqCst);
+
+    for (label, window) in app.webview_windows() {
+        let wanted = layout.windows.iter().any(|entry| entry.label == label);
+        if panel_id_from_label(&label).is_some() && !wanted {
+            let _ = window.close();
+            result.closed.push(label);
+        }
+    }
+    let mut fixed: Vec<&WindowEntry> = layout
+        .windows
+        .iter()
+        .filter(|entry| entry.kind != WindowKind::Panel)
+        .collect();
+    fixed.sort_by_key(|entry| std::cmp::Reverse(entry.z_order));
+    for entry in fixed {
+        let min_size = match entry.kind {
+            WindowKind::Settings => SETTINGS_WINDOW_MIN_SIZE,
+            _ => MAIN_MIN_SIZE,
+        };
+        if entry.kind == WindowKind::Settings && app.get_webview_window("settings").is_none() {
+            if let Err(err) = open_settings_window(app) {
+                log_event(app, "WARN", "window_layout_settings_failed", &[("error", &err)]);
+                continue;
+            }
+            result.opened.push(entry.label.clone());
+        }
+        let (Some(window), Some(geometry)) = (app.get_webview_window(&entry.label), placed(app, entry, min_size)) else {
+            continue;
+        };
+        match window_geometry::apply(&window, geometry) {
+            Ok(()) => result.moved.push(entry.label.clone()),
+            Err(err) => log_event(app, "WARN", "window_layout_move_failed", &[("window", &entry.label), ("error", &err)]),
+        }
+    }
+    for (_, entry) in panels_back_to_front(&layout) {
+        let (Some(window), Some(geometry)) = (
+            app.get_webview_window(&entry.label),
+            placed(app, entry, panel_windows::MIN_SIZE),
+        ) else {
+            continue;
+        };
+        if window_geometry::apply(&window, geometry).is_ok() {
+            result.moved.push(entry.label.clone());
+        }
+    }
+    let missing = Layout {
+        windows: layout
+            .windows
+            .iter()
+            .filter(|entry| app.get_webview_window(&entry.label).is_none())
+            .cloned()
+            .collect(),
+    };
+    result.opened.extend(open_panels(app, &missing));
+    if let Some(front) = layout.windows.iter().min_by_key(|entry| entry.z_order) {
+        if let Some(window) = app.get_webview_window(&front.label) {
+            let _ = window.set_focus();
+        }
+    }
+
+    state.restoring.store(false, Ordering::SeqCst);
+    let closed: Vec<&str> = result.closed.iter().map(String::as_str).collect();
+    record_current(app, &closed);
+    log_event(
+        app,
+        "INFO",
+        "window_layout_restored",
+        &[
+            ("name", &name),
+            ("opened", &result.opened.len().to_string()),
+            ("closed", &result.closed.len().to_string()),
+        ],
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{legacy_layout, panels_back_to_front, validate_name, z_ordered, Layout, WindowKind, WindowLayoutState};
+
+    #[test]
+    fn only_the_last_move_in_a_burst_is_written() {
+        let state = WindowLayoutState::default();
+        let first = state.bump();
+        let second = state.bump();
+        assert!(!state.is_latest(first));
+        assert!(state.is_latest(second));
+    }
+
+    #[test]
+    fn focused_window_is_frontmost() {
+        let order = z_ordered(vec![
+            ("panel-map".to_string(), false),
+            ("main".to_string(), false),
+            ("settings".to_string(), true),
+        ]);
+        assert_eq!(order, ["settings", "main", "panel-map"]);
+    }
+
+    #[test]
+    fn legacy_pref_becomes_a_panel_layout() {
+        let layout = legacy_layout(&json!({
+            "map": {"x": 10, "y": 20, "width": 800, "height": 600},
+            "intel": null,
+            "bogus": null,
+        }))
+        .unwrap();
+        let labels: Vec<&str> = layout.windows.iter().map(|entry| entry.label.as_str()).collect();
+        assert_eq!(labels, ["panel-intel", "panel-map"]);
+        assert!(layout.windows.iter().all(|entry| entry.kind == WindowKind::Panel));
+        assert!(layout.windows[0].geometry.is_none());
+        assert_eq!(layout.windows[1].geometry.unwrap().width, 800.0);
+    }
+
+    #[test]
+    fn panels_restore_back_to_front_and_skip_unknown_ids() {
+        let layout: Layout = serde_json::from_value(json!({"windows": [
+            {"label": "main", "kind": "main", "geometry": null, "monitor": null, "z_order": 1},
+            {"label": "panel-map", "kind": "panel", "panel": "map", "geometry": null, "monitor": "DP-2", "z_order": 0},
+            {"label": "panel-intel", "kind": "panel", "panel": "intel", "geometry": null, "monitor": null, "z_order": 2},
+            {"label": "panel-x", "kind": "panel", "panel": "../x", "geometry": null, "monitor": null, "z_order": 3},
+        ]}))
+        .unwrap();
+        let ids: Vec<&str> = panels_back_to_front(&layout).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["intel", "map"]);
+    }
+
+    #[test]
+    fn layout_names_are_bounded() {
+        assert_eq!(validate_name("  two-monitor ops ").unwrap(), "two-monitor ops");
+        assert!(validate_name("").is_err());
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name("a\nb").is_err());
+        assert!(validate_name(&"x".repeat(65)).is_err());
+    }
+}
@@ -0,0 +1,635 @@
+//! WebSocket connections held by the shell for the webview, so the vessel
+//! panel keeps its AISstream feed while the sidecar is restarting or Node is
+//! missing. Only AISstream and the configured relay hosts are reachable,
+//! and a host on the local network only once it is in `trustedLocalHosts`.
+//! Incoming messages go to the owning window as `ws-message` events and the
+//! end of a connection as `ws-closed`; connections die with their window.
+//!
+//! The protocol itself is tokio-tungstenite's; this module dials (so the
+//! allowlist and connect timeout apply to the address actually used), adds
+//! the extra CA certificates to TLS, and pumps messages to and from events.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender, WeakUnboundedSender};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+use crate::error::DesktopError;
+use crate::extra_ca;
+use crate::logging::log_event;
+use crate::polling;
+use crate::trusted_hosts::{self, HostScope, TrustedHost};
+use crate::SecretsCache;
+
+/// AISstream, including subdomains (`stream.aisstream.io`).
+const ALLOWED_HOSTS: [&str; 1] = ["aisstream.io"];
+/// Secrets naming a relay whose host may also be dialled.
+const RELAY_SECRET_KEYS: [&str; 2] = ["WS_RELAY_URL", "VITE_WS_RELAY_URL"];
+const MAX_CONNECTIONS: usize = 8;
+/// Largest message accepted in either direction.
+const MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+const MAX_ID_LEN: usize = 64;
+const MAX_PROTOCOLS: usize = 8;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a local close waits for the server's close frame.
+const CLOSE_GRACE: Duration = Duration::from_secs(2);
+
+const CLOSE_NORMAL: u16 = 1000;
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+const CLOSE_NO_STATUS: u16 = 1005;
+const CLOSE_ABNORMAL: u16 = 1006;
+const CLOSE_INVALID_DATA: u16 = 1007;
+const CLOSE_TOO_BIG: u16 = 1009;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, PartialEq)]
+struct Target {
+    tls: bool,
+    /// Without the brackets of an IPv6 literal, ready to dial.
+    host: String,
+    port: u16,
+}
+
+fn relay_hosts(relay_urls: &[String]) -> Vec<String> {
+    relay_urls
+        .iter()
+        .filter_map(|url| reqwest::Url::parse(url.trim()).ok())
+        .filter_map(|url| url.host_str().map(|host| host.trim_end_matches('.').to_ascii_lowercase()))
+        .collect()
+}
+
+fn is_aisstream_host(host: &str) -> bool {
+    ALLOWED_HOSTS
+        .iter()
+        .any(|allowed| host == *allowed || host.strip_suffix(allowed).is_some_and(|rest| rest.ends_with('.')))
+}
+
+/// Check `url` against the allowlist. `wss` reaches any allowed host; plain
+/// `ws` is only accepted for a configured relay, which may be on loopback,
+/// or on the LAN when trusted; a trusted LAN host needs no relay entry.
+fn parse_target(url: &str, relay_urls: &[String], trusted: &[TrustedHost]) -> Result<Target, DesktopError> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| DesktopError::InvalidUrl("Invalid WebSocket URL".to_string()))?;
+    let tls = match parsed.scheme() {
+        "wss" => true,
+        "ws" => false,
+        _ => return Err(DesktopError::InvalidUrl("WebSocket URL must use ws:// or wss://".to_string())),
+    };
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err(DesktopError::InvalidUrl("WebSocket URL must not carry credentials".to_string()));
+    }
+    let host_str = parsed
+        .host_str()
+        .ok_or_else(|| DesktopError::InvalidUrl("WebSocket URL has no host".to_string()))?;
+    let host = host_str.trim_end_matches('.').to_ascii_lowercase();
+    let port = parsed.port_or_known_default().unwrap_or(if tls { 443 } else { 80 });
+    if trusted_hosts::scope(&host) == HostScope::Local {
+        // Relay or not, the user has to have vouched for it.
+        trusted_hosts::check(trusted, &parsed)?;
+    } else {
+        let is_relay = relay_hosts(relay_urls).contains(&host);
+        let is_aisstream = tls && is_aisstream_host(&host);
+        if !is_relay && !is_aisstream {
+            return Err(DesktopError::InvalidUrl(format!("WebSocket host not allowed: {host}")));
+        }
+    }
+    Ok(Target {
+        tls,
+        host: host_str.trim_start_matches('[').trim_end_matches(']').to_string(),
+        port,
+    })
+}
+
+fn validate_id(id: &str) -> Result<(), DesktopError> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(DesktopError::InvalidArgument("Invalid WebSocket connection id".to_string()))
+    }
+}
+
+fn validate_protocols(protocols: &[String]) -> Result<(), DesktopError> {
+    let is_token = |p: &String| {
+        !p.is_empty() && p.bytes().all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+    };
+    if protocols.len() <= MAX_PROTOCOLS && protocols.iter().all(is_token) {
+        Ok(())
+    } else {
+        Err(DesktopError::InvalidArgument("Invalid WebSocket subprotocol list".to_string()))
+    }
+}
+
+enum Outgoing {
+    Text(String),
+    /// Send a close frame (with this status, if any) and stop writing.
+    Close(Option<u16>),
+    /// The reader has seen the connection end; stop, only flushing what
+    /// tungstenite already queued (such as its reply to a server close).
+    Shutdown,
+}
+
+struct Connection {
+    window: String,
+    generation: u64,
+    /// The only strong sender. Dropping it is how a connection is closed.
+    outgoing: UnboundedSender<Outgoing>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_generation: u64,
+    connections: HashMap<String, Connection>,
+}
+
+impl Registry {
+    fn insert(&mut self, id: &str, window: &str, outgoing: UnboundedSender<Outgoing>) -> Result<u64, DesktopError> {
+        if self.connections.contains_key(id) {
+            return Err(DesktopError::InvalidArgument(format!("WebSocket connection {id} is already open")));
+        }
+        if self.connections.len() >= MAX_CONNECTIONS {
+            return Err(DesktopError::InvalidArgument(format!(
+                "Too many WebSocket connections (at most {MAX_CONNECTIONS})"
+            )));
+        }
+        self.next_generation += 1;
+        self.connections.insert(
+            id.to_string(),
+            Connection {
+                window: window.to_string(),
+                generation: self.next_generation,
+                outgoing,
+            },
+        );
+        Ok(self.next_generation)
+    }
+
+    fn sender(&self, id: &str, window: &str) -> Option<UnboundedSender<Outgoing>> {
+        self.connections
+            .get(id)
+            .filter(|connection| connection.window == window)
+            .map(|connection| connection.outgoing.clone())
+    }
+
+    fn remove(&mut self, id: &str, window: &str) -> bool {
+        let owned = self.connections.get(id).is_some_and(|connection| connection.window == window);
+        owned && self.connections.remove(id).is_some()
+    }
+
+    /// Drop `id` only if it is still the connection started as `generation`,
+    /// not a newer one that reused the id.
+    fn remove_if_current(&mut self, id: &str, generation: u64) {
+        if self.connections.get(id).is_some_and(|connection| connection.generation == generation) {
+            self.connections.remove(id);
+        }
+    }
+
+    fn remove_window(&mut self, window: &str) -> usize {
+        let before = self.connections.len();
+        self.connections.retain(|_, connection| connection.window != window);
+        before - self.connections.len()
+    }
+
+    fn clear(&mut self) -> usize {
+        let count = self.connections.len();
+        self.connections.clear();
+        count
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct WsBridgeState {
+    registry: Mutex<Registry>,
+}
+
+impl WsBridgeState {
+    fn registry(&self) -> std::sync::MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn connect_failed(message: impl std::fmt::Display) -> DesktopError {
+    DesktopError::Http {
+        status: None,
+        message: format!("WebSocket connect failed: {message}"),
+    }
+}
+
+fn handshake_failed(err: WsError) -> DesktopError {
+    match err {
+        WsError::Http(response) => DesktopError::Http {
+            status: Some(response.status().as_u16()),
+            message: "WebSocket upgrade refused".to_string(),
+        },
+        err => connect_failed(err),
+    }
+}
+
+/// The upgrade request for `url`, offering `protocols`. tungstenite rejects
+/// a response that picks a subprotocol we didn't offer.
+fn client_request(url: &str, protocols: &[String]) -> Result<Request, DesktopError> {
+    let mut request = url
+        .into_client_request()
+        .map_err(|_| DesktopError::InvalidUrl("Invalid WebSocket URL".to_string()))?;
+    if !protocols.is_empty() {
+        let offered = HeaderValue::from_str(&protocols.join(", "))
+            .map_err(|_| DesktopError::InvalidArgument("Invalid WebSocket subprotocol list".to_string()))?;
+        request.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, offered);
+    }
+    Ok(request)
+}
+
+/// Dial `target` and complete the upgrade for `url`.
+async fn open(app: &AppHandle, url: &str, target: &Target, protocols: &[String]) -> Result<WsStream, DesktopError> {
+    let request = client_request(url, protocols)?;
+    let tcp = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((target.host.as_str(), target.port)))
+        .await
+        .map_err(|_| connect_failed("timed out"))?
+        .map_err(connect_failed)?;
+    let _ = tcp.set_nodelay(true);
+    let connector = if target.tls {
+        Connector::NativeTls(extra_ca::tls_connector(app)?)
+    } else {
+        Connector::Plain
+    };
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_BYTES))
+        .max_frame_size(Some(MAX_MESSAGE_BYTES));
+    let upgrade = tokio_tungstenite::client_async_tls_with_config(request, tcp, Some(config), Some(connector));
+    let (stream, _) = tokio::time::timeout(CONNECT_TIMEOUT, upgrade)
+        .await
+        .map_err(|_| connect_failed("upgrade timed out"))?
+        .map_err(handshake_failed)?;
+    Ok(stream)
+}
+
+#[derive(Clone, Serialize)]
+struct WsMessageEvent<'a> {
+    id: &'a str,
+    data: String,
+    /// `data` is base64 when the frame was binary.
+    binary: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct WsClosedEvent<'a> {
+    id: &'a str,
+    code: u16,
+    reason: String,
+}
+
+fn send_control(control: &WeakUnboundedSender<Outgoing>, message: Outgoing) {
+    if let Some(sender) = control.upgrade() {
+        let _ = sender.send(message);
+    }
+}
+
+/// The close status to send for a read error, and a reason to report.
+/// `None` when the connection is simply gone.
+fn violation(err: &WsError) -> Option<(u16, &'static str)> {
+    match err {
+        WsError::Capacity(_) => Some((CLOSE_TOO_BIG, "message too large")),
+        WsError::Utf8(_) => Some((CLOSE_INVALID_DATA, "text message is not UTF-8")),
+        WsError::Protocol(_) => Some((CLOSE_PROTOCOL_ERROR, "protocol error")),
+        _ => None,
+    }
+}
+
+fn close_message(code: Option<u16>) -> Message {
+    Message::Close(code.map(|code| CloseFrame {
+        code: code.into(),
+        reason: Default::default(),
+    }))
+}
+
+/// Forward incoming messages until the connection ends; returns the close
+/// status and reason. tungstenite answers pings and server closes itself.
+async fn read_loop(
+    app: AppHandle,
+    id: String,
+    window: String,
+    mut reader: SplitStream<WsStream>,
+    control: WeakUnboundedSender<Outgoing>,
+) -> (u16, String) {
+    while let Some(next) = reader.next().await {
+        let (data, binary) = match next {
+            Ok(Message::Text(text)) => (text.as_str().to_owned(), false),
+            Ok(Message::Binary(bytes)) => (BASE64.encode(&bytes), true),
+            Ok(Message::Close(frame)) => {
+                send_control(&control, Outgoing::Shutdown);
+                return match frame {
+                    Some(frame) => (frame.code.into(), frame.reason.as_str().to_owned()),
+                    None => (CLOSE_NO_STATUS, String::new()),
+                };
+            }
+            Ok(_) => continue,
+            Err(err) => {
+                return match violation(&err) {
+                    Some((code, reason)) => {
+                        send_control(&control, Outgoing::Close(Some(code)));
+                        (code, reason.to_string())
+                    }
+                    None => {
+                        send_control(&control, Outgoing::Shutdown);
+                        (CLOSE_ABNORMAL, "connection lost".to_string())
+                    }
+                };
+            }
+        };
+        let event = WsMessageEvent { id: &id, data, binary };
+        let _ = app.emit_to(window.as_str(), "ws-message", event);
+    }
+    send_control(&control, Outgoing::Shutdown);
+    (CLOSE_ABNORMAL, "connection lost".to_string())
+}
+
+/// Write queued messages. Returns true when the connection was closed from
+/// this side (its sender dropped).
+async fn write_loop(mut writer: SplitSink<WsStream, Message>, mut outgoing: UnboundedReceiver<Outgoing>) -> bool {
+    let mut local = true;
+    while let Some(message) = outgoing.recv().await {
+        match message {
+            Outgoing::Text(text) => {
+                if writer.send(Message::text(text)).await.is_err() {
+                    local = false;
+                    break;
+                }
+            }
+            Outgoing::Close(code) => {
+                let _ = writer.send(close_message(code)).await;
+                local = false;
+                break;
+            }
+            Outgoing::Shutdown => {
+                local = false;
+                break;
+            }
+        }
+    }
+    if local {
+        let _ = writer.send(close_message(Some(CLOSE_NORMAL))).await;
+    }
+    let _ = writer.close().await;
+    local
+}
+
+/// An upgraded connection, handed from `connect` to its session task.
+struct Session {
+    app: AppHandle,
+    id: String,
+    generation: u64,
+    window: String,
+    stream: WsStream,
+    control: WeakUnboundedSender<Outgoing>,
+    outgoing: UnboundedReceiver<Outgoing>,
+}
+
+async fn run_session(session: Session) {
+    let Session {
+        app,
+        id,
+        generation,
+        window,
+        stream,
+        control,
+        outgoing,
+    } = session;
+    let (writer, reader) = stream.split();
+    let mut reading = tokio::spawn(read_loop(app.clone(), id.clone(), window.clone(), reader, control));
+    let local = write_loop(writer, outgoing).await;
+    let (code, reason) = match tokio::time::timeout(CLOSE_GRACE, &mut reading).await {
+        Ok(Ok(outcome)) => outcome,
+        _ => {
+            reading.abort();
+            if local {
+                (CLOSE_NORMAL, String::new())
+            } else {
+                (CLOSE_ABNORMAL, "connection lost".to_string())
+            }
+        }
+    };
+    app.state::<WsBridgeState>().registry().remove_if_current(&id, generation);
+    log_event(
+        &app,
+        "INFO",
+        "ws_closed",
+        &[("id", &id), ("code", &code.to_string()), ("reason", &reason)],
+    );
+    let _ = app.emit_to(window.as_str(), "ws-closed", WsClosedEvent { id: &id, code, reason });
+}
+
+fn relay_urls(app: &AppHandle) -> Vec<String> {
+    let cache = app.state::<SecretsCache>();
+    let secrets = cache.secrets.lock().unwrap_or_else(|e| e.into_inner());
+    RELAY_SECRET_KEYS.iter().filter_map(|key| secrets.get(*key).cloned()).collect()
+}
+
+/// Open connection `id` for `window`. Resolves once the upgrade completes;
+/// messages then arrive as `ws-message` events.
+pub(crate) async fn connect(
+    app: &AppHandle,
+    window: &str,
+    id: String,
+    url: &str,
+    protocols: Vec<String>,
+) -> Result<(), DesktopError> {
+    polling::ensure_active(app)?;
+    validate_id(&id)?;
+    validate_protocols(&protocols)?;
+    let target = parse_target(url, &relay_urls(app), &trusted_hosts::from_prefs(app))?;
+    let (sender, outgoing) = mpsc::unbounded_channel();
+    let control = sender.downgrade();
+    let state = app.state::<WsBridgeState>();
+    // Registered before dialling so the limit counts pending connects, and
+    // so a window closing mid-connect still tears this one down.
+    let generation = state.registry().insert(&id, window, sender)?;
+    let stream = match open(app, url, &target, &protocols).await {
+        Ok(opened) => opened,
+        Err(err) => {
+            state.registry().remove_if_current(&id, generation);
+            log_event(app, "WARN", "ws_connect_failed", &[("host", &target.host), ("error", &err.to_string())]);
+            return Err(err);
+        }
+    };
+    log_event(app, "INFO", "ws_connected", &[("id", &id), ("host", &target.host)]);
+    tauri::async_runtime::spawn(run_session(Session {
+        app: app.clone(),
+        id,
+        generation,
+        window: window.to_string(),
+        stream,
+        control,
+        outgoing,
+    }));
+    Ok(())
+}
+
+pub(crate) fn send(app: &AppHandle, window: &str, id: &str, payload: String) -> Result<(), DesktopError> {
+    if payload.len() > MAX_MESSAGE_BYTES {
+        return Err(DesktopError::InvalidArgument(format!(
+            "WebSocket message exceeds {MAX_MESSAGE_BYTES} bytes"
+        )));
+    }
+    let not_open = || DesktopError::InvalidArgument(format!("WebSocket connection {id} is not open"));
+    let sender = app.state::<WsBridgeState>().registry().sender(id, window).ok_or_else(not_open)?;
+    sender.send(Outgoing::Text(payload)).map_err(|_| not_open())
+}
+
+/// Close `id`. Returns false if the window had no such connection.
+pub(crate) fn close(app: &AppHandle, window: &str, id: &str) -> bool {
+    app.state::<WsBridgeState>().registry().remove(id, window)
+}
+
+/// Tear down every connection owned by `window`, e.g. when it is destroyed.
+pub(crate) fn close_window(app: &AppHandle, window: &str) {
+    let Some(state) = app.try_state::<WsBridgeState>() else {
+        return;
+    };
+    let closed = state.registry().remove_window(window);
+    if closed > 0 {
+        log_event(app, "INFO", "ws_window_closed", &[("window", window), ("connections", &closed.to_string())]);
+    }
+}
+
+pub(crate) fn close_all(app: &AppHandle) {
+    if let Some(state) = app.try_state::<WsBridgeState>() {
+        state.registry().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::{self, error::TryRecvError};
+
+    use tokio_tungstenite::tungstenite::error::{CapacityError, ProtocolError};
+    use tokio_tungstenite::tungstenite::Error as WsError;
+
+    use super::{
+        client_request, parse_target, validate_id, violation, Registry, CLOSE_PROTOCOL_ERROR, CLOSE_TOO_BIG,
+        MAX_CONNECTIONS,
+    };
+    use crate::trusted_hosts::parse_pattern;
+
+    fn relays() -> Vec<String> {
+        vec!["wss://relay.example.org:8443/ws".to_string(), "http://127.0.0.1:3004".to_string()]
+    }
+
+    #[test]
+    fn allowlist_accepts_aisstream_and_configured_relays_only() {
+        let ais = parse_target("wss://stream.aisstream.io/v0/stream", &[], &[]).unwrap();
+        assert_eq!((ais.host.as_str(), ais.port, ais.tls), ("stream.aisstream.io", 443, true));
+        assert!(parse_target("wss://aisstream.io/", &[], &[]).is_ok());
+        assert!(parse_target("wss://STREAM.AISSTREAM.IO./", &[], &[]).is_ok());
+
+        assert!(parse_target("wss://evilaisstream.io/", &[], &[]).is_err());
+        assert!(parse_target("wss://aisstream.io.evil.test/", &[], &[]).is_err());
+        assert!(parse_target("ws://stream.aisstream.io/", &[], &[]).is_err());
+        assert!(parse_target("https://stream.aisstream.io/", &[], &[]).is_err());
+        assert!(parse_target("wss://user:pw@stream.aisstream.io/", &[], &[]).is_err());
+
+        let relay = parse_target("wss://relay.example.org:8443/ws?feed=ais", &relays(), &[]).unwrap();
+        assert_eq!((relay.host.as_str(), relay.port), ("relay.example.org", 8443));
+        let local = parse_target("ws://127.0.0.1:3004/", &relays(), &[]).unwrap();
+        assert!(!local.tls);
+        assert!(parse_target("wss://relay.example.org/", &[], &[]).is_err());
+        assert!(parse_target("wss://other.example.org/", &relays(), &[]).is_err());
+    }
+
+    #[test]
+    fn lan_hosts_need_to_be_trusted_even_as_relays() {
+        let lan_relay = vec!["ws://192.168.1.20:3004".to_string()];
+        let err = parse_target("ws://192.168.1.20:3004/", &lan_relay, &[]).unwrap_err();
+        assert!(err.to_string().contains("trusted local hosts"));
+        let trusted = [parse_pattern("192.168.1.*:3004").unwrap(), parse_pattern("[fd00::9]:*").unwrap()];
+        let relay = parse_target("ws://192.168.1.20:3004/", &lan_relay, &trusted).unwrap();
+        assert_eq!((relay.host.as_str(), relay.port), ("192.168.1.20", 3004));
+        // Trusted LAN services are reachable without a relay entry, IPv6 too.
+        let v6 = parse_target("ws://[fd00::9]:8080/feed", &[], &trusted).unwrap();
+        assert_eq!((v6.host.as_str(), v6.port), ("fd00::9", 8080));
+        assert!(parse_target("ws://192.168.1.20:3005/", &lan_relay, &trusted).is_err());
+        assert!(parse_target("ws://relay.local:3004/", &[], &trusted).is_err());
+    }
+
+    #[test]
+    fn registry_limits_and_tears_down_by_window() {
+        let mut registry = Registry::default();
+        let mut receivers = Vec::new();
+        for i in 0..MAX_CONNECTIONS {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let window = if i % 2 == 0 { "main" } else { "panel-vessels" };
+            registry.insert(&format!("c{i}"), window, tx).unwrap();
+            receivers.push(rx);
+        }
+        let (tx, _rx) = mpsc::unbounded_channel();
+        assert!(registry.insert("overflow", "main", tx).is_err());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        assert!(registry.insert("c0", "main", tx).is_err());
+
+        // Another window can neither use nor close a connection it doesn't own.
+        assert!(registry.sender("c1", "main").is_none());
+        assert!(!registry.remove("c1", "main"));
+
+        assert_eq!(registry.remove_window("panel-vessels"), MAX_CONNECTIONS / 2);
+        for (i, rx) in receivers.iter_mut().enumerate() {
+            let closed = matches!(rx.try_recv(), Err(TryRecvError::Disconnected));
+            assert_eq!(closed, i % 2 == 1, "connection c{i}");
+        }
+        assert_eq!(registry.clear(), MAX_CONNECTIONS / 2);
+        assert!(receivers.iter_mut().all(|rx| matches!(rx.try_recv(), Err(TryRecvError::Disconnected))));
+    }
+
+    #[test]
+    fn stale_sessions_do_not_remove_a_reused_id() {
+        let mut registry = Registry::default();
+        let (tx, _first) = mpsc::unbounded_channel();
+        let old = registry.insert("ais", "main", tx).unwrap();
+        assert!(registry.remove("ais", "main"));
+        let (tx, _second) = mpsc::unbounded_channel();
+        registry.insert("ais", "main", tx).unwrap();
+        registry.remove_if_current("ais", old);
+        assert!(registry.sender("ais", "main").is_some());
+    }
+
+    #[test]
+    fn ids_are_short_tokens() {
+        assert!(validate_id("ais-stream_1").is_ok());
+        assert!(validate_id("").is_err());
+        assert!(validate_id("../x").is_err());
+        assert!(validate_id(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn offered_subprotocols_go_in_the_upgrade_request() {
+        let protocols = ["chat".to_string(), "v2".to_string()];
+        let request = client_request("wss://stream.aisstream.io/v0/stream", &protocols).unwrap();
+        assert_eq!(request.headers()["sec-websocket-protocol"], "chat, v2");
+        assert_eq!(request.uri().path(), "/v0/stream");
+        let plain = client_request("ws://127.0.0.1:3004/", &[]).unwrap();
+        assert!(!plain.headers().contains_key("sec-websocket-protocol"));
+    }
+
+    #[test]
+    fn read_errors_map_to_close_statuses() {
+        let too_big = WsError::Capacity(CapacityError::MessageTooLong { size: 2, max_size: 1 });
+        assert_eq!(violation(&too_big).unwrap().0, CLOSE_TOO_BIG);
+        let masked = WsError::Protocol(ProtocolError::MaskedFrameFromServer);
+        assert_eq!(violation(&masked).unwrap().0, CLOSE_PROTOCOL_ERROR);
+        assert!(violation(&WsError::ConnectionClosed).is_none());
+    }
+}
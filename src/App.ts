@@ -25,6 +25,7 @@ import type { GulfEconomiesPanel } from '@/components/GulfEconomiesPanel';
 import type { StrategicPosturePanel } from '@/components/StrategicPosturePanel';
 import type { StrategicRiskPanel } from '@/components/StrategicRiskPanel';
 import { isDesktopRuntime } from '@/services/runtime';
+import { tryInvokeTauri } from '@/services/tauri-bridge';
 import { BETA_MODE } from '@/config/beta';
 import { trackEvent, trackDeeplinkOpened } from '@/services/analytics';
 import { preloadCountryGeometry, getCountryNameByCode } from '@/services/country-geometry';
@@ -399,6 +400,12 @@ export class App {
       initAisStream();
     }
 
+    // At cold start the sidecar may still be coming up; let it, rather than
+    // failing the first burst of panel requests. A timeout just proceeds.
+    if (isDesktopRuntime()) {
+      await tryInvokeTauri('wait_for_local_api', { timeoutMs: 15_000 });
+    }
+
     // Hydrate in-memory cache from bootstrap endpoint (before panels construct and fetch)
     await fetchBootstrapData();
 
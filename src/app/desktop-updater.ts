@@ -1,5 +1,5 @@
 import type { AppContext, AppModule } from '@/app/app-context';
-import { invokeTauri } from '@/services/tauri-bridge';
+import { invokeTauri, openExternalUrl } from '@/services/tauri-bridge';
 import { trackUpdateShown, trackUpdateClicked, trackUpdateDismissed } from '@/services/analytics';
 import { escapeHtml } from '@/utils/sanitize';
 
@@ -194,7 +194,7 @@ export class DesktopUpdater implements AppModule {
       if (action === 'download') {
         trackUpdateClicked(version);
         if (this.ctx.isDesktopApp) {
-          void invokeTauri<void>('open_url', { url }).catch((error) => {
+          void openExternalUrl(url).catch((error) => {
             this.logUpdaterOutcome('open_failed', { url, error: error instanceof Error ? error.message : String(error) });
             window.open(url, '_blank', 'noopener');
           });
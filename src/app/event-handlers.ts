@@ -43,7 +43,7 @@ import {
 } from '@/services/analytics';
 import { detectPlatform, allButtons, buttonsForPlatform } from '@/components/DownloadBanner';
 import type { Platform } from '@/components/DownloadBanner';
-import { invokeTauri } from '@/services/tauri-bridge';
+import { openExternalUrl } from '@/services/tauri-bridge';
 import { dataFreshness } from '@/services/data-freshness';
 import { mlWorker } from '@/services/ml-worker';
 import { broadcastUserActivity } from '@/services/activity-signal';
@@ -350,11 +350,13 @@ export class EventHandlerManager implements AppModule {
           return;
         }
         if (url.origin === window.location.origin) return;
-        if (!/^https?:$/.test(url.protocol)) return; // Only allow http(s) links
+        // open_url accepts http(s), mailto and any schemes the user allowed via
+        // the allowedUrlSchemes pref; script-capable schemes never leave here.
+        if (/^(javascript|vbscript|data|blob|file|about):$/.test(url.protocol)) return;
         e.preventDefault();
         e.stopPropagation();
-        void invokeTauri<void>('open_url', { url: url.toString() }).catch(() => {
-          window.open(url.toString(), '_blank');
+        void openExternalUrl(url.toString()).catch(() => {
+          if (/^https?:$/.test(url.protocol)) window.open(url.toString(), '_blank');
         });
       };
       document.addEventListener('click', this.boundDesktopExternalLinkHandler, true);
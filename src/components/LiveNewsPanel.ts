@@ -1,6 +1,6 @@
 import { Panel } from './Panel';
 import { fetchLiveVideoInfo } from '@/services/live-news';
-import { isDesktopRuntime, getRemoteApiBaseUrl, getApiBaseUrl, getLocalApiPort } from '@/services/runtime';
+import { isDesktopRuntime, getRemoteApiBaseUrl, getApiBaseUrl, getLocalApiEmbedOrigin, resolveLocalApiConnection } from '@/services/runtime';
 import { t } from '../services/i18n';
 import { loadFromStorage, rafSchedule, saveToStorage } from '@/utils';
 import { escapeHtml, sanitizeUrl } from '@/utils/sanitize';
@@ -454,11 +454,11 @@ export class LiveNewsPanel extends Panel {
     if (!entry) return undefined;
     const failedAt = this.hlsFailureCooldown.get(channelId);
     if (failedAt && Date.now() - failedAt < this.HLS_COOLDOWN_MS) return undefined;
-    return `http://127.0.0.1:${getLocalApiPort()}/api/hls-proxy?url=${encodeURIComponent(entry.url)}`;
+    return `${getApiBaseUrl()}/api/hls-proxy?url=${encodeURIComponent(entry.url)}`;
   }
 
   private get embedOrigin(): string {
-    if (isDesktopRuntime()) return `http://localhost:${getLocalApiPort()}`;
+    if (isDesktopRuntime()) return getLocalApiEmbedOrigin();
     try { return new URL(getRemoteApiBaseUrl()).origin; } catch { return 'https://worldmonitor.app'; }
   }
 
@@ -939,6 +939,9 @@ export class LiveNewsPanel extends Panel {
 
   private async resolveChannelVideo(channel: LiveChannel, forceFallback = false): Promise<void> {
     const useFallbackVideo = channel.useFallbackOnly || forceFallback;
+    // The proxied HLS URL needs the sidecar's base URL, which a cold start
+    // doesn't have until the connection resolves.
+    if (isDesktopRuntime()) await resolveLocalApiConnection();
 
     if (this.getDirectHlsUrl(channel.id) || this.getProxiedHlsUrl(channel.id)) {
       channel.videoId = channel.fallbackVideoId;
@@ -1087,6 +1090,9 @@ export class LiveNewsPanel extends Panel {
     const renderToken = ++this.desktopEmbedRenderToken;
     this.currentVideoId = videoId;
     this.isPlayerReady = true;
+    // Without the sidecar's origin the embed URL would be relative.
+    await resolveLocalApiConnection();
+    if (renderToken !== this.desktopEmbedRenderToken) return;
 
     // Always recreate if container was removed from DOM (e.g. showEmbedError replaced content).
     if (!this.playerContainer || !this.playerContainer.parentElement) {
@@ -1109,7 +1115,7 @@ export class LiveNewsPanel extends Panel {
       mute: this.isMuted ? '1' : '0',
     });
     if (quality !== 'auto') params.set('vq', quality);
-    const embedUrl = `http://localhost:${getLocalApiPort()}/api/youtube-embed?${params.toString()}`;
+    const embedUrl = `${this.embedOrigin}/api/youtube-embed?${params.toString()}`;
 
     if (renderToken !== this.desktopEmbedRenderToken) {
       return;
@@ -1,5 +1,5 @@
 import { Panel } from './Panel';
-import { isDesktopRuntime, getLocalApiPort } from '@/services/runtime';
+import { isDesktopRuntime, getLocalApiEmbedOrigin, resolveLocalApiConnection } from '@/services/runtime';
 import { escapeHtml } from '@/utils/sanitize';
 import { t } from '../services/i18n';
 import { trackWebcamSelected, trackWebcamRegionFiltered } from '@/services/analytics';
@@ -217,10 +217,10 @@ export class LiveWebcamsPanel extends Panel {
     const quality = getStreamQuality();
     if (isDesktopRuntime()) {
       // Use local sidecar embed — YouTube rejects tauri:// parent origin with error 153.
-      // The sidecar serves the embed from http://127.0.0.1:PORT which YouTube accepts.
+      // The sidecar serves the embed from http://localhost:PORT which YouTube accepts.
       const params = new URLSearchParams({ videoId, autoplay: '1', mute: '1' });
       if (quality !== 'auto') params.set('vq', quality);
-      return `http://localhost:${getLocalApiPort()}/api/youtube-embed?${params.toString()}`;
+      return `${getLocalApiEmbedOrigin()}/api/youtube-embed?${params.toString()}`;
     }
     const vq = quality !== 'auto' ? `&vq=${quality}` : '';
     return `https://www.youtube-nocookie.com/embed/${videoId}?autoplay=1&mute=1&controls=0&modestbranding=1&playsinline=1&rel=0${vq}`;
@@ -257,6 +257,15 @@ export class LiveWebcamsPanel extends Panel {
       return;
     }
 
+    // On a cold start the sidecar's origin isn't known yet; render once it is
+    // rather than pointing the iframes at a relative URL.
+    if (isDesktopRuntime() && !getLocalApiEmbedOrigin()) {
+      void resolveLocalApiConnection().then(() => {
+        if (getLocalApiEmbedOrigin()) this.requestRender(true, 'normal');
+      });
+      return;
+    }
+
     const signature = `${this.viewMode}|${this.regionFilter}|${this.activeFeed.id}`;
     if (!force && signature === this.renderSignature) {
       return;
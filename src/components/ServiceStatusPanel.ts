@@ -1,7 +1,7 @@
 
 import { Panel } from './Panel';
 import { t } from '@/services/i18n';
-import { getLocalApiPort, isDesktopRuntime } from '@/services/runtime';
+import { getApiBaseUrl, isDesktopRuntime } from '@/services/runtime';
 import {
   getDesktopReadinessChecks,
   getKeyBackedAvailabilitySummary,
@@ -131,11 +131,13 @@ export class ServiceStatusPanel extends Panel {
       );
     }
 
-    const port = this.localBackend.port ?? getLocalApiPort();
+    const endpoint = this.localBackend.port != null
+      ? `127.0.0.1:${this.localBackend.port}`
+      : getApiBaseUrl().replace(/^https?:\/\//, '');
     const remote = this.localBackend.remoteBase ?? 'https://worldmonitor.app';
 
     return h('div', { className: 'service-status-backend' },
-      'Local backend active on ', h('strong', null, `127.0.0.1:${port}`),
+      'Local backend active on ', h('strong', null, endpoint),
       ' · cloud fallback: ', h('strong', null, remote),
     );
   }
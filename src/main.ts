@@ -195,6 +195,10 @@ import { debugGetCells, getCellCount } from '@/services/geo-convergence';
 import { initMetaTags } from '@/services/meta-tags';
 import { installRuntimeFetchPatch, installWebApiRedirect } from '@/services/runtime';
 import { loadDesktopSecrets } from '@/services/runtime-config';
+import { installQuitGuardPrompt } from '@/services/desktop-quit';
+import { installReloadListeners, onWindowReloading } from '@/services/desktop-reload';
+import { settlePersistentCacheWrites } from '@/services/persistent-cache';
+import { applyDetachedPanelMode, getDetachedPanelId, installPanelWindowPicker } from '@/services/panel-windows';
 import { applyStoredTheme } from '@/utils/theme-manager';
 import { SITE_VARIANT } from '@/config/variant';
 import { clearChunkReloadGuard, installChunkReloadGuard } from '@/bootstrap/chunk-reload';
@@ -216,6 +220,12 @@ installRuntimeFetchPatch();
 // In web production, route RPC calls through api.worldmonitor.app (Cloudflare edge).
 installWebApiRedirect();
 loadDesktopSecrets().catch(() => {});
+installQuitGuardPrompt();
+onWindowReloading(settlePersistentCacheWrites);
+installReloadListeners();
+installPanelWindowPicker();
+const detachedPanelId = getDetachedPanelId();
+if (detachedPanelId) applyDetachedPanelMode(detachedPanelId);
 
 // Apply stored theme preference before app initialization (safety net for inline script)
 applyStoredTheme();
@@ -0,0 +1,43 @@
+/**
+ * Entry point for the safe mode window (Tauri desktop only). Shown when the
+ * Rust shell detects repeated unclean launches; offers log access and a
+ * runtime-prefs reset.
+ */
+import { invokeTauri, tryInvokeTauri } from '@/services/tauri-bridge';
+
+interface DesktopRuntimeInfo {
+  safe_mode: {
+    active: boolean;
+    reason: string | null;
+    unclean_launches: number;
+  };
+}
+
+const summaryEl = document.getElementById('safeModeSummary');
+const statusEl = document.getElementById('safeModeStatus');
+
+function setStatus(text: string): void {
+  if (statusEl) statusEl.textContent = text;
+}
+
+document.getElementById('openLogsBtn')?.addEventListener('click', () => {
+  void tryInvokeTauri<string>('open_logs_folder');
+});
+document.getElementById('resetPrefsBtn')?.addEventListener('click', () => {
+  invokeTauri<void>('reset_runtime_prefs')
+    .then(() => setStatus('Preferences reset. They take effect on the next launch.'))
+    .catch((err: unknown) => setStatus(`Could not reset preferences: ${String(err)}`));
+});
+document.getElementById('continueBtn')?.addEventListener('click', () => {
+  void tryInvokeTauri<void>('dismiss_safe_mode');
+});
+
+async function main(): Promise<void> {
+  const info = await invokeTauri<DesktopRuntimeInfo>('get_desktop_runtime_info');
+  const count = info.safe_mode.unclean_launches;
+  if (summaryEl && count > 0) {
+    summaryEl.textContent = `The last ${count} launches exited before finishing startup.`;
+  }
+}
+
+void main().catch(console.error);
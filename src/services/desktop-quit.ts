@@ -0,0 +1,24 @@
+import { isDesktopRuntime } from './runtime';
+import { invokeTauri, listenTauriEvent } from './tauri-bridge';
+
+/** Payload of the shell's `quit-blocked` event. */
+interface QuitBlockedPayload {
+  busy: boolean;
+  jobs: Array<{ label: string; elapsedMs: number }>;
+}
+
+/**
+ * The shell holds a quit while the local API has work in flight and asks the
+ * main window what to do. Quitting again from the OS also goes through.
+ */
+export function installQuitGuardPrompt(): void {
+  if (!isDesktopRuntime()) return;
+  void listenTauriEvent<QuitBlockedPayload>('quit-blocked', (payload) => {
+    const jobs = payload.jobs.map((job) => job.label).join(', ') || 'background work';
+    const quitNow = window.confirm(
+      `A sync is in progress (${jobs}). Quit anyway?\n\nChoose Cancel to wait for it to finish (up to 30 seconds) and then quit.`,
+    );
+    void invokeTauri<void>(quitNow ? 'force_quit' : 'wait_then_quit')
+      .catch((error) => console.warn('[desktop-quit] Failed to resume quit', error));
+  }).catch((error) => console.warn('[desktop-quit] Failed to listen for quit-blocked', error));
+}
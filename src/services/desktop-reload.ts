@@ -0,0 +1,45 @@
+import { isDesktopRuntime } from './runtime';
+import { listenTauriEvent, tryInvokeTauri } from './tauri-bridge';
+
+/** Payload of the shell's `window-reloading` and `window-reload-blocked` events. */
+export interface WindowReloadPayload {
+  force: boolean;
+}
+
+type Flusher = () => void | Promise<void>;
+
+const flushers = new Set<Flusher>();
+
+/**
+ * Run `flush` when View > Reload is about to reload this window. The shell
+ * waits about 300 ms after `window-reloading` before reloading.
+ */
+export function onWindowReloading(flush: Flusher): () => void {
+  flushers.add(flush);
+  return () => flushers.delete(flush);
+}
+
+/** View > Reload is refused for this window while `work` runs. */
+export async function withReloadGuard<T>(work: () => Promise<T>): Promise<T> {
+  await tryInvokeTauri<void>('set_reload_guard', { dirty: true });
+  try {
+    return await work();
+  } finally {
+    void tryInvokeTauri<void>('set_reload_guard', { dirty: false });
+  }
+}
+
+/** Flush registered state on `window-reloading`; `onBlocked` hears about refused reloads. */
+export function installReloadListeners(onBlocked?: (payload: WindowReloadPayload) => void): void {
+  if (!isDesktopRuntime()) return;
+  void listenTauriEvent<WindowReloadPayload>('window-reloading', () => {
+    for (const flush of flushers) {
+      void Promise.resolve()
+        .then(flush)
+        .catch((error) => console.warn('[desktop-reload] Flush before reload failed', error));
+    }
+  }).catch((error) => console.warn('[desktop-reload] Failed to listen for window-reloading', error));
+  if (!onBlocked) return;
+  void listenTauriEvent<WindowReloadPayload>('window-reload-blocked', onBlocked)
+    .catch((error) => console.warn('[desktop-reload] Failed to listen for window-reload-blocked', error));
+}
@@ -0,0 +1,44 @@
+import { isDesktopRuntime } from './runtime';
+import { invokeTauri, listenTauriEvent } from './tauri-bridge';
+
+/** A panel the shell can pop out, as sent with `panel-window-picker`. */
+interface PanelChoice {
+  id: string;
+  name: string;
+}
+
+/** The panel this window was opened for (`index.html?panel=<id>`), if it is a detached panel window. */
+export function getDetachedPanelId(): string | null {
+  if (!isDesktopRuntime()) return null;
+  const id = new URLSearchParams(window.location.search).get('panel');
+  return id && /^[a-z-]+$/.test(id) ? id : null;
+}
+
+/** Show only `panelId`: the map section for the map, otherwise that one panel filling the window. */
+export function applyDetachedPanelMode(panelId: string): void {
+  document.documentElement.dataset.panelWindow = panelId;
+  const style = document.createElement('style');
+  style.textContent = panelId === 'map'
+    ? '.header, .panels-grid, .map-bottom-grid, .map-resize-handle { display: none !important; }'
+      + ' .map-section { height: 100vh !important; max-height: none !important; }'
+    : `.header, .map-section, .panel:not([data-panel="${panelId}"]) { display: none !important; }`
+      + ` .panels-grid { display: block !important; height: 100vh; }`
+      + ` .panel[data-panel="${panelId}"] { height: 100% !important; max-height: none !important; }`;
+  document.head.appendChild(style);
+}
+
+export function openPanelWindow(panelId: string): Promise<void> {
+  return invokeTauri<void>('create_panel_window', { panelId });
+}
+
+/** Window → New Panel Window… asks the main window which panel to pop out. */
+export function installPanelWindowPicker(): void {
+  if (!isDesktopRuntime() || getDetachedPanelId()) return;
+  void listenTauriEvent<PanelChoice[]>('panel-window-picker', (choices) => {
+    const list = choices.map((choice, index) => `${index + 1}. ${choice.name}`).join('\n');
+    const answer = window.prompt(`Open which panel in a new window?\n\n${list}`, '1');
+    const choice = answer ? choices[Number.parseInt(answer, 10) - 1] : undefined;
+    if (!choice) return;
+    openPanelWindow(choice.id).catch((error) => console.warn('[panel-windows] Failed to open panel window', error));
+  }).catch((error) => console.warn('[panel-windows] Failed to listen for panel-window-picker', error));
+}
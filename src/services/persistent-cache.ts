@@ -14,6 +14,7 @@ const CACHE_DB_VERSION = 1;
 const CACHE_STORE = 'entries';
 
 let cacheDbPromise: Promise<IDBDatabase> | null = null;
+const pendingWrites = new Set<Promise<void>>();
 
 function isIndexedDbAvailable(): boolean {
   return typeof window !== 'undefined' && typeof window.indexedDB !== 'undefined';
@@ -98,7 +99,20 @@ export async function getPersistentCache<T>(key: string): Promise<CacheEnvelope<
   }
 }
 
-export async function setPersistentCache<T>(key: string, data: T): Promise<void> {
+export function setPersistentCache<T>(key: string, data: T): Promise<void> {
+  const write = writePersistentCache(key, data);
+  pendingWrites.add(write);
+  const settle = () => { pendingWrites.delete(write); };
+  write.then(settle, settle);
+  return write;
+}
+
+/** Resolves once every write started so far has landed or failed. */
+export async function settlePersistentCacheWrites(): Promise<void> {
+  await Promise.allSettled([...pendingWrites]);
+}
+
+async function writePersistentCache<T>(key: string, data: T): Promise<void> {
   const payload: CacheEnvelope<T> = { key, data, updatedAt: Date.now() };
 
   if (isDesktopRuntime()) {
@@ -115,12 +115,16 @@ async function polyFetch(endpoint: 'events' | 'markets', params: Record<string,
   // Desktop: use Tauri Rust command (native TLS bypasses Cloudflare JA3 blocking)
   if (isDesktopRuntime()) {
     try {
-      const body = await tryInvokeTauri<string>('fetch_polymarket', { path: endpoint, params: qs });
+      // Offline, the shell answers with its last good copy, marked stale.
+      const result = await tryInvokeTauri<string | { stale: true; cached_at: number; body: string }>(
+        'fetch_polymarket',
+        { path: endpoint, params: qs },
+      );
+      const body = typeof result === 'string' ? result : result?.body;
       if (body) {
-        return new Response(body, {
-          status: 200,
-          headers: { 'Content-Type': 'application/json' },
-        });
+        const headers: Record<string, string> = { 'Content-Type': 'application/json' };
+        if (result && typeof result === 'object') headers['X-WM-Stale-Cached-At'] = String(result.cached_at);
+        return new Response(body, { status: 200, headers });
       }
     } catch { /* Tauri command failed, fall through to proxy */ }
   }
@@ -1,5 +1,5 @@
-import { getApiBaseUrl, isDesktopRuntime } from './runtime';
-import { invokeTauri } from './tauri-bridge';
+import { getApiBaseUrl, isDesktopRuntime, resolveLocalApiConnection } from './runtime';
+import { invokeTauri, listenTauriEvent } from './tauri-bridge';
 
 export type RuntimeSecretKey =
   | 'GROQ_API_KEY'
@@ -425,8 +425,6 @@ const runtimeConfig: RuntimeConfig = {
   secrets: {},
 };
 
-let localApiTokenPromise: Promise<string | null> | null = null;
-
 function notifyConfigChanged(): void {
   for (const listener of listeners) listener();
 }
@@ -461,6 +459,25 @@ if (typeof window !== 'undefined') {
   });
 }
 
+/** Payload of the shell's `settings-closed` event: keys written while the settings window was open. */
+interface SettingsClosedPayload {
+  secrets_changed: string[];
+  prefs_changed: string[];
+}
+
+// The `storage` event can be missed if the settings window closes right after
+// writing, so the main window also reloads when the shell reports the close.
+if (isDesktopRuntime()) {
+  void listenTauriEvent<SettingsClosedPayload>('settings-closed', (changes) => {
+    runtimeConfig.featureToggles = readStoredToggles();
+    if (changes.secrets_changed.length > 0) {
+      void loadDesktopSecrets();
+    } else {
+      notifyConfigChanged();
+    }
+  }).catch((error) => console.warn('[runtime-config] Failed to listen for settings-closed', error));
+}
+
 export function subscribeRuntimeConfig(listener: () => void): () => void {
   listeners.add(listener);
   return () => listeners.delete(listener);
@@ -540,22 +557,9 @@ export async function setSecretValue(key: RuntimeSecretKey, value: string): Prom
   notifyConfigChanged();
 }
 
-async function getLocalApiToken(): Promise<string | null> {
-  if (!localApiTokenPromise) {
-    localApiTokenPromise = invokeTauri<string>('get_local_api_token')
-      .then((token) => token.trim() || null)
-      .catch((error) => {
-        // Allow retries on subsequent calls if bridge/token is temporarily unavailable.
-        localApiTokenPromise = null;
-        throw error;
-      });
-  }
-  return localApiTokenPromise;
-}
-
 async function pushSecretToSidecar(key: string, value: string): Promise<void> {
   const headers = new Headers({ 'Content-Type': 'application/json' });
-  const token = await getLocalApiToken();
+  const token = (await resolveLocalApiConnection())?.token;
   if (token) {
     headers.set('Authorization', `Bearer ${token}`);
   }
@@ -577,7 +581,7 @@ async function pushSecretToSidecar(key: string, value: string): Promise<void> {
 
 async function callSidecarWithAuth(url: string, init: RequestInit): Promise<Response> {
   const headers = new Headers(init.headers ?? {});
-  const token = await getLocalApiToken();
+  const token = (await resolveLocalApiConnection())?.token;
   if (token) {
     headers.set('Authorization', `Bearer ${token}`);
   }
@@ -11,11 +11,19 @@ const DEFAULT_REMOTE_HOSTS: Record<string, string> = {
   happy: WS_API_URL,
 };
 
-const DEFAULT_LOCAL_API_PORT = 46123;
 const FORCE_DESKTOP_RUNTIME = import.meta.env.VITE_DESKTOP_RUNTIME === '1';
 
-let _resolvedPort: number | null = null;
-let _portPromise: Promise<number> | null = null;
+/** `get_local_api_connection` result and `local-api-connection-changed` payload. */
+export interface LocalApiConnection {
+  base_url: string | null;
+  token: string | null;
+  ready: boolean;
+  started_at: string | null;
+}
+
+let _connection: LocalApiConnection | null = null;
+let _connectionPromise: Promise<LocalApiConnection | null> | null = null;
+let _followingConnection = false;
 
 // Cache dynamic imports to avoid re-resolving on every fetch
 let _tauriBridgeMod: typeof import('@/services/tauri-bridge') | null = null;
@@ -31,29 +39,44 @@ async function getRuntimeConfig() {
   return _runtimeConfigMod;
 }
 
-export async function resolveLocalApiPort(): Promise<number> {
-  if (_resolvedPort !== null) return _resolvedPort;
-  if (_portPromise) return _portPromise;
-  _portPromise = (async () => {
+// A sidecar restarted on another port is announced, so open windows
+// re-point without a reload.
+function followLocalApiConnection(): void {
+  if (_followingConnection) return;
+  _followingConnection = true;
+  void getTauriBridge()
+    .then(({ listenTauriEvent }) => listenTauriEvent<LocalApiConnection>('local-api-connection-changed', (connection) => {
+      _connection = connection;
+    }))
+    .catch((error) => {
+      _followingConnection = false;
+      console.warn('[runtime] Failed to listen for local-api-connection-changed', error);
+    });
+}
+
+/** The sidecar's base URL and token; `refresh` asks the shell again. */
+export async function resolveLocalApiConnection(refresh = false): Promise<LocalApiConnection | null> {
+  followLocalApiConnection();
+  if (_connection?.base_url && !refresh) return _connection;
+  if (_connectionPromise) return _connectionPromise;
+  _connectionPromise = (async () => {
     try {
       const { tryInvokeTauri } = await getTauriBridge();
-      const port = await tryInvokeTauri<number>('get_local_api_port');
-      if (port && port > 0) {
-        _resolvedPort = port;
-        return port;
-      }
+      const connection = await tryInvokeTauri<LocalApiConnection>('get_local_api_connection');
+      if (connection) _connection = connection;
     } catch {
       // IPC failed — allow retry on next call
     } finally {
-      _portPromise = null;
+      _connectionPromise = null;
     }
-    return DEFAULT_LOCAL_API_PORT;
+    return _connection;
   })();
-  return _portPromise;
+  return _connectionPromise;
 }
 
-export function getLocalApiPort(): number {
-  return _resolvedPort ?? DEFAULT_LOCAL_API_PORT;
+/** The last connection resolved or announced, if any. */
+export function getLocalApiConnection(): LocalApiConnection | null {
+  return _connection;
 }
 
 function normalizeBaseUrl(baseUrl: string): string {
@@ -121,7 +144,22 @@ export function getApiBaseUrl(): string {
     return normalizeBaseUrl(configuredBaseUrl);
   }
 
-  return `http://127.0.0.1:${getLocalApiPort()}`;
+  // Until the connection resolves, relative `/api/` paths still reach the
+  // sidecar through the fetch patch.
+  return _connection?.base_url ? normalizeBaseUrl(_connection.base_url) : '';
+}
+
+/** The sidecar's origin with a loopback IP spelled `localhost`, which YouTube embeds accept. */
+export function getLocalApiEmbedOrigin(): string {
+  const baseUrl = getApiBaseUrl();
+  if (!baseUrl) return '';
+  try {
+    const url = new URL(baseUrl);
+    if (url.hostname === '127.0.0.1') url.hostname = 'localhost';
+    return url.origin;
+  } catch {
+    return baseUrl;
+  }
 }
 
 export function getRemoteApiBaseUrl(): string {
@@ -252,7 +290,7 @@ async function fetchLocalWithStartupRetry(
 
 // ── Security threat model for the fetch patch ──────────────────────────
 // The LOCAL_API_TOKEN exists to prevent OTHER local processes from
-// accessing the sidecar on port 46123. The renderer IS the intended
+// accessing the sidecar on its localhost port. The renderer IS the intended
 // client — injecting the token automatically is correct by design.
 //
 // If the renderer is compromised (XSS, supply chain), the attacker
@@ -295,21 +333,13 @@ export function installRuntimeFetchPatch(): void {
       return nativeFetch(input, init);
     }
 
-    // Resolve dynamic sidecar port on first API call
-    if (_resolvedPort === null) {
-      try { await resolveLocalApiPort(); } catch { /* use default */ }
-    }
-
+    // Resolve the sidecar's base URL and token on the first API call, and
+    // again once the token's TTL is up.
     const tokenExpired = localApiToken && (Date.now() - tokenFetchedAt > TOKEN_TTL_MS);
     if (!localApiToken || tokenExpired) {
-      try {
-        const { tryInvokeTauri } = await getTauriBridge();
-        localApiToken = await tryInvokeTauri<string>('get_local_api_token');
-        tokenFetchedAt = Date.now();
-      } catch {
-        localApiToken = null;
-        tokenFetchedAt = 0;
-      }
+      const connection = await resolveLocalApiConnection(Boolean(tokenExpired));
+      localApiToken = connection?.token ?? null;
+      tokenFetchedAt = localApiToken ? Date.now() : 0;
     }
 
     const headers = new Headers(init?.headers);
@@ -357,22 +387,17 @@ export function installRuntimeFetchPatch(): void {
       let response = await fetchLocalWithStartupRetry(nativeFetch, localUrl, localInit);
       if (debug) console.log(`[fetch] ${target} → ${response.status} (${Math.round(performance.now() - t0)}ms)`);
 
-      // Token may be stale after a sidecar restart — refresh and retry once.
+      // Token or port may be stale after a sidecar restart — refresh and retry once.
       // Skip retry if we recently failed (avoid doubling every request during auth outages).
       if (response.status === 401 && localApiToken && Date.now() > authRetryCooldownUntil) {
         if (debug) console.log(`[fetch] 401 from sidecar, refreshing token and retrying`);
-        try {
-          const { tryInvokeTauri } = await getTauriBridge();
-          localApiToken = await tryInvokeTauri<string>('get_local_api_token');
-          tokenFetchedAt = Date.now();
-        } catch {
-          localApiToken = null;
-          tokenFetchedAt = 0;
-        }
+        const connection = await resolveLocalApiConnection(true);
+        localApiToken = connection?.token ?? null;
+        tokenFetchedAt = localApiToken ? Date.now() : 0;
         if (localApiToken) {
           const retryHeaders = new Headers(init?.headers);
           retryHeaders.set('Authorization', `Bearer ${localApiToken}`);
-          response = await fetchLocalWithStartupRetry(nativeFetch, localUrl, { ...init, headers: retryHeaders });
+          response = await fetchLocalWithStartupRetry(nativeFetch, `${getApiBaseUrl()}${target}`, { ...init, headers: retryHeaders });
           if (debug) console.log(`[fetch] retry ${target} → ${response.status}`);
           if (response.status === 401) {
             authRetryCooldownUntil = Date.now() + 60_000;
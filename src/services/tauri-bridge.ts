@@ -21,6 +21,43 @@ export function hasTauriInvokeBridge(): boolean {
   return resolveInvokeBridge() !== null;
 }
 
+/** Shape of `DesktopError` as serialized by the Rust commands. */
+export interface DesktopErrorPayload {
+  code: string;
+  message: string;
+  path?: string;
+  status?: number;
+  key?: string;
+}
+
+/** A typed command failure; branch on `code`, show `message`. */
+export class DesktopCommandError extends Error {
+  readonly code: string;
+  readonly path?: string;
+  readonly status?: number;
+  readonly key?: string;
+
+  constructor(payload: DesktopErrorPayload) {
+    super(payload.message);
+    this.name = 'DesktopCommandError';
+    this.code = payload.code;
+    this.path = payload.path;
+    this.status = payload.status;
+    this.key = payload.key;
+  }
+
+  override toString(): string {
+    return this.message;
+  }
+}
+
+function isDesktopErrorPayload(error: unknown): error is DesktopErrorPayload {
+  return typeof error === 'object'
+    && error !== null
+    && typeof (error as DesktopErrorPayload).code === 'string'
+    && typeof (error as DesktopErrorPayload).message === 'string';
+}
+
 export async function invokeTauri<T>(
   command: string,
   payload?: Record<string, unknown>,
@@ -30,7 +67,11 @@ export async function invokeTauri<T>(
     throw new Error('Tauri invoke bridge unavailable');
   }
 
-  return invoke<T>(command, payload);
+  try {
+    return await invoke<T>(command, payload);
+  } catch (error) {
+    throw isDesktopErrorPayload(error) ? new DesktopCommandError(error) : error;
+  }
 }
 
 export async function tryInvokeTauri<T>(
@@ -44,3 +85,39 @@ export async function tryInvokeTauri<T>(
     return null;
   }
 }
+
+interface TauriEventInternals {
+  transformCallback?: (callback: (event: { payload: unknown }) => void) => number;
+  invoke?: TauriInvoke;
+}
+
+/** Subscribe to an event emitted by the Rust shell. Resolves false outside Tauri. */
+export async function listenTauriEvent<T>(event: string, handler: (payload: T) => void): Promise<boolean> {
+  if (typeof window === 'undefined') return false;
+  const internals = (window as unknown as { __TAURI_INTERNALS__?: TauriEventInternals }).__TAURI_INTERNALS__;
+  if (!internals?.transformCallback || !internals.invoke) return false;
+  const callbackId = internals.transformCallback((e) => handler(e.payload as T));
+  await internals.invoke('plugin:event|listen', { event, target: { kind: 'Any' }, handler: callbackId });
+  return true;
+}
+
+export type OpenUrlOutcome =
+  | { status: 'opened' }
+  | {
+    status: 'needs_confirmation';
+    reason: 'userinfo' | 'mixed_script' | 'confusable' | 'invalid_idn';
+    host: string;
+    display_host: string;
+  };
+
+/** Open a link in the system browser, asking first when its host looks like an impersonation. */
+export async function openExternalUrl(url: string): Promise<void> {
+  const outcome = await invokeTauri<OpenUrlOutcome>('open_url', { url });
+  if (outcome.status !== 'needs_confirmation') return;
+  const shown = outcome.display_host === outcome.host
+    ? outcome.host
+    : `${outcome.display_host} (${outcome.host})`;
+  if (window.confirm(`This link goes to ${shown}, which may be imitating another site. Open it anyway?`)) {
+    await invokeTauri<OpenUrlOutcome>('open_url', { url, confirmed: true });
+  }
+}
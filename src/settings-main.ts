@@ -25,8 +25,9 @@ import {
   type RuntimeFeatureId,
   type RuntimeSecretKey,
 } from '@/services/runtime-config';
-import { getApiBaseUrl, getRemoteApiBaseUrl, isDesktopRuntime, resolveLocalApiPort } from '@/services/runtime';
-import { tryInvokeTauri, invokeTauri } from '@/services/tauri-bridge';
+import { getApiBaseUrl, getRemoteApiBaseUrl, isDesktopRuntime, resolveLocalApiConnection } from '@/services/runtime';
+import { tryInvokeTauri, invokeTauri, listenTauriEvent, openExternalUrl } from '@/services/tauri-bridge';
+import { installReloadListeners, withReloadGuard } from '@/services/desktop-reload';
 import { escapeHtml } from '@/utils/sanitize';
 import { initI18n, t } from '@/services/i18n';
 import { applyStoredTheme } from '@/utils/theme-manager';
@@ -53,24 +54,39 @@ async function invokeDesktopAction(command: string, successLabel: string): Promi
   setActionStatus(t('modals.settingsWindow.invokeFail', { command }), 'error');
 }
 
+/** Save and Cancel are explicit, so they close even with unsaved edits. */
 function closeSettingsWindow(): void {
-  void tryInvokeTauri<void>('close_settings_window').then(() => { }, () => window.close());
+  void tryInvokeTauri<void>('close_settings_window', { force: true }).then(() => { }, () => window.close());
+}
+
+let _dirty = false;
+
+/** Tell the shell whether a close from the OS should ask first. */
+function setDirty(dirty: boolean): void {
+  if (_dirty === dirty) return;
+  _dirty = dirty;
+  void tryInvokeTauri<void>('set_settings_dirty', { dirty });
+}
+
+/** The shell held a close because of unsaved edits: save, discard or stay. */
+function installCloseGuard(): void {
+  void listenTauriEvent<null>('settings-close-requested', () => {
+    if (window.confirm(t('modals.settingsWindow.closeSavePrompt'))) {
+      document.getElementById('okBtn')?.click();
+    } else if (window.confirm(t('modals.settingsWindow.closeDiscardPrompt'))) {
+      closeSettingsWindow();
+    }
+  }).catch((error) => console.warn('[settings] Failed to listen for settings-close-requested', error));
 }
 
 function getSidecarBase(): string {
   return getApiBaseUrl() || '';
 }
 
-let _diagToken: string | null = null;
-
 async function diagFetch(path: string, init?: RequestInit): Promise<Response> {
-  if (!_diagToken) {
-    try {
-      _diagToken = await tryInvokeTauri<string>('get_local_api_token');
-    } catch { /* token unavailable */ }
-  }
+  const token = (await resolveLocalApiConnection())?.token;
   const headers = new Headers(init?.headers);
-  if (_diagToken) headers.set('Authorization', `Bearer ${_diagToken}`);
+  if (token) headers.set('Authorization', `Bearer ${token}`);
   return fetch(`${getSidecarBase()}${path}`, { ...init, headers });
 }
 
@@ -528,7 +544,7 @@ function initFeatureSectionListeners(area: HTMLElement): void {
       const url = link.dataset.signupUrl;
       if (!url) return;
       if (isDesktopRuntime()) {
-        void invokeTauri<void>('open_url', { url }).catch(() => window.open(url, '_blank'));
+        void openExternalUrl(url).catch(() => window.open(url, '_blank'));
       } else {
         window.open(url, '_blank');
       }
@@ -843,11 +859,28 @@ function handleSearch(query: string): void {
 
 // ── Init ──
 
+function isKnownSection(id: string): boolean {
+  return id === 'overview' || id === 'debug' || SETTINGS_CATEGORIES.some(c => c.id === id);
+}
+
+/** `settings.html#ai` opens straight into a section. */
+function sectionFromHash(): string {
+  const id = location.hash.slice(1);
+  return isKnownSection(id) ? id : 'overview';
+}
+
+/** The shell emits `settings-navigate` when asked for a section while this window is already open. */
+async function listenForNavigation(): Promise<void> {
+  await listenTauriEvent<unknown>('settings-navigate', (section) => {
+    if (typeof section === 'string' && isKnownSection(section)) renderSection(section);
+  });
+}
+
 async function initSettingsWindow(): Promise<void> {
   await initI18n();
   applyStoredTheme();
 
-  try { await resolveLocalApiPort(); } catch { /* use default */ }
+  await resolveLocalApiConnection();
 
   requestAnimationFrame(() => {
     document.documentElement.classList.remove('no-transition');
@@ -856,7 +889,8 @@ async function initSettingsWindow(): Promise<void> {
   await loadDesktopSecrets();
   settingsManager = new SettingsManager();
 
-  renderSection('overview');
+  renderSection(sectionFromHash());
+  void listenForNavigation().catch(console.error);
 
   document.getElementById('sidebarNav')?.addEventListener('click', (e) => {
     const btn = (e.target as HTMLElement).closest<HTMLButtonElement>('[data-section]');
@@ -872,8 +906,16 @@ async function initSettingsWindow(): Promise<void> {
     searchTimeout = setTimeout(() => handleSearch(searchInput.value), 200);
   });
 
+  const contentRoot = document.getElementById('contentArea');
+  contentRoot?.addEventListener('input', () => setDirty(true));
+  contentRoot?.addEventListener('change', () => setDirty(true));
+  installCloseGuard();
+
+  installReloadListeners(() => setActionStatus(t('modals.settingsWindow.reloadBlocked'), 'error'));
+
   document.getElementById('okBtn')?.addEventListener('click', () => {
-    void (async () => {
+    // Reloading mid-save would drop keys that are verified but not committed.
+    void withReloadGuard(async () => {
       try {
         const wmKeyInput = document.querySelector<HTMLInputElement>('[data-wm-key-input]');
         const wmKeyValue = wmKeyInput?.value.trim();
@@ -907,13 +949,14 @@ async function initSettingsWindow(): Promise<void> {
           await settingsManager.commitVerifiedSecrets();
         }
 
+        setDirty(false);
         setActionStatus(t('modals.settingsWindow.saved'), 'ok');
         closeSettingsWindow();
       } catch (err) {
         console.error('[settings] save error:', err);
         setActionStatus(t('modals.settingsWindow.failed', { error: String(err) }), 'error');
       }
-    })();
+    });
   });
 
   document.getElementById('cancelBtn')?.addEventListener('click', () => {
@@ -0,0 +1,58 @@
+/**
+ * Entry point for the startup splash window (Tauri desktop only).
+ * Mirrors sidecar boot progress emitted by the Rust shell and turns into an
+ * error panel with recovery actions when the sidecar fails to start.
+ */
+import { invokeTauri, tryInvokeTauri } from '@/services/tauri-bridge';
+
+interface StartupStatus {
+  stage: string;
+  error: string | null;
+}
+
+interface TauriEventInternals {
+  transformCallback?: (callback: (event: { payload: StartupStatus }) => void) => number;
+  invoke?: <T>(command: string, payload?: Record<string, unknown>) => Promise<T>;
+}
+
+const STAGE_LABELS: Record<string, string> = {
+  resolving_node: 'Locating Node.js runtime…',
+  spawning_sidecar: 'Starting local API…',
+  waiting_for_health_check: 'Waiting for local API…',
+  ready: 'Ready',
+};
+
+const root = document.getElementById('splash');
+const stageEl = document.getElementById('splashStage');
+const errorEl = document.getElementById('splashError');
+
+function render(status: StartupStatus): void {
+  if (stageEl) stageEl.textContent = STAGE_LABELS[status.stage] ?? 'Starting…';
+  if (errorEl) errorEl.textContent = status.error ?? '';
+  root?.classList.toggle('failed', Boolean(status.error));
+}
+
+async function listen(event: string): Promise<void> {
+  const internals = (window as unknown as { __TAURI_INTERNALS__?: TauriEventInternals }).__TAURI_INTERNALS__;
+  if (!internals?.transformCallback || !internals.invoke) return;
+  const handler = internals.transformCallback((e) => render(e.payload));
+  await internals.invoke('plugin:event|listen', { event, target: { kind: 'Any' }, handler });
+}
+
+document.getElementById('openLogBtn')?.addEventListener('click', () => {
+  void tryInvokeTauri<string>('open_sidecar_log_file');
+});
+document.getElementById('retryBtn')?.addEventListener('click', () => {
+  render({ stage: 'resolving_node', error: null });
+  void tryInvokeTauri<void>('retry_local_api_start');
+});
+document.getElementById('continueBtn')?.addEventListener('click', () => {
+  void tryInvokeTauri<void>('dismiss_splash');
+});
+
+async function main(): Promise<void> {
+  await Promise.all([listen('startup-stage'), listen('startup-failed')]);
+  render(await invokeTauri<StartupStatus>('get_startup_status'));
+}
+
+void main().catch(console.error);
@@ -750,6 +750,8 @@ export default defineConfig({
         main: resolve(__dirname, 'index.html'),
         settings: resolve(__dirname, 'settings.html'),
         liveChannels: resolve(__dirname, 'live-channels.html'),
+        splash: resolve(__dirname, 'splash.html'),
+        safeMode: resolve(__dirname, 'safe-mode.html'),
       },
       output: {
         manualChunks(id) {